@@ -0,0 +1,455 @@
+use crate::embeddings::cosine_similarity;
+use crate::types::{Memory, Tombstone};
+use chrono::{DateTime, Datelike, Duration, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Bucket label for tags too rare to report individually
+const OTHER_BUCKET: &str = "(other)";
+
+/// Cosine similarity at which two memories count as likely duplicates
+const DUPLICATE_THRESHOLD: f64 = 0.9;
+
+/// Upper bounds of the confidence buckets in `TagHealth`
+pub const CONFIDENCE_BUCKETS: [(&str, f64); 3] =
+    [("< 0.3", 0.3), ("0.3 - 0.7", 0.7), (">= 0.7", f64::INFINITY)];
+
+/// Upper bounds, in days, of the age buckets in `TagHealth`
+pub const AGE_BUCKETS: [(&str, i64); 5] = [
+    ("< 1 week", 7),
+    ("< 1 month", 30),
+    ("< 3 months", 90),
+    ("< 1 year", 365),
+    ("older", i64::MAX),
+];
+
+/// Days within which an access counts as recent in `TagHealth`
+pub const RECENT_ACCESS_DAYS: i64 = 30;
+
+/// Memories listed in each section of `roots stats --access`
+pub const ACCESS_TOP: usize = 10;
+
+/// Weeks, and months, covered by the activity histograms of `roots stats`
+pub const ACTIVITY_PERIODS: usize = 12;
+
+/// Tag-level statistics without any memory content
+#[derive(Debug, Serialize)]
+pub struct AggregateExport {
+    pub generated_at: String,
+    pub total_memories: i64,
+    /// Tags used by fewer memories than this are folded into "(other)"
+    pub min_count: usize,
+    /// Privacy budget used for Laplace noise, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub epsilon: Option<f64>,
+    pub kinds: BTreeMap<String, i64>,
+    pub confidence_histogram: BTreeMap<String, i64>,
+    pub tags: Vec<TagSummary>,
+}
+
+/// Anonymized summary of one tag
+#[derive(Debug, Serialize)]
+pub struct TagSummary {
+    pub tag: String,
+    pub count: i64,
+    pub avg_confidence: f64,
+    /// First and last month (YYYY-MM) a memory with this tag was created
+    pub first_month: String,
+    pub last_month: String,
+}
+
+/// Laplace noise source (xorshift PRNG; statistical, not cryptographic)
+struct Noise {
+    state: u64,
+    scale: f64,
+}
+
+impl Noise {
+    fn new(epsilon: f64) -> Self {
+        let seed = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0x9E37_79B9_7F4A_7C15);
+        Self {
+            state: seed | 1,
+            scale: 1.0 / epsilon,
+        }
+    }
+
+    fn uniform(&mut self) -> f64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        (self.state >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Add Laplace(0, 1/epsilon) noise to a count, clamped at zero
+    fn count(&mut self, n: usize) -> i64 {
+        let u = self.uniform() - 0.5;
+        let noise = -self.scale * u.signum() * (1.0 - 2.0 * u.abs()).max(f64::MIN_POSITIVE).ln();
+        (n as f64 + noise).round().max(0.0) as i64
+    }
+}
+
+/// Build an aggregate export.
+///
+/// Tags with fewer than `min_count` memories are merged into "(other)" so
+/// rare, identifying topics are not revealed. With `epsilon`, every count
+/// gets Laplace noise (smaller epsilon = more noise).
+pub fn aggregate(memories: &[Memory], min_count: usize, epsilon: Option<f64>) -> AggregateExport {
+    let mut noise = epsilon.map(Noise::new);
+    let mut count = |n: usize| match noise.as_mut() {
+        Some(noise) => noise.count(n),
+        None => n as i64,
+    };
+
+    let mut kinds: BTreeMap<String, usize> = BTreeMap::new();
+    let mut histogram: BTreeMap<String, usize> = BTreeMap::new();
+    let mut by_tag: BTreeMap<String, Vec<&Memory>> = BTreeMap::new();
+
+    for m in memories {
+        let kind = m.kind.clone().unwrap_or_else(|| "untyped".to_string());
+        *kinds.entry(kind).or_default() += 1;
+
+        let bucket = ((m.confidence * 10.0).floor() as i64).clamp(0, 9);
+        let label = format!("{:.1}-{:.1}", bucket as f64 / 10.0, (bucket + 1) as f64 / 10.0);
+        *histogram.entry(label).or_default() += 1;
+
+        for t in &m.tags {
+            by_tag.entry(t.clone()).or_default().push(m);
+        }
+    }
+
+    let mut other: Vec<&Memory> = Vec::new();
+    let mut tags = Vec::new();
+    for (tag, members) in by_tag {
+        if members.len() < min_count {
+            other.extend(members);
+        } else {
+            tags.push(summarize(tag, &members));
+        }
+    }
+    if !other.is_empty() {
+        other.sort_by_key(|m| m.id);
+        other.dedup_by_key(|m| m.id);
+        tags.push(summarize(OTHER_BUCKET.to_string(), &other));
+    }
+
+    for t in &mut tags {
+        t.count = count(t.count as usize);
+    }
+    tags.sort_by(|a, b| b.count.cmp(&a.count).then(a.tag.cmp(&b.tag)));
+
+    AggregateExport {
+        generated_at: crate::dates::now_rfc3339(),
+        total_memories: count(memories.len()),
+        min_count,
+        epsilon,
+        kinds: kinds.into_iter().map(|(k, n)| (k, count(n))).collect(),
+        confidence_histogram: histogram.into_iter().map(|(k, n)| (k, count(n))).collect(),
+        tags,
+    }
+}
+
+fn summarize(tag: String, members: &[&Memory]) -> TagSummary {
+    let month = |m: &&Memory| m.created_at.get(..7).unwrap_or_default().to_string();
+    let avg = members.iter().map(|m| m.confidence).sum::<f64>() / members.len() as f64;
+
+    TagSummary {
+        tag,
+        count: members.len() as i64,
+        avg_confidence: (avg * 100.0).round() / 100.0,
+        first_month: members.iter().map(month).min().unwrap_or_default(),
+        last_month: members.iter().map(month).max().unwrap_or_default(),
+    }
+}
+
+/// How well-kept the memories under one tag are, for `roots stats --tag`
+#[derive(Debug, Default)]
+pub struct TagHealth {
+    pub count: usize,
+    pub avg_confidence: f64,
+    /// Counts per `CONFIDENCE_BUCKETS` entry
+    pub confidence: [usize; 3],
+    /// Counts per `AGE_BUCKETS` entry, by creation time
+    pub age: [usize; 5],
+    pub never_accessed: usize,
+    /// Accessed within `RECENT_ACCESS_DAYS`
+    pub recently_accessed: usize,
+    pub avg_access_count: f64,
+    /// Pairs of embedded memories similar enough to be duplicates
+    pub duplicate_pairs: usize,
+    /// Members still waiting for an embedding, left out of the estimate
+    pub unembedded: usize,
+}
+
+/// Summarize the members of a tag; `embeddings` are those of the members
+/// that have one
+pub fn tag_health(members: &[Memory], embeddings: &[Vec<f32>], now: DateTime<Utc>) -> TagHealth {
+    let mut health = TagHealth {
+        count: members.len(),
+        unembedded: members.len().saturating_sub(embeddings.len()),
+        ..Default::default()
+    };
+    if members.is_empty() {
+        return health;
+    }
+
+    let days_ago = |ts: &str| crate::dates::parse_timestamp(ts).map(|t| (now - t).num_days());
+    for m in members {
+        let bucket = CONFIDENCE_BUCKETS.iter().position(|(_, max)| m.confidence < *max);
+        health.confidence[bucket.unwrap_or(CONFIDENCE_BUCKETS.len() - 1)] += 1;
+
+        let age = days_ago(&m.created_at).unwrap_or(0);
+        let bucket = AGE_BUCKETS.iter().position(|(_, max)| age < *max);
+        health.age[bucket.unwrap_or(AGE_BUCKETS.len() - 1)] += 1;
+
+        match m.last_accessed_at.as_deref().and_then(days_ago) {
+            None => health.never_accessed += 1,
+            Some(days) if days < RECENT_ACCESS_DAYS => health.recently_accessed += 1,
+            Some(_) => {}
+        }
+    }
+
+    let n = members.len() as f64;
+    health.avg_confidence = members.iter().map(|m| m.confidence).sum::<f64>() / n;
+    health.avg_access_count = members.iter().map(|m| m.access_count as f64).sum::<f64>() / n;
+
+    for (i, a) in embeddings.iter().enumerate() {
+        health.duplicate_pairs += embeddings[i + 1..]
+            .iter()
+            .filter(|b| cosine_similarity(a, b) >= DUPLICATE_THRESHOLD)
+            .count();
+    }
+
+    health
+}
+
+/// How often memories are recalled, for `roots stats --access`
+#[derive(Debug, Default)]
+pub struct AccessReport {
+    pub count: usize,
+    pub never_accessed: usize,
+    /// Up to `ACCESS_TOP`, most accessed first
+    pub most_recalled: Vec<Memory>,
+    /// Up to `ACCESS_TOP` never-accessed memories, oldest first
+    pub oldest_unused: Vec<Memory>,
+    /// Every tag, most accessed first
+    pub tags: Vec<TagAccess>,
+}
+
+/// Access totals for the memories under one tag
+#[derive(Debug, Clone, PartialEq)]
+pub struct TagAccess {
+    pub tag: String,
+    pub count: usize,
+    /// Sum of the members' access counts
+    pub accesses: i64,
+    pub never_accessed: usize,
+    pub last_accessed_at: Option<String>,
+}
+
+impl TagAccess {
+    pub fn avg_accesses(&self) -> f64 {
+        self.accesses as f64 / self.count.max(1) as f64
+    }
+}
+
+/// Summarize access counts and times over `memories`
+pub fn access_report(memories: &[Memory]) -> AccessReport {
+    let mut most_recalled: Vec<&Memory> = memories.iter().filter(|m| m.access_count > 0).collect();
+    most_recalled.sort_by(|a, b| {
+        b.access_count
+            .cmp(&a.access_count)
+            .then_with(|| b.last_accessed_at.cmp(&a.last_accessed_at))
+    });
+    let mut unused: Vec<&Memory> = memories.iter().filter(|m| m.access_count == 0).collect();
+    unused.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+
+    let mut tags: BTreeMap<&str, TagAccess> = BTreeMap::new();
+    for m in memories {
+        for tag in &m.tags {
+            let entry = tags.entry(tag).or_insert_with(|| TagAccess {
+                tag: tag.clone(),
+                count: 0,
+                accesses: 0,
+                never_accessed: 0,
+                last_accessed_at: None,
+            });
+            entry.count += 1;
+            entry.accesses += m.access_count;
+            if m.access_count == 0 {
+                entry.never_accessed += 1;
+            }
+            if m.last_accessed_at > entry.last_accessed_at {
+                entry.last_accessed_at = m.last_accessed_at.clone();
+            }
+        }
+    }
+    // BTreeMap order breaks ties by name
+    let mut tags: Vec<TagAccess> = tags.into_values().collect();
+    tags.sort_by_key(|t| std::cmp::Reverse(t.accesses));
+
+    AccessReport {
+        count: memories.len(),
+        never_accessed: unused.len(),
+        most_recalled: most_recalled.into_iter().take(ACCESS_TOP).cloned().collect(),
+        oldest_unused: unused.into_iter().take(ACCESS_TOP).cloned().collect(),
+        tags,
+    }
+}
+
+/// Memories added and forgotten in one week or month
+#[derive(Debug, Clone, PartialEq)]
+pub struct Activity {
+    /// ISO week (`2026-W07`) or month (`2026-02`)
+    pub period: String,
+    pub added: usize,
+    pub forgotten: usize,
+}
+
+/// Activity per ISO week over the last `ACTIVITY_PERIODS` weeks, oldest first
+pub fn weekly_activity(created: &[&str], tombstones: &[Tombstone], now: DateTime<Utc>) -> Vec<Activity> {
+    let week = |t: DateTime<Utc>| format!("{}-W{:02}", t.iso_week().year(), t.iso_week().week());
+    let periods = (0..ACTIVITY_PERIODS as i64).rev().map(|i| week(now - Duration::weeks(i))).collect();
+    activity(periods, week, created, tombstones)
+}
+
+/// Activity per calendar month over the last `ACTIVITY_PERIODS` months,
+/// oldest first
+pub fn monthly_activity(created: &[&str], tombstones: &[Tombstone], now: DateTime<Utc>) -> Vec<Activity> {
+    let month = |t: DateTime<Utc>| t.format("%Y-%m").to_string();
+    let current = now.year() * 12 + now.month0() as i32;
+    let periods = (current + 1 - ACTIVITY_PERIODS as i32..=current)
+        .map(|m| format!("{}-{:02}", m.div_euclid(12), m.rem_euclid(12) + 1))
+        .collect();
+    activity(periods, month, created, tombstones)
+}
+
+/// Count additions (`created`, plus forgotten memories by their creation
+/// time) and removals into `periods`. Leading periods with nothing in them
+/// are dropped, so a young store doesn't show a run of empty rows.
+fn activity(
+    periods: Vec<String>,
+    period_of: impl Fn(DateTime<Utc>) -> String,
+    created: &[&str],
+    tombstones: &[Tombstone],
+) -> Vec<Activity> {
+    let mut activity: Vec<Activity> = periods
+        .into_iter()
+        .map(|period| Activity { period, added: 0, forgotten: 0 })
+        .collect();
+    let mut count = |ts: &str, forgotten: bool| {
+        let Some(period) = crate::dates::parse_timestamp(ts).map(&period_of) else {
+            return;
+        };
+        if let Some(a) = activity.iter_mut().find(|a| a.period == period) {
+            if forgotten {
+                a.forgotten += 1;
+            } else {
+                a.added += 1;
+            }
+        }
+    };
+    for ts in created.iter().copied().chain(tombstones.iter().map(|t| t.created_at.as_str())) {
+        count(ts, false);
+    }
+    for t in tombstones {
+        count(&t.deleted_at, true);
+    }
+
+    let first = activity.iter().position(|a| a.added + a.forgotten > 0).unwrap_or(activity.len());
+    activity.split_off(first)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    fn memory(id: i64, tags: &[&str]) -> Memory {
+        test_support::memory(id, &format!("secret {}", id), tags)
+    }
+
+    #[test]
+    fn test_rare_tags_are_folded() {
+        let memories = vec![
+            memory(1, &["rust", "rare-a"]),
+            memory(2, &["rust", "rare-b"]),
+            memory(3, &["rust"]),
+        ];
+        let export = aggregate(&memories, 2, None);
+
+        let tags: Vec<_> = export.tags.iter().map(|t| (t.tag.as_str(), t.count)).collect();
+        assert_eq!(tags, vec![("rust", 3), ("(other)", 2)]);
+        assert_eq!(export.total_memories, 3);
+        assert_eq!(export.tags[0].first_month, "2026-01");
+
+        let json = serde_json::to_string(&export).unwrap();
+        assert!(!json.contains("secret"));
+    }
+
+    #[test]
+    fn test_tag_health_buckets() {
+        let now = crate::dates::parse_timestamp("2026-03-01T00:00:00Z").unwrap();
+        let mut recent = memory(1, &["rust"]);
+        recent.created_at = "2026-02-27T00:00:00Z".to_string();
+        recent.last_accessed_at = Some("2026-02-28T00:00:00Z".to_string());
+        recent.confidence = 0.2;
+        let old = memory(2, &["rust"]);
+        let copy = memory(3, &["rust"]);
+
+        let embeddings = vec![vec![1.0, 0.0], vec![0.0, 1.0], vec![0.0, 1.0]];
+        let health = tag_health(&[recent, old, copy], &embeddings, now);
+
+        assert_eq!(health.count, 3);
+        assert_eq!(health.confidence, [1, 0, 2]);
+        assert_eq!(health.age, [1, 0, 2, 0, 0]);
+        assert_eq!((health.never_accessed, health.recently_accessed), (2, 1));
+        assert_eq!(health.duplicate_pairs, 1);
+        assert_eq!(health.unembedded, 0);
+    }
+
+    #[test]
+    fn test_access_report() {
+        let mut hot = memory(1, &["rust"]);
+        hot.access_count = 5;
+        hot.last_accessed_at = Some("2026-02-01T00:00:00Z".to_string());
+        let mut warm = memory(2, &["rust", "db"]);
+        warm.access_count = 1;
+        let mut old = memory(3, &["db"]);
+        old.created_at = "2025-06-01T00:00:00Z".to_string();
+        let unused = memory(4, &["db"]);
+
+        let report = access_report(&[unused, warm, hot, old]);
+        assert_eq!(report.never_accessed, 2);
+        let ids = |ms: &[Memory]| ms.iter().map(|m| m.id).collect::<Vec<_>>();
+        assert_eq!(ids(&report.most_recalled), vec![1, 2]);
+        assert_eq!(ids(&report.oldest_unused), vec![3, 4]);
+
+        let tags: Vec<_> = report.tags.iter().map(|t| (t.tag.as_str(), t.accesses, t.never_accessed)).collect();
+        assert_eq!(tags, vec![("rust", 6, 0), ("db", 1, 2)]);
+        assert_eq!(report.tags[0].last_accessed_at.as_deref(), Some("2026-02-01T00:00:00Z"));
+    }
+
+    #[test]
+    fn test_activity_counts_forgotten_memories_as_added() {
+        let now = crate::dates::parse_timestamp("2026-03-04T00:00:00Z").unwrap();
+        let created = ["2026-01-15T00:00:00Z", "2026-03-02T00:00:00Z", "2024-01-01T00:00:00Z"];
+        let tombstones = vec![Tombstone {
+            created_at: "2026-02-20T00:00:00Z".to_string(),
+            deleted_at: "2026-03-03T00:00:00Z".to_string(),
+        }];
+
+        let monthly = monthly_activity(&created, &tombstones, now);
+        let rows: Vec<_> = monthly.iter().map(|a| (a.period.as_str(), a.added, a.forgotten)).collect();
+        // Leading empty months are dropped; 2024 is out of range
+        assert_eq!(rows, vec![("2026-01", 1, 0), ("2026-02", 1, 0), ("2026-03", 1, 1)]);
+
+        let weekly = weekly_activity(&created, &tombstones, now);
+        assert_eq!(weekly.len(), 8);
+        assert_eq!(weekly[0].period, "2026-W03");
+        let last = weekly.last().unwrap();
+        assert_eq!((last.period.as_str(), last.added, last.forgotten), ("2026-W10", 1, 1));
+    }
+}
@@ -0,0 +1,157 @@
+//! Tag suggestions for memories stored without tags.
+//!
+//! With `auto_tag` set to `suggest` or `apply`, `remember` without `--tags`
+//! proposes up to three tags, strongest first: existing tags the content
+//! names, tags its nearest neighbors share, then key phrases of the content
+//! itself. Key phrases come from RAKE: runs of words between stopwords and
+//! punctuation, each word scored by how many words it appears alongside
+//! relative to how often it appears, and each phrase by its words' scores
+//! summed over its occurrences.
+
+use crate::expand::STOPWORDS;
+use std::collections::HashMap;
+
+/// Values of the `auto_tag` setting
+pub const AUTO_TAG_MODES: &[&str] = &["off", "suggest", "apply"];
+
+/// Tags suggested at most
+pub const MAX_TAGS: usize = 3;
+
+/// Nearest memories tags are borrowed from
+pub const NEIGHBORS: usize = 5;
+
+/// Neighbors less similar than this lend no tags
+const MIN_SIMILARITY: f64 = 0.5;
+
+/// Summed similarity of the neighbors carrying a tag needed to borrow it:
+/// one close memory, or two looser ones
+const BORROW_SCORE: f64 = 0.7;
+
+/// Longer runs of content words are split into overlapping pairs
+const MAX_PHRASE_WORDS: usize = 2;
+
+/// Up to `limit` key phrases of `text`, best first, as tag-shaped
+/// lowercase words joined by `-`
+pub fn keywords(text: &str, limit: usize) -> Vec<String> {
+    let mut runs: Vec<Vec<String>> = Vec::new();
+    let mut current: Vec<String> = Vec::new();
+    let mut flush = |current: &mut Vec<String>| {
+        if !current.is_empty() {
+            runs.push(std::mem::take(current));
+        }
+    };
+
+    for raw in text.split_whitespace() {
+        let word = raw
+            .trim_matches(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-'))
+            .to_lowercase();
+        let content = word.chars().count() >= 3
+            && !STOPWORDS.contains(&word.as_str())
+            && !word.chars().all(|c| c.is_ascii_digit())
+            && word.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-');
+        if content {
+            current.push(word);
+        } else {
+            flush(&mut current);
+        }
+        if raw.ends_with(['.', ',', ';', ':', '!', '?', ')']) {
+            flush(&mut current);
+        }
+    }
+    flush(&mut current);
+
+    let mut frequency: HashMap<&str, f64> = HashMap::new();
+    let mut degree: HashMap<&str, f64> = HashMap::new();
+    for run in &runs {
+        for word in run {
+            *frequency.entry(word).or_default() += 1.0;
+            *degree.entry(word).or_default() += run.len() as f64;
+        }
+    }
+
+    // Ties keep the order first seen
+    let mut scored: Vec<(String, f64)> = Vec::new();
+    for run in &runs {
+        let phrases: Vec<&[String]> = if run.len() > MAX_PHRASE_WORDS {
+            run.windows(MAX_PHRASE_WORDS).collect()
+        } else {
+            vec![run.as_slice()]
+        };
+        for phrase in phrases {
+            let score: f64 = phrase.iter().map(|w| degree[w.as_str()] / frequency[w.as_str()]).sum();
+            let tag = phrase.join("-");
+            match scored.iter_mut().find(|(t, _)| *t == tag) {
+                Some((_, total)) => *total += score,
+                None => scored.push((tag, score)),
+            }
+        }
+    }
+    scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+
+    // Phrases sharing a word with one already taken add little
+    let mut taken: Vec<String> = Vec::new();
+    for (tag, _) in scored {
+        if taken.len() == limit {
+            break;
+        }
+        let overlaps = taken.iter().any(|t| t.split('-').any(|part| tag.split('-').any(|p| p == part)));
+        if !overlaps {
+            taken.push(tag);
+        }
+    }
+    taken
+}
+
+/// Combine the sources, strongest first and without repeats: `mentioned`
+/// existing tags, tags borrowed from `neighbors` (similarity and tags of
+/// each), then `keywords`
+pub fn suggest(mentioned: &[String], neighbors: &[(f64, Vec<String>)], keywords: &[String]) -> Vec<String> {
+    let mut borrowed: Vec<(String, f64)> = Vec::new();
+    for (similarity, tags) in neighbors.iter().filter(|(s, _)| *s >= MIN_SIMILARITY) {
+        for tag in tags {
+            match borrowed.iter_mut().find(|(t, _)| t == tag) {
+                Some((_, score)) => *score += similarity,
+                None => borrowed.push((tag.clone(), *similarity)),
+            }
+        }
+    }
+    borrowed.retain(|(_, score)| *score >= BORROW_SCORE);
+    borrowed.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+
+    let mut tags: Vec<String> = Vec::new();
+    let candidates = mentioned
+        .iter()
+        .chain(borrowed.iter().map(|(t, _)| t))
+        .chain(keywords);
+    for tag in candidates {
+        if tags.len() == MAX_TAGS {
+            break;
+        }
+        if !tags.contains(tag) {
+            tags.push(tag.clone());
+        }
+    }
+    tags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keywords_and_suggest() {
+        let text = "Rotate signing keys every month. The auth service caches signing keys.";
+        let found = keywords(text, 3);
+        assert_eq!(found, vec!["signing-keys", "auth-service", "month"]);
+
+        let neighbors = vec![
+            (0.6, vec!["security".to_string(), "jwt".to_string()]),
+            (0.55, vec!["security".to_string()]),
+            (0.3, vec!["billing".to_string()]),
+        ];
+        assert_eq!(
+            suggest(&["auth".to_string()], &neighbors, &found),
+            vec!["auth", "security", "signing-keys"]
+        );
+    }
+}
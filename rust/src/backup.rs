@@ -0,0 +1,198 @@
+//! Store snapshots, kept in `.roots/backups` and optionally in an
+//! S3-compatible bucket configured in the global config.
+
+use crate::config::global_setting;
+use crate::http::{url_encode, Request};
+use regex::Regex;
+use std::path::Path;
+
+/// Directory under `.roots` holding local snapshots
+pub const BACKUP_DIR: &str = "backups";
+
+/// File name for a snapshot taken now, e.g. `memory-20260101-120000.db`
+pub fn snapshot_name() -> String {
+    format!("memory-{}.db", chrono::Utc::now().format("%Y%m%d-%H%M%S"))
+}
+
+/// Folder in the bucket for a project: its directory name, made key-safe,
+/// and a hash of its full path, so projects that share a name (two `api`
+/// checkouts, say) don't share snapshots
+pub fn project_key(roots_path: &Path) -> String {
+    let project = roots_path
+        .canonicalize()
+        .unwrap_or_else(|_| roots_path.to_path_buf());
+    let project = project.parent().unwrap_or(&project);
+    let name: String = project
+        .file_name()
+        .map_or_else(|| "default".to_string(), |n| n.to_string_lossy().to_string())
+        .chars()
+        .map(|c| match c {
+            'A'..='Z' | 'a'..='z' | '0'..='9' | '-' | '_' | '.' => c,
+            _ => '-',
+        })
+        .collect();
+    let hash = crate::blobs::hash(&project.to_string_lossy());
+    format!("{}-{}", name, &hash[..8])
+}
+
+/// Check that `name` names a snapshot in the backup folder rather than a
+/// path reaching out of it
+pub fn check_name(name: &str) -> Result<(), String> {
+    let plain = Path::new(name).file_name().is_some_and(|n| n == name);
+    if plain && !name.starts_with('.') {
+        Ok(())
+    } else {
+        Err(format!("Not a snapshot name: {} (see 'roots backup --list')", name))
+    }
+}
+
+/// An S3-compatible bucket, from `backup_s3_*` keys in the global config
+/// and the usual `AWS_*` credential variables
+pub struct S3Target {
+    endpoint: String,
+    bucket: String,
+    region: String,
+    prefix: String,
+    access_key: String,
+    secret_key: String,
+    session_token: Option<String>,
+}
+
+impl S3Target {
+    pub fn from_global_config() -> Result<Self, String> {
+        let bucket = global_setting("backup_s3_bucket").ok_or(
+            "No backup bucket configured. Run 'roots config --global backup_s3_bucket <name>' first.",
+        )?;
+        let region = global_setting("backup_s3_region").unwrap_or_default();
+        let endpoint = global_setting("backup_s3_endpoint")
+            .unwrap_or_else(|| format!("https://s3.{}.amazonaws.com", region));
+        let prefix = global_setting("backup_s3_prefix").unwrap_or_default();
+
+        let missing = "No S3 credentials: set AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY";
+        let access_key = global_setting("backup_s3_access_key").ok_or(missing)?;
+        let secret_key = global_setting("backup_s3_secret_key").ok_or(missing)?;
+
+        Ok(Self {
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            bucket,
+            region,
+            prefix,
+            access_key,
+            secret_key,
+            session_token: std::env::var("AWS_SESSION_TOKEN").ok().filter(|t| !t.is_empty()),
+        })
+    }
+
+    /// Object key for a snapshot of a project
+    pub fn key(&self, project: &str, name: &str) -> String {
+        format!("{}{}/{}", self.prefix, project, name)
+    }
+
+    /// Human-readable location of an object
+    pub fn location(&self, key: &str) -> String {
+        format!("s3://{}/{}", self.bucket, key)
+    }
+
+    fn request(&self, method: &str, url: &str) -> Request {
+        let mut request = Request::new(method, url)
+            .opt("aws-sigv4", &format!("aws:amz:{}:s3", self.region))
+            .opt("user", &format!("{}:{}", self.access_key, self.secret_key));
+        if let Some(token) = &self.session_token {
+            request = request.header(&format!("x-amz-security-token: {}", token));
+        }
+        request
+    }
+
+    fn object_url(&self, key: &str) -> String {
+        format!("{}/{}/{}", self.endpoint, self.bucket, url_encode(key, true))
+    }
+
+    /// Upload a file
+    pub fn put(&self, key: &str, file: &Path) -> Result<(), String> {
+        self.request("PUT", &self.object_url(key))
+            .opt("upload-file", &file.to_string_lossy())
+            .send()
+            .map(|_| ())
+            .map_err(|e| format!("Failed to upload {}: {}", self.location(key), e))
+    }
+
+    /// Download an object to a file
+    pub fn get(&self, key: &str, dest: &Path) -> Result<(), String> {
+        self.request("GET", &self.object_url(key))
+            .opt("output", &dest.to_string_lossy())
+            .send()
+            .map(|_| ())
+            .map_err(|e| format!("Failed to download {}: {}", self.location(key), e))
+    }
+
+    /// Names of the snapshots stored for a project, oldest first
+    pub fn list(&self, project: &str) -> Result<Vec<String>, String> {
+        let folder = self.key(project, "");
+        let url = format!(
+            "{}/{}?list-type=2&prefix={}",
+            self.endpoint,
+            self.bucket,
+            url_encode(&folder, false)
+        );
+        let body = self
+            .request("GET", &url)
+            .send()
+            .map_err(|e| format!("Failed to list {}: {}", self.location(&folder), e))?;
+        Ok(parse_keys(&body, &folder))
+    }
+}
+
+/// Snapshot names from a ListObjectsV2 response, relative to `folder`
+fn parse_keys(body: &str, folder: &str) -> Vec<String> {
+    let key = Regex::new(r"<Key>([^<]+)</Key>").unwrap();
+    let mut names: Vec<String> = key
+        .captures_iter(body)
+        .filter_map(|c| c[1].strip_prefix(folder).map(String::from))
+        .filter(|name| !name.is_empty() && !name.contains('/'))
+        .collect();
+    // Timestamped names sort chronologically
+    names.sort();
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_project_key_tells_same_named_projects_apart() {
+        let dir = std::env::temp_dir().join(format!("roots-project-key-{}", std::process::id()));
+        let a = dir.join("one").join("api").join(".roots");
+        let b = dir.join("two").join("api").join(".roots");
+        std::fs::create_dir_all(&a).unwrap();
+        std::fs::create_dir_all(&b).unwrap();
+
+        let key = project_key(&a);
+        assert!(key.starts_with("api-"));
+        assert_eq!(key.len(), "api-".len() + 8);
+        assert_ne!(key, project_key(&b));
+        // However the path is spelled
+        assert_eq!(key, project_key(&dir.join("two/../one/api/.roots")));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_check_name() {
+        assert!(check_name("memory-20260101-120000.db").is_ok());
+        assert!(check_name("pre-restore-memory-20260101-120000.db").is_ok());
+        for name in ["../memory.db", "../../etc/passwd", "a/b.db", "/tmp/x.db", "..", ".", ""] {
+            assert!(check_name(name).is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_parse_keys() {
+        let body = "<ListBucketResult><Contents><Key>roots/app/memory-20260102-000000.db</Key></Contents>\
+                    <Contents><Key>roots/app/memory-20260101-000000.db</Key></Contents>\
+                    <Contents><Key>roots/app/old/memory.db</Key></Contents></ListBucketResult>";
+        assert_eq!(
+            parse_keys(body, "roots/app/"),
+            vec!["memory-20260101-000000.db", "memory-20260102-000000.db"]
+        );
+    }
+}
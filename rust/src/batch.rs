@@ -0,0 +1,185 @@
+//! Files of memories for `remember --from-file`.
+//!
+//! Three formats, picked by extension:
+//! - `.jsonl`: one memory per line, a string or an object
+//! - `.yaml` / `.yml`: a list of the same
+//! - `.md` (or anything else): a markdown list, one memory per top-level
+//!   item, with indented lines continuing it
+//!
+//! Objects have `content` and optionally `tags` (a list or comma-separated),
+//! `confidence`, `kind`, `source`, and `expires` (a duration like `30d`).
+
+use crate::types::{NewMemory, MEMORY_KINDS};
+use serde::Deserialize;
+use std::path::Path;
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Entry {
+    Text(String),
+    Full(FullEntry),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FullEntry {
+    content: String,
+    #[serde(default)]
+    tags: Tags,
+    confidence: Option<f64>,
+    kind: Option<String>,
+    source: Option<String>,
+    expires: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Tags {
+    List(Vec<String>),
+    Text(String),
+}
+
+impl Default for Tags {
+    fn default() -> Self {
+        Self::List(Vec::new())
+    }
+}
+
+/// The memories in `text`, read from `path`. Each starts from `defaults`;
+/// its own tags are added to theirs, and its other fields replace them.
+pub fn parse(path: &Path, text: &str, defaults: &NewMemory) -> Result<Vec<NewMemory>, String> {
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    let unit = if matches!(extension, "yaml" | "yml") { "item" } else { "line" };
+    let entries: Vec<(usize, Entry)> = match extension {
+        "jsonl" | "ndjson" => text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                serde_json::from_str(line)
+                    .map(|entry| (i + 1, entry))
+                    .map_err(|e| format!("Invalid memory on line {}: {}", i + 1, e))
+            })
+            .collect::<Result<_, _>>()?,
+        "yaml" | "yml" => serde_yaml::from_str::<Vec<Entry>>(text)
+            .map_err(|e| format!("Invalid memory list: {}", e))?
+            .into_iter()
+            .enumerate()
+            .map(|(i, entry)| (i + 1, entry))
+            .collect(),
+        _ => markdown_items(text)
+            .into_iter()
+            .map(|(line, item)| (line, Entry::Text(item)))
+            .collect(),
+    };
+
+    entries
+        .into_iter()
+        .map(|(at, entry)| to_memory(entry, defaults).map_err(|e| format!("Memory at {} {}: {}", unit, at, e)))
+        .collect()
+}
+
+fn to_memory(entry: Entry, defaults: &NewMemory) -> Result<NewMemory, String> {
+    let mut memory = defaults.clone();
+    let entry = match entry {
+        Entry::Text(content) => FullEntry {
+            content,
+            tags: Tags::default(),
+            confidence: None,
+            kind: None,
+            source: None,
+            expires: None,
+        },
+        Entry::Full(entry) => entry,
+    };
+
+    memory.content = entry.content.trim().to_string();
+    if memory.content.is_empty() {
+        return Err("content is empty".to_string());
+    }
+    let tags = match entry.tags {
+        Tags::List(tags) => tags,
+        Tags::Text(tags) => tags.split(',').map(|t| t.trim().to_string()).collect(),
+    };
+    for tag in tags.into_iter().filter(|t| !t.is_empty()) {
+        if !memory.tags.contains(&tag) {
+            memory.tags.push(tag);
+        }
+    }
+    if let Some(confidence) = entry.confidence {
+        if !(0.0..=1.0).contains(&confidence) {
+            return Err(format!("confidence must be between 0 and 1, got {}", confidence));
+        }
+        memory.confidence = confidence;
+    }
+    if let Some(kind) = entry.kind {
+        if !MEMORY_KINDS.contains(&kind.as_str()) {
+            return Err(format!("unknown kind {} (use {})", kind, MEMORY_KINDS.join(", ")));
+        }
+        memory.kind = Some(kind);
+    }
+    if let Some(source) = entry.source {
+        memory.source = Some(crate::source::parse_arg(&source)?);
+    }
+    if let Some(expires) = entry.expires {
+        memory.expires_at = Some(crate::dates::from_now(crate::dates::parse_duration(&expires)?)?);
+    }
+    Ok(memory)
+}
+
+/// Top-level items of a markdown list with the line each starts on;
+/// indented lines continue the item above, anything else ends it
+fn markdown_items(text: &str) -> Vec<(usize, String)> {
+    let mut items: Vec<(usize, String)> = Vec::new();
+    let mut open = false;
+    for (i, line) in text.lines().enumerate() {
+        let marker = ["- ", "* ", "+ "].iter().find_map(|m| line.strip_prefix(m));
+        if let Some(item) = marker {
+            items.push((i + 1, item.trim().to_string()));
+            open = true;
+        } else if open && line.starts_with([' ', '\t']) && !line.trim().is_empty() {
+            if let Some((_, item)) = items.last_mut() {
+                item.push('\n');
+                item.push_str(line.trim());
+            }
+        } else {
+            open = false;
+        }
+    }
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_formats() {
+        let defaults = NewMemory {
+            confidence: 0.5,
+            tags: vec!["seed".to_string()],
+            ..Default::default()
+        };
+
+        let jsonl = "\"Use pnpm\"\n\n{\"content\": \"Deploys are blue-green\", \"tags\": \"ops, deploy\", \"kind\": \"decision\", \"confidence\": 0.9}\n";
+        let memories = parse(Path::new("notes.jsonl"), jsonl, &defaults).unwrap();
+        assert_eq!(memories.len(), 2);
+        assert_eq!(memories[0].content, "Use pnpm");
+        assert_eq!(memories[1].tags, vec!["seed", "ops", "deploy"]);
+        assert_eq!(memories[1].kind.as_deref(), Some("decision"));
+        assert_eq!(memories[1].confidence, 0.9);
+
+        let yaml = "- Use pnpm\n- content: Staging resets nightly\n  tags: [ops]\n  expires: 30d\n";
+        let memories = parse(Path::new("notes.yaml"), yaml, &defaults).unwrap();
+        assert_eq!(memories[1].tags, vec!["seed", "ops"]);
+        assert!(memories[1].expires_at.is_some());
+
+        let markdown = "# Notes\n\n- Use pnpm\n- Retries back off\n  exponentially, capped at 30s\n\nTrailing prose\n";
+        let memories = parse(Path::new("notes.md"), markdown, &defaults).unwrap();
+        let contents: Vec<&str> = memories.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, vec!["Use pnpm", "Retries back off\nexponentially, capped at 30s"]);
+
+        let error = parse(Path::new("bad.jsonl"), "\"ok\"\n{\"content\": \"x\", \"kind\": \"nope\"}\n", &defaults).unwrap_err();
+        assert!(error.starts_with("Memory at line 2"), "{}", error);
+    }
+}
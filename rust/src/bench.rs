@@ -0,0 +1,85 @@
+//! Embedder benchmark for `roots bench`.
+//!
+//! Hooks embed one prompt at a time, so the latency of single `embed` calls
+//! decides whether a model fits the hook budget; batched throughput is what
+//! `reindex`, `ingest`, and the embed queue see.
+
+use crate::embeddings::Embedder;
+use std::time::{Duration, Instant};
+
+/// Texts per `embed_batch` call in the throughput run
+pub const BATCH_SIZE: usize = 32;
+
+/// Timings of one embedder over the sample
+#[derive(Debug, Clone)]
+pub struct BenchResult {
+    pub dimensions: usize,
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+    /// Texts per second embedded in batches of `BATCH_SIZE`
+    pub throughput: f64,
+}
+
+/// Embed each of `texts` alone, then all of them in batches. One call
+/// beforehand is left out of the timings, since the first may load a model
+/// or open a connection.
+pub fn run(embedder: &dyn Embedder, texts: &[&str]) -> Result<BenchResult, String> {
+    let Some(first) = texts.first() else {
+        return Err("Nothing to embed".to_string());
+    };
+    let dimensions = embedder.embed(first)?.len();
+
+    let mut latencies = Vec::with_capacity(texts.len());
+    for text in texts {
+        let started = Instant::now();
+        embedder.embed(text)?;
+        latencies.push(started.elapsed());
+    }
+    latencies.sort();
+
+    let started = Instant::now();
+    for batch in texts.chunks(BATCH_SIZE) {
+        embedder.embed_batch(batch)?;
+    }
+    let elapsed = started.elapsed().as_secs_f64();
+
+    Ok(BenchResult {
+        dimensions,
+        p50: percentile(&latencies, 50.0),
+        p90: percentile(&latencies, 90.0),
+        p99: percentile(&latencies, 99.0),
+        max: latencies.last().copied().unwrap_or_default(),
+        throughput: if elapsed > 0.0 { texts.len() as f64 / elapsed } else { f64::INFINITY },
+    })
+}
+
+/// Nearest-rank percentile of sorted `values`
+pub fn percentile(values: &[Duration], p: f64) -> Duration {
+    if values.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = (p / 100.0 * values.len() as f64).ceil() as usize;
+    values[rank.clamp(1, values.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embeddings::LiteEmbedder;
+
+    #[test]
+    fn test_percentile_nearest_rank() {
+        let values: Vec<Duration> = (1..=10).map(Duration::from_millis).collect();
+        assert_eq!(percentile(&values, 50.0), Duration::from_millis(5));
+        assert_eq!(percentile(&values, 90.0), Duration::from_millis(9));
+        assert_eq!(percentile(&values, 99.0), Duration::from_millis(10));
+        assert_eq!(percentile(&values[..1], 50.0), Duration::from_millis(1));
+        assert_eq!(percentile(&[], 50.0), Duration::ZERO);
+
+        let result = run(&LiteEmbedder::new(), &["use uv for python", "run the tests"]).unwrap();
+        assert_eq!(result.dimensions, 384);
+        assert!(result.p50 <= result.p99 && result.p99 <= result.max);
+    }
+}
@@ -0,0 +1,111 @@
+//! Content-addressed storage for oversized memory bodies.
+//!
+//! A memory larger than `blob_threshold` keeps only a summary in the
+//! database; the full text lives in `.roots/objects/<2 hex>/<30 hex>`, named
+//! by its MD5 digest, so identical bodies are stored once.
+
+use md5::{Digest, Md5};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Directory under `.roots` holding blob files
+pub const OBJECTS_DIR: &str = "objects";
+
+/// Characters of the body kept in the database (and embedded) as its summary
+const SUMMARY_CHARS: usize = 2000;
+
+pub struct BlobStore {
+    dir: PathBuf,
+}
+
+impl BlobStore {
+    pub fn new(roots_path: &Path) -> Self {
+        Self {
+            dir: roots_path.join(OBJECTS_DIR),
+        }
+    }
+
+    fn path(&self, hash: &str) -> PathBuf {
+        let (prefix, rest) = hash.split_at(2.min(hash.len()));
+        self.dir.join(prefix).join(rest)
+    }
+
+    /// Store a body, returns its hash
+    pub fn put(&self, content: &str) -> Result<String, String> {
+        let hash = hash(content);
+        let path = self.path(&hash);
+        if path.exists() {
+            return Ok(hash);
+        }
+
+        let parent = path.parent().unwrap_or(&self.dir);
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        // Write then rename, so a crash never leaves a truncated object
+        let partial = path.with_extension("part");
+        fs::write(&partial, content)
+            .and_then(|_| fs::rename(&partial, &path))
+            .map_err(|e| format!("Failed to write blob {}: {}", hash, e))?;
+        Ok(hash)
+    }
+
+    /// Read a body back, checking it still matches its hash
+    pub fn get(&self, hash: &str) -> Result<String, String> {
+        let content = fs::read_to_string(self.path(hash))
+            .map_err(|e| format!("Failed to read blob {}: {}", hash, e))?;
+        if self::hash(&content) != hash {
+            return Err(format!("Blob {} is corrupt (content does not match its hash)", hash));
+        }
+        Ok(content)
+    }
+
+    /// Delete a body no memory refers to any more
+    pub fn remove(&self, hash: &str) -> Result<(), String> {
+        let path = self.path(hash);
+        match fs::remove_file(&path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(format!("Failed to remove blob {}: {}", hash, e)),
+        }
+    }
+}
+
+/// Hex MD5 digest of a body
+pub fn hash(content: &str) -> String {
+    Md5::digest(content.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Leading text kept in the database in place of a full body
+pub fn summary(content: &str) -> String {
+    match content.char_indices().nth(SUMMARY_CHARS) {
+        Some((end, _)) => format!("{}…", content[..end].trim_end()),
+        None => content.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_and_dedup() {
+        let dir = std::env::temp_dir().join(format!("roots-blobs-{}", std::process::id()));
+        let blobs = BlobStore::new(&dir);
+
+        let body = "line\n".repeat(1000);
+        let hash = blobs.put(&body).unwrap();
+        assert_eq!(blobs.put(&body).unwrap(), hash);
+        assert_eq!(blobs.get(&hash).unwrap(), body);
+        assert!(summary(&body).chars().count() <= SUMMARY_CHARS + 1);
+
+        fs::write(blobs.path(&hash), "tampered").unwrap();
+        assert!(blobs.get(&hash).is_err());
+
+        blobs.remove(&hash).unwrap();
+        assert!(blobs.get(&hash).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,788 @@
+//! Initial memories derived from a repository's files, for `roots bootstrap`
+
+use crate::ingest::ImportItem;
+use std::fs;
+use std::path::Path;
+
+/// Directories never worth describing
+const SKIP_DIRS: &[&str] = &[
+    "node_modules",
+    "target",
+    "dist",
+    "build",
+    "vendor",
+    "__pycache__",
+    "venv",
+];
+
+/// Dependencies listed per manifest before eliding the rest
+const MAX_DEPS: usize = 12;
+
+/// Commands listed per CI workflow or task file before eliding the rest
+const MAX_COMMANDS: usize = 8;
+
+/// Scan `root` and describe its stack, commands, CI, and layout
+pub fn scan(root: &Path) -> Vec<ImportItem> {
+    let mut items = Vec::new();
+
+    if let Some(item) = readme(root) {
+        items.push(item);
+    }
+
+    // Manifests at the root and one level down (monorepos, rust/ + python/ splits)
+    let mut dirs = vec![String::new()];
+    dirs.extend(subdirs(root).into_iter().map(|d| format!("{}/", d)));
+    for dir in &dirs {
+        items.extend(manifests(root, dir));
+    }
+
+    items.extend(ci(root));
+
+    if let Some(item) = layout(root) {
+        items.push(item);
+    }
+
+    items
+}
+
+fn item(content: String, source: &str, tags: &[&str]) -> ImportItem {
+    let mut all = vec!["bootstrap".to_string()];
+    all.extend(tags.iter().map(|t| t.to_string()));
+    ImportItem {
+        content,
+        source: format!("bootstrap:{}", source),
+        tags: all,
+    }
+}
+
+fn read(root: &Path, rel: &str) -> Option<String> {
+    fs::read_to_string(root.join(rel)).ok()
+}
+
+fn subdirs(root: &Path) -> Vec<String> {
+    let mut dirs: Vec<String> = fs::read_dir(root)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|e| e.file_type().map(|t| t.is_dir()).unwrap_or(false))
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .filter(|name| !name.starts_with('.') && !SKIP_DIRS.contains(&name.as_str()))
+        .collect();
+    dirs.sort();
+    dirs
+}
+
+fn elide(mut list: Vec<String>, max: usize) -> String {
+    let extra = list.len().saturating_sub(max);
+    list.truncate(max);
+    let mut text = list.join(", ");
+    if extra > 0 {
+        text.push_str(&format!(" (+{} more)", extra));
+    }
+    text
+}
+
+// -----------------------------------------------------------------------------
+// README
+// -----------------------------------------------------------------------------
+
+fn readme(root: &Path) -> Option<ImportItem> {
+    let name = ["README.md", "README.rst", "README.txt", "README"]
+        .into_iter()
+        .find(|n| root.join(n).exists())?;
+    let text = read(root, name)?;
+    let summary = readme_summary(&text)?;
+    Some(item(summary, name, &["overview"]))
+}
+
+/// Title plus first prose paragraph of a README
+pub fn readme_summary(text: &str) -> Option<String> {
+    let mut title = None;
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut in_code = false;
+
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with("```") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            continue;
+        }
+        if let Some(heading) = line.strip_prefix('#') {
+            if title.is_none() {
+                title = Some(heading.trim_start_matches('#').trim());
+                continue;
+            }
+            if !paragraph.is_empty() {
+                break;
+            }
+            continue;
+        }
+        // Skip badges, images, and html
+        if line.starts_with('[') || line.starts_with('!') || line.starts_with('<') {
+            continue;
+        }
+        if line.is_empty() {
+            if !paragraph.is_empty() {
+                break;
+            }
+            continue;
+        }
+        paragraph.push(line);
+    }
+
+    let body: String = paragraph.join(" ").chars().take(400).collect();
+    match (title, body.is_empty()) {
+        (Some(t), false) => Some(format!("Project {}: {}", t, body)),
+        (Some(t), true) => Some(format!("Project: {}", t)),
+        (None, false) => Some(format!("Project overview: {}", body)),
+        (None, true) => None,
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Manifests
+// -----------------------------------------------------------------------------
+
+fn manifests(root: &Path, dir: &str) -> Vec<ImportItem> {
+    let mut items = Vec::new();
+    let at = |name: &str| format!("{}{}", dir, name);
+    let location = if dir.is_empty() {
+        String::new()
+    } else {
+        format!(" in {}", dir)
+    };
+    let located = |desc: String| {
+        if dir.is_empty() {
+            desc
+        } else {
+            format!("In {}: {}", dir, desc)
+        }
+    };
+
+    if let Some(text) = read(root, &at("Cargo.toml")) {
+        if let Some(desc) = describe_cargo(&text) {
+            items.push(item(located(desc), &at("Cargo.toml"), &["stack", "rust"]));
+        }
+    }
+
+    if let Some(text) = read(root, &at("package.json")) {
+        let manager = ["pnpm-lock.yaml:pnpm", "yarn.lock:yarn", "bun.lockb:bun"]
+            .iter()
+            .filter_map(|pair| pair.split_once(':'))
+            .find(|(lock, _)| root.join(at(lock)).exists())
+            .map(|(_, m)| m)
+            .unwrap_or("npm");
+        let (stack, commands) = describe_package_json(&text, manager);
+        if let Some(stack) = stack {
+            items.push(item(
+                located(stack),
+                &at("package.json"),
+                &["stack", "javascript"],
+            ));
+        }
+        if let Some(commands) = commands {
+            let source = format!("{}#scripts", at("package.json"));
+            items.push(item(located(commands), &source, &["commands"]));
+        }
+    }
+
+    if let Some(text) = read(root, &at("pyproject.toml")) {
+        let runner = if root.join(at("uv.lock")).exists() {
+            Some("uv")
+        } else if root.join(at("poetry.lock")).exists() {
+            Some("poetry")
+        } else {
+            None
+        };
+        if let Some(desc) = describe_pyproject(&text, runner) {
+            items.push(item(
+                located(desc),
+                &at("pyproject.toml"),
+                &["stack", "python"],
+            ));
+        }
+    } else if let Some(text) = read(root, &at("requirements.txt")) {
+        let deps: Vec<String> = text
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#') && !l.starts_with('-'))
+            .map(dependency_name)
+            .collect();
+        let content = format!(
+            "Python project{} with dependencies: {}",
+            location,
+            elide(deps, MAX_DEPS)
+        );
+        items.push(item(content, &at("requirements.txt"), &["stack", "python"]));
+    }
+
+    if let Some(text) = read(root, &at("go.mod")) {
+        let module = text
+            .lines()
+            .find_map(|l| l.strip_prefix("module "))
+            .unwrap_or("(unnamed)")
+            .trim();
+        let go = text
+            .lines()
+            .find_map(|l| l.strip_prefix("go "))
+            .map(str::trim);
+        let mut content = format!("Go module {}{}", module, location);
+        if let Some(v) = go {
+            content.push_str(&format!(" (go {})", v));
+        }
+        content.push_str(". Build with `go build ./...`, test with `go test ./...`");
+        items.push(item(content, &at("go.mod"), &["stack", "go"]));
+    }
+
+    if root.join(at("Gemfile")).exists() {
+        items.push(item(
+            format!("Ruby project{} (Bundler Gemfile)", location),
+            &at("Gemfile"),
+            &["stack", "ruby"],
+        ));
+    }
+    if root.join(at("pom.xml")).exists() {
+        items.push(item(
+            format!("Java project{} built with Maven (pom.xml)", location),
+            &at("pom.xml"),
+            &["stack", "java"],
+        ));
+    }
+    for gradle in ["build.gradle", "build.gradle.kts"] {
+        if root.join(at(gradle)).exists() {
+            let content = format!("JVM project{} built with Gradle ({})", location, gradle);
+            items.push(item(content, &at(gradle), &["stack", "java"]));
+        }
+    }
+
+    if let Some(text) = read(root, &at("Makefile")) {
+        let targets = make_targets(&text);
+        if !targets.is_empty() {
+            let content = format!("Make targets{}: {}", location, elide(targets, MAX_COMMANDS));
+            items.push(item(content, &at("Makefile"), &["commands"]));
+        }
+    }
+    if let Some(text) = read(root, &at("justfile")) {
+        let recipes = make_targets(&text);
+        if !recipes.is_empty() {
+            let content = format!("just recipes{}: {}", location, elide(recipes, MAX_COMMANDS));
+            items.push(item(content, &at("justfile"), &["commands"]));
+        }
+    }
+
+    items
+}
+
+/// Flattened `(section, key, value)` entries of a TOML file; multi-line arrays are joined
+fn toml_entries(text: &str) -> Vec<(String, String, String)> {
+    let mut entries = Vec::new();
+    let mut section = String::new();
+    let mut pending: Option<(String, String)> = None;
+
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with('#') {
+            continue;
+        }
+        if let Some((key, mut value)) = pending.take() {
+            value.push_str(line);
+            if line.starts_with(']') {
+                entries.push((section.clone(), key, value));
+            } else {
+                pending = Some((key, value));
+            }
+            continue;
+        }
+        if line.starts_with('[') {
+            section = line.trim_matches(|c| c == '[' || c == ']').to_string();
+            continue;
+        }
+        if let Some((key, value)) = line.split_once('=') {
+            let (key, value) = (key.trim().to_string(), value.trim().to_string());
+            if value.starts_with('[') && !value.ends_with(']') {
+                pending = Some((key, value));
+            } else {
+                entries.push((section.clone(), key, value));
+            }
+        }
+    }
+
+    entries
+}
+
+fn toml_string(entries: &[(String, String, String)], section: &str, key: &str) -> Option<String> {
+    entries
+        .iter()
+        .find(|(s, k, _)| s == section && k == key)
+        .map(|(_, _, v)| v.trim_matches('"').to_string())
+}
+
+fn toml_keys(entries: &[(String, String, String)], section: &str) -> Vec<String> {
+    entries
+        .iter()
+        .filter(|(s, _, _)| s == section)
+        .map(|(_, k, _)| k.clone())
+        .collect()
+}
+
+/// Strings inside a TOML array value
+fn toml_array(value: &str) -> Vec<String> {
+    value
+        .split('"')
+        .skip(1)
+        .step_by(2)
+        .map(String::from)
+        .collect()
+}
+
+/// Package name from a requirement like `numpy>=1.24` or `uvicorn[standard]`
+fn dependency_name(spec: &str) -> String {
+    spec.split(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_' || c == '.'))
+        .next()
+        .unwrap_or(spec)
+        .to_string()
+}
+
+/// Describe a Cargo manifest
+pub fn describe_cargo(text: &str) -> Option<String> {
+    let entries = toml_entries(text);
+    let name = toml_string(&entries, "package", "name");
+    let members = entries
+        .iter()
+        .find(|(s, k, _)| s == "workspace" && k == "members")
+        .map(|(_, _, v)| toml_array(v));
+
+    let mut content = match (&name, &members) {
+        (Some(n), _) => format!("Rust crate {} (Cargo)", n),
+        (None, Some(m)) => format!("Rust workspace (Cargo) with members: {}", m.join(", ")),
+        (None, None) => return None,
+    };
+    if let Some(edition) = toml_string(&entries, "package", "edition") {
+        content.push_str(&format!(", edition {}", edition));
+    }
+
+    let deps = toml_keys(&entries, "dependencies");
+    if !deps.is_empty() {
+        content.push_str(&format!("; dependencies: {}", elide(deps, MAX_DEPS)));
+    }
+    content.push_str(". Build with `cargo build`, test with `cargo test`");
+    Some(content)
+}
+
+/// Describe a package.json: (stack, scripts)
+pub fn describe_package_json(text: &str, manager: &str) -> (Option<String>, Option<String>) {
+    let Ok(json) = serde_json::from_str::<serde_json::Value>(text) else {
+        return (None, None);
+    };
+
+    let name = json["name"].as_str().unwrap_or("(unnamed)");
+    let keys = |field: &str| -> Vec<String> {
+        json[field]
+            .as_object()
+            .map(|o| o.keys().cloned().collect())
+            .unwrap_or_default()
+    };
+
+    let deps = keys("dependencies");
+    let dev = keys("devDependencies");
+    let language = if dev.iter().chain(&deps).any(|d| d == "typescript") {
+        "TypeScript"
+    } else {
+        "JavaScript"
+    };
+    let mut stack = format!("{} package {} using {}", language, name, manager);
+    if !deps.is_empty() {
+        stack.push_str(&format!("; dependencies: {}", elide(deps, MAX_DEPS)));
+    }
+
+    let run = if manager == "npm" { "npm run" } else { manager };
+    let scripts: Vec<String> = json["scripts"]
+        .as_object()
+        .map(|o| {
+            o.iter()
+                .map(|(k, v)| format!("`{} {}` ({})", run, k, v.as_str().unwrap_or("")))
+                .collect()
+        })
+        .unwrap_or_default();
+    let commands = if scripts.is_empty() {
+        None
+    } else {
+        Some(format!(
+            "Package scripts for {}: {}",
+            name,
+            elide(scripts, MAX_COMMANDS)
+        ))
+    };
+
+    (Some(stack), commands)
+}
+
+/// Describe a pyproject.toml; `runner` is the lockfile-detected tool (uv, poetry)
+pub fn describe_pyproject(text: &str, runner: Option<&str>) -> Option<String> {
+    let entries = toml_entries(text);
+    let name = toml_string(&entries, "project", "name")
+        .or_else(|| toml_string(&entries, "tool.poetry", "name"))?;
+
+    let mut content = format!("Python project {}", name);
+    if let Some(python) = toml_string(&entries, "project", "requires-python") {
+        content.push_str(&format!(" (python {})", python));
+    }
+    if let Some(r) = runner {
+        content.push_str(&format!(", managed with {}", r));
+    }
+
+    let deps: Vec<String> = entries
+        .iter()
+        .find(|(s, k, _)| s == "project" && k == "dependencies")
+        .map(|(_, _, v)| toml_array(v).iter().map(|d| dependency_name(d)).collect())
+        .unwrap_or_else(|| {
+            toml_keys(&entries, "tool.poetry.dependencies")
+                .into_iter()
+                .filter(|d| d != "python")
+                .collect()
+        });
+    if !deps.is_empty() {
+        content.push_str(&format!("; dependencies: {}", elide(deps, MAX_DEPS)));
+    }
+
+    let scripts = toml_keys(&entries, "project.scripts");
+    if !scripts.is_empty() {
+        content.push_str(&format!("; console scripts: {}", scripts.join(", ")));
+    }
+
+    let tools: Vec<&str> = ["pytest", "ruff", "black", "mypy"]
+        .into_iter()
+        .filter(|t| {
+            entries
+                .iter()
+                .any(|(s, _, _)| s.starts_with(&format!("tool.{}", t)))
+        })
+        .collect();
+    if !tools.is_empty() {
+        content.push_str(&format!("; tooling: {}", tools.join(", ")));
+    }
+
+    Some(content)
+}
+
+/// Target names from a Makefile or justfile
+fn make_targets(text: &str) -> Vec<String> {
+    let mut targets = Vec::new();
+    for line in text.lines() {
+        if line.starts_with(|c: char| c.is_whitespace() || c == '#' || c == '.') {
+            continue;
+        }
+        let Some((head, rest)) = line.split_once(':') else {
+            continue;
+        };
+        // Skip variable assignments (`X := y`)
+        if rest.starts_with('=') || head.contains('=') || head.contains('$') {
+            continue;
+        }
+        let name = head
+            .split_whitespace()
+            .next()
+            .unwrap_or("")
+            .trim_start_matches('@');
+        if !name.is_empty() && !targets.iter().any(|t| t == name) {
+            targets.push(name.to_string());
+        }
+    }
+    targets
+}
+
+// -----------------------------------------------------------------------------
+// CI
+// -----------------------------------------------------------------------------
+
+fn ci(root: &Path) -> Vec<ImportItem> {
+    let mut items = Vec::new();
+
+    let workflows = root.join(".github/workflows");
+    let mut files: Vec<_> = fs::read_dir(&workflows)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| matches!(p.extension().and_then(|e| e.to_str()), Some("yml" | "yaml")))
+        .collect();
+    files.sort();
+
+    for path in files {
+        let Ok(text) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let file = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        if let Some(desc) = describe_workflow(&file, &text) {
+            items.push(item(desc, &format!(".github/workflows/{}", file), &["ci"]));
+        }
+    }
+
+    if let Some(text) = read(root, ".gitlab-ci.yml") {
+        if let Some(desc) = describe_gitlab_ci(&text) {
+            items.push(item(desc, ".gitlab-ci.yml", &["ci"]));
+        }
+    }
+
+    items
+}
+
+/// First line of each `run` step, deduplicated
+fn run_commands(steps: &serde_yaml::Value, out: &mut Vec<String>) {
+    for step in steps.as_sequence().into_iter().flatten() {
+        let run = step
+            .get("run")
+            .and_then(|r| r.as_str())
+            .or_else(|| step.as_str());
+        if let Some(first) = run.and_then(|r| r.lines().map(str::trim).find(|l| !l.is_empty())) {
+            if !out.iter().any(|c| c == first) {
+                out.push(first.to_string());
+            }
+        }
+    }
+}
+
+/// Describe a GitHub Actions workflow
+pub fn describe_workflow(file: &str, text: &str) -> Option<String> {
+    let yaml: serde_yaml::Value = serde_yaml::from_str(text).ok()?;
+    let name = yaml.get("name").and_then(|n| n.as_str()).unwrap_or(file);
+
+    // `on` parses as the boolean key `true` in YAML 1.1
+    let on = yaml
+        .get("on")
+        .or_else(|| yaml.get(serde_yaml::Value::Bool(true)))
+        .map(|v| match v {
+            serde_yaml::Value::String(s) => s.clone(),
+            serde_yaml::Value::Sequence(s) => s
+                .iter()
+                .filter_map(|e| e.as_str())
+                .collect::<Vec<_>>()
+                .join(", "),
+            serde_yaml::Value::Mapping(m) => m
+                .keys()
+                .filter_map(|k| k.as_str())
+                .collect::<Vec<_>>()
+                .join(", "),
+            _ => String::new(),
+        })
+        .filter(|s| !s.is_empty());
+
+    let mut commands = Vec::new();
+    for (_, job) in yaml.get("jobs")?.as_mapping()? {
+        if let Some(steps) = job.get("steps") {
+            run_commands(steps, &mut commands);
+        }
+    }
+
+    let mut content = format!("CI workflow \"{}\" (GitHub Actions, {})", name, file);
+    if let Some(on) = on {
+        content.push_str(&format!(" runs on {}", on));
+    }
+    if !commands.is_empty() {
+        let commands = commands.into_iter().map(|c| format!("`{}`", c)).collect();
+        content.push_str(&format!("; steps: {}", elide(commands, MAX_COMMANDS)));
+    }
+    Some(content)
+}
+
+/// Describe a GitLab CI config
+fn describe_gitlab_ci(text: &str) -> Option<String> {
+    let yaml: serde_yaml::Value = serde_yaml::from_str(text).ok()?;
+    let mut jobs = Vec::new();
+    let mut commands = Vec::new();
+
+    for (key, job) in yaml.as_mapping()? {
+        let Some(name) = key.as_str() else {
+            continue;
+        };
+        if let Some(script) = job.get("script") {
+            jobs.push(name.to_string());
+            run_commands(script, &mut commands);
+        }
+    }
+
+    let mut content = format!(
+        "CI on GitLab (.gitlab-ci.yml) with jobs: {}",
+        jobs.join(", ")
+    );
+    if !commands.is_empty() {
+        let commands = commands.into_iter().map(|c| format!("`{}`", c)).collect();
+        content.push_str(&format!("; scripts: {}", elide(commands, MAX_COMMANDS)));
+    }
+    Some(content)
+}
+
+// -----------------------------------------------------------------------------
+// Layout
+// -----------------------------------------------------------------------------
+
+fn layout(root: &Path) -> Option<ImportItem> {
+    let dirs = subdirs(root);
+    if dirs.is_empty() {
+        return None;
+    }
+
+    let described: Vec<String> = dirs
+        .iter()
+        .map(|d| {
+            let mut children = subdirs(&root.join(d));
+            children.truncate(5);
+            if children.is_empty() {
+                format!("{}/", d)
+            } else {
+                format!("{}/ ({})", d, children.join(", "))
+            }
+        })
+        .collect();
+
+    let content = format!("Repository layout: {}", described.join("; "));
+    Some(item(content, "layout", &["structure"]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_cargo() {
+        let toml = r#"
+[package]
+name = "roots"
+edition = "2021"
+
+[dependencies]
+clap = { version = "4", features = ["derive"] }
+serde_json = "1"
+
+[profile.release]
+lto = true
+"#;
+        assert_eq!(
+            describe_cargo(toml).unwrap(),
+            "Rust crate roots (Cargo), edition 2021; dependencies: clap, serde_json. \
+             Build with `cargo build`, test with `cargo test`"
+        );
+    }
+
+    #[test]
+    fn test_describe_pyproject() {
+        let toml = r#"
+[project]
+name = "roots-kb"
+requires-python = ">=3.11"
+dependencies = [
+    "pyyaml>=6.0",
+    "sentence-transformers[onnx]>=5.2.0",
+]
+
+[tool.ruff]
+line-length = 100
+"#;
+        assert_eq!(
+            describe_pyproject(toml, Some("uv")).unwrap(),
+            "Python project roots-kb (python >=3.11), managed with uv; \
+             dependencies: pyyaml, sentence-transformers; tooling: ruff"
+        );
+    }
+
+    #[test]
+    fn test_workflow_and_readme() {
+        let workflow = "name: CI\non: [push, pull_request]\njobs:\n  test:\n    steps:\n      - uses: actions/checkout@v4\n      - run: cargo test\n";
+        assert_eq!(
+            describe_workflow("ci.yml", workflow).unwrap(),
+            "CI workflow \"CI\" (GitHub Actions, ci.yml) runs on push, pull_request; steps: `cargo test`"
+        );
+
+        let readme =
+            "# roots\n\n[![badge](x)](y)\n\nPersistent memory\nfor agents.\n\n## Install\n";
+        assert_eq!(
+            readme_summary(readme).unwrap(),
+            "Project roots: Persistent memory for agents."
+        );
+    }
+
+    #[test]
+    fn test_helpers_edge_cases() {
+        let list = |n: usize| (1..=n).map(|i| i.to_string()).collect::<Vec<_>>();
+        assert_eq!(elide(list(3), 3), "1, 2, 3");
+        assert_eq!(elide(list(5), 3), "1, 2, 3 (+2 more)");
+
+        let makefile = "CC := gcc\n.PHONY: test\n# comment\n@build: deps\n\tcc x\ntest:\nbuild:\nout/$(X): y\n";
+        assert_eq!(make_targets(makefile), vec!["build", "test"]);
+
+        // Code blocks and later sections aren't the summary; no title is fine
+        assert_eq!(
+            readme_summary("```\n# not a title\n```\nJust prose.\n\n# Later\n").unwrap(),
+            "Project overview: Just prose."
+        );
+        assert_eq!(readme_summary("# Tool\n\n## Usage\n").unwrap(), "Project: Tool");
+        assert_eq!(readme_summary("![logo](x.png)\n"), None);
+
+        assert_eq!(describe_package_json("{not json", "npm"), (None, None));
+        let poetry = "[tool.poetry]\nname = \"app\"\n\n[tool.poetry.dependencies]\npython = \"^3.11\"\nrequests = \"*\"\n";
+        assert_eq!(
+            describe_pyproject(poetry, Some("poetry")).unwrap(),
+            "Python project app, managed with poetry; dependencies: requests"
+        );
+        assert_eq!(describe_pyproject("[tool.ruff]\n", None), None);
+        assert_eq!(describe_workflow("x.yml", "name: no jobs\n"), None);
+    }
+
+    #[test]
+    fn test_scan_repository() {
+        let root = std::env::temp_dir().join(format!("roots-bootstrap-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        for dir in ["web", "svc/cmd", "node_modules/x", ".github/workflows", ".git"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        let write = |rel: &str, text: &str| fs::write(root.join(rel), text).unwrap();
+        write("README.md", "# shop\n\nSells things.\n");
+        write(
+            "web/package.json",
+            r#"{"name": "web", "scripts": {"dev": "vite"}, "devDependencies": {"typescript": "5"}}"#,
+        );
+        write("web/pnpm-lock.yaml", "");
+        write("svc/go.mod", "module example.com/svc\n\ngo 1.22\n");
+        write("requirements.txt", "# pinned\nflask==3.0\n-r dev.txt\n");
+        write("Makefile", "test:\n\tgo test ./...\n");
+        write(".github/workflows/ci.yml", "on: push\njobs:\n  t:\n    steps:\n      - run: make test\n");
+        write(".gitlab-ci.yml", "stages: [test]\nunit:\n  script:\n    - make test\n");
+
+        let items = scan(&root);
+        let found: Vec<(&str, &str)> = items.iter().map(|i| (i.source.as_str(), i.content.as_str())).collect();
+        assert_eq!(
+            found,
+            vec![
+                ("bootstrap:README.md", "Project shop: Sells things."),
+                ("bootstrap:requirements.txt", "Python project with dependencies: flask"),
+                ("bootstrap:Makefile", "Make targets: test"),
+                (
+                    "bootstrap:svc/go.mod",
+                    "Go module example.com/svc in svc/ (go 1.22). Build with `go build ./...`, test with `go test ./...`"
+                ),
+                ("bootstrap:web/package.json", "In web/: TypeScript package web using pnpm"),
+                ("bootstrap:web/package.json#scripts", "In web/: Package scripts for web: `pnpm dev` (vite)"),
+                (
+                    "bootstrap:.github/workflows/ci.yml",
+                    "CI workflow \"ci.yml\" (GitHub Actions, ci.yml) runs on push; steps: `make test`"
+                ),
+                ("bootstrap:.gitlab-ci.yml", "CI on GitLab (.gitlab-ci.yml) with jobs: unit; scripts: `make test`"),
+                // Hidden and dependency directories are left out
+                ("bootstrap:layout", "Repository layout: svc/ (cmd); web/"),
+            ]
+        );
+        assert!(items.iter().all(|i| i.tags[0] == "bootstrap"));
+        assert_eq!(items[3].tags, vec!["bootstrap", "stack", "go"]);
+
+        let empty = root.join("svc/cmd");
+        assert!(scan(&empty).is_empty());
+        let _ = fs::remove_dir_all(&root);
+    }
+}
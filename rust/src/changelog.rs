@@ -0,0 +1,193 @@
+use crate::lock::FileLock;
+use crate::proto::ChangeEvent;
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+/// Directory under `.roots` holding the change feed
+const CHANGELOG_DIR: &str = "changelog";
+
+/// Change feed file (one JSON event per line)
+const CHANGELOG_FILE: &str = "changes.jsonl";
+
+/// Held while an event is numbered and appended
+const LOCK_FILE: &str = "changes.lock";
+
+/// Append-only JSONL change feed under `.roots/changelog/`
+pub struct Changelog {
+    path: PathBuf,
+}
+
+impl Changelog {
+    pub fn new(roots_path: &Path) -> Self {
+        Self {
+            path: roots_path.join(CHANGELOG_DIR).join(CHANGELOG_FILE),
+        }
+    }
+
+    /// Lock the feed, so the next sequence number is read, appended, and
+    /// recorded by one writer at a time
+    pub fn lock(&self) -> Result<FileLock, String> {
+        let dir = self.create_dir()?;
+        FileLock::acquire(&dir.join(LOCK_FILE)).map_err(|e| format!("Failed to lock changelog: {}", e))
+    }
+
+    fn create_dir(&self) -> Result<&Path, String> {
+        let dir = self.path.parent().unwrap_or(Path::new("."));
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create changelog directory: {}", e))?;
+        Ok(dir)
+    }
+
+    /// Append an event to the feed
+    pub fn append(&self, event: &ChangeEvent) -> Result<(), String> {
+        self.create_dir()?;
+
+        let line = serde_json::to_string(event)
+            .map_err(|e| format!("Failed to serialize change: {}", e))?;
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| format!("Failed to open changelog: {}", e))?;
+        writeln!(file, "{}", line).map_err(|e| format!("Failed to write changelog: {}", e))
+    }
+
+    /// Events with a sequence number greater than `seq`
+    pub fn since(&self, seq: u64) -> Result<Vec<ChangeEvent>, String> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let file =
+            fs::File::open(&self.path).map_err(|e| format!("Failed to open changelog: {}", e))?;
+
+        let mut events = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let line = line.map_err(|e| format!("Failed to read changelog: {}", e))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let event: ChangeEvent = serde_json::from_str(&line)
+                .map_err(|e| format!("Corrupt changelog entry: {}", e))?;
+            if event.seq > seq {
+                events.push(event);
+            }
+        }
+
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::Memories;
+    use crate::test_support;
+    use crate::types::{MemoryUpdate, NewMemory};
+
+    #[test]
+    fn test_since_reads_past_blank_lines_and_rejects_corrupt_ones() {
+        let dir = std::env::temp_dir().join(format!("roots-changelog-{}", std::process::id()));
+        let changelog = Changelog::new(&dir);
+        assert!(changelog.since(0).unwrap().is_empty());
+
+        let event = |seq| ChangeEvent {
+            seq,
+            op: "delete".to_string(),
+            id: seq as i64,
+            at: "2026-03-01T00:00:00+00:00".to_string(),
+            memory: None,
+        };
+        for seq in 1..=3 {
+            changelog.append(&event(seq)).unwrap();
+        }
+        let path = dir.join(CHANGELOG_DIR).join(CHANGELOG_FILE);
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        writeln!(file).unwrap();
+        assert_eq!(changelog.since(1).unwrap(), vec![event(2), event(3)]);
+        assert!(changelog.since(3).unwrap().is_empty());
+
+        writeln!(file, "{{\"seq\": 4").unwrap();
+        assert!(changelog.since(0).unwrap_err().starts_with("Corrupt changelog entry"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_store_writes_feed_only_when_enabled() {
+        let dir = test_support::scratch("changelog-store");
+        let roots = test_support::project(&dir, "");
+        let remember = |mem: &Memories, content: &str| {
+            mem.remember(&NewMemory {
+                content: content.to_string(),
+                confidence: 0.5,
+                ..Default::default()
+            })
+            .unwrap()
+        };
+
+        let mem = Memories::open_at(roots.clone()).unwrap();
+        remember(&mem, "Before the feed");
+        assert!(!mem.changelog_enabled());
+        assert!(!roots.join(CHANGELOG_DIR).exists());
+
+        test_support::project(&dir, "changelog: true\n");
+        let mem = Memories::open_at(roots.clone()).unwrap();
+        let id = remember(&mem, "Tracked");
+        let update = MemoryUpdate {
+            content: Some("Tracked, revised".to_string()),
+            ..Default::default()
+        };
+        mem.update(id, &update).unwrap();
+        mem.forget(id).unwrap();
+
+        let events = mem.changes_since(0).unwrap();
+        let ops: Vec<(u64, &str)> = events.iter().map(|e| (e.seq, e.op.as_str())).collect();
+        assert_eq!(ops, vec![(1, "create"), (2, "update"), (3, "delete")]);
+        assert!(events.iter().all(|e| e.id == id));
+        assert_eq!(events[1].memory.as_ref().unwrap().content, "Tracked, revised");
+        assert!(events[2].memory.is_none());
+
+        // Sequence numbers carry on in a later process
+        let mem = Memories::open_at(roots).unwrap();
+        assert_eq!(mem.changelog_seq().unwrap(), 3);
+        remember(&mem, "Later");
+        assert_eq!(mem.changes_since(3).unwrap()[0].seq, 4);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_concurrent_writers_get_distinct_sequence_numbers() {
+        let dir = test_support::scratch("changelog-concurrent");
+        let roots = test_support::project(&dir, "changelog: true\n");
+        Memories::open_at(roots.clone()).unwrap();
+
+        // Each writer has its own connection, as separate processes would
+        let writers: Vec<_> = (0..4)
+            .map(|w| {
+                let roots = roots.clone();
+                std::thread::spawn(move || {
+                    let mem = Memories::open_at(roots).unwrap();
+                    for i in 0..5 {
+                        mem.remember(&NewMemory {
+                            content: format!("Writer {} note {}", w, i),
+                            confidence: 0.5,
+                            ..Default::default()
+                        })
+                        .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        let mem = Memories::open_at(roots).unwrap();
+        let mut seqs: Vec<u64> = mem.changes_since(0).unwrap().iter().map(|e| e.seq).collect();
+        seqs.sort();
+        assert_eq!(seqs, (1..=20).collect::<Vec<_>>());
+        assert_eq!(mem.changelog_seq().unwrap(), 20);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
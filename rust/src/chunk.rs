@@ -0,0 +1,356 @@
+//! Splitting documents into memory-sized chunks.
+//!
+//! Implement `Chunker` to plug in a custom strategy; `chunker()` builds the
+//! built-in ones by name for the CLI.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Built-in chunking strategies, by CLI name
+pub const CHUNKERS: &[&str] = &["heading", "paragraph", "tokens", "code"];
+
+/// A built-in chunker as chosen on the command line, kept so the same one
+/// can be built again later
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkerSpec {
+    pub name: String,
+    pub size: usize,
+    pub overlap: usize,
+}
+
+impl ChunkerSpec {
+    pub fn build(&self) -> Result<Box<dyn Chunker>, String> {
+        chunker(&self.name, self.size, self.overlap)
+    }
+}
+
+/// A piece of a document, with its 1-based inclusive line range
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chunk {
+    pub text: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    /// Heading or definition the chunk belongs to, if known
+    pub title: Option<String>,
+}
+
+/// A strategy for splitting text into chunks
+pub trait Chunker {
+    fn chunk(&self, text: &str) -> Vec<Chunk>;
+}
+
+/// Build a built-in chunker. `size` and `overlap` are in whitespace-separated
+/// tokens; chunkers other than "tokens" overlap by whole lines.
+pub fn chunker(name: &str, size: usize, overlap: usize) -> Result<Box<dyn Chunker>, String> {
+    if size == 0 {
+        return Err("Chunk size must be positive".to_string());
+    }
+    if overlap >= size {
+        return Err("Overlap must be smaller than the chunk size".to_string());
+    }
+    let by_lines = |inner: Box<dyn Chunker>| -> Box<dyn Chunker> {
+        if overlap == 0 {
+            inner
+        } else {
+            Box::new(Overlap { inner, tokens: overlap })
+        }
+    };
+    match name {
+        "heading" => Ok(by_lines(Box::new(HeadingChunker { max_tokens: size }))),
+        "paragraph" => Ok(by_lines(Box::new(ParagraphChunker { max_tokens: size }))),
+        "tokens" => Ok(Box::new(TokenChunker { size, overlap })),
+        "code" => Ok(by_lines(Box::new(CodeChunker { max_tokens: size }))),
+        _ => Err(format!(
+            "Unknown chunker: {} (use {})",
+            name,
+            CHUNKERS.join(", ")
+        )),
+    }
+}
+
+/// Accumulates consecutive lines into a chunk
+struct Builder {
+    lines: Vec<String>,
+    start: usize,
+    end: usize,
+    title: Option<String>,
+}
+
+impl Builder {
+    fn new() -> Self {
+        Self {
+            lines: Vec::new(),
+            start: 1,
+            end: 1,
+            title: None,
+        }
+    }
+
+    fn tokens(&self) -> usize {
+        self.lines
+            .iter()
+            .map(|l| l.split_whitespace().count())
+            .sum()
+    }
+
+    fn push(&mut self, line_no: usize, line: &str) {
+        if self.lines.is_empty() {
+            self.start = line_no;
+        }
+        self.end = line_no;
+        self.lines.push(line.to_string());
+    }
+
+    /// Emit the current lines as a chunk (if not blank) and start over
+    fn flush(&mut self, out: &mut Vec<Chunk>) {
+        let text = self.lines.join("\n").trim().to_string();
+        if !text.is_empty() {
+            out.push(Chunk {
+                text,
+                start_line: self.start,
+                end_line: self.end,
+                title: self.title.clone(),
+            });
+        }
+        self.lines.clear();
+    }
+}
+
+/// One chunk per markdown section, split further by paragraph when too long
+pub struct HeadingChunker {
+    pub max_tokens: usize,
+}
+
+impl Chunker for HeadingChunker {
+    fn chunk(&self, text: &str) -> Vec<Chunk> {
+        let mut out = Vec::new();
+        let mut current = Builder::new();
+        let mut in_code = false;
+
+        for (i, line) in text.lines().enumerate() {
+            if line.trim_start().starts_with("```") {
+                in_code = !in_code;
+            }
+            if !in_code && line.starts_with('#') {
+                current.flush(&mut out);
+                current.title = Some(line.trim_start_matches('#').trim().to_string());
+            } else if !in_code && line.trim().is_empty() && current.tokens() >= self.max_tokens {
+                current.flush(&mut out);
+            }
+            current.push(i + 1, line);
+        }
+        current.flush(&mut out);
+        out
+    }
+}
+
+/// Blank-line separated paragraphs, merged until `max_tokens`
+pub struct ParagraphChunker {
+    pub max_tokens: usize,
+}
+
+impl Chunker for ParagraphChunker {
+    fn chunk(&self, text: &str) -> Vec<Chunk> {
+        let mut out = Vec::new();
+        let mut current = Builder::new();
+        let mut paragraph: Vec<(usize, &str)> = Vec::new();
+
+        let emit =
+            |paragraph: &mut Vec<(usize, &str)>, current: &mut Builder, out: &mut Vec<Chunk>| {
+                let tokens: usize = paragraph
+                    .iter()
+                    .map(|(_, l)| l.split_whitespace().count())
+                    .sum();
+                if current.tokens() > 0 && current.tokens() + tokens > self.max_tokens {
+                    current.flush(out);
+                }
+                if !current.lines.is_empty() {
+                    // Separator only; the line range still ends at the last text line
+                    current.lines.push(String::new());
+                }
+                for (n, l) in paragraph.drain(..) {
+                    current.push(n, l);
+                }
+            };
+
+        for (i, line) in text.lines().enumerate() {
+            if line.trim().is_empty() {
+                if !paragraph.is_empty() {
+                    emit(&mut paragraph, &mut current, &mut out);
+                }
+            } else {
+                paragraph.push((i + 1, line));
+            }
+        }
+        if !paragraph.is_empty() {
+            emit(&mut paragraph, &mut current, &mut out);
+        }
+        current.flush(&mut out);
+        out
+    }
+}
+
+/// Fixed windows of `size` tokens, each overlapping the previous by `overlap`
+pub struct TokenChunker {
+    pub size: usize,
+    pub overlap: usize,
+}
+
+impl Chunker for TokenChunker {
+    fn chunk(&self, text: &str) -> Vec<Chunk> {
+        let tokens: Vec<(usize, &str)> = text
+            .lines()
+            .enumerate()
+            .flat_map(|(i, line)| line.split_whitespace().map(move |t| (i + 1, t)))
+            .collect();
+
+        let step = self.size - self.overlap;
+        let mut out = Vec::new();
+        let mut start = 0;
+        while start < tokens.len() {
+            let window = &tokens[start..(start + self.size).min(tokens.len())];
+            out.push(Chunk {
+                text: window.iter().map(|(_, t)| *t).collect::<Vec<_>>().join(" "),
+                start_line: window[0].0,
+                end_line: window[window.len() - 1].0,
+                title: None,
+            });
+            if start + self.size >= tokens.len() {
+                break;
+            }
+            start += step;
+        }
+        out
+    }
+}
+
+/// Extends each chunk after the first back over the preceding lines until
+/// it repeats at least `tokens` tokens, so text near a boundary keeps its
+/// context in both chunks
+pub struct Overlap {
+    pub inner: Box<dyn Chunker>,
+    pub tokens: usize,
+}
+
+impl Chunker for Overlap {
+    fn chunk(&self, text: &str) -> Vec<Chunk> {
+        let lines: Vec<&str> = text.lines().collect();
+        let mut chunks = self.inner.chunk(text);
+        for i in 1..chunks.len() {
+            let floor = chunks[i - 1].start_line + 1;
+            let mut start = chunks[i].start_line;
+            let mut tokens = 0;
+            while tokens < self.tokens && start > floor {
+                start -= 1;
+                tokens += lines[start - 1].split_whitespace().count();
+            }
+            if start < chunks[i].start_line {
+                let lead = lines[start - 1..chunks[i].start_line - 1].join("\n");
+                chunks[i].text = format!("{}\n{}", lead.trim_start(), chunks[i].text);
+                chunks[i].start_line = start;
+            }
+        }
+        chunks
+    }
+}
+
+/// One chunk per top-level definition (function, class, impl, ...)
+pub struct CodeChunker {
+    pub max_tokens: usize,
+}
+
+impl Chunker for CodeChunker {
+    fn chunk(&self, text: &str) -> Vec<Chunk> {
+        let definition = Regex::new(
+            r"^(pub(\([^)]*\))?\s+)?(async\s+)?(export\s+)?(default\s+)?(fn|def|class|impl|struct|enum|trait|interface|function|func|type|const|mod)\b",
+        )
+        .unwrap();
+
+        let lines: Vec<&str> = text.lines().collect();
+        let mut out = Vec::new();
+        let mut current = Builder::new();
+
+        for (i, line) in lines.iter().enumerate() {
+            if definition.is_match(line) {
+                // Keep doc comments and decorators directly above with the definition
+                let mut lead = Vec::new();
+                while let Some(last) = current.lines.last() {
+                    let t = last.trim_start();
+                    let attached = ["///", "#[", "@", "/**", "*"]
+                        .iter()
+                        .any(|p| t.starts_with(p));
+                    if attached {
+                        lead.push(current.lines.pop().unwrap_or_default());
+                        current.end -= 1;
+                    } else {
+                        break;
+                    }
+                }
+                current.flush(&mut out);
+                lead.reverse();
+                for (j, l) in lead.iter().enumerate() {
+                    current.push(i + 1 - lead.len() + j, l);
+                }
+                current.title = Some(line.trim().trim_end_matches('{').trim().to_string());
+            } else if line.trim().is_empty() && current.tokens() >= self.max_tokens {
+                current.flush(&mut out);
+            }
+            current.push(i + 1, line);
+        }
+        current.flush(&mut out);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heading_chunker() {
+        let text = "# Intro\nHello there.\n\n## Setup\nRun `make`.\n";
+        let chunks = chunker("heading", 100, 0).unwrap().chunk(text);
+
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].title.as_deref(), Some("Intro"));
+        assert_eq!((chunks[1].start_line, chunks[1].end_line), (4, 5));
+        assert_eq!(chunks[1].text, "## Setup\nRun `make`.");
+    }
+
+    #[test]
+    fn test_token_chunker_overlaps() {
+        let text = "a b c d\ne f g";
+        let chunks = chunker("tokens", 4, 2).unwrap().chunk(text);
+
+        let texts: Vec<&str> = chunks.iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(texts, vec!["a b c d", "c d e f", "e f g"]);
+        assert_eq!((chunks[1].start_line, chunks[1].end_line), (1, 2));
+        assert!(chunker("tokens", 4, 4).is_err());
+    }
+
+    #[test]
+    fn test_line_overlap() {
+        let text = "# One
+first part here
+last words
+# Two
+next";
+        let chunks = chunker("heading", 100, 2).unwrap().chunk(text);
+
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[1].text, "last words\n# Two\nnext");
+        assert_eq!((chunks[1].start_line, chunks[1].end_line), (3, 5));
+        assert_eq!(chunks[1].title.as_deref(), Some("Two"));
+    }
+
+    #[test]
+    fn test_code_chunker_keeps_doc_comments() {
+        let text = "use std::io;\n\n/// Adds\nfn add() {}\n\nfn sub() {}\n";
+        let chunks = chunker("code", 100, 0).unwrap().chunk(text);
+
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[1].text, "/// Adds\nfn add() {}");
+        assert_eq!(chunks[1].start_line, 3);
+        assert_eq!(chunks[2].title.as_deref(), Some("fn sub() {}"));
+    }
+}
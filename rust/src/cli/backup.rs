@@ -0,0 +1,175 @@
+use crate::backup::{check_name, project_key, snapshot_name, S3Target, BACKUP_DIR};
+use crate::config::{find_roots_path, RootsConfig};
+use crate::error::Error;
+use crate::index::MemoryStore;
+use crate::lock::MaintenanceLock;
+use crate::memory::Memories;
+use crate::store::Store;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Run the backup command
+pub fn run_backup(remote: bool, list: bool) -> Result<(), Error> {
+    if list {
+        return list_backups(remote);
+    }
+
+    // Fail on a missing bucket before taking the snapshot
+    let target = if remote {
+        Some(S3Target::from_global_config()?)
+    } else {
+        None
+    };
+
+    let mem = Memories::open()?;
+    let dir = backup_dir(mem.roots_path())?;
+    let name = snapshot_name();
+    let path = dir.join(&name);
+
+    mem.snapshot(&path)?;
+    let count = MemoryStore::check_snapshot(&path).map_err(|e| {
+        let _ = fs::remove_file(&path);
+        format!("Failed to verify snapshot: {}", e)
+    })?;
+    println!("Saved {} ({} memories)", path.display(), count);
+
+    if let Some(target) = target {
+        let key = target.key(&project_key(mem.roots_path()), &name);
+        target.put(&key, &path)?;
+        println!("Uploaded to {}", target.location(&key));
+    }
+
+    Ok(())
+}
+
+/// Run the restore command
+pub fn run_restore(name: &str, remote: bool, force: bool, no_wait: bool) -> Result<(), Error> {
+    let roots_path = find_roots_path().ok_or(Error::NotInitialized)?;
+    if RootsConfig::new(roots_path.clone()).store_backend() != "sqlite" {
+        return Err(Error::Invalid("Restore only applies to the sqlite store".to_string()));
+    }
+    let dir = backup_dir(&roots_path)?;
+
+    let source = if remote {
+        check_name(name)?;
+        let target = S3Target::from_global_config()?;
+        let key = target.key(&project_key(&roots_path), name);
+        let partial = dir.join(format!("{}.part", name));
+        // A failed download leaves nothing behind
+        if let Err(e) = target.get(&key, &partial) {
+            let _ = fs::remove_file(&partial);
+            return Err(e.into());
+        }
+        let dest = dir.join(name);
+        fs::rename(&partial, &dest)
+            .map_err(|e| format!("Failed to save {}: {}", dest.display(), e))?;
+        println!("Downloaded {}", target.location(&key));
+        dest
+    } else if Path::new(name).is_file() {
+        PathBuf::from(name)
+    } else {
+        check_name(name)?;
+        dir.join(name)
+    };
+
+    if !source.is_file() {
+        return Err(Error::NotFound(format!(
+            "Snapshot not found: {} (see 'roots backup --list')",
+            source.display()
+        )));
+    }
+    let count = MemoryStore::check_snapshot(&source)
+        .map_err(|e| format!("Not a usable snapshot: {}", e))?;
+
+    if !force {
+        print!(
+            "Replace the current store with {} ({} memories)? [y/N] ",
+            name, count
+        );
+        io::stdout().flush().unwrap();
+
+        let mut input = String::new();
+        io::stdin().read_line(&mut input).unwrap();
+
+        if !input.trim().eq_ignore_ascii_case("y") {
+            println!("Cancelled.");
+            return Ok(());
+        }
+    }
+
+    // Copied next to the store first, so the store is swapped in one rename
+    // and an interrupted copy never leaves it half written
+    let db_path = roots_path.join("memory.db");
+    let staged = roots_path.join("memory.db.restore");
+    fs::copy(&source, &staged).map_err(|e| {
+        let _ = fs::remove_file(&staged);
+        format!("Failed to restore: {}", e)
+    })?;
+    let swapped = swap_in(&roots_path, &db_path, &staged, &dir, no_wait);
+    if swapped.is_err() {
+        let _ = fs::remove_file(&staged);
+    }
+    swapped?;
+
+    println!("Restored {} memories from {}", count, name);
+    Ok(())
+}
+
+/// Replace the store at `db_path` with `staged` under the maintenance lock,
+/// first saving what it replaces to `dir`
+fn swap_in(roots_path: &Path, db_path: &Path, staged: &Path, dir: &Path, no_wait: bool) -> Result<(), Error> {
+    let _lock = MaintenanceLock::acquire(roots_path, "restore", no_wait)?;
+
+    // Keep what is being replaced, in case the wrong snapshot was picked
+    if db_path.exists() {
+        let previous = dir.join(format!("pre-restore-{}", snapshot_name()));
+        MemoryStore::open(db_path)
+            .and_then(|store| store.snapshot(&previous))
+            .map_err(|e| format!("Failed to save the current store: {}", e))?;
+        println!("Saved the current store as {}", previous.display());
+    }
+
+    fs::rename(staged, db_path).map_err(|e| format!("Failed to restore: {}", e))?;
+    for suffix in ["-wal", "-shm"] {
+        let _ = fs::remove_file(roots_path.join(format!("memory.db{}", suffix)));
+    }
+    Ok(())
+}
+
+fn list_backups(remote: bool) -> Result<(), Error> {
+    let roots_path = find_roots_path().ok_or(Error::NotInitialized)?;
+
+    let names = if remote {
+        let target = S3Target::from_global_config()?;
+        let project = project_key(&roots_path);
+        println!("{}\n", target.location(&target.key(&project, "")));
+        target.list(&project)?
+    } else {
+        let dir = backup_dir(&roots_path)?;
+        let mut names: Vec<String> = fs::read_dir(&dir)
+            .map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?
+            .filter_map(|e| e.ok())
+            .map(|e| e.file_name().to_string_lossy().to_string())
+            .filter(|n| n.ends_with(".db"))
+            .collect();
+        names.sort();
+        names
+    };
+
+    if names.is_empty() {
+        println!("No backups yet. Create one with: roots backup");
+        return Ok(());
+    }
+    for name in names {
+        println!("{}", name);
+    }
+    Ok(())
+}
+
+fn backup_dir(roots_path: &Path) -> Result<PathBuf, Error> {
+    let dir = roots_path.join(BACKUP_DIR);
+    fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    Ok(dir)
+}
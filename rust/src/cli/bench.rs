@@ -0,0 +1,99 @@
+use crate::bench::{self, BenchResult};
+use crate::cli::{print_json, Output};
+use crate::embeddings::{Embedder, LiteEmbedder, ServerEmbedder};
+use crate::error::Error;
+use crate::memory::Memories;
+use crate::proto;
+use crate::types::{MemoryFilter, SortOrder};
+use std::time::Duration;
+
+/// Run `roots bench` - time each available embedder on recent memories
+pub fn run_bench(sample: usize, output: Output) -> Result<(), Error> {
+    let mem = Memories::open()?;
+    let memories = mem.list(&MemoryFilter::default(), sample, SortOrder::Recent)?;
+    if memories.is_empty() {
+        return Err(Error::NotFound(
+            "No memories to benchmark with. Add some with: roots remember \"...\"".to_string(),
+        ));
+    }
+    let texts: Vec<&str> = memories.iter().map(|m| m.content.as_str()).collect();
+    let avg_chars = texts.iter().map(|t| t.chars().count()).sum::<usize>() / texts.len();
+    let budget = mem.slow_query();
+
+    let mut embedders: Vec<(String, Box<dyn Embedder>)> =
+        vec![("lite".to_string(), Box::new(LiteEmbedder::new()))];
+    let client = ServerEmbedder::new();
+    let server = client.is_running();
+    if server {
+        let model = client.get_model().unwrap_or_else(|_| "unknown".to_string());
+        embedders.push((model, Box::new(client)));
+    }
+
+    if !output.is_json() {
+        println!(
+            "Embedding {} memories (avg {} chars), one at a time and in batches of {}\n",
+            texts.len(),
+            avg_chars,
+            bench::BATCH_SIZE
+        );
+        println!(
+            "{:28} {:>9} {:>9} {:>9} {:>9} {:>10}",
+            "model", "p50", "p90", "p99", "max", "batched/s"
+        );
+    }
+    let mut results = Vec::new();
+    for (model, embedder) in &embedders {
+        let result = bench::run(embedder.as_ref(), &texts)
+            .map_err(|e| format!("Failed to benchmark {}: {}", model, e))?;
+        if !output.is_json() {
+            print_result(model, &result, budget);
+        }
+        results.push(proto::BenchRun {
+            model: model.clone(),
+            server: embedder.is_remote(),
+            dimensions: result.dimensions,
+            p50_ms: millis(result.p50),
+            p90_ms: millis(result.p90),
+            p99_ms: millis(result.p99),
+            max_ms: millis(result.max),
+            texts_per_sec: result.throughput,
+        });
+    }
+
+    if output.is_json() {
+        return print_json(&proto::Bench {
+            texts: texts.len(),
+            budget_ms: budget.as_millis() as u64,
+            results,
+        });
+    }
+    if !server {
+        println!("\nThe embedding server isn't running; start it with 'roots server start' to time its model too.");
+    }
+    if !budget.is_zero() {
+        println!(
+            "\nHooks embed one prompt per message: pick a model whose p99 leaves room in slow_query_ms ({}ms).",
+            budget.as_millis()
+        );
+    }
+    Ok(())
+}
+
+fn print_result(model: &str, result: &BenchResult, budget: Duration) {
+    let ms = |d: Duration| format!("{:.2}ms", millis(d));
+    let over = !budget.is_zero() && result.p99 > budget;
+    println!(
+        "{:28} {:>9} {:>9} {:>9} {:>9} {:>10.0}{}",
+        model,
+        ms(result.p50),
+        ms(result.p90),
+        ms(result.p99),
+        ms(result.max),
+        result.throughput,
+        if over { "  over budget" } else { "" }
+    );
+}
+
+fn millis(d: Duration) -> f64 {
+    d.as_secs_f64() * 1000.0
+}
@@ -0,0 +1,219 @@
+use crate::error::Error;
+use crate::hook::HookInput;
+use crate::memory::Memories;
+use crate::transcript::{self, Message};
+use crate::types::NewMemory;
+use crate::worthiness;
+use regex::Regex;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Run the capture command: store the decisions and lessons of a session
+/// transcript, tagged with its session id. With `hook_stdin`, the transcript
+/// path and session id come from a Claude Code hook payload.
+pub fn run_capture(path: Option<String>, hook_stdin: bool) -> Result<(), Error> {
+    let hook = hook_stdin.then(HookInput::read).transpose()?.unwrap_or_default();
+    let Some(path) = path.or(hook.transcript_path) else {
+        return Err(Error::Invalid(
+            "No transcript: pass --transcript or a hook payload with transcript_path".to_string(),
+        ));
+    };
+
+    let mem = match Memories::open() {
+        Ok(mem) => mem,
+        // Global hooks run in projects without a store
+        Err(_) if hook_stdin => return Ok(()),
+        Err(e) => return Err(e),
+    };
+
+    let transcript = transcript::read(&path)?;
+    let session = hook
+        .session_id
+        .or(transcript.session_id)
+        .or_else(crate::hook::env_session);
+    let candidates = match mem.capture_command() {
+        Some(command) => run_extractor(command, &transcript.messages)?,
+        None => transcript::extract(&transcript.messages),
+    };
+
+    let threshold = mem.capture_threshold();
+    let assessments = mem.assess_candidates(&candidates, threshold)?;
+    let mut stored = 0;
+    for (content, assessment) in candidates.iter().zip(&assessments) {
+        if assessment.score < threshold {
+            worthiness::log_rejection(mem.roots_path(), content, assessment, threshold)?;
+            continue;
+        }
+
+        let mut tags = mem.auto_tags(content)?;
+        if let Some(session) = &session {
+            tags.insert(0, format!("session-{}", session));
+        }
+        let mut memory = NewMemory {
+            content: content.clone(),
+            confidence: worthiness::AUTO_CONFIDENCE,
+            tags,
+            source: Some(path.clone()),
+            session_id: session.clone(),
+            ..Default::default()
+        };
+        mem.apply_write_policy(&mut memory, "hook");
+        let id = mem.remember(&memory)?;
+        println!("Remembered [{}] {}", id, preview(content));
+        stored += 1;
+    }
+
+    println!(
+        "Captured {} of {} candidates from {} (rejected: .roots/{})",
+        stored,
+        candidates.len(),
+        path,
+        worthiness::REJECTED_LOG
+    );
+    Ok(())
+}
+
+/// Run `capture --commit`: remember a commit's message, tagged `commit` and
+/// with its branch, if it matches `commit_pattern`. Run by the post-commit
+/// hook `roots hooks --git` installs.
+pub fn run_capture_commit(rev: &str) -> Result<(), Error> {
+    let mem = Memories::open()?;
+    let log = git(&["log", "-1", "--format=%H%x00%B", rev])?;
+    let (sha, message) = log.split_once('\0').ok_or("Unexpected git log output")?;
+    let message = message.trim();
+    let branch = git(&["rev-parse", "--abbrev-ref", "HEAD"])?;
+
+    if let Some(pattern) = mem.commit_pattern() {
+        let re = Regex::new(pattern).map_err(|e| format!("Invalid commit_pattern: {}", e))?;
+        if !re.is_match(message) {
+            println!("Skipped {}: message doesn't match commit_pattern", short(sha));
+            return Ok(());
+        }
+    }
+
+    let source = format!("commit:{}", sha);
+    if message.is_empty() || mem.has_source(&source)? {
+        return Ok(());
+    }
+
+    let mut tags = vec!["commit".to_string()];
+    // Detached HEAD has no branch to record
+    if branch != "HEAD" {
+        tags.push(format!("branch-{}", branch));
+    }
+    let mut memory = NewMemory {
+        content: message.to_string(),
+        confidence: 0.5,
+        tags,
+        source: Some(source),
+        session_id: crate::hook::env_session(),
+        ..Default::default()
+    };
+    mem.apply_write_policy(&mut memory, "hook");
+    let id = mem.remember(&memory)?;
+    println!("Remembered [{}] from commit {}", id, short(sha));
+    Ok(())
+}
+
+/// Trimmed stdout of a git command
+fn git(args: &[&str]) -> Result<String, Error> {
+    let output = Command::new("git")
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to run git: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn short(sha: &str) -> &str {
+    sha.get(..7).unwrap_or(sha)
+}
+
+/// Candidates from `capture_command`: the conversation goes in on stdin,
+/// one candidate per line comes out
+fn run_extractor(command: &str, messages: &[Message]) -> Result<Vec<String>, Error> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run capture_command: {}", e))?;
+
+    // Feed stdin from a thread: an extractor that writes while it reads would
+    // otherwise fill the stdout pipe and wait on us forever
+    let input = transcript::render(messages);
+    let stdin = child.stdin.take();
+    let writer = std::thread::spawn(move || match stdin {
+        Some(mut stdin) => match stdin.write_all(input.as_bytes()) {
+            // The extractor is free to stop reading early
+            Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => Ok(()),
+            result => result,
+        },
+        None => Ok(()),
+    });
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("Failed to run capture_command: {}", e))?;
+    writer
+        .join()
+        .map_err(|_| "Failed to write to capture_command".to_string())?
+        .map_err(|e| format!("Failed to write to capture_command: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("capture_command failed: {}", output.status).into());
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| line.trim().trim_start_matches("- ").trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect())
+}
+
+fn preview(content: &str) -> String {
+    let line = content.replace('\n', " ");
+    match line.char_indices().nth(80) {
+        Some((i, _)) => format!("{}...", &line[..i]),
+        None => line,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conversation(size: usize) -> Vec<Message> {
+        vec![Message {
+            role: "user".to_string(),
+            text: "x".repeat(size),
+        }]
+    }
+
+    #[test]
+    fn test_extractor_that_echoes_a_large_transcript() {
+        // Larger than a pipe buffer in both directions
+        let candidates = run_extractor("cat", &conversation(1 << 20)).unwrap();
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].len(), "user: ".len() + (1 << 20));
+    }
+
+    #[test]
+    fn test_extractor_that_ignores_stdin() {
+        let candidates =
+            run_extractor("echo '- Use tabs'; echo", &conversation(1 << 20)).unwrap();
+        assert_eq!(candidates, vec!["Use tabs".to_string()]);
+    }
+
+    #[test]
+    fn test_failing_extractor() {
+        let err = run_extractor("cat >/dev/null; exit 3", &conversation(10)).unwrap_err();
+        assert!(err.to_string().contains("capture_command failed"));
+    }
+}
@@ -1,19 +1,42 @@
 use crate::config::{
-    find_roots_path, resolve_model, RootsConfig, DEFAULT_MODEL, SUGGESTED_MODELS,
+    active_profile, find_roots_path, get_global_config, global_config_file, profile_name, profiles, resolve_model,
+    resolve_setting, set_global_config, setting_spec, unset_global_config, validate_setting,
+    RootsConfig, Scope, DEFAULT_MODEL, SETTINGS, SUGGESTED_MODELS,
 };
+use crate::error::Error;
+use std::collections::HashMap;
 
 /// Run the config command
 pub fn run_config(
     key: Option<&str>,
     value: Option<&str>,
     list_models: bool,
-) -> Result<(), String> {
+    list_keys: bool,
+    list_profiles: bool,
+    unset: Option<&str>,
+    global: bool,
+) -> Result<(), Error> {
     if list_models {
         print_models()?;
         return Ok(());
     }
+    if list_keys {
+        print_keys();
+        return Ok(());
+    }
+    if list_profiles {
+        print_profiles();
+        return Ok(());
+    }
+    if let Some(key) = unset {
+        return run_unset(key, global);
+    }
+
+    if global {
+        return run_global_config(key, value);
+    }
 
-    let roots_path = find_roots_path().ok_or("No .roots directory found. Run 'roots init' first.")?;
+    let roots_path = find_roots_path().ok_or(Error::NotInitialized)?;
     let mut config = RootsConfig::new(roots_path);
 
     match (key, value) {
@@ -32,7 +55,7 @@ pub fn run_config(
                 .unwrap_or(&model);
 
             println!("  model: {}", alias);
-            if alias != &model_name {
+            if *alias != model_name {
                 println!("    -> {}", model_name);
             }
             println!("    type: {}", model_type);
@@ -54,6 +77,7 @@ pub fn run_config(
         (Some(k), Some(v)) => {
             // Set key=value
             if k == "model" || k == "embedding_model" {
+                validate_setting(k, v, false).map_err(Error::Invalid)?;
                 let (model_name, model_type) = resolve_model(v);
 
                 if model_type == "lite" {
@@ -67,19 +91,189 @@ pub fn run_config(
 
                 config.set_embedding_model(v).map_err(|e| format!("Failed to save: {}", e))?;
             } else {
+                validate_setting(k, v, false).map_err(Error::Invalid)?;
                 config.set(k, v).map_err(|e| format!("Failed to save: {}", e))?;
             }
             println!("Set {} = {}", k, v);
         }
         (None, Some(_)) => {
-            return Err("Key required when setting a value".to_string());
+            return Err(Error::Invalid("Key required when setting a value".to_string()));
+        }
+    }
+
+    Ok(())
+}
+
+/// Remove `key` from the project config, or the global one (or selected
+/// profile) with `global`. Unknown keys can be removed too, to clean up
+/// after typos.
+fn run_unset(key: &str, global: bool) -> Result<(), Error> {
+    let key = if key == "model" { "embedding_model" } else { key };
+    let removed = if global {
+        unset_global_config(key)
+    } else {
+        let roots_path = find_roots_path().ok_or(Error::NotInitialized)?;
+        RootsConfig::new(roots_path).unset(key)
+    }
+    .map_err(|e| format!("Failed to save: {}", e))?;
+
+    let profile = profile_name().filter(|_| global);
+    match (removed, setting_spec(key).and_then(|s| s.default)) {
+        (false, _) => println!("{} was not set", key),
+        (true, _) if profile.is_some() => {
+            println!("Unset {} in profile {}", key, profile.unwrap_or_default())
+        }
+        (true, Some(default)) => println!("Unset {} (now the default: {})", key, default),
+        (true, None) => println!("Unset {}", key),
+    }
+    Ok(())
+}
+
+/// Every recognized key with its type, default, and where it is set
+fn print_keys() {
+    println!("  {:<26} {:<8} {:<24} {:<12} ENV", "KEY", "SCOPE", "TYPE", "DEFAULT");
+    for spec in SETTINGS {
+        let scope = match spec.scope {
+            Scope::Project => "project".to_string(),
+            Scope::Global => "global".to_string(),
+            Scope::Env => "env".to_string(),
+        };
+        let default = spec.default.unwrap_or("-");
+        println!(
+            "  {:<26} {:<8} {:<24} {:<12} {}",
+            spec.key,
+            scope,
+            spec.kind.describe(),
+            default,
+            spec.env_var()
+        );
+    }
+    println!("\nSet project keys with 'roots config <key> <value>', global ones with --global;");
+    println!("project keys set with --global are the defaults for every project.");
+}
+
+/// View or set the global `config.yaml`, shared by every project, or
+/// the selected profile in it
+fn run_global_config(key: Option<&str>, value: Option<&str>) -> Result<(), Error> {
+    let key = key.map(|k| if k == "model" { "embedding_model" } else { k });
+    let profile = profile_name();
+    let config = || match &profile {
+        Some(name) => profiles().remove(name).unwrap_or_default(),
+        None => get_global_config(),
+    };
+    match (key, value) {
+        (None, None) => {
+            match &profile {
+                Some(name) => println!("Profile {}:\n", name),
+                None => println!("Global configuration:\n"),
+            }
+            print_settings(&config());
+        }
+        (Some(k), None) => match config().get(k) {
+            Some(v) => println!("{}: {}", k, masked(k, v)),
+            None => println!("{}: (not set)", k),
+        },
+        (Some(k), Some(v)) => {
+            validate_setting(k, v, true).map_err(Error::Invalid)?;
+            set_global_config(k, v).map_err(|e| format!("Failed to save: {}", e))?;
+            match &profile {
+                Some(name) => println!("Set {} = {} in profile {}", k, masked(k, v), name),
+                None => println!("Set {} = {}", k, masked(k, v)),
+            }
+        }
+        (None, Some(_)) => {
+            return Err(Error::Invalid("Key required when setting a value".to_string()));
+        }
+    }
+
+    Ok(())
+}
+
+fn print_settings(config: &HashMap<String, String>) {
+    let mut keys: Vec<_> = config.keys().collect();
+    keys.sort();
+    for k in keys {
+        println!("  {}: {}", k, masked(k, &config[k]));
+    }
+}
+
+/// Every profile in the global config with its settings, the selected one
+/// marked
+fn print_profiles() {
+    let all = profiles();
+    if all.is_empty() {
+        println!("No profiles. Create one with: roots --profile <name> config --global <key> <value>");
+        return;
+    }
+    let selected = profile_name();
+    for (name, settings) in &all {
+        let marker = if selected.as_deref() == Some(name.as_str()) { " *" } else { "" };
+        println!("{}{}", name, marker);
+        print_settings(settings);
+    }
+}
+
+/// Whether `key` holds nested settings, like `ranking:`
+fn is_section(key: &str) -> bool {
+    SETTINGS
+        .iter()
+        .any(|s| s.key.split_once('.').is_some_and(|(section, _)| section == key))
+}
+
+/// Run the explain-config command - every setting with the layer it came from
+pub fn run_explain_config() -> Result<(), Error> {
+    let roots_path = find_roots_path();
+    let project = roots_path.clone().map(RootsConfig::new);
+    let global = get_global_config();
+    let profile = active_profile().map_err(Error::Invalid)?;
+
+    println!("Effective configuration\n");
+    match &roots_path {
+        Some(path) => println!("  project config: {}", path.join("_config.yaml").display()),
+        None => println!("  project config: (no .roots found)"),
+    }
+    println!("  global config:  {}", global_config_file().display());
+    if let Some(profile) = &profile {
+        println!("  profile:        {}", profile.name);
+    }
+    println!();
+
+    println!("  {:<26} {:<24} SOURCE", "KEY", "VALUE");
+    for spec in SETTINGS {
+        if spec.scope == Scope::Project && project.is_none() {
+            continue;
         }
+        let (value, source) = match resolve_setting(spec, project.as_ref(), &global, profile.as_ref()) {
+            Some((value, source)) => (masked(spec.key, &value).to_string(), source.to_string()),
+            None => ("(not set)".to_string(), "-".to_string()),
+        };
+        println!("  {:<26} {:<24} {}", spec.key, value, source);
+    }
+
+    let unknown: Vec<String> = project
+        .iter()
+        .flat_map(|p| p.keys())
+        .filter(|k| setting_spec(k).is_none() && !is_section(k))
+        .collect();
+    if !unknown.is_empty() {
+        println!("\nUnrecognized project keys (ignored): {}", unknown.join(", "));
     }
 
+    println!("\nCommand-line flags override these for the command they are passed to.");
     Ok(())
 }
 
-fn print_models() -> Result<(), String> {
+/// Hide credentials when echoing config values
+fn masked<'a>(key: &str, value: &'a str) -> &'a str {
+    let secret = setting_spec(key).is_some_and(|s| s.secret);
+    if secret || key.contains("secret") || key.contains("token") {
+        "********"
+    } else {
+        value
+    }
+}
+
+fn print_models() -> Result<(), Error> {
     let roots_path = find_roots_path();
     let current = roots_path
         .as_ref()
@@ -87,10 +281,7 @@ fn print_models() -> Result<(), String> {
         .unwrap_or_else(|| DEFAULT_MODEL.to_string());
 
     println!("Available embedding models:\n");
-    println!(
-        "{:2} {:12} {:10} {}",
-        "", "Alias", "Size", "Description"
-    );
+    println!("   {:12} {:10} Description", "Alias", "Size");
     println!("{}", "-".repeat(60));
 
     for model in SUGGESTED_MODELS {
@@ -1,57 +1,378 @@
+use crate::error::Error;
+use crate::hook::HookInput;
 use crate::memory::Memories;
+use crate::proto;
+use crate::source::Source;
+use crate::types::{Memory, MemoryFilter, SearchResult, SortOrder, DOC_KIND, MEMORY_KINDS};
 
-/// Run the prime command - output context for Claude Code hooks
-pub fn run_prime() -> Result<(), String> {
-    let mem = match Memories::open() {
-        Ok(m) => m,
-        Err(_) => {
-            // Silent exit if no memory store
-            return Ok(());
-        }
-    };
+/// Memories shown per kind section in prime output
+const PRIME_PER_KIND: usize = 5;
 
-    let stats = mem.stats()?;
+/// Section of prime output holding high-confidence untyped memories
+const KEY_SECTION: &str = "key";
 
-    if stats.total_memories == 0 {
-        return Ok(());
+/// Section of prime output holding memories created or updated lately
+const RECENT_SECTION: &str = "recent";
+
+/// Memories shown in the recent changes section
+const PRIME_RECENT: usize = 10;
+
+/// Run the prime command - output context for Claude Code hooks, or as
+/// records in another `format`. With `hook_stdin`, the hook payload is read
+/// from stdin and answered in the shape its event expects.
+pub fn run_prime(format: ContextFormat, hook_stdin: bool) -> Result<(), Error> {
+    let hook = hook_stdin.then(HookInput::read).transpose()?;
+    let output = prime(format)?;
+    match hook {
+        Some(hook) => print!("{}", hook.respond(&output)),
+        None => print!("{}", output),
     }
+    Ok(())
+}
 
-    println!("# Memory Context\n");
-    println!("Available: {} memories\n", stats.total_memories);
+/// The prime output for the current store, empty when there is none
+pub fn prime(format: ContextFormat) -> Result<String, Error> {
+    match Memories::open() {
+        Ok(mem) => prime_store(&mem, format),
+        // Silent exit if no memory store
+        Err(_) => Ok(format.empty()),
+    }
+}
+
+/// The prime output for `mem`
+pub fn prime_store(mem: &Memories, format: ContextFormat) -> Result<String, Error> {
+    let count = mem.count()?;
+    let global = mem.global_count()?.unwrap_or(0);
+
+    if count == 0 && global == 0 {
+        return Ok(format.empty());
+    }
 
-    // Show tags
     let tags = mem.tags()?;
+    let context = mem.context_filter()?;
+
+    // Typed memories first, grouped by kind, then high-confidence untyped ones
+    let mut sections: Vec<(&str, Vec<Memory>)> = Vec::new();
+    for kind in MEMORY_KINDS.iter().filter(|k| **k != DOC_KIND) {
+        let filter = MemoryFilter {
+            kind: Some(kind.to_string()),
+            ..context.clone()
+        };
+        sections.push((kind, mem.list_layered(&filter, PRIME_PER_KIND, SortOrder::Recent)?));
+    }
+    let top: Vec<_> = mem
+        .recall("", &context, 5)?
+        .into_iter()
+        .filter(|r| r.memory.confidence >= 0.7 && r.memory.kind.is_none())
+        .map(|r| r.memory)
+        .collect();
+    sections.push((KEY_SECTION, top));
+
+    // What was learned lately, leaving out memories already listed
+    if let Some(recent) = mem.prime_recent() {
+        let filter = MemoryFilter {
+            since: Some(crate::dates::from_now(-recent)?),
+            ..context.clone()
+        };
+        let listed: Vec<i64> = sections.iter().flat_map(|(_, ms)| ms.iter().map(|m| m.id)).collect();
+        let changed = mem
+            .list_layered(&filter, PRIME_RECENT + listed.len(), SortOrder::Recent)?
+            .into_iter()
+            .filter(|m| !listed.contains(&m.id) && m.kind.as_deref() != Some(DOC_KIND))
+            .take(PRIME_RECENT)
+            .collect();
+        sections.push((RECENT_SECTION, changed));
+    }
+
+    if format != ContextFormat::Markdown {
+        let records = sections
+            .iter()
+            .flat_map(|(section, memories)| {
+                memories
+                    .iter()
+                    .map(move |m| record(m, None, Some(section), CONTENT_CHARS))
+            })
+            .collect();
+        let topics = tags.iter().map(|(t, _)| t.clone()).collect();
+        return format.prime(count, global, topics, records);
+    }
+
+    let mut output = String::from("# Memory Context\n\n");
+    if global > 0 {
+        output.push_str(&format!("Available: {} memories (+{} global)\n\n", count, global));
+    } else {
+        output.push_str(&format!("Available: {} memories\n\n", count));
+    }
+
+    // Show tags
     if !tags.is_empty() {
-        println!("Topics: {}\n", tags.iter().map(|(t, _)| t.as_str()).collect::<Vec<_>>().join(", "));
+        let topics = tags.iter().map(|(t, _)| t.as_str()).collect::<Vec<_>>().join(", ");
+        output.push_str(&format!("Topics: {}\n\n", topics));
     }
 
-    // Show high-confidence memories
-    let top = mem.recall("", 5)?;
+    for (section, memories) in &sections {
+        if memories.is_empty() {
+            continue;
+        }
+        let heading = match *section {
+            KEY_SECTION => "Key Memories".to_string(),
+            RECENT_SECTION => "Recent Changes".to_string(),
+            kind => kind_heading(kind),
+        };
+        output.push_str(&format!("## {}\n\n", heading));
+        for m in memories {
+            output.push_str(&prime_line(m));
+        }
+        output.push('\n');
+    }
+
+    output.push_str("Use `roots recall <query>` to search memories.\n");
+
+    Ok(output)
+}
+
+/// Section heading for a memory kind, e.g. "decision" -> "Decisions"
+fn kind_heading(kind: &str) -> String {
+    let mut chars = kind.chars();
+    match chars.next() {
+        Some(first) => format!("{}{}s", first.to_uppercase(), chars.as_str()),
+        None => String::new(),
+    }
+}
+
+/// A single memory as a prime bullet
+fn prime_line(m: &Memory) -> String {
+    let preview: String = m.content.chars().take(150).collect();
+    format!(
+        "- [{}]{} ({:.0}%) {}\n",
+        m.id,
+        crate::cli::memory::global_marker(m),
+        m.confidence * 100.0,
+        preview.replace('\n', " ")
+    )
+}
+
+/// Results `context` shows without a token budget
+const DEFAULT_CONTEXT_LIMIT: usize = 3;
+
+/// Results `context` considers under a token budget, which decides how many fit
+const BUDGET_CONTEXT_LIMIT: usize = 10;
+
+/// Characters of each memory shown without a token budget
+const CONTENT_CHARS: usize = 500;
+
+/// Rough characters per token of English prose and code
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Tokens of content worth showing from a memory cut to fit the budget
+const MIN_TRUNCATED_TOKENS: usize = 25;
+
+/// How `context` finds and prints memories
+pub struct ContextOptions {
+    /// Search mode: tags, lite, or semantic
+    pub mode: String,
+    /// Maximum results (default: 3, or 10 under a token budget)
+    pub limit: Option<usize>,
+    /// Minimum score (default: `context_threshold`)
+    pub threshold: Option<f64>,
+    pub rerank: bool,
+    pub expand: bool,
+    /// Token budget for the output (default: `context_max_tokens`)
+    pub max_tokens: Option<usize>,
+    /// Exclusions to apply on top of the context filter
+    pub exclude: MemoryFilter,
+    pub format: ContextFormat,
+    /// Read the prompt from a Claude Code hook payload on stdin and answer
+    /// in the hook's JSON shape
+    pub hook_stdin: bool,
+}
+
+/// How `context` and `prime` print memories: markdown for Claude Code
+/// hooks, or records other agent frameworks can parse and template
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContextFormat {
+    Markdown,
+    Json,
+    Xml,
+    /// Only the content of each memory, a paragraph each
+    Plain,
+}
+
+impl ContextFormat {
+    pub fn parse(format: &str) -> Result<Self, String> {
+        match format {
+            "markdown" => Ok(Self::Markdown),
+            "json" => Ok(Self::Json),
+            "xml" => Ok(Self::Xml),
+            "plain" => Ok(Self::Plain),
+            _ => Err("expected markdown, json, xml or plain".to_string()),
+        }
+    }
+
+    /// Output with no memories; hooks get nothing, parsers an empty document
+    fn empty(self) -> String {
+        match self {
+            Self::Markdown | Self::Plain => String::new(),
+            Self::Json | Self::Xml => self.document(&[]),
+        }
+    }
+
+    /// `context` output for results, each with at most the given number of
+    /// characters of its content
+    fn document(self, entries: &[(&SearchResult, usize)]) -> String {
+        let records = || entries.iter().map(|(r, max_chars)| record(&r.memory, Some(r.score), None, *max_chars));
+        match self {
+            Self::Markdown => {
+                let mut output = String::from("# Relevant Memories\n\n");
+                for (r, max_chars) in entries {
+                    output.push_str(&format_entry(r, *max_chars));
+                }
+                output
+            }
+            Self::Json => {
+                let context = proto::Context {
+                    version: proto::VERSION,
+                    memories: records().collect(),
+                };
+                serde_json::to_string_pretty(&context).unwrap_or_default() + "\n"
+            }
+            Self::Xml => format!("<memories>\n{}</memories>\n", records().map(|r| record_xml(&r)).collect::<String>()),
+            Self::Plain => records().map(|r| record_plain(&r)).collect(),
+        }
+    }
 
-    if !top.is_empty() {
-        println!("## Key Memories\n");
-        for r in top.iter().filter(|r| r.memory.confidence >= 0.7) {
-            let preview: String = r.memory.content.chars().take(150).collect();
-            println!("- [{}] ({:.0}%) {}", r.memory.id, r.memory.confidence * 100.0, preview.replace('\n', " "));
+    /// `prime` output other than markdown
+    fn prime(
+        self,
+        available: usize,
+        global: usize,
+        topics: Vec<String>,
+        records: Vec<proto::ContextMemory>,
+    ) -> Result<String, Error> {
+        match self {
+            Self::Json => {
+                let prime = proto::Prime {
+                    version: proto::VERSION,
+                    available,
+                    global,
+                    topics,
+                    memories: records,
+                };
+                serde_json::to_string_pretty(&prime)
+                    .map(|json| json + "\n")
+                    .map_err(|e| format!("Failed to serialize: {}", e).into())
+            }
+            Self::Xml => {
+                let mut xml = format!(
+                    "<memories available=\"{}\" global=\"{}\" topics=\"{}\">\n",
+                    available,
+                    global,
+                    xml_escape(&topics.join(", "))
+                );
+                records.iter().for_each(|r| xml.push_str(&record_xml(r)));
+                xml.push_str("</memories>\n");
+                Ok(xml)
+            }
+            _ => Ok(records.iter().map(record_plain).collect()),
         }
     }
+}
+
+/// A memory as `context` and `prime` emit it in json, xml, and plain, with
+/// at most `max_chars` of its content
+fn record(memory: &Memory, score: Option<f64>, section: Option<&str>, max_chars: usize) -> proto::ContextMemory {
+    let content: String = memory.content.chars().take(max_chars).collect();
+    proto::ContextMemory {
+        id: memory.id,
+        score,
+        confidence: memory.confidence,
+        kind: memory.kind.clone(),
+        tags: memory.tags.clone(),
+        source: memory.source.as_deref().map(|s| Source::parse(s).to_string()),
+        global: memory.global,
+        section: section.map(String::from),
+        truncated: content.len() < memory.content.len(),
+        content,
+    }
+}
+
+fn record_xml(r: &proto::ContextMemory) -> String {
+    let mut attrs = format!("id=\"{}\"", r.id);
+    if let Some(score) = r.score {
+        attrs.push_str(&format!(" score=\"{:.3}\"", score));
+    }
+    attrs.push_str(&format!(" confidence=\"{:.2}\"", r.confidence));
+    if let Some(kind) = &r.kind {
+        attrs.push_str(&format!(" kind=\"{}\"", xml_escape(kind)));
+    }
+    if !r.tags.is_empty() {
+        attrs.push_str(&format!(" tags=\"{}\"", xml_escape(&r.tags.join(", "))));
+    }
+    if let Some(source) = &r.source {
+        attrs.push_str(&format!(" source=\"{}\"", xml_escape(source)));
+    }
+    if r.global {
+        attrs.push_str(" global=\"true\"");
+    }
+    if let Some(section) = &r.section {
+        attrs.push_str(&format!(" section=\"{}\"", section));
+    }
+    if r.truncated {
+        attrs.push_str(" truncated=\"true\"");
+    }
+    format!("  <memory {}>{}</memory>\n", attrs, xml_escape(&r.content))
+}
+
+fn record_plain(r: &proto::ContextMemory) -> String {
+    let ellipsis = if r.truncated { "..." } else { "" };
+    format!("{}{}\n\n", r.content, ellipsis)
+}
 
-    println!("\nUse `roots recall <query>` to search memories.");
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Run the context command - find relevant memories for a prompt, leaving
+/// out the exclusions in `exclude`
+pub fn run_context(prompt: Option<&str>, options: ContextOptions) -> Result<(), Error> {
+    if !options.hook_stdin {
+        print!("{}", context(prompt.unwrap_or_default(), options)?);
+        return Ok(());
+    }
 
+    let hook = HookInput::read()?;
+    let output = match hook.prompt.as_deref().or(prompt) {
+        Some(prompt) => context(prompt, options)?,
+        None => String::new(),
+    };
+    print!("{}", hook.respond(&output));
     Ok(())
 }
 
-/// Run the context command - find relevant memories for a prompt
-pub fn run_context(prompt: &str, mode: &str, limit: usize, threshold: f64) -> Result<(), String> {
+fn context(prompt: &str, options: ContextOptions) -> Result<String, Error> {
+    let _span = tracing::info_span!("context", mode = %options.mode).entered();
     let mem = match Memories::open() {
+        // Silent exit if no memory store
         Ok(m) => m,
-        Err(_) => {
-            // Silent exit if no memory store
-            return Ok(());
-        }
+        Err(_) => return Ok(options.format.empty()),
     };
 
-    let results = match mode {
+    let context = MemoryFilter {
+        exclude_tags: options.exclude.exclude_tags,
+        exclude_text: options.exclude.exclude_text,
+        ..mem.context_filter()?
+    };
+    let started = std::time::Instant::now();
+    let budget = options.max_tokens.or(mem.context_max_tokens());
+    let limit = options.limit.unwrap_or(match budget {
+        Some(_) => BUDGET_CONTEXT_LIMIT,
+        None => DEFAULT_CONTEXT_LIMIT,
+    });
+
+    let results = match options.mode.as_str() {
         "tags" => {
             // Extract words from prompt and match against tags
             let words: Vec<&str> = prompt.split_whitespace().collect();
@@ -68,51 +389,167 @@ pub fn run_context(prompt: &str, mode: &str, limit: usize, threshold: f64) -> Re
                 // Get memories with matching tags
                 let mut all = Vec::new();
                 for tag in &matching_tags {
-                    all.extend(mem.recall_by_tag(tag, limit)?);
+                    let filter = MemoryFilter {
+                        tag: Some(tag.clone()),
+                        ..context.clone()
+                    };
+                    all.extend(mem.list_layered(&filter, limit, SortOrder::Recent)?);
                 }
                 // Convert to SearchResult with score 1.0
                 all.into_iter()
                     .take(limit)
-                    .map(|m| crate::types::SearchResult { memory: m, score: 1.0 })
+                    .map(|m| SearchResult {
+                        memory: m,
+                        score: 1.0,
+                        parts: None,
+                    })
                     .collect()
             }
         }
         "lite" | "semantic" => {
             // Both use embedding search (lite embedder or server)
-            mem.recall(prompt, limit * 2)?
+            let query = if options.expand || mem.expand_by_default() {
+                mem.expand_query(prompt, &context)?
+            } else {
+                prompt.to_string()
+            };
+            // The cross-encoder reads the prompt as written
+            if options.rerank || mem.rerank_by_default() {
+                let mut results = mem.recall(&query, &context, mem.rerank_candidates(limit))?;
+                crate::cli::memory::report_recall_timing(&mem);
+                // Hooks fall back to embedding order rather than fail
+                if let Err(e) = mem.rerank_context(prompt, &mut results) {
+                    eprintln!("Warning: {}", e);
+                }
+                results
+            } else {
+                let results = mem.recall(&query, &context, limit * 2)?;
+                crate::cli::memory::report_recall_timing(&mem);
+                results
+            }
         }
         _ => Vec::new(),
     };
 
+    let threshold = options.threshold.unwrap_or(mem.context_threshold());
     let filtered: Vec<_> = results
         .into_iter()
         .filter(|r| r.score >= threshold)
         .take(limit)
         .collect();
 
-    if filtered.is_empty() {
-        return Ok(());
-    }
+    mem.log_query("context", prompt, filtered.iter().map(|r| r.score), started);
 
-    println!("# Relevant Memories\n");
+    let (output, shown) = render(filtered, budget, options.format);
+    if shown.is_empty() {
+        return Ok(options.format.empty());
+    }
 
-    for r in filtered {
-        println!("## [{}] (relevance: {:.0}%)", r.memory.id, r.score * 100.0);
+    mem.record_hits(&shown)?;
+    Ok(output)
+}
 
-        if !r.memory.tags.is_empty() {
-            println!("*Tags: {}*\n", r.memory.tags.join(", "));
+/// The `context` output for `results`, and the results it shows. Under a
+/// budget, results are added best first until it runs out; the first that
+/// doesn't fit is cut short if enough of it fits to be useful.
+fn render(
+    results: Vec<SearchResult>,
+    budget: Option<usize>,
+    format: ContextFormat,
+) -> (String, Vec<SearchResult>) {
+    let mut entries: Vec<(&SearchResult, usize)> = Vec::new();
+    for r in &results {
+        let Some(budget) = budget else {
+            entries.push((r, CONTENT_CHARS));
+            continue;
+        };
+        entries.push((r, usize::MAX));
+        if estimate_tokens(&format.document(&entries)) <= budget {
+            continue;
         }
+        entries.pop();
+        entries.push((r, 0));
+        let content_tokens = budget.saturating_sub(estimate_tokens(&format.document(&entries)));
+        entries.pop();
+        if content_tokens >= MIN_TRUNCATED_TOKENS {
+            entries.push((r, content_tokens * CHARS_PER_TOKEN));
+        }
+        break;
+    }
 
-        // Output content (truncated)
-        let content: String = r.memory.content.chars().take(500).collect();
-        println!("{}", content);
+    let output = format.document(&entries);
+    let shown = entries.iter().map(|(r, _)| (*r).clone()).collect();
+    (output, shown)
+}
 
-        if r.memory.content.len() > 500 {
-            println!("...\n");
-        } else {
-            println!();
-        }
+/// A result as `context` prints it, with at most `max_chars` of its content
+fn format_entry(r: &SearchResult, max_chars: usize) -> String {
+    let mut entry = format!(
+        "## [{}]{} (relevance: {:.0}%)\n",
+        r.memory.id,
+        crate::cli::memory::global_marker(&r.memory),
+        r.score * 100.0
+    );
+    if !r.memory.tags.is_empty() {
+        entry.push_str(&format!("*Tags: {}*\n", r.memory.tags.join(", ")));
+    }
+    if let Some(source) = &r.memory.source {
+        entry.push_str(&format!("*Source: {}*\n", Source::parse(source)));
+    }
+    if !r.memory.tags.is_empty() || r.memory.source.is_some() {
+        entry.push('\n');
     }
 
-    Ok(())
+    let content: String = r.memory.content.chars().take(max_chars).collect();
+    entry.push_str(&content);
+    if content.len() < r.memory.content.len() {
+        entry.push_str("\n...\n\n");
+    } else {
+        entry.push_str("\n\n");
+    }
+    entry
+}
+
+/// Rough token count of `text`, at `CHARS_PER_TOKEN`; the agent's own
+/// tokenizer isn't at hand
+fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(CHARS_PER_TOKEN)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::memory;
+
+    #[test]
+    fn test_render_fits_budget() {
+        let result = |id, words: usize| SearchResult {
+            memory: memory(id, &"word ".repeat(words), &[]),
+            score: 0.9,
+            parts: None,
+        };
+        let results = vec![result(1, 40), result(2, 400), result(3, 10)];
+
+        // Without a budget each memory is cut at CONTENT_CHARS
+        let (output, shown) = render(results.clone(), None, ContextFormat::Markdown);
+        assert_eq!(shown.len(), 3);
+        assert!(output.contains("\n...\n"));
+
+        // The second is cut to fit and the third is dropped
+        let (output, shown) = render(results.clone(), Some(200), ContextFormat::Markdown);
+        assert!(estimate_tokens(&output) <= 200);
+        assert_eq!(shown.iter().map(|r| r.memory.id).collect::<Vec<_>>(), vec![1, 2]);
+        assert!(output.ends_with("...\n\n"));
+
+        // Too little room left to be worth cutting the second
+        let (_, shown) = render(results.clone(), Some(70), ContextFormat::Markdown);
+        assert_eq!(shown.iter().map(|r| r.memory.id).collect::<Vec<_>>(), vec![1]);
+
+        // Records stay parseable when cut to fit
+        let (output, shown) = render(results, Some(200), ContextFormat::Json);
+        let json: proto::Context = serde_json::from_str(&output).unwrap();
+        assert_eq!(json.version, proto::VERSION);
+        assert_eq!(json.memories.len(), shown.len());
+        assert!(json.memories[shown.len() - 1].truncated);
+    }
 }
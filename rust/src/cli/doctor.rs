@@ -0,0 +1,276 @@
+use crate::config::{find_roots_path, RootsConfig};
+use crate::embeddings::ServerEmbedder;
+use crate::error::Error;
+use crate::index::MemoryStore;
+use crate::lock::MaintenanceLock;
+use crate::memory::Memories;
+use crate::types::{MemoryFilter, SortOrder};
+use std::path::Path;
+
+/// Outcome of one check
+enum Status {
+    Ok,
+    Warn,
+    Fail,
+}
+
+/// Prints checks as they run and tallies the problems
+#[derive(Default)]
+struct Report {
+    problems: usize,
+}
+
+impl Report {
+    fn check(&mut self, name: &str, status: Status, detail: &str, fix: Option<&str>) {
+        let label = match status {
+            Status::Ok => "ok",
+            Status::Warn => "warn",
+            Status::Fail => "FAIL",
+        };
+        println!("{:<14} {:<5} {}", name, label, detail);
+        if !matches!(status, Status::Ok) {
+            self.problems += 1;
+            if let Some(fix) = fix {
+                println!("{:<20} fix: {}", "", fix);
+            }
+        }
+    }
+}
+
+/// Run the doctor command
+pub fn run_doctor(fix: bool, no_wait: bool) -> Result<(), Error> {
+    let roots_path = find_roots_path().ok_or(Error::NotInitialized)?;
+    // Only repairs write; a read-only check can run alongside maintenance
+    let _lock = if fix {
+        Some(MaintenanceLock::acquire(&roots_path, "doctor --fix", no_wait)?)
+    } else {
+        None
+    };
+    let config = RootsConfig::new(roots_path.clone());
+    let mut report = Report::default();
+
+    let db_path = roots_path.join("memory.db");
+    let sqlite = config.store_backend() == "sqlite";
+    if sqlite {
+        check_database(&mut report, &db_path, fix);
+    } else {
+        report.check(
+            "database",
+            Status::Ok,
+            &format!("{} store, file checks skipped", config.store_backend()),
+            None,
+        );
+    }
+
+    match Memories::open() {
+        Ok(mem) => check_embeddings(&mut report, &mem, sqlite.then_some(db_path.as_path()))?,
+        Err(e) => report.check("embeddings", Status::Fail, &e.to_string(), None),
+    }
+    check_server(&mut report, &config);
+
+    println!();
+    if report.problems == 0 {
+        println!("No problems found.");
+        Ok(())
+    } else {
+        Err(format!("{} problem(s) found", report.problems).into())
+    }
+}
+
+/// Run the optimize command
+pub fn run_optimize(no_wait: bool) -> Result<(), Error> {
+    let roots_path = find_roots_path().ok_or(Error::NotInitialized)?;
+    if RootsConfig::new(roots_path.clone()).store_backend() != "sqlite" {
+        return Err(Error::Invalid("Optimize only applies to the sqlite store".to_string()));
+    }
+    let _lock = MaintenanceLock::acquire(&roots_path, "optimize", no_wait)?;
+    let db_path = roots_path.join("memory.db");
+
+    let before = database_size(&db_path);
+    MemoryStore::open(&db_path)
+        .and_then(|store| store.optimize())
+        .map_err(|e| format!("Failed to optimize: {}", e))?;
+    let after = database_size(&db_path);
+
+    println!("Rebuilt the search index and vacuumed {}", db_path.display());
+    println!("  before: {}", format_size(before));
+    println!("  after:  {}", format_size(after));
+    if before > after {
+        println!("  saved:  {}", format_size(before - after));
+    }
+    Ok(())
+}
+
+/// Bytes used by the database, including its WAL
+fn database_size(db_path: &Path) -> u64 {
+    ["", "-wal"]
+        .iter()
+        .filter_map(|suffix| std::fs::metadata(format!("{}{}", db_path.display(), suffix)).ok())
+        .map(|m| m.len())
+        .sum()
+}
+
+pub(crate) fn format_size(bytes: u64) -> String {
+    match bytes {
+        b if b >= 1 << 20 => format!("{:.1} MB", b as f64 / (1 << 20) as f64),
+        b if b >= 1 << 10 => format!("{:.1} KB", b as f64 / (1 << 10) as f64),
+        b => format!("{} B", b),
+    }
+}
+
+fn check_database(report: &mut Report, db_path: &Path, fix: bool) {
+    let restore = "roots restore <snapshot> (see 'roots backup --list')";
+    let store = match MemoryStore::open(db_path) {
+        Ok(store) => store,
+        Err(e) => {
+            report.check("database", Status::Fail, &format!("does not open: {}", e), Some(restore));
+            return;
+        }
+    };
+
+    match store.integrity_check() {
+        Ok(result) if result == "ok" => {
+            report.check("database", Status::Ok, &db_path.display().to_string(), None)
+        }
+        Ok(result) => report.check("database", Status::Fail, &result, Some(restore)),
+        Err(e) => report.check("database", Status::Fail, &e.to_string(), Some(restore)),
+    }
+
+    match store.fts_consistent() {
+        Ok(true) => report.check("search index", Status::Ok, "matches memories", None),
+        Ok(false) if fix => match store.rebuild_fts() {
+            Ok(()) => report.check("search index", Status::Ok, "rebuilt", None),
+            Err(e) => report.check("search index", Status::Fail, &e.to_string(), None),
+        },
+        Ok(false) => report.check(
+            "search index",
+            Status::Fail,
+            "out of sync with memories, text search misses or returns stale rows",
+            Some("roots doctor --fix (rebuilds it)"),
+        ),
+        Err(e) => report.check("search index", Status::Fail, &e.to_string(), None),
+    }
+
+    match store.orphaned_tags() {
+        Ok(0) => report.check("tags", Status::Ok, "no orphaned rows", None),
+        Ok(n) if fix => match store.delete_orphaned_tags() {
+            Ok(_) => report.check("tags", Status::Ok, &format!("removed {} orphaned rows", n), None),
+            Err(e) => report.check("tags", Status::Fail, &e.to_string(), None),
+        },
+        Ok(n) => report.check(
+            "tags",
+            Status::Warn,
+            &format!("{} rows point at deleted memories (inflate tag counts)", n),
+            Some("roots doctor --fix (deletes them)"),
+        ),
+        Err(e) => report.check("tags", Status::Fail, &e.to_string(), None),
+    }
+}
+
+/// Embedding sizes are only checked on the sqlite file at `db_path`. They
+/// are checked against the current embedder only when it is the recorded
+/// model, and otherwise against each other, so a server that is down (and
+/// lite standing in for it) doesn't fail them.
+fn check_embeddings(report: &mut Report, mem: &Memories, db_path: Option<&Path>) -> Result<(), Error> {
+    let recorded = mem.get_stored_model()?;
+    let unreachable = mem.server_unreachable() || mem.embedding_dimension().is_err();
+    let probed = recorded
+        .as_deref()
+        .is_none_or(|m| m == mem.current_model())
+        .then(|| mem.embedding_dimension().ok())
+        .flatten()
+        .filter(|_| !unreachable);
+
+    if let Some(store) = db_path.and_then(|p| MemoryStore::open(p).ok()) {
+        let sizes = store.embedding_sizes().map_err(|e| e.to_string())?;
+        // Sizes come most common first
+        let expected = probed
+            .map(|dim| dim * 4)
+            .or_else(|| sizes.iter().find_map(|(size, _)| *size));
+        let wrong: usize = sizes
+            .iter()
+            .filter(|(size, _)| size.is_some() && *size != expected)
+            .map(|(_, n)| n)
+            .sum();
+        let queued: usize = sizes.iter().filter(|(size, _)| size.is_none()).map(|(_, n)| n).sum();
+        let model = recorded.as_deref().unwrap_or(mem.current_model());
+
+        match expected {
+            Some(size) if wrong > 0 => report.check(
+                "embeddings",
+                Status::Fail,
+                &format!(
+                    "{} memories are not {}-dimensional ({}), semantic recall skips or misranks them",
+                    wrong,
+                    size / 4,
+                    model
+                ),
+                Some("roots reindex --missing-only"),
+            ),
+            Some(size) => report.check("embeddings", Status::Ok, &format!("{} dimensions", size / 4), None),
+            None => report.check("embeddings", Status::Ok, "none stored yet", None),
+        }
+        if queued > 0 {
+            report.check(
+                "embed queue",
+                Status::Warn,
+                &format!("{} memories waiting for an embedding", queued),
+                Some("roots embed-queue run"),
+            );
+        }
+    }
+
+    match mem.check_model_mismatch()? {
+        Some(stored) if unreachable => report.check(
+            "model",
+            Status::Warn,
+            &format!("stored with {}, server unreachable (embedding with {} meanwhile)", stored, mem.current_model()),
+            Some("roots server start"),
+        ),
+        Some(stored) => report.check(
+            "model",
+            Status::Warn,
+            &format!("stored with {}, now using {}", stored, mem.current_model()),
+            Some("roots reindex (or switch back: roots config embedding_model <model>)"),
+        ),
+        None => report.check("model", Status::Ok, mem.current_model(), None),
+    }
+
+    let missing = mem
+        .list(&MemoryFilter::everything(), i64::MAX as usize, SortOrder::Recent)?
+        .into_iter()
+        .filter(|m| m.blob.is_some())
+        .filter(|m| mem.hydrate(&mut m.clone()).is_err())
+        .map(|m| m.id.to_string())
+        .collect::<Vec<_>>();
+    if missing.is_empty() {
+        report.check("blobs", Status::Ok, "all readable", None);
+    } else {
+        report.check(
+            "blobs",
+            Status::Fail,
+            &format!("missing or corrupt for [{}]", missing.join(", ")),
+            Some("restore .roots/objects from a copy, or forget and re-add those memories"),
+        );
+    }
+
+    Ok(())
+}
+
+fn check_server(report: &mut Report, config: &RootsConfig) {
+    let (model, model_type) = config.get_resolved_model();
+    let server = ServerEmbedder::new();
+    if server.is_running() {
+        let serving = server.get_model().unwrap_or_else(|_| "unknown".to_string());
+        report.check("server", Status::Ok, &format!("running {}", serving), None);
+    } else if model_type == "lite" {
+        report.check("server", Status::Ok, "not needed for lite", None);
+    } else {
+        report.check(
+            "server",
+            Status::Warn,
+            &format!("not running, {} falls back to lite embeddings", model),
+            Some("roots server start"),
+        );
+    }
+}
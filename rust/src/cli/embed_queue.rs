@@ -0,0 +1,101 @@
+use crate::error::Error;
+use crate::lock::FileLock;
+use crate::memory::Memories;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+/// Lock file under `.roots` held by the worker draining the queue. It is
+/// an OS lock, like the maintenance lock, so a worker that dies releases it.
+const LOCK_FILE: &str = "embed-queue.lock";
+
+/// Run the embed-queue run command
+pub fn run_embed_queue(batch: usize, watch: bool, interval: u64, quiet: bool) -> Result<(), Error> {
+    let mem = Memories::open()?;
+    let Some(_lock) = lock_queue(mem.roots_path())? else {
+        if !quiet {
+            println!("Another embed-queue worker is running.");
+        }
+        return Ok(());
+    };
+
+    let mut total = 0;
+    loop {
+        let embedded = mem.embed_pending(batch.max(1))?;
+        total += embedded;
+        if embedded > 0 {
+            if !quiet {
+                println!("Embedded {} ({} so far)", embedded, total);
+            }
+            continue;
+        }
+        if !watch {
+            break;
+        }
+        thread::sleep(Duration::from_secs(interval));
+    }
+
+    if !quiet {
+        println!("Queue empty, embedded {} memories", total);
+    }
+    Ok(())
+}
+
+/// Run the embed-queue status command
+pub fn run_embed_queue_status() -> Result<(), Error> {
+    let mem = Memories::open()?;
+    let pending = mem.pending_embeddings()?;
+    // Taking the lock and letting go at once, if no worker has it
+    let worker = lock_queue(mem.roots_path())?.is_none();
+
+    println!("Queued: {}", pending);
+    println!("Worker: {}", if worker { "running" } else { "idle" });
+    if pending > 0 && !worker {
+        println!("\nProcess with: roots embed-queue run");
+    }
+    Ok(())
+}
+
+/// Start a detached worker for the store at `roots_path`, so deferred
+/// writes become searchable without anyone waiting on the embedder
+pub fn spawn_worker(roots_path: &Path) -> Result<(), Error> {
+    let exe = std::env::current_exe()
+        .map_err(|e| format!("Failed to locate roots binary: {}", e))?;
+    // Stores are found from the working directory
+    let dir = roots_path.parent().unwrap_or(roots_path);
+    Command::new(exe)
+        .args(["embed-queue", "run", "--quiet"])
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to start embed-queue worker: {}", e))?;
+    Ok(())
+}
+
+/// The worker's lock on the queue, or None when another worker has it
+fn lock_queue(roots_path: &Path) -> Result<Option<FileLock>, Error> {
+    let path = roots_path.join(LOCK_FILE);
+    FileLock::try_acquire(&path).map_err(|e| format!("Failed to lock {}: {}", path.display(), e).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_one_worker_holds_the_queue() {
+        let dir = std::env::temp_dir().join(format!("roots-embed-queue-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let worker = lock_queue(&dir).unwrap();
+        assert!(worker.is_some());
+        assert!(lock_queue(&dir).unwrap().is_none());
+        // A worker that stops, however it stops, frees the queue
+        drop(worker);
+        assert!(lock_queue(&dir).unwrap().is_some());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,93 @@
+use crate::cli::{print_json, Output};
+use crate::config::find_roots_path;
+use crate::error::Error;
+use crate::eval::{self, CaseResult};
+use crate::memory::Memories;
+use crate::proto;
+use crate::types::MemoryFilter;
+use std::fs;
+use std::path::Path;
+
+/// Run `roots eval` - recall each case's query and score the results
+/// against its expected memories
+pub fn run_eval(path: &Path, k: usize, rerank: bool, output: Output) -> Result<(), Error> {
+    if k == 0 {
+        return Err(Error::Invalid("-k must be at least 1".to_string()));
+    }
+    let text = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let cases = eval::parse_cases(&text).map_err(Error::Invalid)?;
+    if cases.is_empty() {
+        return Err(Error::Invalid(format!("No cases in {}", path.display())));
+    }
+
+    // The project store alone: global memories have IDs of their own store
+    let mem = Memories::open_at(find_roots_path().ok_or(Error::NotInitialized)?)?;
+    let rerank = rerank || mem.rerank_by_default();
+    let filter = MemoryFilter::default();
+
+    let mut results: Vec<(Vec<i64>, CaseResult)> = Vec::new();
+    for (i, case) in cases.iter().enumerate() {
+        for id in &case.expected {
+            if mem.get(*id)?.is_none() {
+                eprintln!("Warning: case {} expects memory [{}], which doesn't exist", i + 1, id);
+            }
+        }
+        let recalled = if rerank {
+            let mut recalled = mem.recall(&case.query, &filter, mem.rerank_candidates(k))?;
+            mem.rerank(&case.query, &mut recalled)?;
+            recalled.truncate(k);
+            recalled
+        } else {
+            mem.recall(&case.query, &filter, k)?
+        };
+        let retrieved: Vec<i64> = recalled.iter().map(|r| r.memory.id).collect();
+        let result = eval::score(&case.expected, &retrieved);
+        results.push((retrieved, result));
+    }
+
+    let recall_at_k = eval::mean(results.iter().map(|(_, r)| r.recall));
+    let mrr = eval::mean(results.iter().map(|(_, r)| r.reciprocal_rank));
+
+    if output.is_json() {
+        return print_json(&proto::Eval {
+            model: mem.current_model().to_string(),
+            rerank,
+            k,
+            recall_at_k,
+            mrr,
+            cases: cases
+                .iter()
+                .zip(&results)
+                .map(|(case, (retrieved, result))| proto::EvalCase {
+                    query: case.query.clone(),
+                    expected: case.expected.clone(),
+                    retrieved: retrieved.clone(),
+                    recall: result.recall,
+                    reciprocal_rank: result.reciprocal_rank,
+                })
+                .collect(),
+        });
+    }
+
+    println!(
+        "Evaluating {} cases with {}{} (k={})\n",
+        cases.len(),
+        mem.current_model(),
+        if rerank { " and rerank" } else { "" },
+        k
+    );
+    println!("  {:>6} {:>6}  query", "recall", "rr");
+    for (case, (_, result)) in cases.iter().zip(&results) {
+        let query: String = case.query.chars().take(50).collect();
+        let missed = if result.missed.is_empty() {
+            String::new()
+        } else {
+            let ids: Vec<String> = result.missed.iter().map(|id| id.to_string()).collect();
+            format!("  missed [{}]", ids.join(", "))
+        };
+        println!("  {:>6.2} {:>6.2}  {}{}", result.recall, result.reciprocal_rank, query, missed);
+    }
+    println!("\nrecall@{}: {:.3}", k, recall_at_k);
+    println!("MRR:      {:.3}", mrr);
+    Ok(())
+}
@@ -0,0 +1,442 @@
+use crate::cli::context::{prime_store, ContextFormat};
+use crate::error::Error;
+use crate::hook::CAPTURE_TOOLS;
+use crate::memory::Memories;
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Hook command offering edits and replies to auto-capture
+const AUTO_REMEMBER: &str = "roots remember --auto --hook-stdin --origin hook";
+
+/// Hook command capturing a transcript when its session ends
+const CAPTURE_SESSION: &str = "roots capture --hook-stdin";
+
+/// Markers around the lines `hooks --git` adds to `post-commit`
+const GIT_BEGIN: &str = "# roots:begin";
+const GIT_END: &str = "# roots:end";
+
+/// Runs in the background so commits never wait on roots, and does nothing
+/// where roots isn't installed
+const GIT_COMMAND: &str = "command -v roots >/dev/null 2>&1 && (roots capture --commit >/dev/null 2>&1 &)";
+
+/// Interpreters `GIT_COMMAND` runs under; hooks in anything else (a Python
+/// or Node hook, say) are left alone
+const GIT_SHELLS: &[&str] = &["sh", "bash", "dash", "zsh"];
+
+/// Agents `hooks --tool` can install for: Claude Code hooks, or a rules
+/// file for editors without hooks
+pub const TOOLS: &[&str] = &["claude", "cursor", "windsurf"];
+
+/// Which capture hooks to install
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Capture {
+    /// Offer edits and the agent's replies to `remember --auto`
+    pub auto_remember: bool,
+    /// Run `capture` on the transcript at `SessionEnd`
+    pub sessions: bool,
+}
+
+/// Run the hooks command
+pub fn run_hooks(
+    path: &str,
+    remove: bool,
+    context_mode: &str,
+    capture: Capture,
+    global: bool,
+) -> Result<(), Error> {
+    let settings_path = if global {
+        dirs::home_dir()
+            .ok_or("Could not find home directory")?
+            .join(".claude")
+            .join("settings.json")
+    } else {
+        Path::new(path).join(".claude").join("settings.json")
+    };
+
+    if remove {
+        Ok(remove_hooks(&settings_path)?)
+    } else {
+        Ok(install_hooks(&settings_path, context_mode, capture)?)
+    }
+}
+
+/// Install the prime hooks and, unless `context_mode` is "none", the
+/// per-prompt context hook into a Claude Code settings file, plus the
+/// `capture` hooks asked for
+pub fn install_hooks(settings_path: &Path, context_mode: &str, capture: Capture) -> Result<(), Error> {
+    if let Some(dir) = settings_path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create .claude directory: {}", e))?;
+    }
+
+    let mut settings = read_settings(settings_path)?.unwrap_or_else(|| serde_json::json!({}));
+    let mut commands = vec![
+        ("SessionStart", "", "roots prime".to_string()),
+        ("PreCompact", "", "roots prime".to_string()),
+    ];
+    if context_mode != "none" {
+        commands.push((
+            "UserPromptSubmit",
+            "",
+            format!("roots context --mode {} --hook-stdin", context_mode),
+        ));
+    }
+    if capture.auto_remember {
+        commands.push(("PostToolUse", CAPTURE_TOOLS, AUTO_REMEMBER.to_string()));
+        commands.push(("Stop", "", AUTO_REMEMBER.to_string()));
+    }
+    if capture.sessions {
+        commands.push(("SessionEnd", "", CAPTURE_SESSION.to_string()));
+    }
+    merge_hooks(&mut settings, &commands)?;
+    write_settings(settings_path, &settings)?;
+
+    println!("Hooks installed in {}:", settings_path.display());
+    println!("  SessionStart: roots prime");
+    println!("  PreCompact:   roots prime");
+    if context_mode != "none" {
+        println!("  UserPromptSubmit: roots context --mode {}", context_mode);
+    }
+    if capture.auto_remember {
+        println!("  PostToolUse ({}): {}", CAPTURE_TOOLS, AUTO_REMEMBER);
+        println!("  Stop: {}", AUTO_REMEMBER);
+    }
+    if capture.sessions {
+        println!("  SessionEnd: {}", CAPTURE_SESSION);
+    }
+
+    Ok(())
+}
+
+fn remove_hooks(settings_path: &Path) -> Result<(), Error> {
+    let Some(mut settings) = read_settings(settings_path)? else {
+        println!("No hooks configured.");
+        return Ok(());
+    };
+
+    strip_hooks(&mut settings);
+    write_settings(settings_path, &settings)?;
+
+    println!("Hooks removed from {}.", settings_path.display());
+    Ok(())
+}
+
+/// Run `hooks --tool cursor|windsurf`: write the prime output as an
+/// always-applied rules file, since these editors have no session hooks.
+/// Rerun it to refresh the snapshot.
+pub fn run_rules(path: &str, tool: &str, remove: bool) -> Result<(), Error> {
+    let project = Path::new(path);
+    let (rules_path, frontmatter) = match tool {
+        "cursor" => (
+            project.join(".cursor").join("rules").join("roots.mdc"),
+            "---\ndescription: Project memories from roots\nalwaysApply: true\n---\n\n",
+        ),
+        "windsurf" => (
+            project.join(".windsurf").join("rules").join("roots.md"),
+            "---\ntrigger: always_on\n---\n\n",
+        ),
+        _ => return Err(Error::Invalid(format!("Unknown tool: {} (use {})", tool, TOOLS.join(", ")))),
+    };
+
+    if remove {
+        if rules_path.exists() {
+            fs::remove_file(&rules_path).map_err(|e| format!("Failed to remove rules: {}", e))?;
+            println!("Removed {}", rules_path.display());
+        } else {
+            println!("No rules file at {}", rules_path.display());
+        }
+        return Ok(());
+    }
+
+    // The store for the project the rules are for, wherever this runs
+    let mem = Memories::open_in(project)?;
+    let context = prime_store(&mem, ContextFormat::Markdown)?;
+    if context.is_empty() {
+        return Err(Error::NotFound("No memories to write; remember something first".to_string()));
+    }
+    if let Some(dir) = rules_path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+    fs::write(&rules_path, format!("{}{}", frontmatter, context))
+        .map_err(|e| format!("Failed to write rules: {}", e))?;
+
+    println!("Wrote {}", rules_path.display());
+    println!("  Rerun 'roots hooks --tool {}' to refresh it as memories change.", tool);
+    Ok(())
+}
+
+/// Run `hooks --git`: add a post-commit hook remembering each commit that
+/// matches `commit_pattern`, next to whatever the hook already runs
+pub fn run_git_hook(path: &str, remove: bool) -> Result<(), Error> {
+    let output = std::process::Command::new("git")
+        .args(["rev-parse", "--git-path", "hooks"])
+        .current_dir(path)
+        .output()
+        .map_err(|e| format!("Failed to run git: {}", e))?;
+    if !output.status.success() {
+        return Err(Error::Invalid(format!("{} is not a git repository", path)));
+    }
+    let hooks_dir = Path::new(path).join(String::from_utf8_lossy(&output.stdout).trim());
+    let hook_path = hooks_dir.join("post-commit");
+    let existing = match fs::read_to_string(&hook_path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(format!("Failed to read {}: {}", hook_path.display(), e).into()),
+    };
+
+    if remove {
+        if !existing.contains(GIT_BEGIN) {
+            println!("No roots hook in {}", hook_path.display());
+            return Ok(());
+        }
+        let stripped = strip_git_block(&existing);
+        if stripped.trim() == "#!/bin/sh" {
+            fs::remove_file(&hook_path).map_err(|e| format!("Failed to remove hook: {}", e))?;
+        } else {
+            fs::write(&hook_path, stripped).map_err(|e| format!("Failed to write hook: {}", e))?;
+        }
+        println!("Removed the roots hook from {}", hook_path.display());
+        return Ok(());
+    }
+
+    if !runs_in_shell(&existing) {
+        return Err(Error::Invalid(format!(
+            "{} is not a shell script, so roots can't add to it; have it run: {}",
+            hook_path.display(),
+            GIT_COMMAND
+        )));
+    }
+    fs::create_dir_all(&hooks_dir).map_err(|e| format!("Failed to create {}: {}", hooks_dir.display(), e))?;
+    fs::write(&hook_path, add_git_block(&existing)).map_err(|e| format!("Failed to write hook: {}", e))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&hook_path, fs::Permissions::from_mode(0o755))
+            .map_err(|e| format!("Failed to make hook executable: {}", e))?;
+    }
+
+    println!("Installed post-commit hook in {}", hook_path.display());
+    println!("  Commits are remembered with 'roots capture --commit', tagged 'commit'.");
+    println!("  Only keep some: roots config commit_pattern '(?i)(decision|learned):'");
+    Ok(())
+}
+
+/// Whether a hook script (empty for none yet) runs in a shell, going by its
+/// shebang: `#!/bin/sh`, `#!/usr/bin/env bash` and the like
+fn runs_in_shell(existing: &str) -> bool {
+    if existing.trim().is_empty() {
+        return true;
+    }
+    let Some(shebang) = existing.lines().next().and_then(|l| l.strip_prefix("#!")) else {
+        return false;
+    };
+    let mut words = shebang.split_whitespace();
+    let program = match words.next() {
+        Some(env) if env.ends_with("/env") => words.find(|w| !w.starts_with('-')),
+        program => program,
+    };
+    program
+        .and_then(|p| p.rsplit('/').next())
+        .is_some_and(|name| GIT_SHELLS.contains(&name))
+}
+
+/// The hook script with the roots block added, replacing an earlier one
+fn add_git_block(existing: &str) -> String {
+    let script = strip_git_block(existing);
+    let script = if script.trim().is_empty() {
+        "#!/bin/sh\n".to_string()
+    } else {
+        format!("{}\n", script.trim_end())
+    };
+    format!("{}{}\n{}\n{}\n", script, GIT_BEGIN, GIT_COMMAND, GIT_END)
+}
+
+/// The hook script without the roots block
+fn strip_git_block(existing: &str) -> String {
+    let mut lines = Vec::new();
+    let mut in_block = false;
+    for line in existing.lines() {
+        match line.trim() {
+            GIT_BEGIN => in_block = true,
+            GIT_END => in_block = false,
+            _ if !in_block => lines.push(line),
+            _ => {}
+        }
+    }
+    let mut script = lines.join("\n");
+    if !script.is_empty() {
+        script.push('\n');
+    }
+    script
+}
+
+/// Settings at `path`, or `None` if there are none yet. A file that doesn't
+/// parse is an error rather than something to overwrite.
+fn read_settings(path: &Path) -> Result<Option<Value>, Error> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read settings: {}", e))?;
+    if content.trim().is_empty() {
+        return Ok(None);
+    }
+    serde_json::from_str(&content)
+        .map(Some)
+        .map_err(|e| Error::Invalid(format!("Failed to parse {}: {}", path.display(), e)))
+}
+
+/// Write settings through a temporary file, so an interrupted write never
+/// leaves a truncated settings file behind
+fn write_settings(path: &Path, settings: &Value) -> Result<(), Error> {
+    let json = serde_json::to_string_pretty(settings)
+        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    let mut tmp = PathBuf::from(path);
+    tmp.set_extension("json.tmp");
+    fs::write(&tmp, json + "\n").map_err(|e| format!("Failed to write settings: {}", e))?;
+    fs::rename(&tmp, path).map_err(|e| format!("Failed to write settings: {}", e).into())
+}
+
+/// Whether a hook matcher entry only runs roots commands
+fn is_roots_entry(entry: &Value) -> bool {
+    entry["hooks"].as_array().is_some_and(|hooks| {
+        !hooks.is_empty()
+            && hooks.iter().all(|h| {
+                h["command"]
+                    .as_str()
+                    .is_some_and(|c| c == "roots" || c.starts_with("roots "))
+            })
+    })
+}
+
+/// Add a hook entry running each command to its event, replacing roots
+/// entries from an earlier install and keeping every other hook and setting
+fn merge_hooks(settings: &mut Value, commands: &[(&str, &str, String)]) -> Result<(), Error> {
+    let hooks = settings
+        .as_object_mut()
+        .ok_or_else(|| Error::Invalid("Invalid settings format".to_string()))?
+        .entry("hooks")
+        .or_insert(serde_json::json!({}))
+        .as_object_mut()
+        .ok_or_else(|| Error::Invalid("Invalid hooks format".to_string()))?;
+
+    for (event, matcher, command) in commands {
+        let entries = hooks
+            .entry(event.to_string())
+            .or_insert(serde_json::json!([]))
+            .as_array_mut()
+            .ok_or_else(|| Error::Invalid(format!("Invalid {} hooks format", event)))?;
+        entries.retain(|e| !is_roots_entry(e));
+        entries.push(serde_json::json!({
+            "matcher": matcher,
+            "hooks": [{
+                "type": "command",
+                "command": command
+            }]
+        }));
+    }
+    Ok(())
+}
+
+/// Remove roots hook entries, dropping events and the hooks section once
+/// nothing else is left in them
+fn strip_hooks(settings: &mut Value) {
+    let Some(obj) = settings.as_object_mut() else {
+        return;
+    };
+    let Some(hooks) = obj.get_mut("hooks").and_then(Value::as_object_mut) else {
+        return;
+    };
+    for entries in hooks.values_mut() {
+        if let Some(entries) = entries.as_array_mut() {
+            entries.retain(|e| !is_roots_entry(e));
+        }
+    }
+    hooks.retain(|_, entries| entries.as_array().is_none_or(|e| !e.is_empty()));
+    if hooks.is_empty() {
+        obj.remove("hooks");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_and_strip_keep_other_hooks() {
+        let mut settings = serde_json::json!({
+            "model": "opus",
+            "hooks": {
+                "SessionStart": [
+                    {"matcher": "", "hooks": [{"type": "command", "command": "echo hi"}]},
+                    {"matcher": "", "hooks": [{"type": "command", "command": "roots prime"}]}
+                ]
+            }
+        });
+
+        let commands = [
+            ("SessionStart", "", "roots prime".to_string()),
+            ("UserPromptSubmit", "", "roots context --mode lite --hook-stdin".to_string()),
+        ];
+        merge_hooks(&mut settings, &commands).unwrap();
+        merge_hooks(&mut settings, &commands).unwrap();
+        assert_eq!(settings["hooks"]["SessionStart"].as_array().unwrap().len(), 2);
+        assert_eq!(settings["hooks"]["SessionStart"][0]["hooks"][0]["command"], "echo hi");
+        assert_eq!(settings["hooks"]["UserPromptSubmit"].as_array().unwrap().len(), 1);
+
+        strip_hooks(&mut settings);
+        assert_eq!(
+            settings,
+            serde_json::json!({
+                "model": "opus",
+                "hooks": {
+                    "SessionStart": [
+                        {"matcher": "", "hooks": [{"type": "command", "command": "echo hi"}]}
+                    ]
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_git_block_keeps_existing_hook() {
+        let fresh = add_git_block("");
+        assert!(fresh.starts_with("#!/bin/sh\n# roots:begin\n"));
+        assert_eq!(add_git_block(&fresh), fresh);
+        assert_eq!(strip_git_block(&fresh), "#!/bin/sh\n");
+
+        let existing = "#!/bin/bash\nmake lint\n";
+        let installed = add_git_block(existing);
+        assert!(installed.starts_with(existing));
+        assert_eq!(strip_git_block(&installed), existing);
+    }
+
+    #[test]
+    fn test_only_shell_hooks_are_added_to() {
+        assert!(runs_in_shell(""));
+        assert!(runs_in_shell("#!/bin/sh\nmake lint\n"));
+        assert!(runs_in_shell("#!/usr/bin/env bash\nset -e\n"));
+        assert!(runs_in_shell("#! /bin/bash -e\n"));
+        assert!(!runs_in_shell("#!/usr/bin/env python3\nimport sys\n"));
+        assert!(!runs_in_shell("#!/usr/bin/env node\n"));
+        // No shebang: git can't run it as a script anyway
+        assert!(!runs_in_shell("make lint\n"));
+    }
+
+    #[test]
+    fn test_rules_come_from_the_projects_store() {
+        let dir = crate::test_support::scratch("rules");
+        crate::test_support::store(&dir)
+            .remember(&crate::types::NewMemory {
+                content: "Deploys go through the staging cluster first".to_string(),
+                confidence: 0.9,
+                ..Default::default()
+            })
+            .unwrap();
+
+        // Run from elsewhere, as setup does for the project it was given
+        run_rules(&dir.to_string_lossy(), "cursor", false).unwrap();
+        let rules = fs::read_to_string(dir.join(".cursor/rules/roots.mdc")).unwrap();
+        assert!(rules.contains("Deploys go through the staging cluster first"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,638 @@
+use crate::blobs;
+use crate::bootstrap;
+use crate::error::Error;
+use crate::ingest::{parse_bookmarks, parse_feed, parse_readwise, DocOptions, ImportItem, IngestManifest, IngestedFile};
+use crate::memory::Memories;
+use crate::transcript;
+use crate::types::{MemoryFilter, NewMemory, SortOrder, DOC_KIND, QUARANTINE_NAMESPACE};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Run `ingest bookmarks` - import a browser bookmarks HTML export
+pub fn run_bookmarks(file: &str, tags: &str, confidence: f64) -> Result<(), Error> {
+    let html = read_file(file)?;
+    import_items(parse_bookmarks(&html), tags, confidence, None, None)
+}
+
+/// Run `ingest readwise` - import a Readwise highlights CSV export
+pub fn run_readwise(file: &str, tags: &str, confidence: f64) -> Result<(), Error> {
+    let csv = read_file(file)?;
+    import_items(parse_readwise(&csv)?, tags, confidence, None, None)
+}
+
+/// Run `ingest rss` - import items from an RSS or Atom feed file
+pub fn run_rss(file: &str, tags: &str, confidence: f64) -> Result<(), Error> {
+    let xml = read_file(file)?;
+    import_items(parse_feed(&xml), tags, confidence, None, None)
+}
+
+/// File extensions picked up when walking a directory for `ingest docs`
+const DOC_EXTENSIONS: &[&str] = &[
+    "md", "markdown", "txt", "rst", "adoc", "rs", "py", "js", "ts", "tsx", "go", "java", "rb", "c", "h", "cpp",
+];
+
+/// Run `ingest docs` - chunk files and store each chunk with its line range
+/// as source, as a `doc` memory. With `update`, files whose hash hasn't
+/// changed since they were last ingested are skipped, changed files have
+/// their chunks replaced, and chunks of files gone from `paths` are forgotten.
+pub fn run_docs(paths: &[String], options: &DocOptions, update: bool) -> Result<(), Error> {
+    let mem = Memories::open()?;
+    let report = ingest_docs(&mem, paths, options, update)?;
+
+    println!(
+        "Imported {} of {} chunks from {} files",
+        report.stored, report.chunks, report.files
+    );
+    if report.stored < report.chunks {
+        println!("  skipped {} already imported", report.chunks - report.stored);
+    }
+    if update {
+        println!("  {} unchanged, {} removed", report.unchanged, report.removed);
+    }
+    Ok(())
+}
+
+/// What a run of `ingest_docs` did
+#[derive(Debug, Default)]
+pub struct DocsReport {
+    /// Files chunked
+    pub files: usize,
+    pub chunks: usize,
+    /// Chunks stored, the rest having been imported before
+    pub stored: usize,
+    pub unchanged: usize,
+    pub removed: usize,
+}
+
+/// Chunk and store the files under `paths`, see `run_docs`. The whole run
+/// is one undo step.
+pub fn ingest_docs(mem: &Memories, paths: &[String], options: &DocOptions, update: bool) -> Result<DocsReport, Error> {
+    mem.as_one_step("ingest", || ingest_files(mem, paths, options, false, update))
+}
+
+/// `ingest docs --update` of every file ingested before, each with the
+/// options it was ingested with; `fallback` for files ingested before
+/// options were kept
+pub fn reingest_docs(mem: &Memories, fallback: &DocOptions) -> Result<DocsReport, Error> {
+    let root = mem.project_root();
+    let files: Vec<String> = mem
+        .ingested_files()?
+        .into_keys()
+        .map(|name| root.join(name).display().to_string())
+        .collect();
+    if files.is_empty() {
+        return Ok(DocsReport::default());
+    }
+    mem.as_one_step("ingest", || ingest_files(mem, &files, fallback, true, true))
+}
+
+/// With `saved`, files already in the manifest are ingested with their
+/// own options rather than `options`
+fn ingest_files(
+    mem: &Memories,
+    paths: &[String],
+    options: &DocOptions,
+    saved: bool,
+    update: bool,
+) -> Result<DocsReport, Error> {
+    let root = mem.project_root();
+    // Entries from before paths were kept relative to the project root
+    // are rekeyed, so `./docs/a.md` and `docs/a.md` are one file
+    let mut manifest: IngestManifest = mem
+        .ingested_files()?
+        .into_iter()
+        .map(|(name, file)| (manifest_key(&root, &root.join(&name)), file))
+        .collect();
+    let mut report = DocsReport::default();
+    let mut seen = Vec::new();
+    // Chunk sources, by file, and ids, to forget those of changed and
+    // removed files
+    let existing: Vec<(String, i64)> = if update {
+        let filter = MemoryFilter {
+            kind: Some(DOC_KIND.to_string()),
+            include_expired: true,
+            ..Default::default()
+        };
+        mem.list(&filter, usize::MAX, SortOrder::Recent)?
+            .into_iter()
+            .filter_map(|m| {
+                let source = m.source?;
+                let (file, _) = source.rsplit_once("#L")?;
+                Some((manifest_key(&root, &root.join(file)), m.id))
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let forget_chunks = |name: &str| -> Result<(), Error> {
+        for (_, id) in existing.iter().filter(|(file, _)| file == name) {
+            mem.forget(*id)?;
+        }
+        Ok(())
+    };
+
+    for path in paths {
+        // A file removed since the last run is only missing, not an error
+        let found = if update && !Path::new(path).exists() {
+            Vec::new()
+        } else {
+            doc_files(path)?
+        };
+        for file in found {
+            let Ok(text) = fs::read_to_string(&file) else {
+                continue; // Not UTF-8
+            };
+            let name = manifest_key(&root, &file);
+            let hash = blobs::hash(&text);
+            seen.push(name.clone());
+            let previous = manifest.get(&name);
+            let options = match previous.and_then(|f| f.options.as_ref()) {
+                Some(own) if saved => own.clone(),
+                _ => options.clone(),
+            };
+            if update {
+                // Files from before options were kept are taken to have
+                // been ingested with these
+                if let Some(previous) = previous.filter(|f| f.hash == hash) {
+                    if previous.options.as_ref().is_none_or(|o| *o == options) {
+                        report.unchanged += 1;
+                        manifest.insert(name, IngestedFile { hash, options: Some(options) });
+                        continue;
+                    }
+                }
+                forget_chunks(&name)?;
+            }
+
+            report.files += 1;
+            let chunker = options.chunker.build().map_err(Error::Invalid)?;
+            let extra_tags = split_tags(&options.tags);
+            for c in chunker.chunk(&text) {
+                report.chunks += 1;
+                let item = ImportItem {
+                    content: c.text,
+                    source: format!("{}#L{}-L{}", name, c.start_line, c.end_line),
+                    tags: Vec::new(),
+                };
+                if store_item(mem, item, &extra_tags, options.confidence, Some(DOC_KIND), None)?.is_some() {
+                    report.stored += 1;
+                }
+            }
+            manifest.insert(name, IngestedFile { hash, options: Some(options) });
+        }
+    }
+
+    if update {
+        // Only files that are really gone, under the paths given; one still
+        // there but not found (now hidden or ignored) keeps its chunks
+        let given: Vec<PathBuf> = paths.iter().map(|p| resolve(Path::new(p))).collect();
+        let gone: Vec<String> = manifest
+            .keys()
+            .filter(|name| !seen.contains(name))
+            .filter(|name| {
+                let file = root.join(name);
+                !file.exists() && given.iter().any(|p| resolve(&file).starts_with(p))
+            })
+            .cloned()
+            .collect();
+        for name in gone {
+            forget_chunks(&name)?;
+            manifest.remove(&name);
+            report.removed += 1;
+        }
+    }
+    mem.set_ingested_files(&manifest)?;
+    Ok(report)
+}
+
+/// How the manifest and chunk sources name `path` (relative to the current
+/// directory, or absolute): relative to the project `root`, or absolute
+/// when outside it, whichever directory the command runs from
+fn manifest_key(root: &Path, path: &Path) -> String {
+    let absolute = resolve(path);
+    absolute.strip_prefix(root).unwrap_or(&absolute).display().to_string()
+}
+
+/// `path` made absolute, with symlinks resolved as far as it exists and
+/// `.` and `..` dropped from the rest, so a removed file still compares
+/// equal to the path it had
+fn resolve(path: &Path) -> PathBuf {
+    let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut existing = absolute.as_path();
+    let mut rest = Vec::new();
+    let base = loop {
+        if let Ok(base) = existing.canonicalize() {
+            break base;
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                rest.push(name.to_os_string());
+                existing = parent;
+            }
+            // `..` at the end, or nothing of it exists
+            _ => break PathBuf::new(),
+        }
+    };
+    if base.as_os_str().is_empty() {
+        let mut resolved = PathBuf::new();
+        for component in absolute.components() {
+            match component {
+                std::path::Component::CurDir => {}
+                std::path::Component::ParentDir => {
+                    resolved.pop();
+                }
+                c => resolved.push(c),
+            }
+        }
+        return resolved;
+    }
+    rest.into_iter().rev().fold(base, |path, name| path.join(name))
+}
+
+/// Files to ingest under `path` (the path itself if it is a file), leaving
+/// out hidden and git-ignored ones
+fn doc_files(path: &str) -> Result<Vec<PathBuf>, Error> {
+    let root = Path::new(path);
+    if root.is_file() {
+        return Ok(vec![root.to_path_buf()]);
+    }
+    if !root.is_dir() {
+        return Err(Error::NotFound(format!("No such file or directory: {}", path)));
+    }
+
+    let mut files: Vec<_> = walkdir::WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|e| e.depth() == 0 || !e.file_name().to_string_lossy().starts_with('.'))
+        .flatten()
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+        .filter(|p| {
+            p.extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| DOC_EXTENSIONS.contains(&e))
+        })
+        .collect();
+    let ignored = git_ignored(root, &files);
+    files.retain(|f| !ignored.contains(f));
+    files.sort();
+    Ok(files)
+}
+
+/// Which of `files` git ignores; none outside a repository or without git
+fn git_ignored(root: &Path, files: &[PathBuf]) -> Vec<PathBuf> {
+    let Ok(mut child) = Command::new("git")
+        .args(["check-ignore", "--stdin"])
+        .current_dir(root)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+    else {
+        return Vec::new();
+    };
+    // Paths are relative to `root`, where git runs
+    let relative: Vec<String> = files
+        .iter()
+        .map(|f| f.strip_prefix(root).unwrap_or(f).display().to_string())
+        .collect();
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all((relative.join("\n") + "\n").as_bytes());
+    }
+    let Ok(output) = child.wait_with_output() else {
+        return Vec::new();
+    };
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| root.join(line))
+        .collect()
+}
+
+/// Run `bootstrap` - seed memories about the repository at `path`
+pub fn run_bootstrap(path: &str, confidence: f64, dry_run: bool) -> Result<(), Error> {
+    let root = std::path::Path::new(path);
+    if !root.is_dir() {
+        return Err(Error::Invalid(format!("Not a directory: {}", path)));
+    }
+
+    let items = bootstrap::scan(root);
+    if items.is_empty() {
+        println!("Nothing recognizable found in {}", path);
+        return Ok(());
+    }
+
+    if dry_run {
+        for item in &items {
+            println!("[{}] {}", item.tags.join(", "), item.content);
+        }
+        println!("\n{} memories would be created (dry run).", items.len());
+        return Ok(());
+    }
+
+    import_items(items, "", confidence, Some("fact"), None)
+}
+
+fn read_file(file: &str) -> Result<String, Error> {
+    fs::read_to_string(file).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => Error::NotFound(format!("No such file: {}", file)),
+        _ => format!("Failed to read {}: {}", file, e).into(),
+    })
+}
+
+/// Run `ingest transcript` - store chunks of a conversation for review,
+/// each with the turns it covers as source
+pub fn run_transcript(file: &str, chunk_size: usize, tags: &str, confidence: f64) -> Result<(), Error> {
+    if chunk_size == 0 {
+        return Err(Error::Invalid("Chunk size must be positive".to_string()));
+    }
+    let transcript = transcript::read(file)?;
+    let mut item_tags = vec!["transcript".to_string()];
+    if let Some(session) = &transcript.session_id {
+        item_tags.push(format!("session-{}", session));
+    }
+
+    let chunks = transcript::chunk(&transcript.messages, chunk_size);
+    let mut items: Vec<ImportItem> = Vec::new();
+    for (i, c) in chunks.iter().enumerate() {
+        let mut source = format!("{}#T{}-T{}", file, c.first_turn, c.last_turn);
+        // Pieces of a long turn share its range, so number them
+        let same_range = |o: &transcript::TranscriptChunk| (o.first_turn, o.last_turn) == (c.first_turn, c.last_turn);
+        if chunks.iter().filter(|o| same_range(o)).count() > 1 {
+            source.push_str(&format!("p{}", chunks[..=i].iter().filter(|o| same_range(o)).count()));
+        }
+        items.push(ImportItem {
+            content: c.text.clone(),
+            source,
+            tags: item_tags.clone(),
+        });
+    }
+
+    import_items(items, tags, confidence, None, Some(QUARANTINE_NAMESPACE))?;
+    println!("Review with: roots review --namespace {}", QUARANTINE_NAMESPACE);
+    Ok(())
+}
+
+/// Store parsed items, skipping any whose source was already imported
+fn import_items(
+    items: Vec<ImportItem>,
+    tags: &str,
+    confidence: f64,
+    kind: Option<&str>,
+    namespace: Option<&str>,
+) -> Result<(), Error> {
+    let mem = Memories::open()?;
+    let extra_tags = split_tags(tags);
+
+    let total = items.len();
+    let imported = mem.as_one_step("ingest", || {
+        let mut imported = 0;
+        for item in items {
+            if store_item(&mem, item, &extra_tags, confidence, kind, namespace)?.is_some() {
+                imported += 1;
+            }
+        }
+        Ok::<_, Error>(imported)
+    })?;
+
+    println!("Imported {} of {} items", imported, total);
+    if imported < total {
+        println!("  skipped {} already imported", total - imported);
+    }
+
+    Ok(())
+}
+
+fn split_tags(tags: &str) -> Vec<String> {
+    tags.split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// Store an item with `extra_tags`, unless its source was already imported
+fn store_item(
+    mem: &Memories,
+    item: ImportItem,
+    extra_tags: &[String],
+    confidence: f64,
+    kind: Option<&str>,
+    namespace: Option<&str>,
+) -> Result<Option<i64>, Error> {
+    if !item.source.is_empty() && mem.has_source(&item.source)? {
+        return Ok(None);
+    }
+
+    let mut item_tags = item.tags;
+    for t in extra_tags {
+        if !item_tags.contains(t) {
+            item_tags.push(t.clone());
+        }
+    }
+
+    let id = mem.remember(&NewMemory {
+        content: item.content,
+        confidence,
+        tags: item_tags,
+        kind: kind.map(String::from),
+        source: Some(item.source).filter(|s| !s.is_empty()),
+        namespace: namespace.map(String::from),
+        ..Default::default()
+    })?;
+    Ok(Some(id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::ChunkerSpec;
+    use crate::test_support;
+
+    /// Heading chunks with `tags` and `confidence`
+    fn doc_options(tags: &str, confidence: f64) -> DocOptions {
+        DocOptions {
+            chunker: ChunkerSpec {
+                name: "heading".to_string(),
+                size: 500,
+                overlap: 0,
+            },
+            tags: tags.to_string(),
+            confidence,
+        }
+    }
+
+    #[test]
+    fn test_undo_update_ingest_restores_file_hashes() {
+        let dir = test_support::scratch("ingest-undo");
+        let doc = dir.join("notes.md");
+        fs::write(&doc, "# Deploy\nRun make deploy.\n").unwrap();
+
+        let mem = test_support::store(&dir);
+        let options = doc_options("", 0.5);
+        let paths = vec![doc.display().to_string()];
+        let ingest = || ingest_docs(&mem, &paths, &options, true).unwrap();
+        let chunks = || {
+            let filter = MemoryFilter {
+                kind: Some(DOC_KIND.to_string()),
+                ..Default::default()
+            };
+            mem.list(&filter, 10, SortOrder::Recent).unwrap()
+        };
+
+        assert_eq!(ingest().stored, 1);
+        fs::write(&doc, "# Deploy\nRun make release.\n\n# Test\nRun make test.\n").unwrap();
+        assert_eq!(ingest().stored, 2);
+
+        // One step takes back the whole update, file hashes included
+        let step = mem.undo().unwrap().unwrap();
+        assert_eq!(step.op, "ingest");
+        assert_eq!((step.created.len(), step.before.len()), (2, 1));
+        let restored = chunks();
+        assert_eq!(restored.len(), 1);
+        assert!(restored[0].content.contains("make deploy"));
+
+        // So the changed file is picked up again
+        let report = ingest();
+        assert_eq!((report.unchanged, report.stored), (0, 2));
+        assert_eq!(chunks().len(), 2);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_update_ingest_tracks_changed_and_removed_files() {
+        let dir = test_support::scratch("ingest-update");
+        let docs = dir.join("docs");
+        let other = dir.join("docs2");
+        fs::create_dir_all(&docs).unwrap();
+        fs::create_dir_all(&other).unwrap();
+        fs::write(docs.join("a.md"), "# A\nAlpha.\n").unwrap();
+        fs::write(docs.join("b.md"), "# B\nBravo.\n").unwrap();
+        fs::write(docs.join("c.bin"), [0xff, 0xfe, 0x00]).unwrap();
+        fs::write(other.join("d.md"), "# D\nDelta.\n").unwrap();
+
+        let mem = test_support::store(&dir);
+        let options = doc_options("", 0.5);
+        let ingest = |paths: &[&std::path::Path], update: bool| {
+            let paths: Vec<String> = paths.iter().map(|p| p.display().to_string()).collect();
+            ingest_docs(&mem, &paths, &options, update)
+        };
+        let sources = || {
+            let filter = MemoryFilter {
+                kind: Some(DOC_KIND.to_string()),
+                ..Default::default()
+            };
+            let mut sources: Vec<String> = mem
+                .list(&filter, 10, SortOrder::Recent)
+                .unwrap()
+                .into_iter()
+                .filter_map(|m| m.source)
+                .map(|s| s.rsplit('/').next().unwrap().to_string())
+                .collect();
+            sources.sort();
+            sources
+        };
+
+        // The file that isn't UTF-8 is passed over
+        let first = ingest(&[&docs, &other], true).unwrap();
+        assert_eq!((first.files, first.stored, first.unchanged), (3, 3, 0));
+
+        // Only the changed file is chunked again, its old chunk replaced
+        fs::write(docs.join("a.md"), "# A\nAlpha, revised.\n").unwrap();
+        let second = ingest(&[&docs, &other], true).unwrap();
+        assert_eq!((second.files, second.stored, second.unchanged), (1, 1, 2));
+        assert_eq!(sources(), ["a.md#L1-L2", "b.md#L1-L2", "d.md#L1-L2"]);
+
+        // A removed file loses its chunks, but only under the paths given:
+        // docs2 isn't inside docs
+        fs::remove_file(docs.join("b.md")).unwrap();
+        let third = ingest(&[&docs], true).unwrap();
+        assert_eq!((third.files, third.unchanged, third.removed), (0, 1, 1));
+        assert_eq!(sources(), ["a.md#L1-L2", "d.md#L1-L2"]);
+
+        // A directory removed since is only missing under --update
+        fs::remove_dir_all(&other).unwrap();
+        assert_eq!(ingest(&[&other], false).unwrap_err().exit_code(), 4);
+        assert_eq!(ingest(&[&other], true).unwrap().removed, 1);
+        assert_eq!(sources(), ["a.md#L1-L2"]);
+
+        // Without --update nothing is skipped by hash, but chunks already
+        // imported aren't stored twice
+        let again = ingest(&[&docs], false).unwrap();
+        assert_eq!((again.files, again.stored), (1, 0));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_manifest_keys_are_relative_to_the_project() {
+        let dir = test_support::scratch("ingest-keys");
+        let docs = dir.join("docs");
+        fs::create_dir_all(&docs).unwrap();
+        fs::write(docs.join("a.md"), "# A\nAlpha.\n").unwrap();
+        fs::write(docs.join(".hidden.md"), "# H\nHidden.\n").unwrap();
+
+        let mem = test_support::store(&dir);
+        let options = doc_options("", 0.5);
+        let ingest = |path: &std::path::Path| {
+            ingest_docs(&mem, &[path.display().to_string()], &options, true).unwrap()
+        };
+        let keys = || mem.ingested_files().unwrap().into_keys().collect::<Vec<_>>();
+
+        // However the path is spelled, the file is docs/a.md
+        assert_eq!(ingest(&dir.join("docs/./../docs")).files, 1);
+        assert_eq!(keys(), ["docs/a.md"]);
+        assert_eq!(ingest(&docs.join("a.md")).unchanged, 1);
+        assert_eq!(resolve(&dir.join("docs/gone/../a.md")), resolve(&docs.join("a.md")));
+
+        // A file still on disk isn't forgotten because it wasn't found
+        assert_eq!(ingest(&docs.join(".hidden.md")).files, 1);
+        let report = ingest(&docs);
+        assert_eq!((report.unchanged, report.removed), (1, 0));
+        assert_eq!(keys(), ["docs/.hidden.md", "docs/a.md"]);
+
+        // Entries keyed before paths were relative to the project are
+        // rekeyed rather than taken for removed files
+        let mut legacy = IngestManifest::new();
+        legacy.insert("./docs/a.md".to_string(), mem.ingested_files().unwrap()["docs/a.md"].clone());
+        mem.set_ingested_files(&legacy).unwrap();
+        let report = ingest(&docs);
+        assert_eq!((report.files, report.unchanged, report.removed), (0, 1, 0));
+        assert_eq!(keys(), ["docs/a.md"]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_reingest_keeps_each_files_options() {
+        let dir = test_support::scratch("reingest");
+        fs::write(dir.join("ops.md"), "# Ops\nDeploy on Fridays.\n").unwrap();
+        fs::write(dir.join("old.md"), "# Old\nLegacy notes.\n").unwrap();
+
+        let mem = test_support::store(&dir);
+        let ops = dir.join("ops.md").display().to_string();
+        ingest_docs(&mem, &[ops], &doc_options("runbook", 0.9), false).unwrap();
+        // A manifest from before options were kept has only hashes
+        let mut manifest = mem.ingested_files().unwrap();
+        let legacy: IngestManifest = serde_json::from_str(r#"{"old.md": "stale"}"#).unwrap();
+        assert_eq!(legacy["old.md"].options, None);
+        manifest.extend(legacy);
+        mem.set_ingested_files(&manifest).unwrap();
+
+        fs::write(dir.join("ops.md"), "# Ops\nNever deploy on Fridays.\n").unwrap();
+        let report = reingest_docs(&mem, &doc_options("", 0.5)).unwrap();
+        assert_eq!((report.files, report.removed), (2, 0));
+
+        let chunk = |text: &str| {
+            let all = mem.list(&MemoryFilter::everything(), 10, SortOrder::Recent).unwrap();
+            all.into_iter().find(|m| m.content.contains(text)).unwrap()
+        };
+        let ops = chunk("Never deploy");
+        assert_eq!((ops.tags, ops.confidence), (vec!["runbook".to_string()], 0.9));
+        let old = chunk("Legacy");
+        assert_eq!((old.tags.len(), old.confidence), (0, 0.5));
+        // Nothing changed since, so nothing is ingested again
+        assert_eq!(reingest_docs(&mem, &doc_options("", 0.5)).unwrap().unchanged, 2);
+        assert_eq!(
+            mem.ingested_files().unwrap()["old.md"].options,
+            Some(doc_options("", 0.5))
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -1,169 +1,301 @@
-use crate::memory::Memories;
+use crate::aggregate::{Activity, ACCESS_TOP};
+use crate::cli::doctor::format_size;
+use crate::cli::{print_json, Output};
+use crate::config::Ranking;
+use crate::error::Error;
+use crate::lock::MaintenanceLock;
+use crate::memory::{Memories, ReindexScope};
+use crate::picker;
+use crate::proto;
+use crate::query_log::QuerySummary;
+use crate::resolve::{self, Resolution, Resolver, Strategy};
+use crate::draft::{self, Draft};
+use crate::term::{self, Progress};
+use crate::types::{
+    Attachment, Memory, MemoryFilter, MemoryUpdate, NewMemory, PruneCriteria, ScoreParts, SearchResult,
+    SortOrder, TagMatch, Tombstone, MEMORY_KINDS, QUARANTINE_NAMESPACE, SNIPPET_END, SNIPPET_START,
+};
+use serde::Deserialize;
 use std::fs;
-use std::io::{self, Write};
+use std::io::{self, Read, Write};
 use std::path::Path;
+use std::time::Instant;
+
+/// Score a streamed recall hit must reach when no --threshold is given
+const STREAM_THRESHOLD: f64 = 0.5;
+
+/// Most recent memories offered by --pick
+const PICK_LIMIT: usize = 5000;
 
 /// Run the init command
-pub fn run_init(path: &str, hooks: bool) -> Result<(), String> {
+pub fn run_init(path: &str, hooks: bool) -> Result<(), Error> {
     let path = Path::new(path);
     let roots_path = path.join(".roots");
 
     if roots_path.exists() {
-        return Err(format!(
+        // Re-running init still (re)registers the project for --all-projects
+        let _ = crate::config::register_project(&roots_path);
+        return Err(Error::Invalid(format!(
             ".roots already exists at {}",
             roots_path.display()
-        ));
+        )));
     }
 
     let mem = Memories::init(path)?;
     println!("Initialized .roots at {}", mem.roots_path().display());
 
+    if let Err(e) = crate::config::register_project(mem.roots_path()) {
+        eprintln!("Warning: could not register project for cross-project search: {}", e);
+    }
+
     if hooks {
-        install_hooks(path, "none")?;
+        let settings_path = path.join(".claude").join("settings.json");
+        crate::cli::hooks::install_hooks(&settings_path, "none", Default::default())?;
     }
 
     Ok(())
 }
 
-/// Run the hooks command
-pub fn run_hooks(path: &str, remove: bool, context_mode: &str) -> Result<(), String> {
-    let path = Path::new(path);
+/// How `remember` stores a memory
+pub struct RememberOptions {
+    /// Comma-separated tags
+    pub tags: String,
+    pub expires: Option<String>,
+    pub origin: String,
+    pub global: bool,
+    pub defer: bool,
+    /// Store only if worth it, tagging untagged memories with the existing
+    /// tags they name
+    pub auto: bool,
+    /// Write the memory in $EDITOR first
+    pub edit: bool,
+    /// Take the content from a Claude Code hook payload on stdin
+    pub hook_stdin: bool,
+    /// Remember every memory in this file instead
+    pub from_file: Option<String>,
+}
 
-    if remove {
-        remove_hooks(path)
-    } else {
-        install_hooks(path, context_mode)
+/// Run the remember command
+pub fn run_remember(mut memory: NewMemory, options: RememberOptions) -> Result<(), Error> {
+    let RememberOptions {
+        mut tags,
+        expires,
+        origin,
+        global,
+        defer,
+        auto,
+        edit,
+        hook_stdin,
+        from_file,
+    } = options;
+    let expires_at = expires
+        .as_deref()
+        .map(|e| crate::dates::parse_duration(e).and_then(crate::dates::from_now))
+        .transpose()?;
+
+    if let Some(path) = from_file {
+        memory.tags = tags.split(',').map(|t| t.trim().to_string()).filter(|t| !t.is_empty()).collect();
+        memory.expires_at = expires_at;
+        return remember_file(Path::new(&path), &memory, &origin, global, defer);
     }
-}
 
-fn install_hooks(path: &Path, context_mode: &str) -> Result<(), String> {
-    let claude_dir = path.join(".claude");
-    fs::create_dir_all(&claude_dir)
-        .map_err(|e| format!("Failed to create .claude directory: {}", e))?;
+    // `-` takes the content from a pipe, newlines and all
+    if memory.content == "-" {
+        let mut input = String::new();
+        io::stdin()
+            .read_to_string(&mut input)
+            .map_err(|e| format!("Failed to read stdin: {}", e))?;
+        memory.content = input.trim().to_string();
+        if memory.content.is_empty() {
+            return Err(Error::Invalid("Nothing to remember: stdin was empty".to_string()));
+        }
+    }
+
+    if edit {
+        let defaults = Draft {
+            content: memory.content.clone(),
+            tags: tags.split(',').map(|t| t.trim().to_string()).filter(|t| !t.is_empty()).collect(),
+            confidence: memory.confidence,
+            kind: memory.kind.clone(),
+        };
+        let draft = draft::parse(&term::edit_text(&draft::template(&defaults))?, &defaults)?;
+        if draft.content.is_empty() {
+            return Err(Error::Invalid("Nothing to remember: the memory was left empty".to_string()));
+        }
+        if let Some(kind) = draft.kind.as_deref().filter(|k| !MEMORY_KINDS.contains(k)) {
+            return Err(Error::Invalid(format!("Unknown kind: {} (use {})", kind, MEMORY_KINDS.join(", "))));
+        }
+        memory.content = draft.content;
+        memory.confidence = draft.confidence;
+        memory.kind = draft.kind;
+        tags = draft.tags.join(",");
+    }
 
-    let settings_path = claude_dir.join("settings.json");
+    if hook_stdin {
+        let hook = crate::hook::HookInput::read()?;
+        match hook.capture() {
+            Some(content) => memory.content = content,
+            None => return Ok(()),
+        }
+        memory.session_id = hook.session_id.or(memory.session_id);
+    }
 
-    // Read existing settings or create new
-    let mut settings: serde_json::Value = if settings_path.exists() {
-        let content = fs::read_to_string(&settings_path)
-            .map_err(|e| format!("Failed to read settings: {}", e))?;
-        serde_json::from_str(&content).unwrap_or(serde_json::json!({}))
+    let opened = if global {
+        Memories::open_global()
     } else {
-        serde_json::json!({})
+        Memories::open()
+    };
+    let mem = match opened {
+        Ok(mem) => mem,
+        // Global hooks run in projects without a store
+        Err(_) if hook_stdin => return Ok(()),
+        Err(e) => return Err(e),
     };
 
-    // Add hooks
-    let hooks = settings
-        .as_object_mut()
-        .ok_or("Invalid settings format")?
-        .entry("hooks")
-        .or_insert(serde_json::json!({}));
-
-    let hooks_obj = hooks.as_object_mut().ok_or("Invalid hooks format")?;
-
-    // SessionStart hook
-    hooks_obj.insert(
-        "SessionStart".to_string(),
-        serde_json::json!([{
-            "matcher": "",
-            "hooks": [{
-                "type": "command",
-                "command": "roots prime"
-            }]
-        }]),
-    );
+    if auto {
+        let assessment = mem.assess_worthiness(&memory.content)?;
+        let threshold = mem.capture_threshold();
+        if assessment.score < threshold {
+            crate::worthiness::log_rejection(mem.roots_path(), &memory.content, &assessment, threshold)?;
+            println!(
+                "Not remembered: worthiness {:.2} is below capture_threshold {:.2}",
+                assessment.score, threshold
+            );
+            println!(
+                "  length {:.2}, novelty {:.2}, cues {:.2}",
+                assessment.length, assessment.novelty, assessment.cues
+            );
+            if let Some(id) = assessment.nearest.filter(|_| assessment.novelty < 0.1) {
+                println!("  closest existing memory: [{}]", id);
+            }
+            return Ok(());
+        }
+    }
 
-    // PreCompact hook
-    hooks_obj.insert(
-        "PreCompact".to_string(),
-        serde_json::json!([{
-            "matcher": "",
-            "hooks": [{
-                "type": "command",
-                "command": "roots prime"
-            }]
-        }]),
-    );
+    // Borrowing tags from neighbors embeds the content, which deferring avoids
+    let defer = defer || mem.defers_embeddings();
+    let suggested = if tags.is_empty() && !auto && mem.auto_tag_mode() != "off" {
+        mem.suggest_tags(&memory.content, !defer)?
+    } else {
+        Vec::new()
+    };
+    let tags_vec: Vec<String> = if !tags.is_empty() {
+        tags.split(',').map(|s| s.trim().to_string()).collect()
+    } else if auto {
+        mem.auto_tags(&memory.content)?
+    } else if mem.auto_tag_mode() == "apply" {
+        suggested.clone()
+    } else {
+        Vec::new()
+    };
+
+    memory.tags = tags_vec.clone();
+    memory.expires_at = expires_at.clone();
+    let kind = memory.kind.clone();
+    let quarantined = mem.apply_write_policy(&mut memory, &origin);
+    let id = if defer {
+        mem.remember_deferred(&memory)?
+    } else {
+        mem.remember(&memory)?
+    };
 
-    // UserPromptSubmit hook for context on each message
-    if context_mode != "none" {
-        let cmd = format!("roots context --mode {} \"$CLAUDE_USER_PROMPT\"", context_mode);
-        hooks_obj.insert(
-            "UserPromptSubmit".to_string(),
-            serde_json::json!([{
-                "matcher": "",
-                "hooks": [{
-                    "type": "command",
-                    "command": cmd
-                }]
-            }]),
+    if global {
+        println!("Remembered [{}] in global store ({})", id, mem.roots_path().display());
+    } else {
+        println!("Remembered [{}]", id);
+    }
+    if quarantined {
+        println!(
+            "  quarantined: {} writes need tags or a kind (review with: roots list --namespace {})",
+            origin, QUARANTINE_NAMESPACE
         );
     }
-
-    // Write settings
-    let json = serde_json::to_string_pretty(&settings)
-        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
-    fs::write(&settings_path, json)
-        .map_err(|e| format!("Failed to write settings: {}", e))?;
-
-    println!("Hooks installed:");
-    println!("  SessionStart: roots prime");
-    println!("  PreCompact:   roots prime");
-    if context_mode != "none" {
-        println!("  UserPromptSubmit: roots context --mode {}", context_mode);
+    if let Some(k) = kind {
+        println!("  kind: {}", k);
     }
-
-    Ok(())
-}
-
-fn remove_hooks(path: &Path) -> Result<(), String> {
-    let settings_path = path.join(".claude").join("settings.json");
-
-    if !settings_path.exists() {
-        println!("No hooks configured.");
-        return Ok(());
+    if let Some(e) = &expires_at {
+        println!("  expires: {}", crate::dates::date_part(e));
     }
-
-    let content =
-        fs::read_to_string(&settings_path).map_err(|e| format!("Failed to read settings: {}", e))?;
-    let mut settings: serde_json::Value =
-        serde_json::from_str(&content).map_err(|e| format!("Failed to parse settings: {}", e))?;
-
-    if let Some(obj) = settings.as_object_mut() {
-        obj.remove("hooks");
+    if !quarantined {
+        if let Some(ns) = mem.get(id)?.and_then(|m| m.namespace) {
+            println!("  namespace: {}", ns);
+        }
+    }
+    if !tags_vec.is_empty() {
+        println!("  tags: {}", tags_vec.join(", "));
+    } else if !suggested.is_empty() {
+        println!(
+            "  suggested tags: {} (apply with: roots update {} --tags {})",
+            suggested.join(", "),
+            id,
+            suggested.join(",")
+        );
+    }
+    if defer {
+        println!("  embedding: queued");
+        if let Err(e) = crate::cli::embed_queue::spawn_worker(mem.roots_path()) {
+            eprintln!("Warning: {} (run 'roots embed-queue run')", e);
+        }
     }
 
-    let json = serde_json::to_string_pretty(&settings)
-        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
-    fs::write(&settings_path, json).map_err(|e| format!("Failed to write settings: {}", e))?;
-
-    println!("Hooks removed.");
     Ok(())
 }
 
-/// Run the remember command
-pub fn run_remember(content: &str, tags: &str, confidence: f64) -> Result<(), String> {
-    let mem = Memories::open()?;
+/// Remember every memory in a `--from-file` file, each starting from
+/// `defaults`
+fn remember_file(path: &Path, defaults: &NewMemory, origin: &str, global: bool, defer: bool) -> Result<(), Error> {
+    let text =
+        fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let mut memories = crate::batch::parse(path, &text, defaults)?;
+    if memories.is_empty() {
+        return Err(Error::Invalid(format!("No memories found in {}", path.display())));
+    }
 
-    let tags_vec: Vec<String> = if tags.is_empty() {
-        Vec::new()
+    let mem = if global {
+        Memories::open_global()?
     } else {
-        tags.split(',').map(|s| s.trim().to_string()).collect()
+        Memories::open()?
     };
-
-    let id = mem.remember(content, confidence, &tags_vec)?;
-
-    println!("Remembered [{}]", id);
-    if !tags_vec.is_empty() {
-        println!("  tags: {}", tags_vec.join(", "));
+    let mut quarantined = 0;
+    for memory in &mut memories {
+        if mem.apply_write_policy(memory, origin) {
+            quarantined += 1;
+        }
+    }
+    let defer = defer || mem.defers_embeddings();
+    let ids = mem.remember_batch(&memories, defer)?;
+
+    println!("Remembered {} memories from {}", ids.len(), path.display());
+    if quarantined > 0 {
+        println!(
+            "  quarantined: {} {} writes need tags or a kind (review with: roots list --namespace {})",
+            quarantined, origin, QUARANTINE_NAMESPACE
+        );
+    }
+    if defer {
+        println!("  embeddings: queued");
+        if let Err(e) = crate::cli::embed_queue::spawn_worker(mem.roots_path()) {
+            eprintln!("Warning: {} (run 'roots embed-queue run')", e);
+        }
     }
-
     Ok(())
 }
 
 /// Run the recall command
-pub fn run_recall(query: Option<&str>, tag: Option<&str>, limit: usize) -> Result<(), String> {
+pub fn run_recall(
+    query: Option<&str>,
+    mut filter: MemoryFilter,
+    limit: usize,
+    threshold: Option<f64>,
+    explain: bool,
+    rerank: bool,
+    output: Output,
+) -> Result<(), Error> {
     let mem = Memories::open()?;
+    if !fuzzy_tag(&mem, &mut filter, output)? {
+        return Ok(());
+    }
+    let tag = filter.tag.as_deref();
 
     // Check for embedding model mismatch
     if let Some(stored) = mem.check_model_mismatch()? {
@@ -171,35 +303,65 @@ pub fn run_recall(query: Option<&str>, tag: Option<&str>, limit: usize) -> Resul
         eprintln!("Run 'roots reindex' to rebuild embeddings for better search quality.\n");
     }
 
-    if let Some(t) = tag {
-        // Search by tag
-        let memories = mem.recall_by_tag(t, limit)?;
-
-        if memories.is_empty() {
-            println!("No memories with tag: {}", t);
-            return Ok(());
+    if let Some(q) = query {
+        // Semantic search, among memories with the tag if one is given
+        let started = Instant::now();
+        let rerank = rerank || (mem.rerank_by_default() && !explain);
+        let mut results = if rerank {
+            let mut results = mem.recall(q, &filter, mem.rerank_candidates(limit))?;
+            mem.rerank(q, &mut results)?;
+            results.truncate(limit);
+            results
+        } else {
+            mem.recall(q, &filter, limit)?
+        };
+        report_recall_timing(&mem);
+        if let Some(threshold) = threshold {
+            results.retain(|r| r.score >= threshold);
         }
+        mem.log_query("recall", q, results.iter().map(|r| r.score), started);
 
-        println!("Memories tagged '{}':\n", t);
-        for m in memories {
-            print_memory(&m);
+        if output.is_json() {
+            mem.record_hits(&results)?;
+            return print_json(&results.iter().map(hit).collect::<Vec<_>>());
         }
-    } else if let Some(q) = query {
-        // Semantic search
-        let results = mem.recall(q, limit)?;
-
         if results.is_empty() {
             println!("No matching memories.");
             return Ok(());
         }
 
+        mem.record_hits(&results)?;
+
+        let ranking = explain.then(|| mem.ranking());
         for r in results {
-            print_memory_with_score(&r.memory, r.score);
+            print_memory_with_score(&r, q, ranking);
+        }
+    } else if let Some(t) = tag {
+        // List by tag
+        let memories = mem.list_layered(&filter, limit, SortOrder::Recent)?;
+
+        if output.is_json() {
+            mem.record_access(&memories)?;
+            return print_json(&memories.iter().map(proto::Memory::from).collect::<Vec<_>>());
+        }
+        if memories.is_empty() {
+            println!("No memories with tag: {}", t);
+            return Ok(());
+        }
+
+        mem.record_access(&memories)?;
+
+        println!("Memories tagged '{}':\n", t);
+        for m in memories {
+            print_memory(&m);
         }
     } else {
         // Show recent
-        let memories = mem.list(limit)?;
+        let memories = mem.list_layered(&filter, limit, SortOrder::Recent)?;
 
+        if output.is_json() {
+            return print_json(&memories.iter().map(proto::Memory::from).collect::<Vec<_>>());
+        }
         if memories.is_empty() {
             println!("No memories yet. Add one with: roots remember \"...\"");
             return Ok(());
@@ -214,13 +376,196 @@ pub fn run_recall(query: Option<&str>, tag: Option<&str>, limit: usize) -> Resul
     Ok(())
 }
 
+/// Run `recall --stream` - print hits as the scan reaches them
+pub fn run_recall_stream(
+    query: &str,
+    filter: MemoryFilter,
+    limit: usize,
+    threshold: Option<f64>,
+    output: Output,
+) -> Result<(), Error> {
+    let mem = Memories::open()?;
+    let started = Instant::now();
+    let mut hits = Vec::new();
+    let mut scores = Vec::new();
+    mem.recall_stream(query, &filter, threshold.unwrap_or(STREAM_THRESHOLD), limit, |r| {
+        // One hit per line as JSON, for reading as they arrive
+        match output {
+            Output::Json => println!("{}", serde_json::to_string(&hit(r)).unwrap_or_default()),
+            Output::Text => print_memory_with_score(r, query, None),
+        }
+        let _ = io::stdout().flush();
+        hits.push(r.clone());
+        scores.push(r.score);
+    })?;
+    mem.log_query("recall", query, scores, started);
+
+    if hits.is_empty() && !output.is_json() {
+        println!("No matching memories.");
+        return Ok(());
+    }
+    Ok(mem.record_hits(&hits)?)
+}
+
+/// Run `recall --fts` - exact-term search, best BM25 match first
+pub fn run_recall_fts(query: &str, mut filter: MemoryFilter, limit: usize, output: Output) -> Result<(), Error> {
+    let mem = Memories::open()?;
+    if !fuzzy_tag(&mem, &mut filter, output)? {
+        return Ok(());
+    }
+    let started = Instant::now();
+    let matches = mem.search_text(query, &filter, limit)?;
+    mem.log_query("fts", query, matches.iter().map(|m| m.score), started);
+
+    if output.is_json() {
+        mem.record_access(matches.iter().map(|m| &m.memory))?;
+        let hits: Vec<proto::Hit> = matches
+            .iter()
+            .map(|m| proto::Hit {
+                memory: proto::Memory::from(&m.memory),
+                score: m.score,
+                snippet: Some(m.snippet.replace([SNIPPET_START, SNIPPET_END], "**")),
+                project: None,
+            })
+            .collect();
+        return print_json(&hits);
+    }
+    if matches.is_empty() {
+        println!("No memories contain: {}", query);
+        return Ok(());
+    }
+    mem.record_access(matches.iter().map(|m| &m.memory))?;
+
+    for m in matches {
+        println!(
+            "[{}]{} bm25: {:.2}, confidence: {:.2}",
+            m.memory.id,
+            global_marker(&m.memory),
+            m.score,
+            m.memory.confidence
+        );
+        if !m.memory.tags.is_empty() {
+            println!("    tags: {}", m.memory.tags.join(", "));
+        }
+        println!("    {}\n", crate::term::highlight_snippet(&m.snippet));
+    }
+    Ok(())
+}
+
+/// Run `recall --all-projects` - search every registered project store
+pub fn run_recall_all_projects(query: &str, filter: MemoryFilter, limit: usize, output: Output) -> Result<(), Error> {
+    let mut stores = crate::config::registered_projects();
+    if let Some(current) = crate::config::find_roots_path().and_then(|p| p.canonicalize().ok()) {
+        if !stores.contains(&current) {
+            stores.push(current);
+        }
+    }
+
+    let mut opened = Vec::new();
+    let mut results = Vec::new();
+    for roots_path in stores.into_iter().filter(|p| p.is_dir()) {
+        let project = project_label(&roots_path);
+        let mem = match Memories::open_at(roots_path) {
+            Ok(mem) => mem,
+            Err(e) => {
+                eprintln!("Skipping {}: {}", project, e);
+                continue;
+            }
+        };
+
+        let found = mem.recall(query, &filter, limit)?;
+        results.extend(found.into_iter().map(|r| (project.clone(), opened.len(), r)));
+        opened.push(mem);
+    }
+
+    if results.is_empty() && !output.is_json() {
+        println!("No matching memories in any project.");
+        return Ok(());
+    }
+
+    results.sort_by(|a, b| b.2.score.partial_cmp(&a.2.score).unwrap_or(std::cmp::Ordering::Equal));
+    results.truncate(limit);
+    // Only what is shown counts as an access, in the store it came from
+    for (i, mem) in opened.iter().enumerate() {
+        mem.record_hits(results.iter().filter(|(_, store, _)| *store == i).map(|(_, _, r)| r))?;
+    }
+    if output.is_json() {
+        let hits: Vec<proto::Hit> = results
+            .into_iter()
+            .map(|(project, _, r)| proto::Hit {
+                project: Some(project),
+                ..hit(&r)
+            })
+            .collect();
+        return print_json(&hits);
+    }
+    for (project, _, r) in results {
+        println!("{}:", project);
+        print_memory_with_score(&r, query, None);
+    }
+
+    Ok(())
+}
+
+/// Project name for a `.roots` directory: its parent directory, with ~ for home
+fn project_label(roots_path: &Path) -> String {
+    let project = roots_path.parent().unwrap_or(roots_path);
+    match dirs::home_dir().and_then(|home| project.strip_prefix(home).ok().map(Path::to_path_buf)) {
+        Some(rel) => format!("~/{}", rel.display()),
+        None => project.display().to_string(),
+    }
+}
+
+/// Print recall timings (with `ROOTS_TIMING`) and any slow-query hint to stderr
+pub fn report_recall_timing(mem: &Memories) {
+    if crate::timing::timing_enabled() {
+        if let Some(t) = mem.last_recall_timings() {
+            eprintln!("[timing] recall: {}", t.summary());
+        }
+    }
+    if let Some(hint) = mem.slow_query_hint() {
+        eprintln!("{}", hint);
+    }
+}
+
+/// The memory a command acts on: `id`, or with none given, the one the
+/// user picks from the most recent memories. `None` if they cancel.
+fn pick_target(mem: &Memories, id: Option<i64>, action: &str) -> Result<Option<i64>, Error> {
+    if let Some(id) = id {
+        return Ok(Some(id));
+    }
+    if !term::is_interactive() {
+        return Err(Error::Invalid("--pick needs an interactive terminal".to_string()));
+    }
+
+    let memories = mem.list(&MemoryFilter::everything(), PICK_LIMIT, SortOrder::Recent)?;
+    if memories.is_empty() {
+        return Err(Error::NotFound("No memories to pick from".to_string()));
+    }
+    let entries: Vec<String> = memories
+        .iter()
+        .map(|m| {
+            let mut entry = format!("[{}] {}", m.id, first_line(&m.content));
+            if !m.tags.is_empty() {
+                entry.push_str(&format!("  ({})", m.tags.join(", ")));
+            }
+            entry
+        })
+        .collect();
+    Ok(picker::pick(action, &entries)?.map(|i| memories[i].id))
+}
+
 /// Run the forget command
-pub fn run_forget(id: i64, force: bool) -> Result<(), String> {
+pub fn run_forget(id: Option<i64>, force: bool) -> Result<(), Error> {
     let mem = Memories::open()?;
+    let Some(id) = pick_target(&mem, id, "Forget")? else {
+        println!("Cancelled.");
+        return Ok(());
+    };
 
     let memory = mem
         .get(id)?
-        .ok_or_else(|| format!("Memory not found: {}", id))?;
+        .ok_or_else(|| Error::NotFound(format!("Memory not found: {}", id)))?;
 
     if !force {
         println!("Forget [{}]:", id);
@@ -245,13 +590,188 @@ pub fn run_forget(id: i64, force: bool) -> Result<(), String> {
     Ok(())
 }
 
+/// Run the undo command
+pub fn run_undo() -> Result<(), Error> {
+    let mem = Memories::open()?;
+
+    let Some(step) = mem.undo()? else {
+        println!("Nothing to undo.");
+        return Ok(());
+    };
+    let list = |ids: &mut dyn Iterator<Item = i64>| ids.map(|id| format!("[{}]", id)).collect::<Vec<_>>().join(", ");
+    let mut done = Vec::new();
+    if !step.created.is_empty() {
+        done.push(format!("forgot {}", list(&mut step.created.iter().copied())));
+    }
+    if !step.before.is_empty() {
+        let verb = if step.op == "update" { "reverted" } else { "restored" };
+        done.push(format!("{} {}", verb, list(&mut step.before.iter().map(|s| s.memory.id))));
+    }
+    if step.ingested_files.is_some() {
+        done.push("restored ingested file hashes".to_string());
+    }
+    println!("Undid {} from {}: {}", step.op, step.at, done.join("; "));
+
+    Ok(())
+}
+
+/// Run the edit command - rewrite a memory's content in $EDITOR
+pub fn run_edit(id: Option<i64>) -> Result<(), Error> {
+    let mem = Memories::open()?;
+    let Some(id) = pick_target(&mem, id, "Edit")? else {
+        println!("Cancelled.");
+        return Ok(());
+    };
+
+    let mut memory = mem
+        .get(id)?
+        .ok_or_else(|| Error::NotFound(format!("Memory not found: {}", id)))?;
+    mem.hydrate(&mut memory)?;
+
+    let content = term::edit_text(&memory.content)?;
+    if content.is_empty() || content == memory.content {
+        println!("Unchanged [{}]", id);
+        return Ok(());
+    }
+    mem.update(
+        id,
+        &MemoryUpdate {
+            content: Some(content),
+            ..Default::default()
+        },
+    )?;
+    println!("Updated [{}]", id);
+
+    Ok(())
+}
+
+/// Run the show command
+pub fn run_show(id: i64, output: Output) -> Result<(), Error> {
+    let mem = Memories::open()?;
+
+    let mut memory = mem
+        .get(id)?
+        .ok_or_else(|| Error::NotFound(format!("Memory not found: {}", id)))?;
+    let stored = memory.content.len();
+    mem.hydrate(&mut memory)?;
+
+    if output.is_json() {
+        return print_json(&proto::Detail {
+            memory: proto::Memory::from(&memory),
+            blob: memory.blob.clone(),
+            attachments: mem.attachments(id)?.iter().map(proto::Attachment::from).collect(),
+        });
+    }
+
+    println!("[{}] confidence: {:.2}", memory.id, memory.confidence);
+    for (label, value) in [
+        ("kind", memory.kind.clone()),
+        ("namespace", memory.namespace.clone()),
+        (
+            "source",
+            memory.source.as_deref().map(|s| {
+                let source = crate::source::Source::parse(s);
+                format!("{} ({})", source, source.kind())
+            }),
+        ),
+        ("session", memory.session_id.clone()),
+        ("expires", memory.expires_at.as_deref().map(|e| crate::dates::date_part(e).to_string())),
+        ("tags", Some(memory.tags.join(", ")).filter(|t| !t.is_empty())),
+    ] {
+        if let Some(value) = value {
+            println!("    {}: {}", label, value);
+        }
+    }
+    if memory.pinned {
+        println!("    pinned");
+    }
+    println!("    created: {}", memory.created_at);
+    println!("    updated: {}", memory.updated_at);
+    println!(
+        "    accessed: {} times{}",
+        memory.access_count,
+        memory
+            .last_accessed_at
+            .as_deref()
+            .map(|t| format!(", last {}", t))
+            .unwrap_or_default()
+    );
+    if let Some(hash) = &memory.blob {
+        println!(
+            "    blob: {} ({} bytes, {} in the index)",
+            hash,
+            memory.content.len(),
+            stored
+        );
+    }
+
+    println!("\n{}", memory.content);
+    for attachment in mem.attachments(id)? {
+        print_attachment(&mem, &attachment);
+    }
+    Ok(())
+}
+
+/// An attachment with its lines as they are now, or as attached when the
+/// file is gone or shorter
+fn print_attachment(mem: &Memories, attachment: &Attachment) {
+    let path = mem.attachment_path(attachment);
+    let current = fs::read_to_string(&path).ok();
+    let Some(range) = attachment.lines else {
+        let state = if current.is_some() { "" } else { " (missing)" };
+        println!("\n[attachment {}] {}{}", attachment.id, attachment.path, state);
+        return;
+    };
+
+    let now = current.as_deref().and_then(|text| crate::source::select_lines(text, range));
+    let state = match (&now, &attachment.snippet) {
+        (None, _) => " (missing; as attached)",
+        (Some(now), Some(then)) if now != then => " (changed since attached)",
+        _ => "",
+    };
+    println!("\n[attachment {}] {}:{}-{}{}", attachment.id, attachment.path, range.0, range.1, state);
+    let Some(text) = now.or_else(|| attachment.snippet.clone()) else {
+        return;
+    };
+    let width = range.1.to_string().len();
+    for (n, line) in (range.0..).zip(text.lines()) {
+        println!("{:>width$} | {}", n, line, width = width);
+    }
+}
+
+/// Run the attach command
+pub fn run_attach(id: i64, path: &Path, lines: Option<(u32, u32)>) -> Result<(), Error> {
+    let mem = Memories::open()?;
+    let attachment = mem.attach(id, path, lines)?;
+    let range = lines.map(|(start, end)| format!(":{}-{}", start, end)).unwrap_or_default();
+    println!("Attached {}{} to [{}] (attachment {})", path.display(), range, id, attachment);
+    Ok(())
+}
+
 /// Run the update command
-pub fn run_update(id: i64, confidence: Option<f64>, tags: Option<&str>) -> Result<(), String> {
+pub fn run_update(
+    id: i64,
+    confidence: Option<f64>,
+    tags: Option<&str>,
+    kind: Option<&str>,
+    namespace: Option<String>,
+    pinned: Option<bool>,
+) -> Result<(), Error> {
     let mem = Memories::open()?;
 
+    if let Some(k) = kind {
+        if !k.is_empty() && !MEMORY_KINDS.contains(&k) {
+            return Err(Error::Invalid(format!(
+                "Unknown kind: {} (expected one of: {})",
+                k,
+                MEMORY_KINDS.join(", ")
+            )));
+        }
+    }
+
     // Check if exists
     mem.get(id)?
-        .ok_or_else(|| format!("Memory not found: {}", id))?;
+        .ok_or_else(|| Error::NotFound(format!("Memory not found: {}", id)))?;
 
     let tags_vec: Option<Vec<String>> = tags.map(|t| {
         if t.is_empty() {
@@ -261,32 +781,84 @@ pub fn run_update(id: i64, confidence: Option<f64>, tags: Option<&str>) -> Resul
         }
     });
 
-    mem.update(id, confidence, tags_vec.as_deref())?;
+    let changes = MemoryUpdate {
+        confidence,
+        tags: tags_vec,
+        kind: kind.map(String::from),
+        namespace: namespace.clone(),
+        pinned,
+        ..Default::default()
+    };
+    mem.update(id, &changes)?;
 
     println!("Updated [{}]", id);
     if let Some(c) = confidence {
         println!("  confidence: {:.2}", c);
     }
+    if let Some(k) = kind {
+        println!("  kind: {}", if k.is_empty() { "(none)" } else { k });
+    }
     if let Some(t) = tags {
         println!("  tags: {}", t);
     }
+    if let Some(ns) = &namespace {
+        println!("  namespace: {}", if ns.is_empty() { "(default)" } else { ns });
+    }
 
     Ok(())
 }
 
+/// Resolve the filter's tag for recall and list: a tag no memory has
+/// matches the tags it starts, noted on stderr, and otherwise nothing can
+/// match, so say so with the closest tag and return false
+fn fuzzy_tag(mem: &Memories, filter: &mut MemoryFilter, output: Output) -> Result<bool, Error> {
+    match mem.resolve_tag(filter)? {
+        TagMatch::Exact => Ok(true),
+        TagMatch::Prefix(tags) => {
+            eprintln!("No tag '{}'; matching {}\n", filter.tag.as_deref().unwrap_or(""), tags.join(", "));
+            Ok(true)
+        }
+        TagMatch::Missing(closest) => {
+            let tag = filter.tag.as_deref().unwrap_or("");
+            let message = match closest {
+                Some(c) => format!("No memories with tag: {}. Did you mean '{}'?", tag, c),
+                None => format!("No memories with tag: {}", tag),
+            };
+            // Scripts still get a (empty) document on stdout
+            if output.is_json() {
+                eprintln!("{}", message);
+                println!("[]");
+            } else {
+                println!("{}", message);
+            }
+            Ok(false)
+        }
+    }
+}
+
 /// Run the list command
-pub fn run_list(tag: Option<&str>, limit: usize) -> Result<(), String> {
+pub fn run_list(mut filter: MemoryFilter, limit: usize, sort: &str, output: Output) -> Result<(), Error> {
     let mem = Memories::open()?;
+    if !fuzzy_tag(&mem, &mut filter, output)? {
+        return Ok(());
+    }
 
-    let memories = if let Some(t) = tag {
-        mem.recall_by_tag(t, limit)?
-    } else {
-        mem.list(limit)?
+    let order = match sort {
+        "importance" => {
+            mem.refresh_importance()?;
+            SortOrder::Importance
+        }
+        _ => SortOrder::Recent,
     };
 
+    let memories = mem.list(&filter, limit, order)?;
+
+    if output.is_json() {
+        return print_json(&memories.iter().map(proto::Memory::from).collect::<Vec<_>>());
+    }
     if memories.is_empty() {
-        if tag.is_some() {
-            println!("No memories with that tag.");
+        if filter.tag.is_some() || filter.kind.is_some() || filter.namespace.is_some() || filter.session.is_some() {
+            println!("No memories match that filter.");
         } else {
             println!("No memories yet.");
         }
@@ -300,35 +872,140 @@ pub fn run_list(tag: Option<&str>, limit: usize) -> Result<(), String> {
     Ok(())
 }
 
-/// Run the tags command
-pub fn run_tags() -> Result<(), String> {
+/// Run the prune command
+pub fn run_prune(
+    expired: bool,
+    below_confidence: Option<f64>,
+    unaccessed_for: Option<String>,
+    dry_run: bool,
+) -> Result<(), Error> {
+    let unaccessed_before = match unaccessed_for {
+        Some(age) => Some(crate::dates::from_now(-crate::dates::parse_duration(&age)?)?),
+        None => None,
+    };
+    let criteria = PruneCriteria {
+        expired,
+        below_confidence,
+        unaccessed_before,
+    };
+    if criteria.is_empty() {
+        return Err(Error::Invalid(
+            "Nothing to prune. Specify --expired, --below-confidence, or --unaccessed-for".to_string(),
+        ));
+    }
+
+    let mem = Memories::open()?;
+    let candidates = mem.prunable(&criteria)?;
+
+    if candidates.is_empty() {
+        println!("No memories match.");
+        return Ok(());
+    }
+
+    for m in &candidates {
+        let preview: String = first_line(&m.content).chars().take(60).collect();
+        let last_used = m.last_accessed_at.as_deref().unwrap_or(&m.created_at);
+        let mut detail = format!(
+            "conf {:.1}, last used {}",
+            m.confidence,
+            crate::dates::date_part(last_used)
+        );
+        if let Some(expires) = &m.expires_at {
+            detail.push_str(&format!(", expires {}", crate::dates::date_part(expires)));
+        }
+        println!("  [{}] ({}) {}", m.id, detail, preview);
+
+        if !dry_run {
+            mem.forget(m.id)?;
+        }
+    }
+
+    if dry_run {
+        println!("\nWould prune {} memories (dry run).", candidates.len());
+    } else {
+        println!("\nPruned {} memories.", candidates.len());
+    }
+
+    Ok(())
+}
+
+/// Run the sessions command: agent sessions with memories, latest first
+pub fn run_sessions(limit: usize) -> Result<(), Error> {
     let mem = Memories::open()?;
-    let tags = mem.tags()?;
+    let sessions = mem.sessions(limit)?;
 
-    if tags.is_empty() {
-        println!("No tags yet.");
+    if sessions.is_empty() {
+        println!("No sessions yet. Memories record one from hook payloads or ROOTS_SESSION.");
         return Ok(());
     }
 
-    println!("Tags:\n");
-    for (tag, count) in tags {
-        println!("  {:20} ({})", tag, count);
+    for s in sessions {
+        let first = crate::dates::date_part(&s.first);
+        let last = crate::dates::date_part(&s.last);
+        let span = if first == last {
+            first.to_string()
+        } else {
+            format!("{} to {}", first, last)
+        };
+        println!("  {:40} {:>4} memories  {}", s.id, s.count, span);
     }
+    println!("\nShow one with: roots list --session <id>");
 
     Ok(())
 }
 
 /// Run the stats command
-pub fn run_stats() -> Result<(), String> {
+pub fn run_stats(tag: Option<&str>, access: bool, queries: bool, output: Output) -> Result<(), Error> {
     let mem = Memories::open()?;
+    if let Some(tag) = tag {
+        return print_tag_health(&mem, tag, output);
+    }
+    if access {
+        return print_access(&mem, output);
+    }
+    if queries {
+        return print_queries(&mem, output);
+    }
     let stats = mem.stats()?;
 
+    if output.is_json() {
+        let mut tags: Vec<proto::TagCount> = stats
+            .by_tag
+            .iter()
+            .map(|(tag, &count)| proto::TagCount { tag: tag.clone(), count })
+            .collect();
+        tags.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.tag.cmp(&b.tag)));
+        let activity = |rows: &[Activity]| -> Vec<proto::Activity> {
+            rows.iter()
+                .map(|a| proto::Activity { period: a.period.clone(), added: a.added, forgotten: a.forgotten })
+                .collect()
+        };
+        return print_json(&proto::Stats {
+            total_memories: stats.total_memories,
+            total_tags: stats.total_tags,
+            avg_confidence: stats.avg_confidence,
+            avg_content_chars: stats.avg_content_chars,
+            forgotten: stats.forgotten,
+            db_bytes: stats.db_bytes,
+            tags,
+            weekly: activity(&stats.weekly),
+            monthly: activity(&stats.monthly),
+        });
+    }
+
     println!("Memory Statistics");
     println!("=================\n");
 
     println!("Total memories: {}", stats.total_memories);
+    println!("Forgotten:      {}", stats.forgotten);
     println!("Total tags:     {}", stats.total_tags);
     println!("Avg confidence: {:.2}", stats.avg_confidence);
+    println!("Avg length:     {:.0} chars", stats.avg_content_chars);
+    if let Some(bytes) = stats.db_bytes {
+        println!("Database size:  {}", format_size(bytes));
+    }
+    print_activity("Per week", &stats.weekly);
+    print_activity("Per month", &stats.monthly);
 
     if !stats.by_tag.is_empty() {
         println!("\nTop tags:");
@@ -343,13 +1020,261 @@ pub fn run_stats() -> Result<(), String> {
     Ok(())
 }
 
+/// Longest bar in the activity histograms
+const ACTIVITY_BAR: usize = 30;
+
+/// Added/forgotten counts per period, with `+` and `-` bars scaled to the
+/// busiest period
+fn print_activity(title: &str, rows: &[Activity]) {
+    if rows.is_empty() {
+        return;
+    }
+    let busiest = rows.iter().map(|a| a.added + a.forgotten).max().unwrap_or(0).max(1);
+    let bar = |n: usize, c: &str| c.repeat((n * ACTIVITY_BAR).div_ceil(busiest));
+    println!("\n{} (added / forgotten):", title);
+    for a in rows {
+        println!(
+            "  {:10} {:>5} {:>5}  {}{}",
+            a.period,
+            a.added,
+            a.forgotten,
+            bar(a.added, "+"),
+            bar(a.forgotten, "-")
+        );
+    }
+}
+
+fn print_access(mem: &Memories, output: Output) -> Result<(), Error> {
+    let report = mem.access_report()?;
+    if output.is_json() {
+        return print_json(&proto::AccessStats {
+            count: report.count,
+            never_accessed: report.never_accessed,
+            most_recalled: report.most_recalled.iter().map(proto::Memory::from).collect(),
+            oldest_unused: report.oldest_unused.iter().map(proto::Memory::from).collect(),
+            tags: report
+                .tags
+                .iter()
+                .map(|t| proto::TagAccess {
+                    tag: t.tag.clone(),
+                    count: t.count,
+                    accesses: t.accesses,
+                    avg_accesses: t.avg_accesses(),
+                    never_accessed: t.never_accessed,
+                    last_accessed_at: t.last_accessed_at.clone(),
+                })
+                .collect(),
+        });
+    }
+    let share = |n: usize| format!("{} ({:.0}%)", n, 100.0 * n as f64 / report.count.max(1) as f64);
+
+    println!("Access");
+    println!("======\n");
+
+    println!("Memories:       {}", report.count);
+    println!("Never accessed: {}", share(report.never_accessed));
+
+    if !report.most_recalled.is_empty() {
+        println!("\nMost recalled:");
+        for m in &report.most_recalled {
+            let preview: String = first_line(&m.content).chars().take(60).collect();
+            let last_used = m.last_accessed_at.as_deref().map_or("-", crate::dates::date_part);
+            println!("  [{}] ({}x, last {}) {}", m.id, m.access_count, last_used, preview);
+        }
+    }
+
+    if !report.oldest_unused.is_empty() {
+        println!("\nNever accessed, oldest first:");
+        for m in &report.oldest_unused {
+            let preview: String = first_line(&m.content).chars().take(60).collect();
+            println!("  [{}] (added {}) {}", m.id, crate::dates::date_part(&m.created_at), preview);
+        }
+        println!("  Prune with 'roots prune --unaccessed-for <days>'");
+    }
+
+    if !report.tags.is_empty() {
+        println!("\nTags by access:");
+        println!("  {:20} {:>8} {:>6} {:>6}  last used", "tag", "accesses", "avg", "never");
+        for t in report.tags.iter().take(ACCESS_TOP) {
+            println!(
+                "  {:20} {:>8} {:>6.1} {:>6}  {}",
+                t.tag,
+                t.accesses,
+                t.avg_accesses(),
+                t.never_accessed,
+                t.last_accessed_at.as_deref().map_or("-", crate::dates::date_part)
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Queries listed in each section of `stats --queries`
+const QUERY_TOP: usize = 10;
+
+fn print_queries(mem: &Memories, output: Output) -> Result<(), Error> {
+    let report = mem.query_report(QUERY_TOP)?;
+    let totals = &report.totals;
+    if output.is_json() {
+        let counts = |summaries: &[QuerySummary]| -> Vec<proto::QueryCount> {
+            summaries
+                .iter()
+                .map(|s| proto::QueryCount {
+                    query_hash: s.query_hash.clone(),
+                    query: s.query.clone(),
+                    count: s.count,
+                    avg_results: s.avg_results,
+                    last_at: s.last_at.clone(),
+                })
+                .collect()
+        };
+        return print_json(&proto::QueryStats {
+            logging: mem.logging_queries(),
+            queries: totals.queries,
+            distinct: totals.distinct,
+            empty: totals.empty,
+            avg_latency_ms: totals.avg_latency_ms,
+            since: totals.since.clone(),
+            frequent: counts(&report.frequent),
+            unanswered: counts(&report.unanswered),
+        });
+    }
+    let enable = "Turn it on with 'roots config query_log text' ('hash' keeps only hashes of the text)";
+    if totals.queries == 0 {
+        println!("No queries logged.");
+        if !mem.logging_queries() {
+            println!("{}", enable);
+        }
+        return Ok(());
+    }
+
+    println!("Queries");
+    println!("=======\n");
+
+    let since = totals.since.as_deref().map_or("", crate::dates::date_part);
+    println!("Logged:      {} since {}", totals.queries, since);
+    println!("Distinct:    {}", totals.distinct);
+    println!(
+        "No results:  {} ({:.0}%)",
+        totals.empty,
+        100.0 * totals.empty as f64 / totals.queries as f64
+    );
+    println!("Avg latency: {:.0}ms", totals.avg_latency_ms);
+    if !mem.logging_queries() {
+        println!("\nLogging is off. {}", enable);
+    }
+
+    // Queries logged in "hash" mode show as the start of their hash
+    let label = |s: &QuerySummary| match &s.query {
+        Some(query) => first_line(query).chars().take(60).collect(),
+        None => format!("#{}", s.query_hash.chars().take(12).collect::<String>()),
+    };
+    println!("\nMost frequent:");
+    for s in &report.frequent {
+        println!("  {:>5}x  {}  {}", s.count, label(s), term::dim(&format!("({:.1} results)", s.avg_results)));
+    }
+    if !report.unanswered.is_empty() {
+        println!("\nNothing found (missing knowledge?):");
+        for s in &report.unanswered {
+            println!("  {:>5}x  {}  {}", s.count, label(s), term::dim(&format!("(last {})", crate::dates::date_part(&s.last_at))));
+        }
+    }
+
+    Ok(())
+}
+
+fn print_tag_health(mem: &Memories, tag: &str, output: Output) -> Result<(), Error> {
+    use crate::aggregate::{AGE_BUCKETS, CONFIDENCE_BUCKETS, RECENT_ACCESS_DAYS};
+
+    let health = mem.tag_health(tag)?;
+    if health.count == 0 {
+        return Err(Error::NotFound(format!("No memories tagged '{}'", tag)));
+    }
+    if output.is_json() {
+        fn buckets<T>(labels: &[(&str, T)], counts: &[usize]) -> Vec<proto::Bucket> {
+            labels
+                .iter()
+                .zip(counts)
+                .map(|((label, _), &count)| proto::Bucket {
+                    label: label.to_string(),
+                    count,
+                })
+                .collect()
+        }
+        return print_json(&proto::TagHealth {
+            tag: tag.to_string(),
+            count: health.count,
+            avg_confidence: health.avg_confidence,
+            confidence: buckets(&CONFIDENCE_BUCKETS, &health.confidence),
+            age: buckets(&AGE_BUCKETS, &health.age),
+            never_accessed: health.never_accessed,
+            recently_accessed: health.recently_accessed,
+            avg_access_count: health.avg_access_count,
+            duplicate_pairs: health.duplicate_pairs,
+            unembedded: health.unembedded,
+        });
+    }
+    let share = |n: usize| format!("{:>5} ({:.0}%)", n, 100.0 * n as f64 / health.count as f64);
+
+    println!("Tag: {}", tag);
+    println!("==={}\n", "=".repeat(tag.len() + 2));
+
+    println!("Memories:       {}", health.count);
+    println!("Avg confidence: {:.2}", health.avg_confidence);
+
+    println!("\nConfidence:");
+    for ((label, _), n) in CONFIDENCE_BUCKETS.iter().zip(health.confidence) {
+        println!("  {:12} {}", label, share(n));
+    }
+
+    println!("\nAge:");
+    for ((label, _), n) in AGE_BUCKETS.iter().zip(health.age) {
+        println!("  {:12} {}", label, share(n));
+    }
+
+    println!("\nAccess:");
+    println!("  {:12} {}", "never", share(health.never_accessed));
+    println!(
+        "  {:12} {}",
+        format!("last {}d", RECENT_ACCESS_DAYS),
+        share(health.recently_accessed)
+    );
+    println!("  {:12} {:>5.1}", "avg count", health.avg_access_count);
+
+    println!("\nLikely duplicate pairs: {}", health.duplicate_pairs);
+    if health.unembedded > 0 {
+        println!("  ({} not embedded yet, see 'roots embed-queue status')", health.unembedded);
+    }
+
+    Ok(())
+}
+
 /// Run the export command
-pub fn run_export(format: &str) -> Result<(), String> {
+pub fn run_export(filter: &MemoryFilter, format: &str, tombstones: bool) -> Result<(), Error> {
     let mem = Memories::open()?;
-    let memories = mem.list(10000)?; // Get all
+    let mut memories = mem.list(filter, 10000, SortOrder::Recent)?; // Get all
+    for m in &mut memories {
+        mem.hydrate(m)?;
+    }
+
+    if tombstones && format != "json" {
+        return Err(Error::Invalid("--tombstones requires --format json".to_string()));
+    }
 
     match format {
+        "json" if tombstones => {
+            let bundle = proto::ExportBundle {
+                version: proto::VERSION,
+                memories: memories.iter().map(proto::Memory::from).collect(),
+                tombstones: mem.tombstones()?.iter().map(proto::Tombstone::from).collect(),
+            };
+            let json = serde_json::to_string_pretty(&bundle)
+                .map_err(|e| format!("Failed to serialize: {}", e))?;
+            println!("{}", json);
+        }
         "json" => {
+            let memories: Vec<proto::Memory> = memories.iter().map(proto::Memory::from).collect();
             let json = serde_json::to_string_pretty(&memories)
                 .map_err(|e| format!("Failed to serialize: {}", e))?;
             println!("{}", json);
@@ -364,58 +1289,396 @@ pub fn run_export(format: &str) -> Result<(), String> {
                 println!("---\n");
             }
         }
+        "dot" => print!("{}", graph_dot(&memories)),
+        "mermaid" => print!("{}", graph_mermaid(&memories)),
         _ => {
-            return Err(format!("Unknown format: {}", format));
+            return Err(Error::Invalid(format!("Unknown format: {}", format)));
         }
     }
 
     Ok(())
 }
 
-// Helper to print a memory
-fn print_memory(m: &crate::types::Memory) {
-    println!("[{}] confidence: {:.2}", m.id, m.confidence);
+/// Either export shape `import` accepts
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ImportFile {
+    Bundle(proto::ExportBundle),
+    Memories(Vec<proto::Memory>),
+}
 
-    if !m.tags.is_empty() {
-        println!("    tags: {}", m.tags.join(", "));
+/// Run the import command
+pub fn run_import(file: &str, strategy: &str) -> Result<(), Error> {
+    let data = std::fs::read_to_string(file).map_err(|e| format!("Failed to read {}: {}", file, e))?;
+    let (incoming, tombstones) =
+        match serde_json::from_str(&data).map_err(|e| format!("Failed to parse {}: {}", file, e))? {
+            ImportFile::Bundle(b) if b.version > proto::VERSION => {
+                return Err(Error::Invalid(format!(
+                    "{} was written with export format {}, newer than this roots reads ({})",
+                    file,
+                    b.version,
+                    proto::VERSION
+                )))
+            }
+            ImportFile::Bundle(b) => (b.memories, b.tombstones),
+            ImportFile::Memories(m) => (m, Vec::new()),
+        };
+    let incoming: Vec<Memory> = incoming.into_iter().map(Memory::from).collect();
+    let tombstones: Vec<Tombstone> = tombstones.into_iter().map(Tombstone::from).collect();
+
+    let mem = Memories::open()?;
+    let mut resolver = Resolver::new(Strategy::parse(strategy)?);
+    let (mut added, mut unchanged, mut updated, mut kept, mut skipped) = (0, 0, 0, 0, 0);
+    let (mut deleted, mut buried) = (0, 0);
+
+    // Deletions first, so a memory deleted on either side is not re-imported
+    for tombstone in &tombstones {
+        if mem.apply_tombstone(tombstone)? {
+            deleted += 1;
+        }
     }
 
-    // Truncate content for display
-    let preview: String = m.content.chars().take(200).collect();
-    let preview = if m.content.len() > 200 {
-        format!("{}...", preview)
+    for remote in &incoming {
+        if mem.tombstone(&remote.created_at)?.is_some() {
+            buried += 1;
+            continue;
+        }
+
+        // The same memory, or the same text remembered on both sides
+        let local = match mem.get_by_created_at(&remote.created_at)? {
+            Some(local) => Some(local),
+            None => mem.get_by_content(&remote.content)?,
+        };
+        let Some(mut local) = local else {
+            mem.remember(&NewMemory {
+                content: remote.content.clone(),
+                confidence: remote.confidence,
+                tags: remote.tags.clone(),
+                kind: remote.kind.clone(),
+                expires_at: remote.expires_at.clone(),
+                source: remote.source.clone(),
+                session_id: remote.session_id.clone(),
+                namespace: remote.namespace.clone(),
+                created_at: Some(remote.created_at.clone()),
+                pinned: remote.pinned,
+                blob: None,
+            })?;
+            added += 1;
+            continue;
+        };
+        mem.hydrate(&mut local)?;
+
+        if !resolve::conflicts(&local, remote) {
+            unchanged += 1;
+            continue;
+        }
+
+        let resolution = resolver.resolve(&local, remote)?;
+        match resolve::update_for(&local, remote, &resolution) {
+            Some(changes) => {
+                mem.update(local.id, &changes)?;
+                updated += 1;
+            }
+            None if resolution == Resolution::Skip => skipped += 1,
+            None => kept += 1,
+        }
+    }
+
+    println!("Imported {} of {} memories", added, incoming.len());
+    if unchanged > 0 {
+        println!("  {} already up to date", unchanged);
+    }
+    if buried > 0 {
+        println!("  {} skipped, forgotten here", buried);
+    }
+    if deleted > 0 {
+        println!("  {} forgotten, deleted in the export", deleted);
+    }
+    if updated + kept + skipped > 0 {
+        println!(
+            "  conflicts: {} updated, {} kept local, {} skipped",
+            updated, kept, skipped
+        );
+    }
+
+    Ok(())
+}
+
+/// Run `export --aggregate` - tag-level statistics without memory content
+pub fn run_export_aggregate(
+    filter: &MemoryFilter,
+    min_count: usize,
+    epsilon: Option<f64>,
+) -> Result<(), Error> {
+    if epsilon.is_some_and(|e| e <= 0.0) {
+        return Err(Error::Invalid("--epsilon must be positive".to_string()));
+    }
+
+    let mem = Memories::open()?;
+    let memories = mem.list(filter, usize::MAX >> 1, SortOrder::Recent)?;
+
+    let export = crate::aggregate::aggregate(&memories, min_count, epsilon);
+    let json = serde_json::to_string_pretty(&export)
+        .map_err(|e| format!("Failed to serialize: {}", e))?;
+    println!("{}", json);
+
+    Ok(())
+}
+
+/// Run the changes command - print change feed events as JSON lines
+pub fn run_changes(since: u64) -> Result<(), Error> {
+    let mem = Memories::open()?;
+
+    if !mem.changelog_enabled() {
+        eprintln!("Change feed is disabled. Enable it with: roots config changelog true");
+    }
+
+    for event in mem.changes_since(since)? {
+        let line = serde_json::to_string(&event)
+            .map_err(|e| format!("Failed to serialize: {}", e))?;
+        println!("{}", line);
+    }
+
+    Ok(())
+}
+
+/// Tags used by the given memories, in first-seen order
+fn graph_tags(memories: &[crate::types::Memory]) -> Vec<&str> {
+    let mut tags: Vec<&str> = Vec::new();
+    for m in memories {
+        for t in &m.tags {
+            if !tags.contains(&t.as_str()) {
+                tags.push(t);
+            }
+        }
+    }
+    tags
+}
+
+/// Short single-line label for a memory node
+fn graph_label(m: &crate::types::Memory) -> String {
+    let first = first_line(&m.content);
+    let mut label: String = first.chars().take(40).collect();
+    if first.chars().count() > 40 {
+        label.push_str("...");
+    }
+    label
+}
+
+/// Render memories and their tags as a Graphviz digraph
+fn graph_dot(memories: &[crate::types::Memory]) -> String {
+    let escape = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+    let mut out = String::from("digraph roots {\n    rankdir=LR;\n");
+
+    out.push_str("    node [shape=box];\n");
+    for m in memories {
+        out.push_str(&format!(
+            "    m{} [label=\"[{}] {}\"];\n",
+            m.id,
+            m.id,
+            escape(&graph_label(m))
+        ));
+    }
+
+    out.push_str("    node [shape=ellipse];\n");
+    let tags = graph_tags(memories);
+    for (i, tag) in tags.iter().enumerate() {
+        out.push_str(&format!("    t{} [label=\"#{}\"];\n", i, escape(tag)));
+    }
+
+    for m in memories {
+        for t in &m.tags {
+            if let Some(i) = tags.iter().position(|x| x == t) {
+                out.push_str(&format!("    m{} -> t{};\n", m.id, i));
+            }
+        }
+    }
+
+    out.push_str("}\n");
+    out
+}
+
+/// Render memories and their tags as a Mermaid flowchart
+fn graph_mermaid(memories: &[crate::types::Memory]) -> String {
+    // Mermaid labels can't contain raw double quotes
+    let escape = |s: &str| s.replace('"', "#quot;");
+    let mut out = String::from("graph LR\n");
+
+    for m in memories {
+        out.push_str(&format!(
+            "    m{}[\"[{}] {}\"]\n",
+            m.id,
+            m.id,
+            escape(&graph_label(m))
+        ));
+    }
+
+    let tags = graph_tags(memories);
+    for (i, tag) in tags.iter().enumerate() {
+        out.push_str(&format!("    t{}((\"#{}\"))\n", i, escape(tag)));
+    }
+
+    for m in memories {
+        for t in &m.tags {
+            if let Some(i) = tags.iter().position(|x| x == t) {
+                out.push_str(&format!("    m{} --> t{}\n", m.id, i));
+            }
+        }
+    }
+
+    out
+}
+
+// Helper to print a memory
+/// " (global)" for memories from the user-global store
+pub fn global_marker(m: &crate::types::Memory) -> &'static str {
+    if m.global {
+        " (global)"
     } else {
-        preview
-    };
-    let preview = preview.replace('\n', " ");
-    println!("    {}\n", preview);
+        ""
+    }
+}
+
+/// A recall result as `--output json` prints it
+fn hit(r: &SearchResult) -> proto::Hit {
+    proto::Hit {
+        memory: proto::Memory::from(&r.memory),
+        score: r.score,
+        snippet: None,
+        project: None,
+    }
 }
 
-fn print_memory_with_score(m: &crate::types::Memory, score: f64) {
-    println!("[{}] score: {:.3}, confidence: {:.2}", m.id, score, m.confidence);
+fn print_memory(m: &crate::types::Memory) {
+    println!(
+        "{}{} confidence: {}",
+        term::bold(&format!("[{}]", m.id)),
+        global_marker(m),
+        term::scaled(m.confidence, &format!("{:.2}", m.confidence))
+    );
+    print_details(m);
+    print_preview(&m.content, &[]);
+}
 
+/// Print a recall result, with the words of `query` highlighted; with
+/// `explain`, break the score down by signal
+fn print_memory_with_score(r: &SearchResult, query: &str, explain: Option<&Ranking>) {
+    let m = &r.memory;
+    println!(
+        "{}{} score: {}, confidence: {}",
+        term::bold(&format!("[{}]", m.id)),
+        global_marker(m),
+        term::scaled(r.score, &format!("{:.3}", r.score)),
+        term::scaled(m.confidence, &format!("{:.2}", m.confidence))
+    );
+    print_details(m);
+
+    if let (Some(ranking), Some(parts)) = (explain, &r.parts) {
+        print_score_parts(m, ranking, parts);
+    }
+
+    print_preview(&m.content, &term::query_terms(query));
+}
+
+/// The metadata lines under a memory's header, dimmed
+fn print_details(m: &Memory) {
+    let mut details = Vec::new();
+    if let Some(k) = &m.kind {
+        details.push(format!("kind: {}", k));
+    }
+    if m.pinned {
+        details.push("pinned".to_string());
+    }
+    if let Some(ns) = &m.namespace {
+        details.push(format!("namespace: {}", ns));
+    }
+    if let Some(e) = &m.expires_at {
+        details.push(format!("expires: {}", crate::dates::date_part(e)));
+    }
+    if let Some(src) = &m.source {
+        details.push(format!("source: {}", src));
+    }
     if !m.tags.is_empty() {
-        println!("    tags: {}", m.tags.join(", "));
+        details.push(format!("tags: {}", m.tags.join(", ")));
+    }
+    for detail in details {
+        println!("    {}", term::dim(&detail));
     }
+}
 
-    let preview: String = m.content.chars().take(200).collect();
-    let preview = if m.content.len() > 200 {
+/// The start of a memory's content, wrapped to the terminal, with `terms`
+/// highlighted
+fn print_preview(content: &str, terms: &[String]) {
+    // Truncate content for display
+    let preview: String = content.chars().take(200).collect();
+    let preview = if content.len() > 200 {
         format!("{}...", preview)
     } else {
         preview
     };
     let preview = preview.replace('\n', " ");
-    println!("    {}\n", preview);
+    for line in term::wrap(&preview, "    ", term::output_width()) {
+        println!("{}", term::highlight_terms(&line, terms));
+    }
+    println!();
+}
+
+/// Each signal, its weight, and what it adds to the score
+fn print_score_parts(m: &Memory, ranking: &Ranking, parts: &ScoreParts) {
+    let accessed = match &m.last_accessed_at {
+        Some(at) => format!("recalled {} times, last {}", m.access_count, crate::dates::date_part(at)),
+        None => "never recalled".to_string(),
+    };
+    let rows = [
+        ("similarity", parts.similarity, ranking.similarity, "cosine to the query".to_string()),
+        ("confidence", parts.confidence, ranking.confidence, String::new()),
+        (
+            "recency",
+            parts.recency,
+            ranking.recency,
+            format!("updated {}", crate::dates::date_part(&m.updated_at)),
+        ),
+        ("access", parts.access, ranking.access, accessed),
+        ("importance", parts.importance, ranking.importance, "graph centrality".to_string()),
+    ];
+    for (name, value, weight, note) in rows {
+        let line = format!(
+            "    {:<10} {:>6.3} x {:<4} = {:>6.3}  {}",
+            name,
+            value,
+            weight,
+            value * weight,
+            note
+        );
+        println!("{}", line.trim_end());
+    }
+    if m.pinned {
+        println!("    pinned: exempt from context_max_age, no score bonus");
+    }
 }
 
 /// Run the sync command - export memories to markdown files
-pub fn run_sync() -> Result<(), String> {
+pub fn run_sync() -> Result<(), Error> {
     let mem = Memories::open()?;
-    let memories = mem.list(10000)?;
+    let synced = write_markdown(&mem)?;
+    if synced == 0 {
+        println!("No memories to sync.");
+    } else {
+        println!("Synced {} memories to {}/", synced, mem.roots_path().join("memories").display());
+    }
+    Ok(())
+}
+
+/// Write every memory as a markdown file under `.roots/memories`,
+/// replacing the previous files; returns how many were written
+pub fn write_markdown(mem: &Memories) -> Result<usize, Error> {
+    let mut memories = mem.list(&MemoryFilter::everything(), 10000, SortOrder::Recent)?;
+    for m in &mut memories {
+        mem.hydrate(m)?;
+    }
 
     if memories.is_empty() {
-        println!("No memories to sync.");
-        return Ok(());
+        return Ok(0);
     }
 
     // Create memories directory
@@ -426,7 +1689,7 @@ pub fn run_sync() -> Result<(), String> {
     // Clear existing files
     if let Ok(entries) = fs::read_dir(&memories_dir) {
         for entry in entries.flatten() {
-            if entry.path().extension().map_or(false, |e| e == "md") {
+            if entry.path().extension().is_some_and(|e| e == "md") {
                 fs::remove_file(entry.path()).ok();
             }
         }
@@ -460,9 +1723,7 @@ pub fn run_sync() -> Result<(), String> {
             .map_err(|e| format!("Failed to write {}: {}", filename, e))?;
     }
 
-    println!("Synced {} memories to {}/", memories.len(), memories_dir.display());
-
-    Ok(())
+    Ok(memories.len())
 }
 
 /// Create a slug from content for filenames
@@ -504,9 +1765,29 @@ fn first_line(text: &str) -> &str {
     text.lines().next().unwrap_or(text).trim()
 }
 
+/// Run the migrate command. Opening the store applies pending migrations;
+/// this reports where that left it.
+pub fn run_migrate() -> Result<(), Error> {
+    let mem = Memories::open()?;
+    let [schema, created_by, migrated_by] = mem.schema_stamp()?;
+    let unknown = || "unknown".to_string();
+
+    println!(
+        "Schema version: {} (roots {} supports up to {})",
+        schema.unwrap_or_else(unknown),
+        env!("CARGO_PKG_VERSION"),
+        crate::store::SCHEMA_VERSION
+    );
+    println!("Created by:     roots {}", created_by.unwrap_or_else(unknown));
+    println!("Migrated by:    roots {}", migrated_by.unwrap_or_else(unknown));
+    println!("\nThe store is up to date.");
+    Ok(())
+}
+
 /// Run the reindex command - rebuild all embeddings with current model
-pub fn run_reindex() -> Result<(), String> {
+pub fn run_reindex(missing_only: bool, force: bool, no_wait: bool) -> Result<(), Error> {
     let mem = Memories::open()?;
+    let _lock = MaintenanceLock::acquire(mem.roots_path(), "reindex", no_wait)?;
 
     let stored = mem.get_stored_model()?;
     let current = mem.current_model();
@@ -518,10 +1799,154 @@ pub fn run_reindex() -> Result<(), String> {
         }
     }
 
-    println!("\nRebuilding embeddings...");
-    let count = mem.reindex()?;
+    let scope = if force {
+        println!("\nRebuilding all embeddings...");
+        ReindexScope::All
+    } else if missing_only {
+        println!("\nEmbedding missing and stale memories...");
+        ReindexScope::Missing
+    } else {
+        println!("\nRebuilding embeddings...");
+        ReindexScope::Changed
+    };
+    let progress = Progress::new();
+    let summary = mem.reindex(scope, |done, total| progress.update(done, total))?;
+    progress.finish();
 
-    println!("Reindexed {} memories with model: {}", count, current);
+    println!("Reindexed {} memories with model: {}", summary.reindexed, current);
+    if summary.unchanged > 0 {
+        println!("  {} unchanged since last embedded (--force to redo them)", summary.unchanged);
+    }
+    if summary.failures.is_empty() {
+        return Ok(());
+    }
 
-    Ok(())
+    println!("\n{} failed (they keep their old embeddings):", summary.failures.len());
+    for (id, error) in &summary.failures {
+        println!("  [{}] {}", id, error);
+    }
+    Err("Some memories were not reindexed, retry them with 'roots reindex --missing-only'".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{self, memory};
+
+    #[test]
+    fn test_graph_export_shares_tag_nodes() {
+        let memories = vec![
+            memory(1, "Use \"quoted\" paths\nsecond line", &["rust", "paths"]),
+            memory(2, &"x".repeat(60), &["rust"]),
+        ];
+
+        let dot = graph_dot(&memories);
+        assert!(dot.starts_with("digraph roots {\n"));
+        assert!(dot.ends_with("}\n"));
+        assert!(dot.contains("m1 [label=\"[1] Use \\\"quoted\\\" paths\"];"));
+        assert!(dot.contains(&format!("m2 [label=\"[2] {}...\"];", "x".repeat(40))));
+        // One node per distinct tag, in first-seen order
+        assert!(dot.contains("t0 [label=\"#rust\"];"));
+        assert!(dot.contains("t1 [label=\"#paths\"];"));
+        assert!(!dot.contains("t2 "));
+        assert!(dot.contains("m1 -> t0;\n    m1 -> t1;\n    m2 -> t0;"));
+
+        let mermaid = graph_mermaid(&memories);
+        assert!(mermaid.starts_with("graph LR\n"));
+        assert!(mermaid.contains("m1[\"[1] Use #quot;quoted#quot; paths\"]"));
+        assert!(mermaid.contains("t0((\"#rust\"))"));
+        assert!(mermaid.contains("m2 --> t0"));
+    }
+
+    #[test]
+    fn test_graph_export_without_tags() {
+        let memories = vec![memory(7, "untagged", &[])];
+        assert_eq!(
+            graph_dot(&memories),
+            "digraph roots {\n    rankdir=LR;\n    node [shape=box];\n    m7 [label=\"[7] untagged\"];\n    node [shape=ellipse];\n}\n"
+        );
+        assert_eq!(graph_mermaid(&memories), "graph LR\n    m7[\"[7] untagged\"]\n");
+    }
+
+    #[test]
+    fn test_repeated_recall_only_reinforces_relevant_hits() {
+        let dir = test_support::scratch("recall-access");
+        // A heavy access weight, so any reinforcement of filler would show
+        let roots = test_support::project(&dir, "context_threshold: 0.3\nranking:\n  access: 1\n");
+        let mem = Memories::open_at(roots).unwrap();
+        let contents = [
+            "Deploy to production with make deploy",
+            "Deploy to staging with make deploy-staging",
+            "The cat sleeps on the warm windowsill",
+            "Bananas ripen faster next to apples",
+        ];
+        let ids: Vec<i64> = contents
+            .iter()
+            .map(|content| {
+                mem.remember(&NewMemory {
+                    content: content.to_string(),
+                    confidence: 0.8,
+                    ..Default::default()
+                })
+                .unwrap()
+            })
+            .collect();
+
+        // As `recall` does: search, show every result, record the hits
+        let recall = || {
+            let results = mem.recall("deploy with make", &MemoryFilter::default(), 4).unwrap();
+            mem.record_hits(&results).unwrap();
+            results.iter().map(|r| r.memory.id).collect::<Vec<_>>()
+        };
+        let first = recall();
+        assert_eq!(first.len(), 4);
+        for _ in 0..10 {
+            assert_eq!(recall(), first);
+        }
+
+        let accesses = |id| mem.get(id).unwrap().unwrap().access_count;
+        assert_eq!((accesses(ids[0]), accesses(ids[1])), (11, 11));
+        // Filler that only padded out the list was never counted
+        assert_eq!((accesses(ids[2]), accesses(ids[3])), (0, 0));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_resolve_tag_widens_to_prefix_only_when_needed() {
+        let dir = test_support::scratch("fuzzy-tag");
+        let mem = test_support::store(&dir);
+        for (content, tag) in [("Postgres runs on 5432", "postgres"), ("Use pgbouncer", "postgresql"), ("Ship it", "deploy")] {
+            mem.remember(&NewMemory {
+                content: content.to_string(),
+                confidence: 0.8,
+                tags: vec![tag.to_string()],
+                ..Default::default()
+            })
+            .unwrap();
+        }
+        let tagged = |tag: &str| MemoryFilter {
+            tag: Some(tag.to_string()),
+            ..Default::default()
+        };
+
+        // An exact tag isn't widened to the tags it starts
+        let mut exact = tagged("postgres");
+        assert!(fuzzy_tag(&mem, &mut exact, Output::Text).unwrap());
+        assert_eq!(mem.list(&exact, 10, SortOrder::Recent).unwrap().len(), 1);
+
+        let mut prefix = tagged("Post");
+        assert_eq!(
+            mem.resolve_tag(&mut prefix).unwrap(),
+            TagMatch::Prefix(vec!["postgres".to_string(), "postgresql".to_string()])
+        );
+        assert_eq!(mem.list(&prefix, 10, SortOrder::Recent).unwrap().len(), 2);
+        assert_eq!(mem.recall("postgres", &prefix, 10).unwrap().len(), 2);
+
+        // A typo stops the command with a suggestion instead of listing nothing
+        let mut typo = tagged("delpoy");
+        assert!(!fuzzy_tag(&mem, &mut typo, Output::Json).unwrap());
+        assert!(!typo.tag_prefix);
+        assert_eq!(mem.resolve_tag(&mut MemoryFilter::default()).unwrap(), TagMatch::Exact);
+        let _ = fs::remove_dir_all(&dir);
+    }
 }
@@ -1,4 +1,47 @@
+pub mod backup;
+pub mod bench;
+pub mod capture;
 pub mod config;
 pub mod context;
+pub mod doctor;
+pub mod embed_queue;
+pub mod eval;
+pub mod hooks;
+pub mod ingest;
 pub mod memory;
+pub mod pack;
+pub mod review;
 pub mod server;
+pub mod setup;
+pub mod sync;
+pub mod tags;
+pub mod watch;
+
+/// How `recall`, `list`, `show`, `stats`, `tags`, and `context` print
+/// results: for people, or as JSON (see `proto.rs`) for scripts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Output {
+    Text,
+    Json,
+}
+
+impl Output {
+    pub fn parse(output: &str) -> Result<Self, String> {
+        match output {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => Err("expected text or json".to_string()),
+        }
+    }
+
+    pub fn is_json(self) -> bool {
+        self == Self::Json
+    }
+}
+
+/// Print `value` as pretty JSON
+pub fn print_json<T: serde::Serialize>(value: &T) -> Result<(), crate::error::Error> {
+    let json = serde_json::to_string_pretty(value).map_err(|e| format!("Failed to serialize: {}", e))?;
+    println!("{}", json);
+    Ok(())
+}
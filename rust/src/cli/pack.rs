@@ -0,0 +1,321 @@
+use crate::error::Error;
+use crate::http;
+use crate::memory::Memories;
+use crate::proto::{self, Pack};
+use crate::types::{Memory, MemoryFilter, NewMemory, SortOrder};
+use std::collections::BTreeMap;
+
+/// `source` of installed pack memories is `pack:<name>@<version>`
+const PACK_SOURCE_PREFIX: &str = "pack:";
+
+/// Seconds to wait when installing a pack from a URL
+const FETCH_TIMEOUT_SECS: u32 = 60;
+
+/// Run the pack create command - write selected memories as a pack file
+pub fn run_pack_create(
+    name: &str,
+    version: &str,
+    description: Option<&str>,
+    filter: &MemoryFilter,
+    output: Option<&str>,
+) -> Result<(), Error> {
+    validate_name(name)?;
+    if version.trim().is_empty() {
+        return Err(Error::Invalid("Pack version can't be empty".to_string()));
+    }
+
+    let mem = Memories::open()?;
+    let pack = build(&mem, name, version, description, filter)?;
+    let json =
+        serde_json::to_string_pretty(&pack).map_err(|e| format!("Failed to serialize: {}", e))?;
+
+    let path = output
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("{}-{}.pack.json", name, version));
+    std::fs::write(&path, json + "\n").map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    println!("Packed {} memories as {} {} in {}", pack.memories.len(), name, version, path);
+    Ok(())
+}
+
+/// A pack of the memories `filter` selects
+fn build(
+    mem: &Memories,
+    name: &str,
+    version: &str,
+    description: Option<&str>,
+    filter: &MemoryFilter,
+) -> Result<Pack, Error> {
+    let mut memories = mem.list(filter, usize::MAX >> 1, SortOrder::Recent)?;
+    if memories.is_empty() {
+        return Err("No memories match; nothing to pack".into());
+    }
+    for m in &mut memories {
+        mem.hydrate(m)?;
+    }
+
+    Ok(Pack {
+        version: proto::VERSION,
+        name: name.to_string(),
+        pack_version: version.to_string(),
+        description: description.map(str::to_string),
+        created_at: crate::dates::now_rfc3339(),
+        memories: memories.iter().enumerate().map(|(i, m)| packed(i, m)).collect(),
+    })
+}
+
+/// A memory as shipped in a pack: local ids, usage, and provenance are
+/// meaningless in another repository
+fn packed(index: usize, memory: &Memory) -> proto::Memory {
+    proto::Memory {
+        id: index as i64 + 1,
+        last_accessed_at: None,
+        access_count: 0,
+        importance: 0.0,
+        source: None,
+        namespace: None,
+        global: false,
+        ..proto::Memory::from(memory)
+    }
+}
+
+/// Run the pack install command - add a pack's memories in their own
+/// namespace, replacing any installed version of the same pack
+pub fn run_pack_install(location: &str, namespace: Option<&str>, force: bool) -> Result<(), Error> {
+    let pack = load(location)?;
+    let mem = Memories::open()?;
+    let namespace = install(&mem, &pack, namespace, force)?;
+
+    println!(
+        "Installed {} {}: {} memories in namespace {}",
+        pack.name,
+        pack.pack_version,
+        pack.memories.len(),
+        namespace
+    );
+    if let Some(description) = &pack.description {
+        println!("  {}", description);
+    }
+    Ok(())
+}
+
+/// Read the pack at a path or URL
+fn load(location: &str) -> Result<Pack, Error> {
+    let data = if location.starts_with("http://") || location.starts_with("https://") {
+        http::Request::new("GET", location)
+            .timeout(FETCH_TIMEOUT_SECS)
+            .send()
+            .map_err(|e| format!("Failed to download {}: {}", location, e))?
+    } else {
+        std::fs::read_to_string(location).map_err(|e| format!("Failed to read {}: {}", location, e))?
+    };
+    let pack: Pack =
+        serde_json::from_str(&data).map_err(|e| format!("Failed to parse {}: {}", location, e))?;
+    if pack.version > proto::VERSION {
+        return Err(Error::Invalid(format!(
+            "{} was written with pack format {}, newer than this roots reads ({})",
+            location,
+            pack.version,
+            proto::VERSION
+        )));
+    }
+    validate_name(&pack.name)?;
+    Ok(pack)
+}
+
+/// Add `pack`'s memories to `namespace` (by default the pack's own),
+/// replacing any installed version of it; returns the namespace
+fn install(mem: &Memories, pack: &Pack, namespace: Option<&str>, force: bool) -> Result<String, Error> {
+    let installed = installed_memories(mem, &pack.name)?;
+    if let Some(current) = installed.first().and_then(pack_source) {
+        if current.1 == pack.pack_version && !force {
+            return Err(Error::Invalid(format!(
+                "{} {} is already installed (reinstall with --force)",
+                pack.name, pack.pack_version
+            )));
+        }
+        println!("Replacing {} {} ({} memories)", pack.name, current.1, installed.len());
+    }
+
+    let namespace = namespace
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("{}{}", PACK_SOURCE_PREFIX, pack.name));
+    let source = format!("{}{}@{}", PACK_SOURCE_PREFIX, pack.name, pack.pack_version);
+    // Add the new version before dropping the old, so a failed install
+    // leaves the old one in place
+    for m in &pack.memories {
+        mem.remember(&NewMemory {
+            content: m.content.clone(),
+            confidence: m.confidence,
+            tags: m.tags.clone(),
+            kind: m.kind.clone(),
+            expires_at: m.expires_at.clone(),
+            source: Some(source.clone()),
+            namespace: Some(namespace.clone()),
+            pinned: m.pinned,
+            ..Default::default()
+        })?;
+    }
+    for m in &installed {
+        mem.forget(m.id)?;
+    }
+    Ok(namespace)
+}
+
+/// Run the pack list command - show installed packs
+pub fn run_pack_list() -> Result<(), Error> {
+    let mem = Memories::open()?;
+    let mut packs: BTreeMap<(String, String), (usize, Option<String>)> = BTreeMap::new();
+    for m in mem.list(&MemoryFilter::everything(), usize::MAX >> 1, SortOrder::Recent)? {
+        if let Some((name, version)) = pack_source(&m) {
+            let entry = packs.entry((name, version)).or_insert((0, m.namespace.clone()));
+            entry.0 += 1;
+        }
+    }
+
+    if packs.is_empty() {
+        println!("No packs installed. Install one with: roots pack install <path|url>");
+        return Ok(());
+    }
+    for ((name, version), (count, namespace)) in packs {
+        println!(
+            "{:<24} {:<10} {:>5} memories  namespace: {}",
+            name,
+            version,
+            count,
+            namespace.as_deref().unwrap_or("(default)")
+        );
+    }
+    Ok(())
+}
+
+/// Memories installed from the pack called `name`
+fn installed_memories(mem: &Memories, name: &str) -> Result<Vec<Memory>, Error> {
+    Ok(mem
+        .list(&MemoryFilter::everything(), usize::MAX >> 1, SortOrder::Recent)?
+        .into_iter()
+        .filter(|m| pack_source(m).is_some_and(|(n, _)| n == name))
+        .collect())
+}
+
+/// Pack name and version a memory was installed from
+fn pack_source(memory: &Memory) -> Option<(String, String)> {
+    let (name, version) = memory
+        .source
+        .as_deref()?
+        .strip_prefix(PACK_SOURCE_PREFIX)?
+        .split_once('@')?;
+    Some((name.to_string(), version.to_string()))
+}
+
+/// Pack names end up in file names, namespaces, and sources
+fn validate_name(name: &str) -> Result<(), Error> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::Invalid(format!(
+            "Invalid pack name '{}': use letters, digits, '-', '_', and '.'",
+            name
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::store;
+    use std::fs;
+
+    fn installed(mem: &Memories) -> Vec<Memory> {
+        mem.list(&MemoryFilter::everything(), 100, SortOrder::Recent).unwrap()
+    }
+
+    #[test]
+    fn test_pack_round_trip_and_reinstall() {
+        let dir = std::env::temp_dir().join(format!("roots-pack-{}", std::process::id()));
+        let source = store(&dir.join("source"));
+        for content in ["Use ruff for linting", "Pin tools with uv"] {
+            let id = source
+                .remember(&NewMemory {
+                    content: content.to_string(),
+                    confidence: 0.9,
+                    tags: vec!["python".to_string()],
+                    source: Some("notes.md".to_string()),
+                    ..Default::default()
+                })
+                .unwrap();
+            source.record_access([&source.get(id).unwrap().unwrap()]).unwrap();
+        }
+        let filter = MemoryFilter {
+            tag: Some("python".to_string()),
+            ..Default::default()
+        };
+        let pack = build(&source, "python-tooling", "1.0.0", None, &filter).unwrap();
+        // Local ids, usage, and provenance stay behind
+        assert_eq!(pack.memories.iter().map(|m| m.id).collect::<Vec<_>>(), vec![1, 2]);
+        assert!(pack.memories.iter().all(|m| m.access_count == 0 && m.source.is_none()));
+        let nothing = MemoryFilter {
+            tag: Some("go".to_string()),
+            ..Default::default()
+        };
+        assert!(build(&source, "go", "1.0.0", None, &nothing).is_err());
+
+        let target = store(&dir.join("target"));
+        assert_eq!(install(&target, &pack, None, false).unwrap(), "pack:python-tooling");
+        let first = installed(&target);
+        assert_eq!(first.len(), 2);
+        for m in &first {
+            assert_eq!(m.namespace.as_deref(), Some("pack:python-tooling"));
+            assert_eq!(m.source.as_deref(), Some("pack:python-tooling@1.0.0"));
+        }
+
+        // The same version again needs --force, and replaces rather than adds
+        assert_eq!(install(&target, &pack, None, false).unwrap_err().exit_code(), 2);
+        install(&target, &pack, None, true).unwrap();
+        assert_eq!(installed(&target).len(), 2);
+
+        // A new version replaces the old one
+        let newer = Pack {
+            pack_version: "1.1.0".to_string(),
+            memories: pack.memories[..1].to_vec(),
+            ..pack.clone()
+        };
+        install(&target, &newer, Some("team"), false).unwrap();
+        let replaced = installed(&target);
+        assert_eq!(replaced.len(), 1);
+        assert_eq!(replaced[0].namespace.as_deref(), Some("team"));
+        assert_eq!(replaced[0].source.as_deref(), Some("pack:python-tooling@1.1.0"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_load_rejects_bad_packs() {
+        let dir = std::env::temp_dir().join(format!("roots-pack-load-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let write = |name: &str, version: u32| {
+            let path = dir.join(format!("{}.json", version));
+            let pack = serde_json::json!({
+                "version": version,
+                "name": name,
+                "pack_version": "1.0.0",
+                "created_at": "2026-03-01T00:00:00+00:00",
+                "memories": [],
+            });
+            fs::write(&path, pack.to_string()).unwrap();
+            path.display().to_string()
+        };
+
+        assert_eq!(load(&write("ok", proto::VERSION)).unwrap().name, "ok");
+        let newer = load(&write("ok", proto::VERSION + 1)).unwrap_err();
+        assert!(newer.to_string().contains("newer than this roots reads"));
+        assert_eq!(load(&write("../escape", proto::VERSION)).unwrap_err().exit_code(), 2);
+        assert!(load(&dir.join("missing.json").display().to_string()).is_err());
+        for name in ["", "a b", "x/y"] {
+            assert!(validate_name(name).is_err(), "{:?}", name);
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,170 @@
+use crate::error::Error;
+use crate::memory::Memories;
+use crate::term::{self, RawMode};
+use crate::types::{Memory, MemoryFilter, MemoryUpdate, SortOrder};
+
+/// Confidence change for the +/- keys
+const CONFIDENCE_STEP: f64 = 0.1;
+
+const KEY_HELP: &str = "[k]eep  [e]dit  [t]ags  [+/-] confidence  [f]orget  [q]uit";
+
+#[derive(Default)]
+struct ReviewTally {
+    reviewed: usize,
+    edited: usize,
+    forgotten: usize,
+}
+
+/// Run the review command - step through memories with single keystrokes
+pub fn run_review(filter: MemoryFilter, order: &str, limit: usize) -> Result<(), Error> {
+    if !term::is_interactive() {
+        return Err(Error::Invalid("roots review needs an interactive terminal".to_string()));
+    }
+
+    let order = match order {
+        "confidence" => SortOrder::LowConfidence,
+        _ => SortOrder::Oldest,
+    };
+
+    let mem = Memories::open()?;
+    let memories = mem.list(&filter, limit, order)?;
+
+    if memories.is_empty() {
+        println!("Nothing to review.");
+        return Ok(());
+    }
+
+    let total = memories.len();
+    let mut tally = ReviewTally::default();
+
+    'memories: for (i, original) in memories.into_iter().enumerate() {
+        let mut m = original;
+        tally.reviewed += 1;
+
+        let mut redraw = true;
+        loop {
+            if redraw {
+                print_card(&m, i + 1, total);
+            }
+            redraw = true;
+
+            let key = {
+                let _raw = RawMode::enable()?;
+                term::read_key()?
+            };
+
+            match key {
+                Some('k') | Some(' ') | Some('\n') => break,
+                Some('q') | None => break 'memories,
+                Some('e') => {
+                    let content = term::edit_text(&m.content)?;
+                    if content.is_empty() || content == m.content {
+                        println!("  unchanged");
+                        continue;
+                    }
+                    apply(
+                        &mem,
+                        &mut m,
+                        MemoryUpdate {
+                            content: Some(content),
+                            ..Default::default()
+                        },
+                    )?;
+                    tally.edited += 1;
+                }
+                Some('t') => {
+                    let current = m.tags.join(", ");
+                    let Some(input) = term::prompt_line(&format!("  tags [{}]: ", current))? else {
+                        continue;
+                    };
+                    if input.is_empty() {
+                        continue;
+                    }
+                    let tags = input
+                        .split(',')
+                        .map(|t| t.trim().to_string())
+                        .filter(|t| !t.is_empty())
+                        .collect();
+                    apply(
+                        &mem,
+                        &mut m,
+                        MemoryUpdate {
+                            tags: Some(tags),
+                            ..Default::default()
+                        },
+                    )?;
+                    tally.edited += 1;
+                }
+                Some(c @ ('+' | '=' | '-')) => {
+                    let step = if c == '-' {
+                        -CONFIDENCE_STEP
+                    } else {
+                        CONFIDENCE_STEP
+                    };
+                    let confidence = ((m.confidence + step) * 10.0).round() / 10.0;
+                    apply(
+                        &mem,
+                        &mut m,
+                        MemoryUpdate {
+                            confidence: Some(confidence.clamp(0.0, 1.0)),
+                            ..Default::default()
+                        },
+                    )?;
+                    tally.edited += 1;
+                }
+                Some('f') => {
+                    mem.forget(m.id)?;
+                    println!("  forgot [{}]", m.id);
+                    tally.forgotten += 1;
+                    break;
+                }
+                // Ignore other keys without redrawing the card
+                Some(_) => redraw = false,
+            }
+        }
+    }
+
+    println!(
+        "\nReviewed {} of {}: {} changes, {} forgotten.",
+        tally.reviewed, total, tally.edited, tally.forgotten
+    );
+
+    Ok(())
+}
+
+/// Save changes and reload the memory so the card shows them
+fn apply(mem: &Memories, m: &mut Memory, changes: MemoryUpdate) -> Result<(), Error> {
+    mem.update(m.id, &changes)?;
+    if let Some(updated) = mem.get(m.id)? {
+        *m = updated;
+    }
+    Ok(())
+}
+
+fn print_card(m: &Memory, position: usize, total: usize) {
+    println!(
+        "\n── {}/{} ── [{}] confidence {:.1}, created {}",
+        position,
+        total,
+        m.id,
+        m.confidence,
+        crate::dates::date_part(&m.created_at)
+    );
+    if let Some(k) = &m.kind {
+        println!("  kind: {}", k);
+    }
+    if !m.tags.is_empty() {
+        println!("  tags: {}", m.tags.join(", "));
+    }
+    if let Some(last) = &m.last_accessed_at {
+        println!(
+            "  last used: {} ({}x)",
+            crate::dates::date_part(last),
+            m.access_count
+        );
+    }
+    for line in m.content.lines() {
+        println!("  {}", line);
+    }
+    println!("{}", KEY_HELP);
+}
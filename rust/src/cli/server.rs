@@ -1,12 +1,27 @@
-use crate::config::{get_global_config, get_server_model, resolve_model, set_global_config, SUGGESTED_MODELS};
+use crate::cli::doctor::format_size;
+use crate::config::{
+    get_server_model, get_server_models, global_setting, resolve_model, server_backend, server_dir, server_idle_timeout,
+    server_socket, set_global_config, unset_global_config, SUGGESTED_MODELS,
+};
 use crate::embeddings::ServerEmbedder;
+use crate::error::Error;
+use crate::server;
 use std::fs;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
-/// Run server start command
-pub fn run_start(foreground: bool) -> Result<(), String> {
-    if ServerEmbedder::is_running() {
-        let model = ServerEmbedder::get_model().unwrap_or_else(|_| "unknown".to_string());
+const SERVICE_UNIT: &str = "roots-embedder.service";
+const SOCKET_UNIT: &str = "roots-embedder.socket";
+
+/// How long `server start` waits for the model to load
+const START_WAIT: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Run server start command; the server exits after `idle_timeout` (e.g.
+/// "30m", default `server_idle_timeout`) without requests
+pub fn run_start(foreground: bool, idle_timeout: Option<&str>) -> Result<(), Error> {
+    let server = ServerEmbedder::new();
+    if server.is_running() {
+        let model = server.get_model().unwrap_or_else(|_| "unknown".to_string());
         println!("Server already running with model: {}", model);
         return Ok(());
     }
@@ -14,15 +29,20 @@ pub fn run_start(foreground: bool) -> Result<(), String> {
     let (model_name, model_type) = get_server_model();
 
     if model_type == "lite" {
-        return Err(
+        return Err(Error::Invalid(
             "Lite mode doesn't need a server.\n\
              To use ML embeddings, set a model first:\n\
              roots server model bge-base"
                 .to_string(),
-        );
+        ));
     }
 
-    println!("Starting embedding server with model: {}", model_name);
+    let models = get_server_models();
+    if models.len() > 1 {
+        println!("Starting embedding server with models: {}", models.join(", "));
+    } else {
+        println!("Starting embedding server with model: {}", model_name);
+    }
 
     // Check if sentence-transformers is installed, install if needed
     let check = Command::new("uv")
@@ -37,91 +57,119 @@ pub fn run_start(foreground: bool) -> Result<(), String> {
             .map_err(|e| format!("Failed to install sentence-transformers: {}", e))?;
 
         if !install.success() {
-            return Err("Failed to install sentence-transformers".to_string());
+            return Err("Failed to install sentence-transformers".into());
         }
     }
 
-    // Use uv run to handle Python environment
-    let server_cmd = if foreground {
-        format!("uv run python -m roots.server --model '{}'", model_name)
-    } else {
-        format!(
-            "nohup uv run python -m roots.server --model '{}' > /tmp/roots-server.log 2>&1 &",
-            model_name
-        )
+    let idle_timeout = match idle_timeout {
+        Some(value) => Some(crate::dates::parse_duration(value).map_err(Error::Invalid)?)
+            .filter(|d| *d > chrono::Duration::zero()),
+        None => server_idle_timeout(),
     };
 
-    let status = Command::new("sh")
-        .arg("-c")
-        .arg(&server_cmd)
-        .status()
-        .map_err(|e| format!("Failed to start server: {}", e))?;
+    // Use uv run to handle Python environment
+    let args = server::args(&models, &server_backend(), server.socket(), idle_timeout);
+
+    // Autostart launches it from here too, wherever a hook runs
+    save_server_dir()?;
 
     if foreground {
-        // Foreground mode - command returned
+        let dir = socket_dir(server.socket())?;
+        fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        let status = Command::new("uv")
+            .args(&args)
+            .status()
+            .map_err(|e| format!("Failed to start server: {}", e))?;
         if !status.success() {
-            return Err("Server exited with error".to_string());
+            return Err("Server exited with error".into());
         }
     } else {
         // Background mode - poll until server is ready (model loading can take a while)
+        let mut child = server::spawn(&args, server.socket(), None)?;
         println!("Waiting for model to load...");
-        let mut ready = false;
-        for i in 0..60 {
-            std::thread::sleep(std::time::Duration::from_secs(1));
-            if ServerEmbedder::is_running() {
-                ready = true;
-                break;
+        server::wait_until_ready(&server, &mut child, START_WAIT, |seconds| {
+            if seconds % 10 == 0 {
+                println!("Still loading... ({} seconds)", seconds);
             }
-            if i > 0 && i % 10 == 0 {
-                println!("Still loading... ({} seconds)", i);
-            }
-        }
+        })
+        .map_err(|e| format!("Server failed to start. {}", e))?;
 
-        if ready {
-            println!("Server started successfully.");
-        } else {
-            return Err(
-                "Server failed to start. Check /tmp/roots-server.log for details.".to_string(),
-            );
+        println!("Server started successfully.");
+        if let Some(idle) = idle_timeout {
+            println!("It exits after {} minutes without requests.", idle.num_minutes());
         }
     }
 
     Ok(())
 }
 
+/// Remember the current directory as the one the server runs from
+/// (`server_dir`), so autostart can run it from there too
+fn save_server_dir() -> Result<PathBuf, Error> {
+    let cwd = std::env::current_dir().map_err(|e| format!("Failed to get current directory: {}", e))?;
+    if server_dir().as_ref() != Some(&cwd) {
+        set_global_config("server_dir", &cwd.to_string_lossy())
+            .map_err(|e| format!("Failed to save config: {}", e))?;
+    }
+    Ok(cwd)
+}
+
+/// The socket's directory, which must exist before the server binds
+fn socket_dir(socket: &Path) -> Result<PathBuf, Error> {
+    socket
+        .parent()
+        .map(Path::to_path_buf)
+        .ok_or_else(|| Error::Invalid(format!("Invalid socket path: {}", socket.display())))
+}
+
 /// Run server stop command
-pub fn run_stop() -> Result<(), String> {
-    if !ServerEmbedder::is_running() {
+pub fn run_stop() -> Result<(), Error> {
+    let server = ServerEmbedder::new();
+    if !server.is_running() {
         println!("Server not running.");
         return Ok(());
     }
 
-    // Send stop command
-    use std::io::Write;
-    use std::os::unix::net::UnixStream;
-
-    let socket_path = "/tmp/roots-embedder.sock";
-
-    let mut stream = UnixStream::connect(socket_path)
-        .map_err(|e| format!("Failed to connect to server: {}", e))?;
-
-    let request = serde_json::json!({"cmd": "stop"});
-    let json = serde_json::to_string(&request).unwrap();
-
-    stream.write_all(json.as_bytes()).ok();
-    stream.shutdown(std::net::Shutdown::Write).ok();
+    server.stop()?;
 
     println!("Server stopped.");
     Ok(())
 }
 
 /// Run server status command
-pub fn run_status() -> Result<(), String> {
-    if ServerEmbedder::is_running() {
-        let model = ServerEmbedder::get_model().unwrap_or_else(|_| "unknown".to_string());
-        println!("Server: running");
-        println!("Model:  {}", model);
-        println!("Socket: /tmp/roots-embedder.sock");
+pub fn run_status() -> Result<(), Error> {
+    let server = ServerEmbedder::new();
+    if server.is_running() {
+        let status = server.status().unwrap_or_default();
+        let model = if status.model.is_empty() { "unknown" } else { &status.model };
+        println!("Server:   running");
+        match status.dimensions {
+            Some(dims) => println!("Model:    {} ({} dimensions)", model, dims),
+            None => println!("Model:    {}", model),
+        }
+        if status.models.len() > 1 {
+            println!("Also:     {}", status.models[1..].join(", "));
+        }
+        if !status.rerankers.is_empty() {
+            println!("Rerank:   {}", status.rerankers.join(", "));
+        }
+        if let Some(device) = &status.device {
+            println!("Device:   {}", device);
+        }
+        if let Some(secs) = status.uptime_secs {
+            println!("Uptime:   {}", format_uptime(secs as u64));
+        }
+        if let Some(bytes) = status.rss_bytes {
+            println!("Memory:   {}", format_size(bytes));
+        }
+        if let Some(requests) = status.requests {
+            match status.avg_latency_ms {
+                Some(ms) => println!("Requests: {} (avg {:.1} ms)", requests, ms),
+                None => println!("Requests: {}", requests),
+            }
+        }
+        println!("Socket:   {}", server.socket().display());
+        println!("Log:      {}", server::log_path().display());
     } else {
         println!("Server: not running");
 
@@ -133,33 +181,53 @@ pub fn run_status() -> Result<(), String> {
     Ok(())
 }
 
+/// `3d 4h`, `2h 05m`, `12m 30s`, or `45s`
+fn format_uptime(secs: u64) -> String {
+    let (days, hours, minutes) = (secs / 86400, secs / 3600 % 24, secs / 60 % 60);
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {:02}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m {:02}s", minutes, secs % 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
 /// Run server restart command
-pub fn run_restart() -> Result<(), String> {
-    if ServerEmbedder::is_running() {
+pub fn run_restart() -> Result<(), Error> {
+    if ServerEmbedder::new().is_running() {
         run_stop()?;
         std::thread::sleep(std::time::Duration::from_secs(1));
     }
 
-    run_start(false)
+    run_start(false, None)
 }
 
 /// Run server model command
-pub fn run_model(model: Option<&str>, list: bool) -> Result<(), String> {
+pub fn run_model(model: Option<&str>, list: bool, add: Option<&str>, remove: Option<&str>) -> Result<(), Error> {
     if list {
         print_server_models()?;
         return Ok(());
     }
+    if let Some(m) = add {
+        return add_model(m);
+    }
+    if let Some(m) = remove {
+        return remove_model(m);
+    }
 
     match model {
         Some(m) => {
             let (model_name, model_type) = resolve_model(m);
 
             if model_type == "lite" {
-                return Err(
+                return Err(Error::Invalid(
                     "Lite mode doesn't use the server.\n\
                      Use: roots config model lite"
                         .to_string(),
-                );
+                ));
             }
 
             set_global_config("server_model", m)
@@ -167,7 +235,7 @@ pub fn run_model(model: Option<&str>, list: bool) -> Result<(), String> {
 
             println!("Server model set to: {}", model_name);
 
-            if ServerEmbedder::is_running() {
+            if ServerEmbedder::new().is_running() {
                 println!("\nRestart the server to use the new model:");
                 println!("  roots server restart");
             }
@@ -189,7 +257,15 @@ pub fn run_model(model: Option<&str>, list: bool) -> Result<(), String> {
             }
             println!("  type: {}", model_type);
 
-            if ServerEmbedder::is_running() {
+            let extra = &get_server_models()[1..];
+            if !extra.is_empty() {
+                println!("\nAlso loaded:");
+                for name in extra {
+                    println!("  {}", name);
+                }
+            }
+
+            if ServerEmbedder::new().is_running() {
                 println!("\nServer is running with this model.");
             }
         }
@@ -198,15 +274,76 @@ pub fn run_model(model: Option<&str>, list: bool) -> Result<(), String> {
     Ok(())
 }
 
-fn print_server_models() -> Result<(), String> {
-    let config = get_global_config();
-    let current = config.get("server_model").cloned().unwrap_or_default();
+/// The models in `server_extra_models`, as configured (aliases or names)
+fn extra_models() -> Vec<String> {
+    global_setting("server_extra_models")
+        .unwrap_or_default()
+        .split(',')
+        .map(|m| m.trim().to_string())
+        .filter(|m| !m.is_empty())
+        .collect()
+}
+
+fn save_extra_models(models: &[String]) -> Result<(), Error> {
+    let saved = if models.is_empty() {
+        unset_global_config("server_extra_models").map(|_| ())
+    } else {
+        set_global_config("server_extra_models", &models.join(","))
+    };
+    saved.map_err(|e| format!("Failed to save config: {}", e).into())
+}
+
+fn restart_hint() {
+    if ServerEmbedder::new().is_running() {
+        println!("\nRestart the server for the change to take effect:");
+        println!("  roots server restart");
+    }
+}
+
+/// Load `model` alongside the server model
+fn add_model(model: &str) -> Result<(), Error> {
+    let (model_name, model_type) = resolve_model(model);
+    if model_type == "lite" {
+        return Err(Error::Invalid("Lite mode doesn't use the server".to_string()));
+    }
+    if get_server_models().contains(&model_name) {
+        println!("The server already loads {}", model_name);
+        return Ok(());
+    }
+
+    let mut extra = extra_models();
+    extra.push(model.to_string());
+    save_extra_models(&extra)?;
+    println!("The server will also load: {}", model_name);
+    println!("Projects with `embedding_model: {}` embed with it", model);
+    restart_hint();
+    Ok(())
+}
+
+/// Stop loading a model added with `add_model`
+fn remove_model(model: &str) -> Result<(), Error> {
+    let (model_name, _) = resolve_model(model);
+    let mut extra = extra_models();
+    let before = extra.len();
+    extra.retain(|m| resolve_model(m).0 != model_name);
+    if extra.len() == before {
+        return Err(Error::Invalid(format!(
+            "{} is not an added model (see 'roots server model')",
+            model
+        )));
+    }
+
+    save_extra_models(&extra)?;
+    println!("The server will no longer load: {}", model_name);
+    restart_hint();
+    Ok(())
+}
+
+fn print_server_models() -> Result<(), Error> {
+    let models = get_server_models();
 
     println!("Available server models:\n");
-    println!(
-        "{:2} {:12} {:10} {}",
-        "", "Alias", "Size", "Description"
-    );
+    println!("   {:12} {:10} Description", "Alias", "Size");
     println!("{}", "-".repeat(60));
 
     for model in SUGGESTED_MODELS {
@@ -215,10 +352,10 @@ fn print_server_models() -> Result<(), String> {
             continue;
         }
 
-        let marker = if model.alias == current || model.name == current {
-            " *"
-        } else {
-            "  "
+        let marker = match models.iter().position(|m| m == model.name) {
+            Some(0) => " *",
+            Some(_) => " +",
+            None => "  ",
         };
 
         println!(
@@ -227,27 +364,60 @@ fn print_server_models() -> Result<(), String> {
         );
     }
 
-    println!("\n* = currently configured");
+    println!("\n* = currently configured, + = also loaded");
     println!("\nSet model with: roots server model <alias>");
+    println!("Load another with: roots server model --add <alias>");
 
     Ok(())
 }
 
-/// Run server install command (systemd)
-pub fn run_install() -> Result<(), String> {
-    let home = dirs::home_dir().ok_or("Could not find home directory")?;
-    let systemd_dir = home.join(".config/systemd/user");
+/// Where systemd looks for user units, `$XDG_CONFIG_HOME/systemd/user`
+fn systemd_user_dir() -> Result<PathBuf, Error> {
+    dirs::config_dir()
+        .map(|dir| dir.join("systemd").join("user"))
+        .ok_or_else(|| "Could not find the config directory".into())
+}
+
+/// Run server install command (systemd). With `server_idle_timeout` set,
+/// systemd holds the socket and starts the server on the first request after
+/// it exits for being idle; otherwise the server starts at login.
+pub fn run_install() -> Result<(), Error> {
+    let systemd_dir = systemd_user_dir()?;
 
     fs::create_dir_all(&systemd_dir)
         .map_err(|e| format!("Failed to create systemd directory: {}", e))?;
 
-    let (model_name, _) = get_server_model();
+    let socket = server_socket();
+    let idle_timeout = server_idle_timeout();
 
-    // Get current working directory to use as WorkingDirectory in service
-    let cwd = std::env::current_dir()
-        .map_err(|e| format!("Failed to get current directory: {}", e))?;
+    // The service and autostart both run the server from here
+    let cwd = save_server_dir()?;
     let cwd_str = cwd.to_string_lossy();
 
+    let mut args = server::args(&get_server_models(), &server_backend(), &socket, idle_timeout);
+    let install = if idle_timeout.is_some() {
+        // systemd passes the listening socket as fd 3
+        args.extend(["--listen-fd".to_string(), "3".to_string()]);
+        let socket_content = format!(
+            r#"[Unit]
+Description=Roots Embedding Server socket
+
+[Socket]
+ListenStream={}
+SocketMode=0600
+
+[Install]
+WantedBy=sockets.target
+"#,
+            socket.display()
+        );
+        fs::write(systemd_dir.join(SOCKET_UNIT), socket_content)
+            .map_err(|e| format!("Failed to write socket file: {}", e))?;
+        String::new()
+    } else {
+        "\n[Install]\nWantedBy=default.target\n".to_string()
+    };
+
     let service_content = format!(
         r#"[Unit]
 Description=Roots Embedding Server
@@ -256,67 +426,77 @@ After=network.target
 [Service]
 Type=simple
 WorkingDirectory={}
-ExecStart=/bin/sh -c "uv run python -m roots.server --model '{}'"
+ExecStartPre=/bin/mkdir -p '{}'
+ExecStart={}
 Restart=on-failure
 RestartSec=5
-
-[Install]
-WantedBy=default.target
-"#,
-        cwd_str, model_name
+{}"#,
+        cwd_str,
+        socket_dir(&socket)?.display(),
+        server::exec_start(&args),
+        install
     );
 
-    let service_path = systemd_dir.join("roots-embedder.service");
+    let service_path = systemd_dir.join(SERVICE_UNIT);
     fs::write(&service_path, service_content)
         .map_err(|e| format!("Failed to write service file: {}", e))?;
 
-    // Enable and start the service
+    // Enable and start the socket, or the service
+    let unit = if idle_timeout.is_some() { SOCKET_UNIT } else { SERVICE_UNIT };
     Command::new("systemctl")
         .args(["--user", "daemon-reload"])
         .status()
         .map_err(|e| format!("Failed to reload systemd: {}", e))?;
 
     Command::new("systemctl")
-        .args(["--user", "enable", "roots-embedder"])
+        .args(["--user", "enable", unit])
         .status()
         .map_err(|e| format!("Failed to enable service: {}", e))?;
 
     Command::new("systemctl")
-        .args(["--user", "start", "roots-embedder"])
+        .args(["--user", "start", unit])
         .status()
         .map_err(|e| format!("Failed to start service: {}", e))?;
 
     println!("Installed systemd user service: roots-embedder");
     println!("Working directory: {}", cwd_str);
-    println!("\nThe server will now start automatically on login.");
+    match idle_timeout {
+        Some(idle) => println!(
+            "\nThe server will start on the first request and exit after {} minutes without any.",
+            idle.num_minutes()
+        ),
+        None => println!("\nThe server will now start automatically on login."),
+    }
     println!("\nManage with:");
-    println!("  systemctl --user status roots-embedder");
-    println!("  systemctl --user restart roots-embedder");
-    println!("  systemctl --user stop roots-embedder");
+    println!("  systemctl --user status {}", unit);
+    println!("  systemctl --user restart {}", unit);
+    println!("  systemctl --user stop {}", unit);
 
     Ok(())
 }
 
 /// Run server uninstall command
-pub fn run_uninstall() -> Result<(), String> {
-    // Stop and disable the service
-    Command::new("systemctl")
-        .args(["--user", "stop", "roots-embedder"])
-        .status()
-        .ok();
-
-    Command::new("systemctl")
-        .args(["--user", "disable", "roots-embedder"])
-        .status()
-        .ok();
+pub fn run_uninstall() -> Result<(), Error> {
+    // Stop and disable the socket and the service
+    for unit in [SOCKET_UNIT, SERVICE_UNIT] {
+        Command::new("systemctl")
+            .args(["--user", "stop", unit])
+            .status()
+            .ok();
 
-    // Remove the service file
-    let home = dirs::home_dir().ok_or("Could not find home directory")?;
-    let service_path = home.join(".config/systemd/user/roots-embedder.service");
+        Command::new("systemctl")
+            .args(["--user", "disable", unit])
+            .status()
+            .ok();
+    }
 
-    if service_path.exists() {
-        fs::remove_file(&service_path)
-            .map_err(|e| format!("Failed to remove service file: {}", e))?;
+    // Remove the unit files
+    let systemd_dir = systemd_user_dir()?;
+    for unit in [SOCKET_UNIT, SERVICE_UNIT] {
+        let path = systemd_dir.join(unit);
+        if path.exists() {
+            fs::remove_file(&path).map_err(|e| format!("Failed to remove {}: {}", unit, e))?;
+        }
     }
 
     Command::new("systemctl")
@@ -0,0 +1,299 @@
+use crate::cli::hooks::{run_hooks, run_rules};
+use crate::cli::memory::run_init;
+use crate::cli::server::run_start;
+use crate::config::{
+    find_roots_path, global_roots_path, set_global_config, RootsConfig, DEFAULT_MODEL,
+    SUGGESTED_MODELS,
+};
+use crate::error::Error;
+use crate::memory::Memories;
+use crate::term;
+use clap_complete::env::Shells;
+use std::fs;
+use std::path::Path;
+
+/// Context modes offered for the Claude Code prompt hook, with what they cost
+const CONTEXT_MODES: &[(&str, &str)] = &[
+    ("none", "only prime at session start and before compaction"),
+    ("tags", "also match tags in each prompt (instant)"),
+    ("lite", "also match each prompt with lite embeddings (fast)"),
+    ("semantic", "also search each prompt with the configured model (best, slower)"),
+];
+
+/// Run the completions command - print the script registering completion
+/// for `shell`. Sourcing it makes Tab call back into this binary, so tags,
+/// IDs, and models are completed from the store at hand.
+pub fn run_completions(shell: &str) -> Result<(), Error> {
+    let shells = Shells::builtins();
+    let completer = shells
+        .completer(shell)
+        .ok_or_else(|| Error::Invalid(format!("Unknown shell: {}", shell)))?;
+    // Wherever roots is installed, even off PATH
+    let bin = std::env::current_exe()
+        .map(|p| p.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| "roots".to_string());
+    completer
+        .write_registration("COMPLETE", "roots", "roots", &bin, &mut std::io::stdout())
+        .map_err(|e| format!("Failed to write completions: {}", e))?;
+    Ok(())
+}
+
+/// Run the setup command - guided first-run configuration
+pub fn run_setup() -> Result<(), Error> {
+    if !term::is_interactive() {
+        return Err(Error::Invalid("roots setup needs an interactive terminal".to_string()));
+    }
+
+    println!("roots setup\n");
+
+    let roots_path = match find_roots_path() {
+        Some(path) => {
+            println!("Using the store at {}\n", path.display());
+            path
+        }
+        None => {
+            if !ask_yes_no("No .roots here. Initialize a store in this directory?", true)? {
+                println!("\nNothing to set up without a store. Run 'roots init' when ready.");
+                return Ok(());
+            }
+            run_init(".", false)?;
+            println!();
+            Path::new(".roots").to_path_buf()
+        }
+    };
+    let project = roots_path.parent().unwrap_or(Path::new("."));
+    let mut config = RootsConfig::new(roots_path.clone());
+
+    choose_embeddings(&mut config)?;
+    install_agent_hooks(project)?;
+    choose_context_defaults(&mut config)?;
+    offer_global_store(&config.embedding_model())?;
+
+    println!("\nDone. Check everything with: roots doctor");
+    Ok(())
+}
+
+fn choose_embeddings(config: &mut RootsConfig) -> Result<(), Error> {
+    println!("Embeddings");
+    let backends = [
+        ("lite", "n-gram hashing, no dependencies, instant startup"),
+        ("server", "sentence-transformers models via 'roots server' (needs uv, downloads a model)"),
+        ("onnx", "the same models on ONNX Runtime, faster on CPU (needs uv, downloads a model)"),
+    ];
+    let backend = choose("Embedding backend", &backends, 0)?;
+
+    if backend == "lite" {
+        save_embeddings(config, backend, "lite", set_global_config)?;
+        println!("Using lite embeddings.\n");
+        return Ok(());
+    }
+
+    let models: Vec<(&str, &str)> = SUGGESTED_MODELS
+        .iter()
+        .filter(|m| m.model_type != "lite")
+        .map(|m| (m.alias, m.description))
+        .collect();
+    let default = SUGGESTED_MODELS
+        .iter()
+        .filter(|m| m.model_type != "lite")
+        .position(|m| m.name == DEFAULT_MODEL || m.alias == DEFAULT_MODEL)
+        .unwrap_or(0);
+    let model = choose("Model", &models, default)?;
+
+    save_embeddings(config, backend, model, set_global_config)?;
+    println!("Using {}{}.", model, if backend == "onnx" { " on ONNX Runtime" } else { "" });
+
+    if ask_yes_no("Start the embedding server now?", true)? {
+        run_start(false, None)?;
+    } else {
+        println!("Start it later with: roots server start");
+    }
+    println!();
+    Ok(())
+}
+
+/// Save the chosen backend and model: the project's `embedding_model`, and
+/// for a server backend the server's model and what it runs on, through
+/// `set_global`
+fn save_embeddings(
+    config: &mut RootsConfig,
+    backend: &str,
+    model: &str,
+    mut set_global: impl FnMut(&str, &str) -> std::io::Result<()>,
+) -> Result<(), Error> {
+    let saved = |e: std::io::Error| format!("Failed to save config: {}", e);
+    config.set_embedding_model(model).map_err(saved)?;
+    if backend == "lite" {
+        return Ok(());
+    }
+    // The project and the server must agree, or recall falls back to lite
+    set_global("server_model", model).map_err(saved)?;
+    let runtime = if backend == "onnx" { "onnx" } else { "torch" };
+    set_global("server_backend", runtime).map_err(saved)?;
+    Ok(())
+}
+
+fn install_agent_hooks(project: &Path) -> Result<(), Error> {
+    println!("Agents");
+    let home = dirs::home_dir();
+    let detected = |dir: &str| {
+        project.join(dir).is_dir() || home.as_ref().is_some_and(|h| h.join(dir).is_dir())
+    };
+
+    let mut found = false;
+    if detected(".claude") {
+        found = true;
+        if ask_yes_no("Claude Code detected. Install roots hooks for this project?", true)? {
+            let mode = choose("Context on each prompt", CONTEXT_MODES, 1)?;
+            run_hooks(&project.to_string_lossy(), false, mode, Default::default(), false)?;
+        }
+    }
+    for (dir, tool, name) in [(".cursor", "cursor", "Cursor"), (".windsurf", "windsurf", "Windsurf")] {
+        if !detected(dir) {
+            continue;
+        }
+        found = true;
+        if ask_yes_no(&format!("{} detected. Write a roots rules file for this project?", name), true)? {
+            // An empty store has nothing to write yet
+            if let Err(e) = run_rules(&project.to_string_lossy(), tool, false) {
+                eprintln!("Warning: {}. Write it later with: roots hooks --tool {}", e, tool);
+            }
+        }
+    }
+    if !found {
+        println!("No supported agents detected. Install Claude Code hooks later with: roots hooks");
+    }
+    println!();
+    Ok(())
+}
+
+fn choose_context_defaults(config: &mut RootsConfig) -> Result<(), Error> {
+    println!("Context");
+    let current = config.get("context_max_age").unwrap_or_default();
+    let prompt = format!(
+        "Leave memories untouched for longer than this out of hook context (e.g. 180d, blank for no limit) [{}]: ",
+        current
+    );
+    loop {
+        let input = term::prompt_line(&prompt)?.ok_or_else(cancelled)?;
+        if input.is_empty() {
+            break;
+        }
+        match crate::dates::parse_duration(&input) {
+            Ok(_) => {
+                config
+                    .set("context_max_age", &input)
+                    .map_err(|e| format!("Failed to save config: {}", e))?;
+                break;
+            }
+            Err(e) => println!("  {}", e),
+        }
+    }
+    println!();
+    Ok(())
+}
+
+fn offer_global_store(model: &str) -> Result<(), Error> {
+    let Some(global) = global_roots_path() else {
+        return Ok(());
+    };
+    if global.is_dir() {
+        println!("Global store: {}", global.display());
+        return Ok(());
+    }
+
+    let prompt = format!(
+        "Create a global store at {} for preferences shared by every project?",
+        global.display()
+    );
+    if ask_yes_no(&prompt, false)? {
+        // Same model as the project, so merged recall compares like with like
+        fs::create_dir_all(&global)
+            .map_err(|e| format!("Failed to create {}: {}", global.display(), e))?;
+        RootsConfig::new(global)
+            .set_embedding_model(model)
+            .map_err(|e| format!("Failed to save config: {}", e))?;
+        let mem = Memories::open_global()?;
+        println!("Created {}. Add to it with: roots remember --global", mem.roots_path().display());
+    }
+    Ok(())
+}
+
+/// Pick one of `options` by number, `default` on an empty answer
+fn choose<'a>(prompt: &str, options: &[(&'a str, &str)], default: usize) -> Result<&'a str, Error> {
+    for (i, (name, description)) in options.iter().enumerate() {
+        println!("  {}) {:<10} {}", i + 1, name, description);
+    }
+    loop {
+        let input = term::prompt_line(&format!("{} [{}]: ", prompt, default + 1))?
+            .ok_or_else(cancelled)?;
+        if input.is_empty() {
+            return Ok(options[default].0);
+        }
+        let picked = input
+            .parse::<usize>()
+            .ok()
+            .and_then(|n| options.get(n.wrapping_sub(1)))
+            .or_else(|| options.iter().find(|(name, _)| *name == input));
+        match picked {
+            Some((name, _)) => return Ok(name),
+            None => println!("  Enter a number from 1 to {}", options.len()),
+        }
+    }
+}
+
+fn ask_yes_no(prompt: &str, default: bool) -> Result<bool, Error> {
+    let hint = if default { "Y/n" } else { "y/N" };
+    loop {
+        let input = term::prompt_line(&format!("{} [{}] ", prompt, hint))?.ok_or_else(cancelled)?;
+        match input.to_lowercase().as_str() {
+            "" => return Ok(default),
+            "y" | "yes" => return Ok(true),
+            "n" | "no" => return Ok(false),
+            _ => println!("  Answer y or n"),
+        }
+    }
+}
+
+fn cancelled() -> String {
+    "Setup cancelled".to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_backends_are_saved_for_the_server() {
+        let dir = std::env::temp_dir().join(format!("roots-setup-backend-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut config = RootsConfig::new(dir.clone());
+        let save = |config: &mut RootsConfig, backend, model| {
+            let mut global = Vec::new();
+            save_embeddings(config, backend, model, |key, value| {
+                global.push((key.to_string(), value.to_string()));
+                Ok(())
+            })
+            .unwrap();
+            global
+        };
+
+        let global = save(&mut config, "onnx", "bge-small");
+        assert_eq!(
+            global,
+            vec![
+                ("server_model".to_string(), "bge-small".to_string()),
+                ("server_backend".to_string(), "onnx".to_string()),
+            ]
+        );
+        assert_eq!(RootsConfig::new(dir.clone()).embedding_model(), "bge-small");
+
+        // Choosing the plain server again switches back off ONNX
+        let global = save(&mut config, "server", "bge-base");
+        assert_eq!(global[1], ("server_backend".to_string(), "torch".to_string()));
+        assert!(save(&mut config, "lite", "lite").is_empty());
+        assert_eq!(RootsConfig::new(dir.clone()).embedding_model(), "lite");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
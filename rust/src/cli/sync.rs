@@ -0,0 +1,103 @@
+use crate::error::Error;
+use crate::managed;
+use crate::memory::Memories;
+use crate::types::{Memory, MemoryFilter, SortOrder};
+use std::fs;
+use std::path::Path;
+
+/// Memories considered for a managed block, before ranking
+const CANDIDATES: usize = 10000;
+
+/// Instruction files agents read, updated by `sync --claude-md`; with
+/// neither present, the first is created
+const AGENT_FILES: &[&str] = &["CLAUDE.md", "AGENTS.md"];
+
+/// Run `sync --aider` and/or `sync --claude-md`: keep a block of the most
+/// trusted memories in aider's `CONVENTIONS.md`, and in `CLAUDE.md` /
+/// `AGENTS.md`, which agents read without any hook support
+pub fn run_sync_agents(aider: bool, claude_md: bool, min_confidence: f64, limit: usize) -> Result<(), Error> {
+    let mem = Memories::open()?;
+    let project = mem.roots_path().parent().unwrap_or(Path::new("."));
+    let memories = trusted(&mem, min_confidence, limit)?;
+
+    if aider {
+        let path = project.join("CONVENTIONS.md");
+        write_block(&path, &render("aider", &memories))?;
+        println!("Synced {} memories to {}", memories.len(), path.display());
+
+        let config = project.join(".aider.conf.yml");
+        let loaded = fs::read_to_string(&config).is_ok_and(|c| c.contains("CONVENTIONS.md"));
+        if !loaded {
+            println!("  Have aider load it: add 'read: CONVENTIONS.md' to .aider.conf.yml");
+        }
+    }
+
+    if claude_md {
+        let mut paths: Vec<_> = AGENT_FILES
+            .iter()
+            .map(|f| project.join(f))
+            .filter(|p| p.exists())
+            .collect();
+        if paths.is_empty() {
+            paths.push(project.join(AGENT_FILES[0]));
+        }
+        for path in paths {
+            write_block(&path, &render("claude-md", &memories))?;
+            println!("Synced {} memories to {}", memories.len(), path.display());
+        }
+    }
+    Ok(())
+}
+
+/// Pinned memories, then those with at least `min_confidence`, most
+/// confident first
+fn trusted(mem: &Memories, min_confidence: f64, limit: usize) -> Result<Vec<Memory>, Error> {
+    let mut memories: Vec<Memory> = mem
+        .list(&MemoryFilter::default(), CANDIDATES, SortOrder::Recent)?
+        .into_iter()
+        .filter(|m| m.pinned || m.confidence >= min_confidence)
+        .collect();
+    memories.sort_by(|a, b| {
+        b.pinned
+            .cmp(&a.pinned)
+            .then(b.confidence.partial_cmp(&a.confidence).unwrap_or(std::cmp::Ordering::Equal))
+    });
+    memories.truncate(limit);
+    for m in &mut memories {
+        mem.hydrate(m)?;
+    }
+    Ok(memories)
+}
+
+/// The managed block: a note on where it comes from and one bullet per
+/// memory
+fn render(flag: &str, memories: &[Memory]) -> String {
+    let mut body = format!(
+        "<!-- Managed by roots: `roots sync --{}` rewrites this block, edit memories instead. -->\n## Project memories\n\n",
+        flag
+    );
+    if memories.is_empty() {
+        body.push_str("_No memories yet._\n");
+    }
+    for m in memories {
+        let content = m.content.split_whitespace().collect::<Vec<_>>().join(" ");
+        match &m.kind {
+            Some(kind) => body.push_str(&format!("- ({}) {}\n", kind, content)),
+            None => body.push_str(&format!("- {}\n", content)),
+        }
+    }
+    body
+}
+
+fn write_block(path: &Path, body: &str) -> Result<(), Error> {
+    let existing = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e).into()),
+    };
+    let updated = managed::update(&existing, body).map_err(|e| format!("{} in {}", e, path.display()))?;
+    if updated != existing {
+        fs::write(path, updated).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    }
+    Ok(())
+}
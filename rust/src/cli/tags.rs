@@ -0,0 +1,98 @@
+use crate::cli::{print_json, Output};
+use crate::error::Error;
+use crate::memory::Memories;
+use crate::proto;
+use crate::types::{MemoryFilter, MemoryUpdate, SortOrder};
+
+/// Run the tags command
+pub fn run_tags(output: Output) -> Result<(), Error> {
+    let mem = Memories::open()?;
+    let tags = mem.tags()?;
+
+    if output.is_json() {
+        let tags: Vec<proto::TagCount> = tags.into_iter().map(|(tag, count)| proto::TagCount { tag, count }).collect();
+        return print_json(&tags);
+    }
+    if tags.is_empty() {
+        println!("No tags yet.");
+        return Ok(());
+    }
+
+    println!("Tags:\n");
+    for (tag, count) in tags {
+        println!("  {:20} ({})", tag, count);
+    }
+
+    Ok(())
+}
+
+/// Run `tags rename`: replace one tag with another everywhere
+pub fn run_rename(old: &str, new: &str) -> Result<(), Error> {
+    let mem = Memories::open()?;
+    let ids = mem.rename_tags(&[old.to_string()], new)?;
+    println!("Renamed '{}' to '{}' on {} memories", old, new, ids.len());
+    Ok(())
+}
+
+/// Run `tags merge`: fold the other tags into the first
+pub fn run_merge(tags: &[String]) -> Result<(), Error> {
+    let (into, from) = tags.split_first().ok_or_else(|| Error::Invalid("Give at least two tags".to_string()))?;
+    let mem = Memories::open()?;
+    let ids = mem.rename_tags(from, into)?;
+    println!("Merged {} into '{}' on {} memories", from.join(", "), into, ids.len());
+    Ok(())
+}
+
+/// Run `tags suggest`: propose tags for one memory, or every untagged one,
+/// from the tags of its nearest tagged memories and existing tags it names
+pub fn run_suggest(id: Option<i64>, untagged: bool, apply: bool, limit: usize) -> Result<(), Error> {
+    let mem = Memories::open()?;
+    let ids: Vec<i64> = match id {
+        Some(id) => vec![id],
+        None if untagged => mem
+            .list(&MemoryFilter::default(), usize::MAX, SortOrder::Recent)?
+            .into_iter()
+            .filter(|m| m.tags.is_empty())
+            .take(limit)
+            .map(|m| m.id)
+            .collect(),
+        None => return Err(Error::Invalid("Give a memory id or --untagged".to_string())),
+    };
+    if ids.is_empty() {
+        println!("No untagged memories.");
+        return Ok(());
+    }
+
+    let mut suggested = 0;
+    for (memory, tags) in mem.neighbor_tags(&ids)? {
+        let preview: String = memory.content.lines().next().unwrap_or("").chars().take(60).collect();
+        println!("[{}] {}", memory.id, preview);
+        if tags.is_empty() {
+            println!("  no suggestions");
+            continue;
+        }
+        suggested += 1;
+        if apply {
+            let mut all = memory.tags.clone();
+            all.extend(tags.iter().cloned());
+            mem.update(
+                memory.id,
+                &MemoryUpdate {
+                    tags: Some(all),
+                    ..Default::default()
+                },
+            )?;
+            println!("  tagged: {}", tags.join(", "));
+        } else {
+            println!("  suggested: {}", tags.join(", "));
+        }
+    }
+
+    if ids.len() > 1 {
+        println!("\n{} of {} memories have suggestions", suggested, ids.len());
+    }
+    if !apply && suggested > 0 {
+        println!("Apply with --apply, or one at a time with: roots update <id> --tags ...");
+    }
+    Ok(())
+}
@@ -0,0 +1,139 @@
+use crate::cli::ingest::reingest_docs;
+use crate::cli::memory::write_markdown;
+use crate::config::RootsConfig;
+use crate::error::Error;
+use crate::ingest::DocOptions;
+use crate::index::MemoryStore;
+use crate::lock::MaintenanceLock;
+use crate::memory::Memories;
+use crate::types::{MemoryFilter, PruneCriteria, SortOrder};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Memories embedded per batch while draining the queue
+const EMBED_BATCH: usize = 32;
+
+pub struct WatchOptions {
+    /// Time between passes
+    pub interval: Duration,
+    /// Time between pruning and index maintenance
+    pub maintain_every: Duration,
+    /// Run a single pass, with maintenance, and exit
+    pub once: bool,
+    pub quiet: bool,
+}
+
+/// Run the watch command: a maintenance loop so interactive commands stay
+/// fast. Each pass embeds queued memories, re-ingests ingested files that
+/// changed (forgetting the chunks of removed ones), and rewrites the synced
+/// markdown directory when memories change; every `maintain_every` it also
+/// prunes expired memories, refreshes importance, and optimizes the index.
+/// Files are re-ingested as they were ingested, or with `fallback` if they
+/// were ingested before that was kept.
+pub fn run_watch(fallback: &DocOptions, options: WatchOptions) -> Result<(), Error> {
+    let mem = Memories::open()?;
+    let log = |message: String| {
+        if !options.quiet {
+            println!("[{}] {}", chrono::Local::now().format("%H:%M:%S"), message);
+        }
+    };
+
+    let mut synced = None;
+    let mut maintained: Option<Instant> = None;
+    loop {
+        let due = maintained.is_none_or(|t| t.elapsed() >= options.maintain_every);
+        let result = pass(&mem, fallback, &mut synced, &log).and_then(|_| {
+            if due {
+                maintain(&mem, &log)?;
+                maintained = Some(Instant::now());
+            }
+            Ok(())
+        });
+
+        if options.once {
+            return result;
+        }
+        // A failed pass is retried next time rather than ending the watch
+        if let Err(e) = result {
+            eprintln!("Error: {}", e);
+        }
+        thread::sleep(options.interval);
+    }
+}
+
+/// The frequent work: embeddings, ingested files, synced markdown
+fn pass(mem: &Memories, fallback: &DocOptions, synced: &mut Option<String>, log: &dyn Fn(String)) -> Result<(), Error> {
+    let mut embedded = 0;
+    loop {
+        let n = mem.embed_pending(EMBED_BATCH)?;
+        if n == 0 {
+            break;
+        }
+        embedded += n;
+    }
+    if embedded > 0 {
+        log(format!("Embedded {} queued memories", embedded));
+    }
+
+    let report = reingest_docs(mem, fallback)?;
+    if report.files > 0 || report.removed > 0 {
+        log(format!(
+            "Re-ingested {} changed files ({} chunks), removed {}",
+            report.files, report.stored, report.removed
+        ));
+    }
+
+    // Only stores that use `roots sync` have the directory
+    if mem.roots_path().join("memories").is_dir() {
+        let state = fingerprint(mem)?;
+        if synced.as_ref() != Some(&state) {
+            let count = write_markdown(mem)?;
+            if synced.is_some() {
+                log(format!("Synced {} memories to markdown", count));
+            }
+            *synced = Some(state);
+        }
+    }
+    Ok(())
+}
+
+/// Changes when memories are added, updated, or forgotten
+fn fingerprint(mem: &Memories) -> Result<String, Error> {
+    let count = mem.count()?;
+    let latest = mem.list(&MemoryFilter::everything(), 1, SortOrder::Recent)?;
+    Ok(match latest.first() {
+        Some(m) => format!("{}:{}:{}", count, m.id, m.updated_at),
+        None => count.to_string(),
+    })
+}
+
+/// The occasional work: pruning expired memories, importance, the index
+fn maintain(mem: &Memories, log: &dyn Fn(String)) -> Result<(), Error> {
+    let expired = mem.prunable(&PruneCriteria {
+        expired: true,
+        ..Default::default()
+    })?;
+    for m in &expired {
+        mem.forget(m.id)?;
+    }
+    if !expired.is_empty() {
+        log(format!("Pruned {} expired memories", expired.len()));
+    }
+
+    mem.refresh_importance()?;
+
+    let roots_path = mem.roots_path();
+    if RootsConfig::new(roots_path.to_path_buf()).store_backend() == "sqlite" {
+        // Leave the index to a manual reindex or optimize that is running
+        match MaintenanceLock::acquire(roots_path, "watch", true) {
+            Ok(_lock) => {
+                MemoryStore::open(&roots_path.join("memory.db"))
+                    .and_then(|store| store.optimize())
+                    .map_err(|e| format!("Failed to optimize: {}", e))?;
+                log("Optimized the search index".to_string());
+            }
+            Err(e) => log(format!("Skipped optimize: {}", e)),
+        }
+    }
+    Ok(())
+}
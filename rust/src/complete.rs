@@ -0,0 +1,111 @@
+//! Values offered by shell completion (`roots completions <shell>`).
+//!
+//! The script a shell sources calls back into roots on each Tab
+//! (`COMPLETE=<shell> roots -- <words>`), so tags and IDs come from the
+//! store of the directory being completed in, opened read-only without
+//! migrations or an embedder. Completers never fail: with no store (or a
+//! remote one) they offer nothing.
+
+use crate::config::{find_roots_path, RootsConfig, SETTINGS, SUGGESTED_MODELS};
+use crate::index::MemoryStore;
+use crate::store::Store;
+use crate::types::{MemoryFilter, SortOrder};
+use clap_complete::engine::CompletionCandidate;
+use std::ffi::OsStr;
+
+/// Recent memories offered where an ID is expected
+const ID_CANDIDATES: usize = 200;
+
+/// Characters of a memory shown beside its ID
+const ID_PREVIEW_CHARS: usize = 60;
+
+/// The local store of the directory being completed in
+fn store() -> Option<MemoryStore> {
+    let roots_path = find_roots_path()?;
+    if RootsConfig::new(roots_path.clone()).store_backend() != "sqlite" {
+        return None;
+    }
+    MemoryStore::open_read_only(&roots_path.join("memory.db")).ok()
+}
+
+/// Tags in the store, most used first
+pub fn tags() -> Vec<CompletionCandidate> {
+    let Some(Ok(tags)) = store().map(|store| store.get_all_tags()) else {
+        return Vec::new();
+    };
+    tags.into_iter()
+        .map(|(tag, count)| CompletionCandidate::new(tag).help(Some(format!("{} memories", count).into())))
+        .collect()
+}
+
+/// The last of comma-separated tags, keeping the ones before it
+pub fn tag_list(current: &OsStr) -> Vec<CompletionCandidate> {
+    let Some(current) = current.to_str() else {
+        return Vec::new();
+    };
+    let (done, last) = match current.rsplit_once(',') {
+        Some((done, last)) => (format!("{},", done), last),
+        None => (String::new(), current),
+    };
+    let listed: Vec<&str> = done.split(',').map(str::trim).collect();
+    tags()
+        .into_iter()
+        .filter(|c| {
+            let tag = c.get_value().to_string_lossy();
+            tag.starts_with(last) && !listed.contains(&tag.as_ref())
+        })
+        .map(|c| c.add_prefix(done.clone()))
+        .collect()
+}
+
+/// IDs of the most recent memories, each with the start of its content
+pub fn ids() -> Vec<CompletionCandidate> {
+    let recent = store().map(|store| store.list(&MemoryFilter::everything(), ID_CANDIDATES, SortOrder::Recent));
+    let Some(Ok(recent)) = recent else {
+        return Vec::new();
+    };
+    recent
+        .into_iter()
+        .map(|m| {
+            let preview: String = m.content.lines().next().unwrap_or("").chars().take(ID_PREVIEW_CHARS).collect();
+            CompletionCandidate::new(m.id.to_string()).help(Some(preview.into()))
+        })
+        .collect()
+}
+
+/// Model aliases, as `config model` and `server model` accept them
+pub fn models() -> Vec<CompletionCandidate> {
+    SUGGESTED_MODELS
+        .iter()
+        .map(|m| CompletionCandidate::new(m.alias).help(Some(m.description.into())))
+        .collect()
+}
+
+/// Profiles in the global config, for `--profile`
+pub fn profiles() -> Vec<CompletionCandidate> {
+    crate::config::profiles().into_keys().map(CompletionCandidate::new).collect()
+}
+
+/// Setting names for `config`, with `model` (the usual way to set
+/// `embedding_model`) first
+pub fn config_keys() -> Vec<CompletionCandidate> {
+    std::iter::once(CompletionCandidate::new("model").help(Some("Embedding model".into())))
+        .chain(SETTINGS.iter().map(|s| CompletionCandidate::new(s.key)))
+        .collect()
+}
+
+/// Values for the setting being set by `config <key>`. Completers only see
+/// the word being completed, so the key is read from the command line,
+/// which during completion is `roots -- roots [options] config <key> ...`.
+pub fn config_values() -> Vec<CompletionCandidate> {
+    let args: Vec<String> = std::env::args().collect();
+    let key = args
+        .iter()
+        .skip_while(|a| *a != "config")
+        .skip(1)
+        .find(|a| !a.starts_with('-'));
+    match key.map(String::as_str) {
+        Some("model" | "embedding_model" | "server_model") => models(),
+        _ => Vec::new(),
+    }
+}
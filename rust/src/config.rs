@@ -1,7 +1,8 @@
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 
 /// Model information
 #[derive(Debug, Clone)]
@@ -78,6 +79,385 @@ pub static SUGGESTED_MODELS: &[ModelInfo] = &[
 
 pub const DEFAULT_MODEL: &str = "bge-base";
 
+/// What the server can run models on, PyTorch (the default) or ONNX Runtime
+pub const SERVER_BACKENDS: &[&str] = &["torch", "onnx"];
+
+/// Longest span a day count or duration setting takes (about a century),
+/// so timestamps computed from it stay representable
+pub const MAX_SETTING_DAYS: i64 = 36500;
+
+/// Cross-encoder the embedding server reranks with
+pub const DEFAULT_RERANK_MODEL: &str = "BAAI/bge-reranker-base";
+
+// -----------------------------------------------------------------------------
+// Known settings
+// -----------------------------------------------------------------------------
+
+/// Where a setting is read from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    /// `.roots/_config.yaml`
+    Project,
+    /// `config.yaml` in the global config directory (`~/.config/roots`)
+    Global,
+    /// Only the environment
+    Env,
+}
+
+/// What values a setting takes, checked when `roots config` sets it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueKind {
+    Text,
+    Bool,
+    /// A whole number, 0 or more
+    Count,
+    /// A whole number of days, 0 to `MAX_SETTING_DAYS`
+    Days,
+    /// A number from 0 to 1
+    Fraction,
+    /// A number, 0 or more
+    Weight,
+    /// A duration like `30d` or `2w`, up to `MAX_SETTING_DAYS`
+    Duration,
+    OneOf(&'static [&'static str]),
+    /// Comma-separated
+    List,
+    Regex,
+    /// A model alias (`roots config --list-models`) or sentence-transformers name
+    Model,
+    /// Nested YAML, edited in the file
+    Structured,
+}
+
+impl ValueKind {
+    /// Short description for `config --list-keys`
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Text => "text".to_string(),
+            Self::Bool => "true|false".to_string(),
+            Self::Count => "integer >= 0".to_string(),
+            Self::Days => format!("days 0-{}", MAX_SETTING_DAYS),
+            Self::Fraction => "number 0-1".to_string(),
+            Self::Weight => "number >= 0".to_string(),
+            Self::Duration => format!("duration (30d) up to {}d", MAX_SETTING_DAYS),
+            Self::OneOf(values) => values.join("|"),
+            Self::List => "comma-separated list".to_string(),
+            Self::Regex => "regex".to_string(),
+            Self::Model => "model".to_string(),
+            Self::Structured => "yaml (edit the file)".to_string(),
+        }
+    }
+
+    /// Check `value`, describing what was expected if it doesn't fit
+    pub fn validate(&self, value: &str) -> Result<(), String> {
+        let number = || value.parse::<f64>().ok().filter(|n| n.is_finite());
+        let ok = match self {
+            Self::Text | Self::List => true,
+            Self::Model => !value.trim().is_empty(),
+            Self::Bool => matches!(value, "true" | "false"),
+            Self::Count => value.parse::<u64>().is_ok(),
+            Self::Days => value.parse::<i64>().is_ok_and(|d| (0..=MAX_SETTING_DAYS).contains(&d)),
+            Self::Fraction => number().is_some_and(|n| (0.0..=1.0).contains(&n)),
+            Self::Weight => number().is_some_and(|n| n >= 0.0),
+            Self::Duration => crate::dates::parse_duration(value)
+                .is_ok_and(|d| d > chrono::Duration::zero() && d <= chrono::Duration::days(MAX_SETTING_DAYS)),
+            Self::OneOf(values) => values.contains(&value),
+            Self::Regex => {
+                return regex::Regex::new(value).map(|_| ()).map_err(|e| e.to_string());
+            }
+            Self::Structured => false,
+        };
+        if ok {
+            Ok(())
+        } else {
+            Err(format!("expected {}", self.describe()))
+        }
+    }
+}
+
+/// A setting roots understands. Values resolve env first, then the project
+/// config (project settings only), then the global config, then the default.
+pub struct SettingSpec {
+    pub key: &'static str,
+    pub scope: Scope,
+    pub env: Option<&'static str>,
+    pub default: Option<&'static str>,
+    /// Masked when printed
+    pub secret: bool,
+    pub kind: ValueKind,
+}
+
+const fn setting(key: &'static str, scope: Scope) -> SettingSpec {
+    SettingSpec {
+        key,
+        scope,
+        env: None,
+        default: None,
+        secret: false,
+        kind: ValueKind::Text,
+    }
+}
+
+impl SettingSpec {
+    /// Environment variable that overrides the setting: `ROOTS_` and the
+    /// key in capitals with dots as underscores, unless it names its own
+    pub fn env_var(&self) -> String {
+        match self.env {
+            Some(var) => var.to_string(),
+            None => format!("ROOTS_{}", self.key.to_uppercase().replace('.', "_")),
+        }
+    }
+
+    const fn kind(mut self, kind: ValueKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    const fn env(mut self, var: &'static str) -> Self {
+        self.env = Some(var);
+        self
+    }
+
+    const fn default(mut self, value: &'static str) -> Self {
+        self.default = Some(value);
+        self
+    }
+
+    const fn secret(mut self) -> Self {
+        self.secret = true;
+        self
+    }
+}
+
+pub const SETTINGS: &[SettingSpec] = &[
+    setting("embedding_model", Scope::Project)
+        .env("ROOTS_MODEL")
+        .default(DEFAULT_MODEL)
+        .kind(ValueKind::Model),
+    setting("store", Scope::Project)
+        .default("sqlite")
+        .kind(ValueKind::OneOf(crate::store::STORE_BACKENDS)),
+    setting("libsql_url", Scope::Project),
+    setting("libsql_auth_token", Scope::Project).secret(),
+    setting("changelog", Scope::Project).default("false").kind(ValueKind::Bool),
+    setting("slow_query_ms", Scope::Project).default("200").kind(ValueKind::Count),
+    setting("tombstone_retention_days", Scope::Project)
+        .default("90")
+        .kind(ValueKind::Days),
+    setting("context_max_age", Scope::Project).kind(ValueKind::Duration),
+    setting("context_max_tokens", Scope::Project).kind(ValueKind::Count),
+    setting("context_threshold", Scope::Project).default("0.5").kind(ValueKind::Weight),
+    setting("prime_recent_days", Scope::Project).default("7").kind(ValueKind::Days),
+    setting("defer_embeddings", Scope::Project).default("false").kind(ValueKind::Bool),
+    setting("blob_threshold", Scope::Project).default("16384").kind(ValueKind::Count),
+    setting("require_tags_from", Scope::Project).kind(ValueKind::List),
+    setting("capture_threshold", Scope::Project).default("0.5").kind(ValueKind::Fraction),
+    setting("capture_command", Scope::Project),
+    setting("commit_pattern", Scope::Project).kind(ValueKind::Regex),
+    setting("ranking.similarity", Scope::Project).default("1").kind(ValueKind::Weight),
+    setting("ranking.confidence", Scope::Project).default("0").kind(ValueKind::Weight),
+    setting("ranking.recency", Scope::Project).default("0").kind(ValueKind::Weight),
+    setting("ranking.recency_half_life", Scope::Project)
+        .default("30d")
+        .kind(ValueKind::Duration),
+    setting("ranking.access", Scope::Project).default("0.05").kind(ValueKind::Weight),
+    setting("ranking.importance", Scope::Project).default("0.05").kind(ValueKind::Weight),
+    setting("rerank", Scope::Project).default("false").kind(ValueKind::Bool),
+    setting("expand_queries", Scope::Project).default("false").kind(ValueKind::Bool),
+    setting("auto_tag", Scope::Project)
+        .default("off")
+        .kind(ValueKind::OneOf(crate::autotag::AUTO_TAG_MODES)),
+    setting("query_log", Scope::Project)
+        .default("off")
+        .kind(ValueKind::OneOf(&crate::query_log::QUERY_LOG_MODES)),
+    setting("rerank_candidates", Scope::Project).default("20").kind(ValueKind::Count),
+    setting("rerank_model", Scope::Project).default(DEFAULT_RERANK_MODEL),
+    setting("context_rerank_model", Scope::Project),
+    setting("routing", Scope::Project).kind(ValueKind::Structured),
+    setting("server_model", Scope::Global)
+        .default(DEFAULT_MODEL)
+        .kind(ValueKind::Model),
+    setting("server_extra_models", Scope::Global).kind(ValueKind::List),
+    setting("server_backend", Scope::Global)
+        .default(SERVER_BACKENDS[0])
+        .kind(ValueKind::OneOf(SERVER_BACKENDS)),
+    setting("server_socket", Scope::Global).env("ROOTS_SOCKET"),
+    setting("server_idle_timeout", Scope::Global).kind(ValueKind::Duration),
+    setting("server_dir", Scope::Global),
+    setting("server.autostart", Scope::Global).default("false").kind(ValueKind::Bool),
+    setting("hooks.fail_fast", Scope::Global).default("false").kind(ValueKind::Bool),
+    setting("backup_s3_bucket", Scope::Global),
+    setting("backup_s3_endpoint", Scope::Global),
+    setting("backup_s3_region", Scope::Global).default("us-east-1"),
+    setting("backup_s3_prefix", Scope::Global).default("roots/"),
+    setting("backup_s3_access_key", Scope::Global).env("AWS_ACCESS_KEY_ID"),
+    setting("backup_s3_secret_key", Scope::Global)
+        .env("AWS_SECRET_ACCESS_KEY")
+        .secret(),
+    setting("profile", Scope::Env).env("ROOTS_PROFILE"),
+    setting("origin", Scope::Env).env("ROOTS_ORIGIN").default("cli"),
+    setting("global_path", Scope::Env).env("ROOTS_GLOBAL_PATH").default("~/.roots"),
+    setting("timing", Scope::Env)
+        .env("ROOTS_TIMING")
+        .default("0")
+        .kind(ValueKind::OneOf(&["0", "1"])),
+];
+
+/// Look up a known setting
+pub fn setting_spec(key: &str) -> Option<&'static SettingSpec> {
+    SETTINGS.iter().find(|s| s.key == key)
+}
+
+/// Check that `roots config` can write `value` to `key` in the project
+/// config, or the global one with `global`; returns the setting. `model`
+/// stands for `embedding_model`.
+pub fn validate_setting(key: &str, value: &str, global: bool) -> Result<&'static SettingSpec, String> {
+    let key = if key == "model" { "embedding_model" } else { key };
+    let spec = setting_spec(key).ok_or_else(|| match key.strip_prefix("ranking.") {
+        Some(_) => format!(
+            "Unknown ranking key '{}' (use similarity, confidence, recency, recency_half_life, access, or importance)",
+            key
+        ),
+        None => format!("Unknown setting '{}' (see 'roots config --list-keys')", key),
+    })?;
+    match (spec.scope, global) {
+        (Scope::Env, _) => {
+            return Err(format!("{} is only read from {}", key, spec.env_var()));
+        }
+        (Scope::Global, false) => {
+            return Err(format!("{} is a global setting: roots config --global {} {}", key, key, value));
+        }
+        // Project settings in the global config are defaults for every project
+        _ => {}
+    }
+    if spec.kind == ValueKind::Structured {
+        return Err(format!("{} is structured; edit it in .roots/_config.yaml", key));
+    }
+    spec.kind
+        .validate(value)
+        .map_err(|e| format!("Invalid {} '{}': {}", key, value, e))?;
+    Ok(spec)
+}
+
+/// The layer an effective value came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    Default,
+    Global,
+    Project,
+    Profile(String),
+    Env(String),
+}
+
+impl std::fmt::Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Source::Default => write!(f, "default"),
+            Source::Global => write!(f, "global config"),
+            Source::Project => write!(f, "project config"),
+            Source::Profile(name) => write!(f, "profile {}", name),
+            Source::Env(var) => write!(f, "env {}", var),
+        }
+    }
+}
+
+/// A variable of the process environment
+fn process_env(var: &str) -> Option<String> {
+    std::env::var(var).ok()
+}
+
+/// Value from the environment for a setting, if its variable is set
+fn env_value(spec: &SettingSpec) -> Option<(String, Source)> {
+    env_value_in(spec, process_env)
+}
+
+/// `env_value` with variables looked up by `env`
+fn env_value_in(spec: &SettingSpec, env: impl Fn(&str) -> Option<String>) -> Option<(String, Source)> {
+    let var = spec.env_var();
+    env(&var)
+        .filter(|v| !v.is_empty())
+        .map(|v| (v, Source::Env(var)))
+}
+
+/// Effective value of a setting and its layer, or `None` if unset everywhere.
+/// Flags of a command override all of these for that command.
+pub fn resolve_setting(
+    spec: &SettingSpec,
+    project: Option<&RootsConfig>,
+    global: &HashMap<String, String>,
+    profile: Option<&Profile>,
+) -> Option<(String, Source)> {
+    resolve_setting_in(process_env, spec, project, global, profile)
+}
+
+/// `resolve_setting` with environment variables looked up by `env`
+fn resolve_setting_in(
+    env: impl Fn(&str) -> Option<String>,
+    spec: &SettingSpec,
+    project: Option<&RootsConfig>,
+    global: &HashMap<String, String>,
+    profile: Option<&Profile>,
+) -> Option<(String, Source)> {
+    if let Some(found) = env_value_in(spec, env) {
+        return Some(found);
+    }
+    if let Some(profile) = profile.filter(|_| spec.scope != Scope::Env) {
+        if let Some(value) = profile.settings.get(spec.key) {
+            return Some((value.clone(), Source::Profile(profile.name.clone())));
+        }
+    }
+    let from_project = match spec.scope {
+        Scope::Project => project
+            .and_then(|p| p.display_value(spec.key))
+            .map(|v| (v, Source::Project)),
+        Scope::Global | Scope::Env => None,
+    };
+    let from_global = || match spec.scope {
+        Scope::Project | Scope::Global => global.get(spec.key).map(|v| (v.clone(), Source::Global)),
+        Scope::Env => None,
+    };
+    from_project
+        .or_else(from_global)
+        .or_else(|| spec.default.map(|d| (d.to_string(), Source::Default)))
+}
+
+/// A resolved value if it fits the setting's kind; otherwise a warning
+/// (once per process) and the setting's default
+fn checked(spec: &SettingSpec, value: String, source: &Source) -> Option<String> {
+    match spec.kind.validate(&value) {
+        Ok(()) => Some(value),
+        Err(e) => {
+            warn_once(format!(
+                "Ignoring {} '{}' from {}: {}; using {}",
+                spec.key,
+                value,
+                source,
+                e,
+                spec.default.unwrap_or("no value")
+            ));
+            spec.default.map(String::from)
+        }
+    }
+}
+
+fn warn_once(message: String) {
+    static WARNED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+    let mut warned = WARNED.lock().unwrap_or_else(|e| e.into_inner());
+    if !warned.contains(&message) {
+        eprintln!("Warning: {}", message);
+        warned.push(message);
+    }
+}
+
+/// Effective value of a global-scope setting (env, then profile, then global
+/// config, then default), checked as `RootsConfig::setting` checks it
+pub fn global_setting(key: &str) -> Option<String> {
+    let spec = setting_spec(key)?;
+    let profile = active_profile().ok().flatten();
+    let (value, source) = resolve_setting(spec, None, &get_global_config(), profile.as_ref())?;
+    checked(spec, value, &source)
+}
+
 /// Get model aliases lookup
 pub fn model_aliases() -> HashMap<&'static str, &'static ModelInfo> {
     SUGGESTED_MODELS.iter().map(|m| (m.alias, m)).collect()
@@ -101,70 +481,350 @@ pub fn resolve_model(model_input: &str) -> (String, String) {
     (model_input.to_string(), "sentence-transformers".to_string())
 }
 
+/// How long the server waits without requests before exiting
+/// (`server_idle_timeout`); `None` keeps it running
+pub fn server_idle_timeout() -> Option<chrono::Duration> {
+    global_setting("server_idle_timeout")
+        .and_then(|v| crate::dates::parse_duration(&v).ok())
+        .filter(|d| *d > chrono::Duration::zero())
+}
+
+/// Directory the server runs from, where `uv run` finds the roots Python
+/// package (`server_dir`); saved by `roots server start` and `install`
+pub fn server_dir() -> Option<PathBuf> {
+    global_setting("server_dir").map(PathBuf::from)
+}
+
+/// What the server runs its models on (`server_backend`)
+pub fn server_backend() -> String {
+    global_setting("server_backend").unwrap_or_else(|| SERVER_BACKENDS[0].to_string())
+}
+
+/// Whether a command that wants the server starts it when it is down
+/// (`server.autostart`)
+pub fn server_autostart() -> bool {
+    global_setting("server.autostart").is_some_and(|v| v == "true")
+}
+
+/// Whether hooks give up on the server at its first failure instead of
+/// retrying (`hooks.fail_fast`)
+pub fn hooks_fail_fast() -> bool {
+    global_setting("hooks.fail_fast").is_some_and(|v| v == "true")
+}
+
 // -----------------------------------------------------------------------------
-// Global config (for embedding server)
+// Base directories
 // -----------------------------------------------------------------------------
 
-fn global_config_dir() -> PathBuf {
-    dirs::home_dir()
-        .unwrap_or_else(|| PathBuf::from("."))
-        .join(".config")
-        .join("roots")
+/// Where versions before XDG support kept the global config on every platform
+fn legacy_config_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".config").join("roots"))
+}
+
+/// Global config: `$XDG_CONFIG_HOME/roots` (`~/.config/roots`), or the
+/// platform's config directory on macOS and Windows. A `~/.config/roots`
+/// from an older version is used until the new directory exists.
+pub fn global_config_dir() -> PathBuf {
+    let legacy = legacy_config_dir();
+    match dirs::config_dir().map(|dir| dir.join("roots")) {
+        Some(dir) if dir.exists() => dir,
+        Some(dir) => legacy.filter(|l| l.is_dir()).unwrap_or(dir),
+        None => legacy.unwrap_or_else(|| PathBuf::from(".config").join("roots")),
+    }
+}
+
+/// Data kept across runs, like the project registry: `$XDG_DATA_HOME/roots`
+/// (`~/.local/share/roots`), or the platform's data directory
+pub fn data_dir() -> PathBuf {
+    dirs::data_dir()
+        .map(|dir| dir.join("roots"))
+        .unwrap_or_else(global_config_dir)
+}
+
+/// Logs: `$XDG_STATE_HOME/roots` (`~/.local/state/roots`), or the data
+/// directory where the platform has no state directory
+pub fn state_dir() -> PathBuf {
+    dirs::state_dir().map(|dir| dir.join("roots")).unwrap_or_else(data_dir)
 }
 
-fn global_config_file() -> PathBuf {
+/// The embedding server's socket: `server_socket` (or `ROOTS_SOCKET`), by
+/// default `$XDG_RUNTIME_DIR/roots/embedder.sock`, which only the user can
+/// reach, or the state directory where there is no runtime directory
+pub fn server_socket() -> PathBuf {
+    if let Some(path) = global_setting("server_socket") {
+        return PathBuf::from(path);
+    }
+    dirs::runtime_dir()
+        .map(|dir| dir.join("roots"))
+        .unwrap_or_else(state_dir)
+        .join("embedder.sock")
+}
+
+// -----------------------------------------------------------------------------
+// Global config (for embedding server)
+// -----------------------------------------------------------------------------
+
+pub fn global_config_file() -> PathBuf {
     global_config_dir().join("config.yaml")
 }
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
-struct GlobalConfig {
-    #[serde(default)]
-    server_model: Option<String>,
+/// Section of the global config holding named profiles
+const PROFILES_SECTION: &str = "profiles";
+
+/// A named set of settings under `profiles:` in the global config, applied
+/// over the project and global config when selected
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Profile {
+    pub name: String,
+    pub settings: HashMap<String, String>,
 }
 
-/// Get global config
-pub fn get_global_config() -> HashMap<String, String> {
-    let path = global_config_file();
-    if path.exists() {
-        if let Ok(content) = fs::read_to_string(&path) {
-            if let Ok(config) = serde_yaml::from_str::<HashMap<String, String>>(&content) {
-                return config;
+fn read_global_file() -> serde_yaml::Mapping {
+    fs::read_to_string(global_config_file())
+        .ok()
+        .and_then(|content| serde_yaml::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn write_global_file(file: &serde_yaml::Mapping) -> std::io::Result<()> {
+    fs::create_dir_all(global_config_dir())?;
+    let content = serde_yaml::to_string(file).unwrap_or_default();
+    fs::write(global_config_file(), content)
+}
+
+/// A scalar YAML value as a string
+fn scalar(value: &serde_yaml::Value) -> Option<String> {
+    match value {
+        serde_yaml::Value::String(s) => Some(s.clone()),
+        serde_yaml::Value::Number(n) => Some(n.to_string()),
+        serde_yaml::Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// Scalars of a mapping by key; a nested `section:` gives `section.key` entries
+fn flatten(mapping: &serde_yaml::Mapping, prefix: &str, out: &mut HashMap<String, String>) {
+    for (key, value) in mapping {
+        let Some(key) = scalar(key) else { continue };
+        let key = format!("{}{}", prefix, key);
+        match value {
+            serde_yaml::Value::Mapping(nested) => flatten(nested, &format!("{}.", key), out),
+            _ => {
+                if let Some(value) = scalar(value) {
+                    out.insert(key, value);
+                }
             }
         }
     }
-    HashMap::new()
 }
 
-/// Set a global config value
+/// The mapping under `key`, made one if it is missing or something else
+fn section_mut<'a>(mapping: &'a mut serde_yaml::Mapping, key: &str) -> &'a mut serde_yaml::Mapping {
+    let entry = mapping
+        .entry(serde_yaml::Value::String(key.to_string()))
+        .or_insert_with(|| serde_yaml::Value::Mapping(Default::default()));
+    if !entry.is_mapping() {
+        *entry = serde_yaml::Value::Mapping(Default::default());
+    }
+    entry.as_mapping_mut().expect("entry was just made a mapping")
+}
+
+/// Where global settings are written: the selected profile, if any
+fn writable_section(file: &mut serde_yaml::Mapping) -> &mut serde_yaml::Mapping {
+    match profile_name() {
+        Some(name) => section_mut(section_mut(file, PROFILES_SECTION), &name),
+        None => file,
+    }
+}
+
+/// Get global config, without its profiles
+pub fn get_global_config() -> HashMap<String, String> {
+    let mut file = read_global_file();
+    file.remove(PROFILES_SECTION);
+    let mut config = HashMap::new();
+    flatten(&file, "", &mut config);
+    config
+}
+
+/// Set a global config value, in the selected profile if there is one
 pub fn set_global_config(key: &str, value: &str) -> std::io::Result<()> {
-    let dir = global_config_dir();
-    fs::create_dir_all(&dir)?;
+    let mut file = read_global_file();
+    writable_section(&mut file).insert(key.into(), value.into());
+    write_global_file(&file)
+}
 
-    let mut config = get_global_config();
-    config.insert(key.to_string(), value.to_string());
+/// Remove a global config value, from the selected profile if there is one;
+/// returns whether it was set
+pub fn unset_global_config(key: &str) -> std::io::Result<bool> {
+    let mut file = read_global_file();
+    if writable_section(&mut file).remove(key).is_none() {
+        return Ok(false);
+    }
+    write_global_file(&file)?;
+    Ok(true)
+}
 
-    let content = serde_yaml::to_string(&config).unwrap_or_default();
-    fs::write(global_config_file(), content)
+/// Name of the selected profile (`--profile` or `ROOTS_PROFILE`)
+pub fn profile_name() -> Option<String> {
+    std::env::var("ROOTS_PROFILE").ok().filter(|v| !v.is_empty())
+}
+
+/// Every profile in the global config, by name
+pub fn profiles() -> BTreeMap<String, HashMap<String, String>> {
+    let file = read_global_file();
+    let Some(section) = file.get(PROFILES_SECTION).and_then(|v| v.as_mapping()) else {
+        return BTreeMap::new();
+    };
+    section
+        .iter()
+        .filter_map(|(name, settings)| {
+            let mut flat = HashMap::new();
+            if let Some(settings) = settings.as_mapping() {
+                flatten(settings, "", &mut flat);
+            }
+            Some((scalar(name)?, flat))
+        })
+        .collect()
+}
+
+/// The selected profile; an error if the global config doesn't define it
+pub fn active_profile() -> Result<Option<Profile>, String> {
+    let Some(name) = profile_name() else {
+        return Ok(None);
+    };
+    let mut all = profiles();
+    match all.remove(&name) {
+        Some(settings) => Ok(Some(Profile { name, settings })),
+        None if all.is_empty() => Err(format!(
+            "Unknown profile '{}': none are defined; create it with \
+             'roots --profile {} config --global <key> <value>'",
+            name, name
+        )),
+        None => Err(format!(
+            "Unknown profile '{}' (profiles: {})",
+            name,
+            all.into_keys().collect::<Vec<_>>().join(", ")
+        )),
+    }
+}
+
+const PROJECTS_FILE: &str = "projects.yaml";
+
+/// Project `.roots` directories registered by `roots init`, for cross-project search
+pub fn registered_projects() -> Vec<PathBuf> {
+    // Older versions kept the registry with the config
+    let file = data_dir().join(PROJECTS_FILE);
+    let file = if file.exists() { file } else { global_config_dir().join(PROJECTS_FILE) };
+    fs::read_to_string(file)
+        .ok()
+        .and_then(|content| serde_yaml::from_str::<Vec<PathBuf>>(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Add a `.roots` directory to the project registry, dropping entries that no longer exist
+pub fn register_project(roots_path: &Path) -> std::io::Result<()> {
+    let roots_path = roots_path.canonicalize()?;
+    let mut projects: Vec<PathBuf> = registered_projects()
+        .into_iter()
+        .filter(|p| p.is_dir() && *p != roots_path)
+        .collect();
+    projects.push(roots_path);
+
+    let dir = data_dir();
+    fs::create_dir_all(&dir)?;
+    let content = serde_yaml::to_string(&projects).unwrap_or_default();
+    fs::write(dir.join(PROJECTS_FILE), content)?;
+    let legacy = global_config_dir().join(PROJECTS_FILE);
+    if legacy != dir.join(PROJECTS_FILE) && legacy.exists() {
+        fs::remove_file(legacy)?;
+    }
+    Ok(())
 }
 
 /// Get the model configured for the embedding server
 pub fn get_server_model() -> (String, String) {
-    let config = get_global_config();
-    let model = config
-        .get("server_model")
-        .cloned()
-        .unwrap_or_else(|| DEFAULT_MODEL.to_string());
+    let model = global_setting("server_model").unwrap_or_else(|| DEFAULT_MODEL.to_string());
     resolve_model(&model)
 }
 
+/// Every model the server loads: `server_model` first, then
+/// `server_extra_models` (`roots server model --add`), by full name
+pub fn get_server_models() -> Vec<String> {
+    let mut models = vec![get_server_model().0];
+    for extra in global_setting("server_extra_models").unwrap_or_default().split(',') {
+        let (name, model_type) = resolve_model(extra.trim());
+        if !extra.trim().is_empty() && model_type != "lite" && !models.contains(&name) {
+            models.push(name);
+        }
+    }
+    models
+}
+
 // -----------------------------------------------------------------------------
 // Per-project config
 // -----------------------------------------------------------------------------
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
-struct ProjectConfigFile {
+/// Default similarity a memory needs to a rule's seed description
+const DEFAULT_ROUTING_THRESHOLD: f64 = 0.5;
+
+/// A rule assigning incoming memories to a namespace (`routing:` in config).
+///
+/// A rule matches when any of its tags is on the memory, its source contains
+/// any of its path fragments, or the memory is similar enough to its seed
+/// description. Rules are tried in order; the first match wins.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RoutingRule {
+    pub namespace: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
     #[serde(default)]
-    embedding_model: Option<String>,
+    pub paths: Vec<String>,
+    #[serde(default)]
+    pub seed: Option<String>,
+    #[serde(default)]
+    pub threshold: Option<f64>,
+}
+
+impl RoutingRule {
+    /// Whether the rule matches on tags or source path alone
+    pub fn matches_metadata(&self, tags: &[String], source: Option<&str>) -> bool {
+        let tag_match = self
+            .tags
+            .iter()
+            .any(|t| tags.iter().any(|m| m.eq_ignore_ascii_case(t)));
+        let path_match = source.is_some_and(|src| self.paths.iter().any(|p| src.contains(p.as_str())));
+        tag_match || path_match
+    }
+
+    /// Minimum seed similarity for this rule
+    pub fn threshold(&self) -> f64 {
+        self.threshold.unwrap_or(DEFAULT_ROUTING_THRESHOLD)
+    }
+}
+
+/// Weights of the blended recall score (`ranking:` in config). The score is
+/// the weighted sum of cosine similarity, confidence, recency of the last
+/// update (halving every `recency_half_life`), the access boost, and
+/// importance; the defaults rank by similarity with small nudges.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ranking {
+    pub similarity: f64,
+    pub confidence: f64,
+    pub recency: f64,
+    pub recency_half_life: chrono::Duration,
+    pub access: f64,
+    pub importance: f64,
+}
+
+impl Ranking {
+    /// Weighted sum of the signals
+    pub fn score(&self, parts: &crate::types::ScoreParts) -> f64 {
+        self.similarity * parts.similarity
+            + self.confidence * parts.confidence
+            + self.recency * parts.recency
+            + self.access * parts.access
+            + self.importance * parts.importance
+    }
 }
 
 /// Configuration manager for a .roots directory
@@ -172,6 +832,10 @@ pub struct RootsConfig {
     roots_path: PathBuf,
     config_file: PathBuf,
     config: HashMap<String, serde_yaml::Value>,
+    /// The global config, under the project config
+    global: HashMap<String, String>,
+    /// The selected profile, over the project config
+    profile: Option<Profile>,
 }
 
 impl RootsConfig {
@@ -181,6 +845,8 @@ impl RootsConfig {
             roots_path,
             config_file,
             config: HashMap::new(),
+            global: get_global_config(),
+            profile: active_profile().ok().flatten(),
         };
         instance.load();
         instance
@@ -204,24 +870,147 @@ impl RootsConfig {
         fs::write(&self.config_file, content)
     }
 
+    /// A value by key; `section.key` also finds `key` nested under `section:`
+    fn value(&self, key: &str) -> Option<&serde_yaml::Value> {
+        self.config.get(key).or_else(|| {
+            let (section, key) = key.split_once('.')?;
+            self.config.get(section)?.as_mapping()?.get(key)
+        })
+    }
+
+    /// Effective value of a key: for a known setting its env variable wins,
+    /// then the selected profile, and a project setting missing from the
+    /// project config falls back to the global config
     pub fn get(&self, key: &str) -> Option<String> {
-        self.config.get(key).and_then(|v| match v {
-            serde_yaml::Value::String(s) => Some(s.clone()),
-            serde_yaml::Value::Number(n) => Some(n.to_string()),
-            serde_yaml::Value::Bool(b) => Some(b.to_string()),
-            _ => None,
+        let spec = setting_spec(key);
+        if let Some((value, _)) = spec.and_then(env_value) {
+            return Some(value);
+        }
+        if let Some(value) = self.profile_value(key) {
+            return Some(value);
+        }
+        self.file_value(key).or_else(|| {
+            spec.filter(|s| s.scope == Scope::Project)
+                .and_then(|_| self.global.get(key).cloned())
         })
     }
 
+    /// A scalar value from the project config file alone
+    fn file_value(&self, key: &str) -> Option<String> {
+        self.value(key).and_then(scalar)
+    }
+
+    fn profile_value(&self, key: &str) -> Option<String> {
+        self.profile.as_ref()?.settings.get(key).cloned()
+    }
+
+    /// Get a list value, from a YAML sequence or a comma-separated string
+    pub fn get_list(&self, key: &str) -> Vec<String> {
+        let overridden =
+            setting_spec(key).and_then(env_value).is_some() || self.profile_value(key).is_some();
+        match self.value(key) {
+            Some(serde_yaml::Value::Sequence(items)) if !overridden => items
+                .iter()
+                .filter_map(|v| v.as_str().map(|s| s.trim().to_string()))
+                .filter(|s| !s.is_empty())
+                .collect(),
+            _ => self
+                .get(key)
+                .map(|s| {
+                    s.split(',')
+                        .map(|s| s.trim().to_string())
+                        .filter(|s| !s.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+
+    /// A project config value of any shape, flattened for display
+    pub fn display_value(&self, key: &str) -> Option<String> {
+        match self.value(key)? {
+            serde_yaml::Value::Sequence(items) if key == "routing" => {
+                Some(format!("{} rules", items.len()))
+            }
+            serde_yaml::Value::Sequence(_) => Some(self.get_list(key).join(", ")),
+            serde_yaml::Value::Null => None,
+            serde_yaml::Value::Mapping(_) | serde_yaml::Value::Tagged(_) => {
+                Some("(structured)".to_string())
+            }
+            _ => self.file_value(key),
+        }
+    }
+
+    /// Keys in the project config file
+    pub fn keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = self.config.keys().cloned().collect();
+        keys.sort();
+        keys
+    }
+
+    /// Set a value; `section.key` is saved nested under `section:`
     pub fn set(&mut self, key: &str, value: &str) -> std::io::Result<()> {
-        self.config
-            .insert(key.to_string(), serde_yaml::Value::String(value.to_string()));
+        let value = serde_yaml::Value::String(value.to_string());
+        match key.split_once('.') {
+            Some((section, nested)) => {
+                self.config.remove(key);
+                let entry = self
+                    .config
+                    .entry(section.to_string())
+                    .or_insert_with(|| serde_yaml::Value::Mapping(Default::default()));
+                if !entry.is_mapping() {
+                    *entry = serde_yaml::Value::Mapping(Default::default());
+                }
+                if let Some(mapping) = entry.as_mapping_mut() {
+                    mapping.insert(serde_yaml::Value::String(nested.to_string()), value);
+                }
+            }
+            None => {
+                self.config.insert(key.to_string(), value);
+            }
+        }
         self.save()
     }
 
+    /// Remove a value, and its section if that leaves it empty; returns
+    /// whether it was set
+    pub fn unset(&mut self, key: &str) -> std::io::Result<bool> {
+        let mut removed = self.config.remove(key).is_some();
+        if let Some((section, nested)) = key.split_once('.') {
+            if let Some(mapping) = self.config.get_mut(section).and_then(|v| v.as_mapping_mut()) {
+                removed |= mapping.remove(nested).is_some();
+                if mapping.is_empty() {
+                    self.config.remove(section);
+                }
+            }
+        }
+        if removed {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+
+    /// Effective value of a known setting, or its default. A value that
+    /// doesn't fit the setting's kind, as `roots config` would refuse it,
+    /// is warned about and the default used instead.
+    fn setting(&self, key: &str) -> Option<String> {
+        let spec = setting_spec(key)?;
+        let (value, source) = resolve_setting(spec, Some(self), &self.global, self.profile.as_ref())?;
+        checked(spec, value, &source)
+    }
+
+    /// A numeric setting
+    fn number<T: std::str::FromStr + Default>(&self, key: &str) -> T {
+        self.setting(key).and_then(|v| v.parse().ok()).unwrap_or_default()
+    }
+
+    /// A true|false setting
+    fn flag(&self, key: &str) -> bool {
+        self.setting(key).is_some_and(|v| v == "true")
+    }
+
     pub fn embedding_model(&self) -> String {
-        self.get("embedding_model")
-            .unwrap_or_else(|| DEFAULT_MODEL.to_string())
+        self.setting("embedding_model").unwrap_or_default()
     }
 
     pub fn set_embedding_model(&mut self, value: &str) -> std::io::Result<()> {
@@ -231,11 +1020,192 @@ impl RootsConfig {
     pub fn get_resolved_model(&self) -> (String, String) {
         resolve_model(&self.embedding_model())
     }
+
+    /// Namespace routing rules, in evaluation order
+    pub fn routing_rules(&self) -> Result<Vec<RoutingRule>, String> {
+        match self.config.get("routing") {
+            Some(value) => serde_yaml::from_value(value.clone())
+                .map_err(|e| format!("Invalid routing rules in {}: {}", self.config_file.display(), e)),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Whether the append-only change feed is enabled
+    pub fn changelog(&self) -> bool {
+        self.flag("changelog")
+    }
+
+    /// Recall time budget in milliseconds (0 disables slow-query hints)
+    pub fn slow_query_ms(&self) -> u64 {
+        self.number("slow_query_ms")
+    }
+
+    /// A day count setting as a duration. Unlike other settings, a value
+    /// `roots config` would refuse is an error rather than a warning.
+    fn days(&self, key: &str) -> Result<chrono::Duration, String> {
+        let spec = setting_spec(key).ok_or_else(|| format!("Unknown setting '{}'", key))?;
+        let (value, source) = resolve_setting(spec, Some(self), &self.global, self.profile.as_ref())
+            .unwrap_or_else(|| ("0".to_string(), Source::Default));
+        spec.kind
+            .validate(&value)
+            .map_err(|e| format!("Invalid {} '{}' from {}: {}", key, value, source, e))?;
+        value
+            .parse()
+            .ok()
+            .and_then(chrono::TimeDelta::try_days)
+            .ok_or_else(|| format!("Invalid {} '{}' from {}: out of range", key, value, source))
+    }
+
+    /// How long to keep tombstones of forgotten memories
+    pub fn tombstone_retention_days(&self) -> Result<chrono::Duration, String> {
+        self.days("tombstone_retention_days")
+    }
+
+    /// Storage backend, one of `STORE_BACKENDS` (default: sqlite)
+    pub fn store_backend(&self) -> String {
+        self.setting("store").unwrap_or_default()
+    }
+
+    /// Server URL for the libsql backend (libsql://, https://, or http://)
+    pub fn libsql_url(&self) -> Option<String> {
+        self.setting("libsql_url")
+    }
+
+    /// Auth token for the libsql backend; `ROOTS_LIBSQL_AUTH_TOKEN` wins so
+    /// the token can stay out of a committed config
+    pub fn libsql_auth_token(&self) -> Option<String> {
+        self.setting("libsql_auth_token")
+    }
+
+    /// How recently a memory must be updated or accessed to be injected by
+    /// hooks (e.g. "180d"); unset means no limit
+    pub fn context_max_age(&self) -> Option<chrono::Duration> {
+        self.setting("context_max_age")
+            .and_then(|v| crate::dates::parse_duration(&v).ok())
+    }
+
+    /// Score a memory needs to be injected by `context` without `--threshold`
+    pub fn context_threshold(&self) -> f64 {
+        self.number("context_threshold")
+    }
+
+    /// Token budget for hook-injected context; unset or 0 means none
+    pub fn context_max_tokens(&self) -> Option<usize> {
+        Some(self.number("context_max_tokens")).filter(|n| *n > 0)
+    }
+
+    /// How far back `prime` lists new and updated memories; 0 turns the
+    /// section off
+    pub fn prime_recent_days(&self) -> Result<Option<chrono::Duration>, String> {
+        let recent = self.days("prime_recent_days")?;
+        Ok(Some(recent).filter(|d| *d > chrono::Duration::zero()))
+    }
+
+    /// Body size in bytes above which only a summary is kept in the
+    /// database (`None` when set to 0 or the backend is not sqlite)
+    pub fn blob_threshold(&self) -> Option<usize> {
+        if self.store_backend() != "sqlite" {
+            return None;
+        }
+        Some(self.number("blob_threshold")).filter(|t| *t > 0)
+    }
+
+    /// Whether `remember` queues embedding work instead of waiting for it
+    pub fn defer_embeddings(&self) -> bool {
+        self.flag("defer_embeddings")
+    }
+
+    /// Worthiness score (0-1) `remember --auto` needs to store a memory
+    pub fn capture_threshold(&self) -> f64 {
+        self.number("capture_threshold")
+    }
+
+    /// Shell command `capture` pipes a transcript to instead of extracting
+    /// candidates by rule; it prints one candidate memory per line
+    pub fn capture_command(&self) -> Option<String> {
+        self.setting("capture_command").filter(|c| !c.trim().is_empty())
+    }
+
+    /// Regex a commit message must match for the git hook to remember it;
+    /// unset means every commit
+    pub fn commit_pattern(&self) -> Option<String> {
+        self.setting("commit_pattern").filter(|p| !p.trim().is_empty())
+    }
+
+    /// Whether recall and context rerank candidates with the server's
+    /// cross-encoder without being asked
+    pub fn rerank(&self) -> bool {
+        self.flag("rerank")
+    }
+
+    /// Whether `context` expands short prompts without being asked
+    pub fn expand_queries(&self) -> bool {
+        self.flag("expand_queries")
+    }
+
+    /// What `remember` does about missing tags: "off", "suggest", or
+    /// "apply" (see `autotag`)
+    pub fn auto_tag(&self) -> String {
+        self.setting("auto_tag").unwrap_or_default()
+    }
+
+    /// What is logged of each recall and context query: "off", "hash", or
+    /// "text" (see `query_log`)
+    pub fn query_log(&self) -> String {
+        self.setting("query_log").unwrap_or_default()
+    }
+
+    /// How many embedding matches a rerank considers
+    pub fn rerank_candidates(&self) -> usize {
+        self.number("rerank_candidates")
+    }
+
+    /// Cross-encoder `recall --rerank` asks the server for
+    pub fn rerank_model(&self) -> String {
+        self.setting("rerank_model").unwrap_or_default()
+    }
+
+    /// Cross-encoder hook `context` reranks with, so hooks can use a
+    /// smaller, faster one than recall; `rerank_model` when unset
+    pub fn context_rerank_model(&self) -> String {
+        self.setting("context_rerank_model")
+            .filter(|m| !m.trim().is_empty())
+            .unwrap_or_else(|| self.rerank_model())
+    }
+
+    /// Recall score weights (`ranking.*`)
+    pub fn ranking(&self) -> Ranking {
+        Ranking {
+            similarity: self.number("ranking.similarity"),
+            confidence: self.number("ranking.confidence"),
+            recency: self.number("ranking.recency"),
+            recency_half_life: self
+                .setting("ranking.recency_half_life")
+                .and_then(|v| crate::dates::parse_duration(&v).ok())
+                .unwrap_or_default(),
+            access: self.number("ranking.access"),
+            importance: self.number("ranking.importance"),
+        }
+    }
+}
+
+/// The user-global store, `~/.roots` (or `ROOTS_GLOBAL_PATH`)
+pub fn global_roots_path() -> Option<PathBuf> {
+    if let Ok(path) = std::env::var("ROOTS_GLOBAL_PATH") {
+        return Some(PathBuf::from(path));
+    }
+    dirs::home_dir().map(|home| home.join(".roots"))
 }
 
 /// Find the .roots directory, searching upward from current directory
 pub fn find_roots_path() -> Option<PathBuf> {
-    let mut current = std::env::current_dir().ok()?;
+    find_roots_path_from(&std::env::current_dir().ok()?)
+}
+
+/// The `.roots` directory in `dir` or the nearest parent, or else at
+/// `ROOTS_PATH`
+pub fn find_roots_path_from(dir: &Path) -> Option<PathBuf> {
+    let mut current = dir.to_path_buf();
 
     loop {
         let roots = current.join(".roots");
@@ -258,3 +1228,230 @@ pub fn find_roots_path() -> Option<PathBuf> {
 
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_routing_rule_metadata_match() {
+        let rules: Vec<RoutingRule> = serde_yaml::from_str(
+            "- namespace: infra\n  paths: [deploy/]\n- namespace: trading\n  tags: [Funding]\n",
+        )
+        .unwrap();
+
+        assert!(rules[0].matches_metadata(&[], Some("deploy/k8s.yaml:10")));
+        assert!(!rules[0].matches_metadata(&[], None));
+        assert!(rules[1].matches_metadata(&["funding".to_string()], None));
+        assert_eq!(rules[1].threshold(), DEFAULT_ROUTING_THRESHOLD);
+    }
+
+    #[test]
+    fn test_defaults_come_from_settings() {
+        for spec in SETTINGS.iter().filter(|s| s.kind != ValueKind::Structured) {
+            if let Some(default) = spec.default {
+                assert!(spec.kind.validate(default).is_ok(), "{}", spec.key);
+            }
+        }
+        assert_eq!(setting_spec("auto_tag").unwrap().default, Some(crate::autotag::AUTO_TAG_MODES[0]));
+        assert_eq!(setting_spec("query_log").unwrap().default, Some(crate::query_log::QUERY_LOG_MODES[0]));
+
+        let mut config = RootsConfig::new(PathBuf::from("/nonexistent/.roots"));
+        config.global.clear();
+        config.profile = None;
+        assert_eq!(config.slow_query_ms(), 200);
+        assert_eq!(config.tombstone_retention_days(), Ok(chrono::Duration::days(90)));
+        assert_eq!(config.prime_recent_days(), Ok(Some(chrono::Duration::days(7))));
+        assert_eq!(config.blob_threshold(), Some(16384));
+        assert_eq!(config.capture_threshold(), 0.5);
+        assert_eq!(config.rerank_candidates(), 20);
+        assert_eq!(config.context_max_tokens(), None);
+        assert_eq!(config.auto_tag(), "off");
+        assert!(!config.rerank());
+        let spec = setting_spec("store").unwrap();
+        assert_eq!(
+            resolve_setting(spec, Some(&config), &HashMap::new(), None),
+            Some((config.store_backend(), Source::Default))
+        );
+    }
+
+    #[test]
+    fn test_getters_follow_precedence_and_skip_invalid_values() {
+        let dir = std::env::temp_dir().join(format!("roots-precedence-{}", std::process::id()));
+        let mut config = RootsConfig::new(dir.clone());
+        config.set("slow_query_ms", "150").unwrap();
+        config.set("rerank_candidates", "lots").unwrap();
+        config.set("ranking.similarity", "-5").unwrap();
+        config.set("changelog", "yes").unwrap();
+
+        let mut config = RootsConfig::new(dir.clone());
+        config.global = HashMap::from([
+            ("slow_query_ms".to_string(), "300".to_string()),
+            ("blob_threshold".to_string(), "1024".to_string()),
+        ]);
+        config.profile = None;
+        // Project over global, global over the default
+        assert_eq!(config.slow_query_ms(), 150);
+        assert_eq!(config.blob_threshold(), Some(1024));
+        // Invalid values fall back to the default, not to zero
+        assert_eq!(config.rerank_candidates(), 20);
+        assert_eq!(config.ranking().similarity, 1.0);
+        assert!(!config.changelog());
+
+        // A profile wins over both
+        config.profile = Some(Profile {
+            name: "fast".to_string(),
+            settings: HashMap::from([("slow_query_ms".to_string(), "50".to_string())]),
+        });
+        assert_eq!(config.slow_query_ms(), 50);
+
+        // And the environment over everything, checked the same way
+        let spec = setting_spec("slow_query_ms").unwrap();
+        let resolve = |value: &str| {
+            let env = HashMap::from([("ROOTS_SLOW_QUERY_MS".to_string(), value.to_string())]);
+            let env = |var: &str| env.get(var).cloned();
+            let (value, source) =
+                resolve_setting_in(env, spec, Some(&config), &config.global, config.profile.as_ref()).unwrap();
+            (checked(spec, value, &source), source)
+        };
+        assert_eq!(resolve("80"), (Some("80".to_string()), Source::Env("ROOTS_SLOW_QUERY_MS".to_string())));
+        assert_eq!(resolve("abc").0, spec.default.map(String::from));
+        // An empty variable counts as unset
+        assert_eq!(resolve("").0, Some("50".to_string()));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_rerank_model_per_operation() {
+        let dir = std::env::temp_dir().join(format!("roots-rerank-model-{}", std::process::id()));
+        let mut config = RootsConfig::new(dir.clone());
+        config.global.clear();
+        config.profile = None;
+        assert_eq!(config.rerank_model(), DEFAULT_RERANK_MODEL);
+        assert_eq!(config.context_rerank_model(), DEFAULT_RERANK_MODEL);
+
+        // Hooks follow recall until given their own, which recall ignores
+        config.global = HashMap::from([("rerank_model".to_string(), "global-reranker".to_string())]);
+        assert_eq!(config.context_rerank_model(), "global-reranker");
+        config.set("rerank_model", "big-reranker").unwrap();
+        assert_eq!(config.context_rerank_model(), "big-reranker");
+        config.set("context_rerank_model", "small-reranker").unwrap();
+        assert_eq!(config.rerank_model(), "big-reranker");
+        assert_eq!(config.context_rerank_model(), "small-reranker");
+
+        assert!(validate_setting("context_rerank_model", "x", false).is_ok());
+        assert!(validate_setting("rerank_model", "x", true).is_ok());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_profile_applies_over_project_and_global() {
+        let file: serde_yaml::Mapping =
+            serde_yaml::from_str("offline:\n  embedding_model: lite\n  ranking:\n    recency: 0.3\n").unwrap();
+        let mut settings = HashMap::new();
+        flatten(file["offline"].as_mapping().unwrap(), "", &mut settings);
+        assert_eq!(settings["ranking.recency"], "0.3");
+        let profile = Profile { name: "offline".to_string(), settings };
+
+        let config = RootsConfig::new(PathBuf::from("/nonexistent/.roots"));
+        let global = HashMap::from([
+            ("embedding_model".to_string(), "minilm".to_string()),
+            ("slow_query_ms".to_string(), "300".to_string()),
+        ]);
+        let resolve = |key| resolve_setting(setting_spec(key).unwrap(), Some(&config), &global, Some(&profile));
+        assert_eq!(
+            resolve("embedding_model"),
+            Some(("lite".to_string(), Source::Profile("offline".to_string())))
+        );
+        assert_eq!(resolve("slow_query_ms"), Some(("300".to_string(), Source::Global)));
+        assert_eq!(resolve("rerank"), Some(("false".to_string(), Source::Default)));
+    }
+
+    #[test]
+    fn test_ranking_defaults_and_nested_keys() {
+        let dir = std::env::temp_dir().join(format!("roots-ranking-{}", std::process::id()));
+        let mut config = RootsConfig::new(dir.clone());
+        for spec in SETTINGS.iter().filter(|s| s.key.starts_with("ranking.")) {
+            let value = spec.default.unwrap();
+            validate_setting(spec.key, value, false).unwrap();
+            config.set(spec.key, value).unwrap();
+        }
+
+        // Saved as a `ranking:` section and read back through dotted keys
+        let config = RootsConfig::new(dir.clone());
+        assert_eq!(config.keys(), vec!["ranking"]);
+        assert_eq!(
+            config.ranking(),
+            Ranking {
+                similarity: 1.0,
+                confidence: 0.0,
+                recency: 0.0,
+                recency_half_life: chrono::Duration::days(30),
+                access: 0.05,
+                importance: 0.05,
+            }
+        );
+
+        let parts = crate::types::ScoreParts {
+            similarity: 0.6,
+            access: 1.0,
+            importance: 0.2,
+            ..Default::default()
+        };
+        assert!((config.ranking().score(&parts) - 0.66).abs() < 1e-9);
+
+        assert!(validate_setting("ranking.recency", "-1", false).is_err());
+        assert!(validate_setting("ranking.recency_half_life", "soon", false).is_err());
+        assert!(validate_setting("ranking.freshness", "1", false).is_err());
+
+        // Unsetting the last ranking key drops the section
+        let mut config = RootsConfig::new(dir.clone());
+        for spec in SETTINGS.iter().filter(|s| s.key.starts_with("ranking.")) {
+            assert!(config.unset(spec.key).unwrap());
+        }
+        assert!(!config.unset("ranking.access").unwrap());
+        assert!(RootsConfig::new(dir.clone()).keys().is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_validate_setting_checks_key_scope_and_value() {
+        assert!(validate_setting("slow_query_ms", "150", false).is_ok());
+        assert!(validate_setting("model", "bge-base", false).is_ok());
+        assert!(validate_setting("capture_threshold", "0.7", false).is_ok());
+
+        let err = |key, value, global| validate_setting(key, value, global).err().unwrap();
+        assert!(err("slow_query_ms", "fast", false).contains("integer"));
+        assert!(err("capture_threshold", "2", false).contains("0-1"));
+        assert!(err("changelog", "yes", false).contains("true|false"));
+        assert!(err("auto_tag", "always", false).contains("off|suggest|apply"));
+        assert!(err("commit_pattern", "(", false).contains("commit_pattern"));
+        assert!(err("no_such_key", "1", false).contains("--list-keys"));
+        assert!(err("server_model", "bge-base", false).contains("--global"));
+        // Project settings can be set globally, as defaults for every project
+        assert!(validate_setting("rerank", "true", true).is_ok());
+        assert!(err("timing", "1", false).contains("ROOTS_TIMING"));
+        assert!(err("routing", "x", false).contains("_config.yaml"));
+        assert!(err("tombstone_retention_days", "99999999999999", false).contains("days 0-36500"));
+        assert!(err("prime_recent_days", "-1", false).contains("days"));
+        assert!(err("context_max_age", "9999999d", false).contains("up to"));
+    }
+
+    #[test]
+    fn test_out_of_range_days_are_a_config_error() {
+        let dir = std::env::temp_dir().join(format!("roots-days-{}", std::process::id()));
+        let mut config = RootsConfig::new(dir.clone());
+        assert_eq!(
+            config.tombstone_retention_days(),
+            Ok(chrono::Duration::days(90))
+        );
+
+        // Written by hand, bypassing `roots config` validation
+        config.set("tombstone_retention_days", "99999999999999").unwrap();
+        config.set("prime_recent_days", "0").unwrap();
+        let config = RootsConfig::new(dir.clone());
+        assert!(config.tombstone_retention_days().unwrap_err().contains("tombstone_retention_days"));
+        assert_eq!(config.prime_recent_days(), Ok(None));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,115 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeDelta, Utc};
+
+/// Parse a relative duration like `30d`, `2w`, `12h`, or `45m`
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| format!("Missing unit in duration: {} (use m, h, d, w, or y)", input))?;
+    let (number, unit) = input.split_at(split);
+
+    let n: i64 = number
+        .parse()
+        .map_err(|_| format!("Invalid duration: {}", input))?;
+
+    let duration = match unit {
+        "m" | "min" => TimeDelta::try_minutes(n),
+        "h" => TimeDelta::try_hours(n),
+        "d" => TimeDelta::try_days(n),
+        "w" => TimeDelta::try_weeks(n),
+        "y" => n.checked_mul(365).and_then(TimeDelta::try_days),
+        _ => return Err(format!("Unknown duration unit: {} (use m, h, d, w, or y)", unit)),
+    };
+    duration.ok_or_else(|| format!("Duration out of range: {}", input))
+}
+
+/// Current time as an RFC 3339 string, matching stored timestamps
+pub fn now_rfc3339() -> String {
+    Utc::now().to_rfc3339()
+}
+
+/// Timestamp `duration` from now, as an RFC 3339 string. Fails past the
+/// four-digit years SQLite's date functions understand
+pub fn from_now(duration: Duration) -> Result<String, String> {
+    Utc::now()
+        .checked_add_signed(duration)
+        .filter(|t| (1..=9999).contains(&t.year()))
+        .map(|t| t.to_rfc3339())
+        .ok_or_else(|| "Time out of range (must fall within years 1 to 9999)".to_string())
+}
+
+/// Date part (YYYY-MM-DD) of a stored timestamp
+pub fn date_part(timestamp: &str) -> &str {
+    timestamp.get(..10).unwrap_or(timestamp)
+}
+
+/// Parse a point in time as an RFC 3339 string: a date (`2024`, `2024-06`,
+/// `2024-06-15`, meaning its start in UTC), a full RFC 3339 timestamp, or
+/// a duration ago (`3d`, `2w`)
+pub fn parse_point(input: &str) -> Result<String, String> {
+    let input = input.trim();
+    if let Some(t) = parse_timestamp(input) {
+        return Ok(t.to_rfc3339());
+    }
+    let padded = match input.len() {
+        4 => format!("{}-01-01", input),
+        7 => format!("{}-01", input),
+        _ => input.to_string(),
+    };
+    if let Ok(date) = NaiveDate::parse_from_str(&padded, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc().to_rfc3339());
+    }
+    match parse_duration(input) {
+        Ok(d) => from_now(-d),
+        Err(_) => Err(format!("Invalid date: {} (use 2024-06-15, 2024-06, 2024, or a duration like 2w)", input)),
+    }
+}
+
+/// Parse a stored RFC 3339 timestamp
+pub fn parse_timestamp(timestamp: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(timestamp)
+        .ok()
+        .map(|t| t.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("30d").unwrap(), Duration::days(30));
+        assert_eq!(parse_duration("2w").unwrap(), Duration::weeks(2));
+        assert_eq!(parse_duration("12h").unwrap(), Duration::hours(12));
+        assert!(parse_duration("30").is_err());
+        assert!(parse_duration("3x").is_err());
+        assert!(parse_duration("d").is_err());
+    }
+
+    #[test]
+    fn test_out_of_range_is_an_error() {
+        assert!(parse_duration("99999999999999d").is_err());
+        assert!(parse_duration("99999999999999w").is_err());
+        assert!(parse_duration("9999999999999999y").is_err());
+        assert!(parse_duration("99999999999999999999d").is_err());
+
+        // Representable, but lands past year 9999
+        let far = parse_duration("9999999d").unwrap();
+        assert!(from_now(far).is_err());
+        assert!(from_now(-far).is_err());
+        assert!(parse_point("99999999999999w").is_err());
+        assert!(parse_point("9999999d").is_err());
+
+        assert!(from_now(parse_duration("100y").unwrap()).unwrap() > now_rfc3339());
+    }
+
+    #[test]
+    fn test_parse_point() {
+        assert_eq!(parse_point("2024-06").unwrap(), "2024-06-01T00:00:00+00:00");
+        assert_eq!(parse_point("2024").unwrap(), "2024-01-01T00:00:00+00:00");
+        assert_eq!(parse_point("2024-06-15").unwrap(), "2024-06-15T00:00:00+00:00");
+        assert!(parse_point("3d").unwrap() < now_rfc3339());
+        assert!(parse_point("2024-13").is_err());
+        assert!(parse_point("soon").is_err());
+    }
+}
@@ -0,0 +1,105 @@
+//! Memories written in `$EDITOR` by `remember --edit`: YAML front-matter for
+//! tags, confidence, and kind, then the content.
+
+use serde_yaml::Value;
+
+/// A memory as written in the editor
+#[derive(Debug, Clone, PartialEq)]
+pub struct Draft {
+    pub content: String,
+    pub tags: Vec<String>,
+    pub confidence: f64,
+    pub kind: Option<String>,
+}
+
+/// The buffer opened in the editor, filled in from `draft`
+pub fn template(draft: &Draft) -> String {
+    format!(
+        "---\ntags: {}\nconfidence: {}\nkind: {}\n---\n\n{}",
+        draft.tags.join(", "),
+        draft.confidence,
+        draft.kind.as_deref().unwrap_or(""),
+        draft.content
+    )
+}
+
+/// Read the saved buffer back. Front-matter fields left out keep their
+/// values from `defaults` and blank ones clear them; a buffer without
+/// front-matter is all content.
+pub fn parse(text: &str, defaults: &Draft) -> Result<Draft, String> {
+    let mut draft = defaults.clone();
+    let body = match text.strip_prefix("---\n").and_then(|rest| rest.split_once("\n---")) {
+        Some((front, body)) => {
+            let front: Value =
+                serde_yaml::from_str(front).map_err(|e| format!("Invalid front-matter: {}", e))?;
+            apply(&front, &mut draft)?;
+            body
+        }
+        None => text,
+    };
+    draft.content = body.trim().to_string();
+    Ok(draft)
+}
+
+fn apply(front: &Value, draft: &mut Draft) -> Result<(), String> {
+    match front.get("tags") {
+        None => {}
+        Some(Value::String(tags)) => {
+            draft.tags = tags
+                .split(',')
+                .map(|t| t.trim().to_string())
+                .filter(|t| !t.is_empty())
+                .collect()
+        }
+        Some(Value::Sequence(tags)) => {
+            draft.tags = tags.iter().filter_map(|t| t.as_str()).map(str::to_string).collect()
+        }
+        Some(_) => draft.tags.clear(),
+    }
+    match &front["confidence"] {
+        Value::Null => {}
+        value => {
+            let confidence = value.as_f64().ok_or("confidence must be a number")?;
+            if !(0.0..=1.0).contains(&confidence) {
+                return Err(format!("confidence must be between 0 and 1, got {}", confidence));
+            }
+            draft.confidence = confidence;
+        }
+    }
+    if let Some(kind) = front.get("kind") {
+        draft.kind = kind.as_str().map(|k| k.trim().to_string()).filter(|k| !k.is_empty());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_template_round_trip() {
+        let defaults = Draft {
+            content: String::new(),
+            tags: vec!["api".to_string()],
+            confidence: 0.5,
+            kind: None,
+        };
+        let blank = template(&defaults);
+        assert_eq!(parse(&blank, &defaults).unwrap(), defaults);
+
+        let written = blank
+            .replace("tags: api", "tags: [api, retries]")
+            .replace("kind: ", "kind: decision")
+            .replace("confidence: 0.5", "confidence: 0.9")
+            + "Retry idempotent calls only.\n\nPOSTs are retried by the caller.\n";
+        let draft = parse(&written, &defaults).unwrap();
+        assert_eq!(draft.tags, vec!["api", "retries"]);
+        assert_eq!(draft.confidence, 0.9);
+        assert_eq!(draft.kind.as_deref(), Some("decision"));
+        assert_eq!(draft.content, "Retry idempotent calls only.\n\nPOSTs are retried by the caller.");
+
+        assert_eq!(parse("Just text", &defaults).unwrap().content, "Just text");
+        assert!(parse("---\ntags:\n---\nx", &defaults).unwrap().tags.is_empty());
+        assert!(parse("---\nconfidence: 3\n---\nx", &defaults).is_err());
+    }
+}
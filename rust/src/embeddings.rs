@@ -1,19 +1,49 @@
+use crate::wire::{self, Connection, Opened};
 use md5::{Digest, Md5};
 use serde::{Deserialize, Serialize};
-use std::io::{Read, Write};
-use std::os::unix::net::UnixStream;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Mutex};
+use std::thread;
+use std::time::Duration;
 
 /// Embedding dimension for lite embedder
 const LITE_DIM: usize = 384;
 
-/// Socket path for embedding server
-const SOCKET_PATH: &str = "/tmp/roots-embedder.sock";
+/// Requests the server is sent at once; it embeds one batch at a time per
+/// model, so the rest are queued and sent back without a round trip between
+const SERVER_CONCURRENCY: usize = 4;
+
+/// Times an embed or rerank request is tried again while the server is
+/// unreachable; with the doubling delay, about 6s in all, enough for a
+/// restart of a small model
+const RETRIES: u32 = 5;
+const RETRY_DELAY: Duration = Duration::from_millis(200);
+
+/// Whether server requests give up on the first failure, set by
+/// `set_fail_fast`
+static FAIL_FAST: AtomicBool = AtomicBool::new(false);
+
+/// Try server requests once, for hooks with `hooks.fail_fast`, which would
+/// rather answer without the server than wait for it; applies to embedders
+/// created afterwards
+pub fn set_fail_fast() {
+    FAIL_FAST.store(true, Ordering::Relaxed);
+}
 
 /// Trait for embedding implementations
-pub trait Embedder {
+pub trait Embedder: Send + Sync {
     fn embed(&self, text: &str) -> Result<Vec<f32>, String>;
-    #[allow(dead_code)]
+    /// The model that computes the embeddings, which vectors are stamped with
+    fn model(&self) -> &str;
+    /// Whether embeddings are computed out of process (by the server)
+    fn is_remote(&self) -> bool {
+        false
+    }
+    /// How many batches can usefully be embedded in parallel
+    fn concurrency(&self) -> usize {
+        1
+    }
     fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>, String>;
 }
 
@@ -76,6 +106,14 @@ impl Embedder for LiteEmbedder {
         Ok(vector)
     }
 
+    fn model(&self) -> &str {
+        "lite"
+    }
+
+    fn concurrency(&self) -> usize {
+        thread::available_parallelism().map_or(1, |n| n.get())
+    }
+
     fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>, String> {
         texts.iter().map(|t| self.embed(t)).collect()
     }
@@ -97,12 +135,24 @@ fn md5_hash(text: &str) -> u128 {
 struct EmbedRequest<'a> {
     cmd: &'a str,
     text: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    model: Option<&'a str>,
 }
 
 #[derive(Serialize)]
 struct EmbedBatchRequest<'a> {
     cmd: &'a str,
     texts: &'a [&'a str],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    model: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct RerankRequest<'a> {
+    cmd: &'a str,
+    query: &'a str,
+    texts: &'a [&'a str],
+    model: &'a str,
 }
 
 #[derive(Serialize)]
@@ -124,37 +174,127 @@ struct EmbedBatchResponse {
     error: Option<String>,
 }
 
+#[derive(Deserialize)]
+struct RerankResponse {
+    ok: bool,
+    scores: Option<Vec<f64>>,
+    error: Option<String>,
+}
+
 #[derive(Deserialize)]
 struct PingResponse {
     ok: bool,
     model: Option<String>,
+    /// Every model loaded, the default (`model`) first; servers from before
+    /// multiple models leave it out
+    models: Option<Vec<String>>,
     error: Option<String>,
 }
 
+#[derive(Deserialize)]
+struct StatusResponse {
+    ok: bool,
+    #[serde(flatten)]
+    status: ServerStatus,
+}
+
+/// What a running server reports about itself for `roots server status`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ServerStatus {
+    pub model: String,
+    pub dimensions: Option<usize>,
+    /// Where the model runs: cpu, cuda:0, mps, ...
+    pub device: Option<String>,
+    /// Every model loaded, `model` (the default) first
+    pub models: Vec<String>,
+    /// Cross-encoders loaded by reranks so far
+    pub rerankers: Vec<String>,
+    pub uptime_secs: Option<f64>,
+    /// Resident memory of the server process
+    pub rss_bytes: Option<u64>,
+    /// Embed and rerank requests served
+    pub requests: Option<u64>,
+    /// Mean time to serve those requests; none before the first
+    pub avg_latency_ms: Option<f64>,
+}
+
 /// Embedder that uses the Python embedding server daemon
-pub struct ServerEmbedder;
+pub struct ServerEmbedder {
+    socket: PathBuf,
+    /// Which of the server's models embeds; its default when `None`
+    model: Option<String>,
+    /// Open connections not in use, kept for the next request
+    idle: Mutex<Vec<Connection>>,
+    /// Whether the server predates framing (`wire`)
+    legacy: AtomicBool,
+    /// Whether a request ran out of retries; later ones are tried once
+    /// until one gets through, so a dead server fails a reindex quickly
+    unreachable: AtomicBool,
+    /// Whether every request is tried once (`set_fail_fast`)
+    fail_fast: bool,
+    /// `embed` calls waiting to be sent (see `embed`)
+    waiting: Mutex<Waiting>,
+}
+
+/// `embed` calls made while another is in flight, sent together in one
+/// `embed_batch` when it returns
+#[derive(Default)]
+struct Waiting {
+    /// Whether a call is sending; the others wait to be answered or led
+    busy: bool,
+    calls: Vec<(String, mpsc::Sender<Turn>)>,
+}
+
+/// What a waiting `embed` call is told
+enum Turn {
+    Embedded(Result<Vec<f32>, String>),
+    /// Send everything waiting, this call's text included
+    Lead,
+}
 
 impl ServerEmbedder {
+    /// A client of the server at the configured socket (`server_socket`)
     pub fn new() -> Self {
-        Self
+        Self::at(crate::config::server_socket())
+    }
+
+    /// A client of the server listening on `socket`
+    pub fn at(socket: PathBuf) -> Self {
+        Self {
+            socket,
+            model: None,
+            idle: Mutex::new(Vec::new()),
+            legacy: AtomicBool::new(false),
+            unreachable: AtomicBool::new(false),
+            fail_fast: FAIL_FAST.load(Ordering::Relaxed),
+            waiting: Mutex::default(),
+        }
+    }
+
+    /// Embed with `model`, one of those the server has loaded
+    pub fn with_model(mut self, model: &str) -> Self {
+        self.model = Some(model.to_string());
+        self
+    }
+
+    pub fn socket(&self) -> &Path {
+        &self.socket
     }
 
     /// Check if the server is running
-    pub fn is_running() -> bool {
-        if !Path::new(SOCKET_PATH).exists() {
+    pub fn is_running(&self) -> bool {
+        if !self.socket.exists() {
             return false;
         }
 
-        match Self::ping() {
-            Ok(_) => true,
-            Err(_) => false,
-        }
+        self.ping().is_ok()
     }
 
     /// Ping the server and get the model name
-    pub fn ping() -> Result<String, String> {
+    pub fn ping(&self) -> Result<String, String> {
         let request = PingRequest { cmd: "ping" };
-        let response: PingResponse = send_request(&request)?;
+        let response: PingResponse = self.request(&request)?;
 
         if response.ok {
             Ok(response.model.unwrap_or_default())
@@ -164,21 +304,133 @@ impl ServerEmbedder {
     }
 
     /// Get the model the server is using
-    pub fn get_model() -> Result<String, String> {
-        Self::ping()
+    pub fn get_model(&self) -> Result<String, String> {
+        self.ping()
     }
-}
 
-impl Default for ServerEmbedder {
-    fn default() -> Self {
-        Self::new()
+    /// Every model the server has loaded, its default first
+    pub fn models(&self) -> Result<Vec<String>, String> {
+        let request = PingRequest { cmd: "ping" };
+        let response: PingResponse = self.request(&request)?;
+
+        if !response.ok {
+            return Err(response.error.unwrap_or_else(|| "Unknown error".to_string()));
+        }
+        Ok(response
+            .models
+            .unwrap_or_else(|| response.model.into_iter().collect()))
     }
-}
 
-impl Embedder for ServerEmbedder {
-    fn embed(&self, text: &str) -> Result<Vec<f32>, String> {
-        let request = EmbedRequest { cmd: "embed", text };
-        let response: EmbedResponse = send_request(&request)?;
+    /// Uptime, device, memory, and request stats. A server from before the
+    /// `status` command only has its model to report.
+    pub fn status(&self) -> Result<ServerStatus, String> {
+        let request = PingRequest { cmd: "status" };
+        match self.request::<_, StatusResponse>(&request)? {
+            response if response.ok => Ok(response.status),
+            _ => Ok(ServerStatus {
+                model: self.ping()?,
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// Ask the server to exit
+    pub fn stop(&self) -> Result<(), String> {
+        let request = PingRequest { cmd: "stop" };
+        self.request::<_, serde_json::Value>(&request).map(|_| ())
+    }
+
+    /// Send `request` once and parse the answer; for probes like `ping`,
+    /// which a stopped server should fail straight away
+    fn request<R, T>(&self, request: &R) -> Result<T, String>
+    where
+        R: Serialize,
+        T: for<'de> Deserialize<'de>,
+    {
+        parse(&self.exchange(request)?)
+    }
+
+    /// Send `request` and parse the answer, trying again with exponential
+    /// backoff while the server is unreachable, as while it restarts;
+    /// once only with `hooks.fail_fast` in a hook
+    fn request_with_retry<R, T>(&self, request: &R) -> Result<T, String>
+    where
+        R: Serialize,
+        T: for<'de> Deserialize<'de>,
+    {
+        let once = self.fail_fast || self.unreachable.load(Ordering::Relaxed);
+        let retries = if once { 0 } else { RETRIES };
+        let mut delay = RETRY_DELAY;
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            match self.exchange(request) {
+                Ok(response) => {
+                    self.unreachable.store(false, Ordering::Relaxed);
+                    return parse(&response);
+                }
+                Err(failure) if failure.transient && attempt <= retries => {
+                    tracing::debug!(attempt, error = %failure.message, "retrying server request");
+                    thread::sleep(delay);
+                    delay *= 2;
+                }
+                Err(failure) if failure.transient => {
+                    self.unreachable.store(true, Ordering::Relaxed);
+                    let tries = if attempt > 1 { format!(" after {} attempts", attempt) } else { String::new() };
+                    return Err(format!(
+                        "Embedding server unavailable{}: {} (see 'roots server status')",
+                        tries, failure.message
+                    ));
+                }
+                Err(failure) => return Err(failure.message),
+            }
+        }
+    }
+
+    /// One request and its raw answer, on an idle connection if there is
+    /// one. A kept connection the server has since closed is replaced by a
+    /// new one.
+    fn exchange<R: Serialize>(&self, request: &R) -> Result<Vec<u8>, wire::Failure> {
+        let _span = tracing::info_span!("server_request").entered();
+
+        let kept = self.idle.lock().unwrap().pop();
+        if let Some(mut conn) = kept {
+            if let Ok(response) = conn.send(request) {
+                self.idle.lock().unwrap().push(conn);
+                return Ok(response);
+            }
+        }
+
+        if self.legacy.load(Ordering::Relaxed) {
+            let response = wire::legacy_request(&self.socket, request);
+            // A server restarted since may speak framing
+            if !matches!(&response, Ok(bytes) if !bytes.is_empty()) {
+                self.legacy.store(false, Ordering::Relaxed);
+            }
+            return response;
+        }
+        let mut conn = match Connection::open(&self.socket)? {
+            Opened::Framed(conn) => conn,
+            Opened::Legacy => {
+                self.legacy.store(true, Ordering::Relaxed);
+                return wire::legacy_request(&self.socket, request);
+            }
+            // The next request shakes hands again
+            Opened::Unanswered => return wire::legacy_request(&self.socket, request),
+        };
+        let response = conn.send(request)?;
+        self.idle.lock().unwrap().push(conn);
+        Ok(response)
+    }
+
+    /// Embed one text
+    fn embed_one(&self, text: &str) -> Result<Vec<f32>, String> {
+        let request = EmbedRequest {
+            cmd: "embed",
+            text,
+            model: self.model.as_deref(),
+        };
+        let response: EmbedResponse = self.request_with_retry(&request)?;
 
         if response.ok {
             response
@@ -189,12 +441,107 @@ impl Embedder for ServerEmbedder {
         }
     }
 
+    /// Send the waiting `embed` calls and answer them, then hand the lead
+    /// to a call that arrived meanwhile. A batch that fails is sent again
+    /// one text at a time, so one bad text doesn't fail the calls it was
+    /// coalesced with.
+    fn send_waiting(&self) {
+        let calls = std::mem::take(&mut self.waiting.lock().unwrap().calls);
+        let texts: Vec<&str> = calls.iter().map(|(text, _)| text.as_str()).collect();
+        let results = match texts[..] {
+            [text] => vec![self.embed_one(text)],
+            _ => match self.embed_batch(&texts) {
+                Ok(vectors) if vectors.len() == texts.len() => vectors.into_iter().map(Ok).collect(),
+                _ => texts.iter().map(|text| self.embed_one(text)).collect(),
+            },
+        };
+        for ((_, tx), result) in calls.into_iter().zip(results) {
+            let _ = tx.send(Turn::Embedded(result));
+        }
+
+        let mut waiting = self.waiting.lock().unwrap();
+        while let Some((_, tx)) = waiting.calls.first() {
+            if tx.send(Turn::Lead).is_ok() {
+                return;
+            }
+            waiting.calls.remove(0);
+        }
+        waiting.busy = false;
+    }
+
+    /// Relevance (0-1) of each text to `query`, from the cross-encoder
+    /// `model`, whatever model embeds. The server loads it on first use.
+    pub fn rerank(&self, model: &str, query: &str, texts: &[&str]) -> Result<Vec<f64>, String> {
+        let request = RerankRequest {
+            cmd: "rerank",
+            query,
+            texts,
+            model,
+        };
+        let response: RerankResponse = self.request_with_retry(&request)?;
+
+        if !response.ok {
+            return Err(response.error.unwrap_or_else(|| "Unknown error".to_string()));
+        }
+        let scores = response
+            .scores
+            .ok_or_else(|| "No scores in response".to_string())?;
+        if scores.len() != texts.len() {
+            return Err(format!("Expected {} scores, got {}", texts.len(), scores.len()));
+        }
+        Ok(scores)
+    }
+}
+
+impl Default for ServerEmbedder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Embedder for ServerEmbedder {
+    fn is_remote(&self) -> bool {
+        true
+    }
+
+    /// The model asked for; "default" stands for whichever the server
+    /// embeds with when none was
+    fn model(&self) -> &str {
+        self.model.as_deref().unwrap_or("default")
+    }
+
+    fn concurrency(&self) -> usize {
+        SERVER_CONCURRENCY
+    }
+
+    /// Concurrent calls are coalesced: one sends while the rest wait, and
+    /// when it returns, the next sends everything that waited as one batch
+    fn embed(&self, text: &str) -> Result<Vec<f32>, String> {
+        let (tx, rx) = mpsc::channel();
+        let lead = {
+            let mut waiting = self.waiting.lock().unwrap();
+            waiting.calls.push((text.to_string(), tx));
+            !std::mem::replace(&mut waiting.busy, true)
+        };
+        if !lead {
+            if let Turn::Embedded(result) = receive(&rx)? {
+                return result;
+            }
+        }
+        self.send_waiting();
+        match receive(&rx)? {
+            Turn::Embedded(result) => result,
+            Turn::Lead => unreachable!("a call is sent with the first batch it leads"),
+        }
+    }
+
     fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>, String> {
         let request = EmbedBatchRequest {
             cmd: "embed_batch",
             texts,
+            model: self.model.as_deref(),
         };
-        let response: EmbedBatchResponse = send_request(&request)?;
+        let response: EmbedBatchResponse = self.request_with_retry(&request)?;
 
         if response.ok {
             response
@@ -206,41 +553,12 @@ impl Embedder for ServerEmbedder {
     }
 }
 
-/// Send a request to the embedding server and parse the response
-fn send_request<R, T>(request: &R) -> Result<T, String>
-where
-    R: Serialize,
-    T: for<'de> Deserialize<'de>,
-{
-    // Connect to socket
-    let mut stream =
-        UnixStream::connect(SOCKET_PATH).map_err(|e| format!("Failed to connect to server: {}", e))?;
-
-    // Set timeout
-    stream
-        .set_read_timeout(Some(std::time::Duration::from_secs(60)))
-        .map_err(|e| format!("Failed to set timeout: {}", e))?;
-
-    // Send request
-    let json = serde_json::to_string(request).map_err(|e| format!("Failed to serialize: {}", e))?;
-    stream
-        .write_all(json.as_bytes())
-        .map_err(|e| format!("Failed to send: {}", e))?;
-
-    // Shutdown write side to signal end of request
-    stream
-        .shutdown(std::net::Shutdown::Write)
-        .map_err(|e| format!("Failed to shutdown write: {}", e))?;
-
-    // Read response (up to 1MB)
-    let mut buffer = Vec::new();
-    stream
-        .take(1024 * 1024)
-        .read_to_end(&mut buffer)
-        .map_err(|e| format!("Failed to read response: {}", e))?;
-
-    // Parse response
-    serde_json::from_slice(&buffer).map_err(|e| format!("Failed to parse response: {}", e))
+fn receive(rx: &mpsc::Receiver<Turn>) -> Result<Turn, String> {
+    rx.recv().map_err(|_| "Embedding call abandoned".to_string())
+}
+
+fn parse<T: for<'de> Deserialize<'de>>(response: &[u8]) -> Result<T, String> {
+    serde_json::from_slice(response).map_err(|e| format!("Failed to parse response: {}", e))
 }
 
 // =============================================================================
@@ -275,14 +593,22 @@ pub fn get_embedder(model_name: Option<&str>, model_type: &str, use_server: bool
         return Box::new(LiteEmbedder::new());
     }
 
-    // Try server if requested
-    if use_server {
-        if ServerEmbedder::is_running() {
-            if let Ok(server_model) = ServerEmbedder::get_model() {
-                let requested_model = model_name.unwrap_or("BAAI/bge-base-en-v1.5");
-                if server_model == requested_model {
-                    return Box::new(ServerEmbedder::new());
-                }
+    // Try server if requested, starting it first with `server.autostart`
+    // (but never from Tab completion, which must not wait for a model)
+    let server = ServerEmbedder::new();
+    let requested_model = model_name.unwrap_or("BAAI/bge-base-en-v1.5");
+    let completing = std::env::var_os("COMPLETE").is_some();
+    let running = use_server
+        && (server.is_running()
+            || crate::config::server_autostart()
+                && !completing
+                && crate::server::autostart(&server, requested_model)
+                    .inspect_err(|e| eprintln!("Warning: {}", e))
+                    .is_ok());
+    if running {
+        if let Ok(models) = server.models() {
+            if models.iter().any(|m| m == requested_model) {
+                return Box::new(server.with_model(requested_model));
             }
         }
     }
@@ -313,6 +639,23 @@ mod tests {
         assert!((norm - 1.0).abs() < 0.001);
     }
 
+    #[test]
+    fn test_status_response_parses_with_and_without_stats() {
+        let full: StatusResponse = serde_json::from_str(
+            r#"{"ok": true, "model": "BAAI/bge-base-en-v1.5", "dimensions": 768, "device": "cpu",
+                "uptime_secs": 12.5, "rss_bytes": 1024, "requests": 3, "avg_latency_ms": null}"#,
+        )
+        .unwrap();
+        assert!(full.ok);
+        assert_eq!(full.status.dimensions, Some(768));
+        assert_eq!(full.status.requests, Some(3));
+        assert_eq!(full.status.avg_latency_ms, None);
+
+        let old: StatusResponse = serde_json::from_str(r#"{"ok": false, "error": "unknown: status"}"#).unwrap();
+        assert!(!old.ok);
+        assert!(old.status.model.is_empty());
+    }
+
     #[test]
     fn test_cosine_similarity() {
         let a = vec![1.0, 0.0, 0.0];
@@ -337,4 +680,277 @@ mod tests {
         // Similar texts should have higher similarity
         assert!(sim_ab > sim_ac);
     }
+
+    /// A framed server on a temp socket answering each request with `respond`
+    fn serve(
+        name: &str,
+        respond: impl Fn(serde_json::Value) -> serde_json::Value + Clone + Send + 'static,
+    ) -> PathBuf {
+        serve_with(name, usize::MAX, respond)
+    }
+
+    /// `serve`, hanging up each connection after `per_connection` messages,
+    /// the hello included
+    fn serve_with(
+        name: &str,
+        per_connection: usize,
+        respond: impl Fn(serde_json::Value) -> serde_json::Value + Clone + Send + 'static,
+    ) -> PathBuf {
+        use std::io::{Read, Write};
+        use std::os::unix::net::UnixListener;
+
+        let socket = std::env::temp_dir().join(format!("roots-{}-{}.sock", name, std::process::id()));
+        let _ = std::fs::remove_file(&socket);
+        let listener = UnixListener::bind(&socket).unwrap();
+        thread::spawn(move || {
+            for mut stream in listener.incoming().map_while(Result::ok) {
+                let respond = respond.clone();
+                thread::spawn(move || {
+                    for _ in 0..per_connection {
+                        let mut header = [0u8; 4];
+                        if stream.read_exact(&mut header).is_err() {
+                            return;
+                        }
+                        let mut payload = vec![0u8; u32::from_be_bytes(header) as usize];
+                        stream.read_exact(&mut payload).unwrap();
+                        let body = respond(serde_json::from_slice(&payload).unwrap()).to_string();
+                        stream.write_all(&(body.len() as u32).to_be_bytes()).unwrap();
+                        stream.write_all(body.as_bytes()).unwrap();
+                    }
+                });
+            }
+        });
+        socket
+    }
+
+    /// A server that embeds each text as its length, taking `delay` over
+    /// each request, and records how many texts each request carried
+    fn fake_server(name: &str, delay: Duration) -> (PathBuf, std::sync::Arc<Mutex<Vec<usize>>>) {
+        let sizes = std::sync::Arc::new(Mutex::new(Vec::new()));
+        let recorded = sizes.clone();
+        let socket = serve(name, move |request| {
+            let embed = |text: &serde_json::Value| vec![text.as_str().unwrap().len() as f32];
+            match request["cmd"].as_str().unwrap() {
+                "hello" => serde_json::json!({"ok": true, "version": 1}),
+                "embed" => {
+                    recorded.lock().unwrap().push(1);
+                    thread::sleep(delay);
+                    serde_json::json!({"ok": true, "embedding": embed(&request["text"])})
+                }
+                _ => {
+                    let texts = request["texts"].as_array().unwrap();
+                    recorded.lock().unwrap().push(texts.len());
+                    thread::sleep(delay);
+                    let embeddings: Vec<_> = texts.iter().map(embed).collect();
+                    serde_json::json!({"ok": true, "embeddings": embeddings})
+                }
+            }
+        });
+        (socket, sizes)
+    }
+
+    /// A server that answers pings with `ping` and records every embed and
+    /// rerank request
+    fn recording_server(name: &str, ping: serde_json::Value) -> (PathBuf, std::sync::Arc<Mutex<Vec<serde_json::Value>>>) {
+        let requests = std::sync::Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+        let socket = serve(name, move |request| {
+            let response = match request["cmd"].as_str().unwrap() {
+                "hello" => return serde_json::json!({"ok": true, "version": 1}),
+                "ping" => return ping.clone(),
+                "embed" => serde_json::json!({"ok": true, "embedding": [1.0]}),
+                "embed_batch" => serde_json::json!({"ok": true, "embeddings": [[1.0], [1.0]]}),
+                "rerank" => serde_json::json!({"ok": true, "scores": [0.5]}),
+                cmd => serde_json::json!({"ok": false, "error": format!("unknown: {}", cmd)}),
+            };
+            recorded.lock().unwrap().push(request);
+            response
+        });
+        (socket, requests)
+    }
+
+    /// A client that retries, whatever `set_fail_fast` a test has called
+    fn retrying(socket: PathBuf) -> ServerEmbedder {
+        let mut embedder = ServerEmbedder::at(socket);
+        embedder.fail_fast = false;
+        embedder
+    }
+
+    #[test]
+    fn test_request_waits_for_a_starting_server() {
+        let socket = std::env::temp_dir().join(format!("roots-starting-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&socket);
+        thread::spawn(|| {
+            thread::sleep(Duration::from_millis(500));
+            fake_server("starting", Duration::ZERO);
+        });
+
+        let embedder = retrying(socket.clone());
+        assert_eq!(embedder.embed("abcd").unwrap(), vec![4.0]);
+        assert!(!embedder.unreachable.load(Ordering::Relaxed));
+        std::fs::remove_file(&socket).unwrap();
+    }
+
+    #[test]
+    fn test_missing_server_fails_once_then_quickly() {
+        let socket = std::env::temp_dir().join(format!("roots-missing-{}.sock", std::process::id()));
+        let embedder = retrying(socket);
+
+        let error = embedder.embed("a").unwrap_err();
+        assert!(error.starts_with("Embedding server unavailable after 6 attempts"), "{}", error);
+        assert!(error.contains("roots server status"), "{}", error);
+
+        // Later requests are tried once until one gets through
+        let started = std::time::Instant::now();
+        let error = embedder.embed_batch(&["b", "c"]).unwrap_err();
+        assert!(started.elapsed() < RETRY_DELAY, "{:?}", started.elapsed());
+        assert!(error.starts_with("Embedding server unavailable: "), "{}", error);
+    }
+
+    #[test]
+    fn test_fail_fast_tries_once() {
+        set_fail_fast();
+        let socket = std::env::temp_dir().join(format!("roots-fail-fast-{}.sock", std::process::id()));
+        let embedder = ServerEmbedder::at(socket);
+
+        let started = std::time::Instant::now();
+        let error = embedder.embed("a").unwrap_err();
+        assert!(started.elapsed() < RETRY_DELAY, "{:?}", started.elapsed());
+        assert!(error.starts_with("Embedding server unavailable: "), "{}", error);
+    }
+
+    #[test]
+    fn test_dropped_idle_connection_is_replaced() {
+        let hellos = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counted = hellos.clone();
+        // The server closes each connection after one request, as an idle
+        // timeout or a restart would
+        let socket = serve_with("reconnect", 2, move |request| match request["cmd"].as_str().unwrap() {
+            "hello" => {
+                counted.fetch_add(1, Ordering::Relaxed);
+                serde_json::json!({"ok": true, "version": 1})
+            }
+            _ => serde_json::json!({"ok": true, "embedding": [request["text"].as_str().unwrap().len()]}),
+        });
+        let embedder = ServerEmbedder::at(socket.clone());
+
+        assert_eq!(embedder.embed("a").unwrap(), vec![1.0]);
+        assert_eq!(embedder.embed("bb").unwrap(), vec![2.0]);
+        assert_eq!(embedder.embed("ccc").unwrap(), vec![3.0]);
+        assert_eq!(hellos.load(Ordering::Relaxed), 3);
+        assert!(!embedder.legacy.load(Ordering::Relaxed));
+        std::fs::remove_file(&socket).unwrap();
+    }
+
+    #[test]
+    fn test_unanswered_hello_does_not_stick_to_legacy() {
+        use std::io::{Read, Write};
+        use std::os::unix::net::UnixListener;
+
+        let socket = std::env::temp_dir().join(format!("roots-busy-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&socket);
+        let listener = UnixListener::bind(&socket).unwrap();
+        thread::spawn(move || {
+            // Too busy to answer the first hello, then a framed server
+            let (first, _) = listener.accept().unwrap();
+            let (mut legacy, _) = listener.accept().unwrap();
+            let _ = legacy.read_to_end(&mut Vec::new());
+            drop(legacy);
+            drop(first);
+            for mut stream in listener.incoming().map_while(Result::ok) {
+                for _ in 0..2 {
+                    let mut header = [0u8; 4];
+                    if stream.read_exact(&mut header).is_err() {
+                        break;
+                    }
+                    let mut payload = vec![0u8; u32::from_be_bytes(header) as usize];
+                    stream.read_exact(&mut payload).unwrap();
+                    let request: serde_json::Value = serde_json::from_slice(&payload).unwrap();
+                    let body = match request["cmd"].as_str().unwrap() {
+                        "hello" => serde_json::json!({"ok": true, "version": 1}),
+                        _ => serde_json::json!({"ok": true, "model": "framed"}),
+                    }
+                    .to_string();
+                    stream.write_all(&(body.len() as u32).to_be_bytes()).unwrap();
+                    stream.write_all(body.as_bytes()).unwrap();
+                }
+            }
+        });
+        let embedder = ServerEmbedder::at(socket.clone());
+
+        // The fallback request gets nothing back from a framed server
+        assert!(embedder.ping().is_err());
+        assert!(!embedder.legacy.load(Ordering::Relaxed));
+        assert_eq!(embedder.ping().unwrap(), "framed");
+        std::fs::remove_file(&socket).unwrap();
+    }
+
+    #[test]
+    fn test_concurrent_embeds_are_coalesced_into_a_batch() {
+        let (socket, sizes) = fake_server("coalesce", Duration::from_millis(300));
+        let embedder = ServerEmbedder::at(socket.clone());
+
+        let texts: Vec<String> = (1..=8).map(|n| "x".repeat(n)).collect();
+        let vectors: Vec<Vec<f32>> = thread::scope(|scope| {
+            let first = scope.spawn(|| embedder.embed(&texts[0]).unwrap());
+            // The rest arrive while the first is in flight
+            thread::sleep(Duration::from_millis(50));
+            let rest: Vec<_> = texts[1..]
+                .iter()
+                .map(|text| scope.spawn(|| embedder.embed(text).unwrap()))
+                .collect();
+            std::iter::once(first).chain(rest).map(|h| h.join().unwrap()).collect()
+        });
+
+        // Each call gets its own text's vector
+        for (text, vector) in texts.iter().zip(&vectors) {
+            assert_eq!(vector, &vec![text.len() as f32]);
+        }
+        assert_eq!(*sizes.lock().unwrap(), vec![1, 7]);
+        // With nothing in flight, a call is sent on its own straight away
+        assert_eq!(embedder.embed("abc").unwrap(), vec![3.0]);
+        assert_eq!(sizes.lock().unwrap().last(), Some(&1));
+        std::fs::remove_file(&socket).unwrap();
+    }
+
+    #[test]
+    fn test_requests_name_the_model_they_want() {
+        let ping = serde_json::json!({"ok": true, "model": "big", "models": ["big", "small"]});
+        let (socket, requests) = recording_server("models", ping);
+        let server = ServerEmbedder::at(socket.clone());
+        assert_eq!(server.models().unwrap(), vec!["big", "small"]);
+
+        // The server's default model unless one is chosen
+        server.embed("a").unwrap();
+        let small = ServerEmbedder::at(socket.clone()).with_model("small");
+        small.embed("b").unwrap();
+        small.embed_batch(&["c", "d"]).unwrap();
+        // Reranks always name their cross-encoder
+        assert_eq!(server.rerank("fast-reranker", "q", &["e"]).unwrap(), vec![0.5]);
+
+        let requests = requests.lock().unwrap();
+        let models: Vec<_> = requests.iter().map(|r| (r["cmd"].as_str().unwrap(), r.get("model"))).collect();
+        assert_eq!(
+            models,
+            vec![
+                ("embed", None),
+                ("embed", Some(&serde_json::json!("small"))),
+                ("embed_batch", Some(&serde_json::json!("small"))),
+                ("rerank", Some(&serde_json::json!("fast-reranker"))),
+            ]
+        );
+        std::fs::remove_file(&socket).unwrap();
+    }
+
+    #[test]
+    fn test_models_of_a_single_model_server() {
+        // Servers from before several models only report their one
+        let (socket, _) = recording_server("one-model", serde_json::json!({"ok": true, "model": "big"}));
+        assert_eq!(ServerEmbedder::at(socket.clone()).models().unwrap(), vec!["big"]);
+        std::fs::remove_file(&socket).unwrap();
+
+        let (socket, _) = recording_server("no-ping", serde_json::json!({"ok": false, "error": "loading"}));
+        assert_eq!(ServerEmbedder::at(socket.clone()).models(), Err("loading".to_string()));
+        std::fs::remove_file(&socket).unwrap();
+    }
 }
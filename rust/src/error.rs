@@ -0,0 +1,116 @@
+//! Errors commands fail with, and the exit code of each kind.
+//!
+//! Most of roots passes errors around as messages; the kinds automation
+//! wants to branch on are raised as their own variants where they happen
+//! (opening a store, looking up a memory, embedding, taking a lock) and
+//! keep their kind up to `main`. Everything else is `Other`.
+
+use crate::store::StoreError;
+
+/// Exit codes, stable across releases. 2 is also what clap exits with for
+/// a bad command line.
+pub const EXIT_OTHER: i32 = 1;
+pub const EXIT_INVALID: i32 = 2;
+pub const EXIT_NOT_INITIALIZED: i32 = 3;
+pub const EXIT_NOT_FOUND: i32 = 4;
+pub const EXIT_EMBEDDER_UNAVAILABLE: i32 = 5;
+pub const EXIT_LOCKED: i32 = 6;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// No `.roots` directory here or above
+    #[error("No .roots directory found. Run 'roots init' first.")]
+    NotInitialized,
+    /// A memory, snapshot, or other named thing that does not exist
+    #[error("{0}")]
+    NotFound(String),
+    /// Neither the embedding server nor the configured model could embed
+    #[error("{0}")]
+    EmbedderUnavailable(String),
+    /// Another process holds the store or its maintenance lock
+    #[error("{0}")]
+    Locked(String),
+    /// Arguments that parse but make no sense together
+    #[error("{0}")]
+    Invalid(String),
+    #[error("{0}")]
+    Other(String),
+}
+
+impl Error {
+    /// Short name of the kind, as `--output json` reports it
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::NotInitialized => "not_initialized",
+            Self::NotFound(_) => "not_found",
+            Self::EmbedderUnavailable(_) => "embedder_unavailable",
+            Self::Locked(_) => "locked",
+            Self::Invalid(_) => "invalid",
+            Self::Other(_) => "other",
+        }
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::NotInitialized => EXIT_NOT_INITIALIZED,
+            Self::NotFound(_) => EXIT_NOT_FOUND,
+            Self::EmbedderUnavailable(_) => EXIT_EMBEDDER_UNAVAILABLE,
+            Self::Locked(_) => EXIT_LOCKED,
+            Self::Invalid(_) => EXIT_INVALID,
+            Self::Other(_) => EXIT_OTHER,
+        }
+    }
+
+    /// A store error, prefixed with what was being done
+    pub fn store(context: &str, e: StoreError) -> Self {
+        let message = format!("{}: {}", context, e);
+        match e {
+            StoreError::Sqlite(rusqlite::Error::SqliteFailure(failure, _))
+                if failure.code == rusqlite::ErrorCode::DatabaseBusy
+                    || failure.code == rusqlite::ErrorCode::DatabaseLocked =>
+            {
+                Self::Locked(message)
+            }
+            _ => Self::Other(message),
+        }
+    }
+}
+
+impl From<String> for Error {
+    fn from(message: String) -> Self {
+        Self::Other(message)
+    }
+}
+
+impl From<&str> for Error {
+    fn from(message: &str) -> Self {
+        Self::Other(message.to_string())
+    }
+}
+
+/// For the many functions that still fail with a message
+impl From<Error> for String {
+    fn from(e: Error) -> Self {
+        e.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kinds_keep_their_exit_codes() {
+        assert_eq!(Error::NotInitialized.exit_code(), 3);
+        assert_eq!(Error::NotFound("Memory not found: 4".to_string()).kind(), "not_found");
+        assert_eq!(Error::from("Failed to read x".to_string()).exit_code(), EXIT_OTHER);
+
+        let busy = rusqlite::Error::SqliteFailure(rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_BUSY), None);
+        let error = Error::store("Failed to open store", StoreError::Sqlite(busy));
+        assert_eq!(error.kind(), "locked");
+        assert!(error.to_string().starts_with("Failed to open store: "));
+
+        let message: String = Error::NotInitialized.into();
+        assert!(message.contains("roots init"));
+    }
+}
@@ -0,0 +1,86 @@
+//! Retrieval evaluation for `roots eval`.
+//!
+//! A cases file is a YAML list of queries, each with the IDs of the
+//! memories a good recall should return:
+//!
+//! ```yaml
+//! - query: how do we deploy to staging
+//!   expected: [12, 40]
+//! - query: which python tool do we use
+//!   expected: [3]
+//! ```
+//!
+//! Each query is recalled with the current embedder and ranking; recall@k is
+//! the share of expected memories in the top k, and the reciprocal rank is
+//! 1/rank of the first expected memory found (0 if none is). Both are
+//! averaged over the cases.
+
+use serde::Deserialize;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Case {
+    pub query: String,
+    pub expected: Vec<i64>,
+}
+
+/// How one case did
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaseResult {
+    /// Share of the expected IDs retrieved
+    pub recall: f64,
+    pub reciprocal_rank: f64,
+    /// Expected IDs not retrieved
+    pub missed: Vec<i64>,
+}
+
+/// The cases in `text`
+pub fn parse_cases(text: &str) -> Result<Vec<Case>, String> {
+    let cases: Vec<Case> = serde_yaml::from_str(text).map_err(|e| format!("Invalid cases: {}", e))?;
+    if let Some(i) = cases.iter().position(|c| c.expected.is_empty()) {
+        return Err(format!("Case {} ('{}') expects no memories", i + 1, cases[i].query));
+    }
+    Ok(cases)
+}
+
+/// Score the IDs a recall returned, best first, against the expected ones
+pub fn score(expected: &[i64], retrieved: &[i64]) -> CaseResult {
+    let missed: Vec<i64> = expected.iter().copied().filter(|id| !retrieved.contains(id)).collect();
+    let first = retrieved.iter().position(|id| expected.contains(id));
+    CaseResult {
+        recall: (expected.len() - missed.len()) as f64 / expected.len().max(1) as f64,
+        reciprocal_rank: first.map_or(0.0, |rank| 1.0 / (rank + 1) as f64),
+        missed,
+    }
+}
+
+/// Mean of `values`, 0 for none
+pub fn mean(values: impl Iterator<Item = f64>) -> f64 {
+    let (sum, n) = values.fold((0.0, 0), |(sum, n), v| (sum + v, n + 1));
+    if n == 0 {
+        0.0
+    } else {
+        sum / n as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_score_recall_and_reciprocal_rank() {
+        let result = score(&[12, 40], &[7, 40, 3]);
+        assert_eq!(result.recall, 0.5);
+        assert_eq!(result.reciprocal_rank, 0.5);
+        assert_eq!(result.missed, vec![12]);
+
+        let none = score(&[12], &[1, 2]);
+        assert_eq!((none.recall, none.reciprocal_rank), (0.0, 0.0));
+
+        let cases = parse_cases("- query: deploy\n  expected: [12, 40]\n").unwrap();
+        assert_eq!(cases, vec![Case { query: "deploy".to_string(), expected: vec![12, 40] }]);
+        assert!(parse_cases("- query: deploy\n  expected: []\n").is_err());
+        assert!(parse_cases("- query: deploy\n  ids: [1]\n").is_err());
+    }
+}
@@ -0,0 +1,122 @@
+//! Query expansion for short prompts.
+//!
+//! "fix the auth bug" gives an embedding little to work with. With
+//! `context --expand` (or `expand_queries: true`), a short prompt gets the
+//! names of tags it mentions and the most frequent words of its best
+//! full-text matches appended before it is embedded, pulling the query
+//! toward the memories it is about.
+
+use std::collections::HashMap;
+
+/// Prompts with more words than this are embedded as they are
+pub const MAX_WORDS: usize = 8;
+
+/// Full-text matches keywords are drawn from
+pub const FTS_MATCHES: usize = 5;
+
+/// Keywords appended from the full-text matches
+const KEYWORDS: usize = 5;
+
+/// Words too common to say what a prompt or memory is about
+pub const STOPWORDS: &[&str] = &[
+    "about", "after", "also", "and", "any", "are", "before", "between", "but", "can", "could",
+    "did", "does", "during", "each", "every", "for", "from", "had", "has", "have", "how", "into",
+    "its", "just", "let", "like", "make", "more", "most", "not", "now", "only", "other", "our",
+    "out", "please", "should", "some", "such", "than", "that", "the", "their", "them", "then",
+    "there", "these", "this", "through", "use", "used", "uses", "very", "was", "were", "what",
+    "when", "where", "which", "while", "who", "why", "will", "with", "without", "would", "you",
+    "your",
+];
+
+/// Lowercased words of `text` worth matching on: three or more characters
+/// and not stopwords
+pub fn terms(text: &str) -> Vec<String> {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-'))
+        .map(|w| w.trim_matches('-').to_lowercase())
+        .filter(|w| w.chars().count() >= 3 && !STOPWORDS.contains(&w.as_str()))
+        .collect()
+}
+
+/// Tags of `tags` that `text` names in full: every word of a tag like
+/// `auth-service` appears among its terms
+pub fn mentioned_tags(text: &str, tags: &[(String, usize)]) -> Vec<String> {
+    let text_terms = terms(text);
+    tags.iter()
+        .filter(|(tag, _)| {
+            let mut parts = tag.split(|c: char| !c.is_alphanumeric()).filter(|p| !p.is_empty()).peekable();
+            parts.peek().is_some() && parts.all(|part| text_terms.iter().any(|t| t.eq_ignore_ascii_case(part)))
+        })
+        .map(|(tag, _)| tag.clone())
+        .collect()
+}
+
+/// FTS5 query matching any of `terms`, each quoted so none is read as syntax
+pub fn fts_query(terms: &[String]) -> String {
+    terms
+        .iter()
+        .map(|t| format!("\"{}\"", t.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" OR ")
+}
+
+/// The prompt with tags it names and keywords of its full-text matches
+/// (`texts`) appended; words already in the prompt are not repeated
+pub fn expand(prompt: &str, tags: &[(String, usize)], texts: &[&str]) -> String {
+    let prompt_terms = terms(prompt);
+    let mut added: Vec<String> = Vec::new();
+
+    for (tag, _) in tags {
+        let named = tag
+            .split(|c: char| !c.is_alphanumeric())
+            .any(|part| prompt_terms.iter().any(|t| t.eq_ignore_ascii_case(part)));
+        if named && !prompt_terms.contains(tag) {
+            added.push(tag.clone());
+        }
+    }
+
+    // Most frequent across the matches; ties keep the order first seen
+    let mut counts: HashMap<String, (usize, usize)> = HashMap::new();
+    for word in texts.iter().flat_map(|t| terms(t)) {
+        let seen = counts.len();
+        counts.entry(word).or_insert((0, seen)).0 += 1;
+    }
+    let mut keywords: Vec<(String, (usize, usize))> = counts
+        .into_iter()
+        .filter(|(w, _)| !prompt_terms.contains(w) && !added.contains(w))
+        .collect();
+    keywords.sort_by(|a, b| b.1 .0.cmp(&a.1 .0).then(a.1 .1.cmp(&b.1 .1)));
+    added.extend(keywords.into_iter().take(KEYWORDS).map(|(w, _)| w));
+
+    if added.is_empty() {
+        prompt.to_string()
+    } else {
+        format!("{} {}", prompt, added.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_adds_tags_and_keywords() {
+        let tags = vec![("auth-service".to_string(), 4), ("deploy".to_string(), 2)];
+        let texts = [
+            "Auth tokens are signed with the JWT secret from vault",
+            "JWT refresh fails when the vault token expires",
+        ];
+
+        assert_eq!(terms("Fix the auth bug"), vec!["fix", "auth", "bug"]);
+        assert_eq!(fts_query(&terms("auth bug")), "\"auth\" OR \"bug\"");
+        assert_eq!(
+            expand("fix the auth bug", &tags, &texts),
+            "fix the auth bug auth-service jwt vault tokens signed secret"
+        );
+        assert_eq!(expand("hello", &[], &[]), "hello");
+        assert_eq!(
+            mentioned_tags("The auth service signs JWTs", &tags),
+            vec!["auth-service"]
+        );
+        assert!(mentioned_tags("auth tokens", &tags).is_empty());
+    }
+}
@@ -0,0 +1,168 @@
+/// Damping factor for PageRank
+const DAMPING: f64 = 0.85;
+
+/// Number of power iterations
+const ITERATIONS: usize = 30;
+
+/// Weighted PageRank over an undirected graph of `n` nodes.
+///
+/// Edges are `(a, b, weight)`. Scores are min-max normalized to 0-1 so the
+/// most central node scores 1.0 and isolated nodes score 0.0. A graph with
+/// no edges yields all zeros.
+pub fn centrality(n: usize, edges: &[(usize, usize, f64)]) -> Vec<f64> {
+    if n == 0 {
+        return Vec::new();
+    }
+
+    let mut neighbors: Vec<Vec<(usize, f64)>> = vec![Vec::new(); n];
+    for &(a, b, w) in edges {
+        if a == b || w <= 0.0 {
+            continue;
+        }
+        neighbors[a].push((b, w));
+        neighbors[b].push((a, w));
+    }
+
+    let out_weight: Vec<f64> = neighbors
+        .iter()
+        .map(|adj| adj.iter().map(|(_, w)| w).sum())
+        .collect();
+
+    let base = (1.0 - DAMPING) / n as f64;
+    let mut rank = vec![1.0 / n as f64; n];
+
+    for _ in 0..ITERATIONS {
+        // Nodes without edges spread their rank evenly
+        let dangling: f64 = (0..n)
+            .filter(|&i| out_weight[i] == 0.0)
+            .map(|i| rank[i])
+            .sum();
+
+        let mut next = vec![base + DAMPING * dangling / n as f64; n];
+        for (i, adj) in neighbors.iter().enumerate() {
+            for &(j, w) in adj {
+                next[j] += DAMPING * rank[i] * w / out_weight[i];
+            }
+        }
+        rank = next;
+    }
+
+    normalized(&rank)
+}
+
+/// Scores min-max normalized to 0-1, all zeros when they are all equal
+pub fn normalized(scores: &[f64]) -> Vec<f64> {
+    let max = scores.iter().cloned().fold(f64::MIN, f64::max);
+    let min = scores.iter().cloned().fold(f64::MAX, f64::min);
+    if max - min < 1e-12 {
+        return vec![0.0; scores.len()];
+    }
+
+    scores.iter().map(|r| (r - min) / (max - min)).collect()
+}
+
+/// Edges from each vector to its `k` most similar others at or above
+/// `threshold`, weighted by cosine similarity, each pair once. Every pair
+/// is still compared (there is no vector index to ask), but the graph stays
+/// at most `k` edges a node.
+pub fn nearest_edges(vectors: &[&[f32]], threshold: f64, k: usize) -> Vec<(usize, usize, f64)> {
+    // Unit vectors, so each comparison is a dot product
+    let units: Vec<Vec<f32>> = vectors
+        .iter()
+        .map(|v| {
+            let norm = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+            v.iter().map(|x| if norm > 0.0 { x / norm } else { 0.0 }).collect()
+        })
+        .collect();
+
+    let mut nearest: Vec<Vec<(usize, f64)>> = vec![Vec::new(); units.len()];
+    for i in 0..units.len() {
+        for j in (i + 1)..units.len() {
+            if units[i].len() != units[j].len() {
+                continue;
+            }
+            let sim = units[i].iter().zip(&units[j]).map(|(a, b)| a * b).sum::<f32>() as f64;
+            if sim >= threshold {
+                keep_nearest(&mut nearest[i], (j, sim), k);
+                keep_nearest(&mut nearest[j], (i, sim), k);
+            }
+        }
+    }
+
+    let mut edges = Vec::new();
+    for (i, near) in nearest.iter().enumerate() {
+        for &(j, sim) in near {
+            // A pair in both lists is added from its lower end
+            if i < j || !nearest[j].iter().any(|&(other, _)| other == i) {
+                edges.push((i, j, sim));
+            }
+        }
+    }
+    edges
+}
+
+/// Add a neighbour to a list of the `k` most similar
+fn keep_nearest(near: &mut Vec<(usize, f64)>, candidate: (usize, f64), k: usize) {
+    if near.len() < k {
+        near.push(candidate);
+        return;
+    }
+    let weakest = (0..near.len())
+        .min_by(|&a, &b| near[a].1.total_cmp(&near[b].1))
+        .filter(|&i| near[i].1 < candidate.1);
+    if let Some(i) = weakest {
+        near[i] = candidate;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hub_is_most_central() {
+        // Star graph: node 0 connects to everyone else
+        let edges = vec![(0, 1, 1.0), (0, 2, 1.0), (0, 3, 1.0)];
+        let scores = centrality(4, &edges);
+
+        assert_eq!(scores[0], 1.0);
+        assert!(scores[1] < scores[0]);
+        assert!((scores[1] - scores[2]).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_nearest_edges_keep_k_per_node() {
+        let edges_of = |vectors: &[Vec<f32>], k| {
+            let refs: Vec<&[f32]> = vectors.iter().map(|v| v.as_slice()).collect();
+            nearest_edges(&refs, 0.5, k)
+        };
+
+        // Two close pairs and an outlier
+        let pairs = vec![
+            vec![1.0, 0.0, 0.0],
+            vec![1.0, 0.1, 0.0],
+            vec![0.0, 1.0, 0.0],
+            vec![0.0, 1.0, 0.1],
+            vec![0.0, 0.0, 1.0],
+        ];
+        let edges = edges_of(&pairs, 1);
+        assert_eq!(edges.iter().map(|&(a, b, _)| (a, b)).collect::<Vec<_>>(), vec![(0, 1), (2, 3)]);
+        assert!(edges.iter().all(|&(_, _, w)| (0.99..=1.0).contains(&w)));
+
+        // Five similar vectors: one neighbour each leaves at most five edges,
+        // room for all leaves each of the ten pairs exactly once
+        let fan: Vec<Vec<f32>> = (0..5).map(|i| vec![1.0, i as f32 * 0.1]).collect();
+        assert!(edges_of(&fan, 1).len() <= 5);
+        let edges = edges_of(&fan, 10);
+        assert_eq!(edges.len(), 10);
+        for &(a, b, _) in &edges {
+            assert_eq!(edges.iter().filter(|&&(x, y, _)| (x, y) == (a, b) || (x, y) == (b, a)).count(), 1);
+        }
+    }
+
+    #[test]
+    fn test_no_edges() {
+        assert_eq!(centrality(3, &[]), vec![0.0, 0.0, 0.0]);
+        assert!(centrality(0, &[]).is_empty());
+    }
+}
@@ -0,0 +1,169 @@
+//! Claude Code hook payloads.
+//!
+//! Claude Code runs hook commands with a JSON payload on stdin (the event
+//! name, session id, transcript path, and for `UserPromptSubmit` the prompt)
+//! rather than in environment variables. With `--hook-stdin`, `context` and
+//! `prime` read the payload and answer in the JSON shape the event expects,
+//! so the output is added to the conversation as context, and `remember
+//! --auto` captures edits (`PostToolUse`) and the agent's last reply
+//! (`Stop`) as candidate memories. `capture` reads the whole transcript at
+//! `SessionEnd`.
+
+use serde::Deserialize;
+use serde_json::Value;
+use std::io::Read;
+
+/// Events whose response can carry `additionalContext`
+const CONTEXT_EVENTS: &[&str] = &["SessionStart", "UserPromptSubmit"];
+
+/// Tools whose results `remember --hook-stdin` captures
+pub const CAPTURE_TOOLS: &str = "Edit|MultiEdit|Write";
+
+/// Longest candidate captured from a hook; whole-file writes are cut here
+const CAPTURE_MAX_CHARS: usize = 2000;
+
+/// The fields of a hook payload roots uses
+#[derive(Debug, Default, Deserialize)]
+pub struct HookInput {
+    #[serde(default)]
+    pub hook_event_name: String,
+    /// The submitted prompt, for `UserPromptSubmit`
+    pub prompt: Option<String>,
+    /// The tool that ran and its input, for `PostToolUse`
+    pub tool_name: Option<String>,
+    #[serde(default)]
+    pub tool_input: Value,
+    pub session_id: Option<String>,
+    /// The session's JSONL transcript
+    pub transcript_path: Option<String>,
+}
+
+/// Session for writes made outside a hook payload, e.g. by an agent that
+/// exports it before running roots
+pub fn env_session() -> Option<String> {
+    std::env::var("ROOTS_SESSION").ok().filter(|s| !s.is_empty())
+}
+
+impl HookInput {
+    /// Read the payload Claude Code passes on stdin
+    pub fn read() -> Result<Self, String> {
+        let mut input = String::new();
+        std::io::stdin()
+            .read_to_string(&mut input)
+            .map_err(|e| format!("Failed to read hook input: {}", e))?;
+        Self::parse(&input)
+    }
+
+    pub fn parse(input: &str) -> Result<Self, String> {
+        serde_json::from_str(input).map_err(|e| format!("Failed to parse hook input: {}", e))
+    }
+
+    /// What to print for `context`: the hook JSON for events that take
+    /// additional context, the context itself for any other event, and
+    /// nothing when there is none
+    pub fn respond(&self, context: &str) -> String {
+        if context.is_empty() || !CONTEXT_EVENTS.contains(&self.hook_event_name.as_str()) {
+            return context.to_string();
+        }
+        let response = serde_json::json!({
+            "hookSpecificOutput": {
+                "hookEventName": self.hook_event_name,
+                "additionalContext": context,
+            }
+        });
+        format!("{}\n", response)
+    }
+
+    /// A memory candidate from the event: the file and new text of an
+    /// edit for `PostToolUse`, the agent's last reply for `Stop`
+    pub fn capture(&self) -> Option<String> {
+        let candidate = match self.hook_event_name.as_str() {
+            "PostToolUse" => self.edit(),
+            "Stop" => last_assistant_text(self.transcript_path.as_deref()?),
+            _ => None,
+        }?;
+        let candidate = candidate.trim();
+        (!candidate.is_empty()).then(|| candidate.chars().take(CAPTURE_MAX_CHARS).collect())
+    }
+
+    fn edit(&self) -> Option<String> {
+        let input = &self.tool_input;
+        let text = match self.tool_name.as_deref()? {
+            "Edit" => input["new_string"].as_str()?.to_string(),
+            "Write" => input["content"].as_str()?.to_string(),
+            "MultiEdit" => input["edits"]
+                .as_array()?
+                .iter()
+                .filter_map(|e| e["new_string"].as_str())
+                .collect::<Vec<_>>()
+                .join("\n"),
+            _ => return None,
+        };
+        Some(format!("{}:\n{}", input["file_path"].as_str()?, text))
+    }
+}
+
+/// Text of the last assistant message in a Claude Code transcript
+fn last_assistant_text(path: &str) -> Option<String> {
+    crate::transcript::read(path)
+        .ok()?
+        .messages
+        .into_iter()
+        .rev()
+        .find(|m| m.role == "assistant")
+        .map(|m| m.text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_respond() {
+        let input = HookInput::parse(
+            r#"{"session_id": "abc", "transcript_path": "/tmp/t.jsonl", "cwd": "/repo",
+                "hook_event_name": "UserPromptSubmit", "prompt": "fix the auth bug"}"#,
+        )
+        .unwrap();
+        assert_eq!(input.prompt.as_deref(), Some("fix the auth bug"));
+
+        let response: serde_json::Value =
+            serde_json::from_str(&input.respond("# Relevant Memories\n")).unwrap();
+        assert_eq!(response["hookSpecificOutput"]["hookEventName"], "UserPromptSubmit");
+        assert_eq!(response["hookSpecificOutput"]["additionalContext"], "# Relevant Memories\n");
+        assert_eq!(input.respond(""), "");
+
+        let compact = HookInput::parse(r#"{"hook_event_name": "PreCompact"}"#).unwrap();
+        assert_eq!(compact.respond("context"), "context");
+        assert!(HookInput::parse("not json").is_err());
+    }
+
+    #[test]
+    fn test_capture_edits_and_last_reply() {
+        let edit = HookInput::parse(
+            r#"{"hook_event_name": "PostToolUse", "tool_name": "Edit",
+                "tool_input": {"file_path": "src/db.rs", "old_string": "a", "new_string": "b"}}"#,
+        )
+        .unwrap();
+        assert_eq!(edit.capture().as_deref(), Some("src/db.rs:\nb"));
+
+        let path = std::env::temp_dir().join(format!("roots-transcript-{}.jsonl", std::process::id()));
+        std::fs::write(
+            &path,
+            [
+                r#"{"type": "user", "message": {"role": "user", "content": "why?"}}"#,
+                r#"{"type": "assistant", "message": {"content": [{"type": "text", "text": "Because of X"}]}}"#,
+                r#"{"type": "assistant", "message": {"content": [{"type": "tool_use", "name": "Bash"}]}}"#,
+            ]
+            .join("\n"),
+        )
+        .unwrap();
+        let stop = HookInput {
+            hook_event_name: "Stop".to_string(),
+            transcript_path: Some(path.to_string_lossy().to_string()),
+            ..Default::default()
+        };
+        assert_eq!(stop.capture().as_deref(), Some("Because of X"));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
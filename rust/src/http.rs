@@ -0,0 +1,98 @@
+//! Minimal HTTP through the system `curl`.
+//!
+//! Requests are described as a curl config read from stdin, so URLs, bodies,
+//! and credentials never show up in the process list.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Default seconds before a request is abandoned
+const DEFAULT_TIMEOUT_SECS: u32 = 300;
+
+/// A curl config built option by option
+pub struct Request {
+    config: String,
+    timeout_secs: u32,
+}
+
+impl Request {
+    pub fn new(method: &str, url: &str) -> Self {
+        Self {
+            config: String::new(),
+            timeout_secs: DEFAULT_TIMEOUT_SECS,
+        }
+        .opt("request", method)
+        .opt("url", url)
+    }
+
+    pub fn timeout(mut self, secs: u32) -> Self {
+        self.timeout_secs = secs;
+        self
+    }
+
+    /// Add a `name = "value"` config line
+    pub fn opt(mut self, name: &str, value: &str) -> Self {
+        self.config
+            .push_str(&format!("{} = \"{}\"\n", name, escape(value)));
+        self
+    }
+
+    pub fn header(self, header: &str) -> Self {
+        self.opt("header", header)
+    }
+
+    /// Run the request, returning the response body. A failed request
+    /// reports the response body if there is one, otherwise curl's message.
+    pub fn send(self) -> Result<String, String> {
+        let mut child = Command::new("curl")
+            .args(["-sS", "--fail-with-body", "--max-time"])
+            .arg(self.timeout_secs.to_string())
+            .args(["-K", "-"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Failed to run curl: {}", e))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(self.config.as_bytes())
+                .map_err(|e| format!("Failed to send request: {}", e))?;
+        }
+        let output = child
+            .wait_with_output()
+            .map_err(|e| format!("Failed to run curl: {}", e))?;
+
+        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        if output.status.success() {
+            Ok(stdout)
+        } else if stdout.trim().is_empty() {
+            Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+        } else {
+            Err(stdout.trim().to_string())
+        }
+    }
+}
+
+/// Escape a value for a double-quoted curl config string
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+/// Percent-encode everything but RFC 3986 unreserved characters (and `/`
+/// when `keep_slash`, for object paths)
+pub fn url_encode(value: &str, keep_slash: bool) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            b'/' if keep_slash => "/".to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
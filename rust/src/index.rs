@@ -1,8 +1,29 @@
-use crate::types::Memory;
-use rusqlite::{params, Connection, Result};
+use crate::dates::now_rfc3339;
+use crate::store::{
+    deserialize_embedding, refuse_newer_schema, serialize_embedding, stamp_schema_version, Embedding, Store,
+    StoreError, StoreResult, VectorRow,
+};
+use crate::types::{
+    Attachment, Memory, MemoryFilter, MemoryUpdate, NewMemory, PruneCriteria, SessionSummary, SortOrder, TagMatch, TextMatch,
+    Tombstone,
+    QUARANTINE_NAMESPACE, SNIPPET_END, SNIPPET_START,
+};
+use rusqlite::types::Value;
+use rusqlite::{
+    params, params_from_iter, Connection, OpenFlags, OptionalExtension, Result, Row, Transaction,
+    TransactionBehavior,
+};
+use std::cell::Cell;
+use std::collections::HashMap;
 use std::path::Path;
+use std::time::{Duration, Instant};
 
-const SCHEMA: &str = r#"
+/// The metadata table alone, where the schema version is read from before
+/// anything else is created or migrated
+pub(crate) const METADATA_SCHEMA: &str =
+    "CREATE TABLE IF NOT EXISTS metadata (key TEXT PRIMARY KEY, value TEXT NOT NULL);";
+
+pub(crate) const SCHEMA: &str = r#"
 CREATE TABLE IF NOT EXISTS memories (
     id INTEGER PRIMARY KEY AUTOINCREMENT,
     content TEXT NOT NULL,
@@ -11,7 +32,17 @@ CREATE TABLE IF NOT EXISTS memories (
     created_at TEXT NOT NULL,
     updated_at TEXT NOT NULL,
     last_accessed_at TEXT,
-    access_count INTEGER DEFAULT 0
+    access_count INTEGER DEFAULT 0,
+    importance REAL DEFAULT 0,
+    kind TEXT,
+    expires_at TEXT,
+    source TEXT,
+    session_id TEXT,
+    namespace TEXT,
+    pinned INTEGER DEFAULT 0,
+    blob TEXT,
+    embedding_model TEXT,
+    embedding_hash TEXT
 );
 
 CREATE TABLE IF NOT EXISTS tags (
@@ -26,7 +57,24 @@ CREATE TABLE IF NOT EXISTS metadata (
     value TEXT NOT NULL
 );
 
+CREATE TABLE IF NOT EXISTS tombstones (
+    created_at TEXT PRIMARY KEY,
+    deleted_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS attachments (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    memory_id INTEGER NOT NULL,
+    path TEXT NOT NULL,
+    start_line INTEGER,
+    end_line INTEGER,
+    snippet TEXT,
+    created_at TEXT NOT NULL,
+    FOREIGN KEY (memory_id) REFERENCES memories(id) ON DELETE CASCADE
+);
+
 CREATE INDEX IF NOT EXISTS idx_tags_tag ON tags(tag);
+CREATE INDEX IF NOT EXISTS idx_attachments_memory ON attachments(memory_id);
 
 -- Full-text search (will error if already exists, that's ok)
 CREATE VIRTUAL TABLE IF NOT EXISTS memories_fts USING fts5(
@@ -50,278 +98,948 @@ CREATE TRIGGER IF NOT EXISTS memories_au AFTER UPDATE ON memories BEGIN
 END;
 "#;
 
+/// How long to wait for another process's write lock before failing
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Columns added after the initial schema, applied to older databases on open
+pub(crate) const COLUMN_MIGRATIONS: &[(&str, &str)] = &[
+    ("importance", "REAL DEFAULT 0"),
+    ("kind", "TEXT"),
+    ("expires_at", "TEXT"),
+    ("source", "TEXT"),
+    ("namespace", "TEXT"),
+    ("pinned", "INTEGER DEFAULT 0"),
+    ("blob", "TEXT"),
+    ("embedding_model", "TEXT"),
+    ("embedding_hash", "TEXT"),
+    ("session_id", "TEXT"),
+];
+
+/// Indexes on migrated columns, created once the columns exist
+pub(crate) const MIGRATED_INDEXES: &str = "CREATE INDEX IF NOT EXISTS idx_memories_embedding_hash ON memories(embedding_hash);
+     CREATE INDEX IF NOT EXISTS idx_memories_session ON memories(session_id);";
+
+/// Statements run once right after a column is added, to fill it in
+pub(crate) const COLUMN_BACKFILLS: &[(&str, &str)] = &[
+    // Before per-row tracking, every embedding came from the store-wide model
+    (
+        "embedding_model",
+        "UPDATE memories SET embedding_model = (SELECT value FROM metadata WHERE key = 'embedding_model') WHERE embedding IS NOT NULL",
+    ),
+];
+
+/// Condition over `memories` matching rows `reindex --missing-only` redoes,
+/// with the model as ?1 and the embedding size in bytes as ?2
+pub(crate) const STALE_EMBEDDING: &str =
+    "embedding IS NULL OR length(embedding) != ?2 OR embedding_model IS NOT ?1";
+
+/// Columns selected for a full memory row, in `memory_from_row` order
+pub(crate) const MEMORY_COLUMNS: &str = "m.id, m.content, m.confidence, m.created_at, m.updated_at, \
+     m.last_accessed_at, m.access_count, m.importance, m.kind, m.expires_at, m.source, m.namespace, \
+     m.pinned, m.blob, m.session_id";
+
+/// Number of columns in `MEMORY_COLUMNS`
+pub(crate) const MEMORY_COLUMN_COUNT: usize = 15;
+
+/// Columns selected for a `VectorRow`, in field order
+pub(crate) const VECTOR_COLUMNS: &str =
+    "m.id, m.confidence, m.updated_at, m.importance, m.access_count, m.last_accessed_at, m.embedding";
+
+/// Add any columns missing from an older `memories` table
+fn migrate(conn: &Connection) -> Result<()> {
+    let mut stmt = conn.prepare("PRAGMA table_info(memories)")?;
+    let existing: Vec<String> = stmt
+        .query_map([], |row| row.get(1))?
+        .collect::<Result<_>>()?;
+
+    for (name, decl) in COLUMN_MIGRATIONS {
+        if !existing.iter().any(|c| c == name) {
+            conn.execute_batch(&format!("ALTER TABLE memories ADD COLUMN {} {}", name, decl))?;
+            for (_, sql) in COLUMN_BACKFILLS.iter().filter(|(column, _)| column == name) {
+                conn.execute_batch(sql)?;
+            }
+            if *name == "embedding_hash" {
+                backfill_embedding_hashes(conn)?;
+            }
+        }
+    }
+    conn.execute_batch(MIGRATED_INDEXES)?;
+
+    Ok(())
+}
+
+/// Existing embeddings were all made from the row's current content
+fn backfill_embedding_hashes(conn: &Connection) -> Result<()> {
+    let mut stmt = conn.prepare("SELECT id, content FROM memories WHERE embedding IS NOT NULL")?;
+    let rows: Vec<(i64, String)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<_>>()?;
+    for (id, content) in rows {
+        conn.execute(
+            "UPDATE memories SET embedding_hash = ?1 WHERE id = ?2",
+            params![crate::blobs::hash(&content), id],
+        )?;
+    }
+    Ok(())
+}
+
+/// Build a `WHERE` clause over the `m` alias, with its positional parameters
+pub(crate) fn filter_clause(filter: &MemoryFilter) -> (String, Vec<Value>) {
+    let mut conditions = Vec::new();
+    let mut values = Vec::new();
+
+    if let Some(tag) = &filter.tag {
+        if filter.tag_prefix {
+            conditions.push("m.id IN (SELECT memory_id FROM tags WHERE substr(tag, 1, length(?)) = ?)".to_string());
+            values.push(Value::Text(tag.to_lowercase()));
+        } else {
+            conditions.push("m.id IN (SELECT memory_id FROM tags WHERE tag = ?)".to_string());
+        }
+        values.push(Value::Text(tag.to_lowercase()));
+    }
+
+    for tag in &filter.exclude_tags {
+        conditions.push("m.id NOT IN (SELECT memory_id FROM tags WHERE tag = ?)".to_string());
+        values.push(Value::Text(tag.to_lowercase()));
+    }
+
+    for text in &filter.exclude_text {
+        conditions.push("instr(lower(m.content), lower(?)) = 0".to_string());
+        values.push(Value::Text(text.clone()));
+    }
+
+    if let Some(kind) = &filter.kind {
+        conditions.push("m.kind = ?".to_string());
+        values.push(Value::Text(kind.to_lowercase()));
+    }
+
+    if let Some(session) = &filter.session {
+        conditions.push("m.session_id = ?".to_string());
+        values.push(Value::Text(session.clone()));
+    }
+
+    match &filter.namespace {
+        Some(ns) => {
+            conditions.push("m.namespace = ?".to_string());
+            values.push(Value::Text(ns.clone()));
+        }
+        None if !filter.include_quarantine => {
+            conditions.push("(m.namespace IS NULL OR m.namespace != ?)".to_string());
+            values.push(Value::Text(QUARANTINE_NAMESPACE.to_string()));
+        }
+        None => {}
+    }
+
+    // Compared as instants, not strings, so offsets and precision don't
+    // matter; an expiry SQLite can't parse is treated as never reached
+    if !filter.include_expired {
+        conditions.push("COALESCE(julianday(m.expires_at) > julianday(?), 1)".to_string());
+        values.push(Value::Text(now_rfc3339()));
+    }
+
+    if let Some(since) = &filter.active_since {
+        conditions.push("(m.pinned = 1 OR m.updated_at >= ? OR m.last_accessed_at >= ?)".to_string());
+        values.push(Value::Text(since.clone()));
+        values.push(Value::Text(since.clone()));
+    }
+
+    if let Some(after) = &filter.created_after {
+        conditions.push("m.created_at >= ?".to_string());
+        values.push(Value::Text(after.clone()));
+    }
+
+    if let Some(before) = &filter.created_before {
+        conditions.push("m.created_at < ?".to_string());
+        values.push(Value::Text(before.clone()));
+    }
+
+    // Created or updated within the window; updated_at never precedes
+    // created_at, so either bound alone is a single comparison in effect
+    if filter.since.is_some() || filter.until.is_some() {
+        let mut window = Vec::new();
+        for column in ["m.created_at", "m.updated_at"] {
+            let mut bounds = Vec::new();
+            if let Some(since) = &filter.since {
+                bounds.push(format!("{} >= ?", column));
+                values.push(Value::Text(since.clone()));
+            }
+            if let Some(until) = &filter.until {
+                bounds.push(format!("{} < ?", column));
+                values.push(Value::Text(until.clone()));
+            }
+            window.push(format!("({})", bounds.join(" AND ")));
+        }
+        conditions.push(format!("({})", window.join(" OR ")));
+    }
+
+    for (op, value) in &filter.confidence {
+        conditions.push(format!("m.confidence {} ?", op.sql()));
+        values.push(Value::Real(*value));
+    }
+
+    if conditions.is_empty() {
+        (String::new(), values)
+    } else {
+        (format!("WHERE {}", conditions.join(" AND ")), values)
+    }
+}
+
+/// Words of context `search_fts` keeps around the matches
+const SNIPPET_TOKENS: usize = 16;
+
+/// Build the `search_fts` query: `MEMORY_COLUMNS`, then the BM25 score and
+/// the snippet, with its positional parameters
+pub(crate) fn fts_query(query: &str, filter: &MemoryFilter, limit: usize) -> (String, Vec<Value>) {
+    let (where_clause, mut values) = filter_clause(filter);
+    let sql = format!(
+        "SELECT {}, -bm25(memories_fts),
+                snippet(memories_fts, 0, char({}), char({}), '...', {})
+         FROM memories m
+         JOIN memories_fts ON m.id = memories_fts.rowid
+         {} {} memories_fts MATCH ?
+         ORDER BY bm25(memories_fts)
+         LIMIT ?",
+        MEMORY_COLUMNS,
+        SNIPPET_START as u32,
+        SNIPPET_END as u32,
+        SNIPPET_TOKENS,
+        where_clause,
+        if where_clause.is_empty() { "WHERE" } else { "AND" }
+    );
+    values.push(Value::Text(query.to_string()));
+    values.push(Value::Integer(limit as i64));
+    (sql, values)
+}
+
+/// Build the condition for `get_prunable`, with its positional parameters
+pub(crate) fn prune_clause(criteria: &PruneCriteria) -> (String, Vec<Value>) {
+    let mut clauses = Vec::new();
+    let mut values: Vec<Value> = Vec::new();
+
+    if criteria.expired {
+        clauses.push("julianday(m.expires_at) <= julianday(?)");
+        values.push(Value::Text(now_rfc3339()));
+    }
+    if let Some(confidence) = criteria.below_confidence {
+        clauses.push("m.confidence < ?");
+        values.push(Value::Real(confidence));
+    }
+    if let Some(before) = &criteria.unaccessed_before {
+        clauses.push("COALESCE(m.last_accessed_at, m.created_at) <= ?");
+        values.push(Value::Text(before.clone()));
+    }
+
+    if clauses.is_empty() {
+        ("0".to_string(), values)
+    } else {
+        (clauses.join(" AND "), values)
+    }
+}
+
+/// Resolve a tag filter against the `known` tags and their counts: the tag
+/// itself, else the tags it starts, else the nearest tag within a couple of
+/// edits (the most used on ties) to suggest
+pub fn match_tag(tag: &str, known: &[(String, usize)]) -> TagMatch {
+    let tag = tag.to_lowercase();
+    if known.iter().any(|(t, _)| *t == tag) {
+        return TagMatch::Exact;
+    }
+
+    let prefixed: Vec<String> = known
+        .iter()
+        .filter(|(t, _)| t.starts_with(&tag))
+        .map(|(t, _)| t.clone())
+        .collect();
+    if !prefixed.is_empty() {
+        return TagMatch::Prefix(prefixed);
+    }
+
+    // Short tags are a couple of edits away from almost anything
+    let max_edits = (tag.chars().count() / 3).min(2);
+    let closest = known
+        .iter()
+        .map(|(t, count)| (edit_distance(&tag, t), std::cmp::Reverse(*count), t))
+        .filter(|(distance, _, _)| *distance <= max_edits)
+        .min()
+        .map(|(_, _, t)| t.clone());
+    TagMatch::Missing(closest)
+}
+
+/// Levenshtein distance counting a swap of adjacent characters as one edit
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![(0..=b.len()).collect::<Vec<usize>>()];
+    for i in 1..=a.len() {
+        let mut row = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            row[j] = (rows[i - 1][j] + 1).min(row[j - 1] + 1).min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                row[j] = row[j].min(rows[i - 2][j - 2] + 1);
+            }
+        }
+        rows.push(row);
+    }
+    rows[a.len()][b.len()]
+}
+
+/// Insert a memory and its tags within `tx`, returns the ID
+fn insert_memory(tx: &Transaction, memory: &NewMemory, embedding: Option<&Embedding>) -> Result<i64> {
+    let now = chrono::Utc::now().to_rfc3339();
+    let embedding_bytes = embedding.map(|e| serialize_embedding(e.vector));
+    let embedding_model = embedding.map(|e| e.model);
+    let embedding_hash = embedding.map(|e| &e.text_hash);
+    let kind = memory.kind.as_ref().map(|k| k.to_lowercase());
+    let created_at = memory.created_at.as_deref().unwrap_or(&now);
+
+    tx.execute(
+        "INSERT INTO memories (content, confidence, embedding, created_at, updated_at, kind, expires_at, source, namespace, pinned, blob, embedding_model, embedding_hash, session_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+        params![memory.content, memory.confidence, embedding_bytes, created_at, now, kind, memory.expires_at, memory.source, memory.namespace, memory.pinned, memory.blob, embedding_model, embedding_hash, memory.session_id],
+    )?;
+
+    let id = tx.last_insert_rowid();
+
+    // Add tags
+    for tag in &memory.tags {
+        tx.execute(
+            "INSERT OR IGNORE INTO tags (memory_id, tag) VALUES (?1, ?2)",
+            params![id, tag.to_lowercase()],
+        )?;
+    }
+
+    Ok(id)
+}
+
 /// Memory store backed by SQLite
 pub struct MemoryStore {
     conn: Connection,
+    /// Number of tag lookups since the last `take_tag_stats`
+    tag_queries: Cell<usize>,
+    /// Time spent in tag lookups since the last `take_tag_stats`
+    tag_time: Cell<Duration>,
 }
 
 impl MemoryStore {
     /// Open or create the memory database
-    pub fn open(db_path: &Path) -> Result<Self> {
+    pub fn open(db_path: &Path) -> StoreResult<Self> {
         let conn = Connection::open(db_path)?;
-        conn.execute_batch(SCHEMA)?;
-        Ok(Self { conn })
+        // Hooks, the CLI, and agents share the file: readers never block the
+        // writer, and a writer waits its turn instead of failing
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        Self::from_connection(conn).prepared()
+    }
+
+    /// Open an existing database for reading only, without creating or
+    /// migrating anything, for shell completion on every Tab
+    pub fn open_read_only(db_path: &Path) -> StoreResult<Self> {
+        let conn = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        let store = Self::from_connection(conn);
+        refuse_newer_schema(&store)?;
+        Ok(store)
     }
 
     /// Open an in-memory database (for testing)
     #[allow(dead_code)]
-    pub fn in_memory() -> Result<Self> {
-        let conn = Connection::open_in_memory()?;
-        conn.execute_batch(SCHEMA)?;
-        Ok(Self { conn })
+    pub fn in_memory() -> StoreResult<Self> {
+        Self::from_connection(Connection::open_in_memory()?).prepared()
+    }
+
+    /// Bring the schema up to date, unless a newer build wrote it
+    fn prepared(self) -> StoreResult<Self> {
+        self.conn.execute_batch(METADATA_SCHEMA)?;
+        refuse_newer_schema(&self)?;
+        self.conn.execute_batch(SCHEMA)?;
+        migrate(&self.conn)?;
+        stamp_schema_version(&self)?;
+        Ok(self)
+    }
+
+    /// Check that a file is an intact memory database, returns its memory count
+    pub fn check_snapshot(path: &Path) -> StoreResult<usize> {
+        let conn = Connection::open(path)?;
+        let integrity: String = conn.query_row("PRAGMA integrity_check", [], |row| row.get(0))?;
+        if integrity != "ok" {
+            return Err(StoreError::Corrupt(integrity));
+        }
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM memories", [], |row| row.get(0))?;
+        Ok(count as usize)
     }
 
     // -------------------------------------------------------------------------
-    // Embedding serialization
+    // Diagnostics (roots doctor)
     // -------------------------------------------------------------------------
 
-    fn serialize_embedding(embedding: &[f32]) -> Vec<u8> {
-        embedding.iter().flat_map(|f| f.to_le_bytes()).collect()
+    /// Result of `PRAGMA integrity_check`, "ok" when the file is sound
+    pub fn integrity_check(&self) -> StoreResult<String> {
+        Ok(self.conn.query_row("PRAGMA integrity_check", [], |row| row.get(0))?)
     }
 
-    fn deserialize_embedding(data: &[u8]) -> Vec<f32> {
-        data.chunks_exact(4)
-            .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
-            .collect()
+    /// Whether the full-text index matches the memories table
+    pub fn fts_consistent(&self) -> StoreResult<bool> {
+        match self.conn.execute(
+            "INSERT INTO memories_fts(memories_fts, rank) VALUES('integrity-check', 1)",
+            [],
+        ) {
+            Ok(_) => Ok(true),
+            Err(rusqlite::Error::SqliteFailure(e, _))
+                if e.code == rusqlite::ErrorCode::DatabaseCorrupt =>
+            {
+                Ok(false)
+            }
+            Err(e) => Err(e.into()),
+        }
     }
 
-    // -------------------------------------------------------------------------
-    // Memory operations
-    // -------------------------------------------------------------------------
+    /// Rebuild the full-text index from the memories table
+    pub fn rebuild_fts(&self) -> StoreResult<()> {
+        self.conn
+            .execute("INSERT INTO memories_fts(memories_fts) VALUES('rebuild')", [])?;
+        Ok(())
+    }
 
-    /// Add a new memory, returns the ID
-    pub fn add(&self, content: &str, confidence: f64, embedding: &[f32], tags: &[String]) -> Result<i64> {
-        let now = chrono::Utc::now().to_rfc3339();
-        let embedding_bytes = Self::serialize_embedding(embedding);
+    /// Rebuild the search index, reclaim free pages, and refresh planner
+    /// statistics (roots optimize)
+    pub fn optimize(&self) -> StoreResult<()> {
+        self.rebuild_fts()?;
+        self.conn.execute_batch("PRAGMA optimize; VACUUM;")?;
+        // Fold the WAL back in, so the file sizes reflect the result
+        self.conn
+            .query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+        Ok(())
+    }
 
-        self.conn.execute(
-            "INSERT INTO memories (content, confidence, embedding, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5)",
-            params![content, confidence, embedding_bytes, now, now],
+    /// Count of memories per embedding size in bytes (`None` for queued)
+    pub fn embedding_sizes(&self) -> StoreResult<Vec<(Option<usize>, usize)>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT length(embedding), COUNT(*) FROM memories GROUP BY 1 ORDER BY 2 DESC")?;
+        let sizes = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, Option<i64>>(0)?.map(|n| n as usize), row.get::<_, i64>(1)? as usize))
+            })?
+            .collect::<Result<_>>()?;
+        Ok(sizes)
+    }
+
+    /// Count of tag rows whose memory no longer exists
+    pub fn orphaned_tags(&self) -> StoreResult<usize> {
+        let count: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM tags WHERE memory_id NOT IN (SELECT id FROM memories)",
+            [],
+            |row| row.get(0),
         )?;
+        Ok(count as usize)
+    }
 
-        let id = self.conn.last_insert_rowid();
+    /// Delete tag rows whose memory no longer exists
+    pub fn delete_orphaned_tags(&self) -> StoreResult<usize> {
+        Ok(self.conn.execute(
+            "DELETE FROM tags WHERE memory_id NOT IN (SELECT id FROM memories)",
+            [],
+        )?)
+    }
 
-        // Add tags
-        for tag in tags {
-            self.conn.execute(
-                "INSERT OR IGNORE INTO tags (memory_id, tag) VALUES (?1, ?2)",
-                params![id, tag.to_lowercase()],
-            )?;
+    /// Start a transaction holding the write lock from the outset, so the
+    /// busy timeout applies instead of failing on upgrade
+    fn write_transaction(&self) -> Result<Transaction<'_>> {
+        Transaction::new_unchecked(&self.conn, TransactionBehavior::Immediate)
+    }
+
+    fn from_connection(conn: Connection) -> Self {
+        Self {
+            conn,
+            tag_queries: Cell::new(0),
+            tag_time: Cell::new(Duration::ZERO),
         }
+    }
 
-        Ok(id)
+    // -------------------------------------------------------------------------
+    // Memory operations
+    // -------------------------------------------------------------------------
+
+    /// Add a new memory with just content and tags, returns the ID. The
+    /// embedding is recorded as made by a model named "test".
+    #[allow(dead_code)]
+    pub fn add(&self, content: &str, confidence: f64, embedding: &[f32], tags: &[String]) -> StoreResult<i64> {
+        let memory = NewMemory {
+            content: content.to_string(),
+            confidence,
+            tags: tags.to_vec(),
+            ..Default::default()
+        };
+        self.insert(&memory, Some(&Embedding::new(embedding, "test", content)))
     }
 
-    /// Get a memory by ID
-    pub fn get(&self, id: i64) -> Result<Option<Memory>> {
+    /// Build a memory from a row selected with `MEMORY_COLUMNS`, without
+    /// its tags (see `attach_tags`)
+    fn memory_from_row(row: &Row) -> Result<Memory> {
+        Ok(Memory {
+            id: row.get(0)?,
+            content: row.get(1)?,
+            confidence: row.get(2)?,
+            tags: Vec::new(),
+            created_at: row.get(3)?,
+            updated_at: row.get(4)?,
+            last_accessed_at: row.get(5)?,
+            access_count: row.get(6)?,
+            importance: row.get::<_, Option<f64>>(7)?.unwrap_or(0.0),
+            kind: row.get(8)?,
+            expires_at: row.get(9)?,
+            source: row.get(10)?,
+            namespace: row.get(11)?,
+            pinned: row.get::<_, Option<bool>>(12)?.unwrap_or(false),
+            blob: row.get(13)?,
+            session_id: row.get(14)?,
+            global: false,
+        })
+    }
+
+    /// Get memories by tag, or by the tags it starts when no memory has the
+    /// tag itself; the match says which, or suggests a close tag
+    #[allow(dead_code)]
+    pub fn get_by_tag(&self, tag: &str, limit: usize, order: SortOrder) -> StoreResult<(Vec<Memory>, TagMatch)> {
+        let tag_match = match_tag(tag, &self.get_all_tags()?);
+        let filter = MemoryFilter {
+            tag: Some(tag.to_string()),
+            tag_prefix: matches!(tag_match, TagMatch::Prefix(_)),
+            ..Default::default()
+        };
+        let memories = match tag_match {
+            TagMatch::Missing(_) => Vec::new(),
+            _ => self.list(&filter, limit, order)?,
+        };
+        Ok((memories, tag_match))
+    }
+
+    /// Tags of each of these memories, in one query
+    pub fn get_tags_for(&self, ids: &[i64]) -> Result<HashMap<i64, Vec<String>>> {
+        let mut tags: HashMap<i64, Vec<String>> = HashMap::new();
+        if ids.is_empty() {
+            return Ok(tags);
+        }
+
+        let started = Instant::now();
         let mut stmt = self.conn.prepare(
-            "SELECT id, content, confidence, created_at, updated_at, last_accessed_at, access_count FROM memories WHERE id = ?1"
+            "SELECT memory_id, tag FROM tags WHERE memory_id IN (SELECT value FROM json_each(?1)) ORDER BY rowid",
         )?;
+        let mut rows = stmt.query(params![serde_json::to_string(ids).unwrap_or_default()])?;
+        while let Some(row) = rows.next()? {
+            tags.entry(row.get(0)?).or_default().push(row.get(1)?);
+        }
+        self.tag_queries.set(self.tag_queries.get() + 1);
+        self.tag_time.set(self.tag_time.get() + started.elapsed());
 
-        let mut rows = stmt.query(params![id])?;
+        Ok(tags)
+    }
 
-        if let Some(row) = rows.next()? {
-            let memory_id: i64 = row.get(0)?;
-            let tags = self.get_tags(memory_id)?;
-
-            Ok(Some(Memory {
-                id: memory_id,
-                content: row.get(1)?,
-                confidence: row.get(2)?,
-                tags,
-                created_at: row.get(3)?,
-                updated_at: row.get(4)?,
-                last_accessed_at: row.get(5)?,
-                access_count: row.get(6)?,
-            }))
-        } else {
-            Ok(None)
+    /// Fill in the tags of memories built by `memory_from_row`
+    fn attach_tags(&self, memories: &mut [Memory]) -> Result<()> {
+        let ids: Vec<i64> = memories.iter().map(|m| m.id).collect();
+        let mut tags = self.get_tags_for(&ids)?;
+        for memory in memories {
+            memory.tags = tags.remove(&memory.id).unwrap_or_default();
         }
+        Ok(())
     }
 
-    /// Get all memories with their embeddings (for vector search)
-    pub fn get_all_with_embeddings(&self) -> Result<Vec<(Memory, Vec<f32>)>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT id, content, confidence, embedding, created_at, updated_at, last_accessed_at, access_count FROM memories"
-        )?;
+    /// Memories from a query selecting `MEMORY_COLUMNS`, with their tags
+    fn select_memories(&self, sql: &str, params: impl rusqlite::Params) -> Result<Vec<Memory>> {
+        let mut stmt = self.conn.prepare(sql)?;
+        let mut memories = stmt
+            .query_map(params, Self::memory_from_row)?
+            .collect::<Result<Vec<_>>>()?;
+        self.attach_tags(&mut memories)?;
+        Ok(memories)
+    }
+}
 
-        let mut results = Vec::new();
-        let mut rows = stmt.query([])?;
+impl Store for MemoryStore {
+    fn snapshot(&self, dest: &Path) -> StoreResult<()> {
+        self.conn
+            .execute("VACUUM INTO ?1", params![dest.to_string_lossy()])?;
+        Ok(())
+    }
 
-        while let Some(row) = rows.next()? {
-            let memory_id: i64 = row.get(0)?;
-            let embedding_bytes: Vec<u8> = row.get(3)?;
-            let tags = self.get_tags(memory_id)?;
-
-            let memory = Memory {
-                id: memory_id,
-                content: row.get(1)?,
-                confidence: row.get(2)?,
-                tags,
-                created_at: row.get(4)?,
-                updated_at: row.get(5)?,
-                last_accessed_at: row.get(6)?,
-                access_count: row.get(7)?,
-            };
+    /// Tag lookup count and time since the last call, resetting both
+    fn take_tag_stats(&self) -> (usize, Duration) {
+        (self.tag_queries.replace(0), self.tag_time.replace(Duration::ZERO))
+    }
 
-            results.push((memory, Self::deserialize_embedding(&embedding_bytes)));
-        }
+    /// Insert a new memory, returns the ID
+    fn insert(&self, memory: &NewMemory, embedding: Option<&Embedding>) -> StoreResult<i64> {
+        let tx = self.write_transaction()?;
+        let id = insert_memory(&tx, memory, embedding)?;
+        tx.commit()?;
+        Ok(id)
+    }
 
-        Ok(results)
+    /// Insert several memories in one transaction, returns their IDs
+    fn insert_batch(&self, memories: &[(NewMemory, Option<Embedding>)]) -> StoreResult<Vec<i64>> {
+        let tx = self.write_transaction()?;
+        let ids = memories
+            .iter()
+            .map(|(memory, embedding)| insert_memory(&tx, memory, embedding.as_ref()))
+            .collect::<Result<Vec<_>>>()?;
+        tx.commit()?;
+        Ok(ids)
     }
 
-    /// Full-text search
-    #[allow(dead_code)]
-    pub fn search_fts(&self, query: &str, limit: usize) -> Result<Vec<Memory>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT m.id, m.content, m.confidence, m.created_at, m.updated_at, m.last_accessed_at, m.access_count
-             FROM memories m
-             JOIN memories_fts fts ON m.id = fts.rowid
-             WHERE memories_fts MATCH ?1
-             LIMIT ?2"
+    /// Put a deleted memory back as it was
+    fn restore(&self, memory: &Memory, embedding: Option<&Embedding>) -> StoreResult<()> {
+        let tx = self.write_transaction()?;
+        tx.execute(
+            "INSERT INTO memories (id, content, confidence, embedding, created_at, updated_at, last_accessed_at, access_count, importance, kind, expires_at, source, namespace, pinned, blob, embedding_model, embedding_hash, session_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)",
+            params![
+                memory.id,
+                memory.content,
+                memory.confidence,
+                embedding.map(|e| serialize_embedding(e.vector)),
+                memory.created_at,
+                memory.updated_at,
+                memory.last_accessed_at,
+                memory.access_count,
+                memory.importance,
+                memory.kind,
+                memory.expires_at,
+                memory.source,
+                memory.namespace,
+                memory.pinned,
+                memory.blob,
+                embedding.map(|e| e.model),
+                embedding.map(|e| &e.text_hash),
+                memory.session_id
+            ],
         )?;
+        for tag in &memory.tags {
+            tx.execute(
+                "INSERT OR IGNORE INTO tags (memory_id, tag) VALUES (?1, ?2)",
+                params![memory.id, tag],
+            )?;
+        }
+        tx.execute("DELETE FROM tombstones WHERE created_at = ?1", params![memory.created_at])?;
+        tx.commit()?;
+        Ok(())
+    }
 
-        let mut results = Vec::new();
-        let mut rows = stmt.query(params![query, limit as i64])?;
+    /// Get a memory by ID
+    fn get(&self, id: i64) -> StoreResult<Option<Memory>> {
+        let sql = format!("SELECT {} FROM memories m WHERE m.id = ?1", MEMORY_COLUMNS);
+        Ok(self.select_memories(&sql, params![id])?.pop())
+    }
+
+    /// Get all memories matching a filter with their embeddings (for vector search)
+    fn get_all_with_embeddings(&self, filter: &MemoryFilter) -> StoreResult<Vec<(Memory, Vec<f32>)>> {
+        let (where_clause, values) = filter_clause(filter);
+        let sql = format!(
+            "SELECT {}, m.embedding FROM memories m {} {} m.embedding IS NOT NULL",
+            MEMORY_COLUMNS,
+            where_clause,
+            if where_clause.is_empty() { "WHERE" } else { "AND" }
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+
+        let mut memories = Vec::new();
+        let mut embeddings = Vec::new();
+        let mut rows = stmt.query(params_from_iter(values))?;
 
         while let Some(row) = rows.next()? {
-            let memory_id: i64 = row.get(0)?;
-            let tags = self.get_tags(memory_id)?;
-
-            results.push(Memory {
-                id: memory_id,
-                content: row.get(1)?,
-                confidence: row.get(2)?,
-                tags,
-                created_at: row.get(3)?,
-                updated_at: row.get(4)?,
-                last_accessed_at: row.get(5)?,
-                access_count: row.get(6)?,
-            });
+            let embedding_bytes: Vec<u8> = row.get(MEMORY_COLUMN_COUNT)?;
+            memories.push(Self::memory_from_row(row)?);
+            embeddings.push(deserialize_embedding(&embedding_bytes));
         }
+        self.attach_tags(&mut memories)?;
 
-        Ok(results)
+        Ok(memories.into_iter().zip(embeddings).collect())
     }
 
-    /// Get memories by tag
-    pub fn get_by_tag(&self, tag: &str, limit: usize) -> Result<Vec<Memory>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT m.id, m.content, m.confidence, m.created_at, m.updated_at, m.last_accessed_at, m.access_count
-             FROM memories m
-             JOIN tags t ON m.id = t.memory_id
-             WHERE t.tag = ?1
-             ORDER BY m.updated_at DESC
-             LIMIT ?2"
-        )?;
+    fn get_many(&self, ids: &[i64]) -> StoreResult<Vec<Memory>> {
+        let sql = format!(
+            "SELECT {} FROM memories m WHERE m.id IN (SELECT value FROM json_each(?1))",
+            MEMORY_COLUMNS
+        );
+        let mut found: HashMap<i64, Memory> = self
+            .select_memories(&sql, params![serde_json::to_string(ids).unwrap_or_default()])?
+            .into_iter()
+            .map(|m| (m.id, m))
+            .collect();
+        Ok(ids.iter().filter_map(|id| found.remove(id)).collect())
+    }
 
-        let mut results = Vec::new();
-        let mut rows = stmt.query(params![tag.to_lowercase(), limit as i64])?;
+    fn scan_vectors(
+        &self,
+        filter: &MemoryFilter,
+        visit: &mut dyn FnMut(VectorRow) -> bool,
+    ) -> StoreResult<()> {
+        let (where_clause, values) = filter_clause(filter);
+        let sql = format!(
+            "SELECT {} FROM memories m {} {} m.embedding IS NOT NULL",
+            VECTOR_COLUMNS,
+            where_clause,
+            if where_clause.is_empty() { "WHERE" } else { "AND" }
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let mut rows = stmt.query(params_from_iter(values))?;
 
         while let Some(row) = rows.next()? {
-            let memory_id: i64 = row.get(0)?;
-            let tags = self.get_tags(memory_id)?;
-
-            results.push(Memory {
-                id: memory_id,
-                content: row.get(1)?,
-                confidence: row.get(2)?,
-                tags,
-                created_at: row.get(3)?,
-                updated_at: row.get(4)?,
+            let embedding_bytes: Vec<u8> = row.get(6)?;
+            let vector = VectorRow {
+                id: row.get(0)?,
+                confidence: row.get(1)?,
+                updated_at: row.get(2)?,
+                importance: row.get::<_, Option<f64>>(3)?.unwrap_or(0.0),
+                access_count: row.get(4)?,
                 last_accessed_at: row.get(5)?,
-                access_count: row.get(6)?,
-            });
+                embedding: deserialize_embedding(&embedding_bytes),
+            };
+            if !visit(vector) {
+                break;
+            }
         }
-
-        Ok(results)
+        Ok(())
     }
 
-    /// List recent memories
-    pub fn list(&self, limit: usize) -> Result<Vec<Memory>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT id, content, confidence, created_at, updated_at, last_accessed_at, access_count
-             FROM memories
-             ORDER BY updated_at DESC
-             LIMIT ?1"
-        )?;
-
-        let mut results = Vec::new();
-        let mut rows = stmt.query(params![limit as i64])?;
+    fn search_fts(&self, query: &str, filter: &MemoryFilter, limit: usize) -> StoreResult<Vec<TextMatch>> {
+        let (sql, values) = fts_query(query, filter, limit);
+        let mut stmt = self.conn.prepare(&sql)?;
+        let mut memories = Vec::new();
+        let mut extras = Vec::new();
+        let mut rows = stmt.query(params_from_iter(values))?;
 
         while let Some(row) = rows.next()? {
-            let memory_id: i64 = row.get(0)?;
-            let tags = self.get_tags(memory_id)?;
-
-            results.push(Memory {
-                id: memory_id,
-                content: row.get(1)?,
-                confidence: row.get(2)?,
-                tags,
-                created_at: row.get(3)?,
-                updated_at: row.get(4)?,
-                last_accessed_at: row.get(5)?,
-                access_count: row.get(6)?,
-            });
+            memories.push(Self::memory_from_row(row)?);
+            extras.push((row.get(MEMORY_COLUMN_COUNT)?, row.get(MEMORY_COLUMN_COUNT + 1)?));
         }
+        self.attach_tags(&mut memories)?;
 
-        Ok(results)
+        Ok(memories
+            .into_iter()
+            .zip(extras)
+            .map(|(memory, (score, snippet))| TextMatch { memory, score, snippet })
+            .collect())
+    }
+
+    /// List memories matching a filter in the given order
+    fn list(&self, filter: &MemoryFilter, limit: usize, order: SortOrder) -> StoreResult<Vec<Memory>> {
+        let (where_clause, mut values) = filter_clause(filter);
+        let sql = format!(
+            "SELECT {}
+             FROM memories m
+             {}
+             ORDER BY {}
+             LIMIT ?",
+            MEMORY_COLUMNS,
+            where_clause,
+            order.sql()
+        );
+        values.push(Value::Integer(limit as i64));
+        Ok(self.select_memories(&sql, params_from_iter(values))?)
     }
 
     /// Update a memory
-    pub fn update(&self, id: i64, confidence: Option<f64>, tags: Option<&[String]>) -> Result<bool> {
+    fn update(&self, id: i64, changes: &MemoryUpdate) -> StoreResult<bool> {
         let now = chrono::Utc::now().to_rfc3339();
+        let tx = self.write_transaction()?;
+
+        if let Some(content) = &changes.content {
+            tx.execute(
+                "UPDATE memories SET content = ?1, updated_at = ?2 WHERE id = ?3",
+                params![content, now, id],
+            )?;
+        }
 
-        if let Some(conf) = confidence {
-            self.conn.execute(
+        if let Some(blob) = &changes.blob {
+            let blob = if blob.is_empty() { None } else { Some(blob.as_str()) };
+            tx.execute("UPDATE memories SET blob = ?1 WHERE id = ?2", params![blob, id])?;
+        }
+
+        if let Some(k) = &changes.kind {
+            // An empty kind clears it
+            let k = if k.is_empty() { None } else { Some(k.to_lowercase()) };
+            tx.execute(
+                "UPDATE memories SET kind = ?1, updated_at = ?2 WHERE id = ?3",
+                params![k, now, id],
+            )?;
+        }
+
+        if let Some(ns) = &changes.namespace {
+            // An empty namespace moves it back to the default
+            let ns = if ns.is_empty() { None } else { Some(ns.as_str()) };
+            tx.execute(
+                "UPDATE memories SET namespace = ?1, updated_at = ?2 WHERE id = ?3",
+                params![ns, now, id],
+            )?;
+        }
+
+        if let Some(pinned) = changes.pinned {
+            tx.execute(
+                "UPDATE memories SET pinned = ?1, updated_at = ?2 WHERE id = ?3",
+                params![pinned, now, id],
+            )?;
+        }
+
+        if let Some(conf) = changes.confidence {
+            tx.execute(
                 "UPDATE memories SET confidence = ?1, updated_at = ?2 WHERE id = ?3",
                 params![conf, now, id],
             )?;
         }
 
-        if let Some(new_tags) = tags {
+        if let Some(new_tags) = &changes.tags {
             // Replace all tags
-            self.conn.execute("DELETE FROM tags WHERE memory_id = ?1", params![id])?;
+            tx.execute("DELETE FROM tags WHERE memory_id = ?1", params![id])?;
             for tag in new_tags {
-                self.conn.execute(
+                tx.execute(
                     "INSERT OR IGNORE INTO tags (memory_id, tag) VALUES (?1, ?2)",
                     params![id, tag.to_lowercase()],
                 )?;
             }
-            self.conn.execute(
+            tx.execute(
                 "UPDATE memories SET updated_at = ?1 WHERE id = ?2",
                 params![now, id],
             )?;
         }
 
+        tx.commit()?;
         Ok(true)
     }
 
-    /// Record an access to a memory
-    #[allow(dead_code)]
-    pub fn record_access(&self, id: i64) -> Result<()> {
+    /// Record an access to each of the given memories
+    fn record_access(&self, ids: &[i64]) -> StoreResult<()> {
         let now = chrono::Utc::now().to_rfc3339();
-        self.conn.execute(
-            "UPDATE memories SET last_accessed_at = ?1, access_count = access_count + 1 WHERE id = ?2",
-            params![now, id],
-        )?;
+        let tx = self.write_transaction()?;
+        for id in ids {
+            tx.execute(
+                "UPDATE memories SET last_accessed_at = ?1, access_count = access_count + 1 WHERE id = ?2",
+                params![now, id],
+            )?;
+        }
+        tx.commit()?;
         Ok(())
     }
 
-    /// Delete a memory
-    pub fn delete(&self, id: i64) -> Result<bool> {
+    /// Store computed importance scores
+    fn set_importance(&self, scores: &[(i64, f64)]) -> StoreResult<()> {
+        let tx = self.write_transaction()?;
+        for (id, score) in scores {
+            tx.execute(
+                "UPDATE memories SET importance = ?1 WHERE id = ?2",
+                params![score, id],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Check whether any memory was imported from the given source
+    fn has_source(&self, source: &str) -> StoreResult<bool> {
+        let count: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM memories WHERE source = ?1",
+            params![source],
+            |row| row.get(0),
+        )?;
+        Ok(count > 0)
+    }
+
+    /// Get the memory created at exactly this timestamp (identity across exports)
+    fn get_by_created_at(&self, created_at: &str) -> StoreResult<Option<Memory>> {
+        let sql = format!("SELECT {} FROM memories m WHERE m.created_at = ?1", MEMORY_COLUMNS);
+        Ok(self.select_memories(&sql, params![created_at])?.pop())
+    }
+
+    fn get_by_text_hash(&self, text_hash: &str) -> StoreResult<Vec<Memory>> {
+        let sql = format!("SELECT {} FROM memories m WHERE m.embedding_hash = ?1 ORDER BY m.id", MEMORY_COLUMNS);
+        Ok(self.select_memories(&sql, params![text_hash])?)
+    }
+
+    /// Get memories matching every set prune criterion (none set matches nothing)
+    fn get_prunable(&self, criteria: &PruneCriteria) -> StoreResult<Vec<Memory>> {
+        let (where_clause, values) = prune_clause(criteria);
+        let sql = format!(
+            "SELECT {} FROM memories m WHERE {} ORDER BY m.id",
+            MEMORY_COLUMNS, where_clause
+        );
+        Ok(self.select_memories(&sql, params_from_iter(values))?)
+    }
+
+    /// Delete a memory, leaving a tombstone
+    fn delete(&self, id: i64) -> StoreResult<bool> {
+        let tx = self.write_transaction()?;
+        tx.execute(
+            "INSERT OR REPLACE INTO tombstones (created_at, deleted_at) SELECT created_at, ?1 FROM memories WHERE id = ?2",
+            params![now_rfc3339(), id],
+        )?;
         // Tags will be deleted via ON DELETE CASCADE
-        let count = self.conn.execute("DELETE FROM memories WHERE id = ?1", params![id])?;
+        let count = tx.execute("DELETE FROM memories WHERE id = ?1", params![id])?;
+        tx.commit()?;
         Ok(count > 0)
     }
 
+    /// Record a deletion that happened elsewhere
+    fn add_tombstone(&self, tombstone: &Tombstone) -> StoreResult<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO tombstones (created_at, deleted_at) VALUES (?1, ?2)",
+            params![tombstone.created_at, tombstone.deleted_at],
+        )?;
+        Ok(())
+    }
+
+    /// Tombstone for the memory created at this timestamp, if it was deleted
+    fn get_tombstone(&self, created_at: &str) -> StoreResult<Option<Tombstone>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT created_at, deleted_at FROM tombstones WHERE created_at = ?1")?;
+        let mut rows = stmt.query(params![created_at])?;
+
+        match rows.next()? {
+            Some(row) => Ok(Some(Tombstone {
+                created_at: row.get(0)?,
+                deleted_at: row.get(1)?,
+            })),
+            None => Ok(None),
+        }
+    }
+
+    /// All tombstones, oldest deletion first
+    fn get_tombstones(&self) -> StoreResult<Vec<Tombstone>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT created_at, deleted_at FROM tombstones ORDER BY deleted_at")?;
+        let rows = stmt.query_map([], |row| {
+            Ok(Tombstone {
+                created_at: row.get(0)?,
+                deleted_at: row.get(1)?,
+            })
+        })?;
+        Ok(rows.collect::<Result<_>>()?)
+    }
+
+    /// Drop tombstones for deletions before `before`, returns how many
+    fn purge_tombstones(&self, before: &str) -> StoreResult<usize> {
+        Ok(self
+            .conn
+            .execute("DELETE FROM tombstones WHERE deleted_at < ?1", params![before])?)
+    }
+
     /// Get count of memories
-    pub fn count(&self) -> Result<usize> {
+    fn count(&self) -> StoreResult<usize> {
         let count: i64 = self.conn.query_row("SELECT COUNT(*) FROM memories", [], |row| row.get(0))?;
         Ok(count as usize)
     }
 
+    fn count_blob_refs(&self, hash: &str) -> StoreResult<usize> {
+        let count: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM memories WHERE blob = ?1",
+            params![hash],
+            |row| row.get(0),
+        )?;
+        Ok(count as usize)
+    }
+
     /// Get all unique tags
-    pub fn get_all_tags(&self) -> Result<Vec<(String, usize)>> {
+    fn get_all_tags(&self) -> StoreResult<Vec<(String, usize)>> {
         let mut stmt = self.conn.prepare(
             "SELECT tag, COUNT(*) as count FROM tags GROUP BY tag ORDER BY count DESC"
         )?;
@@ -336,25 +1054,96 @@ impl MemoryStore {
         Ok(results)
     }
 
-    // Helper to get tags for a memory
-    fn get_tags(&self, memory_id: i64) -> Result<Vec<String>> {
-        let mut stmt = self.conn.prepare("SELECT tag FROM tags WHERE memory_id = ?1")?;
-        let mut tags = Vec::new();
-        let mut rows = stmt.query(params![memory_id])?;
+    /// Attach a file, or lines of one, to a memory, returns the attachment ID
+    fn add_attachment(&self, attachment: &Attachment) -> StoreResult<i64> {
+        self.conn.execute(
+            "INSERT INTO attachments (memory_id, path, start_line, end_line, snippet, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                attachment.memory_id,
+                attachment.path,
+                attachment.lines.map(|(start, _)| start),
+                attachment.lines.map(|(_, end)| end),
+                attachment.snippet,
+                attachment.created_at
+            ],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
 
-        while let Some(row) = rows.next()? {
-            tags.push(row.get(0)?);
-        }
+    /// A memory's attachments, oldest first
+    fn get_attachments(&self, memory_id: i64) -> StoreResult<Vec<Attachment>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, memory_id, path, start_line, end_line, snippet, created_at FROM attachments WHERE memory_id = ?1 ORDER BY id",
+        )?;
+        let attachments = stmt
+            .query_map(params![memory_id], |row| {
+                let start: Option<u32> = row.get(3)?;
+                let end: Option<u32> = row.get(4)?;
+                Ok(Attachment {
+                    id: row.get(0)?,
+                    memory_id: row.get(1)?,
+                    path: row.get(2)?,
+                    lines: start.zip(end),
+                    snippet: row.get(5)?,
+                    created_at: row.get(6)?,
+                })
+            })?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(attachments)
+    }
 
-        Ok(tags)
+    /// Sessions with memories, most recently active first
+    fn sessions(&self, limit: usize) -> StoreResult<Vec<SessionSummary>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT session_id, COUNT(*), MIN(created_at), MAX(created_at) FROM memories WHERE session_id IS NOT NULL GROUP BY session_id ORDER BY MAX(created_at) DESC LIMIT ?1",
+        )?;
+        let sessions = stmt
+            .query_map(params![limit as i64], |row| {
+                Ok(SessionSummary {
+                    id: row.get(0)?,
+                    count: row.get::<_, i64>(1)? as usize,
+                    first: row.get(2)?,
+                    last: row.get(3)?,
+                })
+            })?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(sessions)
     }
 
-    // -------------------------------------------------------------------------
-    // Metadata
-    // -------------------------------------------------------------------------
+    /// Replace tags in one transaction, keeping a single row per memory
+    fn rename_tags(&self, from: &[String], to: &str) -> StoreResult<Vec<i64>> {
+        let placeholders = (2..from.len() + 2).map(|i| format!("?{}", i)).collect::<Vec<_>>().join(", ");
+        let mut values = vec![Value::Text(to.to_string())];
+        values.extend(from.iter().map(|t| Value::Text(t.clone())));
+
+        let tx = self.write_transaction()?;
+        let ids = {
+            let mut stmt = tx.prepare(&format!(
+                "SELECT DISTINCT memory_id FROM tags WHERE tag IN ({}) AND tag != ?1 ORDER BY memory_id",
+                placeholders
+            ))?;
+            let ids = stmt
+                .query_map(params_from_iter(values.iter()), |row| row.get(0))?
+                .collect::<Result<Vec<i64>>>()?;
+            ids
+        };
+        tx.execute(
+            &format!(
+                "INSERT OR IGNORE INTO tags (memory_id, tag) SELECT memory_id, ?1 FROM tags WHERE tag IN ({})",
+                placeholders
+            ),
+            params_from_iter(values.iter()),
+        )?;
+        tx.execute(
+            &format!("DELETE FROM tags WHERE tag IN ({}) AND tag != ?1", placeholders),
+            params_from_iter(values.iter()),
+        )?;
+        tx.commit()?;
+        Ok(ids)
+    }
 
     /// Get a metadata value
-    pub fn get_metadata(&self, key: &str) -> Result<Option<String>> {
+    fn get_metadata(&self, key: &str) -> StoreResult<Option<String>> {
         let mut stmt = self.conn.prepare("SELECT value FROM metadata WHERE key = ?1")?;
         let mut rows = stmt.query(params![key])?;
 
@@ -366,7 +1155,7 @@ impl MemoryStore {
     }
 
     /// Set a metadata value
-    pub fn set_metadata(&self, key: &str, value: &str) -> Result<()> {
+    fn set_metadata(&self, key: &str, value: &str) -> StoreResult<()> {
         self.conn.execute(
             "INSERT OR REPLACE INTO metadata (key, value) VALUES (?1, ?2)",
             params![key, value],
@@ -375,7 +1164,7 @@ impl MemoryStore {
     }
 
     /// Get all memories with their embeddings for reindexing
-    pub fn get_all_for_reindex(&self) -> Result<Vec<(i64, String)>> {
+    fn get_all_for_reindex(&self) -> StoreResult<Vec<(i64, String)>> {
         let mut stmt = self.conn.prepare("SELECT id, content FROM memories")?;
         let mut results = Vec::new();
         let mut rows = stmt.query([])?;
@@ -387,20 +1176,79 @@ impl MemoryStore {
         Ok(results)
     }
 
+    fn get_pending_embeddings(&self, limit: usize) -> StoreResult<Vec<(i64, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, content FROM memories WHERE embedding IS NULL ORDER BY id LIMIT ?1",
+        )?;
+        let mut results = Vec::new();
+        let mut rows = stmt.query(params![limit as i64])?;
+
+        while let Some(row) = rows.next()? {
+            results.push((row.get(0)?, row.get(1)?));
+        }
+
+        Ok(results)
+    }
+
+    fn count_pending_embeddings(&self) -> StoreResult<usize> {
+        let count: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM memories WHERE embedding IS NULL",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(count as usize)
+    }
+
+    fn get_stale_embeddings(&self, model: &str, dim: usize) -> StoreResult<Vec<(i64, String)>> {
+        let sql = format!("SELECT id, content FROM memories WHERE {} ORDER BY id", STALE_EMBEDDING);
+        let mut stmt = self.conn.prepare(&sql)?;
+        let mut results = Vec::new();
+        let mut rows = stmt.query(params![model, (dim * 4) as i64])?;
+
+        while let Some(row) = rows.next()? {
+            results.push((row.get(0)?, row.get(1)?));
+        }
+
+        Ok(results)
+    }
+
     /// Update embedding for a memory
-    pub fn update_embedding(&self, id: i64, embedding: &[f32]) -> Result<()> {
-        let emb_bytes = Self::serialize_embedding(embedding);
+    fn update_embedding(&self, id: i64, embedding: &Embedding) -> StoreResult<()> {
+        let emb_bytes = serialize_embedding(embedding.vector);
         self.conn.execute(
-            "UPDATE memories SET embedding = ?1 WHERE id = ?2",
-            params![emb_bytes, id],
+            "UPDATE memories SET embedding = ?1, embedding_model = ?2, embedding_hash = ?3 WHERE id = ?4",
+            params![emb_bytes, embedding.model, embedding.text_hash, id],
         )?;
         Ok(())
     }
+
+    fn find_embedding(&self, model: &str, text_hash: &str) -> StoreResult<Option<Vec<f32>>> {
+        let embedding: Option<Vec<u8>> = self
+            .conn
+            .query_row(
+                "SELECT embedding FROM memories WHERE embedding_hash = ?1 AND embedding_model = ?2 AND embedding IS NOT NULL LIMIT 1",
+                params![text_hash, model],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(embedding.map(|e| deserialize_embedding(&e)))
+    }
+
+    fn get_embedding_hashes(&self, model: &str) -> StoreResult<HashMap<i64, String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, embedding_hash FROM memories WHERE embedding_model = ?1 AND embedding_hash IS NOT NULL",
+        )?;
+        let hashes = stmt
+            .query_map(params![model], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<_>>()?;
+        Ok(hashes)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::store::{SCHEMA_VERSION, SCHEMA_VERSION_KEY};
 
     #[test]
     fn test_add_and_get() {
@@ -419,6 +1267,24 @@ mod tests {
         assert_eq!(memory.tags, vec!["test", "example"]);
     }
 
+    #[test]
+    fn test_rename_tags_merges() {
+        let store = MemoryStore::in_memory().unwrap();
+
+        let both = store.add("Index the user_id column", 0.5, &[1.0], &["db".to_string(), "database".to_string()]).unwrap();
+        let one = store.add("Vacuum weekly", 0.5, &[1.0], &["databases".to_string()]).unwrap();
+        store.add("Use tabs", 0.5, &[1.0], &["style".to_string()]).unwrap();
+
+        let from = ["db".to_string(), "databases".to_string(), "database".to_string()];
+        assert_eq!(store.rename_tags(&from, "database").unwrap(), vec![both, one]);
+        assert_eq!(store.get(both).unwrap().unwrap().tags, vec!["database"]);
+        assert_eq!(store.get(one).unwrap().unwrap().tags, vec!["database"]);
+        assert_eq!(
+            store.get_all_tags().unwrap(),
+            vec![("database".to_string(), 2), ("style".to_string(), 1)]
+        );
+    }
+
     #[test]
     fn test_get_by_tag() {
         let store = MemoryStore::in_memory().unwrap();
@@ -427,8 +1293,583 @@ mod tests {
         store.add("Memory 2", 0.5, &[1.0], &["rust".to_string(), "cli".to_string()]).unwrap();
         store.add("Memory 3", 0.5, &[1.0], &["python".to_string()]).unwrap();
 
-        let rust_memories = store.get_by_tag("rust", 10).unwrap();
+        let (rust_memories, _) = store.get_by_tag("rust", 10, SortOrder::Recent).unwrap();
         assert_eq!(rust_memories.len(), 2);
+
+        let (memories, tag_match) = store.get_by_tag("py", 10, SortOrder::Recent).unwrap();
+        assert_eq!(memories.len(), 1);
+        assert_eq!(tag_match, TagMatch::Prefix(vec!["python".to_string()]));
+
+        let (memories, tag_match) = store.get_by_tag("pyhton", 10, SortOrder::Recent).unwrap();
+        assert!(memories.is_empty());
+        assert_eq!(tag_match, TagMatch::Missing(Some("python".to_string())));
+        assert_eq!(store.get_by_tag("go", 10, SortOrder::Recent).unwrap().1, TagMatch::Missing(None));
+    }
+
+    #[test]
+    fn test_match_tag_edge_cases() {
+        let known = |tags: &[(&str, usize)]| tags.iter().map(|(t, n)| (t.to_string(), *n)).collect::<Vec<_>>();
+        let tags = known(&[("database", 3), ("databases", 1), ("deploy", 5), ("ci", 2)]);
+
+        // Case doesn't matter, and every tag a prefix starts is matched
+        assert_eq!(match_tag("CI", &tags), TagMatch::Exact);
+        assert_eq!(
+            match_tag("data", &tags),
+            TagMatch::Prefix(vec!["database".to_string(), "databases".to_string()])
+        );
+        // A swap of two letters is one edit
+        assert_eq!(match_tag("databsae", &tags), TagMatch::Missing(Some("database".to_string())));
+        // Equally close tags go to the most used
+        assert_eq!(match_tag("databasex", &tags), TagMatch::Missing(Some("database".to_string())));
+        // Short tags get no suggestion; too many edits get none either
+        assert_eq!(match_tag("cx", &tags), TagMatch::Missing(None));
+        assert_eq!(match_tag("dpeolyy", &tags), TagMatch::Missing(None));
+        assert_eq!(match_tag("rust", &[]), TagMatch::Missing(None));
+
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("ab", "ba"), 1);
+    }
+
+    #[test]
+    fn test_exclusions_apply_before_limit() {
+        let store = MemoryStore::in_memory().unwrap();
+
+        let deploy = store.add("Deploy with the blue-green script", 0.5, &[1.0], &[]).unwrap();
+        store.add("Deploy to the old K8s cluster", 0.5, &[1.0], &[]).unwrap();
+        store.add("Deploy notes in progress", 0.5, &[1.0], &["wip".to_string()]).unwrap();
+
+        let filter = MemoryFilter {
+            exclude_tags: vec!["WIP".to_string()],
+            exclude_text: vec!["old k8s".to_string()],
+            ..Default::default()
+        };
+        let ids: Vec<i64> = store.list(&filter, 1, SortOrder::Recent).unwrap().iter().map(|m| m.id).collect();
+        assert_eq!(ids, vec![deploy]);
+
+        let mut scanned = Vec::new();
+        store
+            .scan_vectors(&filter, &mut |row| {
+                scanned.push(row.id);
+                true
+            })
+            .unwrap();
+        assert_eq!(scanned, vec![deploy]);
+    }
+
+    #[test]
+    fn test_filter_by_kind() {
+        let store = MemoryStore::in_memory().unwrap();
+
+        let decision = NewMemory {
+            content: "Use WAL mode".to_string(),
+            confidence: 0.5,
+            tags: vec!["db".to_string()],
+            kind: Some("decision".to_string()),
+            ..Default::default()
+        };
+        let id = store.insert(&decision, Some(&Embedding::new(&[1.0], "test", ""))).unwrap();
+        store.add("Untyped", 0.5, &[1.0], &["db".to_string()]).unwrap();
+
+        let filter = MemoryFilter {
+            tag: Some("db".to_string()),
+            kind: Some("decision".to_string()),
+            ..Default::default()
+        };
+        let results = store.list(&filter, 10, SortOrder::Recent).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].id, id);
+        assert_eq!(results[0].kind.as_deref(), Some("decision"));
+    }
+
+    #[test]
+    fn test_attachments_follow_their_memory() {
+        let store = MemoryStore::in_memory().unwrap();
+        let id = store.add("Retries back off exponentially", 0.5, &[1.0], &[]).unwrap();
+
+        let attachment = Attachment {
+            id: 0,
+            memory_id: id,
+            path: "src/client.rs".to_string(),
+            lines: Some((120, 121)),
+            snippet: Some("let delay = base * 2u32.pow(attempt);\nsleep(delay);".to_string()),
+            created_at: now_rfc3339(),
+        };
+        store.add_attachment(&attachment).unwrap();
+        store.add_attachment(&Attachment { lines: None, snippet: None, ..attachment }).unwrap();
+
+        let attached = store.get_attachments(id).unwrap();
+        assert_eq!(attached.len(), 2);
+        assert_eq!(attached[0].lines, Some((120, 121)));
+        assert_eq!(attached[1].lines, None);
+
+        store.delete(id).unwrap();
+        assert!(store.get_attachments(id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_sessions() {
+        let store = MemoryStore::in_memory().unwrap();
+
+        let written = [("Use pnpm", "s1", "01"), ("Lockfile is committed", "s1", "02"), ("Retry flaky e2e once", "s2", "03")];
+        for (content, session, day) in written {
+            let memory = NewMemory {
+                content: content.to_string(),
+                session_id: Some(session.to_string()),
+                created_at: Some(format!("2026-01-{}T00:00:00+00:00", day)),
+                ..Default::default()
+            };
+            store.insert(&memory, Some(&Embedding::new(&[1.0], "test", ""))).unwrap();
+        }
+        store.add("No session", 0.5, &[1.0], &[]).unwrap();
+
+        let sessions = store.sessions(10).unwrap();
+        let summary: Vec<(&str, usize)> = sessions.iter().map(|s| (s.id.as_str(), s.count)).collect();
+        assert_eq!(summary, vec![("s2", 1), ("s1", 2)]);
+        assert!(sessions[1].first.starts_with("2026-01-01"));
+        assert!(sessions[1].last.starts_with("2026-01-02"));
+
+        let filter = MemoryFilter {
+            session: Some("s1".to_string()),
+            ..Default::default()
+        };
+        let memories = store.list(&filter, 10, SortOrder::Recent).unwrap();
+        assert_eq!(memories.len(), 2);
+        assert!(memories.iter().all(|m| m.session_id.as_deref() == Some("s1")));
+    }
+
+    #[test]
+    fn test_expired_memories_are_hidden() {
+        let store = MemoryStore::in_memory().unwrap();
+
+        let expired = NewMemory {
+            content: "Staging is down".to_string(),
+            expires_at: Some("2000-01-01T00:00:00+00:00".to_string()),
+            ..Default::default()
+        };
+        let id = store.insert(&expired, Some(&Embedding::new(&[1.0], "test", ""))).unwrap();
+        store.add("Evergreen", 0.5, &[1.0], &[]).unwrap();
+
+        let visible = store.list(&MemoryFilter::default(), 10, SortOrder::Recent).unwrap();
+        assert_eq!(visible.len(), 1);
+        assert_eq!(visible[0].content, "Evergreen");
+
+        let criteria = PruneCriteria {
+            expired: true,
+            ..Default::default()
+        };
+        let expired = store.get_prunable(&criteria).unwrap();
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].id, id);
+    }
+
+    #[test]
+    fn test_far_future_expiry_is_not_expired() {
+        let store = MemoryStore::in_memory().unwrap();
+
+        // An hour ahead, written in an offset that sorts before now as text
+        let soon = (chrono::Utc::now() + chrono::Duration::hours(1))
+            .with_timezone(&chrono::FixedOffset::west_opt(12 * 3600).unwrap())
+            .to_rfc3339();
+        for expires_at in [soon.as_str(), "9999-12-31T00:00:00+00:00", "+29405-01-01T00:00:00+00:00"] {
+            let memory = NewMemory {
+                content: format!("Expires {}", expires_at),
+                expires_at: Some(expires_at.to_string()),
+                ..Default::default()
+            };
+            store.insert(&memory, Some(&Embedding::new(&[1.0], "test", ""))).unwrap();
+        }
+
+        let visible = store.list(&MemoryFilter::default(), 10, SortOrder::Recent).unwrap();
+        assert_eq!(visible.len(), 3);
+
+        let criteria = PruneCriteria {
+            expired: true,
+            ..Default::default()
+        };
+        assert!(store.get_prunable(&criteria).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_prune_criteria() {
+        let store = MemoryStore::in_memory().unwrap();
+
+        let weak = store.add("Maybe tabs", 0.2, &[1.0], &[]).unwrap();
+        store.add("Use spaces", 0.9, &[1.0], &[]).unwrap();
+
+        let low = PruneCriteria {
+            below_confidence: Some(0.3),
+            ..Default::default()
+        };
+        let ids: Vec<i64> = store.get_prunable(&low).unwrap().iter().map(|m| m.id).collect();
+        assert_eq!(ids, vec![weak]);
+
+        // Just created, so not stale yet
+        let stale = PruneCriteria {
+            below_confidence: Some(0.3),
+            unaccessed_before: Some("2000-01-01T00:00:00+00:00".to_string()),
+            ..Default::default()
+        };
+        assert!(store.get_prunable(&stale).unwrap().is_empty());
+
+        assert!(store.get_prunable(&PruneCriteria::default()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_delete_leaves_tombstone() {
+        let store = MemoryStore::in_memory().unwrap();
+        let id = store.add("Temporary", 0.5, &[1.0], &[]).unwrap();
+        let created_at = store.get(id).unwrap().unwrap().created_at;
+
+        assert!(store.delete(id).unwrap());
+        let tombstone = store.get_tombstone(&created_at).unwrap().unwrap();
+        assert_eq!(store.get_tombstones().unwrap(), vec![tombstone.clone()]);
+
+        assert_eq!(store.purge_tombstones("2000-01-01T00:00:00+00:00").unwrap(), 0);
+        assert_eq!(store.purge_tombstones(&crate::dates::from_now(chrono::Duration::days(1)).unwrap()).unwrap(), 1);
+        assert!(store.get_tombstone(&tombstone.created_at).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_restore_undoes_delete() {
+        let store = MemoryStore::in_memory().unwrap();
+        let id = store.add("Deleted by mistake", 0.7, &[1.0, 0.0], &["keep".to_string()]).unwrap();
+        store.record_access(&[id]).unwrap();
+        let memory = store.get(id).unwrap().unwrap();
+
+        store.delete(id).unwrap();
+        store.restore(&memory, Some(&Embedding::new(&[1.0, 0.0], "test", &memory.content))).unwrap();
+
+        let restored = store.get(id).unwrap().unwrap();
+        assert_eq!(restored.tags, vec!["keep"]);
+        assert_eq!((restored.access_count, restored.created_at.as_str()), (1, memory.created_at.as_str()));
+        assert!(store.get_tombstone(&memory.created_at).unwrap().is_none());
+        assert_eq!(store.search_fts("mistake", &MemoryFilter::default(), 5).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_active_since_keeps_pinned() {
+        let store = MemoryStore::in_memory().unwrap();
+
+        let old = store.add("Old decision", 0.5, &[1.0], &[]).unwrap();
+        let pinned = NewMemory {
+            content: "Pinned rule".to_string(),
+            pinned: true,
+            ..Default::default()
+        };
+        let pinned = store.insert(&pinned, Some(&Embedding::new(&[1.0], "test", ""))).unwrap();
+
+        let filter = MemoryFilter {
+            active_since: Some(crate::dates::from_now(chrono::Duration::days(1)).unwrap()),
+            ..Default::default()
+        };
+        let ids: Vec<i64> = store.list(&filter, 10, SortOrder::Recent).unwrap().iter().map(|m| m.id).collect();
+        assert_eq!(ids, vec![pinned]);
+
+        store.update(old, &MemoryUpdate { pinned: Some(true), ..Default::default() }).unwrap();
+        assert_eq!(store.list(&filter, 10, SortOrder::Recent).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_since_until_window() {
+        let store = MemoryStore::in_memory().unwrap();
+        let revised = store.add("Revised in March", 0.5, &[1.0], &[]).unwrap();
+        let february = store.add("Written in February", 0.5, &[1.0], &[]).unwrap();
+        let set_times = |id: i64, created: &str, updated: &str| {
+            store
+                .conn
+                .execute(
+                    "UPDATE memories SET created_at = ?1, updated_at = ?2 WHERE id = ?3",
+                    params![created, updated, id],
+                )
+                .unwrap();
+        };
+        set_times(revised, "2024-01-05T00:00:00+00:00", "2024-03-10T00:00:00+00:00");
+        set_times(february, "2024-02-10T00:00:00+00:00", "2024-02-10T00:00:00+00:00");
+
+        let ids = |since: Option<&str>, until: Option<&str>| -> Vec<i64> {
+            let filter = MemoryFilter {
+                since: since.map(|s| crate::dates::parse_point(s).unwrap()),
+                until: until.map(|s| crate::dates::parse_point(s).unwrap()),
+                ..Default::default()
+            };
+            store.list(&filter, 10, SortOrder::Recent).unwrap().iter().map(|m| m.id).collect()
+        };
+        assert_eq!(ids(Some("2024-03"), None), vec![revised]);
+        assert_eq!(ids(None, Some("2024-02")), vec![revised]);
+        assert_eq!(ids(Some("2024-02"), Some("2024-03")), vec![february]);
+        assert!(ids(Some("1d"), None).is_empty());
+    }
+
+    #[test]
+    fn test_deferred_embedding_is_queued() {
+        let store = MemoryStore::in_memory().unwrap();
+
+        store.add("Embedded", 0.5, &[1.0], &[]).unwrap();
+        let deferred = NewMemory {
+            content: "Hook capture".to_string(),
+            ..Default::default()
+        };
+        let id = store.insert(&deferred, None).unwrap();
+
+        assert_eq!(store.get_all_with_embeddings(&MemoryFilter::default()).unwrap().len(), 1);
+        assert_eq!(store.get_pending_embeddings(10).unwrap(), vec![(id, "Hook capture".to_string())]);
+
+        store.update_embedding(id, &Embedding::new(&[1.0], "test", "Hook capture")).unwrap();
+        assert_eq!(store.count_pending_embeddings().unwrap(), 0);
+        assert_eq!(store.get_all_with_embeddings(&MemoryFilter::default()).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_stale_embeddings() {
+        let store = MemoryStore::in_memory().unwrap();
+
+        let current = store.add("Current", 0.5, &[1.0, 0.0], &[]).unwrap();
+        let short = store.add("Short", 0.5, &[1.0], &[]).unwrap();
+        let other = store.add("Other model", 0.5, &[0.0, 1.0], &[]).unwrap();
+        store.update_embedding(other, &Embedding::new(&[0.0, 1.0], "old", "Other model")).unwrap();
+        let deferred = NewMemory {
+            content: "Deferred".to_string(),
+            ..Default::default()
+        };
+        let queued = store.insert(&deferred, None).unwrap();
+
+        let stale: Vec<i64> = store
+            .get_stale_embeddings("test", 2)
+            .unwrap()
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+        assert_eq!(stale, vec![short, other, queued]);
+        assert!(!stale.contains(&current));
+    }
+
+    #[test]
+    fn test_embeddings_found_by_text_hash() {
+        let store = MemoryStore::in_memory().unwrap();
+
+        let id = store.add("Use uv", 0.5, &[1.0, 0.0], &[]).unwrap();
+        let hash = crate::blobs::hash("Use uv");
+        assert_eq!(store.find_embedding("test", &hash).unwrap(), Some(vec![1.0, 0.0]));
+        assert_eq!(store.find_embedding("other", &hash).unwrap(), None);
+        assert_eq!(store.get_embedding_hashes("test").unwrap().get(&id), Some(&hash));
+    }
+
+    #[test]
+    fn test_list_fetches_tags_in_one_query() {
+        let store = MemoryStore::in_memory().unwrap();
+        store.add("one", 0.5, &[1.0], &["b".to_string(), "a".to_string()]).unwrap();
+        store.add("two", 0.5, &[1.0], &[]).unwrap();
+        store.add("three", 0.5, &[1.0], &["c".to_string()]).unwrap();
+
+        let memories = store.list(&MemoryFilter::default(), 10, SortOrder::Recent).unwrap();
+        let tags: Vec<(&str, Vec<String>)> =
+            memories.iter().map(|m| (m.content.as_str(), m.tags.clone())).collect();
+        assert!(tags.contains(&("one", vec!["b".to_string(), "a".to_string()])));
+        assert!(tags.contains(&("two", vec![])));
+        assert!(tags.contains(&("three", vec!["c".to_string()])));
+        assert_eq!(store.take_tag_stats().0, 1);
+    }
+
+    #[test]
+    fn test_fts_ranks_and_highlights() {
+        let store = MemoryStore::in_memory().unwrap();
+        store.add("Deploy with helm, not kubectl apply", 0.5, &[1.0], &[]).unwrap();
+        store.add("helm charts live in deploy/helm; helm lint before merging", 0.5, &[1.0], &["ci".to_string()]).unwrap();
+
+        let matches = store.search_fts("helm", &MemoryFilter::default(), 10).unwrap();
+        assert_eq!(matches.len(), 2);
+        assert!(matches[0].memory.content.starts_with("helm charts"));
+        assert!(matches[0].score > matches[1].score);
+        assert!(matches[1].snippet.contains(&format!("{}helm{}", SNIPPET_START, SNIPPET_END)));
+
+        let tagged = MemoryFilter {
+            tag: Some("ci".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(store.search_fts("kubectl", &tagged, 10).unwrap().len(), 0);
+    }
+
+    #[test]
+    fn test_scan_stops_when_asked() {
+        let store = MemoryStore::in_memory().unwrap();
+        for content in ["one", "two", "three"] {
+            store.add(content, 0.5, &[1.0], &[]).unwrap();
+        }
+
+        let mut seen = Vec::new();
+        store
+            .scan_vectors(&MemoryFilter::default(), &mut |row| {
+                seen.push(row.id);
+                seen.len() < 2
+            })
+            .unwrap();
+        assert_eq!(seen.len(), 2);
+
+        // Fetched in the order asked for
+        let contents: Vec<String> = store
+            .get_many(&[seen[1], 999, seen[0]])
+            .unwrap()
+            .into_iter()
+            .map(|m| m.content)
+            .collect();
+        assert_eq!(contents, vec!["two", "one"]);
+    }
+
+    #[test]
+    fn test_quarantine_hidden_by_default() {
+        let store = MemoryStore::in_memory().unwrap();
+
+        let quarantined = NewMemory {
+            content: "Untagged hook capture".to_string(),
+            namespace: Some(QUARANTINE_NAMESPACE.to_string()),
+            ..Default::default()
+        };
+        let id = store.insert(&quarantined, Some(&Embedding::new(&[1.0], "test", ""))).unwrap();
+
+        let visible = store.list(&MemoryFilter::default(), 10, SortOrder::Recent).unwrap();
+        assert!(visible.is_empty());
+
+        let filter = MemoryFilter {
+            namespace: Some(QUARANTINE_NAMESPACE.to_string()),
+            ..Default::default()
+        };
+        let review = store.list(&filter, 10, SortOrder::Recent).unwrap();
+        assert_eq!(review[0].id, id);
+
+        let release = MemoryUpdate {
+            namespace: Some(String::new()),
+            ..Default::default()
+        };
+        store.update(id, &release).unwrap();
+        assert_eq!(store.get(id).unwrap().unwrap().namespace, None);
+    }
+
+    #[test]
+    fn test_doctor_checks() {
+        let store = MemoryStore::in_memory().unwrap();
+        let kept = store.add("Kept", 0.5, &[1.0, 0.0], &["a".to_string()]).unwrap();
+        let gone = store.add("Gone", 0.5, &[1.0, 0.0], &["b".to_string()]).unwrap();
+        assert!(store.fts_consistent().unwrap());
+
+        // Other tools may delete with foreign keys off
+        store.conn.execute_batch("PRAGMA foreign_keys = OFF").unwrap();
+        store.conn.execute("DELETE FROM memories WHERE id = ?1", params![gone]).unwrap();
+        assert_eq!(store.orphaned_tags().unwrap(), 1);
+        assert_eq!(store.delete_orphaned_tags().unwrap(), 1);
+
+        store
+            .conn
+            .execute(
+                "INSERT INTO memories_fts(memories_fts, rowid, content) VALUES('delete', ?1, 'Kept')",
+                params![kept],
+            )
+            .unwrap();
+        assert!(!store.fts_consistent().unwrap());
+        store.rebuild_fts().unwrap();
+        assert!(store.fts_consistent().unwrap());
+
+        assert_eq!(store.embedding_sizes().unwrap(), vec![(Some(8), 1)]);
+    }
+
+    #[test]
+    fn test_refuses_newer_schema() {
+        let path = std::env::temp_dir().join(format!("roots-schema-{}.db", std::process::id()));
+        let store = MemoryStore::open(&path).unwrap();
+        assert_eq!(store.get_metadata(SCHEMA_VERSION_KEY).unwrap(), Some(SCHEMA_VERSION.to_string()));
+        store.set_metadata(SCHEMA_VERSION_KEY, &(SCHEMA_VERSION + 1).to_string()).unwrap();
+        // Say the newer schema did away with a table
+        store.conn.execute_batch("DROP TABLE tombstones").unwrap();
+        drop(store);
+
+        let err = MemoryStore::open(&path).err().unwrap();
+        assert!(matches!(err, StoreError::SchemaTooNew { .. }));
+        assert!(err.to_string().contains("roots migrate"));
+        // Refused before anything was created in it
+        let conn = Connection::open(&path).unwrap();
+        let tables: i64 = conn
+            .query_row("SELECT COUNT(*) FROM sqlite_master WHERE name = 'tombstones'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(tables, 0);
+        drop(conn);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+    }
+
+    #[test]
+    fn test_read_only_open_changes_nothing() {
+        let path = std::env::temp_dir().join(format!("roots-read-only-{}.db", std::process::id()));
+        assert!(MemoryStore::open_read_only(&path).is_err());
+        assert!(!path.exists());
+
+        let store = MemoryStore::open(&path).unwrap();
+        store
+            .insert(
+                &NewMemory {
+                    content: "Tabs complete from here".to_string(),
+                    confidence: 0.5,
+                    tags: vec!["shell".to_string()],
+                    ..Default::default()
+                },
+                None,
+            )
+            .unwrap();
+        // Say an older build made it, before the session index
+        store.conn.execute_batch("DROP INDEX idx_memories_session").unwrap();
+        drop(store);
+
+        let store = MemoryStore::open_read_only(&path).unwrap();
+        assert_eq!(store.get_all_tags().unwrap(), vec![("shell".to_string(), 1)]);
+        assert!(store.set_metadata("touched", "yes").is_err());
+        let indexes: i64 = store
+            .conn
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE name = 'idx_memories_session'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(indexes, 0);
+        drop(store);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+    }
+
+    #[test]
+    fn test_waits_for_another_writer() {
+        let path = std::env::temp_dir().join(format!("roots-busy-{}.db", std::process::id()));
+        let first = MemoryStore::open(&path).unwrap();
+        let second = MemoryStore::open(&path).unwrap();
+
+        // Hold the write lock briefly from the first connection
+        first.conn.execute_batch("BEGIN IMMEDIATE").unwrap();
+        let writer = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(200));
+            first.conn.execute_batch("COMMIT").unwrap();
+        });
+
+        let id = second.add("Written while locked", 0.5, &[1.0], &["a".to_string()]).unwrap();
+        writer.join().unwrap();
+        assert_eq!(second.get(id).unwrap().unwrap().tags, vec!["a"]);
+
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+    }
+
+    #[test]
+    fn test_record_access() {
+        let store = MemoryStore::in_memory().unwrap();
+
+        let id = store.add("Accessed", 0.5, &[1.0], &[]).unwrap();
+        store.record_access(&[id]).unwrap();
+        store.record_access(&[id]).unwrap();
+
+        let memory = store.get(id).unwrap().unwrap();
+        assert_eq!(memory.access_count, 2);
+        assert!(memory.last_accessed_at.is_some());
     }
 
     #[test]
@@ -441,4 +1882,19 @@ mod tests {
         store.delete(id).unwrap();
         assert!(store.get(id).unwrap().is_none());
     }
+
+    #[test]
+    fn test_list_by_importance() {
+        let store = MemoryStore::in_memory().unwrap();
+
+        let a = store.add("Leaf", 0.5, &[1.0], &[]).unwrap();
+        let b = store.add("Hub", 0.5, &[1.0], &[]).unwrap();
+        store.set_importance(&[(a, 0.1), (b, 0.9)]).unwrap();
+
+        let ordered = store
+            .list(&MemoryFilter::default(), 10, SortOrder::Importance)
+            .unwrap();
+        assert_eq!(ordered[0].id, b);
+        assert_eq!(ordered[0].importance, 0.9);
+    }
 }
@@ -0,0 +1,397 @@
+use crate::chunk::ChunkerSpec;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// A memory candidate parsed from an external source
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportItem {
+    pub content: String,
+    pub source: String,
+    pub tags: Vec<String>,
+}
+
+/// Each file `ingest docs` stored, by path relative to the project
+pub type IngestManifest = BTreeMap<String, IngestedFile>;
+
+/// A file in the `IngestManifest`: its content hash, so `--update` can
+/// tell whether it changed, and how it was ingested, so `watch` ingests it
+/// the same way again
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "ManifestEntry")]
+pub struct IngestedFile {
+    pub hash: String,
+    /// None for files ingested before options were kept
+    pub options: Option<DocOptions>,
+}
+
+/// How `ingest docs` chunks and stores a file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DocOptions {
+    pub chunker: ChunkerSpec,
+    /// Extra comma-separated tags for every chunk
+    pub tags: String,
+    pub confidence: f64,
+}
+
+/// A manifest entry as stored: older manifests only kept the hash
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ManifestEntry {
+    Hash(String),
+    File { hash: String, options: Option<DocOptions> },
+}
+
+impl From<ManifestEntry> for IngestedFile {
+    fn from(entry: ManifestEntry) -> Self {
+        match entry {
+            ManifestEntry::Hash(hash) => IngestedFile { hash, options: None },
+            ManifestEntry::File { hash, options } => IngestedFile { hash, options },
+        }
+    }
+}
+
+/// Decode the handful of entities common in bookmark and feed exports
+fn decode_entities(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Strip CDATA wrappers and HTML tags, collapsing whitespace
+fn plain_text(text: &str) -> String {
+    let text = text
+        .trim()
+        .trim_start_matches("<![CDATA[")
+        .trim_end_matches("]]>");
+    let tags = Regex::new(r"<[^>]*>").unwrap();
+    let text = decode_entities(&tags.replace_all(text, " "));
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Turn a folder or title into a tag
+fn tagify(text: &str) -> String {
+    text.trim()
+        .to_lowercase()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Split a comma-separated tag list
+fn split_tags(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(',').map(tagify).filter(|t| !t.is_empty())
+}
+
+// =============================================================================
+// Netscape bookmarks (exported by every major browser)
+// =============================================================================
+
+/// Parse a Netscape-format bookmarks HTML export.
+///
+/// Each link becomes an item tagged `bookmark`, its `TAGS` attribute, and the
+/// folders it is nested in.
+pub fn parse_bookmarks(html: &str) -> Vec<ImportItem> {
+    let folder_re = Regex::new(r"(?i)<H3[^>]*>(.*?)</H3>").unwrap();
+    let link_re = Regex::new(r#"(?i)<A\s+([^>]*)>(.*?)</A>"#).unwrap();
+    let href_re = Regex::new(r#"(?i)HREF="([^"]*)""#).unwrap();
+    let tags_re = Regex::new(r#"(?i)TAGS="([^"]*)""#).unwrap();
+    let desc_re = Regex::new(r"(?i)^\s*<DD>(.*)").unwrap();
+
+    let mut items: Vec<ImportItem> = Vec::new();
+    let mut folders: Vec<String> = Vec::new();
+    let mut pending_folder: Option<String> = None;
+
+    for line in html.lines() {
+        if let Some(caps) = folder_re.captures(line) {
+            pending_folder = Some(plain_text(&caps[1]));
+        } else if line.to_uppercase().contains("<DL") {
+            // A folder's list opens right after its heading
+            folders.push(pending_folder.take().unwrap_or_default());
+        } else if line.to_uppercase().contains("</DL") {
+            folders.pop();
+        } else if let Some(caps) = link_re.captures(line) {
+            let attrs = &caps[1];
+            let Some(href) = href_re.captures(attrs) else {
+                continue;
+            };
+            let url = decode_entities(&href[1]);
+            let title = plain_text(&caps[2]);
+
+            let mut tags = vec!["bookmark".to_string()];
+            tags.extend(folders.iter().filter(|f| !f.is_empty()).map(|f| tagify(f)));
+            if let Some(t) = tags_re.captures(attrs) {
+                tags.extend(split_tags(&t[1]));
+            }
+            // A folder and a tag often share a name, not necessarily adjacent
+            let mut seen = HashSet::new();
+            tags.retain(|t| seen.insert(t.clone()));
+
+            let content = if title.is_empty() { url.clone() } else { title };
+            items.push(ImportItem {
+                content,
+                source: url,
+                tags,
+            });
+        } else if let Some(caps) = desc_re.captures(line) {
+            if let Some(last) = items.last_mut() {
+                let desc = plain_text(&caps[1]);
+                if !desc.is_empty() {
+                    last.content = format!("{}\n\n{}", last.content, desc);
+                }
+            }
+        }
+    }
+
+    items
+}
+
+// =============================================================================
+// RSS / Atom feeds
+// =============================================================================
+
+/// Parse RSS `<item>` or Atom `<entry>` elements into items tagged `rss`
+pub fn parse_feed(xml: &str) -> Vec<ImportItem> {
+    let entry_re = Regex::new(r"(?is)<(item|entry)\b[^>]*>(.*?)</(item|entry)>").unwrap();
+    let field = |body: &str, name: &str| -> Option<String> {
+        let re = Regex::new(&format!(r"(?is)<{0}\b[^>]*>(.*?)</{0}>", name)).unwrap();
+        re.captures(body)
+            .map(|c| plain_text(&c[1]))
+            .filter(|s| !s.is_empty())
+    };
+    let atom_link_re = Regex::new(r#"(?is)<link\b[^>]*href="([^"]*)""#).unwrap();
+    let category_re = Regex::new(r#"(?is)<category\b(?:[^>]*term="([^"]*)")?[^>]*?(?:/>|>(.*?)</category>)"#).unwrap();
+
+    let mut items = Vec::new();
+    for caps in entry_re.captures_iter(xml) {
+        let body = &caps[2];
+
+        let title = field(body, "title").unwrap_or_default();
+        let link = field(body, "link")
+            .or_else(|| atom_link_re.captures(body).map(|c| decode_entities(&c[1])))
+            .unwrap_or_default();
+        let summary = field(body, "description")
+            .or_else(|| field(body, "summary"))
+            .unwrap_or_default();
+
+        if title.is_empty() && summary.is_empty() {
+            continue;
+        }
+
+        let mut tags = vec!["rss".to_string()];
+        for c in category_re.captures_iter(body) {
+            let name = c
+                .get(1)
+                .or_else(|| c.get(2))
+                .map(|m| plain_text(m.as_str()))
+                .unwrap_or_default();
+            let tag = tagify(&name);
+            if !tag.is_empty() && !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+
+        let content = match (title.is_empty(), summary.is_empty()) {
+            (false, false) => format!("{}\n\n{}", title, summary),
+            (false, true) => title,
+            _ => summary,
+        };
+
+        items.push(ImportItem {
+            content,
+            source: link,
+            tags,
+        });
+    }
+
+    items
+}
+
+// =============================================================================
+// Readwise CSV export
+// =============================================================================
+
+/// Parse CSV text into rows, handling quoted fields with commas and newlines
+pub fn parse_csv(text: &str) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, in_quotes) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            ('"', true) => in_quotes = false,
+            ('"', false) if field.is_empty() => in_quotes = true,
+            (',', false) => row.push(std::mem::take(&mut field)),
+            ('\r', false) => {}
+            ('\n', false) => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            _ => field.push(c),
+        }
+    }
+
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+
+    rows.retain(|r| !(r.len() == 1 && r[0].is_empty()));
+    rows
+}
+
+/// Parse a Readwise highlights CSV export into items tagged `readwise`.
+///
+/// Expects the standard header (Highlight, Book Title, Book Author, Note,
+/// Tags, Document tags, ...). Rows without a highlight are skipped. Each
+/// highlight gets a source of its own, the book's URL or title followed by
+/// the highlight's id (or, in exports without one, its text's hash), so
+/// highlights of one book are not skipped as already ingested.
+pub fn parse_readwise(csv: &str) -> Result<Vec<ImportItem>, String> {
+    let rows = parse_csv(csv);
+    let (header, body) = rows.split_first().ok_or("Empty CSV file")?;
+
+    let columns: HashMap<String, usize> = header
+        .iter()
+        .enumerate()
+        .map(|(i, h)| (h.trim().trim_start_matches('\u{feff}').to_lowercase(), i))
+        .collect();
+    if !columns.contains_key("highlight") {
+        return Err("Not a Readwise export: missing 'Highlight' column".to_string());
+    }
+
+    let get = |row: &[String], name: &str| -> String {
+        columns
+            .get(name)
+            .and_then(|&i| row.get(i))
+            .map(|s| s.trim().to_string())
+            .unwrap_or_default()
+    };
+
+    let mut items = Vec::new();
+    for row in body {
+        let highlight = get(row, "highlight");
+        if highlight.is_empty() {
+            continue;
+        }
+
+        let title = get(row, "book title");
+        let author = get(row, "book author");
+        let note = get(row, "note");
+
+        let mut content = highlight;
+        if !note.is_empty() {
+            content = format!("{}\n\nNote: {}", content, note);
+        }
+        match (title.is_empty(), author.is_empty()) {
+            (false, false) => content = format!("{}\n\n— {}, {}", content, title, author),
+            (false, true) => content = format!("{}\n\n— {}", content, title),
+            _ => {}
+        }
+
+        let mut tags = vec!["readwise".to_string()];
+        for t in split_tags(&get(row, "tags")).chain(split_tags(&get(row, "document tags"))) {
+            if !tags.contains(&t) {
+                tags.push(t);
+            }
+        }
+
+        let url = get(row, "url");
+        let book = if !url.is_empty() { url } else { format!("readwise:{}", title) };
+        let id = ["highlight id", "id"]
+            .iter()
+            .map(|column| get(row, column))
+            .find(|id| !id.is_empty())
+            .unwrap_or_else(|| crate::blobs::hash(&get(row, "highlight"))[..12].to_string());
+        let source = format!("{}#{}", book, id);
+
+        items.push(ImportItem {
+            content,
+            source,
+            tags,
+        });
+    }
+
+    Ok(items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_bookmarks() {
+        let html = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<DL><p>
+    <DT><H3>Rust Docs</H3>
+    <DL><p>
+        <DT><A HREF="https://doc.rust-lang.org/book/" ADD_DATE="1" TAGS="learning">The Rust Book</A>
+        <DD>Official guide &amp; reference
+    </DL><p>
+    <DT><A HREF="https://example.com">Example</A>
+</DL><p>"#;
+
+        let items = parse_bookmarks(html);
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].source, "https://doc.rust-lang.org/book/");
+        assert_eq!(items[0].content, "The Rust Book\n\nOfficial guide & reference");
+        assert_eq!(items[0].tags, vec!["bookmark", "rust-docs", "learning"]);
+        assert_eq!(items[1].tags, vec!["bookmark"]);
+
+        // A tag repeating a folder further up is dropped, not just a neighbour
+        let html = r#"<DL><p>
+    <DT><H3>Rust</H3>
+    <DL><p>
+        <DT><H3>Async</H3>
+        <DL><p>
+            <DT><A HREF="https://tokio.rs" TAGS="rust,async">Tokio</A>
+        </DL><p>
+    </DL><p>
+</DL><p>"#;
+        assert_eq!(parse_bookmarks(html)[0].tags, vec!["bookmark", "rust", "async"]);
+    }
+
+    #[test]
+    fn test_parse_feed() {
+        let xml = r#"<rss><channel><title>Blog</title>
+<item><title>Faster builds</title><link>https://blog/1</link>
+<description><![CDATA[<p>Use <b>sccache</b></p>]]></description><category>Rust</category></item>
+</channel></rss>"#;
+
+        let items = parse_feed(xml);
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].content, "Faster builds\n\nUse sccache");
+        assert_eq!(items[0].source, "https://blog/1");
+        assert_eq!(items[0].tags, vec!["rss", "rust"]);
+    }
+
+    #[test]
+    fn test_parse_readwise() {
+        let csv = "Highlight,Book Title,Book Author,Amazon Book ID,Note,Color,Tags,Location Type,Location,Highlighted at,Document tags\n\
+                   \"Simple, not easy\",Clean Code,Bob,,\"nice\",yellow,\"design, craft\",location,42,,\n\
+                   ,Empty,,,,,,,,,\n\
+                   Names matter,Clean Code,Bob,,,yellow,,location,42,,\n";
+
+        let items = parse_readwise(csv).unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].content, "Simple, not easy\n\nNote: nice\n\n— Clean Code, Bob");
+        assert_eq!(items[0].tags, vec!["readwise", "design", "craft"]);
+        // Two highlights at one location are still two sources
+        assert_eq!(items[0].source, format!("readwise:Clean Code#{}", &crate::blobs::hash("Simple, not easy")[..12]));
+        assert_ne!(items[0].source, items[1].source);
+
+        let csv = "Highlight,Book Title,URL,Highlight ID\nFirst,Essay,https://essay,101\nSecond,Essay,https://essay,102\n";
+        let items = parse_readwise(csv).unwrap();
+        assert_eq!(items[0].source, "https://essay#101");
+        assert_eq!(items[1].source, "https://essay#102");
+    }
+}
@@ -0,0 +1,985 @@
+//! libSQL (sqld / Turso) backend, for a store shared by a team.
+//!
+//! Speaks the Hrana-over-HTTP pipeline protocol (see `http.rs`), using the
+//! same schema and SQL as the SQLite backend.
+
+use crate::dates::now_rfc3339;
+use crate::http::Request;
+use crate::index::{
+    filter_clause, fts_query, prune_clause, COLUMN_BACKFILLS, COLUMN_MIGRATIONS, MEMORY_COLUMNS,
+    MEMORY_COLUMN_COUNT, METADATA_SCHEMA, MIGRATED_INDEXES, SCHEMA, STALE_EMBEDDING, VECTOR_COLUMNS,
+};
+use crate::store::{
+    deserialize_embedding, refuse_newer_schema, serialize_embedding, stamp_schema_version, Embedding, Store,
+    StoreError, StoreResult, VectorRow,
+};
+use crate::types::{
+    Attachment, Memory, MemoryFilter, MemoryUpdate, NewMemory, PruneCriteria, SessionSummary, SortOrder, TextMatch,
+    Tombstone,
+};
+use rusqlite::types::Value;
+use serde_json::{json, Value as Json};
+use std::collections::HashMap;
+
+/// Seconds before a request to the server is abandoned
+const REQUEST_TIMEOUT_SECS: u32 = 30;
+
+/// A statement with its positional parameters
+struct Stmt {
+    sql: String,
+    args: Vec<Value>,
+}
+
+impl Stmt {
+    fn new(sql: impl Into<String>, args: Vec<Value>) -> Self {
+        Self {
+            sql: sql.into(),
+            args,
+        }
+    }
+
+    fn to_json(&self) -> Json {
+        json!({
+            "sql": self.sql,
+            "args": self.args.iter().map(value_to_json).collect::<Vec<_>>(),
+        })
+    }
+}
+
+/// Rows and counters returned for one statement
+#[derive(Default)]
+struct StmtResult {
+    rows: Vec<Vec<Json>>,
+    affected: u64,
+    last_insert_rowid: Option<i64>,
+}
+
+/// Memory store on a libSQL server
+pub struct LibsqlStore {
+    pipeline_url: String,
+    auth_token: Option<String>,
+}
+
+impl LibsqlStore {
+    /// Connect and bring the remote schema up to date
+    pub fn open(url: &str, auth_token: Option<String>) -> StoreResult<Self> {
+        let base = match url.strip_prefix("libsql://") {
+            Some(host) => format!("https://{}", host),
+            None => url.to_string(),
+        };
+        let store = Self {
+            pipeline_url: format!("{}/v2/pipeline", base.trim_end_matches('/')),
+            auth_token,
+        };
+        store.pipeline(vec![json!({ "type": "sequence", "sql": METADATA_SCHEMA })])?;
+        refuse_newer_schema(&store)?;
+        store.pipeline(vec![json!({ "type": "sequence", "sql": SCHEMA })])?;
+        store.migrate()?;
+        stamp_schema_version(&store)?;
+        Ok(store)
+    }
+
+    /// Add any columns missing from an older `memories` table
+    fn migrate(&self) -> StoreResult<()> {
+        let existing: Vec<String> = self
+            .query(Stmt::new("PRAGMA table_info(memories)", vec![]))?
+            .iter()
+            .filter_map(|row| text(&row[1]))
+            .collect();
+
+        for (name, decl) in COLUMN_MIGRATIONS {
+            if !existing.iter().any(|c| c == name) {
+                let sql = format!("ALTER TABLE memories ADD COLUMN {} {}", name, decl);
+                self.execute(Stmt::new(sql, vec![]))?;
+                for (_, sql) in COLUMN_BACKFILLS.iter().filter(|(column, _)| column == name) {
+                    self.execute(Stmt::new(*sql, vec![]))?;
+                }
+                if *name == "embedding_hash" {
+                    self.backfill_embedding_hashes()?;
+                }
+            }
+        }
+        self.execute(Stmt::new(MIGRATED_INDEXES, vec![]))?;
+        Ok(())
+    }
+
+    /// Existing embeddings were all made from the row's current content
+    fn backfill_embedding_hashes(&self) -> StoreResult<()> {
+        let stmts = self
+            .query(Stmt::new(
+                "SELECT id, content FROM memories WHERE embedding IS NOT NULL",
+                vec![],
+            ))?
+            .iter()
+            .filter_map(|r| Some((integer(&r[0])?, text(&r[1])?)))
+            .map(|(id, content)| {
+                Stmt::new(
+                    "UPDATE memories SET embedding_hash = ?1 WHERE id = ?2",
+                    vec![Value::Text(crate::blobs::hash(&content)), Value::Integer(id)],
+                )
+            })
+            .collect::<Vec<_>>();
+        if !stmts.is_empty() {
+            self.transaction(stmts)?;
+        }
+        Ok(())
+    }
+
+    // -------------------------------------------------------------------------
+    // Protocol
+    // -------------------------------------------------------------------------
+
+    /// Send requests on a fresh stream and return each response
+    fn pipeline(&self, mut requests: Vec<Json>) -> StoreResult<Vec<Json>> {
+        requests.push(json!({ "type": "close" }));
+        let body = json!({ "baton": null, "requests": requests }).to_string();
+
+        let mut request = Request::new("POST", &self.pipeline_url)
+            .timeout(REQUEST_TIMEOUT_SECS)
+            .header("Content-Type: application/json");
+        if let Some(token) = &self.auth_token {
+            request = request.header(&format!("Authorization: Bearer {}", token));
+        }
+        let stdout = request
+            .opt("data-binary", &body)
+            .send()
+            .map_err(|e| remote(format!("libsql server error: {}", e)))?;
+
+        let response: Json = serde_json::from_str(&stdout)
+            .map_err(|e| remote(format!("Invalid response from libsql server: {}", e)))?;
+        let mut results = Vec::new();
+        for result in response["results"].as_array().into_iter().flatten() {
+            if result["type"] == "error" {
+                return Err(remote(error_message(&result["error"])));
+            }
+            results.push(result["response"].clone());
+        }
+        Ok(results)
+    }
+
+    /// Run one statement
+    fn execute(&self, stmt: Stmt) -> StoreResult<StmtResult> {
+        let request = json!({ "type": "execute", "stmt": stmt.to_json() });
+        let responses = self.pipeline(vec![request])?;
+        let result = responses
+            .first()
+            .map(|r| parse_result(&r["result"]))
+            .unwrap_or_default();
+        Ok(result)
+    }
+
+    /// Run one statement and return its rows
+    fn query(&self, stmt: Stmt) -> StoreResult<Vec<Vec<Json>>> {
+        Ok(self.execute(stmt)?.rows)
+    }
+
+    /// Run statements in one transaction, each only if the previous succeeded
+    fn transaction(&self, stmts: Vec<Stmt>) -> StoreResult<Vec<StmtResult>> {
+        let n = stmts.len();
+        let mut steps = vec![json!({ "stmt": { "sql": "BEGIN" } })];
+        for (i, stmt) in stmts.iter().enumerate() {
+            steps.push(json!({
+                "stmt": stmt.to_json(),
+                "condition": { "type": "ok", "step": i },
+            }));
+        }
+        steps.push(json!({
+            "stmt": { "sql": "COMMIT" },
+            "condition": { "type": "ok", "step": n },
+        }));
+        steps.push(json!({
+            "stmt": { "sql": "ROLLBACK" },
+            "condition": { "type": "not", "cond": { "type": "ok", "step": n + 1 } },
+        }));
+
+        let request = json!({ "type": "batch", "batch": { "steps": steps } });
+        let responses = self.pipeline(vec![request])?;
+        let result = responses
+            .first()
+            .map(|r| r["result"].clone())
+            .unwrap_or(Json::Null);
+
+        if let Some(error) = result["step_errors"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|e| !e.is_null())
+        {
+            return Err(remote(error_message(error)));
+        }
+
+        let step_results = result["step_results"].as_array().cloned().unwrap_or_default();
+        Ok(step_results
+            .iter()
+            .skip(1)
+            .take(n)
+            .map(parse_result)
+            .collect())
+    }
+
+    // -------------------------------------------------------------------------
+    // Rows
+    // -------------------------------------------------------------------------
+
+    /// Build memories from rows selected with `MEMORY_COLUMNS`, fetching
+    /// their tags in one extra request
+    fn memories_from_rows(&self, rows: &[Vec<Json>]) -> StoreResult<Vec<Memory>> {
+        let ids: Vec<i64> = rows.iter().filter_map(|row| integer(&row[0])).collect();
+        let mut tags: HashMap<i64, Vec<String>> = HashMap::new();
+
+        if !ids.is_empty() {
+            let tag_rows = self.query(Stmt::new(
+                "SELECT memory_id, tag FROM tags WHERE memory_id IN (SELECT value FROM json_each(?1)) ORDER BY rowid",
+                vec![Value::Text(json!(ids).to_string())],
+            ))?;
+            for row in tag_rows {
+                if let (Some(id), Some(tag)) = (integer(&row[0]), text(&row[1])) {
+                    tags.entry(id).or_default().push(tag);
+                }
+            }
+        }
+
+        Ok(rows
+            .iter()
+            .map(|row| {
+                let id = integer(&row[0]).unwrap_or_default();
+                Memory {
+                    id,
+                    content: text(&row[1]).unwrap_or_default(),
+                    confidence: real(&row[2]).unwrap_or(0.5),
+                    tags: tags.remove(&id).unwrap_or_default(),
+                    created_at: text(&row[3]).unwrap_or_default(),
+                    updated_at: text(&row[4]).unwrap_or_default(),
+                    last_accessed_at: text(&row[5]),
+                    access_count: integer(&row[6]).unwrap_or(0),
+                    importance: real(&row[7]).unwrap_or(0.0),
+                    kind: text(&row[8]),
+                    expires_at: text(&row[9]),
+                    source: text(&row[10]),
+                    namespace: text(&row[11]),
+                    pinned: integer(&row[12]).unwrap_or(0) != 0,
+                    blob: text(&row[13]),
+                    session_id: text(&row[14]),
+                    global: false,
+                }
+            })
+            .collect())
+    }
+
+    fn select_memories(&self, sql: String, args: Vec<Value>) -> StoreResult<Vec<Memory>> {
+        let rows = self.query(Stmt::new(sql, args))?;
+        self.memories_from_rows(&rows)
+    }
+
+    /// The insert for a memory, then its tags
+    fn insert_stmts(memory: &NewMemory, embedding: Option<&Embedding>) -> Vec<Stmt> {
+        let now = now_rfc3339();
+        let created_at = memory.created_at.clone().unwrap_or_else(|| now.clone());
+
+        let mut stmts = vec![Stmt::new(
+            "INSERT INTO memories (content, confidence, embedding, created_at, updated_at, kind, expires_at, source, namespace, pinned, blob, embedding_model, embedding_hash, session_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+            vec![
+                Value::Text(memory.content.clone()),
+                Value::Real(memory.confidence),
+                embedding.map_or(Value::Null, |e| Value::Blob(serialize_embedding(e.vector))),
+                Value::Text(created_at),
+                Value::Text(now),
+                optional_text(memory.kind.as_ref().map(|k| k.to_lowercase())),
+                optional_text(memory.expires_at.clone()),
+                optional_text(memory.source.clone()),
+                optional_text(memory.namespace.clone()),
+                Value::Integer(memory.pinned as i64),
+                optional_text(memory.blob.clone()),
+                optional_text(embedding.map(|e| e.model.to_string())),
+                optional_text(embedding.map(|e| e.text_hash.clone())),
+                optional_text(memory.session_id.clone()),
+            ],
+        )];
+        // AUTOINCREMENT keeps the new id in sqlite_sequence for the rest of the transaction
+        for tag in &memory.tags {
+            stmts.push(Stmt::new(
+                "INSERT OR IGNORE INTO tags (memory_id, tag) VALUES ((SELECT seq FROM sqlite_sequence WHERE name = 'memories'), ?1)",
+                vec![Value::Text(tag.to_lowercase())],
+            ));
+        }
+        stmts
+    }
+
+    fn tag_stmts(id: &Value, tags: &[String]) -> Vec<Stmt> {
+        tags.iter()
+            .map(|tag| {
+                Stmt::new(
+                    "INSERT OR IGNORE INTO tags (memory_id, tag) VALUES (?1, ?2)",
+                    vec![id.clone(), Value::Text(tag.to_lowercase())],
+                )
+            })
+            .collect()
+    }
+}
+
+impl Store for LibsqlStore {
+    fn insert(&self, memory: &NewMemory, embedding: Option<&Embedding>) -> StoreResult<i64> {
+        let results = self.transaction(Self::insert_stmts(memory, embedding))?;
+        results
+            .first()
+            .and_then(|r| r.last_insert_rowid)
+            .ok_or_else(|| remote("libsql server did not return the new id".to_string()))
+    }
+
+    fn insert_batch(&self, memories: &[(NewMemory, Option<Embedding>)]) -> StoreResult<Vec<i64>> {
+        // Each memory's insert comes first among its statements
+        let mut stmts = Vec::new();
+        let mut inserts = Vec::new();
+        for (memory, embedding) in memories {
+            inserts.push(stmts.len());
+            stmts.extend(Self::insert_stmts(memory, embedding.as_ref()));
+        }
+        let results = self.transaction(stmts)?;
+        inserts
+            .into_iter()
+            .map(|i| {
+                results
+                    .get(i)
+                    .and_then(|r| r.last_insert_rowid)
+                    .ok_or_else(|| remote("libsql server did not return the new id".to_string()))
+            })
+            .collect()
+    }
+
+    fn restore(&self, memory: &Memory, embedding: Option<&Embedding>) -> StoreResult<()> {
+        let id = Value::Integer(memory.id);
+        let mut stmts = vec![Stmt::new(
+            "INSERT INTO memories (id, content, confidence, embedding, created_at, updated_at, last_accessed_at, access_count, importance, kind, expires_at, source, namespace, pinned, blob, embedding_model, embedding_hash, session_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)",
+            vec![
+                id.clone(),
+                Value::Text(memory.content.clone()),
+                Value::Real(memory.confidence),
+                embedding.map_or(Value::Null, |e| Value::Blob(serialize_embedding(e.vector))),
+                Value::Text(memory.created_at.clone()),
+                Value::Text(memory.updated_at.clone()),
+                optional_text(memory.last_accessed_at.clone()),
+                Value::Integer(memory.access_count),
+                Value::Real(memory.importance),
+                optional_text(memory.kind.clone()),
+                optional_text(memory.expires_at.clone()),
+                optional_text(memory.source.clone()),
+                optional_text(memory.namespace.clone()),
+                Value::Integer(memory.pinned as i64),
+                optional_text(memory.blob.clone()),
+                optional_text(embedding.map(|e| e.model.to_string())),
+                optional_text(embedding.map(|e| e.text_hash.clone())),
+                optional_text(memory.session_id.clone()),
+            ],
+        )];
+        stmts.extend(Self::tag_stmts(&id, &memory.tags));
+        stmts.push(Stmt::new(
+            "DELETE FROM tombstones WHERE created_at = ?1",
+            vec![Value::Text(memory.created_at.clone())],
+        ));
+        self.transaction(stmts)?;
+        Ok(())
+    }
+
+    fn get(&self, id: i64) -> StoreResult<Option<Memory>> {
+        let sql = format!("SELECT {} FROM memories m WHERE m.id = ?1", MEMORY_COLUMNS);
+        Ok(self.select_memories(sql, vec![Value::Integer(id)])?.pop())
+    }
+
+    fn get_many(&self, ids: &[i64]) -> StoreResult<Vec<Memory>> {
+        let sql = format!(
+            "SELECT {} FROM memories m WHERE m.id IN (SELECT value FROM json_each(?1))",
+            MEMORY_COLUMNS
+        );
+        let mut found: HashMap<i64, Memory> = self
+            .select_memories(sql, vec![Value::Text(json!(ids).to_string())])?
+            .into_iter()
+            .map(|m| (m.id, m))
+            .collect();
+        Ok(ids.iter().filter_map(|id| found.remove(id)).collect())
+    }
+
+    fn scan_vectors(
+        &self,
+        filter: &MemoryFilter,
+        visit: &mut dyn FnMut(VectorRow) -> bool,
+    ) -> StoreResult<()> {
+        let (where_clause, values) = filter_clause(filter);
+        let sql = format!(
+            "SELECT {} FROM memories m {} {} m.embedding IS NOT NULL",
+            VECTOR_COLUMNS,
+            where_clause,
+            if where_clause.is_empty() { "WHERE" } else { "AND" }
+        );
+        for row in self.query(Stmt::new(sql, values))? {
+            let vector = VectorRow {
+                id: integer(&row[0]).unwrap_or_default(),
+                confidence: real(&row[1]).unwrap_or(0.0),
+                updated_at: text(&row[2]).unwrap_or_default(),
+                importance: real(&row[3]).unwrap_or(0.0),
+                access_count: integer(&row[4]).unwrap_or(0),
+                last_accessed_at: text(&row[5]),
+                embedding: deserialize_embedding(&blob(&row[6])),
+            };
+            if !visit(vector) {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn get_all_with_embeddings(&self, filter: &MemoryFilter) -> StoreResult<Vec<(Memory, Vec<f32>)>> {
+        let (where_clause, values) = filter_clause(filter);
+        let sql = format!(
+            "SELECT {}, m.embedding FROM memories m {} {} m.embedding IS NOT NULL",
+            MEMORY_COLUMNS,
+            where_clause,
+            if where_clause.is_empty() { "WHERE" } else { "AND" }
+        );
+        let rows = self.query(Stmt::new(sql, values))?;
+        let embeddings: Vec<Vec<f32>> = rows
+            .iter()
+            .map(|row| deserialize_embedding(&blob(&row[MEMORY_COLUMN_COUNT])))
+            .collect();
+        Ok(self
+            .memories_from_rows(&rows)?
+            .into_iter()
+            .zip(embeddings)
+            .collect())
+    }
+
+    fn search_fts(&self, query: &str, filter: &MemoryFilter, limit: usize) -> StoreResult<Vec<TextMatch>> {
+        let (sql, values) = fts_query(query, filter, limit);
+        let rows = self.query(Stmt::new(sql, values))?;
+        let extras: Vec<(f64, String)> = rows
+            .iter()
+            .map(|row| {
+                (
+                    real(&row[MEMORY_COLUMN_COUNT]).unwrap_or(0.0),
+                    text(&row[MEMORY_COLUMN_COUNT + 1]).unwrap_or_default(),
+                )
+            })
+            .collect();
+        Ok(self
+            .memories_from_rows(&rows)?
+            .into_iter()
+            .zip(extras)
+            .map(|(memory, (score, snippet))| TextMatch { memory, score, snippet })
+            .collect())
+    }
+
+    fn list(&self, filter: &MemoryFilter, limit: usize, order: SortOrder) -> StoreResult<Vec<Memory>> {
+        let (where_clause, mut values) = filter_clause(filter);
+        let sql = format!(
+            "SELECT {} FROM memories m {} ORDER BY {} LIMIT ?",
+            MEMORY_COLUMNS,
+            where_clause,
+            order.sql()
+        );
+        values.push(Value::Integer(limit as i64));
+        self.select_memories(sql, values)
+    }
+
+    fn update(&self, id: i64, changes: &MemoryUpdate) -> StoreResult<bool> {
+        let now = Value::Text(now_rfc3339());
+        let id = Value::Integer(id);
+        let mut stmts = Vec::new();
+        let mut set = |column: &str, value: Value| {
+            stmts.push(Stmt::new(
+                format!("UPDATE memories SET {} = ?1, updated_at = ?2 WHERE id = ?3", column),
+                vec![value, now.clone(), id.clone()],
+            ));
+        };
+
+        if let Some(content) = &changes.content {
+            set("content", Value::Text(content.clone()));
+        }
+        if let Some(blob) = &changes.blob {
+            set("blob", optional_text(Some(blob.clone()).filter(|b| !b.is_empty())));
+        }
+        if let Some(k) = &changes.kind {
+            // An empty kind clears it
+            set("kind", optional_text(Some(k.to_lowercase()).filter(|k| !k.is_empty())));
+        }
+        if let Some(ns) = &changes.namespace {
+            // An empty namespace moves it back to the default
+            set("namespace", optional_text(Some(ns.clone()).filter(|n| !n.is_empty())));
+        }
+        if let Some(pinned) = changes.pinned {
+            set("pinned", Value::Integer(pinned as i64));
+        }
+        if let Some(conf) = changes.confidence {
+            set("confidence", Value::Real(conf));
+        }
+        if let Some(new_tags) = &changes.tags {
+            // Replace all tags
+            stmts.push(Stmt::new(
+                "DELETE FROM tags WHERE memory_id = ?1",
+                vec![id.clone()],
+            ));
+            stmts.extend(Self::tag_stmts(&id, new_tags));
+            stmts.push(Stmt::new(
+                "UPDATE memories SET updated_at = ?1 WHERE id = ?2",
+                vec![now.clone(), id.clone()],
+            ));
+        }
+
+        if !stmts.is_empty() {
+            self.transaction(stmts)?;
+        }
+        Ok(true)
+    }
+
+    fn record_access(&self, ids: &[i64]) -> StoreResult<()> {
+        let now = now_rfc3339();
+        let stmts = ids
+            .iter()
+            .map(|id| {
+                Stmt::new(
+                    "UPDATE memories SET last_accessed_at = ?1, access_count = access_count + 1 WHERE id = ?2",
+                    vec![Value::Text(now.clone()), Value::Integer(*id)],
+                )
+            })
+            .collect::<Vec<_>>();
+        if !stmts.is_empty() {
+            self.transaction(stmts)?;
+        }
+        Ok(())
+    }
+
+    fn set_importance(&self, scores: &[(i64, f64)]) -> StoreResult<()> {
+        let stmts = scores
+            .iter()
+            .map(|(id, score)| {
+                Stmt::new(
+                    "UPDATE memories SET importance = ?1 WHERE id = ?2",
+                    vec![Value::Real(*score), Value::Integer(*id)],
+                )
+            })
+            .collect::<Vec<_>>();
+        if !stmts.is_empty() {
+            self.transaction(stmts)?;
+        }
+        Ok(())
+    }
+
+    fn has_source(&self, source: &str) -> StoreResult<bool> {
+        let rows = self.query(Stmt::new(
+            "SELECT COUNT(*) FROM memories WHERE source = ?1",
+            vec![Value::Text(source.to_string())],
+        ))?;
+        Ok(rows.first().and_then(|r| integer(&r[0])).unwrap_or(0) > 0)
+    }
+
+    fn get_by_created_at(&self, created_at: &str) -> StoreResult<Option<Memory>> {
+        let sql = format!("SELECT {} FROM memories m WHERE m.created_at = ?1", MEMORY_COLUMNS);
+        Ok(self
+            .select_memories(sql, vec![Value::Text(created_at.to_string())])?
+            .into_iter()
+            .next())
+    }
+
+    fn get_by_text_hash(&self, text_hash: &str) -> StoreResult<Vec<Memory>> {
+        let sql = format!("SELECT {} FROM memories m WHERE m.embedding_hash = ?1 ORDER BY m.id", MEMORY_COLUMNS);
+        self.select_memories(sql, vec![Value::Text(text_hash.to_string())])
+    }
+
+    fn get_prunable(&self, criteria: &PruneCriteria) -> StoreResult<Vec<Memory>> {
+        let (where_clause, values) = prune_clause(criteria);
+        let sql = format!(
+            "SELECT {} FROM memories m WHERE {} ORDER BY m.id",
+            MEMORY_COLUMNS, where_clause
+        );
+        self.select_memories(sql, values)
+    }
+
+    fn delete(&self, id: i64) -> StoreResult<bool> {
+        let id = Value::Integer(id);
+        let results = self.transaction(vec![
+            Stmt::new(
+                "INSERT OR REPLACE INTO tombstones (created_at, deleted_at) SELECT created_at, ?1 FROM memories WHERE id = ?2",
+                vec![Value::Text(now_rfc3339()), id.clone()],
+            ),
+            Stmt::new("DELETE FROM tags WHERE memory_id = ?1", vec![id.clone()]),
+            Stmt::new("DELETE FROM attachments WHERE memory_id = ?1", vec![id.clone()]),
+            Stmt::new("DELETE FROM memories WHERE id = ?1", vec![id]),
+        ])?;
+        Ok(results.last().is_some_and(|r| r.affected > 0))
+    }
+
+    fn add_tombstone(&self, tombstone: &Tombstone) -> StoreResult<()> {
+        self.execute(Stmt::new(
+            "INSERT OR REPLACE INTO tombstones (created_at, deleted_at) VALUES (?1, ?2)",
+            vec![
+                Value::Text(tombstone.created_at.clone()),
+                Value::Text(tombstone.deleted_at.clone()),
+            ],
+        ))?;
+        Ok(())
+    }
+
+    fn get_tombstone(&self, created_at: &str) -> StoreResult<Option<Tombstone>> {
+        let rows = self.query(Stmt::new(
+            "SELECT created_at, deleted_at FROM tombstones WHERE created_at = ?1",
+            vec![Value::Text(created_at.to_string())],
+        ))?;
+        Ok(rows.first().map(|r| tombstone_from_row(r)))
+    }
+
+    fn get_tombstones(&self) -> StoreResult<Vec<Tombstone>> {
+        let rows = self.query(Stmt::new(
+            "SELECT created_at, deleted_at FROM tombstones ORDER BY deleted_at",
+            vec![],
+        ))?;
+        Ok(rows.iter().map(|r| tombstone_from_row(r)).collect())
+    }
+
+    fn purge_tombstones(&self, before: &str) -> StoreResult<usize> {
+        let result = self.execute(Stmt::new(
+            "DELETE FROM tombstones WHERE deleted_at < ?1",
+            vec![Value::Text(before.to_string())],
+        ))?;
+        Ok(result.affected as usize)
+    }
+
+    fn count(&self) -> StoreResult<usize> {
+        let rows = self.query(Stmt::new("SELECT COUNT(*) FROM memories", vec![]))?;
+        Ok(rows.first().and_then(|r| integer(&r[0])).unwrap_or(0) as usize)
+    }
+
+    fn count_blob_refs(&self, hash: &str) -> StoreResult<usize> {
+        let rows = self.query(Stmt::new(
+            "SELECT COUNT(*) FROM memories WHERE blob = ?1",
+            vec![Value::Text(hash.to_string())],
+        ))?;
+        Ok(rows.first().and_then(|r| integer(&r[0])).unwrap_or(0) as usize)
+    }
+
+    fn get_all_tags(&self) -> StoreResult<Vec<(String, usize)>> {
+        let rows = self.query(Stmt::new(
+            "SELECT tag, COUNT(*) as count FROM tags GROUP BY tag ORDER BY count DESC",
+            vec![],
+        ))?;
+        Ok(rows
+            .iter()
+            .filter_map(|r| Some((text(&r[0])?, integer(&r[1])? as usize)))
+            .collect())
+    }
+
+    fn add_attachment(&self, attachment: &Attachment) -> StoreResult<i64> {
+        let result = self.execute(Stmt::new(
+            "INSERT INTO attachments (memory_id, path, start_line, end_line, snippet, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            vec![
+                Value::Integer(attachment.memory_id),
+                Value::Text(attachment.path.clone()),
+                attachment.lines.map_or(Value::Null, |(start, _)| Value::Integer(start as i64)),
+                attachment.lines.map_or(Value::Null, |(_, end)| Value::Integer(end as i64)),
+                optional_text(attachment.snippet.clone()),
+                Value::Text(attachment.created_at.clone()),
+            ],
+        ))?;
+        result
+            .last_insert_rowid
+            .ok_or_else(|| remote("libsql server did not return the new id".to_string()))
+    }
+
+    fn get_attachments(&self, memory_id: i64) -> StoreResult<Vec<Attachment>> {
+        let rows = self.query(Stmt::new(
+            "SELECT id, memory_id, path, start_line, end_line, snippet, created_at FROM attachments WHERE memory_id = ?1 ORDER BY id",
+            vec![Value::Integer(memory_id)],
+        ))?;
+        Ok(rows
+            .iter()
+            .map(|r| Attachment {
+                id: integer(&r[0]).unwrap_or_default(),
+                memory_id: integer(&r[1]).unwrap_or_default(),
+                path: text(&r[2]).unwrap_or_default(),
+                lines: integer(&r[3]).zip(integer(&r[4])).map(|(start, end)| (start as u32, end as u32)),
+                snippet: text(&r[5]),
+                created_at: text(&r[6]).unwrap_or_default(),
+            })
+            .collect())
+    }
+
+    fn sessions(&self, limit: usize) -> StoreResult<Vec<SessionSummary>> {
+        let rows = self.query(Stmt::new(
+            "SELECT session_id, COUNT(*), MIN(created_at), MAX(created_at) FROM memories WHERE session_id IS NOT NULL GROUP BY session_id ORDER BY MAX(created_at) DESC LIMIT ?1",
+            vec![Value::Integer(limit as i64)],
+        ))?;
+        Ok(rows
+            .iter()
+            .filter_map(|r| {
+                Some(SessionSummary {
+                    id: text(&r[0])?,
+                    count: integer(&r[1])? as usize,
+                    first: text(&r[2])?,
+                    last: text(&r[3])?,
+                })
+            })
+            .collect())
+    }
+
+    fn rename_tags(&self, from: &[String], to: &str) -> StoreResult<Vec<i64>> {
+        let placeholders = (2..from.len() + 2).map(|i| format!("?{}", i)).collect::<Vec<_>>().join(", ");
+        let mut values = vec![Value::Text(to.to_string())];
+        values.extend(from.iter().map(|t| Value::Text(t.clone())));
+
+        // The ids are selected in the same transaction as the rename, so
+        // they are exactly the memories it changes
+        let results = self.transaction(vec![
+            Stmt::new(
+                format!(
+                    "SELECT DISTINCT memory_id FROM tags WHERE tag IN ({}) AND tag != ?1 ORDER BY memory_id",
+                    placeholders
+                ),
+                values.clone(),
+            ),
+            Stmt::new(
+                format!(
+                    "INSERT OR IGNORE INTO tags (memory_id, tag) SELECT memory_id, ?1 FROM tags WHERE tag IN ({})",
+                    placeholders
+                ),
+                values.clone(),
+            ),
+            Stmt::new(
+                format!("DELETE FROM tags WHERE tag IN ({}) AND tag != ?1", placeholders),
+                values,
+            ),
+        ])?;
+        let ids: Vec<i64> = results
+            .first()
+            .map(|r| r.rows.iter().filter_map(|row| integer(&row[0])).collect())
+            .unwrap_or_default();
+        Ok(ids)
+    }
+
+    fn get_metadata(&self, key: &str) -> StoreResult<Option<String>> {
+        let rows = self.query(Stmt::new(
+            "SELECT value FROM metadata WHERE key = ?1",
+            vec![Value::Text(key.to_string())],
+        ))?;
+        Ok(rows.first().and_then(|r| text(&r[0])))
+    }
+
+    fn set_metadata(&self, key: &str, value: &str) -> StoreResult<()> {
+        self.execute(Stmt::new(
+            "INSERT OR REPLACE INTO metadata (key, value) VALUES (?1, ?2)",
+            vec![Value::Text(key.to_string()), Value::Text(value.to_string())],
+        ))?;
+        Ok(())
+    }
+
+    fn get_all_for_reindex(&self) -> StoreResult<Vec<(i64, String)>> {
+        let rows = self.query(Stmt::new("SELECT id, content FROM memories", vec![]))?;
+        Ok(rows
+            .iter()
+            .filter_map(|r| Some((integer(&r[0])?, text(&r[1])?)))
+            .collect())
+    }
+
+    fn get_pending_embeddings(&self, limit: usize) -> StoreResult<Vec<(i64, String)>> {
+        let rows = self.query(Stmt::new(
+            "SELECT id, content FROM memories WHERE embedding IS NULL ORDER BY id LIMIT ?1",
+            vec![Value::Integer(limit as i64)],
+        ))?;
+        Ok(rows
+            .iter()
+            .filter_map(|r| Some((integer(&r[0])?, text(&r[1])?)))
+            .collect())
+    }
+
+    fn count_pending_embeddings(&self) -> StoreResult<usize> {
+        let rows = self.query(Stmt::new(
+            "SELECT COUNT(*) FROM memories WHERE embedding IS NULL",
+            vec![],
+        ))?;
+        Ok(rows.first().and_then(|r| integer(&r[0])).unwrap_or(0) as usize)
+    }
+
+    fn get_stale_embeddings(&self, model: &str, dim: usize) -> StoreResult<Vec<(i64, String)>> {
+        let rows = self.query(Stmt::new(
+            format!("SELECT id, content FROM memories WHERE {} ORDER BY id", STALE_EMBEDDING),
+            vec![Value::Text(model.to_string()), Value::Integer((dim * 4) as i64)],
+        ))?;
+        Ok(rows
+            .iter()
+            .filter_map(|r| Some((integer(&r[0])?, text(&r[1])?)))
+            .collect())
+    }
+
+    fn update_embedding(&self, id: i64, embedding: &Embedding) -> StoreResult<()> {
+        self.execute(Stmt::new(
+            "UPDATE memories SET embedding = ?1, embedding_model = ?2, embedding_hash = ?3 WHERE id = ?4",
+            vec![
+                Value::Blob(serialize_embedding(embedding.vector)),
+                Value::Text(embedding.model.to_string()),
+                Value::Text(embedding.text_hash.clone()),
+                Value::Integer(id),
+            ],
+        ))?;
+        Ok(())
+    }
+
+    fn find_embedding(&self, model: &str, text_hash: &str) -> StoreResult<Option<Vec<f32>>> {
+        let rows = self.query(Stmt::new(
+            "SELECT embedding FROM memories WHERE embedding_hash = ?1 AND embedding_model = ?2 AND embedding IS NOT NULL LIMIT 1",
+            vec![Value::Text(text_hash.to_string()), Value::Text(model.to_string())],
+        ))?;
+        Ok(rows.first().map(|r| deserialize_embedding(&blob(&r[0]))))
+    }
+
+    fn get_embedding_hashes(&self, model: &str) -> StoreResult<HashMap<i64, String>> {
+        let rows = self.query(Stmt::new(
+            "SELECT id, embedding_hash FROM memories WHERE embedding_model = ?1 AND embedding_hash IS NOT NULL",
+            vec![Value::Text(model.to_string())],
+        ))?;
+        Ok(rows
+            .iter()
+            .filter_map(|r| Some((integer(&r[0])?, text(&r[1])?)))
+            .collect())
+    }
+}
+
+fn remote(message: String) -> StoreError {
+    StoreError::Remote(message)
+}
+
+fn error_message(error: &Json) -> String {
+    error["message"]
+        .as_str()
+        .unwrap_or("unknown libsql error")
+        .to_string()
+}
+
+fn parse_result(result: &Json) -> StmtResult {
+    StmtResult {
+        rows: result["rows"]
+            .as_array()
+            .map(|rows| {
+                rows.iter()
+                    .map(|r| r.as_array().cloned().unwrap_or_default())
+                    .collect()
+            })
+            .unwrap_or_default(),
+        affected: result["affected_row_count"].as_u64().unwrap_or(0),
+        last_insert_rowid: result["last_insert_rowid"]
+            .as_str()
+            .and_then(|id| id.parse().ok()),
+    }
+}
+
+fn tombstone_from_row(row: &[Json]) -> Tombstone {
+    Tombstone {
+        created_at: text(&row[0]).unwrap_or_default(),
+        deleted_at: text(&row[1]).unwrap_or_default(),
+    }
+}
+
+fn optional_text(value: Option<String>) -> Value {
+    value.map(Value::Text).unwrap_or(Value::Null)
+}
+
+// -----------------------------------------------------------------------------
+// Hrana values
+// -----------------------------------------------------------------------------
+
+fn value_to_json(value: &Value) -> Json {
+    match value {
+        Value::Null => json!({ "type": "null" }),
+        // Integers travel as strings to survive JSON's f64 numbers
+        Value::Integer(i) => json!({ "type": "integer", "value": i.to_string() }),
+        Value::Real(f) => json!({ "type": "float", "value": f }),
+        Value::Text(s) => json!({ "type": "text", "value": s }),
+        Value::Blob(b) => json!({ "type": "blob", "base64": base64_encode(b) }),
+    }
+}
+
+fn text(value: &Json) -> Option<String> {
+    match value["type"].as_str()? {
+        "text" => value["value"].as_str().map(String::from),
+        _ => None,
+    }
+}
+
+fn integer(value: &Json) -> Option<i64> {
+    match value["type"].as_str()? {
+        "integer" => value["value"].as_str()?.parse().ok(),
+        "float" => value["value"].as_f64().map(|f| f as i64),
+        _ => None,
+    }
+}
+
+fn real(value: &Json) -> Option<f64> {
+    match value["type"].as_str()? {
+        "float" => value["value"].as_f64(),
+        "integer" => value["value"].as_str()?.parse().ok(),
+        _ => None,
+    }
+}
+
+fn blob(value: &Json) -> Vec<u8> {
+    value["base64"]
+        .as_str()
+        .map(base64_decode)
+        .unwrap_or_default()
+}
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Standard base64 without padding, as Hrana sends blobs
+fn base64_encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            out.push(BASE64_ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+        }
+    }
+    out
+}
+
+/// Decode standard base64, with or without padding
+fn base64_decode(encoded: &str) -> Vec<u8> {
+    let mut out = Vec::with_capacity(encoded.len() * 3 / 4);
+    let (mut buffer, mut bits) = (0u32, 0);
+    for c in encoded.bytes().take_while(|c| *c != b'=') {
+        let Some(v) = BASE64_ALPHABET.iter().position(|a| *a == c) else {
+            continue;
+        };
+        buffer = buffer << 6 | v as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base64_round_trip() {
+        for data in [&b""[..], b"f", b"fo", b"foo", b"foob", &serialize_embedding(&[0.5, -1.25])] {
+            assert_eq!(base64_decode(&base64_encode(data)), data);
+        }
+        assert_eq!(base64_encode(b"foob"), "Zm9vYg");
+        assert_eq!(base64_decode("Zm9vYg=="), b"foob");
+    }
+
+    #[test]
+    fn test_values_round_trip() {
+        let values = [
+            Value::Integer(9_007_199_254_740_993),
+            Value::Real(0.75),
+            Value::Text("hi".to_string()),
+        ];
+        let json: Vec<Json> = values.iter().map(value_to_json).collect();
+        assert_eq!(integer(&json[0]), Some(9_007_199_254_740_993));
+        assert_eq!(real(&json[1]), Some(0.75));
+        assert_eq!(text(&json[2]).as_deref(), Some("hi"));
+        assert_eq!(text(&value_to_json(&Value::Null)), None);
+    }
+}
@@ -0,0 +1,217 @@
+//! Advisory lock serializing maintenance on a store.
+//!
+//! Reindex, optimize, restore, and doctor --fix rewrite large parts of the
+//! database; two of them interleaving (say a systemd timer and a manual run)
+//! can leave embeddings or the search index half from each. They hold an
+//! OS file lock on `.roots/maintenance.lock`, which names the holder so a
+//! waiting command can say what it is waiting for. The OS drops the lock
+//! when its holder exits, so a crashed run never leaves the store locked,
+//! and the file itself is never deleted.
+//!
+//! `FileLock` is the same OS lock for short critical sections, such as
+//! appending to a journal, that concurrent hooks must take turns at.
+
+use crate::error::Error;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::Write;
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+const LOCK_FILE: &str = "maintenance.lock";
+
+/// How often a waiting command checks the lock again
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Held for the duration of a maintenance operation, released on drop
+pub struct MaintenanceLock(File);
+
+/// Who holds the lock, as written in the lock file
+struct Holder {
+    pid: u32,
+    operation: String,
+    started_at: String,
+}
+
+impl MaintenanceLock {
+    /// Take the lock for `operation`, waiting for the current holder to
+    /// finish, or failing at once with `no_wait`
+    pub fn acquire(roots_path: &Path, operation: &str, no_wait: bool) -> Result<Self, Error> {
+        let path = roots_path.join(LOCK_FILE);
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+        let mut announced = false;
+
+        loop {
+            match file.try_lock() {
+                Ok(()) => {
+                    let record = format!(
+                        "{}\n{}\n{}\n",
+                        std::process::id(),
+                        operation,
+                        crate::dates::now_rfc3339()
+                    );
+                    file.set_len(0)
+                        .and_then(|_| (&file).write_all(record.as_bytes()))
+                        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+                    return Ok(Self(file));
+                }
+                Err(TryLockError::WouldBlock) => {}
+                Err(TryLockError::Error(e)) => {
+                    return Err(format!("Failed to lock {}: {}", path.display(), e).into());
+                }
+            }
+
+            // The holder may not have written its record yet
+            let busy = match read_holder(&path) {
+                Some(holder) => format!(
+                    "{} (pid {}, started {}) is running on this store",
+                    holder.operation, holder.pid, holder.started_at
+                ),
+                None => "Another maintenance operation is running on this store".to_string(),
+            };
+            if no_wait {
+                return Err(Error::Locked(busy));
+            }
+            if !announced {
+                eprintln!("Waiting: {}...", busy);
+                announced = true;
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
+
+impl Drop for MaintenanceLock {
+    fn drop(&mut self) {
+        // Clear the record while still holding the lock; closing the file
+        // releases it
+        let _ = self.0.set_len(0);
+    }
+}
+
+/// An exclusive OS lock on a file, waited for, released on drop
+pub struct FileLock(File);
+
+impl FileLock {
+    /// Lock `path`, creating it if needed, once no one else holds it
+    pub fn acquire(path: &Path) -> std::io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        file.lock()?;
+        Ok(Self(file))
+    }
+
+    /// Lock `path` if no one else holds it, without waiting
+    pub fn try_acquire(path: &Path) -> std::io::Result<Option<Self>> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        match file.try_lock() {
+            Ok(()) => Ok(Some(Self(file))),
+            Err(TryLockError::WouldBlock) => Ok(None),
+            Err(TryLockError::Error(e)) => Err(e),
+        }
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = self.0.unlock();
+    }
+}
+
+fn read_holder(path: &Path) -> Option<Holder> {
+    let content = fs::read_to_string(path).ok()?;
+    let mut lines = content.lines();
+    Some(Holder {
+        pid: lines.next()?.parse().ok()?,
+        operation: lines.next()?.to_string(),
+        started_at: lines.next()?.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Barrier};
+
+    #[test]
+    fn test_lock_excludes_and_recovers() {
+        let dir = std::env::temp_dir().join(format!("roots-lock-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let lock = MaintenanceLock::acquire(&dir, "reindex", true).unwrap();
+        let err = MaintenanceLock::acquire(&dir, "optimize", true).err().unwrap();
+        assert_eq!(err.kind(), "locked");
+        assert!(err.to_string().starts_with("reindex (pid"));
+        drop(lock);
+
+        // A record left by a process that died without unlocking is ignored
+        fs::write(dir.join(LOCK_FILE), "4194304999\nreindex\n2026-01-01T00:00:00Z\n").unwrap();
+        assert!(MaintenanceLock::acquire(&dir, "optimize", true).is_ok());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_file_lock_is_exclusive_until_dropped() {
+        let dir = std::env::temp_dir().join(format!("roots-file-lock-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("journal.lock");
+
+        let held = FileLock::acquire(&path).unwrap();
+        assert!(FileLock::try_acquire(&path).unwrap().is_none());
+        drop(held);
+        let again = FileLock::try_acquire(&path).unwrap();
+        assert!(again.is_some());
+        // A waiter gets it as soon as the holder lets go
+        let waiter = thread::spawn(move || FileLock::acquire(&path).is_ok());
+        thread::sleep(Duration::from_millis(50));
+        drop(again);
+        assert!(waiter.join().unwrap());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_concurrent_acquirers_get_one_lock() {
+        let dir = std::env::temp_dir().join(format!("roots-lock-race-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        for round in 0..50 {
+            // Either acquirer may also find an empty or unreadable record
+            if round % 2 == 0 {
+                fs::write(dir.join(LOCK_FILE), "").unwrap();
+            }
+            let barrier = Arc::new(Barrier::new(2));
+            let attempts: Vec<_> = (0..2)
+                .map(|_| {
+                    let (dir, barrier) = (dir.clone(), barrier.clone());
+                    thread::spawn(move || {
+                        barrier.wait();
+                        let lock = MaintenanceLock::acquire(&dir, "reindex", true);
+                        let acquired = lock.is_ok();
+                        // Hold it until both have tried
+                        barrier.wait();
+                        acquired
+                    })
+                })
+                .collect();
+            let acquired = attempts.into_iter().map(|t| t.join().unwrap()).filter(|a| *a).count();
+            assert_eq!(acquired, 1, "round {}", round);
+            assert!(dir.join(LOCK_FILE).exists());
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
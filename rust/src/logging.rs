@@ -0,0 +1,44 @@
+//! Diagnostics for `-v` and `--log-file`.
+//!
+//! Opening the store, embedding, store queries, ranking, and server
+//! requests run in `tracing` spans, each logged with how long it took when
+//! it closes. `-v` shows those, `-vv` adds the detail inside them (cache
+//! hits, row counts, recall phase timings), `-vvv` everything. Without
+//! either flag nothing is subscribed and the spans cost next to nothing.
+//!
+//! Hooks print their answer on stdout, so logs go to stderr or, for a
+//! `roots context` that only misbehaves inside a hook, to the file named
+//! by `--log-file` / `ROOTS_LOG_FILE`.
+
+use std::fs::OpenOptions;
+use std::path::Path;
+use std::sync::Mutex;
+use tracing::Level;
+use tracing_subscriber::fmt::format::FmtSpan;
+
+/// Start logging at `verbose` (the number of `-v`s), to `log_file` if given
+pub fn init(verbose: u8, log_file: Option<&Path>) -> Result<(), String> {
+    let level = match (verbose, log_file) {
+        (0, None) => return Ok(()),
+        (0 | 1, _) => Level::INFO,
+        (2, _) => Level::DEBUG,
+        _ => Level::TRACE,
+    };
+    let logger = tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_span_events(FmtSpan::CLOSE)
+        .with_target(false);
+
+    match log_file {
+        Some(path) => {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map_err(|e| format!("Failed to open log file {}: {}", path.display(), e))?;
+            logger.with_ansi(false).with_writer(Mutex::new(file)).init();
+        }
+        None => logger.with_writer(std::io::stderr).init(),
+    }
+    Ok(())
+}
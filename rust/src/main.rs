@@ -1,11 +1,54 @@
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, Parser, Subcommand};
+use clap_complete::engine::{ArgValueCandidates, ArgValueCompleter};
+use error::Error;
+use types::MemoryFilter;
 
+mod aggregate;
+mod autotag;
+mod backup;
+mod batch;
+mod bench;
+mod blobs;
+mod bootstrap;
+mod changelog;
+mod chunk;
 mod cli;
+mod complete;
 mod config;
+mod dates;
+mod draft;
 mod embeddings;
+mod error;
+mod eval;
+mod expand;
+mod graph;
+mod hook;
+mod http;
 mod index;
+mod ingest;
+mod libsql;
+mod lock;
+mod logging;
+mod managed;
 mod memory;
+mod picker;
+mod proto;
+mod query;
+mod query_cache;
+mod query_log;
+mod resolve;
+mod server;
+mod source;
+mod store;
+mod term;
+#[cfg(test)]
+mod test_support;
+mod timing;
+mod transcript;
 mod types;
+mod undo;
+mod wire;
+mod worthiness;
 
 #[derive(Parser)]
 #[command(name = "roots")]
@@ -14,6 +57,29 @@ mod types;
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// Print recall, list, show, stats, tags, and context results as text
+    /// or as JSON for scripts
+    #[arg(long, global = true, env = "ROOTS_OUTPUT", default_value = "text", value_parser = cli::Output::parse)]
+    output: cli::Output,
+
+    /// Log timings of embedding, store queries, and ranking to stderr
+    /// (-vv for detail)
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Append the log to a file instead, e.g. to see inside hooks
+    #[arg(long, global = true, env = "ROOTS_LOG_FILE")]
+    log_file: Option<std::path::PathBuf>,
+
+    /// Plain output even on a terminal (also NO_COLOR)
+    #[arg(long, global = true)]
+    no_color: bool,
+
+    /// Apply a profile from the global config's `profiles:` over the
+    /// project and global settings
+    #[arg(long, global = true, env = "ROOTS_PROFILE", add = ArgValueCandidates::new(complete::profiles))]
+    profile: Option<String>,
 }
 
 #[derive(Subcommand)]
@@ -29,7 +95,10 @@ enum Commands {
         hooks: bool,
     },
 
-    /// Install Claude Code hooks
+    /// Guided first-run setup: embeddings, agent hooks, context, global store
+    Setup,
+
+    /// Install Claude Code hooks, or rules files for other editors
     Hooks {
         /// Directory containing .roots
         #[arg(short, long, default_value = ".")]
@@ -42,49 +111,246 @@ enum Commands {
         /// Add context hook on user message (none, tags, lite, semantic)
         #[arg(long, default_value = "none", value_parser = ["none", "tags", "lite", "semantic"])]
         context_mode: String,
+
+        /// Offer edits and the agent's replies to `remember --auto`, which
+        /// keeps those worth remembering
+        #[arg(long)]
+        auto_remember: bool,
+
+        /// Capture decisions and lessons from each session's transcript
+        /// when it ends
+        #[arg(long)]
+        capture_sessions: bool,
+
+        /// Agent to set up: Claude Code hooks, or a rules file with the
+        /// prime output for Cursor or Windsurf
+        #[arg(long, default_value = "claude", value_parser = cli::hooks::TOOLS.to_vec())]
+        tool: String,
+
+        /// Install into ~/.claude/settings.json for every project; hooks
+        /// do nothing in directories without a .roots store
+        #[arg(long, conflicts_with_all = ["path", "tool"])]
+        global: bool,
+
+        /// Install a git post-commit hook that remembers commit messages
+        /// matching `commit_pattern` instead
+        #[arg(long, conflicts_with_all = ["tool", "global", "context_mode", "auto_remember", "capture_sessions"])]
+        git: bool,
     },
 
     /// Remember something
     Remember {
-        /// Content to remember
-        content: String,
+        /// Content to remember (`-` reads it from stdin)
+        #[arg(required_unless_present_any = ["hook_stdin", "stdin", "edit", "from_file"])]
+        content: Option<String>,
+
+        /// Remember every memory in a file, in one transaction: JSONL, a
+        /// YAML list, or a markdown list (--tags are added to each; other
+        /// options are defaults)
+        #[arg(long, conflicts_with_all = ["content", "stdin", "edit", "hook_stdin", "auto", "pin"])]
+        from_file: Option<String>,
+
+        /// Write the memory in $EDITOR, with tags, confidence, and kind as
+        /// front-matter (any content given starts the buffer)
+        #[arg(long, conflicts_with_all = ["stdin", "hook_stdin", "auto"])]
+        edit: bool,
+
+        /// Read the content from stdin, e.g. `git log -1 | roots remember --stdin`
+        #[arg(long, conflicts_with_all = ["content", "hook_stdin"])]
+        stdin: bool,
 
         /// Comma-separated tags
-        #[arg(short, long, default_value = "")]
+        #[arg(short, long, default_value = "", add = ArgValueCompleter::new(complete::tag_list))]
         tags: String,
 
-        /// Confidence (0-1)
-        #[arg(short, long, default_value = "0.5")]
-        confidence: f64,
+        /// Confidence (0-1; default 0.5, or 0.3 with --auto)
+        #[arg(short, long)]
+        confidence: Option<f64>,
+
+        /// Memory kind (decision, preference, fact, todo)
+        #[arg(short, long, value_parser = types::MEMORY_KINDS.to_vec())]
+        kind: Option<String>,
+
+        /// Expire after a duration (e.g. 12h, 30d, 2w)
+        #[arg(short, long)]
+        expires: Option<String>,
+
+        /// Namespace to store in (default: chosen by routing rules)
+        #[arg(long)]
+        namespace: Option<String>,
+
+        /// Where the claim comes from: file[:line[-line]], URL, or command
+        #[arg(long, value_parser = source::parse_arg)]
+        source: Option<String>,
+
+        /// Where the write comes from, for write policies (cli, hook, mcp)
+        #[arg(long, env = "ROOTS_ORIGIN", default_value = "cli", value_parser = types::WRITE_ORIGINS.to_vec())]
+        origin: String,
+
+        /// Store in the user-global store (~/.roots), shared by all projects
+        #[arg(short, long)]
+        global: bool,
+
+        /// Always include in hook context, regardless of context_max_age
+        #[arg(long)]
+        pin: bool,
+
+        /// Agent session the memory was learned in (a hook payload's wins)
+        #[arg(long, env = "ROOTS_SESSION")]
+        session: Option<String>,
+
+        /// Store now and embed in the background (default: defer_embeddings config)
+        #[arg(long)]
+        defer: bool,
+
+        /// Auto-capture: store only if it scores at least capture_threshold
+        /// for worthiness, otherwise log it to .roots/rejected.jsonl.
+        /// Untagged memories get the existing tags they name
+        #[arg(long)]
+        auto: bool,
+
+        /// Capture from the Claude Code hook payload on stdin: the new text
+        /// of an edit (PostToolUse) or the agent's last reply (Stop)
+        #[arg(long, requires = "auto", conflicts_with = "content")]
+        hook_stdin: bool,
+    },
+
+    /// Remember the decisions and lessons of a Claude Code session,
+    /// tagged with its session id
+    Capture {
+        /// JSONL transcript of the session
+        #[arg(long, required_unless_present_any = ["hook_stdin", "commit"])]
+        transcript: Option<String>,
+
+        /// Take the transcript path and session id from the hook payload
+        /// on stdin (SessionEnd)
+        #[arg(long)]
+        hook_stdin: bool,
+
+        /// Remember a git commit's message instead (default: HEAD), if it
+        /// matches `commit_pattern`
+        #[arg(long, value_name = "REV", num_args = 0..=1, default_missing_value = "HEAD",
+              conflicts_with_all = ["transcript", "hook_stdin"])]
+        commit: Option<String>,
     },
 
     /// Recall memories by search
     Recall {
-        /// Search query (omit for recent)
+        /// Search query (omit for recent). May include filters: tag:<t>,
+        /// kind:<k>, ns:<namespace>, after:<date>, before:<date>, conf>=<n>
         query: Option<String>,
 
-        /// Search by tag instead
-        #[arg(short, long)]
+        /// Only memories with this tag (without a query, list them)
+        #[arg(short, long, add = ArgValueCandidates::new(complete::tags))]
         tag: Option<String>,
 
+        /// Only memories of this kind
+        #[arg(short, long, value_parser = types::MEMORY_KINDS.to_vec())]
+        kind: Option<String>,
+
+        /// Only memories in this namespace (e.g. quarantine)
+        #[arg(long)]
+        namespace: Option<String>,
+
         /// Maximum results
         #[arg(short = 'n', long, default_value = "5")]
         limit: usize,
+
+        /// Search every project registered by `roots init`
+        #[arg(long, requires = "query")]
+        all_projects: bool,
+
+        /// Only results scoring at least this (default 0.5 with --stream)
+        #[arg(long)]
+        threshold: Option<f64>,
+
+        /// Print results as the scan finds them instead of best first
+        #[arg(long, requires = "query", conflicts_with = "all_projects")]
+        stream: bool,
+
+        /// Match exact words instead of meaning (FTS5 syntax: "a phrase",
+        /// AND/OR/NOT, prefix*), best BM25 match first with highlights
+        #[arg(long, requires = "query", conflicts_with_all = ["all_projects", "stream", "threshold"])]
+        fts: bool,
+
+        /// Break each result's score down into the signals it blends
+        #[arg(long, requires = "query", conflicts_with_all = ["all_projects", "stream", "fts"])]
+        explain: bool,
+
+        /// Rerank the top embedding matches with the server's cross-encoder
+        /// (default with `rerank: true` in config)
+        #[arg(long, requires = "query", conflicts_with_all = ["all_projects", "stream", "fts", "explain"])]
+        rerank: bool,
+
+        /// Only memories created or updated since this date or duration ago
+        /// (2024-06-15, 2024-06, 2w, 3d)
+        #[arg(long, value_parser = dates::parse_point)]
+        since: Option<String>,
+
+        /// Only memories created or updated before this date or duration ago
+        #[arg(long, value_parser = dates::parse_point)]
+        until: Option<String>,
+
+        /// Leave out memories with this tag (repeatable)
+        #[arg(long = "exclude-tag", value_name = "TAG", add = ArgValueCandidates::new(complete::tags))]
+        exclude_tags: Vec<String>,
+
+        /// Leave out memories containing this text (repeatable)
+        #[arg(long = "exclude", value_name = "TEXT")]
+        exclude_text: Vec<String>,
+    },
+
+    /// Show a memory in full
+    Show {
+        /// Memory ID to show
+        #[arg(add = ArgValueCandidates::new(complete::ids))]
+        id: i64,
+    },
+
+    /// Attach a file, or lines of one, to a memory (shown by `show`)
+    Attach {
+        /// Memory ID
+        #[arg(add = ArgValueCandidates::new(complete::ids))]
+        id: i64,
+
+        /// File to attach
+        path: std::path::PathBuf,
+
+        /// Only these lines (12 or 10-40), kept as they are now
+        #[arg(long, value_parser = source::parse_line_range)]
+        lines: Option<(u32, u32)>,
     },
 
     /// Forget a memory
     Forget {
         /// Memory ID to forget
-        id: i64,
+        #[arg(required_unless_present = "pick", add = ArgValueCandidates::new(complete::ids))]
+        id: Option<i64>,
+
+        /// Choose the memory with a fuzzy picker instead of giving its ID
+        #[arg(long, conflicts_with = "id")]
+        pick: bool,
 
         /// Skip confirmation
         #[arg(short, long)]
         force: bool,
     },
 
+    /// Edit a memory's content in $VISUAL / $EDITOR
+    Edit {
+        /// Memory ID to edit
+        #[arg(required_unless_present = "pick", add = ArgValueCandidates::new(complete::ids))]
+        id: Option<i64>,
+
+        /// Choose the memory with a fuzzy picker instead of giving its ID
+        #[arg(long, conflicts_with = "id")]
+        pick: bool,
+    },
+
     /// Update a memory
     Update {
         /// Memory ID
+        #[arg(add = ArgValueCandidates::new(complete::ids))]
         id: i64,
 
         /// New confidence
@@ -92,79 +358,679 @@ enum Commands {
         confidence: Option<f64>,
 
         /// New tags (comma-separated, replaces existing)
-        #[arg(short, long)]
+        #[arg(short, long, add = ArgValueCompleter::new(complete::tag_list))]
         tags: Option<String>,
+
+        /// New kind (empty string clears it)
+        #[arg(short, long)]
+        kind: Option<String>,
+
+        /// Move to a namespace (empty string releases from quarantine)
+        #[arg(long)]
+        namespace: Option<String>,
+
+        /// Always include in hook context, regardless of context_max_age
+        #[arg(long, conflicts_with = "unpin")]
+        pin: bool,
+
+        /// Let context_max_age apply again
+        #[arg(long)]
+        unpin: bool,
     },
 
+    /// Reverse the latest remember, forget, or update (run again to step further back)
+    Undo,
+
     /// List recent memories
     List {
         /// Filter by tag
-        #[arg(short, long)]
+        #[arg(short, long, add = ArgValueCandidates::new(complete::tags))]
         tag: Option<String>,
 
+        /// Filter by kind
+        #[arg(short, long, value_parser = types::MEMORY_KINDS.to_vec())]
+        kind: Option<String>,
+
+        /// Only memories in this namespace (e.g. quarantine)
+        #[arg(long)]
+        namespace: Option<String>,
+
+        /// Only memories learned in this agent session (see `roots sessions`)
+        #[arg(long)]
+        session: Option<String>,
+
         /// Maximum results
         #[arg(short = 'n', long, default_value = "10")]
         limit: usize,
+
+        /// Sort order (recent, importance)
+        #[arg(short, long, default_value = "recent", value_parser = ["recent", "importance"])]
+        sort: String,
+
+        /// Only memories created or updated since this date or duration ago
+        /// (2024-06-15, 2024-06, 2w, 3d)
+        #[arg(long, value_parser = dates::parse_point)]
+        since: Option<String>,
+
+        /// Only memories created or updated before this date or duration ago
+        #[arg(long, value_parser = dates::parse_point)]
+        until: Option<String>,
+
+        /// Leave out memories with this tag (repeatable)
+        #[arg(long = "exclude-tag", value_name = "TAG", add = ArgValueCandidates::new(complete::tags))]
+        exclude_tags: Vec<String>,
+
+        /// Leave out memories containing this text (repeatable)
+        #[arg(long = "exclude", value_name = "TEXT")]
+        exclude_text: Vec<String>,
+    },
+
+    /// Delete memories matching cleanup criteria
+    Prune {
+        /// Memories past their expiry
+        #[arg(long)]
+        expired: bool,
+
+        /// Memories with confidence below this value
+        #[arg(long)]
+        below_confidence: Option<f64>,
+
+        /// Memories not accessed for this long (e.g. 90d, 6w)
+        #[arg(long)]
+        unaccessed_for: Option<String>,
+
+        /// List what would be deleted without deleting
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Step through memories to keep, edit, retag, or forget them
+    Review {
+        /// Only memories with this tag
+        #[arg(short, long, add = ArgValueCandidates::new(complete::tags))]
+        tag: Option<String>,
+
+        /// Only memories in this namespace (e.g. quarantine)
+        #[arg(long)]
+        namespace: Option<String>,
+
+        /// Review order (oldest, confidence)
+        #[arg(short, long, default_value = "oldest", value_parser = ["oldest", "confidence"])]
+        order: String,
+
+        /// Maximum memories to review
+        #[arg(short = 'n', long, default_value = "50")]
+        limit: usize,
     },
 
     /// List all tags
-    Tags,
+    Tags {
+        #[command(subcommand)]
+        command: Option<TagsCommands>,
+    },
+
+    /// List agent sessions and how many memories each left
+    Sessions {
+        /// Maximum sessions
+        #[arg(short = 'n', long, default_value = "20")]
+        limit: usize,
+    },
 
     /// Show statistics
-    Stats,
+    Stats {
+        /// Confidence, age, access, and duplicate breakdown for one tag
+        #[arg(short, long, add = ArgValueCandidates::new(complete::tags))]
+        tag: Option<String>,
+
+        /// Most recalled and never-accessed memories, and access per tag
+        #[arg(long, conflicts_with = "tag")]
+        access: bool,
+
+        /// Most frequent logged queries, and those that found nothing
+        /// (needs `config query_log hash` or `text`)
+        #[arg(long, conflicts_with_all = ["tag", "access"])]
+        queries: bool,
+    },
 
     /// Export memories to stdout
     Export {
         /// Output format
-        #[arg(short, long, default_value = "json", value_parser = ["json", "md"])]
+        #[arg(short, long, default_value = "json", value_parser = ["json", "md", "dot", "mermaid"])]
         format: String,
+
+        /// Only tag-level statistics, no memory content (always JSON)
+        #[arg(long, conflicts_with_all = ["format", "tombstones"])]
+        aggregate: bool,
+
+        /// With --aggregate, fold tags used fewer times into "(other)"
+        #[arg(long, default_value = "3")]
+        min_count: usize,
+
+        /// With --aggregate, add Laplace noise with this privacy budget
+        #[arg(long)]
+        epsilon: Option<f64>,
+
+        /// Include tombstones of forgotten memories (JSON), so imports delete them too
+        #[arg(long)]
+        tombstones: bool,
+
+        /// Only memories created or updated since this date or duration ago
+        /// (2024-06-15, 2024-06, 2w, 3d)
+        #[arg(long, value_parser = dates::parse_point)]
+        since: Option<String>,
+
+        /// Only memories created or updated before this date or duration ago
+        #[arg(long, value_parser = dates::parse_point)]
+        until: Option<String>,
+    },
+
+    /// Import memories from a JSON export
+    Import {
+        /// File written by `roots export`
+        file: String,
+
+        /// How to settle memories that differ locally: ask, local, remote, skip
+        #[arg(long, default_value = "ask", value_parser = ["ask", "local", "remote", "skip"])]
+        strategy: String,
     },
 
-    /// Sync memories to markdown files for browsing
-    Sync,
+    /// Snapshot the store to .roots/backups (and optionally S3)
+    Backup {
+        /// Also upload to the S3 bucket in the global config (backup_s3_*)
+        #[arg(long)]
+        remote: bool,
+
+        /// List snapshots instead of taking one
+        #[arg(long)]
+        list: bool,
+    },
+
+    /// Replace the store with a snapshot
+    Restore {
+        /// Snapshot name (from `roots backup --list`) or path
+        name: String,
+
+        /// Download the snapshot from the S3 bucket
+        #[arg(long)]
+        remote: bool,
+
+        /// Skip confirmation
+        #[arg(short, long)]
+        force: bool,
+
+        /// Fail at once if other maintenance is running instead of waiting
+        #[arg(long)]
+        no_wait: bool,
+    },
+
+    /// Print change feed events as JSON lines
+    Changes {
+        /// Only events after this sequence number
+        #[arg(long, default_value = "0")]
+        since: u64,
+    },
+
+    /// Sync memories to markdown files for browsing, or into files other
+    /// agents read
+    Sync {
+        /// Keep a managed block of trusted memories in CONVENTIONS.md for aider
+        #[arg(long)]
+        aider: bool,
+
+        /// Keep the same block in CLAUDE.md and/or AGENTS.md (CLAUDE.md is
+        /// created if neither exists)
+        #[arg(long)]
+        claude_md: bool,
+
+        /// Confidence a memory needs for the block (pinned ones always go in)
+        #[arg(long, default_value = "0.7")]
+        min_confidence: f64,
+
+        /// Maximum memories in the block
+        #[arg(short = 'n', long, default_value = "50")]
+        limit: usize,
+    },
 
     /// Rebuild embeddings with current model
-    Reindex,
+    Reindex {
+        /// Only memories with no embedding, the wrong dimension, or one from another model
+        #[arg(long, conflicts_with = "force")]
+        missing_only: bool,
+
+        /// Re-embed memories whose text and model are unchanged too
+        #[arg(long)]
+        force: bool,
+
+        /// Fail at once if other maintenance is running instead of waiting
+        #[arg(long)]
+        no_wait: bool,
+    },
+
+    /// Upgrade the store's schema and show its version
+    Migrate,
 
     /// Output context for Claude Code hooks
-    Prime,
+    Prime {
+        /// Output format: markdown for hooks; json, xml or plain for other agents
+        #[arg(short, long, default_value = "markdown", value_parser = cli::context::ContextFormat::parse)]
+        format: cli::context::ContextFormat,
+
+        /// Read the Claude Code hook payload on stdin and answer in the
+        /// hook's JSON shape
+        #[arg(long, conflicts_with = "format")]
+        hook_stdin: bool,
+    },
 
     /// Find relevant memories for a prompt
     Context {
         /// The prompt to find context for
-        prompt: String,
+        #[arg(required_unless_present = "hook_stdin")]
+        prompt: Option<String>,
 
         /// Search mode (tags, lite, semantic)
         #[arg(short, long, default_value = "semantic", value_parser = ["tags", "lite", "semantic"])]
         mode: String,
 
-        /// Maximum results
-        #[arg(short = 'n', long, default_value = "3")]
-        limit: usize,
+        /// Maximum results (default: 3, or 10 with a token budget)
+        #[arg(short = 'n', long)]
+        limit: Option<usize>,
+
+        /// Minimum similarity threshold (default: `context_threshold` in
+        /// config, 0.5)
+        #[arg(short = 't', long)]
+        threshold: Option<f64>,
+
+        /// Fit the output in about this many tokens, cutting the last
+        /// memory short (default: `context_max_tokens` in config)
+        #[arg(long)]
+        max_tokens: Option<usize>,
+
+        /// Rerank the top embedding matches with the server's cross-encoder
+        /// (default with `rerank: true` in config)
+        #[arg(long)]
+        rerank: bool,
+
+        /// Add matching tag names and keywords to short prompts before
+        /// searching (default with `expand_queries: true` in config)
+        #[arg(long)]
+        expand: bool,
+
+        /// Leave out memories with this tag (repeatable)
+        #[arg(long = "exclude-tag", value_name = "TAG", add = ArgValueCandidates::new(complete::tags))]
+        exclude_tags: Vec<String>,
+
+        /// Leave out memories containing this text (repeatable)
+        #[arg(long = "exclude", value_name = "TEXT")]
+        exclude_text: Vec<String>,
+
+        /// Output format: markdown for hooks; json, xml or plain for other agents
+        #[arg(short, long, default_value = "markdown", value_parser = cli::context::ContextFormat::parse)]
+        format: cli::context::ContextFormat,
 
-        /// Minimum similarity threshold
-        #[arg(short = 't', long, default_value = "0.5")]
-        threshold: f64,
+        /// Read the prompt from the Claude Code hook payload on stdin and
+        /// answer in the hook's JSON shape
+        #[arg(long, conflicts_with = "format")]
+        hook_stdin: bool,
     },
 
     /// View or set configuration
     Config {
         /// Config key
+        #[arg(add = ArgValueCandidates::new(complete::config_keys))]
         key: Option<String>,
 
         /// Config value
+        #[arg(add = ArgValueCandidates::new(complete::config_values))]
         value: Option<String>,
 
         /// List available models
         #[arg(long)]
         list_models: bool,
+
+        /// List every recognized key with its type and default
+        #[arg(long)]
+        list_keys: bool,
+
+        /// List the profiles in the global config with their settings
+        #[arg(long)]
+        list_profiles: bool,
+
+        /// Remove a key, going back to its default
+        #[arg(long, value_name = "KEY", conflicts_with_all = ["key", "value"], add = ArgValueCandidates::new(complete::config_keys))]
+        unset: Option<String>,
+
+        /// Read or write the global config ($XDG_CONFIG_HOME/roots/config.yaml)
+        #[arg(short, long)]
+        global: bool,
+    },
+
+    /// Show every effective setting and which layer it came from
+    ExplainConfig,
+
+    /// Print a completion script: source <(roots completions bash)
+    Completions {
+        /// Shell to complete in
+        #[arg(value_parser = ["bash", "zsh", "fish", "elvish", "powershell"])]
+        shell: String,
+    },
+
+    /// Seed memories about the stack, commands, CI, and layout of a repository
+    Bootstrap {
+        /// Repository root
+        #[arg(default_value = ".")]
+        path: String,
+
+        /// Confidence for generated memories (0-1)
+        #[arg(short, long, default_value = "0.3")]
+        confidence: f64,
+
+        /// Print what would be remembered without storing it
+        #[arg(long)]
+        dry_run: bool,
     },
 
+    /// Import memories from external sources
+    #[command(subcommand)]
+    Ingest(IngestCommands),
+
+    /// Check the store for problems and suggest fixes
+    Doctor {
+        /// Rebuild the search index and drop orphaned tags if needed
+        #[arg(long)]
+        fix: bool,
+
+        /// Fail at once if other maintenance is running instead of waiting
+        #[arg(long)]
+        no_wait: bool,
+    },
+
+    /// Time the lite embedder and the server's model on stored memories:
+    /// latency percentiles and batched throughput
+    Bench {
+        /// Most recent memories to embed
+        #[arg(short = 'n', long, default_value = "100")]
+        sample: usize,
+    },
+
+    /// Score recall against a YAML file of queries and the memory IDs
+    /// each should find: recall@k and MRR for the current model and ranking
+    Eval {
+        /// Cases file: a list of `query` and `expected` (memory IDs)
+        cases: std::path::PathBuf,
+
+        /// Results per query to score
+        #[arg(short, default_value = "5")]
+        k: usize,
+
+        /// Rerank with the server's cross-encoder, as `recall --rerank`
+        #[arg(long)]
+        rerank: bool,
+    },
+
+    /// Vacuum the database and rebuild its search index
+    Optimize {
+        /// Fail at once if other maintenance is running instead of waiting
+        #[arg(long)]
+        no_wait: bool,
+    },
+
+    /// Embed memories stored with --defer
+    #[command(subcommand)]
+    EmbedQueue(EmbedQueueCommands),
+
+    /// Keep the store maintained in the background: embed queued memories,
+    /// re-ingest changed docs, keep synced markdown current, prune expired
+    /// memories and optimize the index
+    Watch {
+        /// Seconds between passes
+        #[arg(long, default_value = "30")]
+        interval: u64,
+
+        /// How often to prune and optimize (e.g. 6h, 1d)
+        #[arg(long, default_value = "1d")]
+        maintain_every: String,
+
+        /// Chunking strategy for docs ingested before their own was kept
+        #[arg(long, default_value = "heading", value_parser = chunk::CHUNKERS.to_vec())]
+        chunker: String,
+
+        /// Maximum chunk size in tokens (words)
+        #[arg(long, default_value = "200")]
+        chunk_size: usize,
+
+        /// Tokens shared between consecutive chunks
+        #[arg(long, default_value = "20")]
+        overlap: usize,
+
+        /// Run one pass, maintenance included, and exit (for cron)
+        #[arg(long)]
+        once: bool,
+
+        /// Print nothing but errors
+        #[arg(short, long)]
+        quiet: bool,
+    },
+
+    /// Share curated bundles of memories between repositories
+    #[command(subcommand)]
+    Pack(PackCommands),
+
     /// Manage embedding server
     #[command(subcommand)]
     Server(ServerCommands),
 }
 
+#[derive(Subcommand)]
+enum TagsCommands {
+    /// Rename a tag on every memory carrying it
+    Rename {
+        /// Current tag
+        #[arg(add = ArgValueCandidates::new(complete::tags))]
+        old: String,
+
+        /// New tag; memories with both end up with just this one
+        new: String,
+    },
+
+    /// Merge tags into the first one
+    Merge {
+        /// Tag to keep, then the tags to fold into it
+        #[arg(required = true, num_args = 2.., add = ArgValueCandidates::new(complete::tags))]
+        tags: Vec<String>,
+    },
+
+    /// Suggest tags from a memory's nearest tagged neighbors
+    Suggest {
+        /// Memory ID
+        #[arg(required_unless_present = "untagged", add = ArgValueCandidates::new(complete::ids))]
+        id: Option<i64>,
+
+        /// Suggest for every untagged memory instead
+        #[arg(long, conflicts_with = "id")]
+        untagged: bool,
+
+        /// Add the suggested tags instead of printing them
+        #[arg(long)]
+        apply: bool,
+
+        /// Untagged memories to go through, most recent first
+        #[arg(short = 'n', long, default_value = "100")]
+        limit: usize,
+    },
+}
+
+#[derive(Subcommand)]
+enum PackCommands {
+    /// Write memories to a pack file (<name>-<version>.pack.json)
+    Create {
+        /// Pack name (letters, digits, '-', '_', '.')
+        name: String,
+
+        /// Pack version, so installs can replace older ones
+        #[arg(long, default_value = "0.1.0")]
+        version: String,
+
+        /// What the pack is for
+        #[arg(short, long)]
+        description: Option<String>,
+
+        /// Only memories with this tag
+        #[arg(short, long, add = ArgValueCandidates::new(complete::tags))]
+        tag: Option<String>,
+
+        /// Only memories of this kind
+        #[arg(short, long, value_parser = types::MEMORY_KINDS.to_vec())]
+        kind: Option<String>,
+
+        /// Only memories in this namespace
+        #[arg(long)]
+        namespace: Option<String>,
+
+        /// Output file
+        #[arg(short = 'o', long = "out", id = "out_file")]
+        out: Option<String>,
+    },
+
+    /// Install a pack from a file or URL
+    Install {
+        /// Pack file path or http(s) URL
+        location: String,
+
+        /// Namespace to install into (default: pack:<name>)
+        #[arg(long)]
+        namespace: Option<String>,
+
+        /// Reinstall even if this version is already installed
+        #[arg(short, long)]
+        force: bool,
+    },
+
+    /// List installed packs
+    List,
+}
+
+#[derive(Subcommand)]
+enum EmbedQueueCommands {
+    /// Embed queued memories until the queue is empty
+    Run {
+        /// Memories to embed per batch
+        #[arg(short, long, default_value = "32")]
+        batch: usize,
+
+        /// Keep running and pick up new memories as they are queued
+        #[arg(short, long)]
+        watch: bool,
+
+        /// Seconds between checks with --watch
+        #[arg(long, default_value = "2")]
+        interval: u64,
+
+        /// Print nothing (for background workers)
+        #[arg(short, long)]
+        quiet: bool,
+    },
+
+    /// Show how many memories are waiting for an embedding
+    Status,
+}
+
+#[derive(Subcommand)]
+enum IngestCommands {
+    /// Import a browser bookmarks HTML export
+    Bookmarks {
+        /// Bookmarks file (Netscape HTML format)
+        file: String,
+
+        /// Extra comma-separated tags for every item
+        #[arg(short, long, default_value = "", add = ArgValueCompleter::new(complete::tag_list))]
+        tags: String,
+
+        /// Confidence (0-1)
+        #[arg(short, long, default_value = "0.5")]
+        confidence: f64,
+    },
+
+    /// Import a Readwise highlights CSV export
+    Readwise {
+        /// Readwise CSV export
+        file: String,
+
+        /// Extra comma-separated tags for every item
+        #[arg(short, long, default_value = "", add = ArgValueCompleter::new(complete::tag_list))]
+        tags: String,
+
+        /// Confidence (0-1)
+        #[arg(short, long, default_value = "0.5")]
+        confidence: f64,
+    },
+
+    /// Import items from an RSS or Atom feed file
+    Rss {
+        /// Feed file (RSS or Atom XML)
+        file: String,
+
+        /// Extra comma-separated tags for every item
+        #[arg(short, long, default_value = "", add = ArgValueCompleter::new(complete::tag_list))]
+        tags: String,
+
+        /// Confidence (0-1)
+        #[arg(short, long, default_value = "0.5")]
+        confidence: f64,
+    },
+
+    /// Split documents (or code) into chunks and store each as a memory
+    Docs {
+        /// Files or directories to ingest
+        #[arg(required = true)]
+        paths: Vec<String>,
+
+        /// Chunking strategy
+        #[arg(long, default_value = "heading", value_parser = chunk::CHUNKERS.to_vec())]
+        chunker: String,
+
+        /// Maximum chunk size in tokens (words)
+        #[arg(long, default_value = "200")]
+        chunk_size: usize,
+
+        /// Tokens shared between consecutive chunks (whole lines, except
+        /// for the tokens chunker)
+        #[arg(long, default_value = "20")]
+        overlap: usize,
+
+        /// Only re-ingest files that changed since the last run, and forget
+        /// the chunks of files that are gone
+        #[arg(long)]
+        update: bool,
+
+        /// Extra comma-separated tags for every chunk
+        #[arg(short, long, default_value = "", add = ArgValueCompleter::new(complete::tag_list))]
+        tags: String,
+
+        /// Confidence (0-1)
+        #[arg(short, long, default_value = "0.5")]
+        confidence: f64,
+    },
+
+    /// Import a conversation (Claude Code JSONL or a markdown chat log) as
+    /// chunks held in quarantine for `roots review`
+    Transcript {
+        /// Transcript file
+        file: String,
+
+        /// Maximum chunk size in tokens (words)
+        #[arg(long, default_value = "300")]
+        chunk_size: usize,
+
+        /// Extra comma-separated tags for every chunk
+        #[arg(short, long, default_value = "", add = ArgValueCompleter::new(complete::tag_list))]
+        tags: String,
+
+        /// Confidence (0-1)
+        #[arg(short, long, default_value = "0.3")]
+        confidence: f64,
+    },
+}
+
 #[derive(Subcommand)]
 enum ServerCommands {
     /// Start the embedding server
@@ -172,6 +1038,11 @@ enum ServerCommands {
         /// Run in foreground
         #[arg(short, long)]
         foreground: bool,
+
+        /// Exit after this long without requests, e.g. 30m (default:
+        /// `server_idle_timeout` in the global config, or never)
+        #[arg(long, value_name = "DURATION")]
+        idle_timeout: Option<String>,
     },
 
     /// Stop the embedding server
@@ -186,11 +1057,22 @@ enum ServerCommands {
     /// View or set server model
     Model {
         /// Model name or alias
+        #[arg(add = ArgValueCandidates::new(complete::models))]
         model: Option<String>,
 
         /// List available models
         #[arg(short, long)]
         list: bool,
+
+        /// Also load this model, for projects whose `embedding_model` it is
+        #[arg(long, value_name = "MODEL", conflicts_with_all = ["model", "list", "remove"])]
+        #[arg(add = ArgValueCandidates::new(complete::models))]
+        add: Option<String>,
+
+        /// Stop loading a model added with --add
+        #[arg(long, value_name = "MODEL", conflicts_with_all = ["model", "list"])]
+        #[arg(add = ArgValueCandidates::new(complete::models))]
+        remove: Option<String>,
     },
 
     /// Install systemd user service
@@ -201,56 +1083,519 @@ enum ServerCommands {
 }
 
 fn main() {
+    // Answers the Tab requests of `roots completions` scripts, then exits
+    clap_complete::CompleteEnv::with_factory(Cli::command).complete();
+
     let cli = Cli::parse();
+    let started = std::time::Instant::now();
+    let output = cli.output;
+    term::init_color(cli.no_color);
+    if let Err(e) = logging::init(cli.verbose, cli.log_file.as_deref()) {
+        eprintln!("Warning: {}", e);
+    }
+    // Settings read the profile from the environment, as hooks and the
+    // server started from here do
+    if let Some(profile) = &cli.profile {
+        std::env::set_var("ROOTS_PROFILE", profile);
+    }
+    // Writing to a profile with `config --global` is how one is created
+    if !matches!(cli.command, Commands::Config { global: true, .. }) {
+        if let Err(e) = config::active_profile() {
+            exit_with(Error::Invalid(e), output);
+        }
+    }
+
+    if from_hook(&cli.command) && config::hooks_fail_fast() {
+        embeddings::set_fail_fast();
+    }
 
     let result = match cli.command {
         Commands::Init { path, hooks } => cli::memory::run_init(&path, hooks),
-        Commands::Hooks { path, remove, context_mode } => cli::memory::run_hooks(&path, remove, &context_mode),
+        Commands::Setup => cli::setup::run_setup(),
+        Commands::Hooks { path, remove, git: true, .. } => cli::hooks::run_git_hook(&path, remove),
+        Commands::Hooks { path, remove, tool, .. } if tool != "claude" => cli::hooks::run_rules(&path, &tool, remove),
+        Commands::Hooks {
+            path,
+            remove,
+            context_mode,
+            auto_remember,
+            capture_sessions,
+            global,
+            ..
+        } => cli::hooks::run_hooks(
+            &path,
+            remove,
+            &context_mode,
+            cli::hooks::Capture {
+                auto_remember,
+                sessions: capture_sessions,
+            },
+            global,
+        ),
         Commands::Remember {
             content,
             tags,
             confidence,
-        } => cli::memory::run_remember(&content, &tags, confidence),
-        Commands::Recall { query, tag, limit } => {
-            cli::memory::run_recall(query.as_deref(), tag.as_deref(), limit)
+            kind,
+            expires,
+            namespace,
+            source,
+            origin,
+            global,
+            pin,
+            session,
+            defer,
+            auto,
+            stdin,
+            edit,
+            hook_stdin,
+            from_file,
+        } => cli::memory::run_remember(
+            types::NewMemory {
+                content: if stdin { "-".to_string() } else { content.unwrap_or_default() },
+                confidence: confidence.unwrap_or(if auto { worthiness::AUTO_CONFIDENCE } else { 0.5 }),
+                kind,
+                namespace,
+                pinned: pin,
+                source,
+                session_id: session,
+                ..Default::default()
+            },
+            cli::memory::RememberOptions {
+                tags,
+                expires,
+                origin,
+                global,
+                defer,
+                auto,
+                edit,
+                hook_stdin,
+                from_file,
+            },
+        ),
+        Commands::Capture { commit: Some(rev), .. } => cli::capture::run_capture_commit(&rev),
+        Commands::Capture { transcript, hook_stdin, .. } => cli::capture::run_capture(transcript, hook_stdin),
+        Commands::Recall {
+            query,
+            tag,
+            kind,
+            namespace,
+            limit,
+            all_projects,
+            threshold,
+            stream,
+            fts,
+            explain,
+            rerank,
+            since,
+            until,
+            exclude_tags,
+            exclude_text,
+        } => {
+            let mut filter = MemoryFilter {
+                tag,
+                kind,
+                namespace,
+                since,
+                until,
+                exclude_tags,
+                exclude_text,
+                ..Default::default()
+            };
+            // Filters in the query; with nothing else left it lists matches
+            match query.map(|q| query::parse(&q, &mut filter)).transpose() {
+                Err(e) => Err(Error::Invalid(e)),
+                Ok(query) => match query.filter(|q| !q.is_empty()) {
+                    Some(q) if all_projects => cli::memory::run_recall_all_projects(&q, filter, limit, output),
+                    Some(q) if stream => cli::memory::run_recall_stream(&q, filter, limit, threshold, output),
+                    Some(q) if fts => cli::memory::run_recall_fts(&q, filter, limit, output),
+                    query => {
+                        cli::memory::run_recall(query.as_deref(), filter, limit, threshold, explain, rerank, output)
+                    }
+                },
+            }
         }
-        Commands::Forget { id, force } => cli::memory::run_forget(id, force),
+        Commands::Show { id } => cli::memory::run_show(id, output),
+        Commands::Attach { id, path, lines } => cli::memory::run_attach(id, &path, lines),
+        Commands::Forget { id, force, .. } => cli::memory::run_forget(id, force),
+        Commands::Edit { id, .. } => cli::memory::run_edit(id),
+        Commands::Undo => cli::memory::run_undo(),
         Commands::Update {
             id,
             confidence,
             tags,
-        } => cli::memory::run_update(id, confidence, tags.as_deref()),
-        Commands::List { tag, limit } => cli::memory::run_list(tag.as_deref(), limit),
-        Commands::Tags => cli::memory::run_tags(),
-        Commands::Stats => cli::memory::run_stats(),
-        Commands::Export { format } => cli::memory::run_export(&format),
-        Commands::Sync => cli::memory::run_sync(),
-        Commands::Reindex => cli::memory::run_reindex(),
-        Commands::Prime => cli::context::run_prime(),
+            kind,
+            namespace,
+            pin,
+            unpin,
+        } => cli::memory::run_update(
+            id,
+            confidence,
+            tags.as_deref(),
+            kind.as_deref(),
+            namespace,
+            (pin || unpin).then_some(pin),
+        ),
+        Commands::List {
+            tag,
+            kind,
+            namespace,
+            session,
+            limit,
+            sort,
+            since,
+            until,
+            exclude_tags,
+            exclude_text,
+        } => cli::memory::run_list(
+            MemoryFilter {
+                tag,
+                kind,
+                namespace,
+                session,
+                since,
+                until,
+                exclude_tags,
+                exclude_text,
+                ..Default::default()
+            },
+            limit,
+            &sort,
+            output,
+        ),
+        Commands::Prune {
+            expired,
+            below_confidence,
+            unaccessed_for,
+            dry_run,
+        } => cli::memory::run_prune(expired, below_confidence, unaccessed_for, dry_run),
+        Commands::Review {
+            tag,
+            namespace,
+            order,
+            limit,
+        } => cli::review::run_review(
+            MemoryFilter {
+                tag,
+                namespace,
+                ..Default::default()
+            },
+            &order,
+            limit,
+        ),
+        Commands::Tags { command: None } => cli::tags::run_tags(output),
+        Commands::Tags {
+            command: Some(TagsCommands::Suggest {
+                id,
+                untagged,
+                apply,
+                limit,
+            }),
+        } => cli::tags::run_suggest(id, untagged, apply, limit),
+        Commands::Tags {
+            command: Some(TagsCommands::Rename { old, new }),
+        } => cli::tags::run_rename(&old, &new),
+        Commands::Tags {
+            command: Some(TagsCommands::Merge { tags }),
+        } => cli::tags::run_merge(&tags),
+        Commands::Sessions { limit } => cli::memory::run_sessions(limit),
+        Commands::Stats { tag, access, queries } => cli::memory::run_stats(tag.as_deref(), access, queries, output),
+        Commands::Export {
+            format,
+            aggregate,
+            min_count,
+            epsilon,
+            tombstones,
+            since,
+            until,
+        } => {
+            let filter = MemoryFilter {
+                since,
+                until,
+                ..MemoryFilter::everything()
+            };
+            if aggregate {
+                cli::memory::run_export_aggregate(&filter, min_count, epsilon)
+            } else {
+                cli::memory::run_export(&filter, &format, tombstones)
+            }
+        }
+        Commands::Import { file, strategy } => cli::memory::run_import(&file, &strategy),
+        Commands::Backup { remote, list } => cli::backup::run_backup(remote, list),
+        Commands::Restore {
+            name,
+            remote,
+            force,
+            no_wait,
+        } => cli::backup::run_restore(&name, remote, force, no_wait),
+        Commands::Changes { since } => cli::memory::run_changes(since),
+        Commands::Sync {
+            aider,
+            claude_md,
+            min_confidence,
+            limit,
+        } => {
+            if aider || claude_md {
+                cli::sync::run_sync_agents(aider, claude_md, min_confidence, limit)
+            } else {
+                cli::memory::run_sync()
+            }
+        }
+        Commands::Reindex {
+            missing_only,
+            force,
+            no_wait,
+        } => cli::memory::run_reindex(missing_only, force, no_wait),
+        Commands::Migrate => cli::memory::run_migrate(),
+        Commands::Prime { format, hook_stdin } => cli::context::run_prime(format, hook_stdin),
         Commands::Context {
             prompt,
             mode,
             limit,
             threshold,
-        } => cli::context::run_context(&prompt, &mode, limit, threshold),
+            max_tokens,
+            rerank,
+            expand,
+            exclude_tags,
+            exclude_text,
+            format,
+            hook_stdin,
+        } => cli::context::run_context(
+            prompt.as_deref(),
+            cli::context::ContextOptions {
+                mode,
+                limit,
+                threshold,
+                rerank,
+                expand,
+                max_tokens,
+                exclude: MemoryFilter {
+                    exclude_tags,
+                    exclude_text,
+                    ..Default::default()
+                },
+                format: if output.is_json() && !hook_stdin { cli::context::ContextFormat::Json } else { format },
+                hook_stdin,
+            },
+        ),
         Commands::Config {
             key,
             value,
             list_models,
-        } => cli::config::run_config(key.as_deref(), value.as_deref(), list_models),
+            list_keys,
+            list_profiles,
+            unset,
+            global,
+        } => cli::config::run_config(
+            key.as_deref(),
+            value.as_deref(),
+            list_models,
+            list_keys,
+            list_profiles,
+            unset.as_deref(),
+            global,
+        ),
+        Commands::ExplainConfig => cli::config::run_explain_config(),
+        Commands::Completions { shell } => cli::setup::run_completions(&shell),
+        Commands::Bootstrap {
+            path,
+            confidence,
+            dry_run,
+        } => cli::ingest::run_bootstrap(&path, confidence, dry_run),
+        Commands::Ingest(cmd) => match cmd {
+            IngestCommands::Bookmarks {
+                file,
+                tags,
+                confidence,
+            } => cli::ingest::run_bookmarks(&file, &tags, confidence),
+            IngestCommands::Readwise {
+                file,
+                tags,
+                confidence,
+            } => cli::ingest::run_readwise(&file, &tags, confidence),
+            IngestCommands::Rss {
+                file,
+                tags,
+                confidence,
+            } => cli::ingest::run_rss(&file, &tags, confidence),
+            IngestCommands::Docs {
+                paths,
+                chunker,
+                chunk_size,
+                overlap,
+                update,
+                tags,
+                confidence,
+            } => {
+                let options = ingest::DocOptions {
+                    chunker: chunk::ChunkerSpec {
+                        name: chunker,
+                        size: chunk_size,
+                        overlap,
+                    },
+                    tags,
+                    confidence,
+                };
+                options
+                    .chunker
+                    .build()
+                    .map_err(Error::Invalid)
+                    .and_then(|_| cli::ingest::run_docs(&paths, &options, update))
+            }
+            IngestCommands::Transcript {
+                file,
+                chunk_size,
+                tags,
+                confidence,
+            } => cli::ingest::run_transcript(&file, chunk_size, &tags, confidence),
+        },
+        Commands::Doctor { fix, no_wait } => cli::doctor::run_doctor(fix, no_wait),
+        Commands::Bench { sample } => cli::bench::run_bench(sample, output),
+        Commands::Eval { cases, k, rerank } => cli::eval::run_eval(&cases, k, rerank, output),
+        Commands::Optimize { no_wait } => cli::doctor::run_optimize(no_wait),
+        Commands::EmbedQueue(cmd) => match cmd {
+            EmbedQueueCommands::Run {
+                batch,
+                watch,
+                interval,
+                quiet,
+            } => cli::embed_queue::run_embed_queue(batch, watch, interval, quiet),
+            EmbedQueueCommands::Status => cli::embed_queue::run_embed_queue_status(),
+        },
+        Commands::Watch {
+            interval,
+            maintain_every,
+            chunker,
+            chunk_size,
+            overlap,
+            once,
+            quiet,
+        } => dates::parse_duration(&maintain_every).map_err(Error::Invalid).and_then(|every| {
+            let fallback = ingest::DocOptions {
+                chunker: chunk::ChunkerSpec {
+                    name: chunker,
+                    size: chunk_size,
+                    overlap,
+                },
+                tags: String::new(),
+                confidence: 0.5,
+            };
+            fallback.chunker.build().map_err(Error::Invalid)?;
+            let options = cli::watch::WatchOptions {
+                interval: std::time::Duration::from_secs(interval.max(1)),
+                maintain_every: every
+                    .to_std()
+                    .map_err(|_| Error::Invalid("Maintenance interval must be positive".to_string()))?,
+                once,
+                quiet,
+            };
+            cli::watch::run_watch(&fallback, options)
+        }),
+        Commands::Pack(cmd) => match cmd {
+            PackCommands::Create {
+                name,
+                version,
+                description,
+                tag,
+                kind,
+                namespace,
+                out,
+            } => cli::pack::run_pack_create(
+                &name,
+                &version,
+                description.as_deref(),
+                &MemoryFilter {
+                    tag,
+                    kind,
+                    namespace,
+                    ..Default::default()
+                },
+                out.as_deref(),
+            ),
+            PackCommands::Install {
+                location,
+                namespace,
+                force,
+            } => cli::pack::run_pack_install(&location, namespace.as_deref(), force),
+            PackCommands::List => cli::pack::run_pack_list(),
+        },
         Commands::Server(cmd) => match cmd {
-            ServerCommands::Start { foreground } => cli::server::run_start(foreground),
+            ServerCommands::Start {
+                foreground,
+                idle_timeout,
+            } => cli::server::run_start(foreground, idle_timeout.as_deref()),
             ServerCommands::Stop => cli::server::run_stop(),
             ServerCommands::Status => cli::server::run_status(),
             ServerCommands::Restart => cli::server::run_restart(),
-            ServerCommands::Model { model, list } => cli::server::run_model(model.as_deref(), list),
+            ServerCommands::Model { model, list, add, remove } => {
+                cli::server::run_model(model.as_deref(), list, add.as_deref(), remove.as_deref())
+            }
             ServerCommands::Install => cli::server::run_install(),
             ServerCommands::Uninstall => cli::server::run_uninstall(),
         },
     };
 
+    if timing::timing_enabled() {
+        eprintln!("[timing] total {}ms", started.elapsed().as_millis());
+    }
+
     if let Err(e) = result {
+        exit_with(e, output);
+    }
+}
+
+/// Whether the command is answering an agent hook (`--hook-stdin`)
+fn from_hook(command: &Commands) -> bool {
+    matches!(
+        command,
+        Commands::Remember { hook_stdin: true, .. }
+            | Commands::Capture { hook_stdin: true, .. }
+            | Commands::Context { hook_stdin: true, .. }
+            | Commands::Prime { hook_stdin: true, .. }
+    )
+}
+
+fn exit_with(e: Error, output: cli::Output) -> ! {
+    // Scripts asking for JSON get the failure as JSON too
+    if output.is_json() {
+        let error = proto::Error {
+            kind: e.kind().to_string(),
+            message: e.to_string(),
+            exit_code: e.exit_code(),
+        };
+        println!("{}", serde_json::to_string_pretty(&error).unwrap_or_default());
+    } else {
         eprintln!("Error: {}", e);
-        std::process::exit(1);
+    }
+    std::process::exit(e.exit_code());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_create_output_file_is_not_the_global_output() {
+        let cli = Cli::try_parse_from(["roots", "--output", "json", "pack", "create", "team", "-o", "team.json"]).unwrap();
+        assert!(cli.output.is_json());
+        match cli.command {
+            Commands::Pack(PackCommands::Create { name, out, .. }) => {
+                assert_eq!(name, "team");
+                assert_eq!(out.as_deref(), Some("team.json"));
+            }
+            _ => panic!("expected pack create"),
+        }
+
+        let cli = Cli::try_parse_from(["roots", "pack", "create", "team", "--out", "x.json"]).unwrap();
+        assert!(!cli.output.is_json());
+        assert!(matches!(cli.command, Commands::Pack(PackCommands::Create { out: Some(_), .. })));
+    }
+
+    #[test]
+    fn test_aggregate_export_only_takes_json() {
+        assert!(Cli::try_parse_from(["roots", "export", "--aggregate"]).is_ok());
+        let err = Cli::try_parse_from(["roots", "export", "--aggregate", "--format", "md"]).err().unwrap();
+        assert_eq!(err.kind(), clap::error::ErrorKind::ArgumentConflict);
+        assert!(Cli::try_parse_from(["roots", "export", "--aggregate", "--tombstones"]).is_err());
     }
 }
@@ -0,0 +1,53 @@
+//! Managed blocks in files people also edit by hand.
+//!
+//! `sync` writes memories into files other agents read (aider's
+//! `CONVENTIONS.md`, `CLAUDE.md`, `AGENTS.md`). Only the text between the
+//! begin and end markers belongs to roots: each sync replaces it in place
+//! and leaves the rest of the file alone, appending the block the first
+//! time.
+
+pub const BEGIN: &str = "<!-- roots:begin -->";
+pub const END: &str = "<!-- roots:end -->";
+
+/// `existing` with its managed block replaced by `body`, or with a block
+/// appended if it has none
+pub fn update(existing: &str, body: &str) -> Result<String, String> {
+    let block = format!("{}\n{}\n{}", BEGIN, body.trim_end(), END);
+    let Some(start) = existing.find(BEGIN) else {
+        let existing = existing.trim_end();
+        return Ok(if existing.is_empty() {
+            format!("{}\n", block)
+        } else {
+            format!("{}\n\n{}\n", existing, block)
+        });
+    };
+    let end = existing[start..]
+        .find(END)
+        .map(|i| start + i + END.len())
+        .ok_or_else(|| format!("Found {} without a matching {}", BEGIN, END))?;
+    Ok(format!("{}{}{}", &existing[..start], block, &existing[end..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update_replaces_only_the_block() {
+        let first = update("", "- one").unwrap();
+        assert_eq!(first, format!("{}\n- one\n{}\n", BEGIN, END));
+
+        let edited = format!("# Conventions\n\nUse tabs.\n\n{}More by hand.\n", first);
+        let second = update(&edited, "- two\n").unwrap();
+        assert_eq!(
+            second,
+            format!("# Conventions\n\nUse tabs.\n\n{}\n- two\n{}\nMore by hand.\n", BEGIN, END)
+        );
+
+        assert_eq!(
+            update("notes", "- one").unwrap(),
+            format!("notes\n\n{}\n- one\n{}\n", BEGIN, END)
+        );
+        assert!(update(&format!("{}\n- dangling", BEGIN), "- one").is_err());
+    }
+}
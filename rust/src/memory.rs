@@ -1,66 +1,228 @@
-use crate::config::{find_roots_path, RootsConfig};
+use crate::aggregate::{access_report, monthly_activity, tag_health, weekly_activity, AccessReport, TagHealth};
+use crate::blobs::{self, BlobStore};
+use crate::changelog::Changelog;
+use crate::config::{find_roots_path, find_roots_path_from, global_roots_path, Ranking, RootsConfig, RoutingRule};
 use crate::embeddings::{cosine_similarity, get_embedder, Embedder};
+use crate::error::Error;
+use crate::graph::{centrality, nearest_edges, normalized};
 use crate::index::MemoryStore;
-use crate::types::{Memory, MemoryStats, SearchResult};
+use crate::ingest::IngestManifest;
+use crate::libsql::LibsqlStore;
+use crate::proto::{self, ChangeEvent};
+use crate::query_cache::QueryCache;
+use crate::query_log::{LoggedQuery, QueryLog, QueryReport};
+use crate::store::{Embedding, Store, VectorRow, CREATED_BY_KEY, MIGRATED_BY_KEY, SCHEMA_VERSION_KEY, STORE_BACKENDS};
+use crate::timing::RecallTimings;
+use crate::types::{
+    Attachment, Memory, MemoryFilter, MemoryStats, MemoryUpdate, NewMemory, PruneCriteria, ScoreParts,
+    SearchResult, SessionSummary, SortOrder, TagMatch, TextMatch, Tombstone, QUARANTINE_NAMESPACE,
+};
+use crate::undo::{Saved, UndoLog, UndoStep};
+use crate::worthiness::{self, Assessment};
+use std::cell::{OnceCell, RefCell};
 use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
 
 const EMBEDDING_MODEL_KEY: &str = "embedding_model";
+const IMPORTANCE_UPDATED_KEY: &str = "importance_updated_at";
+const IMPORTANCE_COUNT_KEY: &str = "importance_count";
+const CHANGELOG_SEQ_KEY: &str = "changelog_seq";
+const INGESTED_FILES_KEY: &str = "ingested_files";
+
+/// Memories sent to the embedder per request while reindexing
+const REINDEX_BATCH: usize = 32;
+
+/// Memories embedded per request by `remember_batch`
+const REMEMBER_BATCH: usize = 32;
+
+/// Minimum cosine similarity for two memories to be linked in the graph
+const SIMILARITY_EDGE_THRESHOLD: f64 = 0.5;
+
+/// Most similar memories each memory is linked to in the graph
+const SIMILARITY_NEIGHBORS: usize = 10;
+
+/// Weight of the edge between a memory and each of its tags
+const SHARED_TAG_WEIGHT: f64 = 0.5;
+
+/// Access count at which the access boost saturates
+const ACCESS_SATURATION: f64 = 10.0;
+
+/// Days for the access boost to decay by a factor of e
+const ACCESS_DECAY_DAYS: f64 = 30.0;
+
+/// Characters of each memory sent for reranking; cross-encoders only read
+/// the first few hundred tokens, and requests must fit the server's buffer
+const RERANK_MAX_CHARS: usize = 1000;
+
+/// Recompute importance when it is older than this
+const IMPORTANCE_MAX_AGE_HOURS: i64 = 24;
 
 /// The main memory interface
 pub struct Memories {
     roots_path: PathBuf,
-    store: MemoryStore,
+    store: Box<dyn Store>,
     embedder: Box<dyn Embedder>,
     current_model: String,
+    /// Length of the current embedder's vectors, once known
+    dimension: OnceCell<usize>,
+    /// Whether lite embeds because the configured model's server is down
+    fallback: bool,
+    slow_query: Duration,
+    last_recall: RefCell<Option<RecallTimings>>,
+    changelog: Option<Changelog>,
+    undo: UndoLog,
+    /// Step the writes inside `as_one_step` are journaled into
+    pending_undo: RefCell<Option<UndoStep>>,
+    require_tags_from: Vec<String>,
+    routing: Vec<RoutingRule>,
+    /// Embeddings of the routing rules' seeds, in rule order, once needed
+    seeds: OnceCell<Vec<Option<Vec<f32>>>>,
+    /// User-global layer merged into recall and prime, if it exists
+    global: Option<Box<Memories>>,
+    tombstone_retention: chrono::Duration,
+    context_max_age: Option<chrono::Duration>,
+    context_threshold: f64,
+    context_max_tokens: Option<usize>,
+    prime_recent: Option<chrono::Duration>,
+    defer_embeddings: bool,
+    capture_threshold: f64,
+    capture_command: Option<String>,
+    commit_pattern: Option<String>,
+    ranking: Ranking,
+    rerank: bool,
+    rerank_candidates: usize,
+    rerank_model: String,
+    context_rerank_model: String,
+    expand_queries: bool,
+    auto_tag: String,
+    blobs: BlobStore,
+    blob_threshold: Option<usize>,
+    /// Recent query embeddings, when the embedder is the server
+    query_cache: Option<QueryCache>,
+    /// Where recall and context queries are logged, unless `query_log` is off
+    query_log: Option<QueryLog>,
 }
 
 impl Memories {
     /// Open an existing memory store
-    pub fn open() -> Result<Self, String> {
-        let roots_path =
-            find_roots_path().ok_or("No .roots directory found. Run 'roots init' first.")?;
+    pub fn open() -> Result<Self, Error> {
+        Self::open_layered(find_roots_path().ok_or(Error::NotInitialized)?)
+    }
+
+    /// Open the store for the project at `dir`, as `open` would from there
+    pub fn open_in(dir: &Path) -> Result<Self, Error> {
+        Self::open_layered(find_roots_path_from(dir).ok_or(Error::NotInitialized)?)
+    }
+
+    /// Open the store at `roots_path`, with the global layer over it. A
+    /// global store that fails to open is left out with a warning.
+    fn open_layered(roots_path: PathBuf) -> Result<Self, Error> {
+        let mut memories = Self::open_at(roots_path)?;
+
+        if let Some(global_path) = global_roots_path().filter(|p| p.is_dir()) {
+            let same = match (global_path.canonicalize(), memories.roots_path.canonicalize()) {
+                (Ok(a), Ok(b)) => a == b,
+                _ => false,
+            };
+            if !same {
+                match Self::open_at(global_path) {
+                    Ok(global) => memories.global = Some(Box::new(global)),
+                    Err(e) => eprintln!("Warning: Global store unavailable, using the project's alone: {}", e),
+                }
+            }
+        }
+
+        Ok(memories)
+    }
+
+    /// Open the user-global store (`~/.roots`), creating it if needed
+    pub fn open_global() -> Result<Self, Error> {
+        let roots_path = global_roots_path().ok_or("Could not find home directory")?;
+        fs::create_dir_all(&roots_path)
+            .map_err(|e| format!("Failed to create {}: {}", roots_path.display(), e))?;
         Self::open_at(roots_path)
     }
 
     /// Open a memory store at a specific path
-    pub fn open_at(roots_path: PathBuf) -> Result<Self, String> {
+    pub fn open_at(roots_path: PathBuf) -> Result<Self, Error> {
         use crate::embeddings::ServerEmbedder;
 
         if !roots_path.exists() {
-            return Err(format!("Path does not exist: {}", roots_path.display()));
+            return Err(format!("Path does not exist: {}", roots_path.display()).into());
         }
 
-        let db_path = roots_path.join("memory.db");
-        let store =
-            MemoryStore::open(&db_path).map_err(|e| format!("Failed to open store: {}", e))?;
+        let config = RootsConfig::new(roots_path.clone());
+        let store = open_store(&roots_path, &config)?;
 
-        // If embedding server is running, use its model
-        let (model_name, model_type) = if ServerEmbedder::is_running() {
-            if let Ok(server_model) = ServerEmbedder::get_model() {
-                (server_model, "server".to_string())
-            } else {
-                let config = RootsConfig::new(roots_path.clone());
-                config.get_resolved_model()
-            }
-        } else {
-            let config = RootsConfig::new(roots_path.clone());
-            config.get_resolved_model()
+        // If embedding server is running, use the project's model when the
+        // server has it loaded, or else the server's default model
+        let server = ServerEmbedder::new();
+        let (configured, configured_type) = config.get_resolved_model();
+        let (model_name, model_type) = match server.is_running().then(|| server.models()) {
+            Some(Ok(models)) if models.contains(&configured) => (configured, "server".to_string()),
+            Some(Ok(models)) if !models.is_empty() => (models[0].clone(), "server".to_string()),
+            _ => (configured, configured_type),
         };
 
+        // Vectors are stamped with the model that actually embeds, which is
+        // lite when the server can't be reached
         let embedder = get_embedder(Some(&model_name), &model_type, true);
+        let current_model = embedder.model().to_string();
+        let fallback = model_type != "lite" && !embedder.is_remote();
+        let changelog = config.changelog().then(|| Changelog::new(&roots_path));
+        let blobs = BlobStore::new(&roots_path);
+        let query_cache = embedder.is_remote().then(|| QueryCache::new(&roots_path));
+        let query_log = match config.query_log().as_str() {
+            "off" => None,
+            mode => Some(QueryLog::new(&roots_path, mode == "text")),
+        };
 
         Ok(Self {
+            changelog,
+            undo: UndoLog::new(&roots_path),
+            pending_undo: RefCell::new(None),
+            require_tags_from: config.get_list("require_tags_from"),
+            routing: config.routing_rules().map_err(Error::Invalid)?,
+            seeds: OnceCell::new(),
             roots_path,
             store,
             embedder,
-            current_model: model_name,
+            current_model,
+            dimension: OnceCell::new(),
+            fallback,
+            slow_query: Duration::from_millis(config.slow_query_ms()),
+            last_recall: RefCell::new(None),
+            global: None,
+            tombstone_retention: config.tombstone_retention_days().map_err(Error::Invalid)?,
+            context_max_age: config.context_max_age(),
+            context_threshold: config.context_threshold(),
+            context_max_tokens: config.context_max_tokens(),
+            prime_recent: config.prime_recent_days().map_err(Error::Invalid)?,
+            defer_embeddings: config.defer_embeddings(),
+            capture_threshold: config.capture_threshold(),
+            capture_command: config.capture_command(),
+            commit_pattern: config.commit_pattern(),
+            ranking: config.ranking(),
+            rerank: config.rerank(),
+            rerank_candidates: config.rerank_candidates(),
+            rerank_model: config.rerank_model(),
+            context_rerank_model: config.context_rerank_model(),
+            expand_queries: config.expand_queries(),
+            auto_tag: config.auto_tag(),
+            blobs,
+            blob_threshold: config.blob_threshold(),
+            query_cache,
+            query_log,
         })
     }
 
     /// Initialize a new memory store
-    pub fn init(path: &Path) -> Result<Self, String> {
+    pub fn init(path: &Path) -> Result<Self, Error> {
         let roots_path = path.join(".roots");
         fs::create_dir_all(&roots_path)
             .map_err(|e| format!("Failed to create .roots directory: {}", e))?;
@@ -73,208 +235,1953 @@ impl Memories {
         &self.roots_path
     }
 
+    /// Write a consistent copy of the store to `dest`
+    pub fn snapshot(&self, dest: &Path) -> Result<(), String> {
+        self.store
+            .snapshot(dest)
+            .map_err(|e| format!("Failed to snapshot store: {}", e))
+    }
+
     // =========================================================================
     // Core operations
     // =========================================================================
 
     /// Remember something new
-    pub fn remember(
-        &self,
-        content: &str,
-        confidence: f64,
-        tags: &[String],
-    ) -> Result<i64, String> {
-        // Store the embedding model on first use
-        let stored_model = self.get_stored_model()?;
-        if stored_model.is_none() {
-            self.set_stored_model(&self.current_model)?;
+    pub fn remember(&self, memory: &NewMemory) -> Result<i64, Error> {
+        let memory = self.externalize(memory)?;
+        let embedding = self.embed_text(&memory.content)?;
+        Ok(self.insert(&memory, Some(&embedding))?)
+    }
+
+    /// Embed `text` with the current model, reusing a stored embedding of
+    /// the same text instead of calling the embedder when there is one of
+    /// the right length
+    fn embed_text(&self, text: &str) -> Result<Vec<f32>, Error> {
+        let _span = tracing::info_span!("embed", chars = text.len()).entered();
+        let hash = blobs::hash(text);
+        let stored = self
+            .store
+            .find_embedding(&self.current_model, &hash)
+            .map_err(|e| format!("Failed to look up embedding: {}", e))?;
+        match stored {
+            Some(embedding) if embedding.len() == self.embedding_dimension()? => {
+                tracing::debug!("reused the stored embedding");
+                Ok(embedding)
+            }
+            _ => self
+                .embedder
+                .embed(text)
+                .map_err(|e| Error::EmbedderUnavailable(format!("Failed to embed content: {}", e))),
         }
+    }
 
+    /// Embed a search query, through the query cache when there is one.
+    /// The cache is only a shortcut, so its errors fall through to the
+    /// embedder.
+    fn embed_query(&self, query: &str) -> Result<Vec<f32>, Error> {
+        let _span = tracing::info_span!("embed_query", chars = query.len(), model = %self.current_model).entered();
+        let cache = self.query_cache.as_ref();
+        if let Some(Ok(Some(embedding))) = cache.map(|c| c.get(&self.current_model, query)) {
+            tracing::debug!("query cache hit");
+            return Ok(embedding);
+        }
         let embedding = self
             .embedder
-            .embed(content)
-            .map_err(|e| format!("Failed to embed content: {}", e))?;
+            .embed(query)
+            .map_err(|e| Error::EmbedderUnavailable(format!("Failed to embed query: {}", e)))?;
+        if let Some(cache) = cache {
+            let _ = cache.put(&self.current_model, query, &embedding);
+        }
+        Ok(embedding)
+    }
+
+    /// Remember something without waiting for the embedder. The memory is
+    /// stored at once and queued for `embed_pending`; until then it is
+    /// found by tag, kind, and listing but not by semantic recall. Routing
+    /// seeds need the embedding, so only metadata rules apply.
+    pub fn remember_deferred(&self, memory: &NewMemory) -> Result<i64, String> {
+        self.insert(&self.externalize(memory)?, None)
+    }
+
+    /// Remember many memories at once, embedded `REMEMBER_BATCH` to a
+    /// request (or queued, with `defer`) and stored in one transaction, so
+    /// either all of them are stored or none
+    pub fn remember_batch(&self, memories: &[NewMemory], defer: bool) -> Result<Vec<i64>, Error> {
+        let memories = memories
+            .iter()
+            .map(|m| self.externalize(m))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut vectors: Vec<Vec<f32>> = Vec::new();
+        if !defer {
+            for chunk in memories.chunks(REMEMBER_BATCH) {
+                let _span = tracing::info_span!("embed_batch", count = chunk.len()).entered();
+                let texts: Vec<&str> = chunk.iter().map(|m| m.content.as_str()).collect();
+                let embedded = self
+                    .embedder
+                    .embed_batch(&texts)
+                    .map_err(|e| Error::EmbedderUnavailable(format!("Failed to embed content: {}", e)))?;
+                if embedded.len() != chunk.len() {
+                    return Err("Failed to embed content: the embedder returned too few embeddings".into());
+                }
+                vectors.extend(embedded);
+            }
+            if self.get_stored_model()?.is_none() {
+                self.set_stored_model(&self.current_model)?;
+            }
+        }
+
+        let mut batch = Vec::with_capacity(memories.len());
+        for (i, mut memory) in memories.into_iter().enumerate() {
+            let vector = vectors.get(i).map(Vec::as_slice);
+            if memory.namespace.is_none() {
+                memory.namespace = self.route(&memory, vector)?;
+            }
+            let embedding = vector.map(|v| Embedding::new(v, &self.current_model, &memory.content));
+            batch.push((memory, embedding));
+        }
+
+        let ids = self
+            .store
+            .insert_batch(&batch)
+            .map_err(|e| format!("Failed to add memories: {}", e))?;
+        for &id in &ids {
+            self.log_change("create", id)?;
+        }
+        self.record_undo("remember", ids.clone(), Vec::new());
+        Ok(ids)
+    }
+
+    /// Move an oversized body to the blob store, keeping its summary
+    fn externalize(&self, memory: &NewMemory) -> Result<NewMemory, String> {
+        let mut memory = memory.clone();
+        let (content, blob) = self.store_body(&memory.content)?;
+        memory.content = content;
+        memory.blob = blob;
+        Ok(memory)
+    }
+
+    /// Text to keep in the database for a body, and its blob hash if the
+    /// full body went to the blob store
+    fn store_body(&self, content: &str) -> Result<(String, Option<String>), String> {
+        match self.blob_threshold {
+            Some(threshold) if content.len() > threshold => {
+                let hash = self.blobs.put(content)?;
+                Ok((blobs::summary(content), Some(hash)))
+            }
+            _ => Ok((content.to_string(), None)),
+        }
+    }
+
+    /// Delete a blob once no memory refers to it
+    fn release_blob(&self, hash: &str) -> Result<(), String> {
+        let refs = self
+            .store
+            .count_blob_refs(hash)
+            .map_err(|e| format!("Failed to check blob references: {}", e))?;
+        if refs == 0 {
+            self.blobs.remove(hash)?;
+        }
+        Ok(())
+    }
+
+    /// Replace a summary with the full body from the blob store
+    pub fn hydrate(&self, memory: &mut Memory) -> Result<(), String> {
+        if let Some(hash) = &memory.blob {
+            memory.content = self.blobs.get(hash)?;
+        }
+        Ok(())
+    }
+
+    /// Whether writes are deferred by default (`defer_embeddings`)
+    pub fn defers_embeddings(&self) -> bool {
+        self.defer_embeddings
+    }
+
+    /// Score `content` as an auto-captured memory, against the closest
+    /// memory already in this store
+    pub fn assess_worthiness(&self, content: &str) -> Result<Assessment, String> {
+        let mut assessments = self.assess_candidates(&[content.to_string()], 1.0)?;
+        Ok(assessments.remove(0))
+    }
 
+    /// Score auto-captured candidates in one pass over the stored vectors.
+    /// Candidates before one that reach `threshold` will be stored first, so
+    /// they count as existing memories for its novelty.
+    pub fn assess_candidates(&self, contents: &[String], threshold: f64) -> Result<Vec<Assessment>, String> {
+        let embeddings = contents
+            .iter()
+            .map(|c| self.embed_text(c).map_err(|e| e.to_string()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut nearest: Vec<Option<(i64, f64)>> = vec![None; contents.len()];
         self.store
-            .add(content, confidence, &embedding, tags)
-            .map_err(|e| format!("Failed to add memory: {}", e))
+            .scan_vectors(&MemoryFilter::everything(), &mut |row| {
+                for (embedding, best) in embeddings.iter().zip(nearest.iter_mut()) {
+                    let similarity = cosine_similarity(embedding, &row.embedding);
+                    if best.is_none_or(|(_, b)| similarity > b) {
+                        *best = Some((row.id, similarity));
+                    }
+                }
+                true
+            })
+            .map_err(|e| format!("Failed to scan memories: {}", e))?;
+
+        let mut assessments: Vec<Assessment> = Vec::with_capacity(contents.len());
+        for (i, content) in contents.iter().enumerate() {
+            let earlier = (0..i)
+                .filter(|&j| assessments[j].score >= threshold)
+                .map(|j| cosine_similarity(&embeddings[i], &embeddings[j]))
+                .fold(None, |best: Option<f64>, s| Some(best.map_or(s, |b| b.max(s))));
+            let stored = nearest[i].filter(|&(_, s)| earlier.is_none_or(|e| s >= e));
+            let similarity = stored.map(|(_, s)| s).or(earlier);
+            let mut assessment = worthiness::assess(content, similarity);
+            assessment.nearest = stored.map(|(id, _)| id);
+            assessments.push(assessment);
+        }
+        Ok(assessments)
     }
 
-    /// Recall memories by semantic search
-    pub fn recall(&self, query: &str, limit: usize) -> Result<Vec<SearchResult>, String> {
-        let query_embedding = self
-            .embedder
-            .embed(query)
-            .map_err(|e| format!("Failed to embed query: {}", e))?;
+    /// Score `context` needs to inject a memory (`context_threshold`)
+    pub fn context_threshold(&self) -> f64 {
+        self.context_threshold
+    }
 
-        let all = self
+    /// Score auto-captured memories need to be stored (`capture_threshold`)
+    pub fn capture_threshold(&self) -> f64 {
+        self.capture_threshold
+    }
+
+    /// Command that extracts candidates from transcripts (`capture_command`)
+    pub fn capture_command(&self) -> Option<&str> {
+        self.capture_command.as_deref()
+    }
+
+    /// Regex commits must match to be remembered (`commit_pattern`)
+    pub fn commit_pattern(&self) -> Option<&str> {
+        self.commit_pattern.as_deref()
+    }
+
+    fn insert(&self, memory: &NewMemory, embedding: Option<&[f32]>) -> Result<i64, String> {
+        // Store the embedding model on first use
+        let stored_model = self.get_stored_model()?;
+        if stored_model.is_none() {
+            self.set_stored_model(&self.current_model)?;
+        }
+
+        let mut memory = memory.clone();
+        if memory.namespace.is_none() {
+            memory.namespace = self.route(&memory, embedding)?;
+        }
+
+        let embedding = embedding.map(|e| Embedding::new(e, &self.current_model, &memory.content));
+        let id = self
             .store
-            .get_all_with_embeddings()
-            .map_err(|e| format!("Failed to get memories: {}", e))?;
+            .insert(&memory, embedding.as_ref())
+            .map_err(|e| format!("Failed to add memory: {}", e))?;
+
+        self.log_change("create", id)?;
+        self.record_undo("remember", vec![id], Vec::new());
+        Ok(id)
+    }
+
+    /// Apply the write policy for a write from `origin` ("cli", "hook", "mcp").
+    ///
+    /// When `require_tags_from` lists the origin and the memory has neither
+    /// tags nor a kind, it is moved to the quarantine namespace for review.
+    /// Returns true if the memory was quarantined.
+    pub fn apply_write_policy(&self, memory: &mut NewMemory, origin: &str) -> bool {
+        let required = self.require_tags_from.iter().any(|o| o == origin);
+        if required && memory.tags.is_empty() && memory.kind.is_none() {
+            memory.namespace = Some(QUARANTINE_NAMESPACE.to_string());
+            return true;
+        }
+        false
+    }
+
+    /// Pick a namespace for a new memory from the routing rules
+    fn route(&self, memory: &NewMemory, embedding: Option<&[f32]>) -> Result<Option<String>, String> {
+        for (i, rule) in self.routing.iter().enumerate() {
+            if rule.matches_metadata(&memory.tags, memory.source.as_deref()) {
+                return Ok(Some(rule.namespace.clone()));
+            }
+
+            if let Some(embedding) = embedding {
+                if let Some(seed_embedding) = &self.seed_embeddings()?[i] {
+                    if cosine_similarity(embedding, seed_embedding) >= rule.threshold() {
+                        return Ok(Some(rule.namespace.clone()));
+                    }
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Embeddings of the routing seeds, computed once per store and kept in
+    /// the query cache across runs
+    fn seed_embeddings(&self) -> Result<&[Option<Vec<f32>>], String> {
+        if let Some(seeds) = self.seeds.get() {
+            return Ok(seeds);
+        }
+        let seeds = self
+            .routing
+            .iter()
+            .map(|rule| rule.seed.as_deref().map(|seed| self.embed_query(seed)).transpose())
+            .collect::<Result<Vec<_>, Error>>()
+            .map_err(|e| format!("Failed to embed routing seed: {}", e))?;
+        Ok(self.seeds.get_or_init(|| seeds))
+    }
 
-        let mut results: Vec<SearchResult> = all
+    /// Recall memories matching a filter by semantic search
+    ///
+    /// Results from the global layer are merged in by score; project
+    /// memories win ties and shadow global ones with the same content.
+    /// Both layers are scored with this store's ranking weights.
+    pub fn recall(
+        &self,
+        query: &str,
+        filter: &MemoryFilter,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, Error> {
+        let mut results = self.recall_layer(query, filter, limit, &self.ranking)?;
+
+        if let Some(global) = &self.global {
+            for mut r in global.recall_layer(query, filter, limit, &self.ranking)? {
+                if !shadowed(&r.memory, results.iter().map(|r| &r.memory)) {
+                    r.memory.global = true;
+                    results.push(r);
+                }
+            }
+            // Stable sort keeps project results ahead on equal scores
+            results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+            results.truncate(limit);
+        }
+
+        Ok(results)
+    }
+
+    /// Semantic search within this store only
+    fn recall_layer(
+        &self,
+        query: &str,
+        filter: &MemoryFilter,
+        limit: usize,
+        ranking: &Ranking,
+    ) -> Result<Vec<SearchResult>, Error> {
+        let _span = tracing::info_span!("recall", store = %self.roots_path.display(), limit).entered();
+        let mut timings = RecallTimings {
+            server: self.embedder.is_remote(),
+            ..Default::default()
+        };
+
+        let started = Instant::now();
+        let query_embedding = self.embed_query(query)?;
+        timings.embed = started.elapsed();
+
+        // Score (id, embedding) rows as they stream by; only the top
+        // results are loaded in full
+        let started = Instant::now();
+        self.store.take_tag_stats();
+        let mut scored: Vec<(i64, f64, ScoreParts)> = Vec::new();
+        // Rows are scored as they are read, so the scan includes scoring
+        let scan = tracing::info_span!("scan", rows = tracing::field::Empty).entered();
+        self.store
+            .scan_vectors(filter, &mut |row| {
+                let scoring = Instant::now();
+                let parts = score_parts(ranking, &query_embedding, &row);
+                scored.push((row.id, ranking.score(&parts), parts));
+                timings.score += scoring.elapsed();
+                true
+            })
+            .map_err(|e| format!("Failed to scan memories: {}", e))?;
+        timings.candidates = scored.len();
+        scan.record("rows", scored.len());
+        drop(scan);
+
+        let rank = tracing::info_span!("rank").entered();
+        let sorting = Instant::now();
+        scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        scored.truncate(limit);
+        timings.score += sorting.elapsed();
+        drop(rank);
+        let load = tracing::info_span!("load", ids = scored.len()).entered();
+
+        let ids: Vec<i64> = scored.iter().map(|(id, _, _)| *id).collect();
+        let scores: HashMap<i64, (f64, ScoreParts)> = scored
+            .into_iter()
+            .map(|(id, score, parts)| (id, (score, parts)))
+            .collect();
+        let results = self
+            .store
+            .get_many(&ids)
+            .map_err(|e| format!("Failed to get memories: {}", e))?
             .into_iter()
-            .map(|(memory, embedding)| {
-                let score = cosine_similarity(&query_embedding, &embedding);
-                SearchResult { memory, score }
+            .map(|memory| {
+                let (score, parts) = scores[&memory.id];
+                SearchResult {
+                    memory,
+                    score,
+                    parts: Some(parts),
+                }
             })
             .collect();
+        drop(load);
+        timings.scan = started.elapsed().saturating_sub(timings.score);
+        (timings.tag_queries, timings.tags) = self.store.take_tag_stats();
+        tracing::debug!("recall phases: {}", timings.summary());
 
-        // Sort by score descending
-        results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+        *self.last_recall.borrow_mut() = Some(timings);
 
-        Ok(results.into_iter().take(limit).collect())
+        Ok(results)
     }
 
-    /// Recall memories by tag
-    pub fn recall_by_tag(&self, tag: &str, limit: usize) -> Result<Vec<Memory>, String> {
-        self.store
-            .get_by_tag(tag, limit)
-            .map_err(|e| format!("Failed to get memories: {}", e))
+    /// Whether `rerank` is on in the project config
+    pub fn rerank_by_default(&self) -> bool {
+        self.rerank
     }
 
-    /// Full-text search
-    #[allow(dead_code)]
-    pub fn search_text(&self, query: &str, limit: usize) -> Result<Vec<Memory>, String> {
-        self.store
-            .search_fts(query, limit)
-            .map_err(|e| format!("Failed to search: {}", e))
+    /// Embedding matches to retrieve for a rerank down to `limit`
+    pub fn rerank_candidates(&self, limit: usize) -> usize {
+        self.rerank_candidates.max(limit)
     }
 
-    /// Get a specific memory
-    pub fn get(&self, id: i64) -> Result<Option<Memory>, String> {
-        self.store
-            .get(id)
-            .map_err(|e| format!("Failed to get memory: {}", e))
+    /// Rescore `results` by the server's cross-encoder (`rerank_model`)
+    /// and sort them best first. Left as they are if the server can't rerank.
+    pub fn rerank(&self, query: &str, results: &mut [SearchResult]) -> Result<(), String> {
+        self.rerank_with(&self.rerank_model, query, results)
     }
 
-    /// List recent memories
-    pub fn list(&self, limit: usize) -> Result<Vec<Memory>, String> {
-        self.store
-            .list(limit)
-            .map_err(|e| format!("Failed to list memories: {}", e))
+    /// `rerank` for hook context, with `context_rerank_model`
+    pub fn rerank_context(&self, prompt: &str, results: &mut [SearchResult]) -> Result<(), String> {
+        self.rerank_with(&self.context_rerank_model, prompt, results)
     }
 
-    /// Update a memory
-    pub fn update(
-        &self,
-        id: i64,
-        confidence: Option<f64>,
-        tags: Option<&[String]>,
-    ) -> Result<(), String> {
-        self.store
-            .update(id, confidence, tags)
-            .map_err(|e| format!("Failed to update memory: {}", e))?;
+    fn rerank_with(&self, model: &str, query: &str, results: &mut [SearchResult]) -> Result<(), String> {
+        let _span = tracing::info_span!("rerank", candidates = results.len(), model).entered();
+        let texts: Vec<String> = results
+            .iter()
+            .map(|r| r.memory.content.chars().take(RERANK_MAX_CHARS).collect())
+            .collect();
+        let texts: Vec<&str> = texts.iter().map(String::as_str).collect();
+        let scores = crate::embeddings::ServerEmbedder::new()
+            .rerank(model, query, &texts)
+            .map_err(|e| format!("Failed to rerank (needs `roots server start`): {}", e))?;
+
+        for (r, score) in results.iter_mut().zip(scores) {
+            r.score = score;
+        }
+        results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
         Ok(())
     }
 
-    /// Forget a memory
-    pub fn forget(&self, id: i64) -> Result<bool, String> {
-        self.store
-            .delete(id)
-            .map_err(|e| format!("Failed to delete memory: {}", e))
+    /// Token budget for `context` output from the project config
+    pub fn context_max_tokens(&self) -> Option<usize> {
+        self.context_max_tokens
     }
 
-    // =========================================================================
-    // Stats and metadata
-    // =========================================================================
-
-    /// Get statistics
-    pub fn stats(&self) -> Result<MemoryStats, String> {
-        let count = self
-            .store
-            .count()
-            .map_err(|e| format!("Failed to count: {}", e))?;
+    /// How far back `prime` lists recent changes (`prime_recent_days`)
+    pub fn prime_recent(&self) -> Option<chrono::Duration> {
+        self.prime_recent
+    }
 
-        let tags = self
-            .store
-            .get_all_tags()
-            .map_err(|e| format!("Failed to get tags: {}", e))?;
+    /// Whether `expand_queries` is on in the project config
+    pub fn expand_by_default(&self) -> bool {
+        self.expand_queries
+    }
 
-        let by_tag: HashMap<String, usize> = tags.into_iter().collect();
+    /// `auto_tag` mode of the project config
+    pub fn auto_tag_mode(&self) -> &str {
+        &self.auto_tag
+    }
 
-        // Calculate average confidence
-        let memories = self
-            .store
-            .list(1000)
-            .map_err(|e| format!("Failed to list: {}", e))?;
+    /// Tags for memories captured without tags: with `auto_tag: apply`,
+    /// the suggested ones, otherwise existing tags `content` names
+    pub fn auto_tags(&self, content: &str) -> Result<Vec<String>, String> {
+        if self.auto_tag == "apply" {
+            return self.suggest_tags(content, true);
+        }
+        Ok(crate::expand::mentioned_tags(content, &self.tags()?))
+    }
 
-        let avg_confidence = if memories.is_empty() {
-            0.0
+    /// Tags for `content`: existing tags it names, tags its nearest
+    /// neighbors share (skipped without `neighbors`, which embeds the
+    /// content), and its key phrases
+    pub fn suggest_tags(&self, content: &str, neighbors: bool) -> Result<Vec<String>, String> {
+        let mentioned = crate::expand::mentioned_tags(content, &self.tags()?);
+        let nearest: Vec<(f64, Vec<String>)> = if neighbors {
+            self.recall(content, &MemoryFilter::default(), crate::autotag::NEIGHBORS)?
+                .into_iter()
+                .filter_map(|r| Some((r.parts?.similarity, r.memory.tags)))
+                .collect()
         } else {
-            memories.iter().map(|m| m.confidence).sum::<f64>() / memories.len() as f64
+            Vec::new()
         };
-
-        Ok(MemoryStats {
-            total_memories: count,
-            total_tags: by_tag.len(),
-            by_tag,
-            avg_confidence,
-        })
+        let keywords = crate::autotag::keywords(content, crate::autotag::MAX_TAGS);
+        Ok(crate::autotag::suggest(&mentioned, &nearest, &keywords))
     }
 
-    /// Get all tags with counts
-    pub fn tags(&self) -> Result<Vec<(String, usize)>, String> {
+    /// Each memory of `ids` with tags suggested from its nearest tagged
+    /// neighbors and the existing tags it names, leaving out tags it has
+    pub fn neighbor_tags(&self, ids: &[i64]) -> Result<Vec<(Memory, Vec<String>)>, String> {
+        let mut vectors: Vec<(i64, Vec<f32>)> = Vec::new();
         self.store
-            .get_all_tags()
-            .map_err(|e| format!("Failed to get tags: {}", e))
+            .scan_vectors(&MemoryFilter::default(), &mut |row| {
+                vectors.push((row.id, row.embedding));
+                true
+            })
+            .map_err(|e| format!("Failed to scan memories: {}", e))?;
+        let tagged: HashMap<i64, Vec<String>> = self
+            .list(&MemoryFilter::default(), usize::MAX, SortOrder::Recent)?
+            .into_iter()
+            .filter(|m| !m.tags.is_empty())
+            .map(|m| (m.id, m.tags))
+            .collect();
+        let all_tags = self.tags()?;
+
+        let mut suggestions = Vec::new();
+        for &id in ids {
+            let mut memory = self.get(id)?.ok_or_else(|| format!("Memory {} not found", id))?;
+            self.hydrate(&mut memory)?;
+            let embedding = match vectors.iter().find(|(other, _)| *other == id) {
+                Some((_, embedding)) => embedding.clone(),
+                None => self.embed_text(&memory.content)?,
+            };
+
+            let mut neighbors: Vec<(f64, Vec<String>)> = vectors
+                .iter()
+                .filter(|(other, _)| *other != id)
+                .filter_map(|(other, v)| Some((cosine_similarity(&embedding, v), tagged.get(other)?.clone())))
+                .collect();
+            neighbors.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+            neighbors.truncate(crate::autotag::NEIGHBORS);
+
+            let mentioned = crate::expand::mentioned_tags(&memory.content, &all_tags);
+            let tags = crate::autotag::suggest(&mentioned, &neighbors, &[])
+                .into_iter()
+                .filter(|t| !memory.tags.contains(t))
+                .collect();
+            suggestions.push((memory, tags));
+        }
+        Ok(suggestions)
     }
 
-    // =========================================================================
-    // Embedding model management
-    // =========================================================================
+    /// `prompt` with tag names and keywords of its full-text matches among
+    /// `filter` appended, if it is short enough to need them
+    pub fn expand_query(&self, prompt: &str, filter: &MemoryFilter) -> Result<String, String> {
+        let terms = crate::expand::terms(prompt);
+        if terms.is_empty() || prompt.split_whitespace().count() > crate::expand::MAX_WORDS {
+            return Ok(prompt.to_string());
+        }
 
-    /// Get the embedding model stored in the database
-    pub fn get_stored_model(&self) -> Result<Option<String>, String> {
-        self.store
-            .get_metadata(EMBEDDING_MODEL_KEY)
-            .map_err(|e| format!("Failed to get metadata: {}", e))
+        let tags = self.tags()?;
+        let query = crate::expand::fts_query(&terms);
+        let matches = self.search_text(&query, filter, crate::expand::FTS_MATCHES)?;
+        let texts: Vec<&str> = matches.iter().map(|m| m.memory.content.as_str()).collect();
+        Ok(crate::expand::expand(prompt, &tags, &texts))
     }
 
-    /// Set the embedding model in the database
-    pub fn set_stored_model(&self, model: &str) -> Result<(), String> {
+    /// Recall without waiting for the whole scan: `on_hit` is called with
+    /// each memory scoring at least `threshold` as soon as the scan reaches
+    /// it, in store order rather than by score, until `limit` hits. The
+    /// global layer is scanned after the project. Returns the hit count.
+    pub fn recall_stream(
+        &self,
+        query: &str,
+        filter: &MemoryFilter,
+        threshold: f64,
+        limit: usize,
+        mut on_hit: impl FnMut(&SearchResult),
+    ) -> Result<usize, Error> {
+        let mut project = Vec::new();
+        self.stream_layer(query, filter, threshold, limit, &self.ranking, &mut |r| {
+            on_hit(&r);
+            project.push(r.memory);
+        })?;
+
+        let mut hits = project.len();
+        if let Some(global) = &self.global {
+            global.stream_layer(query, filter, threshold, limit - hits, &self.ranking, &mut |mut r| {
+                if !shadowed(&r.memory, &project) {
+                    r.memory.global = true;
+                    on_hit(&r);
+                    hits += 1;
+                }
+            })?;
+        }
+        Ok(hits)
+    }
+
+    /// Streaming scan of this store only, stops after `limit` hits
+    fn stream_layer(
+        &self,
+        query: &str,
+        filter: &MemoryFilter,
+        threshold: f64,
+        limit: usize,
+        ranking: &Ranking,
+        on_hit: &mut dyn FnMut(SearchResult),
+    ) -> Result<(), Error> {
+        if limit == 0 {
+            return Ok(());
+        }
+        let _span = tracing::info_span!("recall_stream", store = %self.roots_path.display(), limit).entered();
+        let query_embedding = self.embed_query(query)?;
+
+        let mut hits = 0;
+        let mut fetch_error = None;
         self.store
-            .set_metadata(EMBEDDING_MODEL_KEY, model)
-            .map_err(|e| format!("Failed to set metadata: {}", e))
+            .scan_vectors(filter, &mut |row| {
+                let parts = score_parts(ranking, &query_embedding, &row);
+                let score = ranking.score(&parts);
+                if score < threshold {
+                    return true;
+                }
+                match self.store.get(row.id) {
+                    Ok(Some(memory)) => {
+                        on_hit(SearchResult {
+                            memory,
+                            score,
+                            parts: Some(parts),
+                        });
+                        hits += 1;
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        fetch_error = Some(e);
+                        return false;
+                    }
+                }
+                hits < limit
+            })
+            .map_err(|e| format!("Failed to scan memories: {}", e))?;
+        match fetch_error {
+            Some(e) => Err(format!("Failed to get memory: {}", e).into()),
+            None => Ok(()),
+        }
     }
 
-    /// Get the current embedding model
-    pub fn current_model(&self) -> &str {
-        &self.current_model
+    /// Log a query answered by `command` with these result scores, if
+    /// `query_log` is on. The log is only a record, so its errors are ignored.
+    pub fn log_query(&self, command: &str, query: &str, scores: impl IntoIterator<Item = f64>, started: Instant) {
+        let Some(log) = &self.query_log else {
+            return;
+        };
+        let scores: Vec<f64> = scores.into_iter().collect();
+        let entry = LoggedQuery {
+            command: command.to_string(),
+            query: query.to_string(),
+            result_count: scores.len(),
+            top_score: scores.iter().copied().reduce(f64::max),
+            latency: started.elapsed(),
+        };
+        if let Err(e) = log.record(&entry) {
+            tracing::debug!("query log skipped: {}", e);
+        }
     }
 
-    /// Check if the current model differs from the stored model
-    pub fn check_model_mismatch(&self) -> Result<Option<String>, String> {
-        if let Some(stored) = self.get_stored_model()? {
-            if stored != self.current_model {
-                return Ok(Some(stored));
-            }
+    /// Whether recall and context queries are being logged
+    pub fn logging_queries(&self) -> bool {
+        self.query_log.is_some()
+    }
+
+    /// Totals and the top `limit` frequent and unanswered logged queries,
+    /// including any logged before `query_log` was turned off
+    pub fn query_report(&self, limit: usize) -> Result<QueryReport, String> {
+        QueryLog::new(&self.roots_path, false)
+            .report(limit)
+            .map_err(|e| format!("Failed to read query log: {}", e))
+    }
+
+    /// Timings of the most recent `recall`
+    pub fn last_recall_timings(&self) -> Option<RecallTimings> {
+        self.last_recall.borrow().clone()
+    }
+
+    /// Hint for the most recent `recall` if it exceeded the slow-query budget
+    pub fn slow_query_hint(&self) -> Option<String> {
+        let timings = self.last_recall.borrow();
+        let timings = timings.as_ref()?;
+        if self.slow_query.is_zero() || timings.total() < self.slow_query {
+            return None;
         }
-        Ok(None)
+        Some(timings.bottleneck_hint())
     }
 
-    /// Reindex all memories with the current embedding model
-    pub fn reindex(&self) -> Result<usize, String> {
-        let memories = self
-            .store
-            .get_all_for_reindex()
-            .map_err(|e| format!("Failed to get memories: {}", e))?;
+    /// Filter for hook-injected context: drops memories not updated or
+    /// accessed within `context_max_age`, unless pinned
+    pub fn context_filter(&self) -> Result<MemoryFilter, String> {
+        Ok(MemoryFilter {
+            active_since: self
+                .context_max_age
+                .map(|age| crate::dates::from_now(-age))
+                .transpose()?,
+            ..Default::default()
+        })
+    }
 
-        let count = memories.len();
-        for (id, content) in memories {
-            let embedding = self
-                .embedder
-                .embed(&content)
-                .map_err(|e| format!("Failed to embed memory {}: {}", id, e))?;
+    /// Full-text search, best match first; global matches follow the
+    /// project's. A query that is not valid FTS5 syntax is searched as a
+    /// phrase.
+    pub fn search_text(
+        &self,
+        query: &str,
+        filter: &MemoryFilter,
+        limit: usize,
+    ) -> Result<Vec<TextMatch>, String> {
+        let mut results = self.search_text_layer(query, filter, limit)?;
 
-            self.store
-                .update_embedding(id, &embedding)
-                .map_err(|e| format!("Failed to update embedding for {}: {}", id, e))?;
+        if let Some(global) = &self.global {
+            let remaining = limit.saturating_sub(results.len());
+            for mut m in global.search_text_layer(query, filter, remaining)? {
+                if !shadowed(&m.memory, results.iter().map(|r| &r.memory)) {
+                    m.memory.global = true;
+                    results.push(m);
+                }
+            }
+        }
+        Ok(results)
+    }
+
+    fn search_text_layer(
+        &self,
+        query: &str,
+        filter: &MemoryFilter,
+        limit: usize,
+    ) -> Result<Vec<TextMatch>, String> {
+        if limit == 0 {
+            return Ok(Vec::new());
         }
+        let _span = tracing::info_span!("search_fts", store = %self.roots_path.display(), limit).entered();
+        self.store.search_fts(query, filter, limit).or_else(|e| {
+            let phrase = format!("\"{}\"", query.replace('"', "\"\""));
+            self.store
+                .search_fts(&phrase, filter, limit)
+                .map_err(|_| format!("Failed to search: {}", e))
+        })
+    }
+
+    /// Record that memories were surfaced to the user or agent
+    pub fn record_access<'a>(&self, memories: impl IntoIterator<Item = &'a Memory>) -> Result<(), String> {
+        let (global, local): (Vec<&Memory>, Vec<&Memory>) = memories.into_iter().partition(|m| m.global);
+        let ids = |ms: Vec<&Memory>| ms.iter().map(|m| m.id).collect::<Vec<i64>>();
+
+        self.store
+            .record_access(&ids(local))
+            .map_err(|e| format!("Failed to record access: {}", e))?;
+
+        if let Some(layer) = &self.global {
+            layer
+                .store
+                .record_access(&ids(global))
+                .map_err(|e| format!("Failed to record access: {}", e))?;
+        }
+        Ok(())
+    }
+
+    /// Record an access for each search result relevant enough to count:
+    /// its score, leaving out the access boost, reaches `context_threshold`.
+    /// Filler that only pads out a list isn't reinforced, so repeating a
+    /// query doesn't lift it a little more each time.
+    pub fn record_hits<'a>(&self, results: impl IntoIterator<Item = &'a SearchResult>) -> Result<(), String> {
+        let relevant = results
+            .into_iter()
+            .filter(|r| self.relevance(r) >= self.context_threshold)
+            .map(|r| &r.memory);
+        self.record_access(relevant)
+    }
+
+    /// A result's score without what earlier accesses added to it; results
+    /// not scored by recall (by tag, say) keep their score
+    fn relevance(&self, result: &SearchResult) -> f64 {
+        match &result.parts {
+            Some(parts) => self.ranking.score(&ScoreParts { access: 0.0, ..*parts }),
+            None => result.score,
+        }
+    }
+
+    /// Get a specific memory
+    pub fn get(&self, id: i64) -> Result<Option<Memory>, String> {
+        self.store
+            .get(id)
+            .map_err(|e| format!("Failed to get memory: {}", e))
+    }
+
+    /// List memories matching a filter in the given order
+    pub fn list(
+        &self,
+        filter: &MemoryFilter,
+        limit: usize,
+        order: SortOrder,
+    ) -> Result<Vec<Memory>, String> {
+        let _span = tracing::info_span!("list", store = %self.roots_path.display(), limit).entered();
+        self.store
+            .list(filter, limit, order)
+            .map_err(|e| format!("Failed to list memories: {}", e))
+    }
+
+    /// List memories, topping up from the global layer when the project has
+    /// fewer than `limit` (project memories first)
+    pub fn list_layered(
+        &self,
+        filter: &MemoryFilter,
+        limit: usize,
+        order: SortOrder,
+    ) -> Result<Vec<Memory>, String> {
+        let mut memories = self.list(filter, limit, order)?;
+
+        if let Some(global) = &self.global {
+            for mut m in global.list(filter, limit, order)? {
+                if memories.len() >= limit {
+                    break;
+                }
+                if !shadowed(&m, memories.iter()) {
+                    m.global = true;
+                    memories.push(m);
+                }
+            }
+        }
+
+        Ok(memories)
+    }
+
+    /// Number of memories in the global layer, if one is open
+    pub fn global_count(&self) -> Result<Option<usize>, String> {
+        match &self.global {
+            Some(global) => Ok(Some(global.count()?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Update a memory
+    pub fn update(&self, id: i64, changes: &MemoryUpdate) -> Result<(), String> {
+        let saved = self.save(id, changes.content.is_some())?;
+        let mut changes = changes.clone();
+        let mut previous_blob = None;
+        if let Some(content) = &changes.content {
+            previous_blob = self.get(id)?.and_then(|m| m.blob);
+            let (content, blob) = self.store_body(content)?;
+            changes.content = Some(content);
+            changes.blob = Some(blob.unwrap_or_default());
+        }
+
+        // Embedded before anything is written, so new content never sits
+        // with the old content's vector
+        let embedding = match &changes.content {
+            Some(content) => Some(self.embed_text(content)?),
+            None => None,
+        };
+        self.store
+            .update(id, &changes)
+            .map_err(|e| format!("Failed to update memory: {}", e))?;
+
+        if let (Some(content), Some(embedding)) = (&changes.content, &embedding) {
+            self.store
+                .update_embedding(id, &Embedding::new(embedding, &self.current_model, content))
+                .map_err(|e| format!("Failed to update embedding: {}", e))?;
+        }
+        if let Some(hash) = previous_blob {
+            self.release_blob(&hash)?;
+        }
+
+        self.log_change("update", id)?;
+        self.record_undo("update", Vec::new(), saved.into_iter().collect());
+        Ok(())
+    }
+
+    /// Find the memory with this creation timestamp, if any
+    pub fn get_by_created_at(&self, created_at: &str) -> Result<Option<Memory>, String> {
+        self.store
+            .get_by_created_at(created_at)
+            .map_err(|e| format!("Failed to look up memory: {}", e))
+    }
+
+    /// Find a memory with exactly this content, by the hash of its embedded
+    /// text; memories still queued for an embedding aren't found
+    pub fn get_by_content(&self, content: &str) -> Result<Option<Memory>, String> {
+        let stored = match self.blob_threshold {
+            Some(threshold) if content.len() > threshold => blobs::summary(content),
+            _ => content.to_string(),
+        };
+        let candidates = self
+            .store
+            .get_by_text_hash(&blobs::hash(&stored))
+            .map_err(|e| format!("Failed to look up memory: {}", e))?;
+        for mut memory in candidates {
+            if self.hydrate(&mut memory).is_ok() && memory.content == content {
+                return Ok(Some(memory));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Check whether any memory was imported from the given source
+    pub fn has_source(&self, source: &str) -> Result<bool, String> {
+        self.store
+            .has_source(source)
+            .map_err(|e| format!("Failed to check source: {}", e))
+    }
+
+    /// Get memories matching the prune criteria
+    pub fn prunable(&self, criteria: &PruneCriteria) -> Result<Vec<Memory>, String> {
+        self.store
+            .get_prunable(criteria)
+            .map_err(|e| format!("Failed to find prunable memories: {}", e))
+    }
+
+    /// Forget a memory
+    pub fn forget(&self, id: i64) -> Result<bool, String> {
+        let Some(saved) = self.save(id, true)? else {
+            return Ok(false);
+        };
+        let deleted = self.delete(id, saved.blob.clone())?;
+        if deleted {
+            self.record_undo("forget", Vec::new(), vec![saved]);
+        }
+        Ok(deleted)
+    }
+
+    /// Delete a memory and release its blob, without journaling it
+    fn delete(&self, id: i64, blob: Option<String>) -> Result<bool, String> {
+        let deleted = self
+            .store
+            .delete(id)
+            .map_err(|e| format!("Failed to delete memory: {}", e))?;
+
+        if deleted {
+            self.log_change("delete", id)?;
+            self.purge_tombstones()?;
+            if let Some(hash) = blob {
+                self.release_blob(&hash)?;
+            }
+        }
+        Ok(deleted)
+    }
+
+    /// Drop tombstones older than the retention window
+    fn purge_tombstones(&self) -> Result<(), String> {
+        let cutoff = crate::dates::from_now(-self.tombstone_retention)?;
+        self.store
+            .purge_tombstones(&cutoff)
+            .map_err(|e| format!("Failed to purge tombstones: {}", e))?;
+        Ok(())
+    }
+
+    /// Tombstones of forgotten memories still within retention
+    pub fn tombstones(&self) -> Result<Vec<Tombstone>, String> {
+        self.store
+            .get_tombstones()
+            .map_err(|e| format!("Failed to get tombstones: {}", e))
+    }
+
+    /// Tombstone for the memory created at this timestamp, if it was forgotten
+    pub fn tombstone(&self, created_at: &str) -> Result<Option<Tombstone>, String> {
+        self.store
+            .get_tombstone(created_at)
+            .map_err(|e| format!("Failed to get tombstone: {}", e))
+    }
+
+    /// Apply a deletion from another store: forget the matching memory unless it
+    /// was updated after the deletion. Returns true if a memory was forgotten.
+    pub fn apply_tombstone(&self, tombstone: &Tombstone) -> Result<bool, String> {
+        let local = self.get_by_created_at(&tombstone.created_at)?;
+
+        match local {
+            Some(m) if m.updated_at > tombstone.deleted_at => Ok(false),
+            Some(m) => self.forget(m.id),
+            None => {
+                self.store
+                    .add_tombstone(tombstone)
+                    .map_err(|e| format!("Failed to record tombstone: {}", e))?;
+                Ok(false)
+            }
+        }
+    }
+
+    // =========================================================================
+    // Undo
+    // =========================================================================
+
+    /// A memory as it is now, for the undo journal. With `content`, the
+    /// write replaces or deletes the content, so its embedding and full
+    /// body are saved too.
+    fn save(&self, id: i64, content: bool) -> Result<Option<Saved>, String> {
+        let Some(memory) = self.get(id)? else {
+            return Ok(None);
+        };
+        let (body, embedding) = if content {
+            // A missing blob cannot be brought back, but must not block the write
+            let body = memory.blob.as_ref().and_then(|hash| self.blobs.get(hash).ok());
+            let embedding = self
+                .store
+                .find_embedding(&self.current_model, &blobs::hash(&memory.content))
+                .map_err(|e| format!("Failed to look up embedding: {}", e))?;
+            (body, embedding)
+        } else {
+            (None, None)
+        };
+        Ok(Some(Saved {
+            memory: proto::Memory::from(&memory),
+            blob: memory.blob,
+            body,
+            embedding,
+        }))
+    }
+
+    fn record_undo(&self, op: &str, created: Vec<i64>, before: Vec<Saved>) {
+        if let Some(step) = self.pending_undo.borrow_mut().as_mut() {
+            step.created.extend(created);
+            step.before.extend(before);
+            return;
+        }
+        self.journal(&UndoStep {
+            op: op.to_string(),
+            at: crate::dates::now_rfc3339(),
+            created,
+            before,
+            ingested_files: None,
+        });
+    }
+
+    /// Add a step to the undo journal. The write it records has already
+    /// happened, so a journal that can't be written is only a warning:
+    /// failing would have the caller retry, and store it twice.
+    fn journal(&self, step: &UndoStep) {
+        if let Err(e) = self.undo.push(step) {
+            eprintln!("Warning: {} can't be undone: {}", step.op, e);
+        }
+    }
+
+    /// Run `write`, journaling the remembers and forgets it makes as one
+    /// step `op` that undo reverses as a whole. What it managed before
+    /// failing is journaled too.
+    pub fn as_one_step<T, E: From<String>>(&self, op: &str, write: impl FnOnce() -> Result<T, E>) -> Result<T, E> {
+        *self.pending_undo.borrow_mut() = Some(UndoStep {
+            op: op.to_string(),
+            at: crate::dates::now_rfc3339(),
+            created: Vec::new(),
+            before: Vec::new(),
+            ingested_files: None,
+        });
+        let result = write();
+        let step = self.pending_undo.borrow_mut().take();
+        if let Some(step) = step.filter(|s| !s.is_empty()) {
+            self.journal(&step);
+        }
+        result
+    }
+
+    /// Reverse the latest write: forget what it created, and put back what
+    /// it changed or deleted. Returns the step undone, or None when there is
+    /// nothing left to undo.
+    pub fn undo(&self) -> Result<Option<UndoStep>, String> {
+        let Some(step) = self.undo.last()? else {
+            return Ok(None);
+        };
+        for &id in &step.created {
+            let blob = self.get(id)?.and_then(|m| m.blob);
+            self.delete(id, blob)?;
+        }
+        for saved in &step.before {
+            if step.op == "update" {
+                self.revert(saved)?;
+            } else {
+                self.restore(saved)?;
+            }
+        }
+        if let Some(files) = &step.ingested_files {
+            self.set_ingested_files(files)?;
+        }
+        self.undo.pop()?;
+        Ok(Some(step))
+    }
+
+    /// Put a forgotten memory back under its old ID
+    fn restore(&self, saved: &Saved) -> Result<(), String> {
+        if let Some(body) = &saved.body {
+            self.blobs.put(body)?;
+        }
+        let memory = saved.to_memory();
+        let embedding = saved
+            .embedding
+            .as_deref()
+            .map(|v| Embedding::new(v, &self.current_model, &memory.content));
+        self.store
+            .restore(&memory, embedding.as_ref())
+            .map_err(|e| format!("Failed to restore memory [{}]: {}", memory.id, e))?;
+        self.log_change("create", memory.id)
+    }
+
+    /// Return an updated memory to its saved content, tags, and metadata
+    fn revert(&self, saved: &Saved) -> Result<(), String> {
+        let memory = saved.to_memory();
+        let current = self
+            .get(memory.id)?
+            .ok_or_else(|| format!("Memory not found: {}", memory.id))?;
+        let content_changed = current.content != memory.content || current.blob != memory.blob;
+        if content_changed {
+            if let Some(body) = &saved.body {
+                self.blobs.put(body)?;
+            }
+        }
+
+        let changes = MemoryUpdate {
+            content: content_changed.then(|| memory.content.clone()),
+            confidence: Some(memory.confidence),
+            tags: Some(memory.tags.clone()),
+            kind: Some(memory.kind.clone().unwrap_or_default()),
+            namespace: Some(memory.namespace.clone().unwrap_or_default()),
+            pinned: Some(memory.pinned),
+            blob: content_changed.then(|| memory.blob.clone().unwrap_or_default()),
+        };
+        self.store
+            .update(memory.id, &changes)
+            .map_err(|e| format!("Failed to update memory: {}", e))?;
+
+        if content_changed {
+            let vector = match &saved.embedding {
+                Some(vector) => vector.clone(),
+                None => self.embed_text(&memory.content)?,
+            };
+            self.store
+                .update_embedding(memory.id, &Embedding::new(&vector, &self.current_model, &memory.content))
+                .map_err(|e| format!("Failed to update embedding: {}", e))?;
+            if let Some(hash) = current.blob {
+                self.release_blob(&hash)?;
+            }
+        }
+        self.log_change("update", memory.id)
+    }
+
+    // =========================================================================
+    // Change feed
+    // =========================================================================
+
+    /// Append a change event if the change feed is enabled
+    fn log_change(&self, op: &str, id: i64) -> Result<(), String> {
+        let Some(changelog) = &self.changelog else {
+            return Ok(());
+        };
+
+        let memory = if op == "delete" { None } else { self.get(id)? };
+        // Other processes log to the same feed
+        let _lock = changelog.lock()?;
+        let seq = self.changelog_seq()? + 1;
+
+        changelog.append(&ChangeEvent {
+            seq,
+            op: op.to_string(),
+            id,
+            at: crate::dates::now_rfc3339(),
+            memory: memory.as_ref().map(proto::Memory::from),
+        })?;
+
+        self.store
+            .set_metadata(CHANGELOG_SEQ_KEY, &seq.to_string())
+            .map_err(|e| format!("Failed to set metadata: {}", e))
+    }
+
+    /// Sequence number of the latest change event (0 if none)
+    pub fn changelog_seq(&self) -> Result<u64, String> {
+        let seq = self
+            .store
+            .get_metadata(CHANGELOG_SEQ_KEY)
+            .map_err(|e| format!("Failed to get metadata: {}", e))?;
+        Ok(seq.and_then(|s| s.parse().ok()).unwrap_or(0))
+    }
+
+    /// Change events after `seq`
+    pub fn changes_since(&self, seq: u64) -> Result<Vec<ChangeEvent>, String> {
+        Changelog::new(&self.roots_path).since(seq)
+    }
+
+    /// Whether the change feed is enabled for this store
+    pub fn changelog_enabled(&self) -> bool {
+        self.changelog.is_some()
+    }
+
+    // =========================================================================
+    // Stats and metadata
+    // =========================================================================
+
+    /// Number of memories, without the rest of `stats`
+    pub fn count(&self) -> Result<usize, String> {
+        self.store.count().map_err(|e| format!("Failed to count: {}", e))
+    }
+
+    /// Get statistics. These read every memory and tombstone for the
+    /// growth figures; callers that only need the total use `count`.
+    pub fn stats(&self) -> Result<MemoryStats, String> {
+        let count = self.count()?;
+
+        let tags = self
+            .store
+            .get_all_tags()
+            .map_err(|e| format!("Failed to get tags: {}", e))?;
+
+        let by_tag: HashMap<String, usize> = tags.into_iter().collect();
+
+        // Calculate average confidence
+        let memories = self
+            .store
+            .list(&MemoryFilter::default(), 1000, SortOrder::Recent)
+            .map_err(|e| format!("Failed to list: {}", e))?;
+
+        let avg_confidence = if memories.is_empty() {
+            0.0
+        } else {
+            memories.iter().map(|m| m.confidence).sum::<f64>() / memories.len() as f64
+        };
+
+        // Lengths and activity cover every memory, not just the recent ones
+        let all = self
+            .store
+            .list(&MemoryFilter::everything(), count.max(1), SortOrder::Recent)
+            .map_err(|e| format!("Failed to list: {}", e))?;
+        let avg_content_chars = if all.is_empty() {
+            0.0
+        } else {
+            all.iter().map(|m| m.content.chars().count()).sum::<usize>() as f64 / all.len() as f64
+        };
+        let tombstones = self.tombstones()?;
+        let created: Vec<&str> = all.iter().map(|m| m.created_at.as_str()).collect();
+        let now = chrono::Utc::now();
+
+        let db_bytes = (RootsConfig::new(self.roots_path.clone()).store_backend() == "sqlite").then(|| {
+            ["memory.db", "memory.db-wal"]
+                .iter()
+                .filter_map(|file| fs::metadata(self.roots_path.join(file)).ok())
+                .map(|m| m.len())
+                .sum()
+        });
+
+        Ok(MemoryStats {
+            total_memories: count,
+            total_tags: by_tag.len(),
+            by_tag,
+            avg_confidence,
+            avg_content_chars,
+            forgotten: tombstones.len(),
+            weekly: weekly_activity(&created, &tombstones, now),
+            monthly: monthly_activity(&created, &tombstones, now),
+            db_bytes,
+        })
+    }
+
+    /// Maintenance statistics for the memories with a tag
+    pub fn tag_health(&self, tag: &str) -> Result<TagHealth, String> {
+        let filter = MemoryFilter {
+            tag: Some(tag.to_lowercase()),
+            ..Default::default()
+        };
+        let members = self
+            .store
+            .list(&filter, i64::MAX as usize, SortOrder::Recent)
+            .map_err(|e| format!("Failed to list: {}", e))?;
+        let embeddings: Vec<Vec<f32>> = self
+            .store
+            .get_all_with_embeddings(&filter)
+            .map_err(|e| format!("Failed to get memories: {}", e))?
+            .into_iter()
+            .map(|(_, e)| e)
+            .collect();
+
+        Ok(tag_health(&members, &embeddings, chrono::Utc::now()))
+    }
+
+    /// How often the live memories are recalled, for `roots stats --access`
+    pub fn access_report(&self) -> Result<AccessReport, String> {
+        let memories = self
+            .store
+            .list(&MemoryFilter::default(), i64::MAX as usize, SortOrder::Recent)
+            .map_err(|e| format!("Failed to list: {}", e))?;
+        Ok(access_report(&memories))
+    }
+
+    /// Get all tags with counts
+    pub fn tags(&self) -> Result<Vec<(String, usize)>, String> {
+        self.store
+            .get_all_tags()
+            .map_err(|e| format!("Failed to get tags: {}", e))
+    }
+
+    /// Attach `path`, or `lines` of it, to a memory. The lines are kept as
+    /// they are now, so `show` can print them after the file changes or goes
+    pub fn attach(&self, id: i64, path: &Path, lines: Option<(u32, u32)>) -> Result<i64, Error> {
+        if self.get(id)?.is_none() {
+            return Err(Error::NotFound(format!("Memory not found: {}", id)));
+        }
+        let text = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let snippet = match lines {
+            Some(range) => Some(crate::source::select_lines(&text, range).ok_or_else(|| {
+                format!("{} has only {} lines", path.display(), text.lines().count())
+            })?),
+            None => None,
+        };
+
+        let absolute = path
+            .canonicalize()
+            .map_err(|e| format!("Failed to resolve {}: {}", path.display(), e))?;
+        let root = self.project_root();
+        let stored = absolute.strip_prefix(&root).unwrap_or(&absolute);
+
+        self.store
+            .add_attachment(&Attachment {
+                id: 0,
+                memory_id: id,
+                path: stored.to_string_lossy().to_string(),
+                lines,
+                snippet,
+                created_at: crate::dates::now_rfc3339(),
+            })
+            .map_err(|e| format!("Failed to attach: {}", e).into())
+    }
+
+    /// A memory's attachments, oldest first
+    pub fn attachments(&self, id: i64) -> Result<Vec<Attachment>, String> {
+        self.store
+            .get_attachments(id)
+            .map_err(|e| format!("Failed to get attachments: {}", e))
+    }
+
+    /// Where an attachment's file is now
+    pub fn attachment_path(&self, attachment: &Attachment) -> PathBuf {
+        self.project_root().join(&attachment.path)
+    }
+
+    /// The directory holding `.roots`, which attachment and ingested file
+    /// paths are relative to
+    pub fn project_root(&self) -> PathBuf {
+        let roots_path = self.roots_path.canonicalize().unwrap_or_else(|_| self.roots_path.clone());
+        roots_path.parent().map(Path::to_path_buf).unwrap_or(roots_path)
+    }
+
+    /// Agent sessions with memories, most recently active first
+    pub fn sessions(&self, limit: usize) -> Result<Vec<SessionSummary>, String> {
+        self.store
+            .sessions(limit)
+            .map_err(|e| format!("Failed to get sessions: {}", e))
+    }
+
+    /// Widen a tag filter no memory, here or in the global layer, matches
+    /// exactly to the tags it starts; the match says which was done
+    pub fn resolve_tag(&self, filter: &mut MemoryFilter) -> Result<TagMatch, String> {
+        let Some(tag) = &filter.tag else {
+            return Ok(TagMatch::Exact);
+        };
+        let mut known = self.tags()?;
+        if let Some(global) = &self.global {
+            known.extend(global.tags()?);
+        }
+        let tag_match = crate::index::match_tag(tag, &known);
+        filter.tag_prefix = matches!(tag_match, TagMatch::Prefix(_));
+        Ok(tag_match)
+    }
+
+    /// Replace every tag in `from` with `to` in one transaction, returning
+    /// the IDs of the memories that changed
+    pub fn rename_tags(&self, from: &[String], to: &str) -> Result<Vec<i64>, String> {
+        // Tags are stored trimmed and lowercased
+        let from: Vec<String> = from.iter().map(|t| t.trim().to_lowercase()).collect();
+        let to = to.trim().to_lowercase();
+        if to.is_empty() || from.iter().any(|t| t.is_empty()) {
+            return Err("Tags cannot be empty".to_string());
+        }
+        let known: Vec<String> = self.tags()?.into_iter().map(|(t, _)| t).collect();
+        if let Some(missing) = from.iter().find(|t| !known.contains(t)) {
+            return Err(format!("No memories are tagged '{}'", missing));
+        }
+
+        let ids = self
+            .store
+            .rename_tags(&from, &to)
+            .map_err(|e| format!("Failed to rename tags: {}", e))?;
+        for &id in &ids {
+            self.log_change("update", id)?;
+        }
+        Ok(ids)
+    }
+
+    // =========================================================================
+    // Importance
+    // =========================================================================
+
+    /// Recompute importance scores from the memory graph.
+    ///
+    /// Each memory is linked to its most similar memories and to its tags,
+    /// which stand in the graph as nodes of their own, so a tag shared by
+    /// many memories costs an edge per memory rather than one per pair;
+    /// importance is each memory's PageRank centrality in that graph.
+    pub fn compute_importance(&self) -> Result<usize, String> {
+        let all = self
+            .store
+            .get_all_with_embeddings(&MemoryFilter::default())
+            .map_err(|e| format!("Failed to get memories: {}", e))?;
+
+        let vectors: Vec<&[f32]> = all.iter().map(|(_, e)| e.as_slice()).collect();
+        let mut edges = nearest_edges(&vectors, SIMILARITY_EDGE_THRESHOLD, SIMILARITY_NEIGHBORS);
+        let mut tag_nodes: HashMap<&str, usize> = HashMap::new();
+        for (i, (memory, _)) in all.iter().enumerate() {
+            for tag in &memory.tags {
+                let next = all.len() + tag_nodes.len();
+                let node = *tag_nodes.entry(tag.as_str()).or_insert(next);
+                edges.push((i, node, SHARED_TAG_WEIGHT));
+            }
+        }
+
+        let ranks = centrality(all.len() + tag_nodes.len(), &edges);
+        let scores = normalized(&ranks[..all.len()]);
+        let updates: Vec<(i64, f64)> = all
+            .iter()
+            .zip(scores)
+            .map(|((m, _), s)| (m.id, s))
+            .collect();
+
+        self.store
+            .set_importance(&updates)
+            .map_err(|e| format!("Failed to store importance: {}", e))?;
+
+        let now = chrono::Utc::now().to_rfc3339();
+        self.store
+            .set_metadata(IMPORTANCE_UPDATED_KEY, &now)
+            .and_then(|_| {
+                self.store
+                    .set_metadata(IMPORTANCE_COUNT_KEY, &updates.len().to_string())
+            })
+            .map_err(|e| format!("Failed to set metadata: {}", e))?;
+
+        Ok(updates.len())
+    }
+
+    /// Recompute importance if it is stale or the store has changed size
+    pub fn refresh_importance(&self) -> Result<(), String> {
+        let meta = |key| {
+            self.store
+                .get_metadata(key)
+                .map_err(|e| format!("Failed to get metadata: {}", e))
+        };
+
+        let count = self
+            .store
+            .count()
+            .map_err(|e| format!("Failed to count: {}", e))?;
+
+        let fresh = match (meta(IMPORTANCE_UPDATED_KEY)?, meta(IMPORTANCE_COUNT_KEY)?) {
+            (Some(updated), Some(stored_count)) => {
+                let age_ok = chrono::DateTime::parse_from_rfc3339(&updated)
+                    .map(|t| {
+                        chrono::Utc::now().signed_duration_since(t)
+                            < chrono::Duration::hours(IMPORTANCE_MAX_AGE_HOURS)
+                    })
+                    .unwrap_or(false);
+                age_ok && stored_count == count.to_string()
+            }
+            _ => false,
+        };
+
+        if !fresh {
+            self.compute_importance()?;
+        }
+        Ok(())
+    }
+
+    // =========================================================================
+    // Embedding model management
+    // =========================================================================
+
+    /// Get the embedding model stored in the database
+    pub fn get_stored_model(&self) -> Result<Option<String>, String> {
+        self.store
+            .get_metadata(EMBEDDING_MODEL_KEY)
+            .map_err(|e| format!("Failed to get metadata: {}", e))
+    }
+
+    /// Set the embedding model in the database
+    pub fn set_stored_model(&self, model: &str) -> Result<(), String> {
+        self.store
+            .set_metadata(EMBEDDING_MODEL_KEY, model)
+            .map_err(|e| format!("Failed to set metadata: {}", e))
+    }
+
+    /// Files stored by `ingest docs`, with their content hashes
+    pub fn ingested_files(&self) -> Result<IngestManifest, String> {
+        let json = self
+            .store
+            .get_metadata(INGESTED_FILES_KEY)
+            .map_err(|e| format!("Failed to get metadata: {}", e))?;
+        match json {
+            Some(json) => serde_json::from_str(&json).map_err(|e| format!("Failed to parse ingested files: {}", e)),
+            None => Ok(IngestManifest::new()),
+        }
+    }
+
+    /// Save the hashes of ingested files; inside `as_one_step`, undo puts
+    /// back the ones they replace
+    pub fn set_ingested_files(&self, files: &IngestManifest) -> Result<(), String> {
+        if self.pending_undo.borrow().as_ref().is_some_and(|s| s.ingested_files.is_none()) {
+            let previous = self.ingested_files()?;
+            if previous != *files {
+                if let Some(step) = self.pending_undo.borrow_mut().as_mut() {
+                    step.ingested_files = Some(previous);
+                }
+            }
+        }
+        let json = serde_json::to_string(files).map_err(|e| format!("Failed to serialize ingested files: {}", e))?;
+        self.store
+            .set_metadata(INGESTED_FILES_KEY, &json)
+            .map_err(|e| format!("Failed to set metadata: {}", e))
+    }
+
+    /// Weights recall blends scores with
+    pub fn ranking(&self) -> &Ranking {
+        &self.ranking
+    }
+
+    /// Recall time past which a query counts as slow (`slow_query_ms`);
+    /// zero when the warning is off
+    pub fn slow_query(&self) -> Duration {
+        self.slow_query
+    }
+
+    /// Get the current embedding model
+    pub fn current_model(&self) -> &str {
+        &self.current_model
+    }
+
+    /// Schema version of the store, and the roots versions that created it
+    /// and last migrated it
+    pub fn schema_stamp(&self) -> Result<[Option<String>; 3], String> {
+        let get = |key| {
+            self.store
+                .get_metadata(key)
+                .map_err(|e| format!("Failed to get metadata: {}", e))
+        };
+        Ok([get(SCHEMA_VERSION_KEY)?, get(CREATED_BY_KEY)?, get(MIGRATED_BY_KEY)?])
+    }
+
+    /// Length of the vectors the current embedder produces
+    pub fn embedding_dimension(&self) -> Result<usize, String> {
+        if let Some(&dimension) = self.dimension.get() {
+            return Ok(dimension);
+        }
+        let dimension = self
+            .embedder
+            .embed("dimension probe")
+            .map(|e| e.len())
+            .map_err(|e| format!("Failed to embed: {}", e))?;
+        Ok(*self.dimension.get_or_init(|| dimension))
+    }
+
+    /// Whether the configured model's server couldn't be reached when the
+    /// store was opened, so lite embeds instead
+    pub fn server_unreachable(&self) -> bool {
+        self.fallback
+    }
+
+    /// Check if the current model differs from the stored model
+    pub fn check_model_mismatch(&self) -> Result<Option<String>, String> {
+        if let Some(stored) = self.get_stored_model()? {
+            if stored != self.current_model {
+                return Ok(Some(stored));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Embed up to `limit` queued memories, returns how many were embedded
+    pub fn embed_pending(&self, limit: usize) -> Result<usize, String> {
+        let pending = self
+            .store
+            .get_pending_embeddings(limit)
+            .map_err(|e| format!("Failed to get queued memories: {}", e))?;
+
+        for (id, content) in &pending {
+            let embedding = self
+                .embed_text(content)
+                .map_err(|e| format!("Failed to embed memory {}: {}", id, e))?;
+
+            self.store
+                .update_embedding(*id, &Embedding::new(&embedding, &self.current_model, content))
+                .map_err(|e| format!("Failed to update embedding for {}: {}", id, e))?;
+        }
+
+        Ok(pending.len())
+    }
+
+    /// Number of memories waiting for an embedding
+    pub fn pending_embeddings(&self) -> Result<usize, String> {
+        self.store
+            .count_pending_embeddings()
+            .map_err(|e| format!("Failed to count queued memories: {}", e))
+    }
+
+    /// Reindex the memories in `scope` with the current embedding model,
+    /// calling `progress(done, total)` as batches complete.
+    ///
+    /// Batches are embedded on as many threads as the embedder allows; rows
+    /// that fail keep their old embedding and are reported in the summary.
+    pub fn reindex(
+        &self,
+        scope: ReindexScope,
+        mut progress: impl FnMut(usize, usize),
+    ) -> Result<ReindexSummary, String> {
+        let mut summary = ReindexSummary::default();
+        let mut memories = match scope {
+            ReindexScope::Missing => self
+                .store
+                .get_stale_embeddings(&self.current_model, self.embedding_dimension()?),
+            ReindexScope::Changed | ReindexScope::All => self.store.get_all_for_reindex(),
+        }
+        .map_err(|e| format!("Failed to get memories: {}", e))?;
+
+        if scope == ReindexScope::Changed {
+            let hashes = self
+                .store
+                .get_embedding_hashes(&self.current_model)
+                .map_err(|e| format!("Failed to get embedding hashes: {}", e))?;
+            let before = memories.len();
+            memories.retain(|(id, content)| hashes.get(id) != Some(&blobs::hash(content)));
+            summary.unchanged = before - memories.len();
+        }
+
+        let total = memories.len();
+        let texts: HashMap<i64, &str> = memories.iter().map(|(id, c)| (*id, c.as_str())).collect();
+        let batches: Vec<_> = memories.chunks(REINDEX_BATCH).collect();
+        let workers = self.embedder.concurrency().clamp(1, batches.len().max(1));
+        let next = AtomicUsize::new(0);
+        let embedder = self.embedder.as_ref();
+
+        // Embedding runs on the workers; the store is only touched from here
+        thread::scope(|scope| {
+            let (tx, rx) = mpsc::channel();
+            for _ in 0..workers {
+                let tx = tx.clone();
+                let (next, batches) = (&next, &batches);
+                scope.spawn(move || {
+                    while let Some(batch) = batches.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        if tx.send(embed_batch(embedder, batch)).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+            drop(tx);
+
+            let mut done = 0;
+            for results in rx {
+                done += results.len();
+                for (id, result) in results {
+                    let stored = result.and_then(|embedding| {
+                        let embedding = Embedding::new(&embedding, &self.current_model, texts[&id]);
+                        self.store
+                            .update_embedding(id, &embedding)
+                            .map_err(|e| format!("Failed to update embedding: {}", e))
+                    });
+                    match stored {
+                        Ok(()) => summary.reindexed += 1,
+                        Err(e) => summary.failures.push((id, e)),
+                    }
+                }
+                progress(done, total);
+            }
+        });
+        summary.failures.sort_by_key(|(id, _)| *id);
+
+        // Rows that failed still hold the old model's vectors
+        if summary.failures.is_empty() {
+            self.set_stored_model(&self.current_model)?;
+        }
+
+        // Similarity edges changed along with the embeddings
+        if summary.reindexed > 0 {
+            self.compute_importance()?;
+        }
+
+        Ok(summary)
+    }
+}
+
+/// Which memories `Memories::reindex` re-embeds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReindexScope {
+    /// Every memory
+    All,
+    /// Memories whose text changed since the current model embedded them
+    Changed,
+    /// Unembedded memories, and ones with the wrong size or from another model
+    Missing,
+}
+
+/// Outcome of `Memories::reindex`
+#[derive(Debug, Default)]
+pub struct ReindexSummary {
+    pub reindexed: usize,
+    /// Skipped because their text and model were unchanged
+    pub unchanged: usize,
+    /// Memory ID and error for each row that was not re-embedded
+    pub failures: Vec<(i64, String)>,
+}
+
+/// Embed a batch in one request, falling back to one request per memory
+/// when the batch fails, so a single bad row doesn't sink its neighbours
+fn embed_batch(embedder: &dyn Embedder, batch: &[(i64, String)]) -> Vec<(i64, Result<Vec<f32>, String>)> {
+    let texts: Vec<&str> = batch.iter().map(|(_, content)| content.as_str()).collect();
+    match embedder.embed_batch(&texts) {
+        Ok(embeddings) if embeddings.len() == batch.len() => batch
+            .iter()
+            .zip(embeddings)
+            .map(|((id, _), embedding)| (*id, Ok(embedding)))
+            .collect(),
+        _ => batch
+            .iter()
+            .map(|(id, content)| {
+                let embedding = embedder
+                    .embed(content)
+                    .map_err(|e| format!("Failed to embed: {}", e));
+                (*id, embedding)
+            })
+            .collect(),
+    }
+}
+
+/// Open the storage backend chosen by the `store` config key
+fn open_store(roots_path: &Path, config: &RootsConfig) -> Result<Box<dyn Store>, Error> {
+    let _span = tracing::info_span!("open_store", path = %roots_path.display()).entered();
+    match config.store_backend().as_str() {
+        "sqlite" => {
+            let store = MemoryStore::open(&roots_path.join("memory.db"))
+                .map_err(|e| Error::store("Failed to open store", e))?;
+            Ok(Box::new(store))
+        }
+        "libsql" => {
+            let url = config
+                .libsql_url()
+                .ok_or("The libsql store needs a server: roots config libsql_url <url>")?;
+            let store = LibsqlStore::open(&url, config.libsql_auth_token())
+                .map_err(|e| Error::store("Failed to open store", e))?;
+            Ok(Box::new(store))
+        }
+        other => Err(Error::Invalid(format!(
+            "Unknown store: {} (expected one of: {})",
+            other,
+            STORE_BACKENDS.join(", ")
+        ))),
+    }
+}
+
+/// Whether a project memory with the same content hides this global one
+fn shadowed<'a>(memory: &Memory, project: impl IntoIterator<Item = &'a Memory>) -> bool {
+    let content = memory.content.trim().to_lowercase();
+    project
+        .into_iter()
+        .any(|p| !p.global && p.content.trim().to_lowercase() == content)
+}
+
+/// Signals blended into a recall score by the `ranking.*` weights
+fn score_parts(ranking: &Ranking, query_embedding: &[f32], row: &VectorRow) -> ScoreParts {
+    ScoreParts {
+        similarity: cosine_similarity(query_embedding, &row.embedding),
+        confidence: row.confidence,
+        recency: recency(&row.updated_at, ranking.recency_half_life),
+        access: access_boost(row),
+        importance: row.importance,
+    }
+}
+
+/// Recency of the last update (0-1): 1 now, halving every `half_life`
+fn recency(updated_at: &str, half_life: chrono::Duration) -> f64 {
+    let Some(updated) = crate::dates::parse_timestamp(updated_at) else {
+        return 0.0;
+    };
+    let age = (chrono::Utc::now() - updated).num_seconds().max(0) as f64;
+    0.5_f64.powf(age / half_life.num_seconds().max(1) as f64)
+}
+
+/// Access reinforcement (0-1): grows with access count and fades with time
+/// since the last access, so knowledge the agent keeps using ranks higher.
+fn access_boost(row: &VectorRow) -> f64 {
+    let Some(last) = row
+        .last_accessed_at
+        .as_deref()
+        .and_then(crate::dates::parse_timestamp)
+    else {
+        return 0.0;
+    };
+
+    let frequency = ((1 + row.access_count) as f64).ln() / (1.0 + ACCESS_SATURATION).ln();
+    let days = (chrono::Utc::now() - last).num_seconds().max(0) as f64 / 86400.0;
+    frequency.min(1.0) * (-days / ACCESS_DECAY_DAYS).exp()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    #[test]
+    fn test_stored_embedding_of_another_length_is_not_reused() {
+        let dir = test_support::scratch("memory-reuse");
+        let mem = test_support::store(&dir);
+        let content = "Deploys go through the staging cluster";
+        let id = mem
+            .remember(&NewMemory {
+                content: content.to_string(),
+                confidence: 0.8,
+                ..Default::default()
+            })
+            .unwrap();
+        let dimension = mem.embedding_dimension().unwrap();
+        assert_eq!(mem.embed_text(content).unwrap().len(), dimension);
+
+        // A vector labelled with this model but embedded by another
+        mem.store
+            .update_embedding(id, &Embedding::new(&[1.0, 0.0], "lite", content))
+            .unwrap();
+        assert_eq!(mem.embed_text(content).unwrap().len(), dimension);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_routing_seeds_are_embedded_once() {
+        let dir = test_support::scratch("memory-routing");
+        let config = "routing:\n  - namespace: infra\n    seed: deploys to the staging cluster\n    threshold: 0.5\n";
+        let roots = test_support::project(&dir, config);
+        let mem = Memories::open_at(roots.clone()).unwrap();
+        assert!(mem.seeds.get().is_none());
+
+        let remember = |content: &str| {
+            let id = mem
+                .remember(&NewMemory {
+                    content: content.to_string(),
+                    confidence: 0.8,
+                    ..Default::default()
+                })
+                .unwrap();
+            mem.get(id).unwrap().unwrap().namespace
+        };
+        assert_eq!(remember("Deploys to the staging cluster need approval"), Some("infra".to_string()));
+        let seed = mem.seeds.get().unwrap()[0].as_ref().unwrap().as_ptr();
+        assert_eq!(remember("Lunch is at noon on Fridays"), None);
+        assert_eq!(mem.seeds.get().unwrap()[0].as_ref().unwrap().as_ptr(), seed);
+
+        // A routing config that doesn't parse is an error, not no routing
+        test_support::project(&dir, "routing: infra\n");
+        let err = Memories::open_at(roots).err().unwrap();
+        assert!(err.to_string().contains("Invalid routing rules"), "{}", err);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_importance_favours_memories_linked_by_tags_and_similarity() {
+        let dir = test_support::scratch("memory-importance");
+        let mem = test_support::store(&dir);
+        let remember = |content: &str, tags: &[&str]| {
+            mem.remember(&NewMemory {
+                content: content.to_string(),
+                confidence: 0.8,
+                tags: tags.iter().map(|t| t.to_string()).collect(),
+                ..Default::default()
+            })
+            .unwrap()
+        };
+        let hub = remember("Deploys go through the staging cluster first", &["deploy", "staging"]);
+        remember("Deploys go through the staging cluster on Fridays", &["deploy"]);
+        remember("Staging cluster credentials live in the vault", &["staging"]);
+        let loner = remember("Lunch is at noon", &[]);
+
+        assert_eq!(mem.compute_importance().unwrap(), 4);
+        let importance = |id| mem.get(id).unwrap().unwrap().importance;
+        assert_eq!(importance(hub), 1.0);
+        assert_eq!(importance(loner), 0.0);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    /// Lite embeddings, four batches at a time, finishing out of order
+    struct Scrambled(crate::embeddings::LiteEmbedder);
+
+    impl Embedder for Scrambled {
+        fn embed(&self, text: &str) -> Result<Vec<f32>, String> {
+            self.0.embed(text)
+        }
+        fn model(&self) -> &str {
+            self.0.model()
+        }
+        fn concurrency(&self) -> usize {
+            4
+        }
+        fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>, String> {
+            let delay = crate::blobs::hash(texts[0]).as_bytes()[0] % 8;
+            thread::sleep(Duration::from_millis(delay as u64 * 5));
+            self.0.embed_batch(texts)
+        }
+    }
+
+    #[test]
+    fn test_parallel_reindex_gives_each_memory_its_own_vector() {
+        let dir = test_support::scratch("memory-reindex");
+        let mut mem = test_support::store(&dir);
+        let memories: Vec<NewMemory> = (0..150)
+            .map(|i| NewMemory {
+                content: format!("Note {} about topic {}", i, i * 7 % 13),
+                confidence: 0.5,
+                ..Default::default()
+            })
+            .collect();
+        let ids = mem.remember_batch(&memories, true).unwrap();
+        let listed = |mem: &Memories| -> Vec<i64> {
+            mem.list(&MemoryFilter::default(), 1000, SortOrder::Recent)
+                .unwrap()
+                .iter()
+                .map(|m| m.id)
+                .collect()
+        };
+        let before = listed(&mem);
+
+        mem.embedder = Box::new(Scrambled(crate::embeddings::LiteEmbedder::new()));
+        let mut reported = Vec::new();
+        let summary = mem
+            .reindex(ReindexScope::All, |done, total| reported.push((done, total)))
+            .unwrap();
+        assert_eq!(summary.reindexed, ids.len());
+        assert!(summary.failures.is_empty());
+        // Progress only moves forward, to the total
+        assert!(reported.windows(2).all(|w| w[0].0 < w[1].0));
+        assert_eq!(reported.last(), Some(&(ids.len(), ids.len())));
+
+        // One vector a memory, computed from that memory's own text
+        let vectors = mem.store.get_all_with_embeddings(&MemoryFilter::default()).unwrap();
+        assert_eq!(vectors.len(), ids.len());
+        let mut seen: Vec<i64> = vectors.iter().map(|(m, _)| m.id).collect();
+        seen.sort();
+        seen.dedup();
+        assert_eq!(seen.len(), ids.len());
+        for (memory, vector) in &vectors {
+            assert_eq!(vector, &mem.embedder.embed(&memory.content).unwrap(), "memory {}", memory.id);
+        }
+        // And the memories keep their order
+        assert_eq!(listed(&mem), before);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_rename_tags_matches_however_the_tags_are_typed() {
+        let dir = test_support::scratch("memory-rename");
+        let mem = test_support::store(&dir);
+        let id = mem
+            .remember(&NewMemory {
+                content: "Migrations run before deploys".to_string(),
+                confidence: 0.8,
+                tags: vec!["postgres".to_string()],
+                ..Default::default()
+            })
+            .unwrap();
 
-        // Update stored model to current
-        self.set_stored_model(&self.current_model)?;
+        assert_eq!(mem.rename_tags(&[" Postgres ".to_string()], " Database ").unwrap(), vec![id]);
+        let tags: Vec<String> = mem.tags().unwrap().into_iter().map(|(t, _)| t).collect();
+        assert_eq!(tags, vec!["database"]);
 
-        Ok(count)
+        let _ = fs::remove_dir_all(&dir);
     }
 }
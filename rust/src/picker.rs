@@ -0,0 +1,210 @@
+//! Built-in fuzzy picker, for choosing a memory without looking up its ID.
+//!
+//! Typing narrows the list to entries containing the typed characters in
+//! order (fzf-style); runs of adjacent characters and matches at word starts
+//! rank first. Up/Down or Ctrl-P/Ctrl-N move, Enter picks, Esc or Ctrl-C
+//! cancels. Drawn on the alternate screen, so the scrollback is left as it was.
+
+use crate::term::{self, RawMode};
+use std::io::{self, Write};
+
+/// Rows used by the prompt and the key help
+const CHROME_ROWS: usize = 2;
+
+/// Score of `query` against `text`, higher is better; `None` if the
+/// characters of `query` do not all appear in `text` in order. Case is
+/// ignored and spaces in the query are skipped.
+pub fn score(query: &str, text: &str) -> Option<i64> {
+    let query: Vec<char> = query.chars().flat_map(char::to_lowercase).filter(|c| !c.is_whitespace()).collect();
+    let text: Vec<char> = text.chars().flat_map(char::to_lowercase).collect();
+    let Some(&first) = query.first() else {
+        return Some(0);
+    };
+    // Matching greedily from the first occurrence can miss a tighter match
+    // later on, so try each place the query could start
+    (0..text.len())
+        .filter(|&start| text[start] == first)
+        .filter_map(|start| score_from(&query, &text, start))
+        .max()
+}
+
+/// Score of a match whose first character is at `start`, taking each
+/// following character at its next occurrence
+fn score_from(query: &[char], text: &[char], start: usize) -> Option<i64> {
+    let mut score = 0;
+    let mut at = start;
+    let mut previous: Option<usize> = None;
+    for &q in query {
+        let found = at + text[at..].iter().position(|&c| c == q)?;
+        score += 1;
+        if previous.is_some_and(|p| p + 1 == found) {
+            score += 4;
+        }
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 2;
+        }
+        previous = Some(found);
+        at = found + 1;
+    }
+    // Among equal matches, prefer the one that starts earlier
+    Some(score * 1000 - (start as i64).min(999))
+}
+
+/// Indexes of the entries matching `query`, best first (list order among
+/// equal scores)
+pub fn filter(query: &str, entries: &[String]) -> Vec<usize> {
+    let mut matches: Vec<(i64, usize)> = entries
+        .iter()
+        .enumerate()
+        .filter_map(|(i, entry)| score(query, entry).map(|s| (s, i)))
+        .collect();
+    matches.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    matches.into_iter().map(|(_, i)| i).collect()
+}
+
+enum Key {
+    Char(char),
+    Up,
+    Down,
+    Enter,
+    Backspace,
+    Cancel,
+    Other,
+}
+
+/// Let the user pick one of `entries` (one line each); returns its index,
+/// or `None` if they cancel
+pub fn pick(prompt: &str, entries: &[String]) -> Result<Option<usize>, String> {
+    let rows = term::size().map_or(20, |(rows, _)| rows).saturating_sub(CHROME_ROWS).max(1);
+    let width = term::size().map_or(80, |(_, cols)| cols);
+
+    let _raw = RawMode::enable_keys()?;
+    let _screen = AlternateScreen::enter()?;
+
+    let mut query = String::new();
+    let mut matches = filter(&query, entries);
+    let mut selected = 0;
+    loop {
+        draw(prompt, &query, entries, &matches, selected, rows, width)?;
+        match read_key()? {
+            Key::Enter => return Ok(matches.get(selected).copied()),
+            Key::Cancel => return Ok(None),
+            Key::Up => selected = selected.saturating_sub(1),
+            Key::Down => selected = (selected + 1).min(matches.len().saturating_sub(1)),
+            Key::Backspace => {
+                query.pop();
+                matches = filter(&query, entries);
+                selected = 0;
+            }
+            Key::Char(c) => {
+                query.push(c);
+                matches = filter(&query, entries);
+                selected = 0;
+            }
+            Key::Other => {}
+        }
+    }
+}
+
+fn draw(
+    prompt: &str,
+    query: &str,
+    entries: &[String],
+    matches: &[usize],
+    selected: usize,
+    rows: usize,
+    width: usize,
+) -> Result<(), String> {
+    // Scroll so the selection stays on screen
+    let first = selected.saturating_sub(rows - 1);
+    let mut screen = String::from("\x1b[H\x1b[2J");
+    screen.push_str(&format!("{} ({}/{})> {}\r\n", prompt, matches.len(), entries.len(), query));
+    screen.push_str("\x1b[2m  up/down to move, enter to pick, esc to cancel\x1b[0m\r\n");
+    for (row, &i) in matches.iter().enumerate().skip(first).take(rows) {
+        let line: String = entries[i].chars().take(width.saturating_sub(2)).collect();
+        if row == selected {
+            screen.push_str(&format!("\x1b[7m> {}\x1b[0m\r\n", line));
+        } else {
+            screen.push_str(&format!("  {}\r\n", line));
+        }
+    }
+    // Leave the cursor after the query
+    let column = prompt.chars().count() + format!(" ({}/{})> ", matches.len(), entries.len()).len() + query.chars().count();
+    screen.push_str(&format!("\x1b[1;{}H", column + 1));
+
+    let mut stdout = io::stdout();
+    stdout
+        .write_all(screen.as_bytes())
+        .and_then(|_| stdout.flush())
+        .map_err(|e| format!("Failed to draw picker: {}", e))
+}
+
+fn read_key() -> Result<Key, String> {
+    let Some(c) = term::read_key()? else {
+        return Ok(Key::Cancel);
+    };
+    Ok(match c {
+        '\r' | '\n' => Key::Enter,
+        '\x7f' | '\x08' => Key::Backspace,
+        // Ctrl-C, Ctrl-G
+        '\x03' | '\x07' => Key::Cancel,
+        // Ctrl-P, Ctrl-N
+        '\x10' => Key::Up,
+        '\x0e' => Key::Down,
+        '\x1b' => match term::read_pending_key()? {
+            // A lone Esc
+            None => Key::Cancel,
+            Some('[') | Some('O') => match term::read_pending_key()? {
+                Some('A') => Key::Up,
+                Some('B') => Key::Down,
+                _ => Key::Other,
+            },
+            Some(_) => Key::Other,
+        },
+        c if c.is_ascii_graphic() || c == ' ' => Key::Char(c),
+        _ => Key::Other,
+    })
+}
+
+/// Switches to the alternate screen until dropped
+struct AlternateScreen;
+
+impl AlternateScreen {
+    fn enter() -> Result<Self, String> {
+        print!("\x1b[?1049h");
+        io::stdout()
+            .flush()
+            .map_err(|e| format!("Failed to draw picker: {}", e))?;
+        Ok(Self)
+    }
+}
+
+impl Drop for AlternateScreen {
+    fn drop(&mut self) {
+        print!("\x1b[?1049l");
+        let _ = io::stdout().flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_ranks_adjacent_and_word_start_matches() {
+        let entries: Vec<String> = [
+            "[3] Prefer tabs in Makefiles",
+            "[2] Use postgres for the main db",
+            "[1] deploy with pg_dump backups",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+
+        assert_eq!(score("xyz", &entries[0]), None);
+        assert_eq!(filter("pg", &entries), vec![2, 1]);
+        assert_eq!(filter("POST db", &entries), vec![1]);
+        // An empty query keeps every entry in list order
+        assert_eq!(filter("", &entries), vec![0, 1, 2]);
+    }
+}
@@ -0,0 +1,595 @@
+//! The JSON contract for everything roots prints or writes for machines:
+//! exports, imports, packs, the change feed, `context` and `prime` records,
+//! and `--output json`.
+//!
+//! These types are deliberately separate from `types.rs`, so internal
+//! structs can change without changing the output. Within a `VERSION`,
+//! fields are only ever added; renaming, removing, or retyping a field
+//! bumps it. The golden files in `tests/golden/` pin the current shapes.
+
+use crate::types;
+use serde::{Deserialize, Serialize};
+
+/// Version of the JSON contract
+pub const VERSION: u32 = 1;
+
+/// A memory
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Memory {
+    pub id: i64,
+    pub content: String,
+    pub confidence: f64,
+    pub tags: Vec<String>,
+    pub created_at: String,
+    pub updated_at: String,
+    pub last_accessed_at: Option<String>,
+    #[serde(default)]
+    pub access_count: i64,
+    #[serde(default)]
+    pub importance: f64,
+    #[serde(default)]
+    pub kind: Option<String>,
+    #[serde(default)]
+    pub expires_at: Option<String>,
+    #[serde(default)]
+    pub source: Option<String>,
+    #[serde(default)]
+    pub namespace: Option<String>,
+    #[serde(default)]
+    pub pinned: bool,
+    /// Agent session the memory was learned in
+    #[serde(default)]
+    pub session_id: Option<String>,
+    /// From the user-global store (only present when true)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub global: bool,
+}
+
+impl From<&types::Memory> for Memory {
+    fn from(m: &types::Memory) -> Self {
+        Self {
+            id: m.id,
+            content: m.content.clone(),
+            confidence: m.confidence,
+            tags: m.tags.clone(),
+            created_at: m.created_at.clone(),
+            updated_at: m.updated_at.clone(),
+            last_accessed_at: m.last_accessed_at.clone(),
+            access_count: m.access_count,
+            importance: m.importance,
+            kind: m.kind.clone(),
+            expires_at: m.expires_at.clone(),
+            source: m.source.clone(),
+            namespace: m.namespace.clone(),
+            pinned: m.pinned,
+            session_id: m.session_id.clone(),
+            global: m.global,
+        }
+    }
+}
+
+impl From<Memory> for types::Memory {
+    fn from(m: Memory) -> Self {
+        Self {
+            id: m.id,
+            content: m.content,
+            confidence: m.confidence,
+            tags: m.tags,
+            created_at: m.created_at,
+            updated_at: m.updated_at,
+            last_accessed_at: m.last_accessed_at,
+            access_count: m.access_count,
+            importance: m.importance,
+            kind: m.kind,
+            expires_at: m.expires_at,
+            source: m.source,
+            session_id: m.session_id,
+            namespace: m.namespace,
+            pinned: m.pinned,
+            blob: None,
+            global: m.global,
+        }
+    }
+}
+
+/// A forgotten memory, identified by its creation time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tombstone {
+    pub created_at: String,
+    pub deleted_at: String,
+}
+
+impl From<&types::Tombstone> for Tombstone {
+    fn from(t: &types::Tombstone) -> Self {
+        Self {
+            created_at: t.created_at.clone(),
+            deleted_at: t.deleted_at.clone(),
+        }
+    }
+}
+
+impl From<Tombstone> for types::Tombstone {
+    fn from(t: Tombstone) -> Self {
+        Self {
+            created_at: t.created_at,
+            deleted_at: t.deleted_at,
+        }
+    }
+}
+
+/// `export --tombstones` output, and one of the shapes `import` reads
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportBundle {
+    /// Contract version the bundle was written with (absent before 1)
+    #[serde(default)]
+    pub version: u32,
+    pub memories: Vec<Memory>,
+    #[serde(default)]
+    pub tombstones: Vec<Tombstone>,
+}
+
+/// A shareable bundle of memories (`roots pack create`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Pack {
+    /// Contract version the pack was written with
+    pub version: u32,
+    pub name: String,
+    /// The pack's own release, e.g. "1.2.0"
+    pub pack_version: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub created_at: String,
+    pub memories: Vec<Memory>,
+}
+
+/// One line of the change feed (`roots changes`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChangeEvent {
+    /// Monotonic sequence number, starting at 1
+    pub seq: u64,
+    /// "create", "update", or "delete"
+    pub op: String,
+    pub id: i64,
+    /// When the change happened (RFC 3339)
+    pub at: String,
+    /// Full memory after the change (absent for deletes)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory: Option<Memory>,
+}
+
+/// A search hit (`recall --output json`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Hit {
+    #[serde(flatten)]
+    pub memory: Memory,
+    /// Recall score, or BM25 relevance with `--fts`
+    pub score: f64,
+    /// Passage around the matched terms, each between `**`, with `--fts`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<String>,
+    /// Project the hit comes from, with `--all-projects`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+}
+
+/// A memory as `context` and `prime` print it for other agent frameworks,
+/// its content cut to fit
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContextMemory {
+    pub id: i64,
+    /// Relevance to the prompt, for `context`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<f64>,
+    pub confidence: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    pub tags: Vec<String>,
+    /// Where the claim comes from, normalized (see `source.rs`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(default)]
+    pub global: bool,
+    /// Kind, "key", or "recent", for `prime`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub section: Option<String>,
+    pub content: String,
+    /// The content was cut short (only present when true)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+}
+
+/// `context --format json` output
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Context {
+    #[serde(default)]
+    pub version: u32,
+    pub memories: Vec<ContextMemory>,
+}
+
+/// `prime --format json` output
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Prime {
+    #[serde(default)]
+    pub version: u32,
+    /// Memories in the project store
+    pub available: usize,
+    /// Memories in the user-global store
+    pub global: usize,
+    pub topics: Vec<String>,
+    pub memories: Vec<ContextMemory>,
+}
+
+/// A file, or lines of one, attached to a memory
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Attachment {
+    pub id: i64,
+    pub path: String,
+    /// First and last line, inclusive
+    #[serde(default)]
+    pub lines: Option<(u32, u32)>,
+    /// The lines as they were when attached
+    #[serde(default)]
+    pub snippet: Option<String>,
+    pub created_at: String,
+}
+
+impl From<&types::Attachment> for Attachment {
+    fn from(a: &types::Attachment) -> Self {
+        Self {
+            id: a.id,
+            path: a.path.clone(),
+            lines: a.lines,
+            snippet: a.snippet.clone(),
+            created_at: a.created_at.clone(),
+        }
+    }
+}
+
+/// A memory with its full content and attachments (`show --output json`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Detail {
+    #[serde(flatten)]
+    pub memory: Memory,
+    /// Hash of the body in the blob store, when it was too big for the index
+    #[serde(default)]
+    pub blob: Option<String>,
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+}
+
+/// A tag and how many memories have it (`tags --output json`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TagCount {
+    pub tag: String,
+    pub count: usize,
+}
+
+/// `stats --output json`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Stats {
+    pub total_memories: usize,
+    pub total_tags: usize,
+    pub avg_confidence: f64,
+    #[serde(default)]
+    pub avg_content_chars: f64,
+    /// Forgotten memories whose tombstones are still kept
+    #[serde(default)]
+    pub forgotten: usize,
+    /// Database files on disk; absent for remote backends
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub db_bytes: Option<u64>,
+    /// Every tag, most used first
+    pub tags: Vec<TagCount>,
+    /// Last 12 ISO weeks with any activity, oldest first
+    #[serde(default)]
+    pub weekly: Vec<Activity>,
+    /// Last 12 months with any activity, oldest first
+    #[serde(default)]
+    pub monthly: Vec<Activity>,
+}
+
+/// Memories added and forgotten in one week or month
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Activity {
+    pub period: String,
+    pub added: usize,
+    pub forgotten: usize,
+}
+
+/// `stats --access --output json`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccessStats {
+    pub count: usize,
+    pub never_accessed: usize,
+    /// Most accessed first
+    pub most_recalled: Vec<Memory>,
+    /// Never-accessed memories, oldest first
+    pub oldest_unused: Vec<Memory>,
+    /// Every tag, most accessed first
+    pub tags: Vec<TagAccess>,
+}
+
+/// Access totals for one tag
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TagAccess {
+    pub tag: String,
+    pub count: usize,
+    pub accesses: i64,
+    pub avg_accesses: f64,
+    pub never_accessed: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_accessed_at: Option<String>,
+}
+
+/// `bench --output json`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bench {
+    /// Memories embedded by each model
+    pub texts: usize,
+    /// `slow_query_ms`, the recall budget; 0 when off
+    pub budget_ms: u64,
+    pub results: Vec<BenchRun>,
+}
+
+/// Timings of one embedder
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchRun {
+    /// "lite", or the model the server has loaded
+    pub model: String,
+    pub server: bool,
+    pub dimensions: usize,
+    /// Latency of single `embed` calls
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+    /// Texts per second embedded in batches
+    pub texts_per_sec: f64,
+}
+
+/// `eval --output json`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Eval {
+    /// Embedding model recall used
+    pub model: String,
+    pub rerank: bool,
+    pub k: usize,
+    /// Mean share of expected memories in the top k
+    pub recall_at_k: f64,
+    /// Mean reciprocal rank of the first expected memory
+    pub mrr: f64,
+    pub cases: Vec<EvalCase>,
+}
+
+/// How one eval case did
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvalCase {
+    pub query: String,
+    pub expected: Vec<i64>,
+    /// IDs recalled, best first
+    pub retrieved: Vec<i64>,
+    pub recall: f64,
+    pub reciprocal_rank: f64,
+}
+
+/// `stats --queries --output json`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueryStats {
+    /// Whether queries are being logged now (`query_log` is not "off")
+    pub logging: bool,
+    pub queries: usize,
+    pub distinct: usize,
+    /// Queries that returned nothing
+    pub empty: usize,
+    pub avg_latency_ms: f64,
+    /// When the oldest logged query ran
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub since: Option<String>,
+    /// Most frequent first
+    pub frequent: Vec<QueryCount>,
+    /// Queries that returned nothing, most frequent first
+    pub unanswered: Vec<QueryCount>,
+}
+
+/// Logged queries with the same normalized text
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueryCount {
+    pub query_hash: String,
+    /// Absent when logged with `query_log hash`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query: Option<String>,
+    pub count: usize,
+    pub avg_results: f64,
+    pub last_at: String,
+}
+
+/// Memories in one bucket of a tag health breakdown
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bucket {
+    pub label: String,
+    pub count: usize,
+}
+
+/// `stats --tag <tag> --output json`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TagHealth {
+    pub tag: String,
+    pub count: usize,
+    pub avg_confidence: f64,
+    pub confidence: Vec<Bucket>,
+    /// By creation time
+    pub age: Vec<Bucket>,
+    pub never_accessed: usize,
+    /// Recalled within the last 30 days
+    pub recently_accessed: usize,
+    pub avg_access_count: f64,
+    /// Pairs similar enough to be duplicates
+    pub duplicate_pairs: usize,
+    /// Members still waiting for an embedding
+    pub unembedded: usize,
+}
+
+/// A failed command, with `--output json` (see `error.rs`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Error {
+    /// "not_initialized", "not_found", "embedder_unavailable", "locked",
+    /// "invalid", or "other"
+    pub kind: String,
+    pub message: String,
+    /// What the process exits with
+    pub exit_code: i32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    fn memory() -> Memory {
+        let memory = types::Memory {
+            created_at: "2026-01-15T09:30:00+00:00".to_string(),
+            updated_at: "2026-02-01T12:00:00+00:00".to_string(),
+            last_accessed_at: Some("2026-02-03T08:00:00+00:00".to_string()),
+            access_count: 3,
+            importance: 0.25,
+            kind: Some("preference".to_string()),
+            source: Some("README.md#L10-L12".to_string()),
+            pinned: true,
+            session_id: Some("4f1c2a".to_string()),
+            ..test_support::memory(7, "Use uv for Python projects", &["python", "tooling"])
+        };
+        Memory::from(&memory)
+    }
+
+    /// Serialized output must match the golden file byte for byte, and the
+    /// golden file must read back to the same value
+    fn assert_golden<T>(value: &T, golden: &str)
+    where
+        T: Serialize + for<'de> Deserialize<'de> + PartialEq + std::fmt::Debug,
+    {
+        assert_eq!(serde_json::to_string_pretty(value).unwrap(), golden.trim_end());
+        assert_eq!(&serde_json::from_str::<T>(golden).unwrap(), value);
+    }
+
+    #[test]
+    fn test_golden_export_bundle() {
+        let bundle = ExportBundle {
+            version: VERSION,
+            memories: vec![memory()],
+            tombstones: vec![Tombstone {
+                created_at: "2026-01-01T00:00:00+00:00".to_string(),
+                deleted_at: "2026-01-20T00:00:00+00:00".to_string(),
+            }],
+        };
+        assert_golden(&bundle, include_str!("../tests/golden/export_bundle.json"));
+    }
+
+    #[test]
+    fn test_golden_change_events() {
+        let events = vec![
+            ChangeEvent {
+                seq: 1,
+                op: "create".to_string(),
+                id: 7,
+                at: "2026-01-15T09:30:00+00:00".to_string(),
+                memory: Some(memory()),
+            },
+            ChangeEvent {
+                seq: 2,
+                op: "delete".to_string(),
+                id: 7,
+                at: "2026-02-10T00:00:00+00:00".to_string(),
+                memory: None,
+            },
+        ];
+        assert_golden(&events, include_str!("../tests/golden/change_events.json"));
+    }
+
+    #[test]
+    fn test_golden_pack() {
+        let pack = Pack {
+            version: VERSION,
+            name: "python-tooling".to_string(),
+            pack_version: "1.2.0".to_string(),
+            description: Some("How we set up Python projects".to_string()),
+            created_at: "2026-03-01T00:00:00+00:00".to_string(),
+            memories: vec![memory()],
+        };
+        assert_golden(&pack, include_str!("../tests/golden/pack.json"));
+    }
+
+    #[test]
+    fn test_golden_output() {
+        let hits = vec![Hit {
+            memory: memory(),
+            score: 0.82,
+            snippet: None,
+            project: Some("~/src/api".to_string()),
+        }];
+        assert_golden(&hits, include_str!("../tests/golden/recall.json"));
+
+        let detail = Detail {
+            memory: memory(),
+            blob: None,
+            attachments: vec![Attachment {
+                id: 1,
+                path: "src/client.rs".to_string(),
+                lines: Some((10, 12)),
+                snippet: Some("fn retry() {\n    backoff();\n}".to_string()),
+                created_at: "2026-02-01T12:00:00+00:00".to_string(),
+            }],
+        };
+        assert_golden(&detail, include_str!("../tests/golden/show.json"));
+
+        let error = Error {
+            kind: "not_found".to_string(),
+            message: "Memory not found: 7".to_string(),
+            exit_code: 4,
+        };
+        assert_golden(&error, include_str!("../tests/golden/error.json"));
+    }
+
+    #[test]
+    fn test_golden_context() {
+        let record = |score, section: Option<&str>, truncated| ContextMemory {
+            id: 7,
+            score,
+            confidence: 0.8,
+            kind: Some("preference".to_string()),
+            tags: vec!["python".to_string(), "tooling".to_string()],
+            source: Some("README.md:10-12".to_string()),
+            global: false,
+            section: section.map(String::from),
+            content: "Use uv for Python projects".to_string(),
+            truncated,
+        };
+
+        let context = Context {
+            version: VERSION,
+            memories: vec![record(Some(0.82), None, true)],
+        };
+        assert_golden(&context, include_str!("../tests/golden/context.json"));
+
+        let prime = Prime {
+            version: VERSION,
+            available: 12,
+            global: 3,
+            topics: vec!["python".to_string(), "tooling".to_string()],
+            memories: vec![record(None, Some("preference"), false)],
+        };
+        assert_golden(&prime, include_str!("../tests/golden/prime.json"));
+    }
+
+    #[test]
+    fn test_reads_older_and_newer_documents() {
+        // Written before the contract had a version, by a newer roots with
+        // fields this one does not know
+        let json = r#"{"memories": [{"id": 1, "content": "x", "confidence": 0.5, "tags": [],
+            "created_at": "2026-01-01T00:00:00Z", "updated_at": "2026-01-01T00:00:00Z",
+            "last_accessed_at": null, "embedding_model": "bge-base"}]}"#;
+        let bundle: ExportBundle = serde_json::from_str(json).unwrap();
+        assert_eq!(bundle.version, 0);
+        assert_eq!(bundle.memories[0].access_count, 0);
+        assert!(bundle.tombstones.is_empty());
+    }
+}
@@ -0,0 +1,89 @@
+//! Field filters inside a recall query.
+//!
+//! `roots recall "tag:architecture conf>=0.7 before:2024-06 database locking"`
+//! takes the `tag:`, `kind:`, `ns:`, `before:`, `after:`, and `conf`
+//! comparisons out of the query as filters and searches for the words that
+//! remain. Any other `word:word` is left in the text.
+
+use crate::types::{Comparison, MemoryFilter, MEMORY_KINDS};
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// Apply the field filters in `query` to `filter`, returning the free text
+pub fn parse(query: &str, filter: &mut MemoryFilter) -> Result<String, String> {
+    static CONFIDENCE_RE: OnceLock<Regex> = OnceLock::new();
+    let confidence_re = CONFIDENCE_RE
+        .get_or_init(|| Regex::new(r"^conf(?:idence)?(<=|>=|<|>|=)(.+)$").unwrap());
+
+    let mut text = Vec::new();
+    for token in query.split_whitespace() {
+        if let Some(caps) = confidence_re.captures(token) {
+            let op = Comparison::parse(&caps[1]).unwrap_or(Comparison::Eq);
+            let value: f64 = caps[2]
+                .parse()
+                .map_err(|_| format!("Invalid confidence in '{}'", token))?;
+            filter.confidence.push((op, value));
+            continue;
+        }
+
+        let Some((key, value)) = token.split_once(':') else {
+            text.push(token);
+            continue;
+        };
+        match key {
+            "tag" | "kind" | "ns" | "namespace" | "before" | "after" if value.is_empty() => {
+                return Err(format!("Missing value in '{}'", token));
+            }
+            "tag" => set_once(&mut filter.tag, value.to_string(), key)?,
+            "kind" => {
+                if !MEMORY_KINDS.contains(&value) {
+                    return Err(format!(
+                        "Unknown kind '{}' (use {})",
+                        value,
+                        MEMORY_KINDS.join(", ")
+                    ));
+                }
+                set_once(&mut filter.kind, value.to_string(), key)?
+            }
+            "ns" | "namespace" => set_once(&mut filter.namespace, value.to_string(), key)?,
+            "before" => set_once(&mut filter.created_before, crate::dates::parse_point(value)?, key)?,
+            "after" => set_once(&mut filter.created_after, crate::dates::parse_point(value)?, key)?,
+            _ => text.push(token),
+        }
+    }
+
+    Ok(text.join(" "))
+}
+
+/// A field given twice (here or also as a flag) is ambiguous
+fn set_once(field: &mut Option<String>, value: String, key: &str) -> Result<(), String> {
+    if field.is_some() {
+        return Err(format!("'{}:' given more than once", key));
+    }
+    *field = Some(value);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_fields_and_text() {
+        let mut filter = MemoryFilter::default();
+        let text = parse(
+            "tag:architecture conf>=0.7 before:2024-06 database locking see:docs",
+            &mut filter,
+        )
+        .unwrap();
+
+        assert_eq!(text, "database locking see:docs");
+        assert_eq!(filter.tag.as_deref(), Some("architecture"));
+        assert_eq!(filter.confidence, vec![(Comparison::Ge, 0.7)]);
+        assert_eq!(filter.created_before.as_deref(), Some("2024-06-01T00:00:00+00:00"));
+
+        assert!(parse("kind:vibe", &mut MemoryFilter::default()).is_err());
+        assert!(parse("conf>high", &mut MemoryFilter::default()).is_err());
+        assert!(parse("tag:a tag:b", &mut MemoryFilter::default()).is_err());
+    }
+}
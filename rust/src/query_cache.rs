@@ -0,0 +1,121 @@
+//! On-disk LRU cache of query embeddings.
+//!
+//! The prompt hook runs `roots context` on every message, and prompts repeat
+//! ("continue", "run the tests", a retried question). With a server model each
+//! lookup is a round trip to the embedding server, so recent query vectors are
+//! kept in `.roots/query_cache.db`, keyed by model and text hash. It is
+//! separate from `memory.db` so it never touches the store's schema, and can
+//! be deleted at any time.
+
+use crate::store::{deserialize_embedding, serialize_embedding};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const CACHE_FILE: &str = "query_cache.db";
+
+/// Queries kept; the least recently used are evicted past this
+const CAPACITY: i64 = 512;
+
+/// A busy cache is skipped rather than waited on
+const BUSY_TIMEOUT: Duration = Duration::from_millis(100);
+
+const SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS query_embeddings (
+    model TEXT NOT NULL,
+    text_hash TEXT NOT NULL,
+    embedding BLOB NOT NULL,
+    used_at INTEGER NOT NULL,
+    PRIMARY KEY (model, text_hash)
+);
+CREATE INDEX IF NOT EXISTS idx_query_embeddings_used ON query_embeddings(used_at);
+"#;
+
+pub struct QueryCache {
+    path: PathBuf,
+}
+
+impl QueryCache {
+    pub fn new(roots_path: &Path) -> Self {
+        Self {
+            path: roots_path.join(CACHE_FILE),
+        }
+    }
+
+    /// Cached embedding of `text` under `model`, marking it recently used
+    pub fn get(&self, model: &str, text: &str) -> rusqlite::Result<Option<Vec<f32>>> {
+        let conn = self.connect()?;
+        let hash = crate::blobs::hash(text);
+        let embedding: Option<Vec<u8>> = conn
+            .query_row(
+                "SELECT embedding FROM query_embeddings WHERE model = ?1 AND text_hash = ?2",
+                params![model, hash],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if embedding.is_some() {
+            conn.execute(
+                "UPDATE query_embeddings SET used_at = ?3 WHERE model = ?1 AND text_hash = ?2",
+                params![model, hash, now_micros()],
+            )?;
+        }
+        Ok(embedding.map(|blob| deserialize_embedding(&blob)))
+    }
+
+    /// Cache the embedding of `text`, evicting the least recently used
+    pub fn put(&self, model: &str, text: &str, embedding: &[f32]) -> rusqlite::Result<()> {
+        let conn = self.connect()?;
+        conn.execute(
+            "INSERT OR REPLACE INTO query_embeddings (model, text_hash, embedding, used_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![model, crate::blobs::hash(text), serialize_embedding(embedding), now_micros()],
+        )?;
+        conn.execute(
+            "DELETE FROM query_embeddings WHERE rowid NOT IN
+             (SELECT rowid FROM query_embeddings ORDER BY used_at DESC LIMIT ?1)",
+            params![CAPACITY],
+        )?;
+        Ok(())
+    }
+
+    fn connect(&self) -> rusqlite::Result<Connection> {
+        let conn = Connection::open(&self.path)?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        // Losing the cache in a crash costs a few server round trips
+        conn.pragma_update(None, "synchronous", "OFF")?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(conn)
+    }
+}
+
+/// Recency stamp; microseconds so lookups in one run stay ordered
+fn now_micros() -> i64 {
+    chrono::Utc::now().timestamp_micros()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_cache_keys_by_model_and_evicts() {
+        let dir = std::env::temp_dir().join(format!("roots-query-cache-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let cache = QueryCache::new(&dir);
+
+        cache.put("bge-base", "run the tests", &[0.5, -1.0]).unwrap();
+        assert_eq!(cache.get("bge-base", "run the tests").unwrap(), Some(vec![0.5, -1.0]));
+        assert_eq!(cache.get("bge-small", "run the tests").unwrap(), None);
+
+        // Touch the first query so it outlives the ones added after it
+        for i in 0..CAPACITY {
+            cache.put("bge-base", &format!("query {}", i), &[i as f32]).unwrap();
+            if i == 0 {
+                cache.get("bge-base", "run the tests").unwrap();
+            }
+        }
+        assert!(cache.get("bge-base", "run the tests").unwrap().is_some());
+        assert_eq!(cache.get("bge-base", "query 0").unwrap(), None);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,233 @@
+//! Log of recall and context queries, for `roots stats --queries`.
+//!
+//! With `query_log` set to "hash" or "text", each query is recorded with its
+//! result count, top score, and latency in `.roots/query_log.db`. Queries
+//! that found nothing point at knowledge missing from the store. "hash"
+//! keeps only a hash of the normalized text, so repeats can be counted
+//! without keeping what was asked. Like the query cache, it is separate from
+//! `memory.db` and can be deleted at any time.
+
+use rusqlite::{params, Connection};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const LOG_FILE: &str = "query_log.db";
+
+/// `query_log` settings; the first is the default
+pub const QUERY_LOG_MODES: [&str; 3] = ["off", "hash", "text"];
+
+/// Queries kept; the oldest are dropped past this
+const CAPACITY: i64 = 10_000;
+
+/// A busy log is skipped rather than waited on
+const BUSY_TIMEOUT: Duration = Duration::from_millis(100);
+
+const SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS query_log (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    created_at TEXT NOT NULL,
+    command TEXT NOT NULL,
+    query_hash TEXT NOT NULL,
+    query TEXT,
+    result_count INTEGER NOT NULL,
+    top_score REAL,
+    latency_ms INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_query_log_hash ON query_log(query_hash);
+"#;
+
+/// One logged query
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoggedQuery {
+    /// "recall", "fts" (`recall --fts`, scored by BM25), or "context"
+    pub command: String,
+    pub query: String,
+    pub result_count: usize,
+    pub top_score: Option<f64>,
+    pub latency: Duration,
+}
+
+/// Queries with the same normalized text, grouped
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuerySummary {
+    pub query_hash: String,
+    /// Absent when logged in "hash" mode
+    pub query: Option<String>,
+    pub count: usize,
+    pub avg_results: f64,
+    pub last_at: String,
+}
+
+/// Totals over the whole log
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QueryTotals {
+    pub queries: usize,
+    pub distinct: usize,
+    /// Queries that returned nothing
+    pub empty: usize,
+    pub avg_latency_ms: f64,
+    pub since: Option<String>,
+}
+
+/// What `roots stats --queries` shows
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QueryReport {
+    pub totals: QueryTotals,
+    pub frequent: Vec<QuerySummary>,
+    pub unanswered: Vec<QuerySummary>,
+}
+
+pub struct QueryLog {
+    path: PathBuf,
+    keep_text: bool,
+}
+
+impl QueryLog {
+    pub fn new(roots_path: &Path, keep_text: bool) -> Self {
+        Self {
+            path: roots_path.join(LOG_FILE),
+            keep_text,
+        }
+    }
+
+    /// Record a query, dropping the oldest past `CAPACITY`
+    pub fn record(&self, entry: &LoggedQuery) -> rusqlite::Result<()> {
+        let conn = self.connect()?;
+        conn.execute(
+            "INSERT INTO query_log (created_at, command, query_hash, query, result_count, top_score, latency_ms)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                chrono::Utc::now().to_rfc3339(),
+                entry.command,
+                query_hash(&entry.query),
+                self.keep_text.then_some(&entry.query),
+                entry.result_count as i64,
+                entry.top_score,
+                entry.latency.as_millis() as i64,
+            ],
+        )?;
+        conn.execute(
+            "DELETE FROM query_log WHERE id <= (SELECT MAX(id) FROM query_log) - ?1",
+            params![CAPACITY],
+        )?;
+        Ok(())
+    }
+
+    pub fn totals(&self) -> rusqlite::Result<QueryTotals> {
+        let conn = self.connect()?;
+        conn.query_row(
+            "SELECT COUNT(*), COUNT(DISTINCT query_hash), COALESCE(SUM(result_count = 0), 0),
+                    COALESCE(AVG(latency_ms), 0), MIN(created_at)
+             FROM query_log",
+            [],
+            |row| {
+                Ok(QueryTotals {
+                    queries: row.get::<_, i64>(0)? as usize,
+                    distinct: row.get::<_, i64>(1)? as usize,
+                    empty: row.get::<_, i64>(2)? as usize,
+                    avg_latency_ms: row.get(3)?,
+                    since: row.get(4)?,
+                })
+            },
+        )
+    }
+
+    /// Totals, and the top `limit` frequent and unanswered queries
+    pub fn report(&self, limit: usize) -> rusqlite::Result<QueryReport> {
+        Ok(QueryReport {
+            totals: self.totals()?,
+            frequent: self.frequent(limit)?,
+            unanswered: self.unanswered(limit)?,
+        })
+    }
+
+    /// Most repeated queries, most frequent first
+    pub fn frequent(&self, limit: usize) -> rusqlite::Result<Vec<QuerySummary>> {
+        self.summaries("", limit)
+    }
+
+    /// Queries that returned nothing, most frequent first
+    pub fn unanswered(&self, limit: usize) -> rusqlite::Result<Vec<QuerySummary>> {
+        self.summaries("WHERE result_count = 0", limit)
+    }
+
+    fn summaries(&self, filter: &str, limit: usize) -> rusqlite::Result<Vec<QuerySummary>> {
+        let conn = self.connect()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT query_hash, MAX(query), COUNT(*), AVG(result_count), MAX(created_at)
+             FROM query_log {}
+             GROUP BY query_hash
+             ORDER BY COUNT(*) DESC, MAX(id) DESC
+             LIMIT ?1",
+            filter
+        ))?;
+        let rows = stmt.query_map(params![limit as i64], |row| {
+            Ok(QuerySummary {
+                query_hash: row.get(0)?,
+                query: row.get(1)?,
+                count: row.get::<_, i64>(2)? as usize,
+                avg_results: row.get(3)?,
+                last_at: row.get(4)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    fn connect(&self) -> rusqlite::Result<Connection> {
+        let conn = Connection::open(&self.path)?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        // Losing the newest entries in a crash costs nothing that matters
+        conn.pragma_update(None, "synchronous", "OFF")?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(conn)
+    }
+}
+
+/// Hash of `query` ignoring case and spacing, so trivial variations group
+pub fn query_hash(query: &str) -> String {
+    let normalized = query.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+    crate::blobs::hash(&normalized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(text: &str, results: usize) -> LoggedQuery {
+        LoggedQuery {
+            command: "recall".to_string(),
+            query: text.to_string(),
+            result_count: results,
+            top_score: (results > 0).then_some(0.8),
+            latency: Duration::from_millis(10),
+        }
+    }
+
+    #[test]
+    fn test_query_log_groups_and_finds_unanswered() {
+        let dir = std::env::temp_dir().join(format!("roots-query-log-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let log = QueryLog::new(&dir, true);
+
+        log.record(&query("run the tests", 3)).unwrap();
+        log.record(&query("Run  the tests", 2)).unwrap();
+        log.record(&query("deploy to staging", 0)).unwrap();
+
+        let totals = log.totals().unwrap();
+        assert_eq!((totals.queries, totals.distinct, totals.empty), (3, 2, 1));
+
+        let frequent = log.frequent(10).unwrap();
+        assert_eq!(frequent[0].count, 2);
+        assert_eq!(frequent[0].avg_results, 2.5);
+        let unanswered = log.unanswered(10).unwrap();
+        assert_eq!(unanswered.len(), 1);
+        assert_eq!(unanswered[0].query.as_deref(), Some("deploy to staging"));
+
+        // In hash mode the text is not kept
+        QueryLog::new(&dir, false).record(&query("secret plans", 0)).unwrap();
+        let unanswered = log.unanswered(10).unwrap();
+        assert_eq!(unanswered[0].query, None);
+        assert_eq!(unanswered[0].query_hash, query_hash("SECRET plans"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
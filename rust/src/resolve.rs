@@ -0,0 +1,222 @@
+//! Settling conflicts between a local memory and an incoming copy of it.
+//!
+//! Used by `roots import`; anything else that pulls memories from another
+//! store should go through `Resolver` so conflicts behave the same everywhere.
+
+use crate::term;
+use crate::types::{Memory, MemoryUpdate};
+use std::io::{self, IsTerminal};
+
+/// How conflicts are settled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    /// Prompt for each conflict (requires a terminal)
+    Ask,
+    /// Keep the local version
+    Local,
+    /// Take the incoming version
+    Remote,
+    /// Leave the local version and report the conflict
+    Skip,
+}
+
+impl Strategy {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "ask" => Ok(Self::Ask),
+            "local" => Ok(Self::Local),
+            "remote" => Ok(Self::Remote),
+            "skip" => Ok(Self::Skip),
+            _ => Err(format!(
+                "Unknown strategy: {} (use ask, local, remote, or skip)",
+                name
+            )),
+        }
+    }
+}
+
+/// Outcome for one conflict
+#[derive(Debug, Clone, PartialEq)]
+pub enum Resolution {
+    KeepLocal,
+    KeepRemote,
+    /// Hand-merged content
+    Merge(String),
+    Skip,
+}
+
+/// Whether two versions of the same memory disagree on anything
+pub fn conflicts(local: &Memory, remote: &Memory) -> bool {
+    let mut local_tags = local.tags.clone();
+    let mut remote_tags = remote.tags.clone();
+    local_tags.sort();
+    remote_tags.sort();
+
+    local.content != remote.content
+        || (local.confidence - remote.confidence).abs() > f64::EPSILON
+        || local_tags != remote_tags
+        || local.kind != remote.kind
+}
+
+/// Changes to apply to the local memory for a resolution, if any
+pub fn update_for(
+    local: &Memory,
+    remote: &Memory,
+    resolution: &Resolution,
+) -> Option<MemoryUpdate> {
+    match resolution {
+        Resolution::KeepLocal | Resolution::Skip => None,
+        Resolution::KeepRemote => Some(MemoryUpdate {
+            content: Some(remote.content.clone()).filter(|c| *c != local.content),
+            confidence: Some(remote.confidence),
+            tags: Some(remote.tags.clone()),
+            kind: Some(remote.kind.clone().unwrap_or_default()),
+            ..Default::default()
+        }),
+        Resolution::Merge(content) => {
+            let mut tags = local.tags.clone();
+            for t in &remote.tags {
+                if !tags.contains(t) {
+                    tags.push(t.clone());
+                }
+            }
+            Some(MemoryUpdate {
+                content: Some(content.clone()),
+                confidence: Some(local.confidence.max(remote.confidence)),
+                tags: Some(tags),
+                kind: local.kind.clone().or_else(|| remote.kind.clone()),
+                ..Default::default()
+            })
+        }
+    }
+}
+
+/// Settles conflicts with a strategy, prompting when it is `Ask`
+pub struct Resolver {
+    strategy: Strategy,
+}
+
+impl Resolver {
+    pub fn new(strategy: Strategy) -> Self {
+        Self { strategy }
+    }
+
+    /// Decide what to do with a conflicting pair
+    pub fn resolve(&mut self, local: &Memory, remote: &Memory) -> Result<Resolution, String> {
+        match self.strategy {
+            Strategy::Local => Ok(Resolution::KeepLocal),
+            Strategy::Remote => Ok(Resolution::KeepRemote),
+            Strategy::Skip => Ok(Resolution::Skip),
+            Strategy::Ask => {
+                if !io::stdin().is_terminal() {
+                    return Err(format!(
+                        "Conflict on [{}] needs a decision. Rerun with --strategy local, remote, or skip",
+                        local.id
+                    ));
+                }
+                self.prompt(local, remote)
+            }
+        }
+    }
+
+    fn prompt(&mut self, local: &Memory, remote: &Memory) -> Result<Resolution, String> {
+        println!(
+            "\nConflict on [{}] (created {})",
+            local.id, local.created_at
+        );
+        print_side("local", local);
+        print_side("incoming", remote);
+
+        loop {
+            let answer = term::prompt_line(
+                "[l]ocal  [r]emote  [m]erge in $EDITOR  [s]kip  (L/R/S for all remaining) > ",
+            )?
+            .ok_or("Aborted")?;
+
+            match answer.as_str() {
+                "l" => return Ok(Resolution::KeepLocal),
+                "r" => return Ok(Resolution::KeepRemote),
+                "s" => return Ok(Resolution::Skip),
+                "L" => self.strategy = Strategy::Local,
+                "R" => self.strategy = Strategy::Remote,
+                "S" => self.strategy = Strategy::Skip,
+                "m" => {
+                    let merged = term::edit_text(&merge_template(local, remote))?;
+                    if merged.contains("<<<<<<<") || merged.contains(">>>>>>>") {
+                        println!("Conflict markers left in the merged text, try again.");
+                        continue;
+                    }
+                    if merged.is_empty() {
+                        println!("Empty merge, skipping.");
+                        return Ok(Resolution::Skip);
+                    }
+                    return Ok(Resolution::Merge(merged));
+                }
+                _ => continue,
+            }
+            // An "all remaining" answer also settles this conflict
+            return self.resolve(local, remote);
+        }
+    }
+}
+
+fn print_side(label: &str, m: &Memory) {
+    println!(
+        "  {:<9} conf {:.1}  tags [{}]{}",
+        label,
+        m.confidence,
+        m.tags.join(", "),
+        m.kind
+            .as_ref()
+            .map(|k| format!("  kind {}", k))
+            .unwrap_or_default()
+    );
+    for line in m.content.lines() {
+        println!("    {}", line);
+    }
+}
+
+fn merge_template(local: &Memory, remote: &Memory) -> String {
+    format!(
+        "<<<<<<< local\n{}\n=======\n{}\n>>>>>>> incoming\n",
+        local.content.trim_end(),
+        remote.content.trim_end()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::memory;
+
+    #[test]
+    fn test_conflicts_ignore_tag_order() {
+        let local = memory(1, "Use tabs", &["style", "go"]);
+        assert!(!conflicts(&local, &memory(1, "Use tabs", &["go", "style"])));
+        assert!(conflicts(&local, &memory(1, "Use spaces", &["go", "style"])));
+    }
+
+    #[test]
+    fn test_merge_unions_tags() {
+        let local = memory(1, "Use tabs", &["style"]);
+        let remote = memory(1, "Use spaces", &["python"]);
+        let merge = Resolution::Merge("Tabs in Go, spaces in Python".to_string());
+
+        let update = update_for(&local, &remote, &merge).unwrap();
+        assert_eq!(
+            update.content.as_deref(),
+            Some("Tabs in Go, spaces in Python")
+        );
+        assert_eq!(
+            update.tags,
+            Some(vec!["style".to_string(), "python".to_string()])
+        );
+
+        assert!(update_for(&local, &remote, &Resolution::KeepLocal).is_none());
+        let mut resolver = Resolver::new(Strategy::Remote);
+        assert_eq!(
+            resolver.resolve(&local, &remote).unwrap(),
+            Resolution::KeepRemote
+        );
+    }
+}
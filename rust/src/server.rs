@@ -0,0 +1,224 @@
+//! Launching the Python embedding server.
+//!
+//! `roots server start` and the systemd unit run the same command. With
+//! `server.autostart`, a command that wants the server and finds it down
+//! launches it here and waits for the model to load, rather than quietly
+//! embedding with lite. It runs from `server_dir`, where `server start` last
+//! ran it, since hooks run from the user's projects rather than the roots
+//! checkout. Concurrent commands (say, several hooks after a reboot) take
+//! an OS lock on `autostart.lock` in the state directory, so only one
+//! launches it and the rest wait.
+
+use crate::config::state_dir;
+use crate::embeddings::ServerEmbedder;
+use crate::lock::FileLock;
+use std::fs::{self, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// How long autostart waits for the model to load before using lite
+pub const AUTOSTART_WAIT: Duration = Duration::from_secs(30);
+
+/// How often a starting server is checked for readiness
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+const AUTOSTART_LOCK: &str = "autostart.lock";
+
+/// Arguments to `uv` that run the server for `models` on `socket` with
+/// `backend` (`server_backend`); the first model is the default, for
+/// requests that don't name one
+pub fn args(models: &[String], backend: &str, socket: &Path, idle_timeout: Option<chrono::Duration>) -> Vec<String> {
+    let onnx = backend == "onnx";
+    // ONNX Runtime comes with the package's onnx extra
+    let extra: &[&str] = if onnx { &["--extra", "onnx"] } else { &[] };
+    let mut args: Vec<String> = std::iter::once(&"run")
+        .chain(extra)
+        .chain(&["python", "-m", "roots.server"])
+        .map(|a| a.to_string())
+        .collect();
+    for (i, model) in models.iter().enumerate() {
+        args.push(if i == 0 { "--model" } else { "--extra-model" }.to_string());
+        args.push(model.clone());
+    }
+    if onnx {
+        args.push("--backend".to_string());
+        args.push(backend.to_string());
+    }
+    args.push("--socket".to_string());
+    args.push(socket.display().to_string());
+    if let Some(idle) = idle_timeout {
+        args.push("--idle-timeout".to_string());
+        args.push(idle.num_seconds().to_string());
+    }
+    args
+}
+
+/// `args` as one shell command, each single-quoted (a `'` within one
+/// becomes `'\''`)
+pub fn shell_command(args: &[String]) -> String {
+    std::iter::once("uv".to_string())
+        .chain(args.iter().map(|a| format!("'{}'", a.replace('\'', r"'\''"))))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The systemd unit's `ExecStart` command: `shell_command` run by
+/// `/bin/sh`, inside double quotes that systemd unescapes and expands `%`
+/// and `$` in first
+pub fn exec_start(args: &[String]) -> String {
+    let command = shell_command(args)
+        .replace('\\', r"\\")
+        .replace('"', r#"\""#)
+        .replace('%', "%%")
+        .replace('$', "$$");
+    format!("/bin/sh -c \"{}\"", command)
+}
+
+/// Where a background server writes its output
+pub fn log_path() -> PathBuf {
+    state_dir().join("server.log")
+}
+
+/// Start the server in the background, detached from the terminal, with its
+/// output appended to `log_path()`; from `dir` when given, or else the
+/// current directory
+pub fn spawn(args: &[String], socket: &Path, dir: Option<&Path>) -> Result<Child, String> {
+    let log = log_path();
+    for dir in [socket.parent(), log.parent()].into_iter().flatten() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+    let output = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&log)
+        .map_err(|e| format!("Failed to open {}: {}", log.display(), e))?;
+    let errors = output.try_clone().map_err(|e| e.to_string())?;
+
+    use std::os::unix::process::CommandExt;
+    let mut command = Command::new("nohup");
+    if let Some(dir) = dir {
+        command.current_dir(dir);
+    }
+    command
+        .arg("uv")
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(output)
+        .stderr(errors)
+        // Its own process group, so Ctrl-C in the terminal doesn't reach it
+        .process_group(0)
+        .spawn()
+        .map_err(|e| format!("Failed to start server: {}", e))
+}
+
+/// Wait up to `timeout` for a spawned server to answer, calling `tick` with
+/// the whole seconds waited so far; fails early if the process exits
+pub fn wait_until_ready(
+    server: &ServerEmbedder,
+    child: &mut Child,
+    timeout: Duration,
+    mut tick: impl FnMut(u64),
+) -> Result<(), String> {
+    let started = Instant::now();
+    let mut seconds = 0;
+    while started.elapsed() < timeout {
+        std::thread::sleep(POLL_INTERVAL);
+        if server.is_running() {
+            return Ok(());
+        }
+        if let Ok(Some(status)) = child.try_wait() {
+            return Err(format!("Server exited ({}); see {}", status, log_path().display()));
+        }
+        if started.elapsed().as_secs() > seconds {
+            seconds = started.elapsed().as_secs();
+            tick(seconds);
+        }
+    }
+    Err(format!(
+        "Server did not start within {}s; see {}",
+        timeout.as_secs(),
+        log_path().display()
+    ))
+}
+
+/// Launch the server for `model` and wait for it, or wait for another
+/// command that is already launching it. The server loads the configured
+/// models too, with `model` as its default if it isn't one of them.
+pub fn autostart(server: &ServerEmbedder, model: &str) -> Result<(), String> {
+    let _span = tracing::info_span!("server_autostart", model).entered();
+    // Without a state directory to lock in, launch it anyway
+    let _lock = match lock_autostart() {
+        Ok(Some(lock)) => Some(lock),
+        Ok(None) => return wait_for_other(server),
+        Err(_) => None,
+    };
+    // It may have come up while the lock was being taken
+    if server.is_running() {
+        return Ok(());
+    }
+    eprintln!("Starting the embedding server ({})...", model);
+    let mut models = crate::config::get_server_models();
+    if !models.iter().any(|m| m == model) {
+        models.insert(0, model.to_string());
+    }
+    let backend = crate::config::server_backend();
+    let args = args(&models, &backend, server.socket(), crate::config::server_idle_timeout());
+    let mut child = spawn(&args, server.socket(), crate::config::server_dir().as_deref())?;
+    wait_until_ready(server, &mut child, AUTOSTART_WAIT, |_| {})
+}
+
+fn wait_for_other(server: &ServerEmbedder) -> Result<(), String> {
+    let started = Instant::now();
+    while started.elapsed() < AUTOSTART_WAIT {
+        std::thread::sleep(POLL_INTERVAL);
+        if server.is_running() {
+            return Ok(());
+        }
+    }
+    Err(format!("Server did not start within {}s", AUTOSTART_WAIT.as_secs()))
+}
+
+/// The lock marking an autostart in progress, or None when another command
+/// holds it. The OS releases it if that command dies.
+fn lock_autostart() -> std::io::Result<Option<FileLock>> {
+    let dir = state_dir();
+    fs::create_dir_all(&dir)?;
+    FileLock::try_acquire(&dir.join(AUTOSTART_LOCK))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_args_and_shell_command() {
+        let models = ["BAAI/bge-base-en-v1.5".to_string()];
+        let plain = args(&models, "torch", Path::new("/run/user/1000/roots/embedder.sock"), None);
+        assert_eq!(
+            shell_command(&plain),
+            "uv 'run' 'python' '-m' 'roots.server' '--model' 'BAAI/bge-base-en-v1.5' \
+             '--socket' '/run/user/1000/roots/embedder.sock'"
+        );
+        let models = ["big".to_string(), "small".to_string()];
+        let idle = args(&models, "torch", Path::new("/tmp/s.sock"), Some(chrono::Duration::minutes(30)));
+        assert_eq!(idle[4..8], ["--model", "big", "--extra-model", "small"]);
+        assert_eq!(idle[idle.len() - 2..], ["--idle-timeout", "1800"]);
+
+        // ONNX needs the extra installed as well as asked for
+        let onnx = args(&models, "onnx", Path::new("/tmp/s.sock"), None);
+        assert_eq!(onnx[..3], ["run", "--extra", "onnx"]);
+        assert_eq!(onnx[10..12], ["--backend", "onnx"]);
+
+        // Quotes and what systemd would expand survive both layers
+        let odd = args(&models, "torch", Path::new("/tmp/it's \"$HOME\" 100%\\.sock"), None);
+        assert!(shell_command(&odd).ends_with(r#"'--socket' '/tmp/it'\''s "$HOME" 100%\.sock'"#));
+        assert!(exec_start(&odd).ends_with(r#"'--socket' '/tmp/it'\\''s \"$$HOME\" 100%%\\.sock'""#));
+        let output = Command::new("/bin/sh")
+            .args(["-c", &shell_command(&odd).replacen("uv", "printf '%s\\n'", 1)])
+            .output()
+            .unwrap();
+        let printed = String::from_utf8(output.stdout).unwrap();
+        assert_eq!(printed.lines().last(), Some("/tmp/it's \"$HOME\" 100%\\.sock"));
+    }
+}
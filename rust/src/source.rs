@@ -0,0 +1,147 @@
+//! Where a memory's claim comes from.
+//!
+//! Memories keep their provenance as one `source` string. `remember
+//! --source` takes a file (with an optional line or range), a URL, or a
+//! command and stores it normalized, so `show` and `context` can present
+//! the parts: `client.rs:42`, `https://...`, `cmd:cargo test`. Sources
+//! written by roots itself (`README.md#L10-L12` from `ingest docs`,
+//! `commit:<sha>`, `pack:<name>@<version>`) parse the same way, as do the
+//! line ranges of `attach --lines`.
+
+use std::fmt;
+
+/// Prefix marking a command in a stored source
+const COMMAND_PREFIX: &str = "cmd:";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Source {
+    /// A file, optionally at a line or a range of lines
+    File { path: String, lines: Option<(u32, u32)> },
+    Url(String),
+    /// A command whose output the claim is based on
+    Command(String),
+    /// Anything else, e.g. `commit:<sha>`
+    Other(String),
+}
+
+impl Source {
+    pub fn parse(raw: &str) -> Self {
+        let raw = raw.trim();
+        if let Some(command) = raw.strip_prefix(COMMAND_PREFIX).or_else(|| raw.strip_prefix("$ ")) {
+            return Self::Command(command.trim().to_string());
+        }
+        if raw.contains("://") {
+            return Self::Url(raw.to_string());
+        }
+        if raw.contains(char::is_whitespace) {
+            return Self::Command(raw.to_string());
+        }
+
+        // path:12, path:12-20, or path#L12-L20 as `ingest docs` writes them
+        let located = raw
+            .rsplit_once('#')
+            .and_then(|(path, lines)| Some((path, parse_lines(lines.strip_prefix('L')?)?)))
+            .or_else(|| raw.rsplit_once(':').and_then(|(path, lines)| Some((path, parse_lines(lines)?))));
+        match located {
+            Some((path, lines)) if !path.is_empty() => Self::File {
+                path: path.to_string(),
+                lines: Some(lines),
+            },
+            _ if raw.contains(':') => Self::Other(raw.to_string()),
+            _ => Self::File {
+                path: raw.to_string(),
+                lines: None,
+            },
+        }
+    }
+
+    /// `file`, `url`, `command`, or `other`
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::File { .. } => "file",
+            Self::Url(_) => "url",
+            Self::Command(_) => "command",
+            Self::Other(_) => "other",
+        }
+    }
+}
+
+/// `12`, `12-20`, or `L12-L20` after the first `L` is stripped
+fn parse_lines(lines: &str) -> Option<(u32, u32)> {
+    let (start, end) = match lines.split_once('-') {
+        Some((start, end)) => (start, end.trim_start_matches('L')),
+        None => (lines, lines),
+    };
+    let (start, end) = (start.parse().ok()?, end.parse().ok()?);
+    (start <= end).then_some((start, end))
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::File { path, lines: None } => write!(f, "{}", path),
+            Self::File {
+                path,
+                lines: Some((start, end)),
+            } if start == end => write!(f, "{}:{}", path, start),
+            Self::File {
+                path,
+                lines: Some((start, end)),
+            } => write!(f, "{}:{}-{}", path, start, end),
+            Self::Url(url) => write!(f, "{}", url),
+            Self::Command(command) => write!(f, "{}{}", COMMAND_PREFIX, command),
+            Self::Other(raw) => write!(f, "{}", raw),
+        }
+    }
+}
+
+/// Parse a `--lines` argument: `12` or `12-20`, counting from 1
+pub fn parse_line_range(arg: &str) -> Result<(u32, u32), String> {
+    parse_lines(arg.trim())
+        .filter(|(start, _)| *start > 0)
+        .ok_or_else(|| format!("Invalid line range: {} (use 12 or 12-20)", arg))
+}
+
+/// Lines `start` to `end` of `text`, if it has that many
+pub fn select_lines(text: &str, (start, end): (u32, u32)) -> Option<String> {
+    let lines: Vec<&str> = text.lines().collect();
+    let selected = lines.get(start as usize - 1..end as usize)?;
+    Some(selected.join("\n"))
+}
+
+/// Normalize a `--source` argument for storage
+pub fn parse_arg(raw: &str) -> Result<String, String> {
+    if raw.trim().is_empty() {
+        return Err("Source cannot be empty".to_string());
+    }
+    Ok(Source::parse(raw).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sources() {
+        let file = |path: &str, lines| Source::File {
+            path: path.to_string(),
+            lines,
+        };
+        assert_eq!(Source::parse("src/client.rs:42"), file("src/client.rs", Some((42, 42))));
+        assert_eq!(Source::parse("README.md#L10-L12"), file("README.md", Some((10, 12))));
+        assert_eq!(Source::parse("notes.md"), file("notes.md", None));
+        assert_eq!(Source::parse("https://example.com/a:1").kind(), "url");
+        assert_eq!(Source::parse("cargo test -p api"), Source::Command("cargo test -p api".to_string()));
+        assert_eq!(Source::parse("commit:4f1c2a").kind(), "other");
+
+        assert_eq!(Source::parse("README.md#L10-L12").to_string(), "README.md:10-12");
+        assert_eq!(parse_arg("$ make deploy").unwrap(), "cmd:make deploy");
+        assert_eq!(parse_arg("cmd:make deploy").unwrap(), "cmd:make deploy");
+
+        assert_eq!(parse_line_range("10-40"), Ok((10, 40)));
+        assert!(parse_line_range("40-10").is_err());
+        assert!(parse_line_range("0").is_err());
+        assert_eq!(select_lines("a\nb\nc", (2, 3)).as_deref(), Some("b\nc"));
+        assert_eq!(select_lines("a\nb\nc", (3, 4)), None);
+    }
+}
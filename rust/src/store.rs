@@ -0,0 +1,284 @@
+//! Storage backends.
+//!
+//! `Memories` talks to storage only through `Store`. SQLite (`index.rs`) is
+//! the default; `libsql.rs` keeps the same schema on a libSQL server so a
+//! team can share one store.
+
+use crate::types::{
+    Attachment, Memory, MemoryFilter, MemoryUpdate, NewMemory, PruneCriteria, SessionSummary, SortOrder, TextMatch,
+    Tombstone,
+};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+
+/// Backends selectable with `config store <name>`
+pub const STORE_BACKENDS: &[&str] = &["sqlite", "libsql"];
+
+/// Schema this build reads and writes. Bump it with any change to the
+/// tables, so older builds refuse the store instead of misreading it.
+pub const SCHEMA_VERSION: i64 = 5;
+
+/// Metadata keys stamped by `stamp_schema_version`
+pub const SCHEMA_VERSION_KEY: &str = "schema_version";
+pub const CREATED_BY_KEY: &str = "created_by";
+pub const MIGRATED_BY_KEY: &str = "migrated_by";
+
+#[derive(Debug, thiserror::Error)]
+pub enum StoreError {
+    #[error(transparent)]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("{0}")]
+    Remote(String),
+    #[error("Corrupt database: {0}")]
+    Corrupt(String),
+    #[error("{0} is not supported by this store")]
+    Unsupported(&'static str),
+    #[error(
+        "This store uses schema version {found} (from roots {migrated_by}), but roots {} only supports up to version {}. \
+         Upgrade roots and run 'roots migrate'.",
+        env!("CARGO_PKG_VERSION"),
+        SCHEMA_VERSION
+    )]
+    SchemaTooNew { found: i64, migrated_by: String },
+}
+
+pub type StoreResult<T> = Result<T, StoreError>;
+
+/// A vector with what produced it, so unchanged text is not embedded twice
+pub struct Embedding<'a> {
+    pub vector: &'a [f32],
+    pub model: &'a str,
+    /// `blobs::hash` of the embedded text
+    pub text_hash: String,
+}
+
+impl<'a> Embedding<'a> {
+    pub fn new(vector: &'a [f32], model: &'a str, text: &str) -> Self {
+        Self {
+            vector,
+            model,
+            text_hash: crate::blobs::hash(text),
+        }
+    }
+}
+
+/// What recall needs to score a memory, without its content or tags
+pub struct VectorRow {
+    pub id: i64,
+    pub confidence: f64,
+    pub updated_at: String,
+    pub importance: f64,
+    pub access_count: i64,
+    pub last_accessed_at: Option<String>,
+    pub embedding: Vec<f32>,
+}
+
+impl VectorRow {
+    fn of(memory: &Memory, embedding: Vec<f32>) -> Self {
+        Self {
+            id: memory.id,
+            confidence: memory.confidence,
+            updated_at: memory.updated_at.clone(),
+            importance: memory.importance,
+            access_count: memory.access_count,
+            last_accessed_at: memory.last_accessed_at.clone(),
+            embedding,
+        }
+    }
+}
+
+/// Refuse a store written with a newer schema. Backends call it before
+/// creating or migrating tables, so a newer build's store is left as it was.
+pub(crate) fn refuse_newer_schema(store: &dyn Store) -> StoreResult<()> {
+    let found = store
+        .get_metadata(SCHEMA_VERSION_KEY)?
+        .and_then(|v| v.parse::<i64>().ok());
+    match found {
+        Some(found) if found > SCHEMA_VERSION => Err(StoreError::SchemaTooNew {
+            found,
+            migrated_by: store
+                .get_metadata(MIGRATED_BY_KEY)?
+                .unwrap_or_else(|| "unknown".to_string()),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Stamp an older or new store, already migrated by the backend, with the
+/// current version
+pub(crate) fn stamp_schema_version(store: &dyn Store) -> StoreResult<()> {
+    let found = store.get_metadata(SCHEMA_VERSION_KEY)?;
+    if found == Some(SCHEMA_VERSION.to_string()) {
+        return Ok(());
+    }
+    let version = env!("CARGO_PKG_VERSION");
+    if store.get_metadata(CREATED_BY_KEY)?.is_none() {
+        // Unstamped stores predate stamping, so their creator is unknown
+        let created_by = if store.count()? == 0 { version } else { "unknown" };
+        store.set_metadata(CREATED_BY_KEY, created_by)?;
+    }
+    store.set_metadata(SCHEMA_VERSION_KEY, &SCHEMA_VERSION.to_string())?;
+    store.set_metadata(MIGRATED_BY_KEY, version)
+}
+
+/// Persistence for memories, tags, tombstones, and metadata
+pub trait Store {
+    /// Insert a new memory, returns the ID. Without an embedding the memory
+    /// waits in the embed queue and is left out of vector search.
+    fn insert(&self, memory: &NewMemory, embedding: Option<&Embedding>) -> StoreResult<i64>;
+
+    /// Insert several memories in one transaction, returns their IDs in order
+    fn insert_batch(&self, memories: &[(NewMemory, Option<Embedding>)]) -> StoreResult<Vec<i64>>;
+
+    /// Put a deleted memory back as it was, ID and counters included, and
+    /// drop the tombstone its deletion left
+    fn restore(&self, memory: &Memory, embedding: Option<&Embedding>) -> StoreResult<()>;
+
+    /// Get a memory by ID
+    fn get(&self, id: i64) -> StoreResult<Option<Memory>>;
+
+    /// Get the memories with these IDs, in the same order (missing ones
+    /// are skipped)
+    fn get_many(&self, ids: &[i64]) -> StoreResult<Vec<Memory>> {
+        let mut memories = Vec::new();
+        for id in ids {
+            memories.extend(self.get(*id)?);
+        }
+        Ok(memories)
+    }
+
+    /// Get all memories matching a filter with their embeddings (for vector search)
+    fn get_all_with_embeddings(&self, filter: &MemoryFilter) -> StoreResult<Vec<(Memory, Vec<f32>)>>;
+
+    /// Call `visit` with the scoring fields and embedding of each memory
+    /// matching a filter as rows are read, until it returns false. Content
+    /// and tags are not loaded; fetch the memories worth it with `get_many`.
+    fn scan_vectors(
+        &self,
+        filter: &MemoryFilter,
+        visit: &mut dyn FnMut(VectorRow) -> bool,
+    ) -> StoreResult<()> {
+        for (memory, embedding) in self.get_all_with_embeddings(filter)? {
+            if !visit(VectorRow::of(&memory, embedding)) {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Full-text search over memories matching a filter, best BM25 match
+    /// first. `query` uses FTS5 syntax (phrases, AND/OR/NOT, prefix*).
+    fn search_fts(&self, query: &str, filter: &MemoryFilter, limit: usize) -> StoreResult<Vec<TextMatch>>;
+
+    /// List memories matching a filter in the given order
+    fn list(&self, filter: &MemoryFilter, limit: usize, order: SortOrder) -> StoreResult<Vec<Memory>>;
+
+    /// Update a memory
+    fn update(&self, id: i64, changes: &MemoryUpdate) -> StoreResult<bool>;
+
+    /// Record an access to each of the given memories
+    fn record_access(&self, ids: &[i64]) -> StoreResult<()>;
+
+    /// Store computed importance scores
+    fn set_importance(&self, scores: &[(i64, f64)]) -> StoreResult<()>;
+
+    /// Check whether any memory was imported from the given source
+    fn has_source(&self, source: &str) -> StoreResult<bool>;
+
+    /// Get the memory created at exactly this timestamp (identity across exports)
+    fn get_by_created_at(&self, created_at: &str) -> StoreResult<Option<Memory>>;
+
+    /// Get the memories whose embedded text has this hash (`blobs::hash`)
+    fn get_by_text_hash(&self, text_hash: &str) -> StoreResult<Vec<Memory>>;
+
+    /// Get memories matching every set prune criterion (none set matches nothing)
+    fn get_prunable(&self, criteria: &PruneCriteria) -> StoreResult<Vec<Memory>>;
+
+    /// Delete a memory, leaving a tombstone
+    fn delete(&self, id: i64) -> StoreResult<bool>;
+
+    /// Record a deletion that happened elsewhere
+    fn add_tombstone(&self, tombstone: &Tombstone) -> StoreResult<()>;
+
+    /// Tombstone for the memory created at this timestamp, if it was deleted
+    fn get_tombstone(&self, created_at: &str) -> StoreResult<Option<Tombstone>>;
+
+    /// All tombstones, oldest deletion first
+    fn get_tombstones(&self) -> StoreResult<Vec<Tombstone>>;
+
+    /// Drop tombstones for deletions before `before`, returns how many
+    fn purge_tombstones(&self, before: &str) -> StoreResult<usize>;
+
+    /// Get count of memories
+    fn count(&self) -> StoreResult<usize>;
+
+    /// Count of memories whose full body is the blob with this hash
+    fn count_blob_refs(&self, hash: &str) -> StoreResult<usize>;
+
+    /// Get all unique tags with their memory counts, most used first
+    fn get_all_tags(&self) -> StoreResult<Vec<(String, usize)>>;
+
+    /// Attach a file, or lines of one, to a memory, returns the attachment ID
+    fn add_attachment(&self, attachment: &Attachment) -> StoreResult<i64>;
+
+    /// A memory's attachments, oldest first
+    fn get_attachments(&self, memory_id: i64) -> StoreResult<Vec<Attachment>>;
+
+    /// Sessions with memories, most recently active first
+    fn sessions(&self, limit: usize) -> StoreResult<Vec<SessionSummary>>;
+
+    /// Replace each tag of `from` with `to` on every memory carrying it, in
+    /// one transaction; returns the IDs of the memories changed
+    fn rename_tags(&self, from: &[String], to: &str) -> StoreResult<Vec<i64>>;
+
+    /// Get a metadata value
+    fn get_metadata(&self, key: &str) -> StoreResult<Option<String>>;
+
+    /// Set a metadata value
+    fn set_metadata(&self, key: &str, value: &str) -> StoreResult<()>;
+
+    /// Get all memory IDs and contents for reindexing
+    fn get_all_for_reindex(&self) -> StoreResult<Vec<(i64, String)>>;
+
+    /// IDs and contents of memories still waiting for an embedding, oldest first
+    fn get_pending_embeddings(&self, limit: usize) -> StoreResult<Vec<(i64, String)>>;
+
+    /// Count of memories still waiting for an embedding
+    fn count_pending_embeddings(&self) -> StoreResult<usize>;
+
+    /// IDs and contents of memories with no embedding, one that is not
+    /// `dim` long, or one made by a model other than `model`
+    fn get_stale_embeddings(&self, model: &str, dim: usize) -> StoreResult<Vec<(i64, String)>>;
+
+    /// Update embedding for a memory
+    fn update_embedding(&self, id: i64, embedding: &Embedding) -> StoreResult<()>;
+
+    /// A stored embedding of the text with this hash by `model`, if any
+    fn find_embedding(&self, model: &str, text_hash: &str) -> StoreResult<Option<Vec<f32>>>;
+
+    /// Hash of the embedded text for each memory embedded by `model`
+    fn get_embedding_hashes(&self, model: &str) -> StoreResult<HashMap<i64, String>>;
+
+    /// Write a consistent copy of the whole store to a new file
+    fn snapshot(&self, _dest: &Path) -> StoreResult<()> {
+        Err(StoreError::Unsupported("Snapshotting"))
+    }
+
+    /// Tag lookup count and time since the last call, resetting both
+    fn take_tag_stats(&self) -> (usize, Duration) {
+        (0, Duration::ZERO)
+    }
+}
+
+/// Serialize an embedding as little-endian f32s
+pub fn serialize_embedding(embedding: &[f32]) -> Vec<u8> {
+    embedding.iter().flat_map(|f| f.to_le_bytes()).collect()
+}
+
+/// Inverse of `serialize_embedding`
+pub fn deserialize_embedding(data: &[u8]) -> Vec<f32> {
+    data.chunks_exact(4)
+        .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect()
+}
@@ -0,0 +1,388 @@
+//! Terminal helpers for interactive commands
+
+use std::io::{self, BufRead, IsTerminal, Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+
+/// Whether output is colored, decided once by `init_color`
+static COLOR: AtomicBool = AtomicBool::new(false);
+
+/// Scores and confidences at or above these are green, then yellow, else red
+const HIGH: f64 = 0.7;
+const MEDIUM: f64 = 0.4;
+
+/// Narrowest width wrapped to, however narrow the terminal
+const MIN_WRAP: usize = 20;
+
+/// Whether stdin and stdout are both attached to a terminal
+pub fn is_interactive() -> bool {
+    io::stdin().is_terminal() && io::stdout().is_terminal()
+}
+
+/// Progress bar with ETA on stderr, drawn only when stderr is a terminal
+pub struct Progress {
+    started: Instant,
+    visible: bool,
+}
+
+impl Progress {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            visible: io::stderr().is_terminal(),
+        }
+    }
+
+    pub fn update(&self, done: usize, total: usize) {
+        if !self.visible || total == 0 {
+            return;
+        }
+        const WIDTH: usize = 30;
+        let filled = WIDTH * done / total;
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let eta = match done {
+            0 => "--".to_string(),
+            _ => format_secs(elapsed / done as f64 * (total - done) as f64),
+        };
+        eprint!(
+            "\r[{}{}] {}/{} eta {}   ",
+            "#".repeat(filled),
+            "-".repeat(WIDTH - filled),
+            done,
+            total,
+            eta
+        );
+        let _ = io::stderr().flush();
+    }
+
+    /// Clear the bar so following output starts on a clean line
+    pub fn finish(&self) {
+        if self.visible {
+            eprint!("\r{}\r", " ".repeat(60));
+        }
+    }
+}
+
+fn format_secs(secs: f64) -> String {
+    let secs = secs.round() as u64;
+    if secs >= 60 {
+        format!("{}m{:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+/// Puts the terminal in single-keystroke mode until dropped
+pub struct RawMode {
+    saved: String,
+}
+
+impl RawMode {
+    pub fn enable() -> Result<Self, String> {
+        let saved = stty(&["-g"])?;
+        stty(&["-icanon", "-echo", "min", "1"])?;
+        Ok(Self { saved })
+    }
+
+    /// Like `enable`, but Ctrl-C arrives as a key instead of ending the
+    /// process, for screens that must be restored on the way out
+    pub fn enable_keys() -> Result<Self, String> {
+        let saved = stty(&["-g"])?;
+        stty(&["-icanon", "-echo", "-isig", "min", "1"])?;
+        Ok(Self { saved })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = stty(&[self.saved.as_str()]);
+    }
+}
+
+fn stty(args: &[&str]) -> Result<String, String> {
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .output()
+        .map_err(|e| format!("Failed to run stty: {}", e))?;
+    if !output.status.success() {
+        return Err("Failed to configure terminal".to_string());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Read one keystroke (call with `RawMode` enabled); `None` at end of input
+pub fn read_key() -> Result<Option<char>, String> {
+    let mut byte = [0u8; 1];
+    match io::stdin().read(&mut byte) {
+        Ok(0) => Ok(None),
+        Ok(_) => Ok(Some(byte[0] as char)),
+        Err(e) => Err(format!("Failed to read key: {}", e)),
+    }
+}
+
+/// Read the rest of a key sequence (call with `RawMode` enabled): the next
+/// byte if one arrives within a tenth of a second, so a lone Esc can be
+/// told apart from an arrow key
+pub fn read_pending_key() -> Result<Option<char>, String> {
+    stty(&["min", "0", "time", "1"])?;
+    let key = read_key();
+    stty(&["min", "1", "time", "0"])?;
+    key
+}
+
+/// Terminal size as (rows, columns), if stdin is a terminal that knows it
+pub fn size() -> Option<(usize, usize)> {
+    let size = stty(&["size"]).ok()?;
+    let (rows, cols) = size.split_once(' ')?;
+    let (rows, cols) = (rows.parse().ok()?, cols.parse().ok()?);
+    (rows > 0 && cols > 0).then_some((rows, cols))
+}
+
+/// Print `prompt` and read a line of input, trimmed; `None` at end of input
+pub fn prompt_line(prompt: &str) -> Result<Option<String>, String> {
+    print!("{}", prompt);
+    io::stdout()
+        .flush()
+        .map_err(|e| format!("Failed to write prompt: {}", e))?;
+
+    let mut line = String::new();
+    let read = io::stdin()
+        .lock()
+        .read_line(&mut line)
+        .map_err(|e| format!("Failed to read input: {}", e))?;
+    if read == 0 {
+        return Ok(None);
+    }
+    Ok(Some(line.trim().to_string()))
+}
+
+/// Color output when stdout is a terminal, unless `--no-color` is given or
+/// `NO_COLOR` is set (https://no-color.org)
+pub fn init_color(no_color: bool) {
+    let disabled = no_color || std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    COLOR.store(!disabled && io::stdout().is_terminal(), Ordering::Relaxed);
+}
+
+pub fn color_enabled() -> bool {
+    COLOR.load(Ordering::Relaxed)
+}
+
+fn paint(text: &str, code: &str) -> String {
+    if color_enabled() {
+        format!("\x1b[{}m{}\x1b[0m", code, text)
+    } else {
+        text.to_string()
+    }
+}
+
+pub fn bold(text: &str) -> String {
+    paint(text, "1")
+}
+
+/// For metadata, so content stands out
+pub fn dim(text: &str) -> String {
+    paint(text, "2")
+}
+
+/// `text` in green, yellow, or red by how high `value` (a score or
+/// confidence) is
+pub fn scaled(value: f64, text: &str) -> String {
+    let code = if value >= HIGH {
+        "32"
+    } else if value >= MEDIUM {
+        "33"
+    } else {
+        "31"
+    };
+    paint(text, code)
+}
+
+/// Words of a search query worth highlighting in results: filters
+/// (`tag:x`, `conf>=0.7`) and short words left out
+pub fn query_terms(query: &str) -> Vec<String> {
+    query
+        .split_whitespace()
+        .filter(|w| !w.contains([':', '<', '>', '=']))
+        .map(|w| w.trim_matches(|c: char| !c.is_alphanumeric()).to_ascii_lowercase())
+        .filter(|w| w.len() >= 3)
+        .collect()
+}
+
+/// `line` with each occurrence of `terms` (ignoring case) in bold yellow
+pub fn highlight_terms(line: &str, terms: &[String]) -> String {
+    if !color_enabled() || terms.is_empty() {
+        return line.to_string();
+    }
+    // ASCII lowercasing keeps byte offsets, so matches index `line` too
+    let lower = line.to_ascii_lowercase();
+    let mut out = String::new();
+    let mut at = 0;
+    while at < line.len() {
+        let next = terms
+            .iter()
+            .filter_map(|t| lower[at..].find(t.as_str()).map(|i| (at + i, t.len())))
+            .min_by_key(|&(start, len)| (start, std::cmp::Reverse(len)));
+        let Some((start, len)) = next else {
+            break;
+        };
+        out.push_str(&line[at..start]);
+        out.push_str(&paint(&line[start..start + len], "1;33"));
+        at = start + len;
+    }
+    out.push_str(&line[at..]);
+    out
+}
+
+/// Columns of the terminal stdout is attached to, if it is one
+pub fn output_width() -> Option<usize> {
+    if !io::stdout().is_terminal() {
+        return None;
+    }
+    std::env::var("COLUMNS")
+        .ok()
+        .and_then(|c| c.parse().ok())
+        .filter(|&c| c > 0)
+        .or_else(|| size().map(|(_, cols)| cols))
+}
+
+/// `text` word-wrapped into lines of at most `width` columns, each starting
+/// with `indent`; a single line when there is no width to wrap to
+pub fn wrap(text: &str, indent: &str, width: Option<usize>) -> Vec<String> {
+    let Some(width) = width else {
+        return vec![format!("{}{}", indent, text)];
+    };
+    let room = width.saturating_sub(indent.chars().count()).max(MIN_WRAP);
+
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    let mut used = 0;
+    for word in text.split_whitespace() {
+        let mut word: Vec<char> = word.chars().collect();
+        if used > 0 && used + 1 + word.len() > room {
+            lines.push(std::mem::take(&mut line));
+            used = 0;
+        }
+        // Longer than a whole line: break it
+        while word.len() > room {
+            if used > 0 {
+                lines.push(std::mem::take(&mut line));
+                used = 0;
+            }
+            lines.push(word.drain(..room).collect());
+        }
+        if word.is_empty() {
+            continue;
+        }
+        if used > 0 {
+            line.push(' ');
+            used += 1;
+        }
+        line.extend(word.iter());
+        used += word.len();
+    }
+    if used > 0 || lines.is_empty() {
+        lines.push(line);
+    }
+    lines.into_iter().map(|l| format!("{}{}", indent, l)).collect()
+}
+
+/// A search snippet for display: matched terms in bold when coloring,
+/// otherwise between `**`
+pub fn highlight_snippet(snippet: &str) -> String {
+    use crate::types::{SNIPPET_END, SNIPPET_START};
+    let (start, end) = if color_enabled() {
+        ("\x1b[1m", "\x1b[0m")
+    } else {
+        ("**", "**")
+    };
+    snippet
+        .replace(SNIPPET_START, start)
+        .replace(SNIPPET_END, end)
+        .replace('\n', " ")
+}
+
+/// Open `initial` in $VISUAL / $EDITOR (default vi) and return the saved text, trimmed
+pub fn edit_text(initial: &str) -> Result<String, String> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+
+    let path = create_temp_file("roots-edit", initial).map_err(|e| format!("Failed to write temp file: {}", e))?;
+
+    // Through the shell so editors with arguments ("code --wait") work
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$1\"", editor))
+        .arg("sh")
+        .arg(&path)
+        .status()
+        .map_err(|e| format!("Failed to run editor: {}", e))?;
+
+    let text =
+        std::fs::read_to_string(&path).map_err(|e| format!("Failed to read temp file: {}", e));
+    let _ = std::fs::remove_file(&path);
+
+    if !status.success() {
+        return Err(format!("Editor exited with {}", status));
+    }
+    Ok(text?.trim().to_string())
+}
+
+/// Create a new file holding `text` in the temp directory, readable only
+/// by this user. The name is new, never an existing file or a link someone
+/// else planted there.
+fn create_temp_file(prefix: &str, text: &str) -> std::io::Result<std::path::PathBuf> {
+    use std::io::Write;
+    use std::os::unix::fs::OpenOptionsExt;
+    let mut attempt = 0u32;
+    loop {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.subsec_nanos());
+        let path = std::env::temp_dir().join(format!("{}-{}-{:x}-{}.md", prefix, std::process::id(), nanos, attempt));
+        match std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&path)
+        {
+            Ok(mut file) => {
+                file.write_all(text.as_bytes())?;
+                return Ok(path);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists && attempt < 100 => attempt += 1,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap_breaks_at_words() {
+        let text = "use blue green deployments with automatic rollback";
+        assert_eq!(wrap(text, "  ", None), vec![format!("  {}", text)]);
+        assert_eq!(
+            wrap(text, "  ", Some(24)),
+            vec!["  use blue green", "  deployments with", "  automatic rollback"]
+        );
+        // A word longer than the line is split
+        assert_eq!(wrap(&"x".repeat(25), "", Some(20)), vec!["x".repeat(20), "x".repeat(5)]);
+    }
+
+    #[test]
+    fn test_temp_files_are_new_and_private() {
+        use std::os::unix::fs::PermissionsExt;
+        let a = create_temp_file("roots-test-edit", "draft").unwrap();
+        let b = create_temp_file("roots-test-edit", "draft").unwrap();
+        assert_ne!(a, b);
+        assert_eq!(std::fs::read_to_string(&a).unwrap(), "draft");
+        assert_eq!(std::fs::metadata(&a).unwrap().permissions().mode() & 0o777, 0o600);
+        let _ = std::fs::remove_file(&a);
+        let _ = std::fs::remove_file(&b);
+    }
+}
@@ -0,0 +1,52 @@
+//! Fixtures shared by the unit tests.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::memory::Memories;
+use crate::types::Memory;
+
+/// An empty scratch directory for the named test
+pub fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("roots-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Set up `.roots` in `dir` with the lite embedder, followed by any further
+/// config lines, and return its path
+pub fn project(dir: &Path, config: &str) -> PathBuf {
+    let roots = dir.join(".roots");
+    fs::create_dir_all(&roots).unwrap();
+    fs::write(roots.join("_config.yaml"), format!("embedding_model: lite\n{}", config)).unwrap();
+    roots
+}
+
+/// Open a store with the lite embedder in `dir`, setting it up first
+pub fn store(dir: &Path) -> Memories {
+    Memories::open_at(project(dir, "")).unwrap()
+}
+
+/// A memory as a store would return it, never accessed or scored
+pub fn memory(id: i64, content: &str, tags: &[&str]) -> Memory {
+    Memory {
+        id,
+        content: content.to_string(),
+        confidence: 0.8,
+        tags: tags.iter().map(|t| t.to_string()).collect(),
+        created_at: "2026-01-15T00:00:00Z".to_string(),
+        updated_at: "2026-01-15T00:00:00Z".to_string(),
+        last_accessed_at: None,
+        access_count: 0,
+        importance: 0.0,
+        kind: None,
+        expires_at: None,
+        source: None,
+        session_id: None,
+        namespace: None,
+        pinned: false,
+        blob: None,
+        global: false,
+    }
+}
@@ -0,0 +1,116 @@
+use std::time::Duration;
+
+/// Phase timings for a single recall
+#[derive(Debug, Clone, Default)]
+pub struct RecallTimings {
+    /// Time spent embedding the query
+    pub embed: Duration,
+    /// Whether the query was embedded by the embedding server
+    pub server: bool,
+    /// Time spent reading candidate vectors and loading the top results
+    /// (including tag lookups)
+    pub scan: Duration,
+    /// Time spent in per-memory tag lookups
+    pub tags: Duration,
+    /// Number of tag lookup queries issued
+    pub tag_queries: usize,
+    /// Time spent computing similarities and sorting
+    pub score: Duration,
+    /// Number of memories scored
+    pub candidates: usize,
+}
+
+impl RecallTimings {
+    /// Total time across all phases
+    pub fn total(&self) -> Duration {
+        self.embed + self.scan + self.score
+    }
+
+    /// One-line summary of each phase
+    pub fn summary(&self) -> String {
+        format!(
+            "embed {}ms{}, scan {}ms ({} rows, {} tag queries {}ms), score {}ms",
+            self.embed.as_millis(),
+            if self.server { " (server)" } else { "" },
+            self.scan.as_millis(),
+            self.candidates,
+            self.tag_queries,
+            self.tags.as_millis(),
+            self.score.as_millis()
+        )
+    }
+
+    /// One-line hint naming the slowest phase and how to speed it up
+    pub fn bottleneck_hint(&self) -> String {
+        let rows = self.scan.saturating_sub(self.tags) + self.score;
+        let (phase, remedy) = if self.embed >= rows && self.embed >= self.tags {
+            if self.server {
+                (
+                    format!("embedding server latency ({}ms)", self.embed.as_millis()),
+                    "check `roots server status` or pick a smaller model with `roots server model`",
+                )
+            } else {
+                (
+                    format!("in-process query embedding ({}ms)", self.embed.as_millis()),
+                    "start the embedding server with `roots server start`",
+                )
+            }
+        } else if self.tags >= rows {
+            (
+                format!(
+                    "tag lookups ({} queries, {}ms)",
+                    self.tag_queries,
+                    self.tags.as_millis()
+                ),
+                "narrow the search with --tag or --kind",
+            )
+        } else {
+            (
+                format!(
+                    "brute-force scan of {} memories ({}ms)",
+                    self.candidates,
+                    rows.as_millis()
+                ),
+                "narrow the search with --tag or --kind, or prune old memories",
+            )
+        };
+
+        format!(
+            "Slow recall ({}ms): bottleneck is {}; {}",
+            self.total().as_millis(),
+            phase,
+            remedy
+        )
+    }
+}
+
+/// Whether per-command timing output is enabled (`ROOTS_TIMING=1`)
+pub fn timing_enabled() -> bool {
+    std::env::var("ROOTS_TIMING").is_ok_and(|v| !v.is_empty() && v != "0")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hint_names_slowest_phase() {
+        let timings = RecallTimings {
+            embed: Duration::from_millis(5),
+            scan: Duration::from_millis(400),
+            tags: Duration::from_millis(350),
+            tag_queries: 5000,
+            candidates: 5000,
+            ..Default::default()
+        };
+        assert!(timings.bottleneck_hint().contains("tag lookups"));
+
+        let timings = RecallTimings {
+            embed: Duration::from_millis(300),
+            server: true,
+            scan: Duration::from_millis(10),
+            ..Default::default()
+        };
+        assert!(timings.bottleneck_hint().contains("embedding server latency"));
+    }
+}
@@ -0,0 +1,306 @@
+//! Conversation transcripts as a source of memories.
+//!
+//! Claude Code keeps each session as JSONL: one entry per line, with user
+//! and assistant turns carrying a `message` whose content is a string or a
+//! list of blocks (text, tool calls, tool results). Only the text people and
+//! the agent wrote is kept. Rule-based extraction then picks the paragraphs
+//! and bullets that read like decisions, preferences, or lessons.
+//!
+//! Markdown chat logs are read too: a turn starts at a heading or a line
+//! label (`## User`, `**Assistant:**`, `Human:`) naming its speaker.
+
+use crate::worthiness;
+use serde_json::Value;
+
+/// A turn of the conversation
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    /// "user" or "assistant"
+    pub role: String,
+    pub text: String,
+}
+
+#[derive(Debug, Default)]
+pub struct Transcript {
+    pub session_id: Option<String>,
+    pub messages: Vec<Message>,
+}
+
+/// Shortest paragraph worth extracting
+const MIN_CANDIDATE_CHARS: usize = 40;
+
+/// Longest paragraph extracted; longer ones are rarely a single point
+const MAX_CANDIDATE_CHARS: usize = 800;
+
+/// Speaker names of markdown chat logs, by role
+const USER_NAMES: &[&str] = &["user", "human", "me", "you", "question"];
+const ASSISTANT_NAMES: &[&str] = &[
+    "assistant", "ai", "claude", "chatgpt", "gpt", "gemini", "bot", "model", "answer",
+];
+
+/// Consecutive turns of a conversation, for storing as one memory
+#[derive(Debug, Clone, PartialEq)]
+pub struct TranscriptChunk {
+    pub text: String,
+    /// First and last turn it covers, counting from 1
+    pub first_turn: usize,
+    pub last_turn: usize,
+}
+
+/// Parse a Claude Code JSONL transcript, skipping lines that don't parse
+pub fn parse_jsonl(text: &str) -> Transcript {
+    let mut transcript = Transcript::default();
+    for line in text.lines() {
+        let Ok(entry) = serde_json::from_str::<Value>(line) else {
+            continue;
+        };
+        if transcript.session_id.is_none() {
+            transcript.session_id = entry["sessionId"].as_str().map(str::to_string);
+        }
+        let role = match entry["type"].as_str() {
+            Some(role @ ("user" | "assistant")) => role,
+            _ => continue,
+        };
+        let text = match &entry["message"]["content"] {
+            Value::String(text) => text.clone(),
+            Value::Array(blocks) => blocks
+                .iter()
+                .filter(|b| b["type"] == "text")
+                .filter_map(|b| b["text"].as_str())
+                .collect::<Vec<_>>()
+                .join("\n"),
+            _ => continue,
+        };
+        if !text.trim().is_empty() {
+            transcript.messages.push(Message {
+                role: role.to_string(),
+                text,
+            });
+        }
+    }
+    transcript
+}
+
+/// Parse a markdown chat log. Text before the first speaker label, or a
+/// log without any, is read as a single user turn.
+pub fn parse_markdown(text: &str) -> Transcript {
+    let mut messages: Vec<Message> = Vec::new();
+    let mut role = "user";
+    let mut lines: Vec<&str> = Vec::new();
+    let mut flush = |role: &str, lines: &mut Vec<&str>| {
+        let text = lines.join("\n").trim().to_string();
+        if !text.is_empty() {
+            messages.push(Message {
+                role: role.to_string(),
+                text,
+            });
+        }
+        lines.clear();
+    };
+
+    for line in text.lines() {
+        match speaker(line) {
+            Some((speaker, rest)) => {
+                flush(role, &mut lines);
+                role = speaker;
+                if !rest.is_empty() {
+                    lines.push(rest);
+                }
+            }
+            None => lines.push(line),
+        }
+    }
+    flush(role, &mut lines);
+
+    Transcript {
+        session_id: None,
+        messages,
+    }
+}
+
+/// The role a markdown line opens a turn for, and any text after the label
+fn speaker(line: &str) -> Option<(&'static str, &str)> {
+    let line = line.trim();
+    let (label, rest) = if line.starts_with('#') {
+        (line.trim_start_matches('#').trim_end_matches(':'), "")
+    } else {
+        let (label, rest) = line.split_once(':')?;
+        // "**User:**" leaves "**" on both sides of the colon
+        (label, rest.trim_start_matches('*'))
+    };
+    let name = label.trim().trim_matches('*').trim().to_lowercase();
+    let role = if USER_NAMES.contains(&name.as_str()) {
+        "user"
+    } else if ASSISTANT_NAMES.contains(&name.as_str()) {
+        "assistant"
+    } else {
+        return None;
+    };
+    Some((role, rest.trim()))
+}
+
+/// Read and parse a transcript: Claude Code JSONL if it looks like JSON
+/// lines, a markdown chat log otherwise
+pub fn read(path: &str) -> Result<Transcript, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let jsonl = path.ends_with(".jsonl") || text.trim_start().starts_with('{');
+    Ok(if jsonl { parse_jsonl(&text) } else { parse_markdown(&text) })
+}
+
+/// Group turns into chunks of about `max_tokens` whitespace-separated
+/// tokens, counting each speaker label as one; a turn longer than that is
+/// split across chunks of its own
+pub fn chunk(messages: &[Message], max_tokens: usize) -> Vec<TranscriptChunk> {
+    let mut chunks = Vec::new();
+    let mut current: Option<(Vec<String>, usize, usize, usize)> = None;
+
+    for (i, message) in messages.iter().enumerate() {
+        let turn = i + 1;
+        let words: Vec<&str> = message.text.split_whitespace().collect();
+        for piece in words.chunks(max_tokens.max(1)) {
+            let text = format!("{}: {}", message.role, piece.join(" "));
+            let tokens = piece.len() + 1;
+            match &mut current {
+                Some((texts, _, last, size)) if *size + tokens <= max_tokens => {
+                    texts.push(text);
+                    *last = turn;
+                    *size += tokens;
+                }
+                _ => {
+                    if let Some((texts, first, last, _)) = current.take() {
+                        chunks.push(TranscriptChunk {
+                            text: texts.join("\n\n"),
+                            first_turn: first,
+                            last_turn: last,
+                        });
+                    }
+                    current = Some((vec![text], turn, turn, tokens));
+                }
+            }
+        }
+    }
+    if let Some((texts, first, last, _)) = current {
+        chunks.push(TranscriptChunk {
+            text: texts.join("\n\n"),
+            first_turn: first,
+            last_turn: last,
+        });
+    }
+    chunks
+}
+
+/// The conversation as plain text, for an external extraction command
+pub fn render(messages: &[Message]) -> String {
+    messages
+        .iter()
+        .map(|m| format!("{}: {}\n\n", m.role, m.text.trim()))
+        .collect()
+}
+
+/// Paragraphs and list items of the conversation that read like a decision,
+/// preference, or lesson, outside code blocks and without repeats
+pub fn extract(messages: &[Message]) -> Vec<String> {
+    let mut candidates: Vec<String> = Vec::new();
+    for message in messages {
+        for block in prose_blocks(&message.text) {
+            let chars = block.chars().count();
+            if (MIN_CANDIDATE_CHARS..=MAX_CANDIDATE_CHARS).contains(&chars)
+                && worthiness::has_cues(&block)
+                && !candidates.contains(&block)
+            {
+                candidates.push(block);
+            }
+        }
+    }
+    candidates
+}
+
+/// Paragraphs and list items of `text`, with code blocks dropped and list
+/// markers stripped
+fn prose_blocks(text: &str) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+    let mut in_code = false;
+    let mut flush = |current: &mut Vec<&str>| {
+        if !current.is_empty() {
+            blocks.push(current.join(" "));
+            current.clear();
+        }
+    };
+
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with("```") {
+            in_code = !in_code;
+            flush(&mut current);
+            continue;
+        }
+        if in_code {
+            continue;
+        }
+        let item = line
+            .strip_prefix("- ")
+            .or_else(|| line.strip_prefix("* "))
+            .or_else(|| {
+                let digits = line.find(|c: char| !c.is_ascii_digit())?;
+                line[digits..].strip_prefix(". ").filter(|_| digits > 0)
+            });
+        if line.is_empty() || line.starts_with('#') || item.is_some() {
+            flush(&mut current);
+        }
+        match item {
+            Some(item) => current.push(item.trim()),
+            None if !line.is_empty() && !line.starts_with('#') => current.push(line),
+            None => {}
+        }
+    }
+    flush(&mut current);
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_from_jsonl() {
+        let jsonl = [
+            r#"{"type": "summary", "summary": "Auth work"}"#,
+            r#"{"type": "user", "sessionId": "s1", "message": {"role": "user", "content": "We should never log raw tokens, because they end up in Sentry."}}"#,
+            r#"{"type": "user", "sessionId": "s1", "message": {"content": [{"type": "tool_result", "content": "ok"}]}}"#,
+            r#"{"type": "assistant", "sessionId": "s1", "message": {"content": [{"type": "text", "text": "Done.\n\n- The root cause was a missing index on sessions.user_id\n- Ran the tests\n\n```\n// always do this because reasons and more words\n```"}]}}"#,
+            "not json",
+        ]
+        .join("\n");
+
+        let transcript = parse_jsonl(&jsonl);
+        assert_eq!(transcript.session_id.as_deref(), Some("s1"));
+        assert_eq!(transcript.messages.len(), 2);
+        assert_eq!(
+            extract(&transcript.messages),
+            vec![
+                "We should never log raw tokens, because they end up in Sentry.",
+                "The root cause was a missing index on sessions.user_id",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_markdown_turns_and_chunks() {
+        let log = "# Chat about caching\n\n**User:** Why is the build slow?\n\n## Assistant\n\nThe cache key includes the timestamp.\nDrop it.\n\nHuman: thanks";
+        let transcript = parse_markdown(log);
+        let roles: Vec<&str> = transcript.messages.iter().map(|m| m.role.as_str()).collect();
+        assert_eq!(roles, vec!["user", "user", "assistant", "user"]);
+        assert_eq!(transcript.messages[1].text, "Why is the build slow?");
+        assert_eq!(transcript.messages[2].text, "The cache key includes the timestamp.\nDrop it.");
+
+        let chunks = chunk(&transcript.messages[1..], 16);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(
+            chunks[0].text,
+            "user: Why is the build slow?\n\nassistant: The cache key includes the timestamp. Drop it."
+        );
+        assert_eq!((chunks[0].first_turn, chunks[0].last_turn), (1, 2));
+        assert_eq!((chunks[1].first_turn, chunks[1].last_turn), (3, 3));
+    }
+}
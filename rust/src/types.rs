@@ -1,7 +1,5 @@
-use serde::{Deserialize, Serialize};
-
 /// A memory entry
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone)]
 pub struct Memory {
     pub id: i64,
     pub content: String,
@@ -11,6 +9,203 @@ pub struct Memory {
     pub updated_at: String,
     pub last_accessed_at: Option<String>,
     pub access_count: i64,
+    /// Graph centrality score (0-1), see `Memories::compute_importance`
+    pub importance: f64,
+    /// Memory kind (see `MEMORY_KINDS`), if typed
+    pub kind: Option<String>,
+    /// When the memory stops being recalled, if ever
+    pub expires_at: Option<String>,
+    /// Where the memory came from (URL, file, command)
+    pub source: Option<String>,
+    /// Agent session the memory was learned in
+    pub session_id: Option<String>,
+    /// Partition the memory belongs to (`None` is the default namespace)
+    pub namespace: Option<String>,
+    /// Always included in context, regardless of `context_max_age`
+    pub pinned: bool,
+    /// Hash of the full body in the blob store, when `content` is a summary
+    pub blob: Option<String>,
+    /// Came from the user-global store rather than the project
+    pub global: bool,
+}
+
+/// Namespace holding automated writes that failed the write policy
+pub const QUARANTINE_NAMESPACE: &str = "quarantine";
+
+/// Where a write originated, for write policies
+pub const WRITE_ORIGINS: &[&str] = &["cli", "hook", "mcp"];
+
+/// Recognized memory kinds, in the order `prime` presents them
+pub const MEMORY_KINDS: &[&str] = &["decision", "preference", "fact", "todo", DOC_KIND];
+
+/// Kind of chunks stored by `ingest docs`; recalled like any memory, but
+/// left out of `prime`
+pub const DOC_KIND: &str = "doc";
+
+/// Fields for a memory being created
+#[derive(Debug, Clone, Default)]
+pub struct NewMemory {
+    pub content: String,
+    pub confidence: f64,
+    pub tags: Vec<String>,
+    pub kind: Option<String>,
+    pub expires_at: Option<String>,
+    pub source: Option<String>,
+    pub session_id: Option<String>,
+    pub namespace: Option<String>,
+    /// Original creation time when importing (defaults to now)
+    pub created_at: Option<String>,
+    pub pinned: bool,
+    /// Hash of the full body in the blob store, when `content` is a summary
+    pub blob: Option<String>,
+}
+
+/// Changes to apply to an existing memory (`None` leaves a field as is)
+#[derive(Debug, Clone, Default)]
+pub struct MemoryUpdate {
+    /// Replaces the content (and its embedding)
+    pub content: Option<String>,
+    pub confidence: Option<f64>,
+    /// Replaces all tags
+    pub tags: Option<Vec<String>>,
+    /// Empty string clears the kind
+    pub kind: Option<String>,
+    /// Empty string moves the memory to the default namespace
+    pub namespace: Option<String>,
+    pub pinned: Option<bool>,
+    /// Empty string marks the content as complete (no blob)
+    pub blob: Option<String>,
+}
+
+/// Filters applied when listing or searching memories
+#[derive(Debug, Clone, Default)]
+pub struct MemoryFilter {
+    pub tag: Option<String>,
+    /// Match `tag` against the start of each tag instead of the whole tag
+    pub tag_prefix: bool,
+    pub kind: Option<String>,
+    /// Only memories from this agent session
+    pub session: Option<String>,
+    /// Only this namespace (default: every namespace except quarantine)
+    pub namespace: Option<String>,
+    /// Include memories past their expiry (excluded by default)
+    pub include_expired: bool,
+    /// Include quarantined memories when no namespace is given
+    pub include_quarantine: bool,
+    /// Only memories updated or accessed since this timestamp, or pinned
+    pub active_since: Option<String>,
+    /// Only memories created at or after this timestamp
+    pub created_after: Option<String>,
+    /// Only memories created before this timestamp
+    pub created_before: Option<String>,
+    /// Only memories created or updated at or after this timestamp
+    pub since: Option<String>,
+    /// Only memories created or updated before this timestamp
+    pub until: Option<String>,
+    /// Leave out memories with any of these tags
+    pub exclude_tags: Vec<String>,
+    /// Leave out memories containing any of these (case-insensitive)
+    pub exclude_text: Vec<String>,
+    /// Conditions on confidence, all of which must hold
+    pub confidence: Vec<(Comparison, f64)>,
+}
+
+/// A file, or lines of one, attached to a memory
+#[derive(Debug, Clone)]
+pub struct Attachment {
+    pub id: i64,
+    pub memory_id: i64,
+    /// Relative to the project root when inside it
+    pub path: String,
+    /// First and last line, inclusive
+    pub lines: Option<(u32, u32)>,
+    /// The lines as they were when attached
+    pub snippet: Option<String>,
+    pub created_at: String,
+}
+
+/// Memories learned in one agent session
+#[derive(Debug, Clone)]
+pub struct SessionSummary {
+    pub id: String,
+    pub count: usize,
+    /// Creation time of the session's first memory
+    pub first: String,
+    /// Creation time of its latest
+    pub last: String,
+}
+
+/// How a tag filter matches the tags in a store
+#[derive(Debug, Clone, PartialEq)]
+pub enum TagMatch {
+    /// Some memory has the tag itself
+    Exact,
+    /// No memory has the tag, but these tags start with it
+    Prefix(Vec<String>),
+    /// Nothing matches; the closest existing tag, if one is close enough
+    Missing(Option<String>),
+}
+
+/// Comparison in a filter condition
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Lt,
+    Le,
+    Eq,
+    Ge,
+    Gt,
+}
+
+impl Comparison {
+    /// Parse `<`, `<=`, `=`, `>=`, or `>`
+    pub fn parse(op: &str) -> Option<Self> {
+        match op {
+            "<" => Some(Self::Lt),
+            "<=" => Some(Self::Le),
+            "=" => Some(Self::Eq),
+            ">=" => Some(Self::Ge),
+            ">" => Some(Self::Gt),
+            _ => None,
+        }
+    }
+
+    pub fn sql(&self) -> &'static str {
+        match self {
+            Self::Lt => "<",
+            Self::Le => "<=",
+            Self::Eq => "=",
+            Self::Ge => ">=",
+            Self::Gt => ">",
+        }
+    }
+}
+
+impl MemoryFilter {
+    /// A filter matching every stored memory, for exports and dumps
+    pub fn everything() -> Self {
+        Self {
+            include_expired: true,
+            include_quarantine: true,
+            ..Default::default()
+        }
+    }
+}
+
+/// Staleness criteria for `roots prune`; a memory must match all that are set
+#[derive(Debug, Clone, Default)]
+pub struct PruneCriteria {
+    /// Past its expiry
+    pub expired: bool,
+    /// Confidence strictly below this value
+    pub below_confidence: Option<f64>,
+    /// Not accessed (or, if never accessed, created) since this timestamp
+    pub unaccessed_before: Option<String>,
+}
+
+impl PruneCriteria {
+    pub fn is_empty(&self) -> bool {
+        !self.expired && self.below_confidence.is_none() && self.unaccessed_before.is_none()
+    }
 }
 
 /// Search result with similarity score
@@ -18,6 +213,46 @@ pub struct Memory {
 pub struct SearchResult {
     pub memory: Memory,
     pub score: f64,
+    /// What the score was blended from, for semantic recall
+    pub parts: Option<ScoreParts>,
+}
+
+/// Unweighted signals of a recall score, each 0-1 except similarity (-1-1)
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ScoreParts {
+    /// Cosine similarity to the query
+    pub similarity: f64,
+    pub confidence: f64,
+    /// Recency of the last update, halving every `ranking.recency_half_life`
+    pub recency: f64,
+    /// Access count and recency of the last recall
+    pub access: f64,
+    pub importance: f64,
+}
+
+/// Marks the start of a matched term in a `TextMatch` snippet
+pub const SNIPPET_START: char = '\u{2}';
+
+/// Marks the end of a matched term in a `TextMatch` snippet
+pub const SNIPPET_END: char = '\u{3}';
+
+/// Full-text search result
+#[derive(Debug, Clone)]
+pub struct TextMatch {
+    pub memory: Memory,
+    /// BM25 relevance, higher is better
+    pub score: f64,
+    /// Passage around the matches, each matched term between
+    /// `SNIPPET_START` and `SNIPPET_END`
+    pub snippet: String,
+}
+
+/// Record of a forgotten memory, so imports delete it instead of resurrecting it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tombstone {
+    /// Creation time of the deleted memory (its identity across stores)
+    pub created_at: String,
+    pub deleted_at: String,
 }
 
 /// Statistics about the memory store
@@ -27,4 +262,37 @@ pub struct MemoryStats {
     pub total_tags: usize,
     pub by_tag: std::collections::HashMap<String, usize>,
     pub avg_confidence: f64,
+    pub avg_content_chars: f64,
+    /// Forgotten memories whose tombstones are still kept
+    pub forgotten: usize,
+    pub weekly: Vec<crate::aggregate::Activity>,
+    pub monthly: Vec<crate::aggregate::Activity>,
+    /// Database files on disk (sqlite backend only)
+    pub db_bytes: Option<u64>,
+}
+
+/// Ordering for memory listings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortOrder {
+    /// Most recently updated first
+    #[default]
+    Recent,
+    /// Most central in the memory graph first
+    Importance,
+    /// Least recently created first
+    Oldest,
+    /// Lowest confidence first, oldest first within a level
+    LowConfidence,
+}
+
+impl SortOrder {
+    /// SQL `ORDER BY` expression over the `m` alias
+    pub fn sql(self) -> &'static str {
+        match self {
+            SortOrder::Recent => "m.updated_at DESC",
+            SortOrder::Importance => "m.importance DESC, m.updated_at DESC",
+            SortOrder::Oldest => "m.created_at ASC",
+            SortOrder::LowConfidence => "m.confidence ASC, m.created_at ASC",
+        }
+    }
 }
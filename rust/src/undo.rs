@@ -0,0 +1,284 @@
+//! Journal of recent writes, so `roots undo` can take the latest one back.
+//!
+//! Each remember, forget, and update appends what reversing it takes: the
+//! IDs it created, or the memories as they were before, with their
+//! embeddings and (when they lived in the blob store) their full bodies.
+//! An ingest is one step covering every chunk it stored or forgot, along
+//! with the file hashes `ingest docs --update` compares against.
+//! Steps are appended to `.roots/undo.jsonl` under a file lock. Once the
+//! journal passes `COMPACT_BYTES` it is cut back to at most `UNDO_DEPTH`
+//! steps and half that size (by a rename, so a reader never sees half of
+//! it). A line that can't be read, say from a crash mid-append, is skipped.
+
+use crate::ingest::IngestManifest;
+use crate::lock::FileLock;
+use crate::proto;
+use crate::types::Memory;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const UNDO_FILE: &str = "undo.jsonl";
+
+/// Held while the journal is appended to or rewritten
+const UNDO_LOCK: &str = "undo.lock";
+
+/// Steps kept; older ones can no longer be undone
+const UNDO_DEPTH: usize = 50;
+
+/// Journal size past which older steps are dropped
+const COMPACT_BYTES: u64 = 4 * 1024 * 1024;
+
+/// One write, with what it takes to reverse it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UndoStep {
+    /// "remember", "forget", "update", or "ingest"
+    pub op: String,
+    /// When the write happened (RFC 3339)
+    pub at: String,
+    /// Memories the write created
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub created: Vec<i64>,
+    /// Memories the write changed or deleted, as they were before it
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub before: Vec<Saved>,
+    /// Hashes of ingested files before the write, when it changed them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ingested_files: Option<IngestManifest>,
+}
+
+impl UndoStep {
+    /// Whether the step did anything to reverse
+    pub fn is_empty(&self) -> bool {
+        self.created.is_empty() && self.before.is_empty() && self.ingested_files.is_none()
+    }
+}
+
+/// A memory as it was before a write
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Saved {
+    pub memory: proto::Memory,
+    /// Hash of the full body in the blob store, when `content` is a summary
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blob: Option<String>,
+    /// The full body itself, since the blob is released with the memory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    /// Embedding of `content` by the store's model, if it had one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding: Option<Vec<f32>>,
+}
+
+impl Saved {
+    /// The memory, as the store holds it
+    pub fn to_memory(&self) -> Memory {
+        let m = &self.memory;
+        Memory {
+            id: m.id,
+            content: m.content.clone(),
+            confidence: m.confidence,
+            tags: m.tags.clone(),
+            created_at: m.created_at.clone(),
+            updated_at: m.updated_at.clone(),
+            last_accessed_at: m.last_accessed_at.clone(),
+            access_count: m.access_count,
+            importance: m.importance,
+            kind: m.kind.clone(),
+            expires_at: m.expires_at.clone(),
+            source: m.source.clone(),
+            session_id: m.session_id.clone(),
+            namespace: m.namespace.clone(),
+            pinned: m.pinned,
+            blob: self.blob.clone(),
+            global: false,
+        }
+    }
+}
+
+/// The journal under `.roots`
+pub struct UndoLog {
+    path: PathBuf,
+    lock: PathBuf,
+}
+
+impl UndoLog {
+    pub fn new(roots_path: &Path) -> Self {
+        Self {
+            path: roots_path.join(UNDO_FILE),
+            lock: roots_path.join(UNDO_LOCK),
+        }
+    }
+
+    /// Record a step, dropping old ones once the journal grows past
+    /// `COMPACT_BYTES`
+    pub fn push(&self, step: &UndoStep) -> Result<(), String> {
+        let mut line = serde_json::to_string(step).map_err(|e| format!("Failed to serialize undo step: {}", e))?;
+        line.push('\n');
+
+        let _lock = self.lock()?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| format!("Failed to open undo journal: {}", e))?;
+        file.write_all(line.as_bytes())
+            .map_err(|e| format!("Failed to write undo journal: {}", e))?;
+
+        if file.metadata().is_ok_and(|m| m.len() > COMPACT_BYTES) {
+            let steps = self.read()?;
+            let lines: Vec<String> = steps.iter().filter_map(|s| serde_json::to_string(s).ok()).collect();
+            let mut keep = 0;
+            let mut size = 0;
+            for line in lines.iter().rev().take(UNDO_DEPTH) {
+                size += line.len() + 1;
+                if keep > 0 && size as u64 > COMPACT_BYTES / 2 {
+                    break;
+                }
+                keep += 1;
+            }
+            self.write(&steps[steps.len() - keep..])?;
+        }
+        Ok(())
+    }
+
+    /// The latest step, if any
+    pub fn last(&self) -> Result<Option<UndoStep>, String> {
+        let _lock = self.lock()?;
+        Ok(self.read()?.pop())
+    }
+
+    /// Drop the latest step once it has been undone
+    pub fn pop(&self) -> Result<(), String> {
+        let _lock = self.lock()?;
+        let mut steps = self.read()?;
+        steps.pop();
+        self.write(&steps)
+    }
+
+    fn lock(&self) -> Result<FileLock, String> {
+        FileLock::acquire(&self.lock).map_err(|e| format!("Failed to lock undo journal: {}", e))
+    }
+
+    /// Every step that can be read, oldest first
+    fn read(&self) -> Result<Vec<UndoStep>, String> {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(format!("Failed to read undo journal: {}", e)),
+        };
+        Ok(content
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+
+    /// Replace the journal with `steps`, all at once
+    fn write(&self, steps: &[UndoStep]) -> Result<(), String> {
+        let mut content = String::new();
+        for step in steps {
+            let line =
+                serde_json::to_string(step).map_err(|e| format!("Failed to serialize undo step: {}", e))?;
+            content.push_str(&line);
+            content.push('\n');
+        }
+        let temp = self.path.with_extension("jsonl.tmp");
+        fs::write(&temp, content)
+            .and_then(|_| fs::rename(&temp, &self.path))
+            .map_err(|e| format!("Failed to write undo journal: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::Memories;
+    use crate::test_support::{self, scratch};
+    use crate::types::NewMemory;
+
+    fn step(op: &str, created: Vec<i64>) -> UndoStep {
+        UndoStep {
+            op: op.to_string(),
+            at: crate::dates::now_rfc3339(),
+            created,
+            before: Vec::new(),
+            ingested_files: None,
+        }
+    }
+
+    #[test]
+    fn test_unreadable_lines_are_skipped() {
+        let dir = scratch("undo-corrupt");
+        let log = UndoLog::new(&dir);
+        log.push(&step("remember", vec![1])).unwrap();
+        // What a crash partway through an append leaves behind
+        let mut file = OpenOptions::new().append(true).open(dir.join(UNDO_FILE)).unwrap();
+        file.write_all(br#"{"op": "forget", "at": "2026-"#).unwrap();
+        file.write_all(b"\n").unwrap();
+        log.push(&step("remember", vec![2])).unwrap();
+
+        assert_eq!(log.last().unwrap().unwrap().created, vec![2]);
+        log.pop().unwrap();
+        assert_eq!(log.last().unwrap().unwrap().created, vec![1]);
+        log.pop().unwrap();
+        assert!(log.last().unwrap().is_none());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_journal_is_cut_back_once_large() {
+        let dir = scratch("undo-compact");
+        let log = UndoLog::new(&dir);
+        // About 90 KB a step, so the journal passes the limit after ~45
+        let ids: Vec<i64> = (1_000_000..1_012_000).collect();
+        for n in 0..UNDO_DEPTH + 10 {
+            let mut created = ids.clone();
+            created.push(n as i64);
+            log.push(&step("ingest", created)).unwrap();
+        }
+
+        let steps = log.read().unwrap();
+        assert!(steps.len() < UNDO_DEPTH);
+        assert!(fs::metadata(dir.join(UNDO_FILE)).unwrap().len() <= COMPACT_BYTES);
+        assert_eq!(steps.last().unwrap().created.last(), Some(&(UNDO_DEPTH as i64 + 9)));
+        assert!(!dir.join("undo.jsonl.tmp").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_concurrent_pushes_all_land() {
+        let dir = scratch("undo-concurrent");
+        std::thread::scope(|scope| {
+            for t in 0..8 {
+                let dir = &dir;
+                scope.spawn(move || {
+                    let log = UndoLog::new(dir);
+                    for n in 0..5 {
+                        log.push(&step("remember", vec![t * 10 + n])).unwrap();
+                    }
+                });
+            }
+        });
+        let mut ids: Vec<i64> = UndoLog::new(&dir).read().unwrap().iter().map(|s| s.created[0]).collect();
+        ids.sort();
+        assert_eq!(ids, (0..8).flat_map(|t| (0..5).map(move |n| t * 10 + n)).collect::<Vec<_>>());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_write_succeeds_when_the_journal_cannot_be_written() {
+        let dir = scratch("undo-unwritable");
+        let roots = test_support::project(&dir, "");
+        fs::create_dir_all(roots.join(UNDO_FILE)).unwrap();
+
+        let mem = Memories::open_at(roots).unwrap();
+        let memory = NewMemory {
+            content: "Stored even without undo".to_string(),
+            confidence: 0.8,
+            ..Default::default()
+        };
+        let id = mem.remember(&memory).unwrap();
+        assert!(mem.get(id).unwrap().is_some());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,307 @@
+//! Framing for the embedding server socket.
+//!
+//! Every message is a 4-byte big-endian length followed by that many bytes
+//! of JSON. A connection opens with `{"cmd": "hello", "version": N}`; the
+//! server answers with the version it will speak (at most N), and the
+//! connection then carries any number of requests, each answered in turn,
+//! until either side closes it.
+//!
+//! Servers from before framing read one bare JSON request, answer it, and
+//! close. They answer a framed hello with a bare JSON error, which starts
+//! with `{` where a length would start with a zero byte, or wait for the
+//! end of the request and don't answer at all. A framed server answers the
+//! hello straight away, without touching a model, so `open` takes a bare
+//! JSON answer as a server from before framing, and silence as most likely
+//! one; the client falls back to `legacy_request` either way, but only
+//! stays with it for the former, since a framed server may just be busy.
+
+use serde::{Deserialize, Serialize};
+use std::io::{self, ErrorKind, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::time::Duration;
+
+/// Version of the message format this client speaks
+pub const VERSION: u32 = 1;
+
+/// Largest message read: a batch of 32 4096-dimension vectors is about 3MB
+/// of JSON
+const MAX_FRAME: usize = 64 << 20;
+
+/// How long a request may wait for its answer
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How long a framed server takes, at most, to answer the hello
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Why an exchange with the server failed
+#[derive(Debug)]
+pub struct Failure {
+    pub message: String,
+    /// The server was unreachable or dropped the connection, as while it
+    /// restarts, so trying again may get through
+    pub transient: bool,
+}
+
+impl Failure {
+    fn io(action: &str, e: io::Error) -> Self {
+        let transient = matches!(
+            e.kind(),
+            ErrorKind::NotFound
+                | ErrorKind::ConnectionRefused
+                | ErrorKind::ConnectionReset
+                | ErrorKind::ConnectionAborted
+                | ErrorKind::BrokenPipe
+                | ErrorKind::UnexpectedEof
+        );
+        Self {
+            message: format!("Failed to {}: {}", action, e),
+            transient,
+        }
+    }
+
+    fn fatal(message: String) -> Self {
+        Self { message, transient: false }
+    }
+}
+
+impl From<Failure> for String {
+    fn from(failure: Failure) -> Self {
+        failure.message
+    }
+}
+
+#[derive(Deserialize)]
+struct HelloResponse {
+    ok: bool,
+    version: Option<u32>,
+    error: Option<String>,
+}
+
+/// A server connection, opened by `open`
+pub enum Opened {
+    Framed(Connection),
+    /// A server from before framing, which only takes `legacy_request`
+    Legacy,
+    /// No answer to the hello in time: a server from before framing, or a
+    /// framed one too busy to answer
+    Unanswered,
+}
+
+/// A framed connection that has completed the handshake
+pub struct Connection {
+    stream: UnixStream,
+}
+
+impl Connection {
+    pub fn open(socket: &Path) -> Result<Opened, Failure> {
+        let mut stream = connect(socket)?;
+        let hello = serde_json::json!({"cmd": "hello", "version": VERSION});
+        write_frame(&mut stream, hello.to_string().as_bytes())?;
+
+        let mut header = [0u8; 4];
+        stream
+            .set_read_timeout(Some(HANDSHAKE_TIMEOUT))
+            .map_err(|e| Failure::io("set timeout", e))?;
+        match stream.read_exact(&mut header[..1]) {
+            Ok(()) if header[0] == b'{' => return Ok(Opened::Legacy),
+            Ok(()) => {}
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                return Ok(Opened::Unanswered)
+            }
+            Err(e) => return Err(Failure::io("read response", e)),
+        }
+        stream
+            .read_exact(&mut header[1..])
+            .map_err(|e| Failure::io("read response", e))?;
+        stream
+            .set_read_timeout(Some(TIMEOUT))
+            .map_err(|e| Failure::io("set timeout", e))?;
+        let payload = read_payload(&mut stream, u32::from_be_bytes(header) as usize)?;
+
+        let response: HelloResponse = serde_json::from_slice(&payload)
+            .map_err(|e| Failure::fatal(format!("Failed to parse response: {}", e)))?;
+        if !response.ok {
+            let error = response.error.unwrap_or_else(|| "Handshake refused".to_string());
+            return Err(Failure::fatal(error));
+        }
+        match response.version {
+            Some(version) if (1..=VERSION).contains(&version) => Ok(Opened::Framed(Self { stream })),
+            version => Err(Failure::fatal(format!(
+                "Server speaks protocol version {}, this roots speaks {}; restart the server",
+                version.unwrap_or(0),
+                VERSION
+            ))),
+        }
+    }
+
+    /// Send `request` and read the raw answer; an error leaves the
+    /// connection unusable
+    pub fn send<R: Serialize>(&mut self, request: &R) -> Result<Vec<u8>, Failure> {
+        let json = serialize(request)?;
+        write_frame(&mut self.stream, &json)?;
+        read_frame(&mut self.stream)
+    }
+}
+
+/// One request to a server from before framing: write the JSON, shut down
+/// the write side, and read the answer to EOF
+pub fn legacy_request<R: Serialize>(socket: &Path, request: &R) -> Result<Vec<u8>, Failure> {
+    let mut stream = connect(socket)?;
+    let json = serialize(request)?;
+    stream.write_all(&json).map_err(|e| Failure::io("send", e))?;
+    stream
+        .shutdown(std::net::Shutdown::Write)
+        .map_err(|e| Failure::io("shutdown write", e))?;
+
+    let mut buffer = Vec::new();
+    stream
+        .take(MAX_FRAME as u64)
+        .read_to_end(&mut buffer)
+        .map_err(|e| Failure::io("read response", e))?;
+    Ok(buffer)
+}
+
+fn connect(socket: &Path) -> Result<UnixStream, Failure> {
+    let stream = UnixStream::connect(socket).map_err(|e| Failure::io("connect to server", e))?;
+    stream
+        .set_read_timeout(Some(TIMEOUT))
+        .map_err(|e| Failure::io("set timeout", e))?;
+    Ok(stream)
+}
+
+fn serialize<R: Serialize>(request: &R) -> Result<Vec<u8>, Failure> {
+    serde_json::to_vec(request).map_err(|e| Failure::fatal(format!("Failed to serialize: {}", e)))
+}
+
+fn write_frame(stream: &mut impl Write, payload: &[u8]) -> Result<(), Failure> {
+    if payload.len() > MAX_FRAME {
+        return Err(Failure::fatal(format!("Request too large ({} bytes)", payload.len())));
+    }
+    stream
+        .write_all(&(payload.len() as u32).to_be_bytes())
+        .and_then(|_| stream.write_all(payload))
+        .map_err(|e| Failure::io("send", e))
+}
+
+fn read_frame(stream: &mut impl Read) -> Result<Vec<u8>, Failure> {
+    let mut header = [0u8; 4];
+    stream
+        .read_exact(&mut header)
+        .map_err(|e| Failure::io("read response", e))?;
+    read_payload(stream, u32::from_be_bytes(header) as usize)
+}
+
+fn read_payload(stream: &mut impl Read, len: usize) -> Result<Vec<u8>, Failure> {
+    if len > MAX_FRAME {
+        return Err(Failure::fatal(format!("Response too large ({} bytes)", len)));
+    }
+    let mut payload = vec![0u8; len];
+    stream
+        .read_exact(&mut payload)
+        .map_err(|e| Failure::io("read response", e))?;
+    Ok(payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixListener;
+    use std::path::PathBuf;
+
+    /// A temp socket whose first connection is handed to `handle`
+    fn listen(name: &str, handle: impl FnOnce(UnixStream) + Send + 'static) -> PathBuf {
+        let socket = std::env::temp_dir().join(format!("roots-wire-{}-{}.sock", name, std::process::id()));
+        let _ = std::fs::remove_file(&socket);
+        let listener = UnixListener::bind(&socket).unwrap();
+        std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            handle(stream);
+        });
+        socket
+    }
+
+    #[test]
+    fn test_hello_opens_a_framed_connection() {
+        let socket = listen("hello", |mut stream| {
+            let hello: serde_json::Value = serde_json::from_slice(&read_frame(&mut stream).unwrap()).unwrap();
+            assert_eq!(hello["cmd"], "hello");
+            assert_eq!(hello["version"], VERSION);
+            write_frame(&mut stream, br#"{"ok": true, "version": 1}"#).unwrap();
+            // Requests then share the connection
+            for _ in 0..2 {
+                let request = read_frame(&mut stream).unwrap();
+                write_frame(&mut stream, &request).unwrap();
+            }
+        });
+
+        let Opened::Framed(mut conn) = Connection::open(&socket).unwrap() else {
+            panic!("expected a framed connection");
+        };
+        assert_eq!(conn.send(&"one").unwrap(), br#""one""#);
+        assert_eq!(conn.send(&"two").unwrap(), br#""two""#);
+        std::fs::remove_file(&socket).unwrap();
+    }
+
+    #[test]
+    fn test_bare_json_answer_is_a_legacy_server() {
+        let socket = listen("legacy", |mut stream| {
+            let mut header = [0u8; 4];
+            stream.read_exact(&mut header).unwrap();
+            stream.write_all(br#"{"ok": false, "error": "Invalid JSON"}"#).unwrap();
+        });
+        assert!(matches!(Connection::open(&socket).unwrap(), Opened::Legacy));
+        std::fs::remove_file(&socket).unwrap();
+    }
+
+    #[test]
+    fn test_silent_server_is_unanswered() {
+        let socket = listen("silent", |stream| {
+            std::thread::sleep(HANDSHAKE_TIMEOUT + Duration::from_millis(500));
+            drop(stream);
+        });
+        assert!(matches!(Connection::open(&socket).unwrap(), Opened::Unanswered));
+        std::fs::remove_file(&socket).unwrap();
+    }
+
+    #[test]
+    fn test_refused_hello_and_newer_version_fail() {
+        let refused = listen("refused", |mut stream| {
+            read_frame(&mut stream).unwrap();
+            write_frame(&mut stream, br#"{"ok": false, "error": "busy"}"#).unwrap();
+        });
+        let failure = Connection::open(&refused).err().unwrap();
+        assert_eq!(failure.message, "busy");
+        assert!(!failure.transient);
+
+        let newer = listen("newer", |mut stream| {
+            read_frame(&mut stream).unwrap();
+            write_frame(&mut stream, br#"{"ok": true, "version": 99}"#).unwrap();
+        });
+        let failure = Connection::open(&newer).err().unwrap();
+        assert!(failure.message.contains("version 99"));
+
+        std::fs::remove_file(&refused).unwrap();
+        std::fs::remove_file(&newer).unwrap();
+    }
+
+    #[test]
+    fn test_frames_round_trip_and_reject_oversize() {
+        let mut buffer = Vec::new();
+        write_frame(&mut buffer, br#"{"cmd":"ping"}"#).unwrap();
+        write_frame(&mut buffer, b"{}").unwrap();
+        assert_eq!(buffer[..4], [0, 0, 0, 14]);
+
+        let mut reader = buffer.as_slice();
+        assert_eq!(read_frame(&mut reader).unwrap(), br#"{"cmd":"ping"}"#);
+        assert_eq!(read_frame(&mut reader).unwrap(), b"{}");
+        // The server hanging up between messages is worth a retry
+        assert!(read_frame(&mut reader).unwrap_err().transient);
+
+        let oversize = ((MAX_FRAME + 1) as u32).to_be_bytes();
+        let failure = read_frame(&mut oversize.as_slice()).unwrap_err();
+        assert!(failure.message.contains("too large") && !failure.transient);
+        assert!(Failure::io("connect to server", ErrorKind::NotFound.into()).transient);
+        assert!(!Failure::io("read response", ErrorKind::TimedOut.into()).transient);
+    }
+}
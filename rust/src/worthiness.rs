@@ -0,0 +1,251 @@
+//! Heuristic filter for automatically captured memories.
+//!
+//! Hooks that capture on their own (rather than an agent or user deciding to
+//! `remember`) see a lot of noise: acknowledgements, restated context, things
+//! already stored. A candidate is scored on length, novelty against the
+//! closest existing memory, and cues that it records a decision or
+//! preference. Cues weigh most: a long, novel candidate without one (most
+//! code edits and status replies) stays under the default threshold.
+//! Candidates below `capture_threshold` are dropped and written to
+//! `.roots/rejected.jsonl`, which keeps the latest few hundred KB, so the
+//! threshold can be tuned.
+
+use regex::Regex;
+use serde::Serialize;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Confidence of auto-captured memories when none is given; lower than
+/// `remember`'s 0.5 since nobody vouched for them
+pub const AUTO_CONFIDENCE: f64 = 0.3;
+
+/// Rejected candidates, one JSON object per line
+pub const REJECTED_LOG: &str = "rejected.jsonl";
+
+/// Candidates shorter than this carry too little to be worth keeping
+const MIN_CHARS: usize = 20;
+
+/// Candidates up to this long are scored as fully sized; longer ones fade
+const IDEAL_MAX_CHARS: usize = 600;
+
+/// Similarity at which a candidate restates an existing memory and scores 0
+const DUPLICATE_SIMILARITY: f64 = 0.95;
+
+/// Weights of the three signals, summing to 1; length and novelty alone
+/// come to 0.45, under the default threshold of 0.5
+const LENGTH_WEIGHT: f64 = 0.15;
+const NOVELTY_WEIGHT: f64 = 0.3;
+const CUE_WEIGHT: f64 = 0.55;
+
+/// Size at which the rejection log is cut back to its newer half
+const REJECTED_MAX_BYTES: u64 = 512 * 1024;
+
+/// Phrases that mark a decision, preference, rule, or lesson
+const CUES: &str = r"(?i)\b(decided|decision|chose|prefer|preferred|always|never|instead of|should|must|don't|do not|avoid|convention|because|the fix|root cause|turns out|gotcha|remember)\b";
+
+/// How a candidate scored and why
+#[derive(Debug, Clone, Serialize)]
+pub struct Assessment {
+    pub score: f64,
+    pub length: f64,
+    pub novelty: f64,
+    pub cues: f64,
+    /// Closest existing memory by cosine similarity, if any
+    pub nearest: Option<i64>,
+}
+
+/// Score `content`, given its similarity to the closest existing memory;
+/// the caller fills in `nearest`
+pub fn assess(content: &str, similarity: Option<f64>) -> Assessment {
+    let length = length_score(content.trim().chars().count());
+    let novelty = similarity.map_or(1.0, |similarity| (1.0 - similarity).clamp(0.0, 1.0));
+    let cues = cue_score(content);
+    let score = if novelty <= 1.0 - DUPLICATE_SIMILARITY {
+        0.0
+    } else {
+        LENGTH_WEIGHT * length + NOVELTY_WEIGHT * novelty + CUE_WEIGHT * cues
+    };
+
+    Assessment {
+        score,
+        length,
+        novelty,
+        cues,
+        nearest: None,
+    }
+}
+
+fn length_score(chars: usize) -> f64 {
+    match chars {
+        n if n < MIN_CHARS => 0.0,
+        n if n <= IDEAL_MAX_CHARS => 1.0,
+        n => (IDEAL_MAX_CHARS as f64 / n as f64).sqrt(),
+    }
+}
+
+/// 0 without cues, 1 with two or more
+fn cue_score(content: &str) -> f64 {
+    static CUE_RE: OnceLock<Regex> = OnceLock::new();
+    let re = CUE_RE.get_or_init(|| Regex::new(CUES).unwrap());
+    (re.find_iter(content).count() as f64 / 2.0).min(1.0)
+}
+
+/// Whether `content` has any wording that marks a decision, preference,
+/// rule, or lesson
+pub fn has_cues(content: &str) -> bool {
+    cue_score(content) > 0.0
+}
+
+#[derive(Serialize)]
+struct Rejection<'a> {
+    at: String,
+    threshold: f64,
+    #[serde(flatten)]
+    assessment: &'a Assessment,
+    content: &'a str,
+}
+
+/// Append a rejected candidate to `.roots/rejected.jsonl`, cutting it back
+/// to its newer half once it passes `REJECTED_MAX_BYTES`
+pub fn log_rejection(
+    roots_path: &Path,
+    content: &str,
+    assessment: &Assessment,
+    threshold: f64,
+) -> Result<(), String> {
+    let line = serde_json::to_string(&Rejection {
+        at: crate::dates::now_rfc3339(),
+        threshold,
+        assessment,
+        content,
+    })
+    .map_err(|e| format!("Failed to serialize rejection: {}", e))?;
+
+    let path = roots_path.join(REJECTED_LOG);
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    writeln!(file, "{}", line).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+
+    if file.metadata().is_ok_and(|m| m.len() > REJECTED_MAX_BYTES) {
+        truncate_log(&path).map_err(|e| format!("Failed to trim {}: {}", path.display(), e))?;
+    }
+    Ok(())
+}
+
+/// Keep the newest whole lines of the log at `path` that fit in half of
+/// `REJECTED_MAX_BYTES`
+fn truncate_log(path: &Path) -> std::io::Result<()> {
+    let log = fs::read_to_string(path)?;
+    let keep = (REJECTED_MAX_BYTES / 2) as usize;
+    let mut start = log.len().saturating_sub(keep);
+    while !log.is_char_boundary(start) {
+        start += 1;
+    }
+    let kept = match log[start..].find('\n') {
+        Some(i) if start > 0 => &log[start + i + 1..],
+        _ => &log[start..],
+    };
+    let tmp = path.with_extension("jsonl.tmp");
+    fs::write(&tmp, kept)?;
+    fs::rename(&tmp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_assess_prefers_novel_decisions() {
+        let decision = "We decided to use sqlx instead of diesel because compile-time checks catch schema drift";
+        let chatter = "ok, sounds good";
+
+        let threshold = default_threshold();
+        assert!(assess(decision, None).score >= threshold);
+        assert!(assess(chatter, None).score < threshold);
+
+        // The same decision again adds nothing
+        assert_eq!(assess(decision, Some(0.98)).score, 0.0);
+    }
+
+    fn default_threshold() -> f64 {
+        crate::config::setting_spec("capture_threshold")
+            .and_then(|s| s.default)
+            .and_then(|d| d.parse().ok())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_hook_payloads_without_cues_are_rejected() {
+        use crate::hook::HookInput;
+        let threshold = default_threshold();
+
+        // What `remember --auto --hook-stdin` gets from a PostToolUse edit
+        let edit = HookInput::parse(
+            r#"{"session_id": "s1", "hook_event_name": "PostToolUse", "tool_name": "Edit",
+                "tool_input": {"file_path": "/repo/src/db.rs",
+                    "old_string": "let pool = Pool::new(url);",
+                    "new_string": "let pool = Pool::builder()\n    .max_size(16)\n    .idle_timeout(Some(Duration::from_secs(30)))\n    .build(url)?;"}}"#,
+        )
+        .unwrap()
+        .capture()
+        .unwrap();
+        assert!(assess(&edit, None).score < threshold, "{:?}", assess(&edit, None));
+
+        let write = HookInput::parse(
+            r###"{"session_id": "s1", "hook_event_name": "PostToolUse", "tool_name": "Write",
+                "tool_input": {"file_path": "/repo/CHANGELOG.md",
+                    "content": "## 0.4.0\n\n- Added connection pooling\n- Fixed the flaky integration tests\n"}}"###,
+        )
+        .unwrap()
+        .capture()
+        .unwrap();
+        assert!(assess(&write, None).score < threshold);
+
+        // Last replies at Stop that only report progress
+        for reply in [
+            "I've updated the connection pool settings in src/db.rs and all 42 tests pass now.",
+            "Done! The README now has the new install steps, and I ran the linter over the docs folder.",
+            "Let me know if you'd like me to make any other changes to the migration script.",
+        ] {
+            assert!(assess(reply, None).score < threshold, "{}", reply);
+        }
+    }
+
+    #[test]
+    fn test_hook_payloads_with_lessons_are_kept() {
+        let threshold = default_threshold();
+        for reply in [
+            "The root cause was the proxy closing idle connections after 60s, so the pool's idle_timeout must stay below that.",
+            "We decided to keep migrations in plain SQL instead of the ORM's DSL because reviewers can read them.",
+            "Gotcha: the integration tests need DATABASE_URL set, otherwise they silently run against sqlite.",
+        ] {
+            assert!(assess(reply, None).score >= threshold, "{}", reply);
+            assert_eq!(assess(reply, Some(0.97)).score, 0.0);
+        }
+    }
+
+    #[test]
+    fn test_rejection_log_is_capped() {
+        let dir = std::env::temp_dir().join(format!("roots-rejected-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let content = "x".repeat(1000);
+        let assessment = assess(&content, None);
+        for _ in 0..(REJECTED_MAX_BYTES / 1000 + 10) {
+            log_rejection(&dir, &content, &assessment, 0.5).unwrap();
+        }
+
+        let log = fs::read_to_string(dir.join(REJECTED_LOG)).unwrap();
+        assert!(log.len() as u64 <= REJECTED_MAX_BYTES);
+        assert!(log.len() as u64 >= REJECTED_MAX_BYTES / 4);
+        // Only whole entries are kept
+        for line in log.lines() {
+            serde_json::from_str::<serde_json::Value>(line).unwrap();
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
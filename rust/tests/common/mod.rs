@@ -0,0 +1,74 @@
+//! Running the binary against a scratch home and store
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// A scratch directory with a home of its own, so no real store, config,
+/// or server is touched
+pub fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("roots-cli-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("home")).unwrap();
+    dir
+}
+
+pub fn roots(dir: &Path, args: &[&str]) -> Command {
+    let home = dir.join("home");
+    let mut command = Command::new(env!("CARGO_BIN_EXE_roots"));
+    command
+        .args(args)
+        .current_dir(dir)
+        .env("HOME", &home)
+        .env("XDG_CONFIG_HOME", home.join(".config"))
+        .env("XDG_STATE_HOME", home.join(".state"))
+        .env("XDG_DATA_HOME", home.join(".data"))
+        .env("ROOTS_SOCKET", dir.join("no-server.sock"))
+        .env_remove("ROOTS_PATH")
+        .env_remove("ROOTS_GLOBAL_PATH")
+        .env_remove("ROOTS_PROFILE")
+        .env_remove("ROOTS_OUTPUT");
+    command
+}
+
+/// A store in `dir` embedding with `model`
+pub fn init(dir: &Path, model: &str) {
+    fs::create_dir_all(dir.join(".roots")).unwrap();
+    fs::write(dir.join(".roots/_config.yaml"), format!("embedding_model: {}\n", model)).unwrap();
+}
+
+
+/// An embedding server on `socket` serving `model`, whose vectors have
+/// `dim` dimensions
+#[allow(dead_code)]
+pub fn embedding_server(socket: &Path, model: &'static str, dim: usize) {
+    use std::io::{Read, Write};
+    let listener = std::os::unix::net::UnixListener::bind(socket).unwrap();
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().map_while(Result::ok) {
+            std::thread::spawn(move || loop {
+                let mut header = [0u8; 4];
+                if stream.read_exact(&mut header).is_err() {
+                    return;
+                }
+                let mut payload = vec![0u8; u32::from_be_bytes(header) as usize];
+                stream.read_exact(&mut payload).unwrap();
+                let request: serde_json::Value = serde_json::from_slice(&payload).unwrap();
+                let vector = vec![1.0 / (dim as f32).sqrt(); dim];
+                let response = match request["cmd"].as_str().unwrap() {
+                    "hello" => serde_json::json!({"ok": true, "version": 1}),
+                    "ping" => serde_json::json!({"ok": true, "model": model, "models": [model]}),
+                    "embed" => serde_json::json!({"ok": true, "embedding": vector}),
+                    "embed_batch" => {
+                        let count = request["texts"].as_array().unwrap().len();
+                        serde_json::json!({"ok": true, "embeddings": vec![vector; count]})
+                    }
+                    _ => serde_json::json!({"ok": false, "error": "unsupported"}),
+                };
+                let body = response.to_string();
+                stream.write_all(&(body.len() as u32).to_be_bytes()).unwrap();
+                stream.write_all(body.as_bytes()).unwrap();
+            });
+        }
+    });
+}
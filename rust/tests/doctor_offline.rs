@@ -0,0 +1,32 @@
+//! Doctor with the embedding server down: vectors the server made are
+//! checked against each other, not against lite's.
+
+mod common;
+
+use common::{embedding_server, init, roots, scratch};
+use std::fs;
+
+#[test]
+fn test_doctor_with_the_server_down_does_not_advise_reindex() {
+    let dir = scratch("doctor-offline");
+    init(&dir, "bge-base");
+    let socket = dir.join("no-server.sock");
+    embedding_server(&socket, "BAAI/bge-base-en-v1.5", 768);
+
+    for content in ["The build uses cargo", "Releases are tagged by CI"] {
+        let remember = roots(&dir, &["remember", content]).output().unwrap();
+        assert!(remember.status.success(), "{}", String::from_utf8_lossy(&remember.stderr));
+    }
+    let doctor = roots(&dir, &["doctor"]).output().unwrap();
+    assert!(String::from_utf8_lossy(&doctor.stdout).contains("768 dimensions"));
+
+    fs::remove_file(&socket).unwrap();
+    let doctor = roots(&dir, &["doctor"]).output().unwrap();
+    let stdout = String::from_utf8_lossy(&doctor.stdout);
+    assert!(!stdout.contains("FAIL"), "{}", stdout);
+    assert!(!stdout.contains("reindex"), "{}", stdout);
+    assert!(stdout.contains("server unreachable"), "{}", stdout);
+    assert!(stdout.contains("768 dimensions"), "{}", stdout);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
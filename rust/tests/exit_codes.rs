@@ -0,0 +1,91 @@
+//! Each kind of failure exits with its own code (see `src/error.rs`).
+
+mod common;
+
+use common::{init, roots, scratch};
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::os::unix::net::UnixListener;
+use std::path::Path;
+use std::process::Command;
+
+fn exit_code(command: &mut Command) -> i32 {
+    command.output().unwrap().status.code().unwrap()
+}
+
+/// A framed server at `socket` that has `model` loaded but fails to embed
+fn failing_server(socket: &Path, model: &'static str) {
+    let listener = UnixListener::bind(socket).unwrap();
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().map_while(Result::ok) {
+            std::thread::spawn(move || loop {
+                let mut header = [0u8; 4];
+                if stream.read_exact(&mut header).is_err() {
+                    return;
+                }
+                let mut payload = vec![0u8; u32::from_be_bytes(header) as usize];
+                stream.read_exact(&mut payload).unwrap();
+                let request: serde_json::Value = serde_json::from_slice(&payload).unwrap();
+                let response = match request["cmd"].as_str().unwrap() {
+                    "hello" => serde_json::json!({"ok": true, "version": 1}),
+                    "ping" => serde_json::json!({"ok": true, "model": model, "models": [model]}),
+                    _ => serde_json::json!({"ok": false, "error": "CUDA out of memory"}),
+                };
+                let body = response.to_string();
+                stream.write_all(&(body.len() as u32).to_be_bytes()).unwrap();
+                stream.write_all(body.as_bytes()).unwrap();
+            });
+        }
+    });
+}
+
+#[test]
+fn test_each_kind_of_failure_has_its_exit_code() {
+    let dir = scratch("kinds");
+
+    // 3: no store here or above
+    assert_eq!(exit_code(&mut roots(&dir, &["list"])), 3);
+
+    init(&dir, "lite");
+    // 2: arguments that parse but make no sense
+    assert_eq!(exit_code(&mut roots(&dir, &["remember", "--kind", "bogus", "x"])), 2);
+    // 4: a file that isn't there
+    assert_eq!(exit_code(&mut roots(&dir, &["ingest", "docs", "missing.md"])), 4);
+    assert_eq!(exit_code(&mut roots(&dir, &["forget", "999"])), 4);
+    // 1: anything else, such as reading a directory as a file
+    assert_eq!(exit_code(&mut roots(&dir, &["ingest", "bookmarks", "home"])), 1);
+
+    // 6: maintenance already running
+    let lock = File::create(dir.join(".roots/maintenance.lock")).unwrap();
+    lock.lock().unwrap();
+    assert_eq!(exit_code(&mut roots(&dir, &["optimize", "--no-wait"])), 6);
+    drop(lock);
+    assert_eq!(exit_code(&mut roots(&dir, &["optimize", "--no-wait"])), 0);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_embedder_failure_has_its_exit_code() {
+    let dir = scratch("embedder");
+    init(&dir, "fake-model");
+    let socket = dir.join("server.sock");
+    failing_server(&socket, "fake-model");
+
+    let output = roots(&dir, &["remember", "something"])
+        .env("ROOTS_SOCKET", &socket)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(5));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("CUDA out of memory"));
+
+    // The same failure while ingesting keeps its kind
+    fs::write(dir.join("notes.md"), "# Notes\n\nSomething worth keeping.\n").unwrap();
+    let ingest = roots(&dir, &["ingest", "docs", "notes.md"])
+        .env("ROOTS_SOCKET", &socket)
+        .output()
+        .unwrap();
+    assert_eq!(ingest.status.code(), Some(5));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,24 @@
+//! Vectors are stamped with the model that embedded them, which is lite
+//! when the configured model's server can't be reached.
+
+mod common;
+
+use common::{init, roots, scratch};
+use std::fs;
+
+#[test]
+fn test_lite_fallback_is_recorded_as_lite() {
+    let dir = scratch("fallback-model");
+    init(&dir, "bge-base");
+
+    let remember = roots(&dir, &["remember", "The build uses cargo"]).output().unwrap();
+    assert!(remember.status.success(), "{}", String::from_utf8_lossy(&remember.stderr));
+    assert!(String::from_utf8_lossy(&remember.stderr).contains("Using lite embedder"));
+
+    let doctor = roots(&dir, &["doctor"]).output().unwrap();
+    let stdout = String::from_utf8_lossy(&doctor.stdout);
+    let model = stdout.lines().find(|l| l.starts_with("model")).unwrap();
+    assert!(model.ends_with("lite"), "{}", model);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,24 @@
+//! A broken global store doesn't take the project's down with it
+
+mod common;
+
+use common::{init, roots, scratch};
+use std::fs;
+
+#[test]
+fn test_unreadable_global_store_is_skipped_with_a_warning() {
+    let dir = scratch("global-broken");
+    init(&dir, "lite");
+    fs::create_dir_all(dir.join("home/.roots")).unwrap();
+    fs::write(dir.join("home/.roots/memory.db"), "not a database").unwrap();
+
+    let remember = roots(&dir, &["remember", "Releases are cut on Thursdays"]).output().unwrap();
+    assert!(remember.status.success(), "{}", String::from_utf8_lossy(&remember.stderr));
+    assert!(String::from_utf8_lossy(&remember.stderr).contains("Warning: Global store unavailable"));
+
+    let list = roots(&dir, &["list"]).output().unwrap();
+    assert!(list.status.success());
+    assert!(String::from_utf8_lossy(&list.stdout).contains("Releases are cut on Thursdays"));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,27 @@
+//! Import recognises a memory it already has by its content, when the
+//! creation timestamps differ (the same note remembered on two machines).
+
+mod common;
+
+use common::{init, roots, scratch};
+use std::fs;
+
+#[test]
+fn test_import_matches_the_same_content_remembered_separately() {
+    let dir = scratch("import-content");
+    init(&dir, "lite");
+    let remember = roots(&dir, &["remember", "Deploys go through staging"]).output().unwrap();
+    assert!(remember.status.success(), "{}", String::from_utf8_lossy(&remember.stderr));
+
+    let export = roots(&dir, &["export"]).output().unwrap();
+    let mut memories: serde_json::Value = serde_json::from_slice(&export.stdout).unwrap();
+    memories[0]["created_at"] = "2025-01-01T00:00:00+00:00".into();
+    fs::write(dir.join("other.json"), memories.to_string()).unwrap();
+
+    let import = roots(&dir, &["import", "other.json", "--strategy", "skip"]).output().unwrap();
+    let stdout = String::from_utf8_lossy(&import.stdout);
+    assert!(stdout.contains("Imported 0 of 1"), "{}", stdout);
+    assert!(stdout.contains("1 already up to date"), "{}", stdout);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,35 @@
+//! Ingested files are tracked by their place in the project, whichever
+//! directory a later command runs from.
+
+mod common;
+
+use common::{init, roots, scratch};
+use std::fs;
+
+#[test]
+fn test_watch_from_a_subdirectory_keeps_ingested_files() {
+    let dir = scratch("watch-subdir");
+    init(&dir, "lite");
+    fs::create_dir_all(dir.join("docs")).unwrap();
+    fs::create_dir_all(dir.join("sub")).unwrap();
+    fs::write(dir.join("docs/a.md"), "# A\n\nAlpha.\n").unwrap();
+
+    let ingest = roots(&dir, &["ingest", "docs", "docs"]).output().unwrap();
+    assert!(ingest.status.success(), "{}", String::from_utf8_lossy(&ingest.stderr));
+
+    let watch = roots(&dir, &["watch", "--once"]).current_dir(dir.join("sub")).output().unwrap();
+    assert!(watch.status.success(), "{}", String::from_utf8_lossy(&watch.stderr));
+    assert!(!String::from_utf8_lossy(&watch.stdout).contains("removed"));
+
+    // The same file under another spelling, from another directory
+    let again = roots(&dir, &["ingest", "docs", "--update", "../docs/./a.md"])
+        .current_dir(dir.join("sub"))
+        .output()
+        .unwrap();
+    assert!(String::from_utf8_lossy(&again.stdout).contains("1 unchanged, 0 removed"));
+
+    let list = roots(&dir, &["list"]).output().unwrap();
+    assert!(String::from_utf8_lossy(&list.stdout).contains("Alpha."));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
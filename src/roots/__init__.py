@@ -13,6 +13,7 @@ Usage:
 
 from roots.config import SUGGESTED_MODELS, get_server_model, resolve_model
 from roots.embeddings import (
+    CrossEncoderReranker,
     EmbedderProtocol,
     LiteEmbedder,
     SentenceTransformerEmbedder,
@@ -29,6 +30,7 @@ __all__ = [
     "start_server",
     "stop_server",
     # Embeddings
+    "CrossEncoderReranker",
     "EmbedderProtocol",
     "LiteEmbedder",
     "SentenceTransformerEmbedder",
@@ -2,16 +2,40 @@
 config - Configuration management for roots.
 
 Handles persistent settings stored in .roots/_config.yaml.
-Also supports global config in ~/.config/roots/config.yaml for the embedding server.
+Also supports global config in $XDG_CONFIG_HOME/roots/config.yaml for the embedding server.
 """
 
+import os
+import sys
 from pathlib import Path
 from typing import Any
 
 import yaml
 
+
+def _global_config_dir() -> Path:
+    """Find the global config directory the same way the CLI does.
+
+    $XDG_CONFIG_HOME/roots (~/.config/roots), or the platform's config
+    directory on macOS and Windows; a ~/.config/roots from an older version
+    is used until the new directory exists.
+    """
+    if sys.platform == "darwin":
+        base = Path.home() / "Library" / "Application Support"
+    elif sys.platform == "win32":
+        base = Path(os.environ.get("APPDATA") or Path.home() / "AppData" / "Roaming")
+    else:
+        xdg = os.environ.get("XDG_CONFIG_HOME", "")
+        base = Path(xdg) if os.path.isabs(xdg) else Path.home() / ".config"
+    legacy = Path.home() / ".config" / "roots"
+    config_dir = base / "roots"
+    if not config_dir.exists() and legacy.is_dir():
+        return legacy
+    return config_dir
+
+
 # Global config location (used by embedding server)
-GLOBAL_CONFIG_DIR = Path.home() / ".config" / "roots"
+GLOBAL_CONFIG_DIR = _global_config_dir()
 GLOBAL_CONFIG_FILE = GLOBAL_CONFIG_DIR / "config.yaml"
 
 # Suggested embedding models (user can use any model, these are just suggestions)
@@ -82,6 +106,9 @@ MODEL_ALIASES = {m["alias"]: m for m in SUGGESTED_MODELS}
 
 DEFAULT_MODEL = "bge-base"
 
+# Cross-encoder the server reranks recall candidates with
+DEFAULT_RERANK_MODEL = "BAAI/bge-reranker-base"
+
 
 def resolve_model(model_input: str) -> tuple[str, str]:
     """
@@ -134,6 +161,36 @@ def get_server_model() -> tuple[str, str]:
     return resolve_model(model)
 
 
+def get_rerank_model() -> str:
+    """Get the cross-encoder configured for reranking."""
+    return get_global_config().get("rerank_model", DEFAULT_RERANK_MODEL)
+
+
+def _state_dir() -> Path:
+    """The CLI's state directory, where the socket goes without a runtime directory."""
+    if sys.platform == "darwin":
+        return Path.home() / "Library" / "Application Support" / "roots"
+    if sys.platform == "win32":
+        return Path(os.environ.get("APPDATA") or Path.home() / "AppData" / "Roaming") / "roots"
+    xdg = os.environ.get("XDG_STATE_HOME", "")
+    base = Path(xdg) if os.path.isabs(xdg) else Path.home() / ".local" / "state"
+    return base / "roots"
+
+
+def get_server_socket() -> Path:
+    """Get the embedding server's socket, as the CLI resolves it.
+
+    ROOTS_SOCKET, then server_socket in the global config, then
+    $XDG_RUNTIME_DIR/roots/embedder.sock, or the state directory without one.
+    """
+    path = os.environ.get("ROOTS_SOCKET") or get_global_config().get("server_socket")
+    if path:
+        return Path(path)
+    runtime = os.environ.get("XDG_RUNTIME_DIR", "")
+    base = Path(runtime) / "roots" if os.path.isabs(runtime) else _state_dir()
+    return base / "embedder.sock"
+
+
 # -----------------------------------------------------------------------------
 # Per-project config
 # -----------------------------------------------------------------------------
@@ -38,8 +38,10 @@ class ServerEmbedder:
 class SentenceTransformerEmbedder:
     """Generate embeddings using sentence-transformers (local)."""
 
-    def __init__(self, model_name: str = "BAAI/bge-base-en-v1.5"):
+    def __init__(self, model_name: str = "BAAI/bge-base-en-v1.5", backend: str = "torch"):
         self.model_name = model_name
+        # "torch", or "onnx" for ONNX Runtime (the package's onnx extra)
+        self.backend = backend
         self._model = None
 
     @property
@@ -48,7 +50,9 @@ class SentenceTransformerEmbedder:
         if self._model is None:
             from sentence_transformers import SentenceTransformer
 
-            self._model = SentenceTransformer(self.model_name, trust_remote_code=True)
+            self._model = SentenceTransformer(
+                self.model_name, backend=self.backend, trust_remote_code=True
+            )
         return self._model
 
     def embed(self, text: str) -> list[float]:
@@ -62,6 +66,41 @@ class SentenceTransformerEmbedder:
         return vectors.tolist()
 
 
+class CrossEncoderReranker:
+    """Score (query, text) pairs with a cross-encoder (local)."""
+
+    def __init__(self, model_name: str = "BAAI/bge-reranker-base"):
+        self.model_name = model_name
+        self._model = None
+
+    @property
+    def model(self):
+        """Lazy load the model."""
+        if self._model is None:
+            from sentence_transformers import CrossEncoder
+
+            self._model = CrossEncoder(self.model_name, trust_remote_code=True)
+        return self._model
+
+    def rerank(self, query: str, texts: list[str]) -> list[float]:
+        """Relevance of each text to the query, from 0 to 1.
+
+        The sigmoid of the model's raw logit, so a text scores the same
+        whatever else is in the batch and whatever activation the model's
+        config names.
+        """
+        if not texts:
+            return []
+        logits = self.model.predict([(query, t) for t in texts], activation_fn=_identity)
+        logits = np.asarray(logits, dtype=np.float64).reshape(len(texts))
+        return (1.0 / (1.0 + np.exp(-logits))).tolist()
+
+
+def _identity(logits):
+    """Activation that leaves a cross-encoder's logits as they are."""
+    return logits
+
+
 class LiteEmbedder:
     """
     Lightweight embedder using character n-gram hashing.
@@ -109,6 +148,7 @@ def get_embedder(
     model_name: str | None = None,
     model_type: str = "sentence-transformers",
     use_server: bool = True,
+    backend: str = "torch",
 ) -> EmbedderProtocol:
     """
     Get an embedder for the specified model.
@@ -117,6 +157,7 @@ def get_embedder(
         model_name: Model name/path. If None, uses default BGE model.
         model_type: Either "sentence-transformers" or "lite"
         use_server: If True, use embedding server (auto-starts if needed)
+        backend: What a local model runs on, "torch" or "onnx"
 
     Returns:
         An embedder instance.
@@ -152,7 +193,7 @@ def get_embedder(
             "Or use lite mode: roots config model lite"
         )
 
-    return SentenceTransformerEmbedder(model_name)
+    return SentenceTransformerEmbedder(model_name, backend)
 
 
 def validate_model(model_name: str) -> tuple[bool, str, int]:
@@ -12,26 +12,211 @@ Usage:
 
 import json
 import os
+import re
 import signal
 import socket
+import struct
 import sys
 import threading
+import time
 from pathlib import Path
 
-SOCKET_PATH = Path("/tmp/roots-embedder.sock")
-PID_FILE = Path("/tmp/roots-embedder.pid")
-LOG_FILE = Path("/tmp/roots-embedder.log")
+from roots.config import get_server_socket
+
+SOCKET_PATH = get_server_socket()
+PID_FILE = SOCKET_PATH.with_suffix(".pid")
+LOG_FILE = SOCKET_PATH.with_suffix(".log")
+
+# Version of the message format; see rust/src/wire.rs
+PROTOCOL_VERSION = 1
+# Largest message accepted
+MAX_FRAME = 64 << 20
+
+# Most texts embedded in one call when single requests are coalesced
+MAX_COALESCED = 64
+
+
+class _Job:
+    """Texts waiting for a Batcher, and their vectors once embedded."""
+
+    def __init__(self, texts: list[str]):
+        self.texts = texts
+        self.vectors = None
+        self.error = None
+        self.done = threading.Event()
+
+    def finish(self, vectors=None, error=None):
+        self.vectors, self.error = vectors, error
+        self.done.set()
+
+    def wait(self) -> list[list[float]]:
+        self.done.wait()
+        if self.error is not None:
+            raise self.error
+        return self.vectors
+
+
+class Batcher:
+    """Runs one model's embedding on a single thread.
+
+    Single `embed` requests that arrive while it is busy are coalesced into
+    one embed_batch call, and go ahead of queued embed_batch requests, so a
+    hook's lookup waits for at most the batch in progress rather than a
+    whole reindex.
+    """
+
+    def __init__(self, embedder):
+        self.embedder = embedder
+        self.cond = threading.Condition()
+        self.single = []
+        self.bulk = []
+        threading.Thread(target=self._run, daemon=True).start()
+
+    def embed(self, text: str) -> list[float]:
+        return self._submit(self.single, [text])[0]
+
+    def embed_batch(self, texts: list[str]) -> list[list[float]]:
+        return self._submit(self.bulk, texts)
+
+    def _submit(self, queue: list, texts: list[str]) -> list[list[float]]:
+        job = _Job(texts)
+        with self.cond:
+            queue.append(job)
+            self.cond.notify()
+        return job.wait()
+
+    def _run(self):
+        while True:
+            with self.cond:
+                while not self.single and not self.bulk:
+                    self.cond.wait()
+                if self.single:
+                    jobs = self.single[:MAX_COALESCED]
+                    del self.single[:MAX_COALESCED]
+                else:
+                    jobs = [self.bulk.pop(0)]
+            self._embed(jobs)
+
+    def _embed(self, jobs: list[_Job]):
+        texts = [t for job in jobs for t in job.texts]
+        try:
+            vectors = self.embedder.embed_batch(texts) if texts else []
+        except Exception as e:
+            if len(jobs) == 1:
+                jobs[0].finish(error=e)
+            else:
+                # Don't let one bad text fail the requests it was coalesced with
+                for job in jobs:
+                    self._embed([job])
+            return
+        start = 0
+        for job in jobs:
+            job.finish(vectors=vectors[start : start + len(job.texts)])
+            start += len(job.texts)
+
+
+def _read_exact(sock: socket.socket, n: int) -> bytes | None:
+    """Exactly n bytes, or None if the peer closes first."""
+    data = b""
+    while len(data) < n:
+        chunk = sock.recv(min(n - len(data), 1 << 20))
+        if not chunk:
+            return None
+        data += chunk
+    return data
+
+
+def _read_frame(sock: socket.socket) -> dict | None:
+    """One length-prefixed JSON message, or None at the end of the connection."""
+    header = _read_exact(sock, 4)
+    if header is None:
+        return None
+    size = struct.unpack(">I", header)[0]
+    if size > MAX_FRAME:
+        raise ValueError(f"message too large ({size} bytes)")
+    payload = _read_exact(sock, size)
+    if payload is None:
+        return None
+    return json.loads(payload.decode())
+
+
+def _write_frame(sock: socket.socket, message: dict):
+    payload = json.dumps(message).encode()
+    sock.sendall(struct.pack(">I", len(payload)) + payload)
+
+
+# Bytes that open or close an object or string, or escape within one
+_JSON_STRUCTURE = re.compile(rb'[{}"\\]')
+
+
+def _recv_request(conn: socket.socket) -> dict | None:
+    """Read one bare JSON request, from a client that predates framing: up
+    to EOF, or until its object closes. Each chunk is scanned once as it
+    arrives, and the request parsed once at the end."""
+    data = bytearray()
+    depth, in_string, skip = 0, False, 0
+    while chunk := conn.recv(65536):
+        start = len(data)
+        data += chunk
+        for match in _JSON_STRUCTURE.finditer(data, start):
+            at = match.start()
+            if at < skip:
+                continue
+            c = data[at : at + 1]
+            if in_string:
+                if c == b"\\":
+                    skip = at + 2
+                elif c == b'"':
+                    in_string = False
+            elif c == b'"':
+                in_string = True
+            elif c == b"{":
+                depth += 1
+            elif c == b"}":
+                depth -= 1
+                if depth == 0:
+                    return json.loads(data[: at + 1].decode())
+    return json.loads(data.decode()) if data.strip() else None
 
 
 class EmbeddingServer:
     """Unix socket server for embedding requests."""
 
-    def __init__(self, model_name: str, model_type: str):
+    def __init__(
+        self,
+        model_name: str,
+        model_type: str,
+        idle_timeout: float = 0,
+        listen_fd: int | None = None,
+        extra_models: list[str] = (),
+        backend: str = "torch",
+    ):
         self.model_name = model_name
         self.model_type = model_type
+        self.backend = backend
+        # Loaded alongside the default; requests pick one with "model"
+        self.extra_models = [m for m in extra_models if m != model_name]
         self.embedder = None
+        self.embedders = {}
+        # One per embedder, which all embedding goes through
+        self.batchers = {}
+        # Cross-encoders by name, each loaded by the first rerank naming it
+        self.rerankers = {}
+        self.reranker_lock = threading.Lock()
         self.running = False
         self.socket = None
+        # Seconds without embedding or reranking before exiting; 0 never
+        self.idle_timeout = idle_timeout
+        # A socket systemd already bound (socket activation), used instead of binding
+        self.listen_fd = listen_fd
+        self.busy = 0
+        self.last_used = time.monotonic()
+        self.activity_lock = threading.Lock()
+        # For `status`: when the model came up, and embed/rerank requests served
+        self.started = None
+        self.dimensions = None
+        self.requests = 0
+        self.busy_seconds = 0.0
 
     def start(self):
         """Start the server."""
@@ -39,20 +224,37 @@ class EmbeddingServer:
 
         print(f"Loading model: {self.model_name}", flush=True)
         # use_server=False to avoid circular dependency
-        self.embedder = get_embedder(self.model_name, self.model_type, use_server=False)
+        self.embedder = get_embedder(
+            self.model_name, self.model_type, use_server=False, backend=self.backend
+        )
+        self.embedders[self.model_name] = self.embedder
+        for name in self.extra_models:
+            print(f"Loading model: {name}", flush=True)
+            self.embedders[name] = get_embedder(
+                name, "sentence-transformers", use_server=False, backend=self.backend
+            )
+            _ = self.embedders[name].embed("warmup")
 
         # Warm up
-        _ = self.embedder.embed("warmup")
+        self.dimensions = len(self.embedder.embed("warmup"))
+        self.batchers = {name: Batcher(e) for name, e in self.embedders.items()}
+        self.started = time.monotonic()
         print("Model ready", flush=True)
 
-        # Clean up old socket
-        if SOCKET_PATH.exists():
-            SOCKET_PATH.unlink()
-
-        self.socket = socket.socket(socket.AF_UNIX, socket.SOCK_STREAM)
-        self.socket.bind(str(SOCKET_PATH))
-        self.socket.listen(10)
+        if self.listen_fd is not None:
+            self.socket = socket.socket(fileno=self.listen_fd)
+        else:
+            # Clean up old socket
+            SOCKET_PATH.parent.mkdir(parents=True, exist_ok=True)
+            if SOCKET_PATH.exists():
+                SOCKET_PATH.unlink()
+
+            self.socket = socket.socket(socket.AF_UNIX, socket.SOCK_STREAM)
+            self.socket.bind(str(SOCKET_PATH))
+            # Room for a burst of hooks arriving together
+            self.socket.listen(64)
         self.socket.settimeout(1.0)
+        self.last_used = time.monotonic()
 
         signal.signal(signal.SIGTERM, self._shutdown)
         signal.signal(signal.SIGINT, self._shutdown)
@@ -61,8 +263,13 @@ class EmbeddingServer:
         print(f"Listening on {SOCKET_PATH}", flush=True)
 
         while self.running:
+            if self._idle():
+                print(f"Idle for {self.idle_timeout:.0f}s, shutting down", flush=True)
+                break
             try:
                 conn, _ = self.socket.accept()
+                # Kept-alive connections wait for requests without a timeout
+                conn.settimeout(None)
                 threading.Thread(target=self._handle, args=(conn,), daemon=True).start()
             except socket.timeout:
                 continue
@@ -73,37 +280,122 @@ class EmbeddingServer:
         self._cleanup()
 
     def _handle(self, conn: socket.socket):
-        """Handle client request."""
+        """Serve a connection: framed requests until the client closes it, or
+        one bare JSON request from a client that predates framing."""
         try:
-            data = conn.recv(65536)
-            if not data:
+            if conn.recv(1, socket.MSG_PEEK) == b"{":
+                req = _recv_request(conn)
+                if req is not None:
+                    conn.sendall(json.dumps(self._respond(req)).encode())
                 return
+            while (req := _read_frame(conn)) is not None:
+                _write_frame(conn, self._respond(req))
+                if req.get("cmd") == "stop":
+                    return
+        except Exception as e:
+            try:
+                _write_frame(conn, {"ok": False, "error": str(e)})
+            except:
+                pass
+        finally:
+            conn.close()
 
-            req = json.loads(data.decode().strip())
-            cmd = req.get("cmd")
-
+    def _respond(self, req: dict) -> dict:
+        """The answer to one request."""
+        cmd = req.get("cmd")
+        # Pings come from every roots command and don't keep the model loaded
+        working = cmd in ("embed", "embed_batch", "rerank")
+        began = time.monotonic()
+        if working:
+            self._begin()
+        try:
             if cmd == "embed":
-                emb = self.embedder.embed(req["text"])
-                resp = {"ok": True, "embedding": emb}
+                emb = self._batcher(req.get("model")).embed(req["text"])
+                return {"ok": True, "embedding": emb}
             elif cmd == "embed_batch":
-                embs = self.embedder.embed_batch(req["texts"])
-                resp = {"ok": True, "embeddings": embs}
+                embs = self._batcher(req.get("model")).embed_batch(req["texts"])
+                return {"ok": True, "embeddings": embs}
+            elif cmd == "rerank":
+                scores = self._get_reranker(req.get("model")).rerank(req["query"], req["texts"])
+                return {"ok": True, "scores": scores}
+            elif cmd == "hello":
+                return {"ok": True, "version": min(int(req.get("version", 1)), PROTOCOL_VERSION)}
             elif cmd == "ping":
-                resp = {"ok": True, "model": self.model_name}
+                return {"ok": True, "model": self.model_name, "models": list(self.embedders)}
+            elif cmd == "status":
+                return {"ok": True, **self._status()}
             elif cmd == "stop":
-                resp = {"ok": True}
                 self.running = False
+                return {"ok": True}
             else:
-                resp = {"ok": False, "error": f"unknown: {cmd}"}
-
-            conn.sendall(json.dumps(resp).encode())
+                return {"ok": False, "error": f"unknown: {cmd}"}
         except Exception as e:
-            try:
-                conn.sendall(json.dumps({"ok": False, "error": str(e)}).encode())
-            except:
-                pass
+            return {"ok": False, "error": str(e)}
         finally:
-            conn.close()
+            if working:
+                self._end(time.monotonic() - began)
+
+    def _batcher(self, model: str | None):
+        """The batcher for a request's model, the default if it names none."""
+        if model is None:
+            return self.batchers[self.model_name]
+        if model not in self.batchers:
+            raise ValueError(f"model not loaded: {model}")
+        return self.batchers[model]
+
+    def _begin(self):
+        with self.activity_lock:
+            self.busy += 1
+
+    def _end(self, seconds: float):
+        with self.activity_lock:
+            self.busy -= 1
+            self.requests += 1
+            self.busy_seconds += seconds
+            self.last_used = time.monotonic()
+
+    def _status(self) -> dict:
+        """What `roots server status` shows beyond the model name."""
+        with self.activity_lock:
+            requests, busy_seconds = self.requests, self.busy_seconds
+        return {
+            "model": self.model_name,
+            "models": list(self.embedders),
+            "rerankers": list(self.rerankers),
+            "dimensions": self.dimensions,
+            "device": _device(self.embedder),
+            "uptime_secs": time.monotonic() - self.started,
+            "rss_bytes": _rss_bytes(),
+            "requests": requests,
+            "avg_latency_ms": busy_seconds / requests * 1000 if requests else None,
+        }
+
+    def _idle(self) -> bool:
+        """Whether idle_timeout has passed since the last request finished."""
+        if not self.idle_timeout:
+            return False
+        with self.activity_lock:
+            return self.busy == 0 and time.monotonic() - self.last_used >= self.idle_timeout
+
+    def _get_reranker(self, model: str | None):
+        """Load a cross-encoder on first use; most servers never rerank.
+
+        Clients name the one they want per request (recall and hook context
+        can use different ones); old clients that don't get `rerank_model`.
+        """
+        with self.reranker_lock:
+            if model is None:
+                from roots.config import get_rerank_model
+
+                model = get_rerank_model()
+            if model not in self.rerankers:
+                from roots.embeddings import CrossEncoderReranker
+
+                print(f"Loading reranker: {model}", flush=True)
+                reranker = CrossEncoderReranker(model)
+                _ = reranker.rerank("warmup", ["warmup"])
+                self.rerankers[model] = reranker
+            return self.rerankers[model]
 
     def _shutdown(self, *_):
         print("\nShutting down...", flush=True)
@@ -112,13 +404,29 @@ class EmbeddingServer:
     def _cleanup(self):
         if self.socket:
             self.socket.close()
-        if SOCKET_PATH.exists():
+        # An activated socket stays with systemd, to start the server again
+        if self.listen_fd is None and SOCKET_PATH.exists():
             SOCKET_PATH.unlink()
         if PID_FILE.exists():
             PID_FILE.unlink()
         print("Stopped", flush=True)
 
 
+def _device(embedder) -> str:
+    """Where the model runs, e.g. cpu, cuda:0, or mps."""
+    device = getattr(getattr(embedder, "model", None), "device", None)
+    return str(device) if device is not None else "cpu"
+
+
+def _rss_bytes() -> int | None:
+    """Resident memory of this process, where /proc has it."""
+    try:
+        pages = int(Path("/proc/self/statm").read_text().split()[1])
+        return pages * os.sysconf("SC_PAGE_SIZE")
+    except (OSError, ValueError, IndexError):
+        return None
+
+
 class EmbeddingClient:
     """Client to connect to embedding server."""
 
@@ -139,6 +447,15 @@ class EmbeddingClient:
         except:
             return None
 
+    @staticmethod
+    def status() -> dict:
+        """Uptime, device, memory, and request stats; empty from older servers."""
+        try:
+            resp = EmbeddingClient._send({"cmd": "status"})
+            return {k: v for k, v in resp.items() if k != "ok"} if resp.get("ok") else {}
+        except:
+            return {}
+
     @staticmethod
     def embed(text: str) -> list[float]:
         resp = EmbeddingClient._send({"cmd": "embed", "text": text})
@@ -153,6 +470,13 @@ class EmbeddingClient:
             raise RuntimeError(resp.get("error", "server error"))
         return resp["embeddings"]
 
+    @staticmethod
+    def rerank(query: str, texts: list[str]) -> list[float]:
+        resp = EmbeddingClient._send({"cmd": "rerank", "query": query, "texts": texts})
+        if not resp.get("ok"):
+            raise RuntimeError(resp.get("error", "server error"))
+        return resp["scores"]
+
     @staticmethod
     def stop() -> bool:
         try:
@@ -167,9 +491,15 @@ class EmbeddingClient:
         sock.settimeout(60.0)
         try:
             sock.connect(str(SOCKET_PATH))
-            sock.sendall(json.dumps(req).encode())
-            data = sock.recv(1024 * 1024)  # 1MB max response
-            return json.loads(data.decode())
+            _write_frame(sock, {"cmd": "hello", "version": PROTOCOL_VERSION})
+            hello = _read_frame(sock)
+            if not hello or not hello.get("ok"):
+                raise RuntimeError("protocol handshake failed")
+            _write_frame(sock, req)
+            resp = _read_frame(sock)
+            if resp is None:
+                raise RuntimeError("server closed the connection")
+            return resp
         finally:
             sock.close()
 
@@ -279,6 +609,7 @@ def server_status() -> dict:
             "model": EmbeddingClient.get_model(),
             "pid": pid,
             "socket": str(SOCKET_PATH),
+            **EmbeddingClient.status(),
         }
     return {"running": False}
 
@@ -289,8 +620,26 @@ if __name__ == "__main__":
     parser = argparse.ArgumentParser(description="Roots embedding server")
     parser.add_argument("--model", required=True, help="Model name")
     parser.add_argument("--type", default="sentence-transformers", help="Model type")
+    parser.add_argument(
+        "--extra-model", action="append", default=[], help="Also load this model (repeatable)"
+    )
+    parser.add_argument(
+        "--backend", default="torch", choices=["torch", "onnx"], help="Run models on this runtime"
+    )
+    parser.add_argument("--socket", type=Path, help="Socket to listen on (default: as configured)")
+    parser.add_argument(
+        "--idle-timeout", type=float, default=0, help="Exit after this many seconds without requests"
+    )
+    parser.add_argument("--listen-fd", type=int, help="Listen on this inherited socket (systemd activation)")
     args = parser.parse_args()
+    if args.socket:
+        # The pid and log files sit beside whichever socket is served
+        SOCKET_PATH = args.socket
+        PID_FILE = SOCKET_PATH.with_suffix(".pid")
+        LOG_FILE = SOCKET_PATH.with_suffix(".log")
 
     # Run in foreground (Rust handles daemonization via nohup)
-    server = EmbeddingServer(args.model, args.type)
+    server = EmbeddingServer(
+        args.model, args.type, args.idle_timeout, args.listen_fd, args.extra_model, args.backend
+    )
     server.start()
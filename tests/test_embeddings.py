@@ -0,0 +1,53 @@
+"""Tests for the local embedders and reranker."""
+
+import math
+
+from roots.embeddings import CrossEncoderReranker
+
+
+class FakeCrossEncoder:
+    """Gives each pair a fixed logit by text, recording the activation asked for."""
+
+    def __init__(self, logits):
+        self.logits = logits
+        self.activations = []
+
+    def predict(self, pairs, activation_fn=None):
+        self.activations.append(activation_fn)
+        scores = [self.logits[text] for _, text in pairs]
+        return [activation_fn(s) for s in scores] if activation_fn else scores
+
+
+def _reranker(logits):
+    reranker = CrossEncoderReranker("fake")
+    reranker._model = FakeCrossEncoder(logits)
+    return reranker
+
+
+def _sigmoid(x):
+    return 1.0 / (1.0 + math.exp(-x))
+
+
+def test_rerank_scores_are_the_sigmoid_of_raw_logits():
+    reranker = _reranker({"close": 3.0, "far": -2.0, "middling": 0.5})
+
+    scores = reranker.rerank("q", ["close", "far", "middling"])
+    expected = [_sigmoid(3.0), _sigmoid(-2.0), _sigmoid(0.5)]
+    assert all(math.isclose(a, b) for a, b in zip(scores, expected))
+    # Logits are asked for unchanged, not through the model's own activation
+    assert reranker.model.activations[0] is not None
+
+
+def test_rerank_score_does_not_depend_on_the_batch():
+    # Logits that all fall in 0-1 are still logits
+    reranker = _reranker({"a": 0.2, "b": 0.9, "c": 4.0})
+
+    alone = reranker.rerank("q", ["a"])[0]
+    with_in_range = reranker.rerank("q", ["a", "b"])[0]
+    with_out_of_range = reranker.rerank("q", ["a", "c"])[0]
+    assert math.isclose(alone, _sigmoid(0.2))
+    assert alone == with_in_range == with_out_of_range
+
+
+def test_rerank_of_nothing():
+    assert _reranker({}).rerank("q", []) == []
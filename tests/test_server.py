@@ -0,0 +1,158 @@
+"""Tests for the embedding server's request handling."""
+
+import json
+import socket
+import sys
+import threading
+import time
+import types
+
+from roots import server
+from roots.server import Batcher, _recv_request
+
+
+class SlowEmbedder:
+    """Embeds each text as its length, slowly, recording each batch."""
+
+    def __init__(self, delay: float = 0.2):
+        self.delay = delay
+        self.batches = []
+
+    def embed_batch(self, texts):
+        self.batches.append(list(texts))
+        time.sleep(self.delay)
+        if "bad" in texts:
+            raise ValueError("bad text")
+        return [[float(len(t))] for t in texts]
+
+
+def _send_in_pieces(sock: socket.socket, payload: bytes, size: int, close: bool):
+    for start in range(0, len(payload), size):
+        sock.sendall(payload[start : start + size])
+    if close:
+        sock.shutdown(socket.SHUT_WR)
+
+
+def test_recv_request_parses_large_request_once(monkeypatch):
+    # Braces and escaped quotes inside strings don't end the request
+    texts = ['a "quoted" {brace} \\ ' * 1000 + str(i) for i in range(100)]
+    payload = json.dumps({"cmd": "embed_batch", "texts": texts}).encode()
+    calls = []
+    loads = json.loads
+    monkeypatch.setattr(server.json, "loads", lambda s: calls.append(1) or loads(s))
+
+    ours, theirs = socket.socketpair()
+    # The old client leaves the connection open while it waits
+    sender = threading.Thread(target=_send_in_pieces, args=(theirs, payload, 4093, False))
+    sender.start()
+    req = _recv_request(ours)
+    sender.join()
+
+    assert req == {"cmd": "embed_batch", "texts": texts}
+    assert len(calls) == 1
+    ours.close()
+    theirs.close()
+
+
+def test_recv_request_reads_to_eof():
+    ours, theirs = socket.socketpair()
+    _send_in_pieces(theirs, b'{"cmd": "ping"}  ', 3, True)
+    assert _recv_request(ours) == {"cmd": "ping"}
+
+    ours, theirs = socket.socketpair()
+    theirs.shutdown(socket.SHUT_WR)
+    assert _recv_request(ours) is None
+
+
+def test_batcher_coalesces_single_requests_while_busy():
+    embedder = SlowEmbedder()
+    batcher = Batcher(embedder)
+    results = {}
+
+    def embed(text):
+        results[text] = batcher.embed(text)
+
+    first = threading.Thread(target=embed, args=("x",))
+    first.start()
+    time.sleep(0.05)
+    rest = [threading.Thread(target=embed, args=("x" * n,)) for n in range(2, 6)]
+    for t in rest:
+        t.start()
+    for t in [first, *rest]:
+        t.join()
+
+    assert [len(b) for b in embedder.batches] == [1, 4]
+    assert results == {"x" * n: [float(n)] for n in range(1, 6)}
+
+
+def test_batcher_runs_single_requests_ahead_of_queued_batches():
+    embedder = SlowEmbedder()
+    batcher = Batcher(embedder)
+    threads = [threading.Thread(target=batcher.embed_batch, args=(["bulk"] * 2,)) for _ in range(2)]
+    threads[0].start()
+    time.sleep(0.05)
+    threads[1].start()
+    time.sleep(0.05)
+    single = threading.Thread(target=batcher.embed, args=("hook",))
+    single.start()
+    for t in [*threads, single]:
+        t.join()
+
+    assert embedder.batches == [["bulk", "bulk"], ["hook"], ["bulk", "bulk"]]
+
+
+def test_batcher_failure_does_not_fail_coalesced_requests():
+    embedder = SlowEmbedder()
+    batcher = Batcher(embedder)
+    results = {}
+
+    def embed(text):
+        try:
+            results[text] = batcher.embed(text)
+        except ValueError as e:
+            results[text] = str(e)
+
+    first = threading.Thread(target=embed, args=("first",))
+    first.start()
+    time.sleep(0.05)
+    rest = [threading.Thread(target=embed, args=(t,)) for t in ("good", "bad")]
+    for t in rest:
+        t.start()
+    for t in [first, *rest]:
+        t.join()
+
+    assert results == {"first": [5.0], "good": [4.0], "bad": "bad text"}
+
+
+def test_requests_pick_a_loaded_model_and_reranker(monkeypatch):
+    class FakeReranker:
+        loaded = []
+
+        def __init__(self, name):
+            self.name = name
+            FakeReranker.loaded.append(name)
+
+        def rerank(self, query, texts):
+            return [float(len(self.name))] * len(texts)
+
+    embeddings = types.ModuleType("roots.embeddings")
+    embeddings.CrossEncoderReranker = FakeReranker
+    monkeypatch.setitem(sys.modules, "roots.embeddings", embeddings)
+
+    srv = server.EmbeddingServer("big", "sentence-transformers", extra_models=["small", "big"])
+    assert srv.extra_models == ["small"]
+    big, small = SlowEmbedder(0), SlowEmbedder(0)
+    srv.batchers = {"big": Batcher(big), "small": Batcher(small)}
+
+    assert srv._respond({"cmd": "embed", "text": "ab"}) == {"ok": True, "embedding": [2.0]}
+    assert srv._respond({"cmd": "embed_batch", "texts": ["a"], "model": "small"})["ok"]
+    assert big.batches == [["ab"]] and small.batches == [["a"]]
+    missing = srv._respond({"cmd": "embed", "text": "a", "model": "huge"})
+    assert missing == {"ok": False, "error": "model not loaded: huge"}
+
+    # Each named cross-encoder loads once, on the first rerank that wants it
+    for name in ["fast", "accurate", "fast"]:
+        resp = srv._respond({"cmd": "rerank", "query": "q", "texts": ["t"], "model": name})
+        assert resp == {"ok": True, "scores": [float(len(name))]}
+    assert FakeReranker.loaded == ["fast", "accurate"]
+    assert list(srv.rerankers) == ["fast", "accurate"]